@@ -14,13 +14,14 @@ except ImportError:
 class IndexerManager:
     """Manager for codebase indexing"""
     
-    def __init__(self, project_id: str, db_path: Path):
+    def __init__(self, project_id: str, db_path: Path, read_only: bool = False):
         self.project_id = project_id
         self.db_path = db_path
+        self.read_only = read_only
         
         if HAS_PYO3:
-            self.indexer = PyCodebaseIndexer(project_id, str(db_path))
-            self.search_engine = PySemanticSearch(str(db_path))
+            self.indexer = PyCodebaseIndexer(project_id, str(db_path), read_only)
+            self.search_engine = PySemanticSearch(str(db_path), read_only)
         else:
             self.indexer = None
             self.search_engine = None
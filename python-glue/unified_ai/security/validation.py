@@ -2,6 +2,7 @@
 
 import re
 import html
+import warnings
 from pathlib import Path
 from typing import Optional
 from urllib.parse import quote, unquote
@@ -122,7 +123,10 @@ def validate_sql_safe(input_str: str) -> str:
     """
     Basic SQL injection prevention check
     
-    Note: This is a basic check. Always use parameterized queries!
+    Deprecated: rejects ordinary text such as code comments, so never apply
+    it to chat messages. Queries bind their parameters; the Rust core checks
+    names with validate_identifier and escapes search text with
+    validate_like_pattern.
     
     Args:
         input_str: Input string to check
@@ -133,6 +137,11 @@ def validate_sql_safe(input_str: str) -> str:
     Raises:
         ValidationError: If potentially dangerous patterns detected
     """
+    warnings.warn(
+        "validate_sql_safe is deprecated; use bound parameters instead",
+        DeprecationWarning,
+        stacklevel=2,
+    )
     dangerous_patterns = [
         r"';",
         r'";',
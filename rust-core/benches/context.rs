@@ -0,0 +1,46 @@
+//! Context window management on large conversations
+//!
+//! Run with `cargo bench -p rust-core --bench context`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rust_core::context::window::ContextWindowManager;
+use rust_core::context::{Context, Role};
+use rust_core::indexer::testgen::SeededRng;
+
+const WORDS: &[&str] = &[
+    "the", "index", "refresh", "failed", "because", "config", "was", "missing", "retry",
+    "after", "budget", "reset", "window", "token", "summary", "schema",
+];
+
+/// A context of `messages` alternating user and assistant messages of 20 to 80 words
+fn large_context(messages: usize, seed: u64) -> Context {
+    let mut rng = SeededRng::new(seed);
+    let mut context = Context::new(Some("bench".to_string()));
+    for i in 0..messages {
+        let words = 20 + rng.below(60);
+        let content: Vec<&str> = (0..words).map(|_| rng.pick(WORDS)).collect();
+        let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+        context.add_message(role, content.join(" "));
+    }
+    context
+}
+
+fn bench_manage_context(c: &mut Criterion) {
+    let manager = ContextWindowManager::new(1000);
+    let mut group = c.benchmark_group("manage_context");
+    group.sample_size(20);
+    for messages in [1_000, 5_000] {
+        let context = large_context(messages, 3);
+        group.bench_with_input(BenchmarkId::from_parameter(messages), &context, |b, context| {
+            b.iter_batched(
+                || context.clone(),
+                |mut context| manager.manage_context(&mut context, "gpt-4"),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_manage_context);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+//! Indexing throughput and search latency over synthetic corpora
+//!
+//! Run with `cargo bench -p rust-core --bench indexing`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rust_core::indexer::codebase::CodebaseIndexer;
+use rust_core::indexer::search::SemanticSearch;
+use rust_core::indexer::storage::IndexStorage;
+use rust_core::indexer::testgen::{generate_blocks, generate_corpus, CorpusSpec};
+use rust_core::migrations::{register_migrations, MigrationRunner};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::path::PathBuf;
+use tokio::runtime::Runtime;
+
+/// Blocks stored per synthetic file in the search benchmarks
+const BLOCKS_PER_FILE: usize = 100;
+
+async fn create_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .expect("Failed to create pool");
+    let mut runner = MigrationRunner::new(pool.clone());
+    register_migrations(&mut runner);
+    runner.migrate_up(None).await.expect("Migration should succeed");
+    pool
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("uai-bench-{}-{}", name, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A search over `blocks` generated blocks, stored without parsing
+async fn seeded_search(blocks: usize) -> SemanticSearch {
+    let storage = IndexStorage::new(create_pool().await);
+    for (i, chunk) in generate_blocks(blocks, 7).chunks(BLOCKS_PER_FILE).enumerate() {
+        let language = chunk[0].language.clone();
+        storage
+            .store_file("bench", &format!("gen/file_{}.src", i), &language, chunk)
+            .await
+            .unwrap();
+    }
+    SemanticSearch::new(storage)
+}
+
+fn bench_index_directory(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let spec = CorpusSpec::new(200, 10).with_seed(1);
+    let root = temp_dir("index");
+    generate_corpus(&spec).write_to(&root).unwrap();
+
+    let mut group = c.benchmark_group("index_directory");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(spec.files as u64));
+    group.bench_function("200_files_x_10_functions", |b| {
+        b.to_async(&rt).iter(|| async {
+            let storage = IndexStorage::new(create_pool().await);
+            let mut indexer = CodebaseIndexer::new("bench".to_string(), storage);
+            indexer.index_directory(&root).await.unwrap()
+        })
+    });
+    group.finish();
+    std::fs::remove_dir_all(&root).ok();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("search");
+    group.sample_size(20);
+    for blocks in [1_000, 10_000, 100_000] {
+        let mut search = rt.block_on(seeded_search(blocks));
+        group.bench_with_input(BenchmarkId::from_parameter(blocks), &blocks, |b, _| {
+            b.iter(|| rt.block_on(search.search("bench", "retry config", 10)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_index_directory, bench_search);
+criterion_main!(benches);
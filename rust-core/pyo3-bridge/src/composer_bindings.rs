@@ -0,0 +1,187 @@
+/// PyO3 bindings for stored composed responses
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rust_core::composer::{ComposedResponse, ResponseStore, StoredResponse, ToolResponse};
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use sqlx::sqlite::SqlitePool;
+use std::time::Duration;
+
+#[pyclass]
+pub struct PyResponseStore {
+    store: ResponseStore,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+impl PyResponseStore {
+    fn from_pool(rt: tokio::runtime::Runtime, pool: SqlitePool) -> PyResult<Self> {
+        let store = rt.block_on(ResponseStore::from_pool(pool))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create response store: {}", e)
+            ))?;
+        
+        Ok(Self {
+            store,
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+}
+
+#[pymethods]
+impl PyResponseStore {
+    #[new]
+    fn new(db_path: String) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Self::from_pool(rt, pool)
+            })
+        })
+    }
+    
+    #[staticmethod]
+    fn with_database(py: Python, database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let pool = database.pool();
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            Self::from_pool(rt, pool)
+        })
+    }
+    
+    /// Store a composed response dict and the raw tool response dicts; returns the row id
+    fn save(
+        &self,
+        py: Python,
+        composed: &PyDict,
+        raw: Vec<&PyDict>,
+        conversation_id: String,
+        request_id: String,
+    ) -> PyResult<i64> {
+        let composed = ComposedResponse {
+            content: required_str(composed, "content")?,
+            sources: optional_item(composed, "sources")?.unwrap_or_default(),
+            strategy: optional_item(composed, "strategy")?.unwrap_or_default(),
+            metadata: json_item(py, composed, "metadata")?,
+            partial: optional_item(composed, "partial")?.unwrap_or_default(),
+        };
+        let raw = raw
+            .into_iter()
+            .map(|response| {
+                Ok(ToolResponse {
+                    tool: required_str(response, "tool")?,
+                    content: required_str(response, "content")?,
+                    metadata: json_item(py, response, "metadata")?,
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.save(&composed, &raw, &conversation_id, &request_id))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Stored responses of a conversation, oldest first
+    fn get_by_conversation(&self, py: Python, conversation_id: String) -> PyResult<Vec<PyObject>> {
+        let responses = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.get_by_conversation(&conversation_id))
+        })
+        .map_err(PyErr::from)?;
+        
+        responses
+            .iter()
+            .map(|response| Ok(stored_to_dict(py, response)?.into()))
+            .collect()
+    }
+    
+    fn get_by_request(&self, py: Python, request_id: String) -> PyResult<Option<PyObject>> {
+        let response = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.get_by_request(&request_id))
+        })
+        .map_err(PyErr::from)?;
+        
+        response
+            .map(|response| Ok(stored_to_dict(py, &response)?.into()))
+            .transpose()
+    }
+    
+    /// Delete responses older than `max_age_days`; returns how many were removed
+    fn prune(&self, py: Python, max_age_days: u32) -> PyResult<u64> {
+        let max_age = Duration::from_secs(max_age_days as u64 * 24 * 60 * 60);
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.prune_older_than(max_age))
+        })
+        .map_err(PyErr::from)
+    }
+}
+
+fn required_str(dict: &PyDict, key: &str) -> PyResult<String> {
+    optional_item(dict, key)?
+        .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Missing {}", key)))
+}
+
+fn optional_item<'p, T: FromPyObject<'p>>(dict: &'p PyDict, key: &str) -> PyResult<Option<T>> {
+    match dict.get_item(key)? {
+        Some(value) if !value.is_none() => Ok(Some(value.extract()?)),
+        _ => Ok(None),
+    }
+}
+
+/// A dict entry as JSON, converted with Python's json module
+fn json_item(py: Python, dict: &PyDict, key: &str) -> PyResult<Option<serde_json::Value>> {
+    match dict.get_item(key)? {
+        Some(value) if !value.is_none() => {
+            let text: String = py.import("json")?.call_method1("dumps", (value,))?.extract()?;
+            serde_json::from_str(&text)
+                .map(Some)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid {}: {}", key, e)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn json_to_py(py: Python, value: &Option<serde_json::Value>) -> PyResult<PyObject> {
+    match value {
+        Some(value) => Ok(py.import("json")?.call_method1("loads", (value.to_string(),))?.into()),
+        None => Ok(py.None()),
+    }
+}
+
+fn stored_to_dict<'p>(py: Python<'p>, response: &StoredResponse) -> PyResult<&'p PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("id", response.id)?;
+    result.set_item("conversation_id", &response.conversation_id)?;
+    result.set_item("request_id", &response.request_id)?;
+    result.set_item("content", &response.content)?;
+    result.set_item("strategy", &response.strategy)?;
+    result.set_item("sources", &response.sources)?;
+    result.set_item("metadata", json_to_py(py, &response.metadata)?)?;
+    result.set_item("created_at", response.created_at)?;
+    
+    let raw = response
+        .raw_responses
+        .iter()
+        .map(|raw| {
+            let entry = PyDict::new(py);
+            entry.set_item("tool", &raw.tool)?;
+            entry.set_item("content", &raw.content)?;
+            entry.set_item("metadata", json_to_py(py, &raw.metadata)?)?;
+            Ok(entry)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    result.set_item("raw_responses", raw)?;
+    Ok(result)
+}
@@ -0,0 +1,121 @@
+/// PyO3 bindings for orchestrator configuration
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rust_core::config::OrchestratorConfig;
+use crate::context_bindings::{PyContextCompressor, PyContextWindowManager};
+use crate::resilience_bindings::PyTimeouts;
+use crate::router_bindings::PyRouter;
+
+fn config_error(e: rust_core::error::OrchestratorError) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+}
+
+#[pyclass]
+pub struct PyOrchestratorConfig {
+    inner: OrchestratorConfig,
+}
+
+#[pymethods]
+impl PyOrchestratorConfig {
+    /// Default configuration with `UAI_` environment overrides applied
+    #[new]
+    fn new() -> PyResult<Self> {
+        let mut inner = OrchestratorConfig::default();
+        inner.apply_env_overrides(std::env::vars()).map_err(config_error)?;
+        inner.validate().map_err(config_error)?;
+        Ok(Self { inner })
+    }
+    
+    /// Load a TOML file, apply `UAI_` environment overrides and validate
+    #[staticmethod]
+    fn from_file(path: String) -> PyResult<Self> {
+        let inner = OrchestratorConfig::from_file(&path).map_err(config_error)?;
+        Ok(Self { inner })
+    }
+    
+    /// Parse TOML without environment overrides
+    #[staticmethod]
+    fn from_toml(contents: String) -> PyResult<Self> {
+        let inner = OrchestratorConfig::from_toml_str(&contents).map_err(config_error)?;
+        Ok(Self { inner })
+    }
+    
+    /// Raise ValueError describing every out-of-range setting
+    fn validate(&self) -> PyResult<()> {
+        self.inner.validate().map_err(config_error)
+    }
+    
+    /// Full configuration as a JSON string
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.inner)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+    }
+    
+    #[getter]
+    fn db_path(&self) -> String {
+        self.inner.storage.db_path.to_string_lossy().to_string()
+    }
+    
+    #[getter]
+    fn index_path(&self) -> String {
+        self.inner.storage.index_path.to_string_lossy().to_string()
+    }
+    
+    #[getter]
+    fn daily_budget_usd(&self) -> f64 {
+        self.inner.cost.daily_budget_usd
+    }
+    
+    #[getter]
+    fn monthly_budget_usd(&self) -> f64 {
+        self.inner.cost.monthly_budget_usd
+    }
+    
+    /// The model an alias or canonical model name stands for, or None if the catalog has neither
+    ///
+    /// A dict of model, tool, context_window (None for the model's usual
+    /// window) and pricing (the pricing table model it is charged by).
+    fn lookup_model<'p>(&self, py: Python<'p>, name: String) -> PyResult<Option<&'p PyDict>> {
+        let catalog = self.inner.build_model_catalog();
+        let entry = match catalog.lookup(&name) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        
+        let result = PyDict::new(py);
+        result.set_item("model", &entry.model)?;
+        result.set_item("tool", &entry.tool)?;
+        result.set_item("context_window", entry.context_window)?;
+        result.set_item("pricing", entry.pricing_model())?;
+        Ok(Some(result))
+    }
+    
+    /// Configured model aliases, sorted
+    fn model_aliases(&self) -> Vec<String> {
+        let mut aliases: Vec<String> = self.inner.models.keys().cloned().collect();
+        aliases.sort();
+        aliases
+    }
+    
+    /// Model aliases without known pricing, as logged by `validate`
+    fn model_warnings(&self) -> Vec<String> {
+        self.inner.model_warnings()
+    }
+    
+    fn build_router(&self) -> PyRouter {
+        PyRouter::from_router(self.inner.build_router())
+    }
+    
+    fn build_window_manager(&self) -> PyContextWindowManager {
+        PyContextWindowManager::from_manager(self.inner.build_window_manager())
+    }
+    
+    fn build_compressor(&self) -> PyContextCompressor {
+        PyContextCompressor::from_compressor(self.inner.build_compressor())
+    }
+    
+    fn build_timeouts(&self) -> PyTimeouts {
+        PyTimeouts::from_config(self.inner.timeouts.clone())
+    }
+}
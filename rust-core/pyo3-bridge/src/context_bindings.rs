@@ -1,11 +1,19 @@
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
-use rust_core::context::{ContextManager, ContextStorage, Context};
-use rust_core::context::window::ContextWindowManager;
-use rust_core::context::compression::ContextCompressor;
-use rust_core::error::Result;
-use std::path::PathBuf;
+use rust_core::context::{AutoManage, BatchReport, CodebaseContext, ContextLimits, ContextManager, ContextStorage, Context, ConversationGuard, InMemoryContextStore, MemoryStore, Message, RetentionPolicy, Role, SystemClock, UpdateReport, ValidationHook, WindowState};
+use rust_core::context::hooks::DEFAULT_MAX_MESSAGE_LENGTH;
+use rust_core::context::diff::{diff_contexts, render_text, ContextDiff};
+use rust_core::context::token_counter::TokenCounter;
+use rust_core::context::summarizer::ContextSummarizer;
+use rust_core::context::tool_cache::{request_hash, ToolCallCache, DEFAULT_TOOL_CACHE_CAPACITY};
+use rust_core::context::window::{ContextWindowManager, ReservedBudget};
+use rust_core::context::compression::{CompressionPlan, CompressionStats, ContextCompressor, TruncationMode};
+use rust_core::error::{ConflictError, OrchestratorError, Result};
+use rust_core::security::AuditLogger;
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use sqlx::sqlite::SqlitePool;
 use pyo3_asyncio::tokio::future_into_py;
+use std::collections::HashMap;
 
 #[pyclass]
 pub struct PyContextManager {
@@ -13,11 +21,79 @@ pub struct PyContextManager {
     runtime: std::sync::Mutex<tokio::runtime::Runtime>,
 }
 
+impl PyContextManager {
+    fn from_pool(rt: tokio::runtime::Runtime, pool: SqlitePool, audit: bool) -> PyResult<Self> {
+        // Audit events need the audit_logs table from the migrations
+        let audit_logger = audit.then(|| AuditLogger::new(pool.clone()));
+        let (storage, memory_store) = rt.block_on(async {
+            Ok::<_, OrchestratorError>((
+                ContextStorage::from_pool(pool.clone()).await?,
+                MemoryStore::from_pool(pool).await?,
+            ))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Failed to create storage: {}", e)
+        ))?;
+        
+        let mut inner = ContextManager::new(storage).with_memory_store(memory_store);
+        if let Some(audit_logger) = audit_logger {
+            inner = inner.with_audit_logger(audit_logger);
+        }
+        
+        Ok(Self {
+            inner,
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+    
+    fn with_limits(mut self, limits: ContextLimits, auto_manage: bool) -> Self {
+        if auto_manage {
+            self.inner = self.inner.with_auto_manage(AutoManage::for_limits(&limits));
+        }
+        self.inner = self.inner.with_limits(limits);
+        self
+    }
+    
+    fn with_validation(mut self, max_message_length: Option<usize>) -> Self {
+        if let Some(max_length) = max_message_length {
+            self.inner.register_message_hook(Box::new(ValidationHook::new(max_length)));
+        }
+        self
+    }
+}
+
 #[pymethods]
 impl PyContextManager {
+    /// With `in_memory`, contexts live only as long as this manager and `db_path` is ignored
+    ///
+    /// Saves of contexts over the `max_*` limits raise ValueError naming the
+    /// exceeded limit; with `auto_manage` they are compressed, summarized and
+    /// windowed to fit first.
+    ///
+    /// With `validate_messages`, new user messages are checked with
+    /// `validate_input` before saving, up to `max_message_length` characters
+    /// (default 100000); a message that fails raises ValueError.
     #[new]
-    fn new(db_path: String) -> PyResult<Self> {
-        let path = PathBuf::from(db_path);
+    fn new(
+        db_path: String,
+        audit: Option<bool>,
+        in_memory: Option<bool>,
+        max_messages: Option<usize>,
+        max_total_bytes: Option<usize>,
+        max_single_message_bytes: Option<usize>,
+        auto_manage: Option<bool>,
+        validate_messages: Option<bool>,
+        max_message_length: Option<usize>,
+    ) -> PyResult<Self> {
+        let limits = ContextLimits {
+            max_messages,
+            max_total_bytes,
+            max_single_message_bytes,
+        };
+        let auto_manage = auto_manage.unwrap_or(false);
+        let max_message_length = validate_messages
+            .unwrap_or(false)
+            .then(|| max_message_length.unwrap_or(DEFAULT_MAX_MESSAGE_LENGTH));
         Python::with_gil(|py| {
             py.allow_threads(|| {
                 let rt = tokio::runtime::Runtime::new()
@@ -25,20 +101,39 @@ impl PyContextManager {
                         format!("Failed to create runtime: {}", e)
                     ))?;
                 
-                let storage = rt.block_on(async {
-                    ContextStorage::new(path).await
-                        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                            format!("Failed to create storage: {}", e)
-                        ))
-                })?;
+                if in_memory.unwrap_or(false) {
+                    if audit.unwrap_or(false) {
+                        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                            "Audit logging needs a database; it is not available in memory"
+                        ));
+                    }
+                    let manager = Self {
+                        inner: ContextManager::new(InMemoryContextStore::new()),
+                        runtime: std::sync::Mutex::new(rt),
+                    };
+                    return Ok(manager.with_limits(limits, auto_manage).with_validation(max_message_length));
+                }
                 
-                Ok(Self {
-                    inner: ContextManager::new(storage),
-                    runtime: std::sync::Mutex::new(rt),
-                })
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Ok(Self::from_pool(rt, pool, audit.unwrap_or(false))?
+                    .with_limits(limits, auto_manage)
+                    .with_validation(max_message_length))
             })
         })
     }
+    
+    #[staticmethod]
+    fn with_database(py: Python, database: PyRef<PyDatabase>, audit: Option<bool>) -> PyResult<Self> {
+        let pool = database.pool();
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            Self::from_pool(rt, pool, audit.unwrap_or(false))
+        })
+    }
 
     fn get_or_create_context<'p>(
         &self,
@@ -61,11 +156,13 @@ impl PyContextManager {
         let result = PyDict::new(py);
         result.set_item("conversation_id", context.conversation_id)?;
         result.set_item("project_id", context.project_id)?;
+        result.set_item("title", context.title)?;
+        result.set_item("labels", context.labels)?;
         
         // Serialize messages
         let messages: Vec<PyDict> = context.messages.iter().map(|msg| {
             let msg_dict = PyDict::new(py);
-            msg_dict.set_item("role", &msg.role).unwrap();
+            msg_dict.set_item("role", msg.role.as_str()).unwrap();
             msg_dict.set_item("content", &msg.content).unwrap();
             msg_dict.set_item("timestamp", msg.timestamp).unwrap();
             msg_dict
@@ -88,6 +185,7 @@ impl PyContextManager {
             tc_dict.set_item("timestamp", tc.timestamp).unwrap();
             tc_dict.set_item("request", &tc.request).unwrap();
             tc_dict.set_item("response", &tc.response).unwrap();
+            tc_dict.set_item("request_id", &tc.request_id).unwrap();
             tc_dict
         }).collect();
         let tool_history_list = pyo3::types::PyList::new(py, tool_history);
@@ -96,7 +194,17 @@ impl PyContextManager {
         Ok(result)
     }
 
-    fn update_context(&self, py: Python, context_dict: &PyDict) -> PyResult<()> {
+    /// Save changes from a context dict
+    ///
+    /// `messages` may be the full list returned by `get_or_create_context`:
+    /// messages already at the end of the stored history are skipped and only
+    /// the rest are appended. Pass `deduplicate=False` to append every message
+    /// as given, e.g. when repeating an identical message on purpose.
+    ///
+    /// Returns what the message hooks did: `hook_outcomes` (dicts with
+    /// `hook`, `message_index` and `decision`), `messages_processed` and
+    /// `messages_dropped`.
+    fn update_context<'p>(&self, py: Python<'p>, context_dict: &PyDict, deduplicate: Option<bool>) -> PyResult<&'p PyDict> {
         // Extract all data from Python dict while holding the GIL
         let conversation_id: String = context_dict
             .get_item("conversation_id")?
@@ -106,22 +214,34 @@ impl PyContextManager {
         let project_id: Option<String> = context_dict.get_item("project_id")
             .and_then(|v| v.extract::<Option<String>>().ok());
         
+        let labels: Option<HashMap<String, String>> = context_dict.get_item("labels")
+            .and_then(|v| v.extract().ok());
+        
         // Extract messages if provided
-        let mut messages_to_add: Vec<(String, String)> = Vec::new();
+        let mut messages_to_add: Vec<Message> = Vec::new();
         if let Some(messages) = context_dict.get_item("messages") {
             if let Ok(msg_list) = messages.downcast::<pyo3::types::PyList>() {
                 for msg_item in msg_list.iter() {
                     if let Ok(msg_dict) = msg_item.downcast::<PyDict>() {
                         let role: String = msg_dict.get_item("role")?.extract()?;
                         let content: String = msg_dict.get_item("content")?.extract()?;
-                        messages_to_add.push((role, content));
+                        // Missing timestamps (0) match any stored timestamp
+                        let timestamp: i64 = msg_dict.get_item("timestamp")
+                            .and_then(|v| v.extract().ok())
+                            .unwrap_or(0);
+                        messages_to_add.push(Message {
+                            role: parse_role(&role)?,
+                            content,
+                            timestamp,
+                            pinned: false,
+                        });
                     }
                 }
             }
         }
         
         // Now perform async operations without GIL
-        py.allow_threads(|| {
+        let report = py.allow_threads(|| {
             let rt = self.runtime.lock().unwrap();
             rt.block_on(async {
                 // Load existing context
@@ -137,19 +257,419 @@ impl PyContextManager {
                 if let Some(pid) = project_id {
                     context.project_id = Some(pid);
                 }
+                if let Some(labels) = labels {
+                    context.labels = labels;
+                }
                 
                 // Add messages
-                for (role, content) in messages_to_add {
-                    context.add_message(role, content);
+                if deduplicate.unwrap_or(true) {
+                    context.reconcile_messages(messages_to_add);
+                } else {
+                    for message in messages_to_add {
+                        context.add_message(message.role, message.content);
+                    }
                 }
                 
-                self.inner.update_context(&context).await
-                    .map_err(|e: rust_core::error::Error| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                        format!("Failed to update context: {}", e)
-                    ))
+                self.inner.update_context(&mut context).await
+                    .map_err(|e| match e {
+                        // Distinct type so callers can reload and retry
+                        OrchestratorError::ConflictDetected(_) => ConflictError::new_err(e.to_string()),
+                        OrchestratorError::InvalidInput(_) => e.into(),
+                        e => PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                            format!("Failed to update context: {}", e)
+                        ),
+                    })
             })
+        })?;
+        update_report_to_dict(py, &report)
+    }
+    
+    /// Hold a conversation's lock for a Python-side critical section
+    ///
+    /// The lock is acquired by this call, waiting at most `timeout_secs`
+    /// (the manager's lock timeout by default) before raising TimeoutError,
+    /// and released on leaving the `with` block:
+    /// `with manager.lock(conversation_id): ...`
+    ///
+    /// The lock is not reentrant: locking the same conversation again inside
+    /// the block waits for it and raises TimeoutError. This manager's own
+    /// calls, `update_context` included, do not take the lock, so they can
+    /// be made inside the block.
+    fn lock(&self, py: Python, conversation_id: String, timeout_secs: Option<f64>) -> PyResult<PyConversationLock> {
+        let locks = self.inner.conversation_locks();
+        let timeout = timeout_secs
+            .map(std::time::Duration::from_secs_f64)
+            .unwrap_or_else(|| locks.timeout());
+        // Wait on a handle so other calls can use the runtime meanwhile
+        let handle = self.runtime.lock().unwrap().handle().clone();
+        let guard = py.allow_threads(|| handle.block_on(locks.lock_with_timeout(&conversation_id, timeout)))
+        .map_err(PyErr::from)?;
+        Ok(PyConversationLock { guard: Some(guard) })
+    }
+    
+    fn delete_context(&self, py: Python, conversation_id: String) -> PyResult<bool> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async { self.inner.delete_context(&conversation_id).await })
         })
+        .map_err(|e: rust_core::error::Error| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Failed to delete context: {}", e)
+        ))
+    }
+    
+    /// Title the conversation after its first user message; keeps a title set with `set_title`
+    fn generate_title(&self, py: Python, conversation_id: String) -> PyResult<Option<String>> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.generate_title(&conversation_id))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    fn set_title(&self, py: Python, conversation_id: String, title: String) -> PyResult<()> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.set_title(&conversation_id, &title))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// One page of contexts, newest first, as {contexts, next_cursor}
+    ///
+    /// `labels_match` keeps only contexts carrying all of the given labels.
+    fn list_contexts<'p>(
+        &self,
+        py: Python<'p>,
+        project_id: Option<String>,
+        page_size: Option<usize>,
+        cursor: Option<String>,
+        labels_match: Option<HashMap<String, String>>,
+    ) -> PyResult<&'p PyDict> {
+        let page = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.list_contexts(
+                project_id.as_deref(),
+                labels_match.as_ref(),
+                page_size.unwrap_or(50),
+                cursor,
+            ))
+        })
+        .map_err(PyErr::from)?;
+        
+        let contexts = pyo3::types::PyList::empty(py);
+        for summary in page.items {
+            let item = PyDict::new(py);
+            item.set_item("conversation_id", summary.conversation_id)?;
+            item.set_item("project_id", summary.project_id)?;
+            item.set_item("updated_at", summary.updated_at)?;
+            item.set_item("title", summary.title)?;
+            item.set_item("labels", summary.labels)?;
+            contexts.append(item)?;
+        }
+        
+        let result = PyDict::new(py);
+        result.set_item("contexts", contexts)?;
+        result.set_item("next_cursor", page.next_cursor)?;
+        Ok(result)
+    }
+    
+    /// Archived summaries as [{summary_id, summary, created_at, messages}], oldest first
+    fn get_archived_messages<'p>(
+        &self,
+        py: Python<'p>,
+        conversation_id: String,
+        summary_id: Option<i64>,
+    ) -> PyResult<&'p pyo3::types::PyList> {
+        let archived = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.get_archived_messages(&conversation_id, summary_id))
+        })
+        .map_err(PyErr::from)?;
+        
+        let result = pyo3::types::PyList::empty(py);
+        for entry in archived {
+            let messages: Vec<PyDict> = entry.messages.iter().map(|msg| {
+                let msg_dict = PyDict::new(py);
+                msg_dict.set_item("role", msg.role.as_str()).unwrap();
+                msg_dict.set_item("content", &msg.content).unwrap();
+                msg_dict.set_item("timestamp", msg.timestamp).unwrap();
+                msg_dict
+            }).collect();
+            
+            let item = PyDict::new(py);
+            item.set_item("summary_id", entry.summary_id)?;
+            item.set_item("summary", entry.summary)?;
+            item.set_item("created_at", entry.created_at)?;
+            item.set_item("messages", pyo3::types::PyList::new(py, messages))?;
+            result.append(item)?;
+        }
+        Ok(result)
+    }
+    
+    /// {last_model, last_window, dropped_messages, last_summary_at}, or None if never window-managed
+    fn get_window_state<'p>(&self, py: Python<'p>, conversation_id: String) -> PyResult<Option<&'p PyDict>> {
+        let state = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.get_window_state(&conversation_id))
+        })
+        .map_err(PyErr::from)?;
+        state.map(|state| window_state_to_dict(py, &state)).transpose()
+    }
+    
+    /// The conversation as it stood at `timestamp` (Unix seconds); returns {"context", "fidelity", "timestamp"}
+    ///
+    /// `fidelity` is "exact" when the context was not saved after
+    /// `timestamp`, otherwise "reconstructed": messages and tool calls are
+    /// filtered by time, but the codebase context, title and labels are the
+    /// latest stored. Raises ValueError if the conversation does not exist.
+    fn replay<'p>(&self, py: Python<'p>, conversation_id: String, timestamp: i64) -> PyResult<&'p PyDict> {
+        let replayed = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.replay(&conversation_id, timestamp))
+        })
+        .map_err(PyErr::from)?;
+        
+        let result = PyDict::new(py);
+        result.set_item("context", context_to_dict(py, &replayed.context)?)?;
+        result.set_item("fidelity", replayed.fidelity.as_str())?;
+        result.set_item("timestamp", replayed.timestamp)?;
+        Ok(result)
+    }
+    
+    /// Prepend the project's most important memories to a context dict; returns {"context", "injected"}
+    ///
+    /// Memories are read from the manager's database and added in one system
+    /// message of at most `max_tokens`; those already in the conversation are
+    /// skipped. Raises ValueError for an in-memory manager.
+    fn inject_memories<'p>(
+        &self,
+        py: Python<'p>,
+        context_dict: &PyDict,
+        project_id: String,
+        max_tokens: usize,
+    ) -> PyResult<&'p PyDict> {
+        let mut context = dict_to_context(context_dict)?;
+        let injected = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.inject_memories(&mut context, &project_id, max_tokens))
+        })
+        .map_err(PyErr::from)?;
+        
+        let result = PyDict::new(py);
+        result.set_item("context", context_to_dict(py, &context)?)?;
+        result.set_item("injected", injected)?;
+        Ok(result)
+    }
+    
+    /// Set a context dict's codebase context and its system message; returns {"context", "attached"}
+    ///
+    /// The message from an earlier call is replaced rather than appended.
+    fn attach_codebase_context<'p>(
+        &self,
+        py: Python<'p>,
+        context_dict: &PyDict,
+        relevant_files: Vec<String>,
+        semantic_matches: Vec<String>,
+        max_tokens: usize,
+    ) -> PyResult<&'p PyDict> {
+        let mut context = dict_to_context(context_dict)?;
+        let codebase = CodebaseContext { relevant_files, semantic_matches };
+        let attached = self.inner.attach_codebase_context(&mut context, codebase, max_tokens);
+        
+        let result = PyDict::new(py);
+        result.set_item("context", context_to_dict(py, &context)?)?;
+        result.set_item("attached", attached)?;
+        Ok(result)
+    }
+    
+    /// Fit a context dict to `model`'s window; returns {"context", "summarized", "dropped_messages", "suggest_restore"}
+    ///
+    /// `suggest_restore` is set when the model's window grew since the last
+    /// call and the conversation has archived messages to restore.
+    fn manage_window<'p>(
+        &self,
+        py: Python<'p>,
+        context_dict: &PyDict,
+        window_manager: PyRef<PyContextWindowManager>,
+        model: String,
+    ) -> PyResult<&'p PyDict> {
+        let mut context = dict_to_context(context_dict)?;
+        let window_manager = &window_manager.inner;
+        let outcome = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.manage_window(&mut context, window_manager, &model))
+        })
+        .map_err(PyErr::from)?;
+        
+        let result = PyDict::new(py);
+        result.set_item("context", context_to_dict(py, &context)?)?;
+        result.set_item("summarized", outcome.summarized)?;
+        result.set_item("dropped_messages", outcome.dropped_messages)?;
+        result.set_item("suggest_restore", outcome.suggest_restore)?;
+        Ok(result)
+    }
+    
+    /// Splice archived messages back in place of their summary; returns the context dict
+    fn restore_from_archive(&self, py: Python, conversation_id: String, summary_id: i64) -> PyResult<PyDict> {
+        let context = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.restore_from_archive(&conversation_id, summary_id))
+        })
+        .map_err(PyErr::from)?;
+        
+        context_to_dict(py, &context)
+    }
+    
+    /// Delete expired contexts; returns {examined, deleted, held, dry_run, deleted_ids}
+    fn apply_retention<'p>(
+        &self,
+        py: Python<'p>,
+        default_ttl_days: Option<u32>,
+        per_project_overrides: Option<std::collections::HashMap<String, u32>>,
+        legal_hold_projects: Option<Vec<String>>,
+        dry_run: Option<bool>,
+    ) -> PyResult<&'p PyDict> {
+        let mut policy = RetentionPolicy::default().with_dry_run(dry_run.unwrap_or(false));
+        if let Some(days) = default_ttl_days {
+            policy.default_ttl_days = days;
+        }
+        policy.per_project_overrides = per_project_overrides.unwrap_or_default();
+        policy.legal_hold_projects = legal_hold_projects.unwrap_or_default().into_iter().collect();
+        
+        let report = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async { self.inner.apply_retention(&policy, &SystemClock).await })
+        })
+        .map_err(|e: rust_core::error::Error| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Failed to apply retention: {}", e)
+        ))?;
+        
+        let result = PyDict::new(py);
+        result.set_item("examined", report.examined)?;
+        result.set_item("deleted", report.deleted)?;
+        result.set_item("held", report.held)?;
+        result.set_item("dry_run", report.dry_run)?;
+        result.set_item("deleted_ids", report.deleted_ids)?;
+        Ok(result)
+    }
+    
+    /// Compress every stored context of a project, saving those that changed
+    ///
+    /// `progress` is called as progress(processed, modified, errors) after
+    /// each page. Returns {processed, modified, errors}, errors being
+    /// [(conversation_id, message)].
+    fn compress_all<'p>(
+        &self,
+        py: Python<'p>,
+        compressor: PyRef<PyContextCompressor>,
+        project_id: Option<String>,
+        limit: Option<usize>,
+        progress: Option<PyObject>,
+    ) -> PyResult<&'p PyDict> {
+        let compressor = &compressor.inner;
+        let report = py.allow_threads(|| {
+            let mut callback = progress_callback(progress);
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.compress_all(project_id.as_deref(), compressor, limit, Some(&mut callback)))
+        })
+        .map_err(PyErr::from)?;
+        
+        batch_report_to_dict(py, report)
+    }
+    
+    /// Summarize and archive every context of a project over `message_threshold` messages
+    ///
+    /// Progress and the result are as for `compress_all`.
+    fn summarize_all<'p>(
+        &self,
+        py: Python<'p>,
+        project_id: Option<String>,
+        message_threshold: Option<usize>,
+        summary_ratio: Option<f64>,
+        limit: Option<usize>,
+        progress: Option<PyObject>,
+    ) -> PyResult<&'p PyDict> {
+        let summarizer = ContextSummarizer::new(message_threshold.unwrap_or(50), summary_ratio.unwrap_or(0.8));
+        let report = py.allow_threads(|| {
+            let mut callback = progress_callback(progress);
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.summarize_all(project_id.as_deref(), &summarizer, limit, Some(&mut callback)))
+        })
+        .map_err(PyErr::from)?;
+        
+        batch_report_to_dict(py, report)
+    }
+}
+
+/// A held conversation lock; see `PyContextManager.lock`
+#[pyclass]
+pub struct PyConversationLock {
+    guard: Option<ConversationGuard>,
+}
+
+#[pymethods]
+impl PyConversationLock {
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+    
+    fn __exit__(&mut self, _exc_type: Option<&PyAny>, _exc_value: Option<&PyAny>, _traceback: Option<&PyAny>) -> bool {
+        self.release();
+        false
     }
+    
+    /// Release the lock early; releasing twice does nothing
+    fn release(&mut self) {
+        self.guard = None;
+    }
+    
+    #[getter]
+    fn held(&self) -> bool {
+        self.guard.is_some()
+    }
+    
+    #[getter]
+    fn conversation_id(&self) -> Option<String> {
+        self.guard.as_ref().map(|guard| guard.conversation_id().to_string())
+    }
+}
+
+/// Forward batch progress to an optional Python callable
+fn progress_callback(progress: Option<PyObject>) -> impl FnMut(&BatchReport) + Send {
+    move |report: &BatchReport| {
+        if let Some(progress) = &progress {
+            Python::with_gil(|py| {
+                // A failing callback is reported but does not stop the batch
+                if let Err(e) = progress.call1(py, (report.processed, report.modified, report.errors.len())) {
+                    e.print(py);
+                }
+            });
+        }
+    }
+}
+
+fn batch_report_to_dict(py: Python, report: BatchReport) -> PyResult<&PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("processed", report.processed)?;
+    result.set_item("modified", report.modified)?;
+    result.set_item("errors", report.errors)?;
+    Ok(result)
+}
+
+fn update_report_to_dict<'p>(py: Python<'p>, report: &UpdateReport) -> PyResult<&'p PyDict> {
+    let outcomes = pyo3::types::PyList::empty(py);
+    for outcome in &report.hook_outcomes {
+        let outcome_dict = PyDict::new(py);
+        outcome_dict.set_item("hook", &outcome.hook)?;
+        outcome_dict.set_item("message_index", outcome.message_index)?;
+        outcome_dict.set_item("decision", outcome.decision.as_str())?;
+        outcomes.append(outcome_dict)?;
+    }
+    let result = PyDict::new(py);
+    result.set_item("hook_outcomes", outcomes)?;
+    result.set_item("messages_processed", report.messages_processed)?;
+    result.set_item("messages_dropped", report.messages_dropped)?;
+    Ok(result)
 }
 
 #[pyclass]
@@ -157,13 +677,58 @@ pub struct PyContextWindowManager {
     inner: ContextWindowManager,
 }
 
+impl PyContextWindowManager {
+    pub(crate) fn from_manager(inner: ContextWindowManager) -> Self {
+        Self { inner }
+    }
+}
+
 #[pymethods]
 impl PyContextWindowManager {
+    /// `reserved_tokens` is a number of output tokens, or a dict with any of
+    /// `output`, `system_suffix`, `tool_schemas` and `safety_margin`
     #[new]
-    fn new(reserved_tokens: Option<usize>) -> Self {
-        Self {
-            inner: ContextWindowManager::new(reserved_tokens.unwrap_or(1000)),
+    fn new(reserved_tokens: Option<&PyAny>) -> PyResult<Self> {
+        let reserved = match reserved_tokens {
+            Some(reserved) => reserved_from_py(reserved)?,
+            None => ReservedBudget::output(1000),
+        };
+        Ok(Self {
+            inner: ContextWindowManager::new(reserved),
+        })
+    }
+    
+    /// Use `window` tokens for `model` and its dated variants, e.g. a newly released model
+    ///
+    /// With `reserved` (a number or dict, as in the constructor), the model
+    /// also gets its own reserved budget.
+    fn register_model(&mut self, model: String, window: usize, reserved: Option<&PyAny>) -> PyResult<()> {
+        if let Some(reserved) = reserved {
+            self.inner.register_model_reserved(model.clone(), reserved_from_py(reserved)?);
         }
+        self.inner.register_model(model, window);
+        Ok(())
+    }
+    
+    fn context_window(&self, model: String) -> usize {
+        self.inner.context_window(&model)
+    }
+    
+    /// The budget reserved for `model`, as a dict with its parts and `total`
+    fn reserved_budget<'p>(&self, py: Python<'p>, model: String) -> PyResult<&'p PyDict> {
+        let reserved = self.inner.reserved_budget(&model);
+        let result = PyDict::new(py);
+        result.set_item("output", reserved.output)?;
+        result.set_item("system_suffix", reserved.system_suffix)?;
+        result.set_item("tool_schemas", reserved.tool_schemas)?;
+        result.set_item("safety_margin", reserved.safety_margin)?;
+        result.set_item("total", reserved.total())?;
+        Ok(result)
+    }
+    
+    /// Tokens of `model`'s window left for messages
+    fn available_tokens(&self, model: String) -> usize {
+        self.inner.available_tokens(&model)
     }
     
     fn manage_context(&self, py: Python, context_dict: &PyDict, model: String) -> PyResult<PyDict> {
@@ -177,9 +742,10 @@ impl PyContextWindowManager {
         context_to_dict(py, &context)
     }
     
-    fn manage_context_with_reserved(&self, py: Python, context_dict: &PyDict, model: String, reserved_tokens: usize) -> PyResult<PyDict> {
+    /// `manage_context` with `reserved_tokens` (a number or dict, as in the constructor) reserved instead
+    fn manage_context_with_reserved(&self, py: Python, context_dict: &PyDict, model: String, reserved_tokens: &PyAny) -> PyResult<PyDict> {
         // Create window manager with custom reserved tokens
-        let manager = ContextWindowManager::new(reserved_tokens);
+        let manager = ContextWindowManager::new(reserved_from_py(reserved_tokens)?);
         
         // Convert Python dict to Rust Context
         let mut context = dict_to_context(context_dict)?;
@@ -190,6 +756,75 @@ impl PyContextWindowManager {
         // Convert back to Python dict
         context_to_dict(py, &context)
     }
+    
+    /// `manage_context`, returning {"context", "diff", "diff_text"}
+    fn manage_context_explained<'p>(&self, py: Python<'p>, context_dict: &PyDict, model: String) -> PyResult<&'p PyDict> {
+        let mut context = dict_to_context(context_dict)?;
+        let diff = self.inner.manage_context_explained(&mut context, &model);
+        
+        let result = PyDict::new(py);
+        result.set_item("context", context_to_dict(py, &context)?)?;
+        result.set_item("diff", diff_to_dict(py, &diff)?)?;
+        result.set_item("diff_text", render_text(&diff))?;
+        Ok(result)
+    }
+}
+
+/// Tool responses keyed by tool and whitespace-normalized request
+#[pyclass]
+pub struct PyToolCache {
+    inner: ToolCallCache,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+#[pymethods]
+impl PyToolCache {
+    /// Persisted in `db_path` if given, otherwise in memory only
+    #[new]
+    fn new(db_path: Option<String>, capacity: Option<usize>) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let mut inner = match db_path {
+                    Some(db_path) => {
+                        let pool = shared_pool_blocking(&rt, &db_path)?;
+                        rt.block_on(ToolCallCache::from_pool(pool)).map_err(PyErr::from)?
+                    }
+                    None => ToolCallCache::new(DEFAULT_TOOL_CACHE_CAPACITY),
+                };
+                if let Some(capacity) = capacity {
+                    inner = inner.with_capacity(capacity);
+                }
+                Ok(Self {
+                    inner,
+                    runtime: std::sync::Mutex::new(rt),
+                })
+            })
+        })
+    }
+    
+    /// Cached response for this tool and request, or None
+    fn check(&self, py: Python, tool: String, request: String) -> PyResult<Option<String>> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.get(&tool, &request_hash(&request)))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Cache a response for `ttl_seconds`, or until evicted if None
+    fn put(&self, py: Python, tool: String, request: String, response: String, ttl_seconds: Option<u64>) -> PyResult<()> {
+        let ttl = ttl_seconds.map(std::time::Duration::from_secs);
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.put(&tool, &request_hash(&request), &response, ttl))
+        })
+        .map_err(PyErr::from)
+    }
 }
 
 #[pyclass]
@@ -197,13 +832,37 @@ pub struct PyContextCompressor {
     inner: ContextCompressor,
 }
 
+impl PyContextCompressor {
+    pub(crate) fn from_compressor(inner: ContextCompressor) -> Self {
+        Self { inner }
+    }
+}
+
 #[pymethods]
 impl PyContextCompressor {
+    /// Over-long messages are cut to `max_length` bytes
+    ///
+    /// `truncation` is the mode for all roles ("head_tail", "head_only",
+    /// "tail_only" or "summary_middle"); `role_truncation` maps roles to their
+    /// own mode, e.g. `{"tool": "tail_only"}`. Unknown modes or roles raise
+    /// ValueError.
     #[new]
-    fn new() -> Self {
-        Self {
-            inner: ContextCompressor::default(),
+    fn new(
+        max_length: Option<usize>,
+        truncation: Option<String>,
+        role_truncation: Option<HashMap<String, String>>,
+    ) -> PyResult<Self> {
+        let mut inner = ContextCompressor::default();
+        if let Some(max_length) = max_length {
+            inner = inner.with_max_length(max_length);
+        }
+        if let Some(mode) = truncation {
+            inner = inner.with_truncation_mode(TruncationMode::parse(&mode)?);
+        }
+        for (role, mode) in role_truncation.unwrap_or_default() {
+            inner = inner.with_role_truncation(parse_role(&role)?, TruncationMode::parse(&mode)?);
         }
+        Ok(Self { inner })
     }
     
     fn compress(&self, py: Python, context_dict: &PyDict) -> PyResult<PyDict> {
@@ -216,9 +875,154 @@ impl PyContextCompressor {
         // Convert back to Python dict
         context_to_dict(py, &context)
     }
+    
+    /// Plan compression without changing the context
+    fn preview(&self, context_dict: &PyDict) -> PyResult<PyCompressionPlan> {
+        let context = dict_to_context(context_dict)?;
+        Ok(PyCompressionPlan {
+            inner: self.inner.preview(&context),
+        })
+    }
+    
+    /// Apply a plan from `preview`; raises ConflictError if the messages changed
+    fn apply(&self, py: Python, context_dict: &PyDict, plan: PyRef<PyCompressionPlan>) -> PyResult<PyDict> {
+        let mut context = dict_to_context(context_dict)?;
+        self.inner.apply_plan(&mut context, &plan.inner)?;
+        context_to_dict(py, &context)
+    }
+    
+    /// `compress`, returning {"context", "stats", "diff", "diff_text"}
+    fn compress_explained<'p>(&self, py: Python<'p>, context_dict: &PyDict) -> PyResult<&'p PyDict> {
+        let mut context = dict_to_context(context_dict)?;
+        let (stats, diff) = self.inner.compress_explained(&mut context);
+        
+        let result = PyDict::new(py);
+        result.set_item("context", context_to_dict(py, &context)?)?;
+        result.set_item("stats", stats_to_dict(py, &stats)?)?;
+        result.set_item("diff", diff_to_dict(py, &diff)?)?;
+        result.set_item("diff_text", render_text(&diff))?;
+        Ok(result)
+    }
+}
+
+/// Human-readable diff between two context dicts
+#[pyfunction]
+pub fn render_context_diff(before: &PyDict, after: &PyDict) -> PyResult<String> {
+    let diff = diff_contexts(&dict_to_context(before)?, &dict_to_context(after)?);
+    Ok(render_text(&diff))
+}
+
+/// Codebase context as a compact prompt block of at most `max_tokens`
+#[pyfunction]
+pub fn render_codebase_context(relevant_files: Vec<String>, semantic_matches: Vec<String>, max_tokens: usize) -> String {
+    let codebase = CodebaseContext { relevant_files, semantic_matches };
+    codebase.render_for_prompt(max_tokens, &TokenCounter::new())
+}
+
+/// Result of `PyContextCompressor.preview`
+#[pyclass]
+pub struct PyCompressionPlan {
+    inner: CompressionPlan,
+}
+
+#[pymethods]
+impl PyCompressionPlan {
+    /// [{index, reason}] where reason is "duplicate" or "similar"
+    #[getter]
+    fn removals<'p>(&self, py: Python<'p>) -> PyResult<Vec<&'p PyDict>> {
+        self.inner.removals.iter().map(|removal| {
+            let item = PyDict::new(py);
+            item.set_item("index", removal.index)?;
+            item.set_item("reason", removal.reason.as_str())?;
+            Ok(item)
+        }).collect()
+    }
+    
+    /// [{index, original_length, new_length}] for messages that would be cut short
+    #[getter]
+    fn truncations<'p>(&self, py: Python<'p>) -> PyResult<Vec<&'p PyDict>> {
+        self.inner.truncations().map(|rewrite| {
+            let item = PyDict::new(py);
+            item.set_item("index", rewrite.index)?;
+            item.set_item("original_length", rewrite.original_length)?;
+            item.set_item("new_length", rewrite.new_length)?;
+            Ok(item)
+        }).collect()
+    }
+    
+    /// Projected compression stats
+    #[getter]
+    fn stats<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        stats_to_dict(py, &self.inner.stats)
+    }
+    
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+fn stats_to_dict<'p>(py: Python<'p>, stats: &CompressionStats) -> PyResult<&'p PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("original_size", stats.original_size)?;
+    result.set_item("compressed_size", stats.compressed_size)?;
+    result.set_item("compression_ratio", stats.compression_ratio)?;
+    result.set_item("duplicates_removed", stats.duplicates_removed)?;
+    result.set_item("similar_removed", stats.similar_removed)?;
+    result.set_item("estimated_tokens_saved", stats.estimated_tokens_saved())?;
+    Ok(result)
+}
+
+fn diff_to_dict<'p>(py: Python<'p>, diff: &ContextDiff) -> PyResult<&'p PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("removed", diff.removed.iter().map(|m| m.index).collect::<Vec<_>>())?;
+    result.set_item("added", diff.added.iter().map(|m| m.index).collect::<Vec<_>>())?;
+    result.set_item(
+        "modified",
+        diff.modified.iter().map(|m| (m.before_index, m.after_index)).collect::<Vec<_>>(),
+    )?;
+    result.set_item("unchanged", diff.unchanged)?;
+    result.set_item("tool_calls_removed", diff.tool_calls_removed.iter().map(|c| c.index).collect::<Vec<_>>())?;
+    result.set_item("tool_calls_added", diff.tool_calls_added.iter().map(|c| c.index).collect::<Vec<_>>())?;
+    result.set_item("tokens_before", diff.tokens_before)?;
+    result.set_item("tokens_after", diff.tokens_after)?;
+    result.set_item("token_delta", diff.token_delta())?;
+    Ok(result)
 }
 
 // Helper functions to convert between Python dicts and Rust Context
+fn parse_role(role: &str) -> PyResult<Role> {
+    Role::parse(role).map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// A reserved budget from a number of output tokens or a dict of its parts
+fn reserved_from_py(value: &PyAny) -> PyResult<ReservedBudget> {
+    if let Ok(output) = value.extract::<usize>() {
+        return Ok(ReservedBudget::output(output));
+    }
+    let dict = value.downcast::<PyDict>().map_err(|_| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>("Reserved tokens must be a number or a dict")
+    })?;
+    
+    let mut reserved = ReservedBudget::default();
+    for (key, tokens) in dict.iter() {
+        let key: String = key.extract()?;
+        let tokens: usize = tokens.extract()?;
+        match key.as_str() {
+            "output" => reserved.output = tokens,
+            "system_suffix" => reserved.system_suffix = tokens,
+            "tool_schemas" => reserved.tool_schemas = tokens,
+            "safety_margin" => reserved.safety_margin = tokens,
+            other => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unknown reserved budget part: {} (expected output, system_suffix, tool_schemas or safety_margin)",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(reserved)
+}
+
 fn dict_to_context(dict: &PyDict) -> PyResult<Context> {
     let conversation_id: String = dict.get_item("conversation_id")?
         .and_then(|v| v.extract().ok())
@@ -229,6 +1033,20 @@ fn dict_to_context(dict: &PyDict) -> PyResult<Context> {
     
     let mut context = Context::new(project_id);
     context.conversation_id = conversation_id;
+    context.title = dict.get_item("title")
+        .and_then(|v| v.extract::<Option<String>>().ok())
+        .flatten();
+    context.labels = dict.get_item("labels")
+        .and_then(|v| v.extract().ok())
+        .unwrap_or_default();
+    if let Some(state) = dict.get_item("window_state").and_then(|v| v.downcast::<PyDict>().ok()) {
+        context.window_state = Some(WindowState {
+            last_model: state.get_item("last_model").and_then(|v| v.extract().ok()).flatten(),
+            last_window: state.get_item("last_window").and_then(|v| v.extract().ok()).unwrap_or(0),
+            dropped_messages: state.get_item("dropped_messages").and_then(|v| v.extract().ok()).unwrap_or(0),
+            last_summary_at: state.get_item("last_summary_at").and_then(|v| v.extract().ok()).flatten(),
+        });
+    }
     
     // Deserialize messages
     if let Some(messages) = dict.get_item("messages") {
@@ -236,15 +1054,20 @@ fn dict_to_context(dict: &PyDict) -> PyResult<Context> {
             for msg_item in msg_list.iter() {
                 if let Ok(msg_dict) = msg_item.downcast::<PyDict>() {
                     let role: String = msg_dict.get_item("role")?.extract()?;
+                    let role = parse_role(&role)?;
                     let content: String = msg_dict.get_item("content")?.extract()?;
                     let timestamp: i64 = msg_dict.get_item("timestamp")
                         .and_then(|v| v.extract().ok())
                         .unwrap_or(0);
+                    let pinned: bool = msg_dict.get_item("pinned")
+                        .and_then(|v| v.extract().ok())
+                        .unwrap_or(false);
                     
-                    context.messages.push(rust_core::context::Message {
+                    context.messages.push(Message {
                         role,
                         content,
                         timestamp,
+                        pinned,
                     });
                 }
             }
@@ -285,12 +1108,15 @@ fn dict_to_context(dict: &PyDict) -> PyResult<Context> {
                     let response: String = tc_dict.get_item("response")
                         .and_then(|v| v.extract().ok())
                         .unwrap_or_default();
+                    let request_id: Option<String> = tc_dict.get_item("request_id")
+                        .and_then(|v| v.extract().ok());
                     
                     context.tool_history.push(rust_core::context::ToolCall {
                         tool,
                         timestamp,
                         request,
                         response,
+                        request_id,
                     });
                 }
             }
@@ -300,17 +1126,33 @@ fn dict_to_context(dict: &PyDict) -> PyResult<Context> {
     Ok(context)
 }
 
+fn window_state_to_dict<'p>(py: Python<'p>, state: &WindowState) -> PyResult<&'p PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("last_model", state.last_model.as_ref())?;
+    dict.set_item("last_window", state.last_window)?;
+    dict.set_item("dropped_messages", state.dropped_messages)?;
+    dict.set_item("last_summary_at", state.last_summary_at)?;
+    Ok(dict)
+}
+
 fn context_to_dict(py: Python, context: &Context) -> PyResult<PyDict> {
     let result = PyDict::new(py);
     result.set_item("conversation_id", &context.conversation_id)?;
     result.set_item("project_id", context.project_id.as_ref())?;
+    result.set_item("title", context.title.as_ref())?;
+    result.set_item("labels", &context.labels)?;
+    match &context.window_state {
+        Some(state) => result.set_item("window_state", window_state_to_dict(py, state)?)?,
+        None => result.set_item("window_state", py.None())?,
+    }
     
     // Serialize messages
     let messages: Vec<PyDict> = context.messages.iter().map(|msg| {
         let msg_dict = PyDict::new(py);
-        msg_dict.set_item("role", &msg.role).unwrap();
+        msg_dict.set_item("role", msg.role.as_str()).unwrap();
         msg_dict.set_item("content", &msg.content).unwrap();
         msg_dict.set_item("timestamp", msg.timestamp).unwrap();
+        msg_dict.set_item("pinned", msg.pinned).unwrap();
         msg_dict
     }).collect();
     let messages_list = pyo3::types::PyList::new(py, messages);
@@ -331,6 +1173,7 @@ fn context_to_dict(py: Python, context: &Context) -> PyResult<PyDict> {
         tc_dict.set_item("timestamp", tc.timestamp).unwrap();
         tc_dict.set_item("request", &tc.request).unwrap();
         tc_dict.set_item("response", &tc.response).unwrap();
+        tc_dict.set_item("request_id", &tc.request_id).unwrap();
         tc_dict
     }).collect();
     let tool_history_list = pyo3::types::PyList::new(py, tool_history);
@@ -338,3 +1181,112 @@ fn context_to_dict(py: Python, context: &Context) -> PyResult<PyDict> {
     
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn temp_db() -> String {
+        std::env::temp_dir()
+            .join(format!("uai-context-{}.db", uuid_suffix()))
+            .to_string_lossy()
+            .to_string()
+    }
+    
+    fn uuid_suffix() -> u128 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    }
+    
+    fn message_count(context: &PyDict) -> usize {
+        context.get_item("messages").unwrap().unwrap().len().unwrap()
+    }
+    
+    #[test]
+    fn test_update_with_returned_dict_does_not_grow_history() {
+        let db_path = temp_db();
+        let manager = PyContextManager::new(db_path.clone(), None, None, None, None, None, None, None, None).unwrap();
+        
+        Python::with_gil(|py| {
+            let context = manager.get_or_create_context(py, None, None).unwrap();
+            let conversation_id: String = context.get_item("conversation_id").unwrap().unwrap().extract().unwrap();
+            
+            let first = PyDict::new(py);
+            first.set_item("role", "User").unwrap();
+            first.set_item("content", "Hello").unwrap();
+            context.set_item("messages", pyo3::types::PyList::new(py, [first])).unwrap();
+            manager.update_context(py, context, None).unwrap();
+            
+            for _ in 0..3 {
+                let context = manager
+                    .get_or_create_context(py, Some(conversation_id.clone()), None)
+                    .unwrap();
+                assert_eq!(message_count(context), 1);
+                manager.update_context(py, context, None).unwrap();
+            }
+            
+            // A new message after the returned history is appended once
+            let context = manager
+                .get_or_create_context(py, Some(conversation_id.clone()), None)
+                .unwrap();
+            let reply = PyDict::new(py);
+            reply.set_item("role", "assistant").unwrap();
+            reply.set_item("content", "Hi").unwrap();
+            context.get_item("messages").unwrap().unwrap()
+                .downcast::<pyo3::types::PyList>().unwrap()
+                .append(reply).unwrap();
+            manager.update_context(py, context, None).unwrap();
+            manager.update_context(py, context, None).unwrap();
+            
+            let context = manager
+                .get_or_create_context(py, Some(conversation_id.clone()), None)
+                .unwrap();
+            assert_eq!(message_count(context), 2);
+            
+            // Opting out of deduplication appends the list as given
+            manager.update_context(py, context, Some(false)).unwrap();
+            let context = manager
+                .get_or_create_context(py, Some(conversation_id), None)
+                .unwrap();
+            assert_eq!(message_count(context), 4);
+        });
+        
+        std::fs::remove_file(&db_path).ok();
+    }
+    
+    #[test]
+    fn test_managers_on_one_handle_share_its_database() {
+        let db_path = temp_db();
+        
+        Python::with_gil(|py| {
+            let database: &PyCell<PyDatabase> = py
+                .get_type::<PyDatabase>()
+                .call1((db_path.clone(),))
+                .unwrap()
+                .downcast()
+                .unwrap();
+            let writer = PyContextManager::with_database(py, database.borrow(), None).unwrap();
+            let reader = PyContextManager::with_database(py, database.borrow(), None).unwrap();
+            
+            let context = writer.get_or_create_context(py, None, None).unwrap();
+            let conversation_id: String = context.get_item("conversation_id").unwrap().unwrap().extract().unwrap();
+            let message = PyDict::new(py);
+            message.set_item("role", "user").unwrap();
+            message.set_item("content", "Hello").unwrap();
+            context.set_item("messages", pyo3::types::PyList::new(py, [message])).unwrap();
+            writer.update_context(py, context, None).unwrap();
+            
+            let seen = reader.get_or_create_context(py, Some(conversation_id.clone()), None).unwrap();
+            assert_eq!(message_count(seen), 1);
+            
+            // A manager opened from the path joins the same pool
+            let by_path = PyContextManager::new(db_path.clone(), None, None, None, None, None, None, None, None).unwrap();
+            let seen = by_path.get_or_create_context(py, Some(conversation_id), None).unwrap();
+            assert_eq!(message_count(seen), 1);
+        });
+        
+        std::fs::remove_file(&db_path).ok();
+    }
+}
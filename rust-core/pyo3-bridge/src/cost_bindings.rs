@@ -0,0 +1,237 @@
+/// PyO3 bindings for cost tracking
+
+use pyo3::prelude::*;
+use rust_core::cost::storage::{CostRecord, CostStorage};
+use rust_core::cost::{BudgetManager, CostCalculator};
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+
+#[pyclass]
+pub struct PyCostTracker {
+    storage: CostStorage,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+impl PyCostTracker {
+    fn from_pool(rt: tokio::runtime::Runtime, pool: SqlitePool) -> PyResult<Self> {
+        let storage = rt.block_on(CostStorage::from_pool(pool))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create cost storage: {}", e)
+            ))?;
+        
+        Ok(Self {
+            storage,
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+}
+
+/// Unix seconds to a UTC time, defaulting to the epoch or now
+fn to_datetime(timestamp: Option<i64>, default: DateTime<Utc>) -> PyResult<DateTime<Utc>> {
+    match timestamp {
+        Some(timestamp) => DateTime::from_timestamp(timestamp, 0).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid timestamp: {}", timestamp))
+        }),
+        None => Ok(default),
+    }
+}
+
+#[pymethods]
+impl PyCostTracker {
+    #[new]
+    fn new(db_path: String) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Self::from_pool(rt, pool)
+            })
+        })
+    }
+    
+    #[staticmethod]
+    fn with_database(py: Python, database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let pool = database.pool();
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            Self::from_pool(rt, pool)
+        })
+    }
+    
+    /// Record one call's cost; raises ValueError if the labels are invalid
+    #[allow(clippy::too_many_arguments)]
+    fn record(
+        &self,
+        py: Python,
+        tool: String,
+        model: String,
+        input_tokens: u32,
+        output_tokens: u32,
+        cost_usd: f64,
+        user_id: Option<String>,
+        project_id: Option<String>,
+        conversation_id: Option<String>,
+        labels: Option<HashMap<String, String>>,
+        request_id: Option<String>,
+    ) -> PyResult<()> {
+        let record = CostRecord {
+            id: None,
+            request_id,
+            tool,
+            model,
+            input_tokens,
+            output_tokens,
+            cost_usd,
+            timestamp: Utc::now(),
+            user_id,
+            project_id,
+            conversation_id,
+            labels: labels.unwrap_or_default(),
+        };
+        
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.storage.record_cost(&record))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Total cost between two Unix timestamps (default: all time)
+    fn get_total_cost(
+        &self,
+        py: Python,
+        start: Option<i64>,
+        end: Option<i64>,
+        user_id: Option<String>,
+        project_id: Option<String>,
+        labels_match: Option<HashMap<String, String>>,
+    ) -> PyResult<f64> {
+        let start = to_datetime(start, DateTime::<Utc>::UNIX_EPOCH)?;
+        let end = to_datetime(end, Utc::now())?;
+        
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.storage.get_total_cost(
+                start,
+                end,
+                user_id.as_deref(),
+                project_id.as_deref(),
+                labels_match.as_ref(),
+            ))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Total cost per value of `label_key` between two Unix timestamps
+    fn get_cost_by_label(
+        &self,
+        py: Python,
+        label_key: String,
+        start: Option<i64>,
+        end: Option<i64>,
+    ) -> PyResult<HashMap<String, f64>> {
+        let start = to_datetime(start, DateTime::<Utc>::UNIX_EPOCH)?;
+        let end = to_datetime(end, Utc::now())?;
+        
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.storage.get_cost_by_label(start, end, &label_key))
+        })
+        .map_err(PyErr::from)
+    }
+}
+
+/// Cost estimates and budget-aware output token caps
+///
+/// Budgets of 0 or None are unlimited.
+#[pyclass]
+pub struct PyCostCalculator {
+    budgets: BudgetManager,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+impl PyCostCalculator {
+    fn from_pool(
+        rt: tokio::runtime::Runtime,
+        pool: SqlitePool,
+        daily_budget_usd: Option<f64>,
+        monthly_budget_usd: Option<f64>,
+    ) -> PyResult<Self> {
+        let storage = rt.block_on(CostStorage::from_pool(pool))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create cost storage: {}", e)
+            ))?;
+        let budgets = BudgetManager::new(storage, CostCalculator::new())
+            .with_daily_budget(daily_budget_usd.unwrap_or(0.0))
+            .with_monthly_budget(monthly_budget_usd.unwrap_or(0.0));
+        
+        Ok(Self {
+            budgets,
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+}
+
+#[pymethods]
+impl PyCostCalculator {
+    #[new]
+    fn new(db_path: String, daily_budget_usd: Option<f64>, monthly_budget_usd: Option<f64>) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Self::from_pool(rt, pool, daily_budget_usd, monthly_budget_usd)
+            })
+        })
+    }
+    
+    #[staticmethod]
+    fn with_database(
+        py: Python,
+        database: PyRef<PyDatabase>,
+        daily_budget_usd: Option<f64>,
+        monthly_budget_usd: Option<f64>,
+    ) -> PyResult<Self> {
+        let pool = database.pool();
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            Self::from_pool(rt, pool, daily_budget_usd, monthly_budget_usd)
+        })
+    }
+    
+    fn calculate(&self, tool: &str, model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
+        self.budgets.calculator().calculate(tool, model, input_tokens, output_tokens)
+    }
+    
+    /// Most output tokens `remaining_budget_usd` pays for after the input; None without pricing
+    fn max_affordable_output_tokens(&self, model: &str, remaining_budget_usd: f64, input_tokens: u32) -> Option<u32> {
+        self.budgets.calculator().max_affordable_output_tokens(model, remaining_budget_usd, input_tokens)
+    }
+    
+    /// Output token cap for the project's next request; None if the model has no pricing
+    fn affordable_output(&self, py: Python, project_id: String, model: String, input_tokens: u32) -> PyResult<Option<u32>> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.budgets.affordable_output(&project_id, &model, input_tokens))
+        })
+        .map_err(PyErr::from)
+    }
+}
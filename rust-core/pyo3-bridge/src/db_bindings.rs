@@ -0,0 +1,393 @@
+/// PyO3 bindings for shared database connection pools
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rust_core::error::OrchestratorError;
+use rust_core::storage::{backup_to, maintain, restore_from, verify_backup, BackupVerification, MaintenanceOptions, VacuumMode};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Connections per pool when no size is given
+pub(crate) const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+/// Process-wide pools keyed by canonical database path
+///
+/// `SqlitePool` is reference-counted, so every object built for the same
+/// file shares one set of connections. Pools live for the whole process.
+static POOL_REGISTRY: OnceLock<Mutex<HashMap<PathBuf, SqlitePool>>> = OnceLock::new();
+
+fn registry() -> &'static Mutex<HashMap<PathBuf, SqlitePool>> {
+    POOL_REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Canonical key for a database path that may not exist yet
+fn registry_key(db_path: &str) -> PathBuf {
+    let path = Path::new(db_path);
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Get the shared pool for `db_path`, opening it on first use
+///
+/// `max_connections` only applies when the pool is first opened. In-memory
+/// databases are never shared since each pool is its own database.
+pub(crate) async fn shared_pool(db_path: &str, max_connections: u32) -> Result<SqlitePool, String> {
+    if db_path == ":memory:" {
+        return open_pool(db_path, max_connections).await;
+    }
+    
+    // The directory must exist for the path to canonicalize consistently
+    if let Some(parent) = Path::new(db_path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create database directory: {}", e))?;
+    }
+    
+    let key = registry_key(db_path);
+    if let Some(pool) = registry().lock().unwrap().get(&key) {
+        return Ok(pool.clone());
+    }
+    
+    let pool = open_pool(&key.to_string_lossy(), max_connections).await?;
+    
+    // Another object may have opened the same path meanwhile; keep the first
+    let mut pools = registry().lock().unwrap();
+    Ok(pools.entry(key).or_insert(pool).clone())
+}
+
+async fn open_pool(db_path: &str, max_connections: u32) -> Result<SqlitePool, String> {
+    SqlitePoolOptions::new()
+        .max_connections(max_connections.max(1))
+        .connect_with(
+            SqliteConnectOptions::new()
+                .filename(db_path)
+                .create_if_missing(true),
+        )
+        .await
+        .map_err(|e| format!("Failed to create pool: {}", e))
+}
+
+/// Open the shared pool for a path-based constructor
+pub(crate) fn shared_pool_blocking(rt: &tokio::runtime::Runtime, db_path: &str) -> PyResult<SqlitePool> {
+    rt.block_on(shared_pool(db_path, DEFAULT_MAX_CONNECTIONS))
+        .map_err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>)
+}
+
+/// Handle to a database's shared connection pool
+///
+/// Bindings that open a database from a path also have a `with_database`
+/// constructor taking this handle instead, so the objects working on one
+/// database can all be built from a single handle and its pool.
+#[pyclass]
+pub struct PyDatabase {
+    pool: SqlitePool,
+    path: String,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+impl PyDatabase {
+    /// Pool handle for constructing other bridge objects
+    pub(crate) fn pool(&self) -> SqlitePool {
+        self.pool.clone()
+    }
+}
+
+#[pymethods]
+impl PyDatabase {
+    #[new]
+    fn new(db_path: String, max_connections: Option<u32>) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let pool = rt.block_on(shared_pool(
+                    &db_path,
+                    max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS),
+                ))
+                .map_err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>)?;
+                
+                Ok(Self {
+                    pool,
+                    path: db_path,
+                    runtime: std::sync::Mutex::new(rt),
+                })
+            })
+        })
+    }
+    
+    #[getter]
+    fn path(&self) -> String {
+        self.path.clone()
+    }
+    
+    /// Connections currently open (idle or in use)
+    fn size(&self) -> u32 {
+        self.pool.size()
+    }
+    
+    fn num_idle(&self) -> usize {
+        self.pool.num_idle()
+    }
+    
+    /// Run a PRAGMA and return its first column as text (e.g. "journal_mode")
+    fn pragma(&self, py: Python, name: String) -> PyResult<Option<String>> {
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid pragma name: {}", name)
+            ));
+        }
+        
+        let pool = self.pool.clone();
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async {
+                // Table-valued pragma form so numeric results decode as text
+                sqlx::query_scalar::<_, String>(&format!("SELECT CAST((SELECT * FROM pragma_{}) AS TEXT)", name))
+                    .fetch_optional(&pool)
+                    .await
+            })
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("PRAGMA {} failed: {}", name, e)
+            ))
+        })
+    }
+    
+    /// Vacuum, analyze, check integrity and checkpoint the WAL
+    ///
+    /// `vacuum` is "none", "incremental" (default) or "full"; a full vacuum is
+    /// refused with ConflictError while other connections are in use.
+    fn maintenance<'p>(
+        &self,
+        py: Python<'p>,
+        vacuum: Option<String>,
+        analyze: Option<bool>,
+        integrity_check: Option<bool>,
+        checkpoint: Option<bool>,
+    ) -> PyResult<&'p PyDict> {
+        let defaults = MaintenanceOptions::default();
+        let vacuum = match vacuum {
+            Some(mode) => VacuumMode::parse(&mode)?,
+            None => defaults.vacuum,
+        };
+        let options = defaults
+            .clone()
+            .with_vacuum(vacuum)
+            .with_analyze(analyze.unwrap_or(defaults.analyze))
+            .with_integrity_check(integrity_check.unwrap_or(defaults.integrity_check))
+            .with_checkpoint(checkpoint.unwrap_or(defaults.checkpoint));
+        
+        let pool = self.pool.clone();
+        let report = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(maintain(&pool, &options))
+        })
+        .map_err(PyErr::from)?;
+        
+        let result = PyDict::new(py);
+        result.set_item("size_before", report.size_before)?;
+        result.set_item("size_after", report.size_after)?;
+        result.set_item("bytes_reclaimed", report.bytes_reclaimed())?;
+        result.set_item("freelist_before", report.freelist_before)?;
+        result.set_item("freelist_after", report.freelist_after)?;
+        result.set_item("vacuum", report.vacuum.as_str())?;
+        result.set_item("analyzed", report.analyzed)?;
+        result.set_item("integrity_ok", report.integrity_ok())?;
+        result.set_item("integrity", report.integrity)?;
+        result.set_item("wal_frames_checkpointed", report.wal_frames_checkpointed)?;
+        result.set_item("duration_ms", report.duration.as_millis() as u64)?;
+        Ok(result)
+    }
+    
+    /// Write a consistent copy of the database while it stays in use
+    fn backup<'p>(&self, py: Python<'p>, dest_path: String, compress: Option<bool>) -> PyResult<&'p PyDict> {
+        let pool = self.pool.clone();
+        let report = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(backup_to(&pool, Path::new(&dest_path), compress.unwrap_or(false)))
+        })
+        .map_err(PyErr::from)?;
+        
+        let result = PyDict::new(py);
+        result.set_item("path", report.path.to_string_lossy().to_string())?;
+        result.set_item("bytes", report.bytes)?;
+        result.set_item("compressed", report.compressed)?;
+        result.set_item("duration_ms", report.duration.as_millis() as u64)?;
+        Ok(result)
+    }
+    
+    /// Integrity-check a backup file, gzip-compressed or not
+    #[staticmethod]
+    fn verify_backup<'p>(py: Python<'p>, path: String) -> PyResult<&'p PyDict> {
+        let verification = py.allow_threads(|| {
+            blocking_runtime()?.block_on(verify_backup(Path::new(&path))).map_err(PyErr::from)
+        })?;
+        verification_dict(py, verification)
+    }
+    
+    /// Restore a backup to `dest_path`
+    ///
+    /// Refuses with ConflictError when the destination exists or has a pool
+    /// open in this process, unless `force` is true.
+    #[staticmethod]
+    fn restore_backup<'p>(py: Python<'p>, src_path: String, dest_path: String, force: Option<bool>) -> PyResult<&'p PyDict> {
+        let force = force.unwrap_or(false);
+        if !force && registry().lock().unwrap().contains_key(&registry_key(&dest_path)) {
+            return Err(OrchestratorError::ConflictDetected(format!(
+                "Database {} is open in this process",
+                dest_path
+            ))
+            .into());
+        }
+        
+        let verification = py.allow_threads(|| {
+            blocking_runtime()?
+                .block_on(restore_from(Path::new(&src_path), Path::new(&dest_path), force))
+                .map_err(PyErr::from)
+        })?;
+        verification_dict(py, verification)
+    }
+}
+
+fn blocking_runtime() -> PyResult<tokio::runtime::Runtime> {
+    tokio::runtime::Runtime::new()
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Failed to create runtime: {}", e)
+        ))
+}
+
+fn verification_dict<'p>(py: Python<'p>, verification: BackupVerification) -> PyResult<&'p PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("ok", verification.is_ok())?;
+    result.set_item("compressed", verification.compressed)?;
+    result.set_item("integrity", verification.integrity)?;
+    result.set_item("tables", verification.tables)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn temp_db() -> String {
+        std::env::temp_dir()
+            .join(format!("uai-bridge-{}", unique_suffix()))
+            .join("db.sqlite")
+            .to_string_lossy()
+            .to_string()
+    }
+    
+    fn unique_suffix() -> u128 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    }
+    
+    #[test]
+    fn test_objects_for_same_path_share_one_pool() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let db_path = temp_db();
+        
+        let pools: Vec<SqlitePool> = (0..12)
+            .map(|_| shared_pool_blocking(&rt, &db_path).unwrap())
+            .collect();
+        
+        rt.block_on(async {
+            // Hold every connection the pools allow at once
+            let mut held = Vec::new();
+            for pool in &pools {
+                if let Ok(conn) = tokio::time::timeout(
+                    std::time::Duration::from_millis(200),
+                    pool.acquire(),
+                ).await {
+                    held.push(conn.unwrap());
+                }
+            }
+            
+            assert_eq!(held.len(), DEFAULT_MAX_CONNECTIONS as usize);
+            assert_eq!(pools[0].size(), DEFAULT_MAX_CONNECTIONS);
+            
+            // Every connection points at the same database file
+            let (_, _, file): (i64, String, String) = sqlx::query_as("PRAGMA database_list")
+                .fetch_one(&mut *held[0])
+                .await
+                .unwrap();
+            assert_eq!(PathBuf::from(file), registry_key(&db_path));
+        });
+        
+        std::fs::remove_dir_all(Path::new(&db_path).parent().unwrap()).ok();
+    }
+    
+    #[test]
+    fn test_equivalent_paths_share_pool() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let db_path = temp_db();
+        let first = shared_pool_blocking(&rt, &db_path).unwrap();
+        
+        let dir = Path::new(&db_path).parent().unwrap();
+        let dotted = dir.join(".").join("db.sqlite").to_string_lossy().to_string();
+        let second = shared_pool_blocking(&rt, &dotted).unwrap();
+        
+        rt.block_on(async {
+            let _conn = first.acquire().await.unwrap();
+            assert_eq!(second.size(), first.size());
+        });
+        std::fs::remove_dir_all(dir).ok();
+    }
+    
+    #[test]
+    fn test_handle_pool_is_shared_with_path_constructors() {
+        let db_path = temp_db();
+        let database = PyDatabase::new(db_path.clone(), Some(2)).unwrap();
+        assert_eq!(database.path(), db_path);
+        
+        let rt = database.runtime.lock().unwrap();
+        // A path-based constructor opened later gets the handle's pool and limit
+        let by_path = shared_pool_blocking(&rt, &db_path).unwrap();
+        rt.block_on(async {
+            let _first = database.pool().acquire().await.unwrap();
+            let _second = by_path.acquire().await.unwrap();
+            assert_eq!(database.size(), 2);
+            assert!(tokio::time::timeout(
+                std::time::Duration::from_millis(200),
+                by_path.acquire(),
+            ).await.is_err());
+        });
+        drop(rt);
+        
+        std::fs::remove_dir_all(Path::new(&db_path).parent().unwrap()).ok();
+    }
+    
+    #[test]
+    fn test_in_memory_handles_are_not_shared() {
+        let first = PyDatabase::new(":memory:".to_string(), Some(1)).unwrap();
+        let second = PyDatabase::new(":memory:".to_string(), Some(1)).unwrap();
+        
+        let rt = first.runtime.lock().unwrap();
+        rt.block_on(async {
+            sqlx::query("CREATE TABLE only_in_first (id INTEGER)")
+                .execute(&first.pool())
+                .await
+                .unwrap();
+            let tables: Vec<(String,)> = sqlx::query_as("SELECT name FROM sqlite_master WHERE name = 'only_in_first'")
+                .fetch_all(&second.pool())
+                .await
+                .unwrap();
+            assert!(tables.is_empty());
+        });
+    }
+}
@@ -1,12 +1,20 @@
 /// PyO3 bindings for codebase indexer
 
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
 use rust_core::indexer::codebase::CodebaseIndexer;
-use rust_core::indexer::search::SemanticSearch;
+use rust_core::indexer::diagnostics::{Diagnostics, Severity};
+use rust_core::indexer::disk_cache::{EmbeddingCacheStats, DEFAULT_EMBEDDING_CACHE_ENTRIES};
+use rust_core::indexer::embed_pool::default_worker_count;
+use rust_core::indexer::extraction_rules::ExtractionRules;
+use rust_core::indexer::search::{render_explanation, Explanation, SemanticSearch};
+use rust_core::indexer::semantic::EmbeddingGenerator;
 use rust_core::indexer::storage::IndexStorage;
-use rust_core::indexer::watcher::FileWatcher;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-use std::path::PathBuf;
+use rust_core::indexer::journal::WatcherJournal;
+use rust_core::indexer::watcher::{FileWatcher, WatcherControl};
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc;
@@ -17,12 +25,55 @@ use tokio::task::JoinHandle;
 #[pyclass]
 pub struct PyCodebaseIndexer {
     indexer: CodebaseIndexer,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+/// Index storage for a path-based constructor
+///
+/// Read-only storage opens its own connection instead of the shared pool,
+/// which may be writable and would create the file if it were missing.
+fn open_storage(rt: &tokio::runtime::Runtime, db_path: &str, read_only: Option<bool>) -> PyResult<IndexStorage> {
+    if read_only.unwrap_or(false) {
+        return rt
+            .block_on(IndexStorage::open_read_only(Path::new(db_path)))
+            .map_err(PyErr::from);
+    }
+    Ok(IndexStorage::new(shared_pool_blocking(rt, db_path)?))
+}
+
+impl PyCodebaseIndexer {
+    fn from_storage(rt: tokio::runtime::Runtime, project_id: String, storage: IndexStorage) -> Self {
+        Self {
+            indexer: CodebaseIndexer::new(project_id, storage),
+            runtime: std::sync::Mutex::new(rt),
+        }
+    }
 }
 
 #[pymethods]
 impl PyCodebaseIndexer {
+    /// With `read_only`, an existing index is opened without write access
+    ///
+    /// With `embed`, `index_directory` also embeds the blocks it stores on
+    /// `embedding_threads` worker threads (half the cores by default).
+    ///
+    /// `rules_path` names a TOML file of per-language extraction rules;
+    /// without it the built-in node kinds are used.
+    ///
+    /// `embedding_cache_path` names a directory where block embeddings are
+    /// kept between runs, at most `embedding_cache_max_entries` of them
+    /// (100000 by default), so unchanged blocks are not embedded again.
     #[new]
-    fn new(project_id: String, db_path: String) -> PyResult<Self> {
+    fn new(
+        project_id: String,
+        db_path: String,
+        read_only: Option<bool>,
+        embed: Option<bool>,
+        embedding_threads: Option<usize>,
+        rules_path: Option<String>,
+        embedding_cache_path: Option<String>,
+        embedding_cache_max_entries: Option<usize>,
+    ) -> PyResult<Self> {
         Python::with_gil(|py| {
             py.allow_threads(|| {
                 let rt = tokio::runtime::Runtime::new()
@@ -30,33 +81,44 @@ impl PyCodebaseIndexer {
                         format!("Failed to create runtime: {}", e)
                     ))?;
                 
-                let pool = rt.block_on(async {
-                    SqlitePoolOptions::new()
-                        .max_connections(5)
-                        .connect_with(
-                            sqlx::sqlite::SqliteConnectOptions::new()
-                                .filename(&db_path)
-                                .create_if_missing(true),
-                        )
-                        .await
-                })
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                    format!("Failed to create pool: {}", e)
-                ))?;
-                
-                let storage = IndexStorage::new(pool);
-                let indexer = CodebaseIndexer::new(project_id, storage);
-                
-                Ok(Self {
-                    indexer,
-                    runtime: std::sync::Mutex::new(rt),
-                })
+                let storage = open_storage(&rt, &db_path, read_only)?;
+                let mut indexer = Self::from_storage(rt, project_id, storage);
+                if embed.unwrap_or(false) {
+                    let threads = embedding_threads.unwrap_or_else(default_worker_count);
+                    indexer.indexer = indexer.indexer.with_embedding_workers(threads);
+                }
+                if let Some(rules_path) = rules_path {
+                    let rules = ExtractionRules::load(Path::new(&rules_path)).map_err(PyErr::from)?;
+                    indexer.indexer = indexer.indexer.with_extraction_rules(rules);
+                }
+                if let Some(cache_path) = embedding_cache_path {
+                    let max_entries = embedding_cache_max_entries.unwrap_or(DEFAULT_EMBEDDING_CACHE_ENTRIES);
+                    let embedding_gen = EmbeddingGenerator::default()
+                        .with_persistent_cache(&cache_path, max_entries)
+                        .map_err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>)?;
+                    indexer.indexer = indexer.indexer.with_embedding_generator(embedding_gen);
+                }
+                Ok(indexer)
             })
         })
     }
     
-    fn index_directory(&mut self, py: Python, root_path: String) -> PyResult<usize> {
+    #[staticmethod]
+    fn with_database(project_id: String, database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create runtime: {}", e)
+            ))?;
+        
+        Ok(Self::from_storage(rt, project_id, IndexStorage::new(database.pool())))
+    }
+    
+    /// `use_git_discovery` lists files with `git ls-files` instead of walking the tree
+    fn index_directory(&mut self, py: Python, root_path: String, use_git_discovery: Option<bool>) -> PyResult<usize> {
         let indexer = &mut self.indexer;
+        if let Some(enabled) = use_git_discovery {
+            indexer.set_git_discovery(enabled);
+        }
         let path = PathBuf::from(root_path);
         
         py.allow_threads(|| {
@@ -74,6 +136,26 @@ impl PyCodebaseIndexer {
         })
     }
     
+    /// `index_directory` returning {files_indexed, embeddings_completed, embeddings_pending}
+    fn index_directory_report<'p>(&mut self, py: Python<'p>, root_path: String) -> PyResult<&'p PyDict> {
+        let indexer = &mut self.indexer;
+        let runtime = &self.runtime;
+        let path = PathBuf::from(root_path);
+        let report = py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(indexer.index_directory_report(&path))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Indexing failed: {}", e)
+        ))?;
+        
+        let result = PyDict::new(py);
+        result.set_item("files_indexed", report.files_indexed)?;
+        result.set_item("embeddings_completed", report.embeddings_completed)?;
+        result.set_item("embeddings_pending", report.embeddings_pending)?;
+        Ok(result)
+    }
+    
     fn index_file(&mut self, py: Python, file_path: String) -> PyResult<()> {
         let indexer = &mut self.indexer;
         let path = PathBuf::from(file_path);
@@ -81,7 +163,7 @@ impl PyCodebaseIndexer {
         py.allow_threads(|| {
             let rt = self.runtime.lock().unwrap();
             rt.block_on(async {
-                indexer.index_file(&path).await
+                indexer.index_file(&path).await.map(|_| ())
             })
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
                 format!("File indexing failed: {}", e)
@@ -100,7 +182,7 @@ impl PyCodebaseIndexer {
                 ))?;
             
             rt.block_on(async {
-                indexer.update_file(&path).await
+                indexer.update_file(&path).await.map(|_| ())
             })
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
                 format!("File update failed: {}", e)
@@ -122,6 +204,188 @@ impl PyCodebaseIndexer {
             ))
         })
     }
+    
+    /// Check the index for orphaned blocks, missing and out-of-date files
+    fn validate_index<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let validation = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.indexer.validate_index())
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Index validation failed: {}", e)
+        ))?;
+        
+        let result = PyDict::new(py);
+        result.set_item("healthy", validation.is_healthy())?;
+        result.set_item("total_files", validation.total_files)?;
+        result.set_item("total_blocks", validation.total_blocks)?;
+        result.set_item("orphaned_blocks", validation.orphaned_blocks)?;
+        result.set_item("missing_files", validation.missing_files)?;
+        result.set_item("out_of_date_files", validation.out_of_date_files)?;
+        result.set_item("mismatched_embeddings", validation.mismatched_embeddings)?;
+        result.set_item("errors", validation.errors)?;
+        Ok(result)
+    }
+    
+    /// Repair the index, returning counts per category
+    ///
+    /// Rows for files missing from disk are kept unless `remove_missing` is true.
+    fn repair_index<'p>(&mut self, py: Python<'p>, remove_missing: Option<bool>) -> PyResult<&'p PyDict> {
+        let indexer = &mut self.indexer;
+        let runtime = &self.runtime;
+        let report = py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(indexer.repair_index(remove_missing.unwrap_or(false)))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Index repair failed: {}", e)
+        ))?;
+        
+        let result = PyDict::new(py);
+        result.set_item("orphaned_blocks_removed", report.orphaned_blocks_removed)?;
+        result.set_item("missing_files_removed", report.missing_files_removed)?;
+        result.set_item("files_reindexed", report.files_reindexed)?;
+        result.set_item("embeddings_cleared", report.embeddings_cleared)?;
+        result.set_item("errors", report.errors)?;
+        Ok(result)
+    }
+    
+    /// Recent problems as [{severity, source, path, message, timestamp}], oldest first
+    ///
+    /// Returns the newest `limit` (all by default); with `drain`, returns and
+    /// removes every entry instead. `min_severity` ("info", "warning" or
+    /// "error") changes which future diagnostics are kept.
+    fn get_diagnostics<'p>(
+        &self,
+        py: Python<'p>,
+        limit: Option<usize>,
+        drain: Option<bool>,
+        min_severity: Option<String>,
+    ) -> PyResult<&'p PyList> {
+        diagnostics_to_list(py, self.indexer.diagnostics(), limit, drain, min_severity)
+    }
+    
+    /// Summarize files that lack a summary; returns how many were summarized
+    fn summarize_files(&mut self, py: Python) -> PyResult<usize> {
+        let indexer = &mut self.indexer;
+        let runtime = &self.runtime;
+        py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(indexer.summarize_files())
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Summarizing files failed: {}", e)
+        ))
+    }
+    
+    /// Import symbols from a universal-ctags JSON file (e.g. for languages the
+    /// parser does not support); returns how many blocks were stored
+    fn import_ctags(&mut self, py: Python, ctags_json_path: String) -> PyResult<usize> {
+        let indexer = &mut self.indexer;
+        let runtime = &self.runtime;
+        py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(indexer.import_ctags(Path::new(&ctags_json_path)))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Importing ctags failed: {}", e)
+        ))
+    }
+    
+    /// Indexer statistics: {"embedding_cache": {...}}
+    ///
+    /// The embedding cache dict counts `memory_hits`, `disk_hits` and
+    /// `computed` embeddings, and gives the persistent cache's `entries` and
+    /// `max_entries` (None without one).
+    fn get_stats<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let embedding_gen = self.indexer.embedding_generator();
+        let stats = embedding_gen.map(|g| g.cache_stats()).unwrap_or_default();
+        let persistent = embedding_gen.and_then(|g| g.persistent_cache());
+        
+        let cache = embedding_cache_stats_to_dict(py, &stats)?;
+        cache.set_item("entries", persistent.map(|c| c.len()))?;
+        cache.set_item("max_entries", persistent.map(|c| c.max_entries()))?;
+        let result = PyDict::new(py);
+        result.set_item("embedding_cache", cache)?;
+        Ok(result)
+    }
+    
+    /// A page of indexed files by path: [{file_path, language, block_count, indexed_at, has_summary}]
+    fn list_files(
+        &self,
+        py: Python,
+        path_prefix: Option<String>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> PyResult<Vec<PyObject>> {
+        let storage = self.indexer.storage();
+        let project_id = self.indexer.project_id();
+        let runtime = &self.runtime;
+        let files = py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(storage.list_files(project_id, path_prefix.as_deref(), limit.unwrap_or(100), offset.unwrap_or(0)))
+        })
+        .map_err(PyErr::from)?;
+        
+        files.into_iter().map(|file| {
+            let dict = PyDict::new(py);
+            dict.set_item("file_path", file.file_path)?;
+            dict.set_item("language", file.language)?;
+            dict.set_item("block_count", file.block_count)?;
+            dict.set_item("indexed_at", file.indexed_at)?;
+            dict.set_item("has_summary", file.has_summary)?;
+            Ok(dict.into())
+        }).collect()
+    }
+    
+    /// Blocks of an indexed file by start line
+    ///
+    /// Dicts of id, parent_id, block_type, name, start_line, end_line,
+    /// docstring and signature; empty if the file is not indexed.
+    fn list_blocks(&self, py: Python, file_path: String) -> PyResult<Vec<PyObject>> {
+        let storage = self.indexer.storage();
+        let project_id = self.indexer.project_id();
+        let runtime = &self.runtime;
+        let blocks = py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(storage.list_blocks(project_id, &file_path))
+        })
+        .map_err(PyErr::from)?;
+        
+        blocks.into_iter().map(|block| {
+            let dict = PyDict::new(py);
+            dict.set_item("id", block.id)?;
+            dict.set_item("parent_id", block.parent_id)?;
+            dict.set_item("block_type", block.block_type)?;
+            dict.set_item("name", block.name)?;
+            dict.set_item("start_line", block.start_line)?;
+            dict.set_item("end_line", block.end_line)?;
+            dict.set_item("docstring", block.docstring)?;
+            dict.set_item("signature", block.signature)?;
+            Ok(dict.into())
+        }).collect()
+    }
+    
+    /// Regenerate all embeddings in the project; returns how many blocks were embedded
+    fn reembed_all(&mut self, py: Python, batch_size: Option<usize>) -> PyResult<usize> {
+        let indexer = &mut self.indexer;
+        let runtime = &self.runtime;
+        py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(indexer.reembed_all(batch_size.unwrap_or(64)))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Re-embedding failed: {}", e)
+        ))
+    }
+}
+
+fn embedding_cache_stats_to_dict<'p>(py: Python<'p>, stats: &EmbeddingCacheStats) -> PyResult<&'p PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("memory_hits", stats.memory_hits)?;
+    dict.set_item("disk_hits", stats.disk_hits)?;
+    dict.set_item("computed", stats.computed)?;
+    Ok(dict)
 }
 
 #[pyclass]
@@ -130,10 +394,20 @@ pub struct PySemanticSearch {
     runtime: std::sync::Mutex<tokio::runtime::Runtime>,
 }
 
+impl PySemanticSearch {
+    fn from_storage(rt: tokio::runtime::Runtime, storage: IndexStorage) -> Self {
+        Self {
+            search: SemanticSearch::new(storage),
+            runtime: std::sync::Mutex::new(rt),
+        }
+    }
+}
+
 #[pymethods]
 impl PySemanticSearch {
+    /// With `read_only`, an existing index is opened without write access
     #[new]
-    fn new(db_path: String) -> PyResult<Self> {
+    fn new(db_path: String, read_only: Option<bool>) -> PyResult<Self> {
         Python::with_gil(|py| {
             py.allow_threads(|| {
                 let rt = tokio::runtime::Runtime::new()
@@ -141,61 +415,273 @@ impl PySemanticSearch {
                         format!("Failed to create runtime: {}", e)
                     ))?;
                 
-                let pool = rt.block_on(async {
-                    SqlitePoolOptions::new()
-                        .max_connections(5)
-                        .connect_with(
-                            sqlx::sqlite::SqliteConnectOptions::new()
-                                .filename(&db_path)
-                                .create_if_missing(true),
-                        )
-                        .await
-                })
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                    format!("Failed to create pool: {}", e)
-                ))?;
-                
-                let storage = IndexStorage::new(pool);
-                let search = SemanticSearch::new(storage);
-                
-                Ok(Self { search })
+                let storage = open_storage(&rt, &db_path, read_only)?;
+                Ok(Self::from_storage(rt, storage))
             })
         })
     }
     
-    fn search(&self, py: Python, project_id: String, query: String, limit: usize) -> PyResult<Vec<(String, String, Option<String>, usize, usize, f32)>> {
-        let search = &self.search;
+    #[staticmethod]
+    fn with_database(database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create runtime: {}", e)
+            ))?;
+        
+        Ok(Self::from_storage(rt, IndexStorage::new(database.pool())))
+    }
+    
+    /// Results as (file_path, block_type, name, start_line, end_line, score, match_ranges, matched_terms)
+    ///
+    /// With `explain`, each tuple gains a ninth item: a dict of the score's
+    /// components, including the rendered text under "text".
+    fn search(
+        &mut self,
+        py: Python,
+        project_id: String,
+        query: String,
+        limit: usize,
+        explain: Option<bool>,
+    ) -> PyResult<Vec<PyObject>> {
+        let search = &mut self.search;
+        let runtime = &self.runtime;
+        let explain = explain.unwrap_or(false);
+        
+        let response = py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(search.search_with_explain(&project_id, &query, limit, explain))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Search error: {}", e)
+        ))?;
+        
+        response.results.into_iter().map(|r| {
+            let explanation = match &r.explanation {
+                Some(explanation) => Some(explanation_to_dict(py, explanation, render_explanation(&r))?),
+                None => None,
+            };
+            let mut row = vec![
+                r.file_path.into_py(py),
+                r.block_type.into_py(py),
+                r.name.into_py(py),
+                r.start_line.into_py(py),
+                r.end_line.into_py(py),
+                r.score.into_py(py),
+                r.match_ranges.into_py(py),
+                r.matched_terms.into_py(py),
+            ];
+            if let Some(explanation) = explanation {
+                row.push(explanation.into_py(py));
+            }
+            Ok(PyTuple::new(py, row).into_py(py))
+        }).collect()
+    }
+    
+    /// Results of several queries at once, one list of `search` tuples per query
+    ///
+    /// Embeddings are loaded once for the whole batch. With `dedup`, a block
+    /// returned for one query is left out of later queries' results.
+    fn search_batch(
+        &mut self,
+        py: Python,
+        project_id: String,
+        queries: Vec<String>,
+        limit_per_query: usize,
+        dedup: Option<bool>,
+    ) -> PyResult<Vec<Vec<PyObject>>> {
+        let search = &mut self.search;
+        let runtime = &self.runtime;
+        let dedup = dedup.unwrap_or(false);
+        
+        let batch = py.allow_threads(|| {
+            let queries: Vec<&str> = queries.iter().map(String::as_str).collect();
+            let rt = runtime.lock().unwrap();
+            rt.block_on(search.search_batch_with_dedup(&project_id, &queries, limit_per_query, dedup))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Search error: {}", e)
+        ))?;
+        
+        Ok(batch.into_iter().map(|results| {
+            results.into_iter().map(|r| {
+                (r.file_path, r.block_type, r.name, r.start_line, r.end_line, r.score, r.match_ranges, r.matched_terms)
+                    .into_py(py)
+            }).collect()
+        }).collect())
+    }
+        
+    /// One page of search results as {results, next_cursor, mode, degraded, warning, query}
+    ///
+    /// Pass `next_cursor` back to fetch the following page; it is None once
+    /// a short page has been returned. `query` holds the parsed query's
+    /// terms, phrases and excluded terms.
+    fn search_page<'p>(
+        &mut self,
+        py: Python<'p>,
+        project_id: String,
+        query: String,
+        page_size: usize,
+        cursor: Option<String>,
+    ) -> PyResult<&'p PyDict> {
+        let search = &mut self.search;
+        let runtime = &self.runtime;
+        
+        let response = py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(search.search_page(&project_id, &query, page_size, cursor))
+        })
+        .map_err(PyErr::from)?;
+        
+        let results: Vec<_> = response.results.into_iter().map(|r| {
+            (r.file_path, r.block_type, r.name, r.start_line, r.end_line, r.score, r.match_ranges, r.matched_terms)
+        }).collect();
+        
+        let result = PyDict::new(py);
+        result.set_item("results", results)?;
+        result.set_item("next_cursor", response.next_cursor)?;
+        result.set_item("mode", response.mode.as_str())?;
+        result.set_item("degraded", response.degraded)?;
+        result.set_item("warning", response.warning)?;
+        
+        let query = PyDict::new(py);
+        query.set_item("terms", &response.query.terms)?;
+        query.set_item("phrases", &response.query.phrases)?;
+        query.set_item("excluded", &response.query.excluded)?;
+        result.set_item("query", query)?;
+        Ok(result)
+    }
+    
+    /// Files whose path or summary matches the query, as (file_path, summary, score) tuples
+    fn search_files(
+        &self,
+        py: Python,
+        project_id: String,
+        query: String,
+        limit: usize,
+    ) -> PyResult<Vec<(String, Option<String>, f32)>> {
+        let results = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.search.search_files(&project_id, &query, limit))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("File search error: {}", e)
+        ))?;
+        
+        Ok(results.into_iter().map(|r| (r.file_path, r.summary, r.score)).collect())
+    }
+    
+    /// Load the project's embeddings now; returns how many were cached
+    fn preload(&mut self, py: Python, project_id: String) -> PyResult<usize> {
+        let search = &mut self.search;
+        let runtime = &self.runtime;
         
         py.allow_threads(|| {
+            let rt = runtime.lock().unwrap();
+            rt.block_on(search.preload(&project_id))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Drop the project's cached embeddings; returns whether any were cached
+    fn invalidate(&mut self, project_id: String) -> bool {
+        self.search.invalidate(&project_id)
+    }
+    
+    /// Which search features are usable for the project, as a dict
+    fn capabilities<'p>(&self, py: Python<'p>, project_id: String) -> PyResult<&'p PyDict> {
+        let capabilities = py.allow_threads(|| {
             let rt = self.runtime.lock().unwrap();
-            rt.block_on(async {
-                let results = search.search(&project_id, &query, limit).await
-                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                        format!("Search error: {}", e)
-                    ))?;
-                Ok(results.into_iter().map(|r| {
-                    (r.file_path, r.block_type, r.name, r.start_line, r.end_line, r.score)
-                }).collect())
-            })
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                format!("Search failed: {}", e)
-            ))
+            rt.block_on(self.search.capabilities(&project_id))
         })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Failed to read search capabilities: {}", e)
+        ))?;
+        
+        let result = PyDict::new(py);
+        result.set_item("embeddings_available", capabilities.embeddings_available)?;
+        result.set_item("embedding_model", capabilities.embedding_model)?;
+        result.set_item("embedding_dim", capabilities.embedding_dim)?;
+        result.set_item("blocks_with_embeddings", capabilities.blocks_with_embeddings)?;
+        result.set_item("blocks_total", capabilities.blocks_total)?;
+        result.set_item("fts_enabled", capabilities.fts_enabled)?;
+        result.set_item("stale_embeddings", capabilities.stale_embeddings)?;
+        result.set_item("warning", capabilities.warning)?;
+        Ok(result)
     }
 }
 
+fn explanation_to_dict<'p>(py: Python<'p>, explanation: &Explanation, text: String) -> PyResult<&'p PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("source", explanation.source.as_str())?;
+    result.set_item("match_kind", explanation.match_kind.map(|kind| kind.as_str()))?;
+    result.set_item("exact_name_match", explanation.exact_name_match)?;
+    result.set_item("name_contains", explanation.name_contains)?;
+    result.set_item("content_hit", explanation.content_hit)?;
+    result.set_item("keyword_score", explanation.keyword_score)?;
+    result.set_item("semantic_score", explanation.semantic_score)?;
+    result.set_item("keyword_weight", explanation.keyword_weight)?;
+    result.set_item("semantic_weight", explanation.semantic_weight)?;
+    result.set_item("pre_dedup_rank", explanation.pre_dedup_rank)?;
+    result.set_item("query", &explanation.query)?;
+    result.set_item("text", text)?;
+    Ok(result)
+}
+
 #[pyclass]
 pub struct PyFileWatcher {
     watcher: Arc<Mutex<FileWatcher>>,
     runtime: std::sync::Mutex<tokio::runtime::Runtime>,
     handle: Arc<std::sync::Mutex<Option<JoinHandle<Result<(), String>>>>>,
     shutdown: Arc<AtomicBool>,
+    diagnostics: Diagnostics, // Readable while the watcher is locked by start()
+    control: WatcherControl, // Usable while the watcher is locked by start()
+    events: IndexStorage, // Reads index_events while the watcher is locked by start()
+    project_id: String,
+}
+
+impl PyFileWatcher {
+    fn from_pool(
+        rt: tokio::runtime::Runtime,
+        project_id: String,
+        pool: SqlitePool,
+        auto_pause_events_per_sec: Option<usize>,
+    ) -> PyResult<Self> {
+        let journal = WatcherJournal::new(pool.clone(), project_id.clone());
+        let events = IndexStorage::new(pool.clone());
+        let storage = IndexStorage::new(pool);
+        let indexer = CodebaseIndexer::new(project_id.clone(), storage);
+        let watcher = FileWatcher::new(indexer)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create watcher: {}", e)
+            ))?
+            .with_journal(journal);
+        let watcher = match auto_pause_events_per_sec {
+            Some(events_per_sec) => watcher.with_auto_pause(events_per_sec),
+            None => watcher,
+        };
+        
+        let shutdown = watcher.shutdown_signal();
+        let diagnostics = watcher.diagnostics().clone();
+        let control = watcher.control();
+        
+        Ok(Self {
+            watcher: Arc::new(Mutex::new(watcher)),
+            runtime: std::sync::Mutex::new(rt),
+            handle: Arc::new(std::sync::Mutex::new(None)),
+            shutdown,
+            diagnostics,
+            control,
+            events,
+            project_id,
+        })
+    }
 }
 
 #[pymethods]
 impl PyFileWatcher {
+    /// With `auto_pause_events_per_sec`, the watcher pauses itself during bursts of changes
     #[new]
-    fn new(project_id: String, db_path: String) -> PyResult<Self> {
+    fn new(project_id: String, db_path: String, auto_pause_events_per_sec: Option<usize>) -> PyResult<Self> {
         Python::with_gil(|py| {
             py.allow_threads(|| {
                 let rt = tokio::runtime::Runtime::new()
@@ -203,40 +689,32 @@ impl PyFileWatcher {
                         format!("Failed to create runtime: {}", e)
                     ))?;
                 
-                let pool = rt.block_on(async {
-                    SqlitePoolOptions::new()
-                        .max_connections(5)
-                        .connect_with(
-                            sqlx::sqlite::SqliteConnectOptions::new()
-                                .filename(&db_path)
-                                .create_if_missing(true),
-                        )
-                        .await
-                        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                            format!("Failed to create pool: {}", e)
-                        ))
-                })?;
-                
-                let storage = IndexStorage::new(pool);
-                let indexer = CodebaseIndexer::new(project_id, storage);
-                let watcher = FileWatcher::new(indexer)
-                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                        format!("Failed to create watcher: {}", e)
-                    ))?;
-                
-                let shutdown = watcher.shutdown_signal();
-                
-                Ok(Self {
-                    watcher: Arc::new(Mutex::new(watcher)),
-                    runtime: std::sync::Mutex::new(rt),
-                    handle: Arc::new(std::sync::Mutex::new(None)),
-                    shutdown,
-                })
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Self::from_pool(rt, project_id, pool, auto_pause_events_per_sec)
             })
         })
     }
     
-    fn watch(&self, py: Python, path: String) -> PyResult<()> {
+    #[staticmethod]
+    fn with_database(
+        project_id: String,
+        database: PyRef<PyDatabase>,
+        auto_pause_events_per_sec: Option<usize>,
+    ) -> PyResult<Self> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create runtime: {}", e)
+            ))?;
+        
+        Self::from_pool(rt, project_id, database.pool(), auto_pause_events_per_sec)
+    }
+    
+    /// Watch `path`, indexing its files under `project_id` if given
+    ///
+    /// Each changed file goes to the project of the most specific watched
+    /// root containing it; files outside every project root are indexed
+    /// under the watcher's own project.
+    fn watch(&self, py: Python, path: String, project_id: Option<String>) -> PyResult<()> {
         let watcher = self.watcher.clone();
         let path_buf = PathBuf::from(path);
         
@@ -256,7 +734,14 @@ impl PyFileWatcher {
             let rt = self.runtime.lock().unwrap();
             rt.block_on(async {
                 let mut w = watcher.lock().await;
-                w.watch(path_buf)
+                let watched = match project_id {
+                    Some(project_id) => {
+                        let indexer = w.project_indexer(project_id);
+                        w.watch_project(path_buf, indexer)
+                    }
+                    None => w.watch(path_buf),
+                };
+                watched
                     .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
                         format!("Failed to watch path: {}", e)
                     ))
@@ -264,6 +749,84 @@ impl PyFileWatcher {
         })
     }
     
+    /// Recent indexing and watcher problems, as for `PyCodebaseIndexer.get_diagnostics`
+    fn get_diagnostics<'p>(
+        &self,
+        py: Python<'p>,
+        limit: Option<usize>,
+        drain: Option<bool>,
+        min_severity: Option<String>,
+    ) -> PyResult<&'p PyList> {
+        diagnostics_to_list(py, &self.diagnostics, limit, drain, min_severity)
+    }
+    
+    /// Latest outcomes of watcher-driven indexing, newest first
+    ///
+    /// Each is a dict of path, action, outcome ("indexed", "removed",
+    /// "skipped" or "failed"), error and recorded_at (Unix seconds).
+    /// Failures are always recorded; successes are sampled.
+    fn get_recent_events(&self, py: Python, limit: Option<usize>) -> PyResult<Vec<PyObject>> {
+        let events = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.events.recent_index_events(&self.project_id, limit.unwrap_or(50)))
+        })
+        .map_err(PyErr::from)?;
+        
+        events
+            .iter()
+            .map(|event| {
+                let result = PyDict::new(py);
+                result.set_item("path", &event.path)?;
+                result.set_item("action", event.action.as_str())?;
+                result.set_item("outcome", event.outcome.as_str())?;
+                result.set_item("error", &event.error)?;
+                result.set_item("recorded_at", event.recorded_at)?;
+                Ok(result.into())
+            })
+            .collect()
+    }
+    
+    /// Record changes without indexing them until `resume`, e.g. during a checkout
+    fn pause(&self) {
+        self.control.pause();
+    }
+    
+    /// Index the changes recorded while paused, or discard them with `drop_backlog`
+    ///
+    /// Drop the backlog when a bulk re-index covers the same changes. The
+    /// running watcher applies this on its next poll.
+    fn resume(&self, drop_backlog: Option<bool>) {
+        self.control.resume(drop_backlog.unwrap_or(false));
+    }
+    
+    fn is_paused(&self) -> bool {
+        self.control.is_paused()
+    }
+    
+    /// Number of paths with changes recorded while paused
+    fn backlog_len(&self) -> usize {
+        self.control.backlog_len()
+    }
+    
+    /// Apply changes journaled before a crash; returns the number of paths replayed
+    fn recover_pending(&self, py: Python) -> PyResult<usize> {
+        if self.handle.lock().unwrap().is_some() {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Cannot call recover_pending() after start(). Call it before start()."
+            ));
+        }
+        
+        let watcher = self.watcher.clone();
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async {
+                let mut w = watcher.lock().await;
+                w.recover_pending().await
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+            })
+        })
+    }
+    
     fn start(&self, py: Python, error_callback: Option<PyObject>) -> PyResult<()> {
         // Check if already running
         {
@@ -361,3 +924,32 @@ impl PyFileWatcher {
         Ok(())
     }
 }
+
+fn diagnostics_to_list<'p>(
+    py: Python<'p>,
+    diagnostics: &Diagnostics,
+    limit: Option<usize>,
+    drain: Option<bool>,
+    min_severity: Option<String>,
+) -> PyResult<&'p PyList> {
+    if let Some(min_severity) = min_severity {
+        diagnostics.set_min_severity(Severity::parse(&min_severity).map_err(PyErr::from)?);
+    }
+    let entries = if drain.unwrap_or(false) {
+        diagnostics.drain()
+    } else {
+        diagnostics.recent(limit.unwrap_or(usize::MAX))
+    };
+    
+    let result = PyList::empty(py);
+    for entry in entries {
+        let dict = PyDict::new(py);
+        dict.set_item("severity", entry.severity.as_str())?;
+        dict.set_item("source", entry.source.as_str())?;
+        dict.set_item("path", entry.path)?;
+        dict.set_item("message", entry.message)?;
+        dict.set_item("timestamp", entry.timestamp)?;
+        result.append(dict)?;
+    }
+    Ok(result)
+}
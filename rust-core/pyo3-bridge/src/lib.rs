@@ -4,22 +4,60 @@ mod router_bindings;
 mod context_bindings;
 mod migration_bindings;
 mod indexer_bindings;
+mod config_bindings;
+mod db_bindings;
+mod resilience_bindings;
+mod cost_bindings;
+mod composer_bindings;
+mod security_bindings;
+mod project_bindings;
+mod vector_bindings;
+mod memory_bindings;
 
 use router_bindings::PyRouter;
-use context_bindings::{PyContextManager, PyContextWindowManager, PyContextCompressor};
-use migration_bindings::PyMigrationRunner;
+use context_bindings::{render_codebase_context, render_context_diff, PyContextManager, PyConversationLock, PyContextWindowManager, PyContextCompressor, PyCompressionPlan, PyToolCache};
+use migration_bindings::{PyMigrationRunner, PyMigrationSet};
 use indexer_bindings::{PyCodebaseIndexer, PySemanticSearch, PyFileWatcher};
+use config_bindings::PyOrchestratorConfig;
+use db_bindings::PyDatabase;
+use resilience_bindings::{PyBulkhead, PyPriorityScheduler, PyTimeouts};
+use cost_bindings::{PyCostCalculator, PyCostTracker};
+use composer_bindings::PyResponseStore;
+use security_bindings::{PyAuthz, PyPromptGuard};
+use project_bindings::PyProjectStore;
+use vector_bindings::PyVectorOps;
+use memory_bindings::PyMemoryStore;
 
 #[pymodule]
-fn pyo3_bridge(_py: Python, m: &PyModule) -> PyResult<()> {
+fn pyo3_bridge(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyRouter>()?;
     m.add_class::<PyContextManager>()?;
+    m.add_class::<PyConversationLock>()?;
     m.add_class::<PyContextWindowManager>()?;
     m.add_class::<PyContextCompressor>()?;
+    m.add_class::<PyCompressionPlan>()?;
+    m.add_class::<PyToolCache>()?;
     m.add_class::<PyMigrationRunner>()?;
+    m.add_class::<PyMigrationSet>()?;
     m.add_class::<PyCodebaseIndexer>()?;
     m.add_class::<PySemanticSearch>()?;
     m.add_class::<PyFileWatcher>()?;
+    m.add_class::<PyOrchestratorConfig>()?;
+    m.add_class::<PyDatabase>()?;
+    m.add_class::<PyBulkhead>()?;
+    m.add_class::<PyPriorityScheduler>()?;
+    m.add_class::<PyTimeouts>()?;
+    m.add_class::<PyCostTracker>()?;
+    m.add_class::<PyCostCalculator>()?;
+    m.add_class::<PyResponseStore>()?;
+    m.add_class::<PyPromptGuard>()?;
+    m.add_class::<PyAuthz>()?;
+    m.add_class::<PyProjectStore>()?;
+    m.add_class::<PyVectorOps>()?;
+    m.add_class::<PyMemoryStore>()?;
+    m.add_function(wrap_pyfunction!(render_context_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(render_codebase_context, m)?)?;
+    m.add("ConflictError", py.get_type::<rust_core::error::ConflictError>())?;
     
     // Initialize observability
     rust_core::observability::setup_logging();
@@ -0,0 +1,150 @@
+/// PyO3 bindings for project memories
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rust_core::context::{Memory, MemoryStore};
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use sqlx::sqlite::SqlitePool;
+
+#[pyclass]
+pub struct PyMemoryStore {
+    store: MemoryStore,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+impl PyMemoryStore {
+    fn from_pool(rt: tokio::runtime::Runtime, pool: SqlitePool) -> PyResult<Self> {
+        let store = rt.block_on(MemoryStore::from_pool(pool))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create memory store: {}", e)
+            ))?;
+        
+        Ok(Self {
+            store,
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+}
+
+#[pymethods]
+impl PyMemoryStore {
+    #[new]
+    fn new(db_path: String) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Self::from_pool(rt, pool)
+            })
+        })
+    }
+    
+    #[staticmethod]
+    fn with_database(py: Python, database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let pool = database.pool();
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            Self::from_pool(rt, pool)
+        })
+    }
+    
+    /// Add a memory, or replace the project's memory with the same key
+    ///
+    /// `expires_at` is Unix seconds; raises ValueError if the key or content
+    /// is empty.
+    fn upsert(
+        &self,
+        py: Python,
+        project_id: String,
+        key: String,
+        content: String,
+        importance: Option<i64>,
+        expires_at: Option<i64>,
+    ) -> PyResult<()> {
+        let mut memory = Memory::new(project_id, key, content).with_importance(importance.unwrap_or(0));
+        memory.expires_at = expires_at;
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.upsert(&memory))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// The memory with this key, or None if there is none or it has expired
+    fn get(&self, py: Python, project_id: String, key: String) -> PyResult<Option<PyObject>> {
+        let memory = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.get(&project_id, &key))
+        })
+        .map_err(PyErr::from)?;
+        
+        memory
+            .map(|memory| Ok(memory_to_dict(py, &memory)?.into()))
+            .transpose()
+    }
+    
+    /// Unexpired memories of a project, most important first
+    fn list(&self, py: Python, project_id: String) -> PyResult<Vec<PyObject>> {
+        let memories = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.list(&project_id))
+        })
+        .map_err(PyErr::from)?;
+        
+        memories
+            .iter()
+            .map(|memory| Ok(memory_to_dict(py, memory)?.into()))
+            .collect()
+    }
+    
+    /// Returns False if there was no such memory
+    fn delete(&self, py: Python, project_id: String, key: String) -> PyResult<bool> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.delete(&project_id, &key))
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Memories whose key or content contains every word of `query`, ignoring case
+    fn search(&self, py: Python, project_id: String, query: String, limit: Option<usize>) -> PyResult<Vec<PyObject>> {
+        let memories = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.search(&project_id, &query, limit.unwrap_or(20)))
+        })
+        .map_err(PyErr::from)?;
+        
+        memories
+            .iter()
+            .map(|memory| Ok(memory_to_dict(py, memory)?.into()))
+            .collect()
+    }
+    
+    /// Delete expired memories; returns how many were deleted
+    fn purge_expired(&self, py: Python) -> PyResult<u64> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.purge_expired())
+        })
+        .map_err(PyErr::from)
+    }
+}
+
+fn memory_to_dict<'p>(py: Python<'p>, memory: &Memory) -> PyResult<&'p PyDict> {
+    let result = PyDict::new(py);
+    result.set_item("project_id", &memory.project_id)?;
+    result.set_item("key", &memory.key)?;
+    result.set_item("content", &memory.content)?;
+    result.set_item("importance", memory.importance)?;
+    result.set_item("created_at", memory.created_at)?;
+    result.set_item("expires_at", memory.expires_at)?;
+    Ok(result)
+}
@@ -1,9 +1,12 @@
 /// PyO3 bindings for database migrations
 
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use pyo3_asyncio::tokio::into_future;
-use rust_core::migrations::MigrationRunner;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use rust_core::migrations::{DatabaseMigrationReport, MigrationRunner, MigrationSet};
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
 
 #[pyclass]
 pub struct PyMigrationRunner {
@@ -22,19 +25,7 @@ impl PyMigrationRunner {
                         format!("Failed to create runtime: {}", e)
                     ))?;
                 
-                let pool = rt.block_on(async {
-                    SqlitePoolOptions::new()
-                        .max_connections(5)
-                        .connect_with(
-                            sqlx::sqlite::SqliteConnectOptions::new()
-                                .filename(&db_path)
-                                .create_if_missing(true),
-                        )
-                        .await
-                })
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                    format!("Failed to create pool: {}", e)
-                ))?;
+                let pool = shared_pool_blocking(&rt, &db_path)?;
                 
                 Ok(Self {
                     pool,
@@ -44,6 +35,19 @@ impl PyMigrationRunner {
         })
     }
     
+    #[staticmethod]
+    fn with_database(database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create runtime: {}", e)
+            ))?;
+        
+        Ok(Self {
+            pool: database.pool(),
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+    
     fn migrate_up(&mut self, py: Python, target_version: Option<u32>) -> PyResult<()> {
         let pool = self.pool.clone();
         
@@ -76,6 +80,24 @@ impl PyMigrationRunner {
         })
     }
     
+    /// Readable report of differences between the schema and the applied migrations
+    fn check_drift(&self, py: Python) -> PyResult<String> {
+        let pool = self.pool.clone();
+        
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async {
+                let mut runner = MigrationRunner::new(pool);
+                rust_core::migrations::register_migrations(&mut runner);
+                runner.check_drift().await
+            })
+            .map(|diff| diff.to_string())
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Drift check failed: {}", e)
+            ))
+        })
+    }
+    
     fn status(&self, py: Python) -> PyResult<Vec<(u32, String, bool)>> {
         let pool = self.pool.clone();
         
@@ -92,3 +114,78 @@ impl PyMigrationRunner {
         })
     }
 }
+
+/// Migrations for several database files at once, e.g.
+/// `PyMigrationSet({"contexts": "ctx.db", "costs": "costs.db", "index": "index.db"})`
+///
+/// Every database gets the full migration list. Databases are migrated in
+/// name order.
+#[pyclass]
+pub struct PyMigrationSet {
+    inner: MigrationSet,
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+#[pymethods]
+impl PyMigrationSet {
+    #[new]
+    fn new(py: Python, databases: HashMap<String, String>, all_or_nothing: Option<bool>) -> PyResult<Self> {
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            let mut names: Vec<_> = databases.into_iter().collect();
+            names.sort();
+            let mut inner = MigrationSet::new().with_all_or_nothing(all_or_nothing.unwrap_or(false));
+            for (name, db_path) in names {
+                let mut runner = MigrationRunner::new(shared_pool_blocking(&rt, &db_path)?);
+                rust_core::migrations::register_migrations(&mut runner);
+                inner.add_database(name, runner);
+            }
+            
+            Ok(Self {
+                inner,
+                runtime: std::sync::Mutex::new(rt),
+            })
+        })
+    }
+    
+    /// Migrate every database, optionally to per-name target versions
+    ///
+    /// Returns one report dict per database attempted; failures are reported
+    /// in the dicts rather than raised.
+    fn migrate_all(&self, py: Python, targets: Option<HashMap<String, u32>>) -> PyResult<Vec<PyObject>> {
+        let report = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.migrate_all(targets))
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        
+        report.databases.iter().map(|db| report_to_dict(py, db)).collect()
+    }
+    
+    /// Dict of database name to (version, name, applied) tuples
+    fn status_all(&self, py: Python) -> PyResult<HashMap<String, Vec<(u32, String, bool)>>> {
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.inner.status_all())
+        })
+        .map(|statuses| statuses.into_iter().map(|s| (s.name, s.migrations)).collect())
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            format!("Status check failed: {}", e)
+        ))
+    }
+}
+
+fn report_to_dict(py: Python, db: &DatabaseMigrationReport) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("name", &db.name)?;
+    dict.set_item("from_version", db.from_version)?;
+    dict.set_item("to_version", db.to_version)?;
+    dict.set_item("error", &db.error)?;
+    dict.set_item("rolled_back", db.rolled_back)?;
+    dict.set_item("rollback_error", &db.rollback_error)?;
+    Ok(dict.into())
+}
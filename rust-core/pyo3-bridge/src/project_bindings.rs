@@ -0,0 +1,204 @@
+/// PyO3 bindings for the project registry
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rust_core::context::{ContextManager, ContextStorage};
+use rust_core::cost::CostStorage;
+use rust_core::indexer::storage::IndexStorage;
+use rust_core::projects::{Project, ProjectCascade, ProjectSettings, ProjectStore};
+use crate::db_bindings::{shared_pool_blocking, PyDatabase};
+use sqlx::sqlite::SqlitePool;
+
+#[pyclass]
+pub struct PyProjectStore {
+    store: ProjectStore,
+    pool: SqlitePool, // For the stores a cascading delete reaches
+    runtime: std::sync::Mutex<tokio::runtime::Runtime>,
+}
+
+impl PyProjectStore {
+    fn from_pool(rt: tokio::runtime::Runtime, pool: SqlitePool) -> PyResult<Self> {
+        let store = rt.block_on(ProjectStore::from_pool(pool.clone()))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create project store: {}", e)
+            ))?;
+        
+        Ok(Self {
+            store,
+            pool,
+            runtime: std::sync::Mutex::new(rt),
+        })
+    }
+}
+
+#[pymethods]
+impl PyProjectStore {
+    #[new]
+    fn new(db_path: String) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                let rt = tokio::runtime::Runtime::new()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                        format!("Failed to create runtime: {}", e)
+                    ))?;
+                
+                let pool = shared_pool_blocking(&rt, &db_path)?;
+                Self::from_pool(rt, pool)
+            })
+        })
+    }
+    
+    #[staticmethod]
+    fn with_database(py: Python, database: PyRef<PyDatabase>) -> PyResult<Self> {
+        let pool = database.pool();
+        py.allow_threads(|| {
+            let rt = tokio::runtime::Runtime::new()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                    format!("Failed to create runtime: {}", e)
+                ))?;
+            
+            Self::from_pool(rt, pool)
+        })
+    }
+    
+    /// Register a project; raises ValueError if the id is taken
+    ///
+    /// `settings` is a dict with optional `skip_patterns`, `routing_overrides`
+    /// (rule key such as "code_editing" -> tools) and `budget_usd` keys.
+    fn create(
+        &self,
+        py: Python,
+        id: String,
+        name: String,
+        root_path: Option<String>,
+        settings: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        let mut project = Project::new(id, name);
+        project.root_path = root_path;
+        if let Some(settings) = settings {
+            project.settings = settings_from_dict(py, settings)?;
+        }
+        
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.create(&project))
+        })
+        .map_err(PyErr::from)?;
+        
+        Ok(project_to_dict(py, &project)?.into())
+    }
+    
+    fn get(&self, py: Python, id: String) -> PyResult<Option<PyObject>> {
+        let project = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.get(&id))
+        })
+        .map_err(PyErr::from)?;
+        
+        project
+            .map(|project| Ok(project_to_dict(py, &project)?.into()))
+            .transpose()
+    }
+    
+    /// All projects, by id
+    fn list(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let projects = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(self.store.list())
+        })
+        .map_err(PyErr::from)?;
+        
+        projects
+            .iter()
+            .map(|project| Ok(project_to_dict(py, project)?.into()))
+            .collect()
+    }
+    
+    /// Change the given fields of a project; returns False if it does not exist
+    fn update(
+        &self,
+        py: Python,
+        id: String,
+        name: Option<String>,
+        root_path: Option<String>,
+        settings: Option<&PyDict>,
+    ) -> PyResult<bool> {
+        let settings = settings.map(|settings| settings_from_dict(py, settings)).transpose()?;
+        py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async {
+                let mut project = match self.store.get(&id).await? {
+                    Some(project) => project,
+                    None => return Ok(false),
+                };
+                if let Some(name) = name {
+                    project.name = name;
+                }
+                if root_path.is_some() {
+                    project.root_path = root_path;
+                }
+                if let Some(settings) = settings {
+                    project.settings = settings;
+                }
+                self.store.update(&project).await
+            })
+        })
+        .map_err(PyErr::from)
+    }
+    
+    /// Remove a project; with `cascade`, also its contexts, indexed files and cost records
+    ///
+    /// Returns None if the project does not exist, otherwise a dict of how
+    /// much was removed along with it.
+    fn delete(&self, py: Python, id: String, cascade: Option<bool>) -> PyResult<Option<PyObject>> {
+        let cascade = cascade.unwrap_or(false);
+        let deletion = py.allow_threads(|| {
+            let rt = self.runtime.lock().unwrap();
+            rt.block_on(async {
+                if !cascade {
+                    return self.store.delete(&id, ProjectCascade::default()).await;
+                }
+                let contexts = ContextManager::new(ContextStorage::from_pool(self.pool.clone()).await?);
+                let index = IndexStorage::new(self.pool.clone());
+                let costs = CostStorage::from_pool(self.pool.clone()).await?;
+                let cascade = ProjectCascade {
+                    contexts: Some(&contexts),
+                    index: Some(&index),
+                    costs: Some(&costs),
+                };
+                self.store.delete(&id, cascade).await
+            })
+        })
+        .map_err(PyErr::from)?;
+        
+        deletion
+            .map(|deletion| {
+                let result = PyDict::new(py);
+                result.set_item("contexts_deleted", deletion.contexts_deleted)?;
+                result.set_item("files_removed", deletion.files_removed)?;
+                result.set_item("cost_records_deleted", deletion.cost_records_deleted)?;
+                Ok(result.into())
+            })
+            .transpose()
+    }
+}
+
+/// Settings from a dict, converted with Python's json module
+fn settings_from_dict(py: Python, settings: &PyDict) -> PyResult<ProjectSettings> {
+    let text: String = py.import("json")?.call_method1("dumps", (settings,))?.extract()?;
+    serde_json::from_str(&text)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid settings: {}", e)))
+}
+
+fn project_to_dict<'p>(py: Python<'p>, project: &Project) -> PyResult<&'p PyDict> {
+    let settings = serde_json::to_string(&project.settings)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    
+    let result = PyDict::new(py);
+    result.set_item("id", &project.id)?;
+    result.set_item("name", &project.name)?;
+    result.set_item("root_path", &project.root_path)?;
+    result.set_item("created_at", project.created_at)?;
+    result.set_item("settings", py.import("json")?.call_method1("loads", (settings,))?)?;
+    Ok(result)
+}
@@ -0,0 +1,226 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3_asyncio::tokio::future_into_py;
+use rust_core::resilience::{Bulkhead, BulkheadPermit, Priority, PriorityScheduler, SchedulerConfig, SchedulerPermit, TimeoutConfig};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Concurrency limiter for Python callers
+///
+/// Usable as `async with bulkhead:` or as an `await acquire()` / `release()`
+/// pair. Permits are held by the object, so `release()` frees the oldest one.
+#[pyclass]
+pub struct PyBulkhead {
+    inner: Bulkhead,
+    permits: Arc<Mutex<Vec<BulkheadPermit>>>,
+}
+
+#[pymethods]
+impl PyBulkhead {
+    #[new]
+    fn new(name: String, max_concurrent: usize, max_queue: Option<usize>) -> Self {
+        Self {
+            inner: Bulkhead::new(name, max_concurrent, max_queue.unwrap_or(0)),
+            permits: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    
+    #[getter]
+    fn name(&self) -> String {
+        self.inner.name().to_string()
+    }
+    
+    /// Awaitable that resolves once a slot is held; raises if the queue is full
+    fn acquire<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let bulkhead = self.inner.clone();
+        let permits = self.permits.clone();
+        future_into_py(py, async move {
+            let permit = bulkhead.acquire().await?;
+            permits.lock().unwrap().push(permit);
+            Ok(())
+        })
+    }
+    
+    /// Take a slot without waiting; returns False if none is free
+    fn try_acquire(&self) -> bool {
+        match self.inner.try_acquire() {
+            Ok(permit) => {
+                self.permits.lock().unwrap().push(permit);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+    
+    /// Release a held slot; returns False if none was held
+    fn release(&self) -> bool {
+        let mut permits = self.permits.lock().unwrap();
+        if permits.is_empty() {
+            return false;
+        }
+        permits.remove(0);
+        true
+    }
+    
+    fn stats<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let stats = self.inner.stats();
+        let result = PyDict::new(py);
+        result.set_item("max_concurrent", stats.max_concurrent)?;
+        result.set_item("max_queue", stats.max_queue)?;
+        result.set_item("in_flight", stats.in_flight)?;
+        result.set_item("queued", stats.queued)?;
+        result.set_item("rejected", stats.rejected)?;
+        Ok(result)
+    }
+    
+    fn __aenter__<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.acquire(py)
+    }
+    
+    fn __aexit__<'p>(
+        &self,
+        py: Python<'p>,
+        _exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        self.release();
+        future_into_py(py, async { Ok(false) })
+    }
+}
+
+/// Priority scheduler for Python-driven work
+///
+/// Call `await acquire_slot(priority)` before starting work and
+/// `release_slot(priority)` when it finishes; priority is "interactive",
+/// "normal" or "background".
+#[pyclass]
+pub struct PyPriorityScheduler {
+    inner: PriorityScheduler,
+    permits: Arc<Mutex<Vec<SchedulerPermit>>>,
+}
+
+#[pymethods]
+impl PyPriorityScheduler {
+    #[new]
+    fn new(
+        max_concurrent: usize,
+        interactive_limit: Option<usize>,
+        normal_limit: Option<usize>,
+        background_limit: Option<usize>,
+        background_aging_secs: Option<f64>,
+    ) -> Self {
+        let mut config = SchedulerConfig::new(max_concurrent);
+        for (priority, limit) in [
+            (Priority::Interactive, interactive_limit),
+            (Priority::Normal, normal_limit),
+            (Priority::Background, background_limit),
+        ] {
+            if let Some(limit) = limit {
+                config = config.with_class_limit(priority, limit);
+            }
+        }
+        if let Some(secs) = background_aging_secs {
+            config = config.with_background_aging(Duration::from_secs_f64(secs.max(0.0)));
+        }
+        Self {
+            inner: PriorityScheduler::new(config),
+            permits: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    
+    /// Awaitable that resolves once a slot in the priority class is held
+    fn acquire_slot<'p>(&self, py: Python<'p>, priority: &str) -> PyResult<&'p PyAny> {
+        let priority = Priority::parse(priority)?;
+        let scheduler = self.inner.clone();
+        let permits = self.permits.clone();
+        future_into_py(py, async move {
+            let permit = scheduler.acquire(priority).await;
+            permits.lock().unwrap().push(permit);
+            Ok(())
+        })
+    }
+    
+    /// Take a slot without waiting; returns False if the class would have to queue
+    fn try_acquire_slot(&self, priority: &str) -> PyResult<bool> {
+        let priority = Priority::parse(priority)?;
+        match self.inner.try_acquire(priority) {
+            Some(permit) => {
+                self.permits.lock().unwrap().push(permit);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    
+    /// Release the oldest held slot of the class; returns False if none was held
+    fn release_slot(&self, priority: &str) -> PyResult<bool> {
+        let priority = Priority::parse(priority)?;
+        let mut permits = self.permits.lock().unwrap();
+        match permits.iter().position(|permit| permit.priority() == priority) {
+            Some(pos) => {
+                permits.remove(pos);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    
+    fn stats<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let stats = self.inner.stats();
+        let result = PyDict::new(py);
+        result.set_item("max_concurrent", stats.max_concurrent)?;
+        result.set_item("running", stats.running)?;
+        for class in &stats.classes {
+            let entry = PyDict::new(py);
+            entry.set_item("limit", class.limit)?;
+            entry.set_item("queued", class.queued)?;
+            entry.set_item("running", class.running)?;
+            entry.set_item("dispatched", class.dispatched)?;
+            entry.set_item("aged", class.aged)?;
+            result.set_item(class.priority.as_str(), entry)?;
+        }
+        Ok(result)
+    }
+}
+
+/// Configured per-tool timeouts, so Python enforces the same durations
+#[pyclass]
+pub struct PyTimeouts {
+    inner: TimeoutConfig,
+}
+
+impl PyTimeouts {
+    pub(crate) fn from_config(inner: TimeoutConfig) -> Self {
+        Self { inner }
+    }
+}
+
+#[pymethods]
+impl PyTimeouts {
+    #[new]
+    fn new(default_secs: Option<u64>, tools: Option<HashMap<String, u64>>) -> Self {
+        let defaults = TimeoutConfig::default();
+        Self::from_config(TimeoutConfig {
+            default_secs: default_secs.unwrap_or(defaults.default_secs),
+            tools: tools.unwrap_or(defaults.tools),
+        })
+    }
+    
+    #[getter]
+    fn default_secs(&self) -> u64 {
+        self.inner.default_secs
+    }
+    
+    /// Tool name -> seconds for tools that override the default
+    #[getter]
+    fn tools(&self) -> HashMap<String, u64> {
+        self.inner.tools.clone()
+    }
+    
+    /// Seconds allowed for a call to `tool`, for `asyncio.wait_for`
+    fn for_tool(&self, tool: &str) -> f64 {
+        self.inner.for_tool(tool).as_secs_f64()
+    }
+}
@@ -1,20 +1,147 @@
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
-use rust_core::router::{Router, RoutingRequest, RoutingDecision};
+use rust_core::router::{
+    analyze_request_detailed, DetailedAnalysis, ProjectRouting, ReloadableRouter, Router, RoutingReasoning,
+    RoutingRequest, RoutingDecision, RulesWatcher, SelectionPolicy,
+};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[pyclass]
 pub struct PyRouter {
-    inner: Router,
+    inner: Arc<ReloadableRouter>,
+    watcher: Option<RulesWatcher>,
+}
+
+impl PyRouter {
+    pub(crate) fn from_router(inner: Router) -> Self {
+        Self {
+            inner: Arc::new(ReloadableRouter::from_router(inner)),
+            watcher: None,
+        }
+    }
 }
 
 #[pymethods]
 impl PyRouter {
+    /// `project_overrides` maps a project id to a dict with optional
+    /// `default_tool` and `extra_rules` (rule key such as "code_editing" ->
+    /// tools); overrides never route to `disabled_tools`.
+    /// `selection_seed` makes weighted random selection reproducible.
     #[new]
-    fn new(routing_rules: HashMap<String, Vec<String>>, default_tool: String) -> Self {
-        Self {
-            inner: Router::new(routing_rules, default_tool),
+    fn new(
+        routing_rules: HashMap<String, Vec<String>>,
+        default_tool: String,
+        project_overrides: Option<HashMap<String, &PyDict>>,
+        disabled_tools: Option<Vec<String>>,
+        selection_seed: Option<u64>,
+    ) -> PyResult<Self> {
+        let mut overrides = HashMap::new();
+        for (project_id, routing) in project_overrides.unwrap_or_default() {
+            overrides.insert(project_id, project_routing_from_dict(routing)?);
         }
+        let router = Router::new(routing_rules, default_tool)
+            .with_project_overrides(overrides)
+            .with_disabled_tools(disabled_tools.unwrap_or_default());
+        let router = match selection_seed {
+            Some(seed) => router.with_selection_seed(seed),
+            None => router,
+        };
+        Ok(Self::from_router(router))
+    }
+    
+    /// Layer routing over the global rules for one project; kept across reloads
+    ///
+    /// Raises ValueError if `extra_rules` has a key no task type is routed by.
+    fn set_project_override(
+        &self,
+        project_id: String,
+        default_tool: Option<String>,
+        extra_rules: Option<HashMap<String, Vec<String>>>,
+    ) -> PyResult<()> {
+        let routing = ProjectRouting {
+            default_tool,
+            extra_rules: extra_rules.unwrap_or_default(),
+        };
+        routing
+            .validate()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        self.inner.set_project_override(&project_id, routing);
+        Ok(())
+    }
+    
+    /// How a rule listing several tools picks the first one: "first_match",
+    /// "round_robin" or "weighted_random" (with `weights` per tool); kept across reloads
+    fn set_selection_policy(
+        &self,
+        rule: String,
+        policy: String,
+        weights: Option<HashMap<String, f32>>,
+    ) -> PyResult<()> {
+        let policy = match policy.as_str() {
+            "first_match" => SelectionPolicy::FirstMatch,
+            "round_robin" => SelectionPolicy::RoundRobin,
+            "weighted_random" => SelectionPolicy::WeightedRandom(weights.unwrap_or_default()),
+            other => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unknown selection policy: {}",
+                    other
+                )))
+            }
+        };
+        self.inner.set_selection_policy(&rule, policy);
+        Ok(())
+    }
+    
+    /// Returns whether the project had an override
+    fn remove_project_override(&self, project_id: String) -> bool {
+        self.inner.remove_project_override(&project_id).is_some()
+    }
+    
+    /// Load routing rules from a TOML file; with `watch`, reload whenever it changes
+    #[staticmethod]
+    fn from_file(path: String, watch: Option<bool>) -> PyResult<Self> {
+        let inner = Arc::new(ReloadableRouter::load(path).map_err(PyErr::from)?);
+        let watcher = if watch.unwrap_or(false) {
+            Some(inner.watch().map_err(PyErr::from)?)
+        } else {
+            None
+        };
+        Ok(Self { inner, watcher })
+    }
+    
+    /// Re-read the rules file; invalid rules raise and the old ones stay in use
+    fn reload(&self) -> PyResult<()> {
+        self.inner.reload().map_err(PyErr::from)
+    }
+    
+    /// When the current rules were loaded (Unix seconds)
+    #[getter]
+    fn last_loaded_at(&self) -> i64 {
+        self.inner.last_loaded_at()
+    }
+    
+    /// Why the last reload failed, e.g. one triggered by the file watcher
+    #[getter]
+    fn last_reload_error(&self) -> Option<String> {
+        self.inner.last_error()
+    }
+    
+    #[getter]
+    fn watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+    
+    /// What a message asks for, for choosing a prompt template
+    ///
+    /// Returns a dict with the routing classification (`task_type`,
+    /// `keyword_hits`, `confidence`) and `task_types`, `detected_languages`,
+    /// `mentioned_paths`, `code_fences` (dicts of `language` and
+    /// `content_len`), `question_count`, `imperative_verbs`,
+    /// `estimated_complexity` and `contains_error_trace`.
+    #[staticmethod]
+    fn analyze_detailed(py: Python, message: String) -> PyResult<&PyDict> {
+        analysis_to_dict(py, &analyze_request_detailed(&message))
     }
 
     fn route(&self, py: Python, request: &PyDict) -> PyResult<PyDict> {
@@ -34,12 +161,17 @@ impl PyRouter {
         let explicit_tool: Option<String> = request
             .get_item("explicit_tool")
             .and_then(|v| v.extract().ok());
+        
+        let request_id: Option<String> = request
+            .get_item("request_id")
+            .and_then(|v| v.extract().ok());
 
         let routing_request = RoutingRequest {
             message,
             conversation_id,
             project_id,
             explicit_tool,
+            request_id,
         };
 
         let decision = self.inner.route(&routing_request);
@@ -48,6 +180,68 @@ impl PyRouter {
         let tools_list = PyList::new(py, decision.selected_tools.iter());
         result.set_item("selected_tools", tools_list)?;
         result.set_item("reasoning", decision.reasoning)?;
+        result.set_item("reasoning_details", reasoning_to_dict(py, &decision.reasoning_details)?)?;
+        result.set_item("selected_models", decision.selected_models)?;
+        result.set_item("request_id", decision.request_id)?;
         Ok(result)
     }
 }
+
+fn reasoning_to_dict<'p>(py: Python<'p>, reasoning: &RoutingReasoning) -> PyResult<&'p PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("explicit_tool", reasoning.explicit_tool.clone())?;
+    dict.set_item("matched_rule", reasoning.matched_rule.clone())?;
+    dict.set_item("task_type", reasoning.task_type.as_str())?;
+    dict.set_item("keyword_hits", reasoning.keyword_hits.clone())?;
+    dict.set_item("confidence", reasoning.confidence)?;
+    dict.set_item("fallback_used", reasoning.fallback_used)?;
+    dict.set_item("skipped_tools", reasoning.skipped_tools.clone())?;
+    dict.set_item("project_override", reasoning.project_override.clone())?;
+    dict.set_item("selection_policy", reasoning.selection_policy.clone())?;
+    dict.set_item("error_trace", reasoning.error_trace)?;
+    Ok(dict)
+}
+
+fn analysis_to_dict<'p>(py: Python<'p>, analysis: &DetailedAnalysis) -> PyResult<&'p PyDict> {
+    let fences = PyList::empty(py);
+    for fence in &analysis.code_fences {
+        let dict = PyDict::new(py);
+        dict.set_item("language", fence.language.clone())?;
+        dict.set_item("content_len", fence.content_len)?;
+        fences.append(dict)?;
+    }
+    let task_types: Vec<&str> = analysis.task_types.iter().map(|t| t.as_str()).collect();
+    
+    let dict = PyDict::new(py);
+    dict.set_item("task_type", analysis.classification.task_type.as_str())?;
+    dict.set_item("keyword_hits", analysis.classification.keyword_hits.clone())?;
+    dict.set_item("confidence", analysis.classification.confidence)?;
+    dict.set_item("task_types", task_types)?;
+    dict.set_item("detected_languages", analysis.detected_languages.clone())?;
+    dict.set_item("mentioned_paths", analysis.mentioned_paths.clone())?;
+    dict.set_item("code_fences", fences)?;
+    dict.set_item("question_count", analysis.question_count)?;
+    dict.set_item("imperative_verbs", analysis.imperative_verbs.clone())?;
+    dict.set_item("estimated_complexity", analysis.estimated_complexity.as_str())?;
+    dict.set_item("contains_error_trace", analysis.contains_error_trace)?;
+    Ok(dict)
+}
+
+fn project_routing_from_dict(routing: &PyDict) -> PyResult<ProjectRouting> {
+    let default_tool: Option<String> = match routing.get_item("default_tool")? {
+        Some(value) if !value.is_none() => Some(value.extract()?),
+        _ => None,
+    };
+    let extra_rules: HashMap<String, Vec<String>> = match routing.get_item("extra_rules")? {
+        Some(value) if !value.is_none() => value.extract()?,
+        _ => HashMap::new(),
+    };
+    let routing = ProjectRouting {
+        default_tool,
+        extra_rules,
+    };
+    routing
+        .validate()
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    Ok(routing)
+}
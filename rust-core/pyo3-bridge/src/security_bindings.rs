@@ -0,0 +1,107 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rust_core::security::authz::{Permission, RolePermissions};
+use rust_core::security::prompt_guard::{GuardAction, GuardPolicy, InjectionFinding, PromptGuard};
+use std::collections::HashMap;
+
+/// Prompt injection scanner for Python callers
+///
+/// Actions per severity default to allow for low, flag for medium and
+/// block for high findings.
+#[pyclass]
+pub struct PyPromptGuard {
+    inner: PromptGuard,
+}
+
+#[pymethods]
+impl PyPromptGuard {
+    #[new]
+    fn new(low: Option<String>, medium: Option<String>, high: Option<String>) -> PyResult<Self> {
+        let defaults = GuardPolicy::default();
+        let action = |value: Option<String>, default: GuardAction| match value {
+            Some(value) => GuardAction::parse(&value).map_err(PyErr::from),
+            None => Ok(default),
+        };
+        let policy = GuardPolicy {
+            low: action(low, defaults.low)?,
+            medium: action(medium, defaults.medium)?,
+            high: action(high, defaults.high)?,
+        };
+        Ok(Self {
+            inner: PromptGuard::new(policy),
+        })
+    }
+    
+    /// Findings as dicts with kind, severity, start, end, quoted and text
+    fn scan(&self, py: Python, text: &str) -> PyResult<Vec<PyObject>> {
+        self.inner
+            .check(text)
+            .findings
+            .iter()
+            .map(|finding| finding_to_dict(py, text, finding))
+            .collect()
+    }
+    
+    /// Dict with the chosen action ("allow", "flag" or "block") and findings
+    fn check(&self, py: Python, text: &str) -> PyResult<PyObject> {
+        let verdict = self.inner.check(text);
+        let findings = verdict
+            .findings
+            .iter()
+            .map(|finding| finding_to_dict(py, text, finding))
+            .collect::<PyResult<Vec<_>>>()?;
+        let dict = PyDict::new(py);
+        dict.set_item("action", verdict.action.as_str())?;
+        dict.set_item("findings", findings)?;
+        Ok(dict.into())
+    }
+}
+
+fn finding_to_dict(py: Python, text: &str, finding: &InjectionFinding) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("kind", finding.kind.as_str())?;
+    dict.set_item("severity", finding.severity.as_str())?;
+    dict.set_item("start", finding.start)?;
+    dict.set_item("end", finding.end)?;
+    dict.set_item("quoted", finding.quoted)?;
+    dict.set_item("text", &text[finding.range()])?;
+    Ok(dict.into())
+}
+
+/// Role permission checks for Python callers
+///
+/// `roles` maps role names to permission names and replaces the default
+/// admin / user / viewer mapping when given.
+#[pyclass]
+pub struct PyAuthz {
+    roles: RolePermissions,
+}
+
+#[pymethods]
+impl PyAuthz {
+    #[new]
+    fn new(roles: Option<HashMap<String, Vec<String>>>) -> PyResult<Self> {
+        let roles = match roles {
+            Some(roles) => {
+                let mut mapping = RolePermissions::empty();
+                for (role, permissions) in roles {
+                    let permissions = permissions
+                        .iter()
+                        .map(|p| Permission::parse(p))
+                        .collect::<rust_core::error::Result<Vec<_>>>()
+                        .map_err(PyErr::from)?;
+                    mapping = mapping.with_role(&role, permissions);
+                }
+                mapping
+            }
+            None => RolePermissions::default(),
+        };
+        Ok(Self { roles })
+    }
+    
+    /// True if `role` holds `permission`; raises on an unknown permission name
+    fn check(&self, role: &str, permission: &str) -> PyResult<bool> {
+        let permission = Permission::parse(permission).map_err(PyErr::from)?;
+        Ok(self.roles.allows(role, permission))
+    }
+}
@@ -0,0 +1,23 @@
+/// PyO3 bindings for embedding vector math
+
+use pyo3::prelude::*;
+use rust_core::indexer::vector;
+
+/// Namespace for vector operations; raises ValueError on mismatched dimensions or zero vectors
+#[pyclass]
+pub struct PyVectorOps;
+
+#[pymethods]
+impl PyVectorOps {
+    #[staticmethod]
+    fn cosine_similarity(a: Vec<f32>, b: Vec<f32>) -> PyResult<f32> {
+        vector::cosine_similarity(&a, &b).map_err(PyErr::from)
+    }
+    
+    /// The `k` corpus vectors most similar to `query`, as (index, similarity) pairs, best first
+    #[staticmethod]
+    fn top_k_similar(py: Python, query: Vec<f32>, corpus: Vec<Vec<f32>>, k: usize) -> PyResult<Vec<(usize, f32)>> {
+        py.allow_threads(|| vector::top_k_similar(&query, &corpus, k))
+            .map_err(PyErr::from)
+    }
+}
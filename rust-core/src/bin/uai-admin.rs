@@ -0,0 +1,574 @@
+//! Maintenance commands for orchestrator databases
+//!
+//! Exits 0 on success, 2 when the command cannot run as given (bad
+//! arguments, missing database or path, invalid input) and 1 when an
+//! operation fails.
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use clap::{Args, Parser, Subcommand};
+use rust_core::cost::CostStorage;
+use rust_core::indexer::codebase::CodebaseIndexer;
+use rust_core::indexer::search::SemanticSearch;
+use rust_core::indexer::storage::IndexStorage;
+use rust_core::migrations::{register_migrations, MigrationError, MigrationRunner};
+use rust_core::storage::{backup_to, maintain, MaintenanceOptions, VacuumMode};
+use rust_core::OrchestratorError;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const EXIT_FAILURE: u8 = 1;
+const EXIT_USAGE: u8 = 2; // Same code clap uses for argument errors
+
+#[derive(Parser)]
+#[command(name = "uai-admin", about = "Maintenance commands for orchestrator databases")]
+struct Cli {
+    /// Print results (and errors) as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply, roll back or inspect schema migrations
+    Migrate {
+        #[arg(long)]
+        db: PathBuf,
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Build, clear, validate or describe a project's code index
+    Index {
+        #[command(flatten)]
+        target: IndexTarget,
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+    /// Search a project's code index
+    Search {
+        #[command(flatten)]
+        target: IndexTarget,
+        query: String,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Report costs recorded over a period
+    Cost(CostArgs),
+    /// Back up or maintain a database
+    Db {
+        #[arg(long)]
+        db: PathBuf,
+        #[command(subcommand)]
+        action: DbAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// Apply pending migrations, up to `--to` if given
+    Up {
+        #[arg(long)]
+        to: Option<u32>,
+    },
+    /// Roll back migrations above `--to`
+    Down {
+        #[arg(long)]
+        to: u32,
+    },
+    /// List every migration and whether it is applied
+    Status,
+    /// List the migrations `up` would apply, without applying them
+    Plan {
+        #[arg(long)]
+        to: Option<u32>,
+    },
+}
+
+#[derive(Args)]
+struct IndexTarget {
+    #[arg(long)]
+    db: PathBuf,
+    #[arg(long)]
+    project: String,
+}
+
+#[derive(Subcommand)]
+enum IndexAction {
+    /// Index every supported file under a path
+    Build { path: PathBuf },
+    /// Remove every file of the project from the index
+    Clear,
+    /// Check the index against the files under a path
+    Validate { path: PathBuf },
+    /// Count indexed files, blocks and embeddings
+    Stats,
+}
+
+#[derive(Args)]
+struct CostArgs {
+    #[arg(long)]
+    db: PathBuf,
+    /// Start of the period (RFC 3339 or YYYY-MM-DD); defaults to `--days` before the end
+    #[arg(long)]
+    since: Option<String>,
+    /// End of the period (RFC 3339 or YYYY-MM-DD); defaults to now
+    #[arg(long)]
+    until: Option<String>,
+    #[arg(long, default_value_t = 30)]
+    days: i64,
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    /// Also break the total down by the values of this label
+    #[arg(long)]
+    by_label: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Write a consistent copy of the database
+    Backup {
+        dest: PathBuf,
+        #[arg(long)]
+        compress: bool,
+    },
+    /// Vacuum, analyze, integrity-check and checkpoint the database
+    Maintain {
+        /// "none", "incremental" or "full"
+        #[arg(long, default_value = "incremental")]
+        vacuum: String,
+        #[arg(long)]
+        skip_analyze: bool,
+        #[arg(long)]
+        skip_integrity_check: bool,
+        #[arg(long)]
+        skip_checkpoint: bool,
+    },
+}
+
+/// Why a command failed, deciding the exit code
+#[derive(Debug)]
+enum AdminError {
+    /// The command cannot run as given
+    Usage(String),
+    /// The command was valid but an operation failed
+    Failure(String),
+}
+
+impl AdminError {
+    fn message(&self) -> &str {
+        match self {
+            AdminError::Usage(message) | AdminError::Failure(message) => message,
+        }
+    }
+
+    fn exit_code(&self) -> u8 {
+        match self {
+            AdminError::Usage(_) => EXIT_USAGE,
+            AdminError::Failure(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+impl From<OrchestratorError> for AdminError {
+    fn from(error: OrchestratorError) -> Self {
+        match error {
+            OrchestratorError::InvalidInput(_)
+            | OrchestratorError::InvalidConfig(_)
+            | OrchestratorError::ConflictDetected(_) => AdminError::Usage(error.to_string()),
+            other => AdminError::Failure(other.to_string()),
+        }
+    }
+}
+
+impl From<MigrationError> for AdminError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::NotFound { .. } | MigrationError::AlreadyApplied { .. } => {
+                AdminError::Usage(error.to_string())
+            }
+            other => AdminError::Failure(other.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AdminError {
+    fn from(error: sqlx::Error) -> Self {
+        AdminError::Failure(format!("Database error: {}", error))
+    }
+}
+
+/// A command's result, as JSON and as text
+struct Output {
+    json: Value,
+    text: String,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command).await {
+        Ok(output) => {
+            if cli.json {
+                println!("{}", output.json);
+            } else {
+                println!("{}", output.text);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            if cli.json {
+                let kind = if error.exit_code() == EXIT_USAGE { "usage" } else { "failure" };
+                eprintln!("{}", json!({ "error": error.message(), "kind": kind }));
+            } else {
+                eprintln!("error: {}", error.message());
+            }
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+async fn run(command: Command) -> Result<Output, AdminError> {
+    match command {
+        Command::Migrate { db, action } => migrate(&db, action).await,
+        Command::Index { target, action } => index(target, action).await,
+        Command::Search { target, query, limit } => search(target, &query, limit).await,
+        Command::Cost(args) => cost(args).await,
+        Command::Db { db, action } => database(&db, action).await,
+    }
+}
+
+/// Pool for `path`; only `create` may make a new database file
+async fn open_pool(path: &Path, create: bool) -> Result<SqlitePool, AdminError> {
+    if !create && !path.is_file() {
+        return Err(AdminError::Usage(format!("Database not found: {}", path.display())));
+    }
+    Ok(SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(create))
+        .await?)
+}
+
+fn migration_runner(pool: SqlitePool) -> MigrationRunner {
+    let mut runner = MigrationRunner::new(pool);
+    register_migrations(&mut runner);
+    runner
+}
+
+async fn migrate(db: &Path, action: MigrateAction) -> Result<Output, AdminError> {
+    let runner = migration_runner(open_pool(db, matches!(action, MigrateAction::Up { .. })).await?);
+    let before = runner.get_current_version().await?;
+
+    match action {
+        MigrateAction::Up { to } => runner.migrate_up(to).await?,
+        MigrateAction::Down { to } => runner.migrate_down(to).await?,
+        MigrateAction::Status => {
+            let status = runner.status().await?;
+            let text = status
+                .iter()
+                .map(|(version, name, applied)| {
+                    format!("{:>4} {} {}", version, if *applied { "applied" } else { "pending" }, name)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let migrations: Vec<Value> = status
+                .iter()
+                .map(|(version, name, applied)| json!({ "version": version, "name": name, "applied": applied }))
+                .collect();
+            return Ok(Output {
+                json: json!({ "current_version": before, "migrations": migrations }),
+                text: format!("Current version: {}\n{}", version_text(before), text),
+            });
+        }
+        MigrateAction::Plan { to } => {
+            let planned = pending(&runner, to).await?;
+            let text = if planned.is_empty() {
+                "Nothing to apply".to_string()
+            } else {
+                planned
+                    .iter()
+                    .map(|(version, name)| format!("{:>4} {}", version, name))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let migrations: Vec<Value> =
+                planned.iter().map(|(version, name)| json!({ "version": version, "name": name })).collect();
+            return Ok(Output {
+                json: json!({ "current_version": before, "pending": migrations }),
+                text,
+            });
+        }
+    }
+
+    let after = runner.get_current_version().await?;
+    Ok(Output {
+        json: json!({ "previous_version": before, "current_version": after }),
+        text: format!("Migrated from {} to {}", version_text(before), version_text(after)),
+    })
+}
+
+/// Registered migrations not yet applied, up to `to`
+async fn pending(runner: &MigrationRunner, to: Option<u32>) -> Result<Vec<(u32, String)>, AdminError> {
+    Ok(runner
+        .status()
+        .await?
+        .into_iter()
+        .filter(|(version, _, applied)| !applied && to.is_none_or(|to| *version <= to))
+        .map(|(version, name, _)| (version, name))
+        .collect())
+}
+
+fn version_text(version: Option<u32>) -> String {
+    version.map_or_else(|| "none".to_string(), |version| version.to_string())
+}
+
+async fn index(target: IndexTarget, action: IndexAction) -> Result<Output, AdminError> {
+    let building = matches!(action, IndexAction::Build { .. });
+    let pool = open_pool(&target.db, building).await?;
+    if building {
+        migration_runner(pool.clone()).migrate_up(None).await?;
+    }
+    let project = target.project;
+
+    match action {
+        IndexAction::Build { path } => {
+            if !path.exists() {
+                return Err(AdminError::Usage(format!("Path not found: {}", path.display())));
+            }
+            let mut indexer = CodebaseIndexer::new(project.clone(), IndexStorage::new(pool));
+            let indexed = indexer.index_directory(&path).await.map_err(AdminError::Failure)?;
+            let diagnostics: Vec<String> = indexer
+                .diagnostics()
+                .drain()
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect();
+            Ok(Output {
+                json: json!({ "project": project, "indexed_files": indexed, "diagnostics": diagnostics }),
+                text: format!("Indexed {} files into project {}", indexed, project),
+            })
+        }
+        IndexAction::Clear => {
+            let mut indexer = CodebaseIndexer::new(project.clone(), IndexStorage::new(pool));
+            let removed = indexer.clear_index().await.map_err(AdminError::Failure)?;
+            Ok(Output {
+                json: json!({ "project": project, "removed_files": removed }),
+                text: format!("Removed {} files from project {}", removed, project),
+            })
+        }
+        IndexAction::Validate { path } => {
+            let indexer = CodebaseIndexer::new(project.clone(), IndexStorage::new(pool)).with_root(path);
+            let result = indexer.validate_index().await.map_err(AdminError::Failure)?;
+            let healthy = result.is_healthy();
+            Ok(Output {
+                json: json!({
+                    "project": project,
+                    "healthy": healthy,
+                    "total_files": result.total_files,
+                    "total_blocks": result.total_blocks,
+                    "orphaned_blocks": result.orphaned_blocks,
+                    "missing_files": result.missing_files,
+                    "out_of_date_files": result.out_of_date_files,
+                    "mismatched_embeddings": result.mismatched_embeddings,
+                    "errors": result.errors,
+                }),
+                text: format!(
+                    "{}: {} files, {} blocks; {} missing, {} out of date, {} orphaned blocks",
+                    if healthy { "Healthy" } else { "Needs repair" },
+                    result.total_files,
+                    result.total_blocks,
+                    result.missing_files.len(),
+                    result.out_of_date_files.len(),
+                    result.orphaned_blocks.len()
+                ),
+            })
+        }
+        IndexAction::Stats => {
+            let storage = IndexStorage::new(pool);
+            let files = storage.list_files_with_hash(&project).await?.len();
+            let (embedded, blocks) = storage.embedding_coverage(&project).await?;
+            let meta = storage.embedding_meta(&project).await?;
+            Ok(Output {
+                json: json!({
+                    "project": project,
+                    "files": files,
+                    "blocks": blocks,
+                    "embedded_blocks": embedded,
+                    "embedding_model": meta.as_ref().and_then(|meta| meta.model.clone()),
+                    "embedding_dim": meta.as_ref().map(|meta| meta.dim),
+                }),
+                text: format!("{} files, {} blocks, {} with embeddings", files, blocks, embedded),
+            })
+        }
+    }
+}
+
+async fn search(target: IndexTarget, query: &str, limit: usize) -> Result<Output, AdminError> {
+    if !target.db.is_file() {
+        return Err(AdminError::Usage(format!("Database not found: {}", target.db.display())));
+    }
+    let storage = IndexStorage::open_read_only(&target.db).await?;
+    let response = SemanticSearch::new(storage).search(&target.project, query, limit).await?;
+
+    let results: Vec<Value> = response
+        .results
+        .iter()
+        .map(|result| {
+            json!({
+                "file_path": result.file_path,
+                "block_type": result.block_type,
+                "name": result.name,
+                "start_line": result.start_line,
+                "end_line": result.end_line,
+                "score": result.score,
+                "parent_name": result.parent_name,
+            })
+        })
+        .collect();
+    let text = if response.results.is_empty() {
+        "No results".to_string()
+    } else {
+        response
+            .results
+            .iter()
+            .map(|result| {
+                format!(
+                    "{:.3} {}:{}-{} {}",
+                    result.score,
+                    result.file_path,
+                    result.start_line,
+                    result.end_line,
+                    result.name.as_deref().unwrap_or(&result.block_type)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    Ok(Output {
+        json: json!({
+            "query": query,
+            "mode": response.mode.as_str(),
+            "degraded": response.degraded,
+            "warning": response.warning,
+            "results": results,
+        }),
+        text,
+    })
+}
+
+async fn cost(args: CostArgs) -> Result<Output, AdminError> {
+    let end = match &args.until {
+        Some(until) => parse_time(until)?,
+        None => Utc::now(),
+    };
+    let start = match &args.since {
+        Some(since) => parse_time(since)?,
+        None => end - Duration::days(args.days),
+    };
+    if start > end {
+        return Err(AdminError::Usage("The period starts after it ends".to_string()));
+    }
+
+    let storage = CostStorage::from_pool(open_pool(&args.db, false).await?).await?;
+    let total = storage
+        .get_total_cost(start, end, args.user.as_deref(), args.project.as_deref(), None)
+        .await?;
+    let by_label = match &args.by_label {
+        Some(key) => Some(storage.get_cost_by_label(start, end, key).await?),
+        None => None,
+    };
+
+    let mut text = format!("Total from {} to {}: ${:.4}", start.to_rfc3339(), end.to_rfc3339(), total);
+    if let Some(by_label) = &by_label {
+        let mut values: Vec<_> = by_label.iter().collect();
+        values.sort_by(|a, b| b.1.total_cmp(a.1));
+        for (value, cost) in values {
+            text.push_str(&format!("\n  {}: ${:.4}", value, cost));
+        }
+    }
+    Ok(Output {
+        json: json!({
+            "start": start.to_rfc3339(),
+            "end": end.to_rfc3339(),
+            "project": args.project,
+            "user": args.user,
+            "total_usd": total,
+            "by_label": by_label,
+        }),
+        text,
+    })
+}
+
+/// RFC 3339 timestamp, or a date meaning its midnight UTC
+fn parse_time(value: &str) -> Result<DateTime<Utc>, AdminError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| Utc.from_utc_datetime(&time))
+        .ok_or_else(|| AdminError::Usage(format!("Invalid time: {} (expected RFC 3339 or YYYY-MM-DD)", value)))
+}
+
+async fn database(db: &Path, action: DbAction) -> Result<Output, AdminError> {
+    let pool = open_pool(db, false).await?;
+    match action {
+        DbAction::Backup { dest, compress } => {
+            let report = backup_to(&pool, &dest, compress).await?;
+            Ok(Output {
+                json: json!({
+                    "path": report.path.to_string_lossy(),
+                    "bytes": report.bytes,
+                    "compressed": report.compressed,
+                    "duration_ms": report.duration.as_millis() as u64,
+                }),
+                text: format!("Wrote {} bytes to {}", report.bytes, report.path.display()),
+            })
+        }
+        DbAction::Maintain {
+            vacuum,
+            skip_analyze,
+            skip_integrity_check,
+            skip_checkpoint,
+        } => {
+            let options = MaintenanceOptions::default()
+                .with_vacuum(VacuumMode::parse(&vacuum)?)
+                .with_analyze(!skip_analyze)
+                .with_integrity_check(!skip_integrity_check)
+                .with_checkpoint(!skip_checkpoint);
+            let report = maintain(&pool, &options).await?;
+            if !report.integrity_ok() {
+                return Err(AdminError::Failure(format!(
+                    "Integrity check failed: {}",
+                    report.integrity.unwrap_or_default().join("; ")
+                )));
+            }
+            Ok(Output {
+                json: json!({
+                    "size_before": report.size_before,
+                    "size_after": report.size_after,
+                    "bytes_reclaimed": report.bytes_reclaimed(),
+                    "freelist_before": report.freelist_before,
+                    "freelist_after": report.freelist_after,
+                    "vacuum": report.vacuum.as_str(),
+                    "analyzed": report.analyzed,
+                    "integrity": report.integrity,
+                    "wal_frames_checkpointed": report.wal_frames_checkpointed,
+                    "duration_ms": report.duration.as_millis() as u64,
+                }),
+                text: format!(
+                    "Reclaimed {} bytes ({} -> {}), vacuum {}",
+                    report.bytes_reclaimed(),
+                    report.size_before,
+                    report.size_after,
+                    report.vacuum.as_str()
+                ),
+            })
+        }
+    }
+}
@@ -0,0 +1,184 @@
+/// Trimming composed responses to a token budget
+
+use super::sanitize::FenceTracker;
+use super::ToolResponse;
+use crate::context::token_counter::TokenCounter;
+use crate::security::limits::TRUNCATION_MARKER;
+use serde::{Deserialize, Serialize};
+
+/// Options for `Composer::with_options`
+#[derive(Debug, Clone, Default)]
+pub struct ComposeOptions {
+    pub max_tokens: Option<usize>, // Budget for the merged responses; None is unlimited
+    pub token_counter: TokenCounter,
+}
+
+impl ComposeOptions {
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+}
+
+/// What trimming did to one response; listed in `metadata["trimming"]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrimReport {
+    pub tool: String,
+    pub budget_tokens: usize, // The response's share of the budget
+    pub kept_tokens: usize,
+    pub dropped_tokens: usize,
+}
+
+/// Paragraph or whole code fence of a response
+struct Chunk<'a> {
+    lines: Vec<&'a str>,
+    fence: bool,
+    gap: bool, // Blank lines separate it from the previous chunk
+}
+
+impl Chunk<'_> {
+    fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// What replaces the chunk, and anything after it, when it does not fit
+    fn marker(&self) -> String {
+        if self.fence {
+            // Count only the code, not the fence lines
+            format!("[code omitted, {} lines]", self.lines.len().saturating_sub(2))
+        } else {
+            TRUNCATION_MARKER.to_string()
+        }
+    }
+}
+
+fn chunks(content: &str) -> Vec<Chunk<'_>> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut current: Option<Chunk> = None;
+    let mut gap = false;
+    let mut fences = FenceTracker::default();
+    for line in content.lines() {
+        let was_open = fences.is_open();
+        let fenced = fences.step(line);
+        if was_open {
+            let chunk = current.get_or_insert_with(|| Chunk { lines: Vec::new(), fence: true, gap: false });
+            chunk.lines.push(line);
+            if !fences.is_open() {
+                chunks.extend(current.take());
+            }
+        } else if fenced {
+            chunks.extend(current.take());
+            current = Some(Chunk { lines: vec![line], fence: true, gap });
+            gap = false;
+        } else if line.trim().is_empty() {
+            chunks.extend(current.take());
+            gap = true;
+        } else {
+            let chunk = current.get_or_insert_with(|| Chunk { lines: Vec::new(), fence: false, gap });
+            chunk.lines.push(line);
+            gap = false;
+        }
+    }
+    chunks.extend(current);
+    chunks
+}
+
+fn append(text: &mut String, addition: &str, gap: bool) {
+    if !text.is_empty() {
+        text.push_str(if gap { "\n\n" } else { "\n" });
+    }
+    text.push_str(addition);
+}
+
+/// Leading lines of a paragraph, or leading words if not even its first line fits
+fn cut_prose(text: &str, budget: usize, counter: &TokenCounter) -> String {
+    let mut kept = String::new();
+    for line in text.lines() {
+        let mut candidate = kept.clone();
+        append(&mut candidate, line, false);
+        if counter.estimate_tokens(&candidate) > budget {
+            break;
+        }
+        kept = candidate;
+    }
+    if !kept.is_empty() {
+        return kept;
+    }
+    for word in text.split_whitespace() {
+        let candidate = if kept.is_empty() { word.to_string() } else { format!("{} {}", kept, word) };
+        if counter.estimate_tokens(&candidate) > budget {
+            break;
+        }
+        kept = candidate;
+    }
+    kept
+}
+
+/// `content` cut down to about `budget` tokens, keeping its leading content
+///
+/// Whole paragraphs are kept while they fit, and the first paragraph is
+/// cut at a line or word if it alone is too long. Code fences are never
+/// split: one that does not fit is replaced by "[code omitted, N lines]".
+pub fn trim_content(content: &str, budget: usize, counter: &TokenCounter) -> String {
+    if counter.estimate_tokens(content) <= budget {
+        return content.to_string();
+    }
+    let chunks = chunks(content);
+    // Leave room for whichever marker ends the trimmed content
+    let reserve = chunks
+        .iter()
+        .map(|chunk| counter.estimate_tokens(&format!("\n\n{}", chunk.marker())) + 1)
+        .max()
+        .unwrap_or(0);
+    let room = budget.saturating_sub(reserve);
+
+    let mut kept = String::new();
+    for chunk in &chunks {
+        let text = chunk.text();
+        let mut candidate = kept.clone();
+        append(&mut candidate, &text, chunk.gap);
+        if counter.estimate_tokens(&candidate) <= room {
+            kept = candidate;
+            continue;
+        }
+
+        if !chunk.fence && kept.is_empty() {
+            kept = cut_prose(&text, room, counter);
+        }
+        append(&mut kept, &chunk.marker(), true);
+        break;
+    }
+    kept
+}
+
+/// Trim responses so their content totals at most `budget` tokens
+///
+/// Each response gets a share of the budget in proportion to its own size,
+/// so a long response loses more than a short one but none is dropped
+/// entirely. Nothing is trimmed if everything fits.
+pub fn trim_proportionally(responses: &mut [ToolResponse], budget: usize, counter: &TokenCounter) -> Vec<TrimReport> {
+    let sizes: Vec<usize> = responses.iter().map(|r| counter.estimate_tokens(&r.content)).collect();
+    let total: usize = sizes.iter().sum();
+
+    responses
+        .iter_mut()
+        .zip(sizes)
+        .map(|(response, size)| {
+            let share = if total <= budget {
+                size
+            } else {
+                (budget as u128 * size as u128 / total as u128) as usize
+            };
+            if size > share {
+                response.content = trim_content(&response.content, share, counter);
+            }
+            let kept = counter.estimate_tokens(&response.content).min(size);
+            TrimReport {
+                tool: response.tool.clone(),
+                budget_tokens: share,
+                kept_tokens: kept,
+                dropped_tokens: size - kept,
+            }
+        })
+        .collect()
+}
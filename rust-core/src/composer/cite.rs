@@ -0,0 +1,83 @@
+/// Inline source citations for composed responses
+
+use super::sanitize::FenceTracker;
+
+/// Marker citing source `number` (1-based, in `ComposedResponse::sources` order)
+pub fn citation_marker(number: usize) -> String {
+    format!("[{}]", number)
+}
+
+/// `content` with `marker` after its last line of prose
+///
+/// Markers are never placed inside a code fence: a section ending in a code
+/// block gets the marker on its own line after the closing fence, and one
+/// ending in a fence that is never closed gets it before that fence opens.
+pub fn cite_section(content: &str, marker: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut fences = FenceTracker::default();
+    let mut last_prose = None; // Last non-blank line outside a fence
+    let mut prose_before_open = None; // `last_prose` when the open fence started
+    let mut open_at = None; // Line the still-open fence started on
+    for (index, line) in lines.iter().enumerate() {
+        let was_open = fences.is_open();
+        let fenced = fences.step(line);
+        if !was_open && fences.is_open() {
+            open_at = Some(index);
+            prose_before_open = last_prose;
+        }
+        if !fenced && !line.trim().is_empty() {
+            last_prose = Some(index);
+        }
+    }
+    
+    let (prose, insert_at) = if fences.is_open() {
+        (prose_before_open, open_at.unwrap_or(0))
+    } else {
+        let last_line = lines.iter().rposition(|line| !line.trim().is_empty());
+        (last_prose.filter(|prose| Some(*prose) == last_line), lines.len())
+    };
+    match prose {
+        Some(index) => {
+            let line = &mut lines[index];
+            line.truncate(line.trim_end().len());
+            line.push(' ');
+            line.push_str(marker);
+        }
+        None => lines.insert(insert_at.min(lines.len()), marker.to_string()),
+    }
+    
+    let mut cited = lines.join("\n");
+    if content.ends_with('\n') {
+        cited.push('\n');
+    }
+    cited
+}
+
+/// URLs listed in a tool's `metadata.citations`
+///
+/// Citations may be URL strings or objects with a `url` field; anything else
+/// is ignored.
+pub fn citation_urls(metadata: Option<&serde_json::Value>) -> Vec<String> {
+    let citations = match metadata.and_then(|metadata| metadata.get("citations")).and_then(|c| c.as_array()) {
+        Some(citations) => citations,
+        None => return Vec::new(),
+    };
+    citations
+        .iter()
+        .filter_map(|citation| citation.as_str().or_else(|| citation.get("url").and_then(|url| url.as_str())))
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Trailing block listing each source with its marker and URLs
+pub fn sources_block(sources: &[(String, Vec<String>)]) -> String {
+    let mut block = String::from("Sources:");
+    for (index, (tool, urls)) in sources.iter().enumerate() {
+        block.push_str(&format!("\n{} {}", citation_marker(index + 1), tool));
+        if !urls.is_empty() {
+            block.push_str(&format!(" ({})", urls.join(", ")));
+        }
+    }
+    block
+}
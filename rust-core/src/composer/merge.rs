@@ -1,3 +1,4 @@
+use super::cite::{cite_section, citation_marker, citation_urls, sources_block};
 use super::{ComposedResponse, ToolResponse};
 
 pub fn merge_responses(responses: Vec<ToolResponse>) -> ComposedResponse {
@@ -5,7 +6,9 @@ pub fn merge_responses(responses: Vec<ToolResponse>) -> ComposedResponse {
         return ComposedResponse {
             content: String::new(),
             sources: Vec::new(),
+            strategy: "empty".to_string(),
             metadata: None,
+            partial: false,
         };
     }
 
@@ -14,7 +17,9 @@ pub fn merge_responses(responses: Vec<ToolResponse>) -> ComposedResponse {
         return ComposedResponse {
             content: resp.content.clone(),
             sources: vec![resp.tool.clone()],
+            strategy: "single".to_string(),
             metadata: resp.metadata.clone(),
+            partial: false,
         };
     }
 
@@ -33,6 +38,52 @@ pub fn merge_responses(responses: Vec<ToolResponse>) -> ComposedResponse {
     ComposedResponse {
         content: content_parts.join("\n"),
         sources,
+        strategy: "concatenate".to_string(),
         metadata: None,
+        partial: false,
     }
 }
+
+/// `merge_responses` with a citation marker after each response's section
+///
+/// `sources` lists each tool once, in order of first appearance, and a
+/// tool's marker is its position there, so a tool contributing several
+/// sections is cited with the same number each time. The content ends with
+/// a "Sources" block naming each tool with the URLs of its
+/// `metadata.citations`.
+pub fn merge_responses_cited(responses: Vec<ToolResponse>) -> ComposedResponse {
+    if responses.is_empty() {
+        return merge_responses(responses);
+    }
+
+    let mut sources: Vec<(String, Vec<String>)> = Vec::new();
+    let mut cited = Vec::with_capacity(responses.len());
+    for resp in responses {
+        let number = match sources.iter().position(|(tool, _)| *tool == resp.tool) {
+            Some(index) => index + 1,
+            None => {
+                sources.push((resp.tool.clone(), Vec::new()));
+                sources.len()
+            }
+        };
+        for url in citation_urls(resp.metadata.as_ref()) {
+            let urls = &mut sources[number - 1].1;
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        cited.push(ToolResponse {
+            content: cite_section(&resp.content, &citation_marker(number)),
+            ..resp
+        });
+    }
+
+    let mut composed = merge_responses(cited);
+    if !composed.content.ends_with('\n') {
+        composed.content.push('\n');
+    }
+    composed.content.push('\n');
+    composed.content.push_str(&sources_block(&sources));
+    composed.sources = sources.into_iter().map(|(tool, _)| tool).collect();
+    composed
+}
@@ -1,5 +1,14 @@
+pub mod budget;
+pub mod cite;
 pub mod merge;
+pub mod sanitize;
+pub mod storage;
 
+pub use budget::{ComposeOptions, TrimReport};
+pub use sanitize::{sanitize_response, SanitizationReport, SanitizePolicy};
+pub use storage::{ResponseStore, StoredResponse};
+
+use crate::error::{OrchestratorError, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,13 +22,207 @@ pub struct ToolResponse {
 pub struct ComposedResponse {
     pub content: String,
     pub sources: Vec<String>,
-    pub metadata: Option<serde_json::Value>,
+    /// How the responses were combined: "empty", "single" or "concatenate"
+    #[serde(default)]
+    pub strategy: String,
+    pub metadata: Option<serde_json::Value>, // Includes "sanitization": one report per response
+    /// Some tools failed and are missing from the content; see `Composer::compose_outcomes`
+    #[serde(default)]
+    pub partial: bool,
 }
 
-pub struct Composer;
+/// What came back from calling one tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ToolOutcome {
+    Success(ToolResponse),
+    Failure {
+        tool: String,
+        error: String,
+        retryable: bool,
+    },
+}
+
+/// How much the composed content says about failed tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailureNote {
+    /// Nothing; failures are only listed in the metadata
+    Off,
+    /// One line naming the failed tools
+    #[default]
+    Tools,
+    /// One line per failed tool with its error
+    Errors,
+}
+
+impl FailureNote {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureNote::Off => "off",
+            FailureNote::Tools => "tools",
+            FailureNote::Errors => "errors",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Composer {
+    cite_sources: bool,
+    failure_note: FailureNote,
+    options: ComposeOptions,
+}
 
 impl Composer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Mark each response's section with its source number (e.g. "[1]") and
+    /// end the content with a "Sources" block; see `merge::merge_responses_cited`
+    pub fn with_cite_sources(mut self, cite_sources: bool) -> Self {
+        self.cite_sources = cite_sources;
+        self
+    }
+    
+    /// How `compose_outcomes` notes failed tools in the content
+    pub fn with_failure_note(mut self, failure_note: FailureNote) -> Self {
+        self.failure_note = failure_note;
+        self
+    }
+    
+    /// Trim responses to `options.max_tokens` before merging them
+    ///
+    /// The budget covers the merged content, section headers and citations
+    /// included; see `budget::trim_proportionally` for how it is shared.
+    /// What each response kept and lost is listed in `metadata["trimming"]`.
+    pub fn with_options(mut self, options: ComposeOptions) -> Self {
+        self.options = options;
+        self
+    }
+    
+    /// Sanitize responses with the default policy, then merge them
     pub fn compose(responses: Vec<ToolResponse>) -> ComposedResponse {
-        merge::merge_responses(responses)
+        Self::compose_with_policy(responses, Some(&SanitizePolicy::default()))
+    }
+    
+    /// Merge responses, sanitizing them first unless `policy` is None
+    pub fn compose_with_policy(responses: Vec<ToolResponse>, policy: Option<&SanitizePolicy>) -> ComposedResponse {
+        Self::default().compose_responses(responses, policy)
+    }
+    
+    /// `compose_with_policy` with this composer's options
+    pub fn compose_responses(&self, mut responses: Vec<ToolResponse>, policy: Option<&SanitizePolicy>) -> ComposedResponse {
+        let reports: Option<Vec<SanitizationReport>> = policy.map(|policy| {
+            responses
+                .iter_mut()
+                .map(|response| sanitize_response(response, policy))
+                .collect()
+        });
+        let trimming = self.options.max_tokens.map(|max_tokens| self.trim(&mut responses, max_tokens));
+        
+        let mut composed = self.merge(responses);
+        if let Some(reports) = reports {
+            insert_metadata(&mut composed, "sanitization", serde_json::to_value(reports).unwrap_or_default());
+        }
+        if let Some(trimming) = trimming {
+            insert_metadata(&mut composed, "trimming", serde_json::to_value(trimming).unwrap_or_default());
+        }
+        composed
+    }
+    
+    /// Compose the successful outcomes, noting the tools that failed
+    ///
+    /// Successes are composed as by `compose_responses`. Failures are listed
+    /// in `metadata["failures"]` and, unless the failure note is `Off`, in a
+    /// note after the content; `partial` is set when there are any. Fails
+    /// with `ToolUnavailable` naming each error if no tool succeeded.
+    pub fn compose_outcomes(&self, outcomes: Vec<ToolOutcome>, policy: Option<&SanitizePolicy>) -> Result<ComposedResponse> {
+        let mut responses = Vec::new();
+        let mut failures = Vec::new();
+        for outcome in outcomes {
+            match outcome {
+                ToolOutcome::Success(response) => responses.push(response),
+                ToolOutcome::Failure { tool, error, retryable } => failures.push((tool, error, retryable)),
+            }
+        }
+        
+        if responses.is_empty() && !failures.is_empty() {
+            let errors: Vec<String> = failures
+                .iter()
+                .map(|(tool, error, _)| format!("{}: {}", tool, error))
+                .collect();
+            return Err(OrchestratorError::ToolUnavailable(format!("All tools failed: {}", errors.join("; "))));
+        }
+        
+        let mut composed = self.compose_responses(responses, policy);
+        if failures.is_empty() {
+            return Ok(composed);
+        }
+        
+        if let Some(note) = failure_note(&failures, self.failure_note) {
+            if !composed.content.is_empty() && !composed.content.ends_with('\n') {
+                composed.content.push('\n');
+            }
+            composed.content.push('\n');
+            composed.content.push_str(&note);
+        }
+        let failures: Vec<serde_json::Value> = failures
+            .into_iter()
+            .map(|(tool, error, retryable)| serde_json::json!({ "tool": tool, "error": error, "retryable": retryable }))
+            .collect();
+        insert_metadata(&mut composed, "failures", serde_json::Value::Array(failures));
+        composed.partial = true;
+        Ok(composed)
+    }
+    
+    /// Share what `max_tokens` leaves after headers and citations among the responses
+    fn trim(&self, responses: &mut [ToolResponse], max_tokens: usize) -> Vec<TrimReport> {
+        let counter = &self.options.token_counter;
+        let skeleton: Vec<ToolResponse> = responses
+            .iter()
+            .map(|response| ToolResponse { content: String::new(), ..response.clone() })
+            .collect();
+        let overhead = counter.estimate_tokens(&self.merge(skeleton).content);
+        budget::trim_proportionally(responses, max_tokens.saturating_sub(overhead), counter)
+    }
+    
+    fn merge(&self, responses: Vec<ToolResponse>) -> ComposedResponse {
+        if self.cite_sources {
+            merge::merge_responses_cited(responses)
+        } else {
+            merge::merge_responses(responses)
+        }
+    }
+}
+
+/// Set `key` in the composed metadata, keeping non-object tool metadata under "tool_metadata"
+fn insert_metadata(composed: &mut ComposedResponse, key: &str, value: serde_json::Value) {
+    composed.metadata = Some(match composed.metadata.take() {
+        Some(serde_json::Value::Object(mut map)) => {
+            map.insert(key.to_string(), value);
+            serde_json::Value::Object(map)
+        }
+        None => serde_json::json!({ key: value }),
+        Some(other) => serde_json::json!({ "tool_metadata": other, key: value }),
+    });
+}
+
+/// Note listing failed tools as (tool, error, retryable), or None when `Off`
+fn failure_note(failures: &[(String, String, bool)], verbosity: FailureNote) -> Option<String> {
+    match verbosity {
+        FailureNote::Off => None,
+        FailureNote::Tools => {
+            let tools: Vec<&str> = failures.iter().map(|(tool, _, _)| tool.as_str()).collect();
+            Some(format!("Some sources failed: {}", tools.join(", ")))
+        }
+        FailureNote::Errors => {
+            let mut note = String::from("Some sources failed:");
+            for (tool, error, retryable) in failures {
+                note.push_str(&format!("\n- {}: {}", tool, error));
+                if *retryable {
+                    note.push_str(" (retryable)");
+                }
+            }
+            Some(note)
+        }
     }
 }
@@ -0,0 +1,350 @@
+use super::ToolResponse;
+use crate::security::limits::{truncate_json, JsonLimits};
+use crate::security::prompt_guard::{GuardAction, InjectionFinding, PromptGuard};
+use serde::{Deserialize, Serialize};
+
+/// Limits applied to tool output before it is composed
+#[derive(Debug, Clone)]
+pub struct SanitizePolicy {
+    /// Lines longer than this many bytes are soft-wrapped
+    pub max_line_len: usize,
+    /// Responses longer than this many bytes are cut, marker included
+    pub max_bytes: usize,
+    pub strip_ansi: bool,
+    pub close_fences: bool,
+    /// Appended where a response was truncated
+    pub truncation_marker: String,
+    /// Tool metadata over these limits is truncated
+    pub metadata_limits: JsonLimits,
+    /// Screens content for prompt injection; blocked responses are withheld
+    pub prompt_guard: Option<PromptGuard>,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        Self {
+            max_line_len: 2_000,
+            max_bytes: 256 * 1024,
+            strip_ansi: true,
+            close_fences: true,
+            truncation_marker: "\n[... response truncated ...]".to_string(),
+            metadata_limits: JsonLimits::default(),
+            prompt_guard: None,
+        }
+    }
+}
+
+impl SanitizePolicy {
+    pub fn with_max_line_len(mut self, max_line_len: usize) -> Self {
+        self.max_line_len = max_line_len.max(1);
+        self
+    }
+    
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+    
+    pub fn with_truncation_marker(mut self, marker: impl Into<String>) -> Self {
+        self.truncation_marker = marker.into();
+        self
+    }
+    
+    pub fn with_metadata_limits(mut self, limits: JsonLimits) -> Self {
+        self.metadata_limits = limits;
+        self
+    }
+    
+    pub fn with_prompt_guard(mut self, guard: PromptGuard) -> Self {
+        self.prompt_guard = Some(guard);
+        self
+    }
+}
+
+/// What `sanitize_response` changed in one response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SanitizationReport {
+    pub tool: String,
+    pub ansi_sequences_removed: usize,
+    /// U+FFFD characters dropped from runs of two or more
+    pub replacement_chars_collapsed: usize,
+    pub lines_wrapped: usize,
+    pub fences_closed: usize,
+    /// Bytes of the original content dropped by truncation
+    pub truncated_bytes: usize,
+    /// Metadata was cut down to `SanitizePolicy::metadata_limits`
+    #[serde(default)]
+    pub metadata_truncated: bool,
+    /// Prompt injection findings, with offsets into the ANSI-stripped content
+    #[serde(default)]
+    pub injection_findings: Vec<InjectionFinding>,
+    /// Content was replaced because the prompt guard blocked it
+    #[serde(default)]
+    pub withheld: bool,
+}
+
+impl SanitizationReport {
+    /// True when the response was left unchanged
+    pub fn is_clean(&self) -> bool {
+        self.ansi_sequences_removed == 0
+            && self.replacement_chars_collapsed == 0
+            && self.lines_wrapped == 0
+            && self.fences_closed == 0
+            && self.truncated_bytes == 0
+            && !self.metadata_truncated
+            && self.injection_findings.is_empty()
+    }
+}
+
+/// Replaces tool output blocked by the prompt guard
+pub const WITHHELD_NOTICE: &str = "[response withheld: possible prompt injection]";
+
+/// Clean up a tool response so it can be embedded in composed markdown
+///
+/// Steps run in order: strip ANSI escapes, collapse runs of replacement
+/// characters, soft-wrap long lines, truncate, then close any code fence
+/// left open so later responses are not swallowed by it. Metadata is held
+/// to `metadata_limits`. With a prompt guard set, content is scanned before
+/// wrapping and replaced by a notice if the guard blocks it.
+pub fn sanitize_response(response: &mut ToolResponse, policy: &SanitizePolicy) -> SanitizationReport {
+    let mut report = SanitizationReport {
+        tool: response.tool.clone(),
+        ..Default::default()
+    };
+    let mut content = std::mem::take(&mut response.content);
+    
+    if policy.strip_ansi {
+        let (stripped, removed) = strip_ansi(&content);
+        content = stripped;
+        report.ansi_sequences_removed = removed;
+    }
+    
+    let (collapsed, dropped) = collapse_replacement_runs(&content);
+    content = collapsed;
+    report.replacement_chars_collapsed = dropped;
+    
+    if let Some(guard) = &policy.prompt_guard {
+        let verdict = guard.check(&content);
+        report.injection_findings = verdict.findings;
+        if verdict.action == GuardAction::Block {
+            content = WITHHELD_NOTICE.to_string();
+            report.withheld = true;
+        }
+    }
+    
+    let (wrapped, lines) = wrap_long_lines(&content, policy.max_line_len);
+    content = wrapped;
+    report.lines_wrapped = lines;
+    
+    let original_len = content.len();
+    let mut truncated = false;
+    if content.len() > policy.max_bytes {
+        // Leave room for the marker and a closing fence
+        let mut budget = policy.max_bytes.saturating_sub(policy.truncation_marker.len());
+        loop {
+            let body = &content[..floor_char_boundary(&content, budget)];
+            let closer = if policy.close_fences { fence_closer(body) } else { String::new() };
+            if body.len() + closer.len() + policy.truncation_marker.len() <= policy.max_bytes || budget == 0 {
+                let cut = body.len();
+                content.truncate(cut);
+                break;
+            }
+            budget = budget.saturating_sub(closer.len());
+        }
+        report.truncated_bytes = original_len - content.len();
+        truncated = true;
+    }
+    
+    if policy.close_fences {
+        let closer = fence_closer(&content);
+        if !closer.is_empty() {
+            content.push_str(&closer);
+            report.fences_closed = 1;
+        }
+    }
+    if truncated {
+        content.push_str(&policy.truncation_marker);
+    }
+    
+    response.content = content;
+    if let Some(metadata) = response.metadata.take() {
+        let (metadata, truncated) = truncate_json(metadata, &policy.metadata_limits);
+        response.metadata = Some(metadata);
+        report.metadata_truncated = truncated;
+    }
+    report
+}
+
+/// Remove CSI, OSC and two-byte escape sequences; returns the count removed
+fn strip_ansi(text: &str) -> (String, usize) {
+    if !text.contains('\u{1b}') {
+        return (text.to_string(), 0);
+    }
+    
+    let mut out = String::with_capacity(text.len());
+    let mut removed = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        removed += 1;
+        match chars.next() {
+            // CSI: parameters and intermediates, then a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ends with BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    (out, removed)
+}
+
+fn collapse_replacement_runs(text: &str) -> (String, usize) {
+    if !text.contains("\u{fffd}\u{fffd}") {
+        return (text.to_string(), 0);
+    }
+    
+    let mut out = String::with_capacity(text.len());
+    let mut dropped = 0;
+    let mut previous = None;
+    for c in text.chars() {
+        if c == '\u{fffd}' && previous == Some('\u{fffd}') {
+            dropped += 1;
+            continue;
+        }
+        out.push(c);
+        previous = Some(c);
+    }
+    (out, dropped)
+}
+
+/// Break lines over `max_len` bytes, preferring the last space in range
+fn wrap_long_lines(text: &str, max_len: usize) -> (String, usize) {
+    if text.split('\n').all(|line| line.len() <= max_len) {
+        return (text.to_string(), 0);
+    }
+    
+    let mut wrapped = 0;
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            if line.len() <= max_len {
+                return line.to_string();
+            }
+            wrapped += 1;
+            let mut pieces = Vec::new();
+            let mut rest = line;
+            while rest.len() > max_len {
+                let limit = floor_char_boundary(rest, max_len).max(rest.chars().next().map_or(1, char::len_utf8));
+                let cut = if rest[limit..].starts_with(' ') {
+                    limit
+                } else {
+                    match rest[..limit].rfind(' ') {
+                        Some(space) if space > 0 => space,
+                        _ => limit,
+                    }
+                };
+                pieces.push(rest[..cut].trim_end());
+                rest = rest[cut..].trim_start_matches(' ');
+            }
+            if !rest.is_empty() {
+                pieces.push(rest);
+            }
+            pieces.join("\n")
+        })
+        .collect();
+    (lines.join("\n"), wrapped)
+}
+
+/// Code fence state while reading text line by line
+///
+/// Follows CommonMark: a fence closes only on a line of the same character
+/// at least as long as the opener with nothing after it, so a shorter or
+/// labelled fence inside an open one is part of its content.
+#[derive(Debug, Default)]
+pub(crate) struct FenceTracker {
+    open: Option<(char, usize)>, // Fence character and run length of the open fence
+}
+
+impl FenceTracker {
+    /// Advance over `line`; true if it opens, closes or is inside a fence
+    pub(crate) fn step(&mut self, line: &str) -> bool {
+        let was_open = self.open.is_some();
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent > 3 {
+            return was_open;
+        }
+        let line = &line[indent..];
+        let fence_char = match line.chars().next() {
+            Some(c @ ('`' | '~')) => c,
+            _ => return was_open,
+        };
+        let run = line.chars().take_while(|c| *c == fence_char).count();
+        if run < 3 {
+            return was_open;
+        }
+        let info = line[run..].trim();
+        match self.open {
+            Some((c, len)) => {
+                if c == fence_char && run >= len && info.is_empty() {
+                    self.open = None;
+                }
+            }
+            None => {
+                if !(fence_char == '`' && info.contains('`')) {
+                    self.open = Some((fence_char, run));
+                }
+            }
+        }
+        was_open || self.open.is_some()
+    }
+    
+    pub(crate) fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+}
+
+/// Text that closes the code fence left open at the end of `text`, if any
+fn fence_closer(text: &str) -> String {
+    let mut fences = FenceTracker::default();
+    for line in text.lines() {
+        fences.step(line);
+    }
+    
+    match fences.open {
+        Some((c, len)) => {
+            let newline = if text.ends_with('\n') { "" } else { "\n" };
+            format!("{}{}\n", newline, c.to_string().repeat(len))
+        }
+        None => String::new(),
+    }
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    let mut index = index;
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -0,0 +1,239 @@
+/// Persistence for composed responses and the tool output behind them
+
+use super::{ComposedResponse, ToolResponse};
+use crate::error::{OrchestratorError, Result};
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A composed response as stored, with the raw tool responses it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub id: i64,
+    pub conversation_id: String,
+    pub request_id: String,
+    pub content: String,
+    pub strategy: String,
+    pub sources: Vec<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub raw_responses: Vec<ToolResponse>,
+    pub created_at: i64, // Unix seconds
+}
+
+pub struct ResponseStore {
+    pool: SqlitePool,
+}
+
+impl ResponseStore {
+    pub async fn new(db_path: PathBuf) -> Result<Self> {
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(OrchestratorError::from)?;
+        }
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(
+                sqlx::sqlite::SqliteConnectOptions::new()
+                    .filename(&db_path)
+                    .create_if_missing(true),
+            )
+            .await
+            .map_err(OrchestratorError::from)?;
+
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing pool, creating the responses table if needed
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        // Raw tool responses are kept as gzipped JSON next to the composed text
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS responses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                conversation_id TEXT NOT NULL,
+                request_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                strategy TEXT NOT NULL,
+                sources TEXT NOT NULL,
+                metadata TEXT,
+                raw_responses BLOB NOT NULL,
+                created_at INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_responses_conversation ON responses(conversation_id, created_at)"
+        )
+        .execute(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_responses_request ON responses(request_id)"
+        )
+        .execute(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_responses_created_at ON responses(created_at)"
+        )
+        .execute(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(Self { pool })
+    }
+
+    /// Store a composed response and its raw inputs; returns the new row id
+    pub async fn save(
+        &self,
+        composed: &ComposedResponse,
+        raw: &[ToolResponse],
+        conversation_id: &str,
+        request_id: &str,
+    ) -> Result<i64> {
+        self.save_at(composed, raw, conversation_id, request_id, Utc::now().timestamp()).await
+    }
+
+    /// `save` with an explicit creation time in Unix seconds
+    pub async fn save_at(
+        &self,
+        composed: &ComposedResponse,
+        raw: &[ToolResponse],
+        conversation_id: &str,
+        request_id: &str,
+        created_at: i64,
+    ) -> Result<i64> {
+        let sources = serde_json::to_string(&composed.sources).map_err(OrchestratorError::from)?;
+        let metadata = composed
+            .metadata
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(OrchestratorError::from)?;
+        let raw_responses = compress_responses(raw)?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO responses
+            (conversation_id, request_id, content, strategy, sources, metadata, raw_responses, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(request_id)
+        .bind(&composed.content)
+        .bind(&composed.strategy)
+        .bind(sources)
+        .bind(metadata)
+        .bind(raw_responses)
+        .bind(created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Responses of a conversation, oldest first
+    pub async fn get_by_conversation(&self, conversation_id: &str) -> Result<Vec<StoredResponse>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, conversation_id, request_id, content, strategy, sources, metadata, raw_responses, created_at
+            FROM responses
+            WHERE conversation_id = ?1
+            ORDER BY created_at ASC, id ASC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        rows.iter().map(row_to_response).collect()
+    }
+
+    /// The response stored for a request; the latest one if it was saved more than once
+    pub async fn get_by_request(&self, request_id: &str) -> Result<Option<StoredResponse>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, conversation_id, request_id, content, strategy, sources, metadata, raw_responses, created_at
+            FROM responses
+            WHERE request_id = ?1
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(request_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        row.as_ref().map(row_to_response).transpose()
+    }
+
+    /// Delete responses older than `max_age`; returns how many were removed
+    pub async fn prune_older_than(&self, max_age: Duration) -> Result<u64> {
+        let cutoff = Utc::now().timestamp() - max_age.as_secs() as i64;
+        self.prune_before(cutoff).await
+    }
+
+    /// Delete responses created before `cutoff` (Unix seconds)
+    pub async fn prune_before(&self, cutoff: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM responses WHERE created_at < ?1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn compress_responses(raw: &[ToolResponse]) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(raw).map_err(OrchestratorError::from)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    Ok(encoder.finish()?)
+}
+
+fn decompress_responses(data: &[u8]) -> Result<Vec<ToolResponse>> {
+    let mut json = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut json)?;
+    serde_json::from_slice(&json).map_err(OrchestratorError::from)
+}
+
+fn row_to_response(row: &sqlx::sqlite::SqliteRow) -> Result<StoredResponse> {
+    let sources: String = row.get("sources");
+    let metadata: Option<String> = row.get("metadata");
+    let raw_responses: Vec<u8> = row.get("raw_responses");
+
+    Ok(StoredResponse {
+        id: row.get("id"),
+        conversation_id: row.get("conversation_id"),
+        request_id: row.get("request_id"),
+        content: row.get("content"),
+        strategy: row.get("strategy"),
+        sources: serde_json::from_str(&sources).map_err(OrchestratorError::from)?,
+        metadata: metadata
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(OrchestratorError::from)?,
+        raw_responses: decompress_responses(&raw_responses)?,
+        created_at: row.get("created_at"),
+    })
+}
@@ -0,0 +1,584 @@
+/// Orchestrator configuration loaded from TOML with environment overrides
+
+use crate::context::compression::ContextCompressor;
+use crate::context::importance::ImportanceConfig;
+use crate::context::summarizer::ContextSummarizer;
+use crate::context::token_counter::TokenCounter;
+use crate::context::window::{ContextWindowManager, ReservedBudget};
+use crate::cost::pricing::PricingTable;
+use crate::cost::{BudgetManager, CostCalculator, CostStorage};
+use crate::error::{OrchestratorError, Result};
+use crate::indexer::codebase::{default_skip_patterns, CodebaseIndexer};
+use crate::indexer::parser::ASTParser;
+use crate::indexer::storage::IndexStorage;
+use crate::models::{ModelCatalog, ModelEntry};
+use crate::projects::{Project, ProjectSettings};
+use crate::resilience::{CircuitBreaker, ExponentialBackoffRetry, RateLimiter, RateLimiterConfig, TimeoutConfig, Timeouts};
+use crate::router::Router;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix for environment variable overrides
+///
+/// Every setting can be overridden by its dotted path upper-cased with
+/// `_` separators, e.g. `context.summarizer.summary_ratio` is read from
+/// `UAI_CONTEXT_SUMMARIZER_SUMMARY_RATIO`. Lists are comma-separated.
+pub const ENV_PREFIX: &str = "UAI_";
+
+/// Largest context window of any supported model
+const MAX_RESERVED_TOKENS: usize = 200_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrchestratorConfig {
+    pub storage: StorageConfig,
+    pub routing: RoutingConfig,
+    pub context: ContextConfig,
+    pub indexer: IndexerConfig,
+    pub resilience: ResilienceConfig,
+    pub timeouts: TimeoutConfig,
+    pub cost: CostConfig,
+    pub models: HashMap<String, ModelEntry>, // Alias -> model, e.g. fast = { model = "...", tool = "claude" }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub db_path: PathBuf,
+    pub index_path: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            db_path: PathBuf::from("~/.uai/db.sqlite"),
+            index_path: PathBuf::from("~/.uai/indexes"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    pub default_tool: String,
+    #[serde(flatten)]
+    pub rules: HashMap<String, Vec<String>>, // Task type -> tools (e.g. code_editing = ["claude"])
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            default_tool: "claude".to_string(),
+            rules: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextConfig {
+    pub reserved_tokens: usize, // Reserve tokens for response
+    pub summarizer: SummarizerConfig,
+    pub compression: CompressionConfig,
+    pub model_windows: HashMap<String, usize>, // Context windows of models missing from MODEL_CONTEXT_WINDOWS
+    pub model_reserved: HashMap<String, ReservedBudget>, // Replace reserved_tokens for these models and their variants
+    pub importance: ImportanceConfig, // Message scoring for both summarization and truncation
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            reserved_tokens: 1000,
+            summarizer: SummarizerConfig::default(),
+            compression: CompressionConfig::default(),
+            model_windows: HashMap::new(),
+            model_reserved: HashMap::new(),
+            importance: ImportanceConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummarizerConfig {
+    pub message_threshold: usize,
+    pub summary_ratio: f64,
+    pub abstractive_threshold: usize,
+}
+
+impl Default for SummarizerConfig {
+    fn default() -> Self {
+        Self {
+            message_threshold: 50,
+            summary_ratio: 0.8,
+            abstractive_threshold: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub max_message_length: usize,
+    pub remove_comments: bool,
+    pub normalize_whitespace: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            max_message_length: 2000,
+            remove_comments: false,
+            normalize_whitespace: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexerConfig {
+    pub skip_patterns: Vec<String>,
+    pub max_cached_trees: usize,
+    pub min_anonymous_function_lines: usize,
+    pub search_candidate_multiplier: usize,
+    pub use_git_discovery: bool, // List files with `git ls-files` on cold starts
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        Self {
+            skip_patterns: default_skip_patterns(),
+            max_cached_trees: 128,
+            min_anonymous_function_lines: 2,
+            search_candidate_multiplier: 5,
+            use_git_discovery: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResilienceConfig {
+    pub retry_max_attempts: u32,
+    pub retry_initial_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub retry_jitter: bool,
+    pub circuit_failure_threshold: u32,
+    pub circuit_timeout_secs: u64,
+    pub rate_limit_capacity: u32,
+    pub rate_limit_refill_per_sec: f64,
+    /// Tokens available at startup; unset means a full bucket
+    pub rate_limit_initial_tokens: Option<u32>,
+    /// Seconds over which the refill rate ramps up from zero; 0 disables
+    pub rate_limit_warmup_secs: u64,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        Self {
+            retry_max_attempts: 3,
+            retry_initial_delay_ms: 100,
+            retry_max_delay_ms: 10_000,
+            retry_jitter: true,
+            circuit_failure_threshold: 5,
+            circuit_timeout_secs: 60,
+            rate_limit_capacity: 60,
+            rate_limit_refill_per_sec: 1.0,
+            rate_limit_initial_tokens: None,
+            rate_limit_warmup_secs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CostConfig {
+    pub daily_budget_usd: f64, // 0.0 = unlimited
+    pub monthly_budget_usd: f64, // 0.0 = unlimited
+}
+
+impl OrchestratorConfig {
+    /// Load from a TOML file, apply `UAI_` environment overrides and validate
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            OrchestratorError::InvalidConfig(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        
+        let mut config = Self::from_toml_str(&contents)?;
+        config.apply_env_overrides(std::env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Parse TOML; missing sections and fields keep their defaults
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        toml::from_str(contents)
+            .map_err(|e| OrchestratorError::InvalidConfig(format!("Failed to parse config: {}", e)))
+    }
+    
+    /// Override settings from `UAI_`-prefixed variables (see [`ENV_PREFIX`])
+    ///
+    /// Variables that don't name a setting are ignored.
+    pub fn apply_env_overrides<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let overrides: HashMap<String, String> = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        if overrides.is_empty() {
+            return Ok(());
+        }
+        
+        let mut value = toml::Value::try_from(&*self)
+            .map_err(|e| OrchestratorError::InvalidConfig(format!("Failed to serialize config: {}", e)))?;
+        override_leaves(&mut value, ENV_PREFIX.trim_end_matches('_'), &overrides)?;
+        
+        *self = value.try_into()
+            .map_err(|e| OrchestratorError::InvalidConfig(format!("Invalid override: {}", e)))?;
+        Ok(())
+    }
+    
+    /// Check value ranges, reporting every problem found
+    ///
+    /// Model aliases without pricing are logged as warnings rather than
+    /// failing validation; see `model_warnings`.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        
+        if self.storage.db_path.as_os_str().is_empty() {
+            errors.push("storage.db_path must not be empty".to_string());
+        }
+        if self.routing.default_tool.trim().is_empty() {
+            errors.push("routing.default_tool must not be empty".to_string());
+        }
+        for (task, tools) in &self.routing.rules {
+            if tools.is_empty() {
+                errors.push(format!("routing.{} must list at least one tool", task));
+            }
+        }
+        
+        let context = &self.context;
+        if context.reserved_tokens > MAX_RESERVED_TOKENS {
+            errors.push(format!(
+                "context.reserved_tokens must be at most {} (got {})",
+                MAX_RESERVED_TOKENS, context.reserved_tokens
+            ));
+        }
+        for (model, reserved) in &context.model_reserved {
+            if reserved.total() > MAX_RESERVED_TOKENS {
+                errors.push(format!(
+                    "context.model_reserved.{} must total at most {} (got {})",
+                    model, MAX_RESERVED_TOKENS, reserved.total()
+                ));
+            }
+        }
+        if context.summarizer.message_threshold == 0 {
+            errors.push("context.summarizer.message_threshold must be greater than 0".to_string());
+        }
+        let ratio = context.summarizer.summary_ratio;
+        if ratio.is_nan() || ratio <= 0.0 || ratio > 1.0 {
+            errors.push(format!(
+                "context.summarizer.summary_ratio must be in (0.0, 1.0] (got {})",
+                ratio
+            ));
+        }
+        let band = &context.importance.length_band;
+        if band.min >= band.max {
+            errors.push(format!(
+                "context.importance.length_band.min must be less than max (got {} >= {})",
+                band.min, band.max
+            ));
+        }
+        if context.compression.max_message_length == 0 {
+            errors.push("context.compression.max_message_length must be greater than 0".to_string());
+        }
+        for (model, window) in &context.model_windows {
+            if *window == 0 {
+                errors.push(format!("context.model_windows.{} must be greater than 0", model));
+            }
+        }
+        
+        if self.indexer.search_candidate_multiplier == 0 {
+            errors.push("indexer.search_candidate_multiplier must be greater than 0".to_string());
+        }
+        
+        let resilience = &self.resilience;
+        if resilience.retry_max_attempts == 0 {
+            errors.push("resilience.retry_max_attempts must be greater than 0".to_string());
+        }
+        if resilience.retry_initial_delay_ms > resilience.retry_max_delay_ms {
+            errors.push(format!(
+                "resilience.retry_initial_delay_ms ({}) must not exceed resilience.retry_max_delay_ms ({})",
+                resilience.retry_initial_delay_ms, resilience.retry_max_delay_ms
+            ));
+        }
+        if resilience.circuit_failure_threshold == 0 {
+            errors.push("resilience.circuit_failure_threshold must be greater than 0".to_string());
+        }
+        if resilience.rate_limit_capacity == 0 {
+            errors.push("resilience.rate_limit_capacity must be greater than 0".to_string());
+        }
+        if let Some(initial_tokens) = resilience.rate_limit_initial_tokens {
+            if initial_tokens > resilience.rate_limit_capacity {
+                errors.push(format!(
+                    "resilience.rate_limit_initial_tokens ({}) must not exceed resilience.rate_limit_capacity ({})",
+                    initial_tokens, resilience.rate_limit_capacity
+                ));
+            }
+        }
+        let refill = resilience.rate_limit_refill_per_sec;
+        if refill.is_nan() || refill <= 0.0 {
+            errors.push(format!(
+                "resilience.rate_limit_refill_per_sec must be greater than 0 (got {})",
+                refill
+            ));
+        }
+        
+        if self.timeouts.default_secs == 0 {
+            errors.push("timeouts.default_secs must be greater than 0".to_string());
+        }
+        for (tool, secs) in &self.timeouts.tools {
+            if *secs == 0 {
+                errors.push(format!("timeouts.tools.{} must be greater than 0", tool));
+            }
+        }
+        
+        for (field, budget) in [
+            ("cost.daily_budget_usd", self.cost.daily_budget_usd),
+            ("cost.monthly_budget_usd", self.cost.monthly_budget_usd),
+        ] {
+            if budget.is_nan() || budget < 0.0 {
+                errors.push(format!("{} must not be negative (got {})", field, budget));
+            }
+        }
+        if self.cost.daily_budget_usd > 0.0
+            && self.cost.monthly_budget_usd > 0.0
+            && self.cost.daily_budget_usd > self.cost.monthly_budget_usd
+        {
+            errors.push(format!(
+                "cost.daily_budget_usd ({}) must not exceed cost.monthly_budget_usd ({})",
+                self.cost.daily_budget_usd, self.cost.monthly_budget_usd
+            ));
+        }
+        
+        let mut aliases: Vec<(&String, &ModelEntry)> = self.models.iter().collect();
+        aliases.sort_by_key(|(alias, _)| *alias);
+        for (alias, entry) in aliases {
+            if entry.model.trim().is_empty() {
+                errors.push(format!("models.{}.model must not be empty", alias));
+            }
+            if entry.tool.trim().is_empty() {
+                errors.push(format!("models.{}.tool must not be empty", alias));
+            }
+            if entry.context_window == Some(0) {
+                errors.push(format!("models.{}.context_window must be greater than 0", alias));
+            }
+        }
+        for warning in self.model_warnings() {
+            tracing::warn!("{}", warning);
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(OrchestratorError::InvalidConfig(errors.join("; ")))
+        }
+    }
+    
+    /// Model aliases whose pricing entry is unknown, so their cost would be recorded as 0
+    pub fn model_warnings(&self) -> Vec<String> {
+        self.build_model_catalog().pricing_warnings(&PricingTable::new())
+    }
+    
+    pub fn build_model_catalog(&self) -> ModelCatalog {
+        let mut catalog = ModelCatalog::new();
+        for (alias, entry) in &self.models {
+            catalog.insert(alias.clone(), entry.clone());
+        }
+        catalog
+    }
+    
+    pub fn build_pricing_table(&self) -> PricingTable {
+        PricingTable::new().with_catalog(self.build_model_catalog())
+    }
+    
+    pub fn build_cost_calculator(&self) -> CostCalculator {
+        CostCalculator::new().with_pricing_table(self.build_pricing_table())
+    }
+    
+    /// Budget manager with the configured daily and monthly budgets
+    pub fn build_budget_manager(&self, storage: CostStorage) -> BudgetManager {
+        BudgetManager::new(storage, self.build_cost_calculator())
+            .with_daily_budget(self.cost.daily_budget_usd)
+            .with_monthly_budget(self.cost.monthly_budget_usd)
+    }
+    
+    pub fn build_router(&self) -> Router {
+        Router::new(self.routing.rules.clone(), self.routing.default_tool.clone())
+            .with_model_catalog(self.build_model_catalog())
+    }
+    
+    /// The configured rules with `settings.routing_overrides` replacing those with the same rule keys
+    pub fn build_project_router(&self, settings: &ProjectSettings) -> Router {
+        let mut rules = self.routing.rules.clone();
+        rules.extend(settings.routing_overrides.clone());
+        Router::new(rules, self.routing.default_tool.clone())
+            .with_model_catalog(self.build_model_catalog())
+    }
+    
+    /// Settings for a new project that inherits this configuration
+    pub fn default_project_settings(&self) -> ProjectSettings {
+        ProjectSettings {
+            skip_patterns: Some(self.indexer.skip_patterns.clone()),
+            routing_overrides: HashMap::new(),
+            budget_usd: Some(self.cost.monthly_budget_usd).filter(|budget| *budget > 0.0),
+        }
+    }
+    
+    /// Monthly budget for a project, 0.0 for unlimited
+    pub fn project_monthly_budget_usd(&self, settings: &ProjectSettings) -> f64 {
+        settings.budget_usd.unwrap_or(self.cost.monthly_budget_usd)
+    }
+    
+    pub fn build_summarizer(&self) -> ContextSummarizer {
+        let summarizer = &self.context.summarizer;
+        ContextSummarizer::new(summarizer.message_threshold, summarizer.summary_ratio)
+            .with_abstractive_threshold(summarizer.abstractive_threshold)
+            .with_importance(self.context.importance.clone())
+    }
+    
+    pub fn build_window_manager(&self) -> ContextWindowManager {
+        let mut manager = ContextWindowManager::new(self.context.reserved_tokens)
+            .with_summarizer(self.build_summarizer())
+            .with_model_catalog(self.build_model_catalog());
+        for (model, window) in &self.context.model_windows {
+            manager.register_model(model.clone(), *window);
+        }
+        for (model, reserved) in &self.context.model_reserved {
+            manager.register_model_reserved(model.clone(), *reserved);
+        }
+        manager
+    }
+    
+    pub fn build_token_counter(&self) -> TokenCounter {
+        let mut counter = TokenCounter::new().with_catalog(self.build_model_catalog());
+        for (model, window) in &self.context.model_windows {
+            counter.register_model(model.clone(), *window);
+        }
+        counter
+    }
+    
+    pub fn build_compressor(&self) -> ContextCompressor {
+        let compression = &self.context.compression;
+        ContextCompressor::new()
+            .with_max_length(compression.max_message_length)
+            .with_remove_comments(compression.remove_comments)
+            .with_normalize_whitespace(compression.normalize_whitespace)
+    }
+    
+    pub fn build_parser(&self) -> ASTParser {
+        ASTParser::new()
+            .with_max_cached_trees(self.indexer.max_cached_trees)
+            .with_min_anonymous_function_lines(self.indexer.min_anonymous_function_lines)
+    }
+    
+    pub fn build_indexer(&self, project_id: String, storage: IndexStorage) -> CodebaseIndexer {
+        CodebaseIndexer::new(project_id, storage)
+            .with_parser(self.build_parser())
+            .with_skip_patterns(self.indexer.skip_patterns.clone())
+            .with_git_discovery(self.indexer.use_git_discovery)
+    }
+    
+    /// `build_indexer` for a registered project, using its root path and skip patterns if set
+    pub fn build_project_indexer(&self, project: &Project, storage: IndexStorage) -> CodebaseIndexer {
+        let skip_patterns = project.settings.skip_patterns.clone()
+            .unwrap_or_else(|| self.indexer.skip_patterns.clone());
+        let indexer = self.build_indexer(project.id.clone(), storage)
+            .with_skip_patterns(skip_patterns);
+        match &project.root_path {
+            Some(root_path) => indexer.with_root(PathBuf::from(root_path)),
+            None => indexer,
+        }
+    }
+    
+    pub fn build_retry_policy(&self) -> ExponentialBackoffRetry {
+        let resilience = &self.resilience;
+        ExponentialBackoffRetry::new(
+            resilience.retry_max_attempts,
+            Duration::from_millis(resilience.retry_initial_delay_ms),
+            Duration::from_millis(resilience.retry_max_delay_ms),
+        )
+        .with_jitter(resilience.retry_jitter)
+    }
+    
+    pub fn build_circuit_breaker(&self, name: impl Into<String>) -> CircuitBreaker {
+        CircuitBreaker::new(
+            name,
+            self.resilience.circuit_failure_threshold,
+            Duration::from_secs(self.resilience.circuit_timeout_secs),
+        )
+    }
+    
+    pub fn build_rate_limiter(&self, name: impl Into<String>) -> RateLimiter {
+        let resilience = &self.resilience;
+        let mut config = RateLimiterConfig::new(resilience.rate_limit_capacity, resilience.rate_limit_refill_per_sec)
+            .with_warmup(Duration::from_secs(resilience.rate_limit_warmup_secs));
+        if let Some(initial_tokens) = resilience.rate_limit_initial_tokens {
+            config = config.with_initial_tokens(initial_tokens);
+        }
+        RateLimiter::from_config(name, config)
+    }
+    
+    /// Timeout for calls to `tool`, falling back to `timeouts.default_secs`
+    pub fn tool_timeout(&self, tool: &str) -> Duration {
+        self.timeouts.for_tool(tool)
+    }
+    
+    pub fn build_timeouts(&self) -> Timeouts {
+        Timeouts::new(self.timeouts.clone())
+    }
+}
+
+/// Replace every leaf whose environment name is present in `overrides`
+fn override_leaves(value: &mut toml::Value, env_name: &str, overrides: &HashMap<String, String>) -> Result<()> {
+    if let toml::Value::Table(table) = value {
+        for (key, child) in table.iter_mut() {
+            let child_name = format!("{}_{}", env_name, key.to_ascii_uppercase());
+            override_leaves(child, &child_name, overrides)?;
+        }
+        return Ok(());
+    }
+    
+    let raw = match overrides.get(env_name) {
+        Some(raw) => raw.trim(),
+        None => return Ok(()),
+    };
+    let invalid = |expected: &str| {
+        OrchestratorError::InvalidConfig(format!("{}: expected {}, got '{}'", env_name, expected, raw))
+    };
+    
+    *value = match &*value {
+        toml::Value::String(_) => toml::Value::String(raw.to_string()),
+        toml::Value::Integer(_) => toml::Value::Integer(raw.parse().map_err(|_| invalid("an integer"))?),
+        toml::Value::Float(_) => toml::Value::Float(raw.parse().map_err(|_| invalid("a number"))?),
+        toml::Value::Boolean(_) => toml::Value::Boolean(match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => true,
+            "false" | "0" | "no" => false,
+            _ => return Err(invalid("a boolean")),
+        }),
+        toml::Value::Array(_) => toml::Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| toml::Value::String(item.to_string()))
+                .collect(),
+        ),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => return Ok(()),
+    };
+    Ok(())
+}
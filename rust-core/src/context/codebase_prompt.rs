@@ -0,0 +1,136 @@
+/// Compact rendering of codebase context for prompts
+
+use super::token_counter::TokenCounter;
+use super::CodebaseContext;
+
+/// Heading of the system message `ContextManager::attach_codebase_context` maintains
+pub const CODEBASE_HEADER: &str = "Codebase context:";
+
+/// A semantic match split into where it is and what it is
+struct MatchEntry<'a> {
+    path: &'a str,
+    lines: Option<&'a str>,
+    name: &'a str,
+}
+
+/// Parse "path:lines name" or "path:lines — name"; lines and name are optional
+fn parse_match(raw: &str) -> MatchEntry<'_> {
+    let raw = raw.trim();
+    let (location, name) = match raw.split_once(" — ") {
+        Some((location, name)) => (location.trim(), name.trim()),
+        None => match raw.split_once(char::is_whitespace) {
+            Some((location, name)) => (location, name.trim()),
+            None => (raw, ""),
+        },
+    };
+    let (path, lines) = match location.rsplit_once(':') {
+        Some((path, lines)) if !lines.is_empty() && lines.chars().all(|c| c.is_ascii_digit() || c == '-') => {
+            (path, Some(lines))
+        }
+        _ => (location, None),
+    };
+    MatchEntry { path, lines, name }
+}
+
+/// Directory shared by every path, with a trailing slash, if all are absolute
+fn common_dir<'a>(paths: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut common: Option<Vec<&str>> = None;
+    for path in paths {
+        if !path.starts_with('/') {
+            return None;
+        }
+        let mut dirs: Vec<&str> = path.split('/').collect();
+        dirs.pop(); // File name
+        common = Some(match common {
+            None => dirs,
+            Some(prefix) => prefix.into_iter().zip(dirs).take_while(|(a, b)| a == b).map(|(a, _)| a).collect(),
+        });
+    }
+    common.filter(|dirs| dirs.len() > 1).map(|dirs| format!("{}/", dirs.join("/")))
+}
+
+impl CodebaseContext {
+    /// Render as a block for a system prompt, within `max_tokens`
+    ///
+    /// Semantic matches come first as "path:lines — name", then the
+    /// remaining relevant files grouped as "dir/: a.rs, b.rs". Paths are
+    /// made relative to the directory all of them share, and repeats or
+    /// files a match already cites are left out. Lines are added in order
+    /// until the next would exceed the budget. Empty if nothing fits.
+    pub fn render_for_prompt(&self, max_tokens: usize, token_counter: &TokenCounter) -> String {
+        let matches: Vec<MatchEntry> = self.semantic_matches.iter().map(|raw| parse_match(raw)).collect();
+        let root = common_dir(
+            matches.iter().map(|entry| entry.path).chain(self.relevant_files.iter().map(String::as_str)),
+        );
+        let relative = |path: &str| -> String {
+            match &root {
+                Some(root) => path.strip_prefix(root.as_str()).unwrap_or(path).to_string(),
+                None => path.trim_start_matches("./").to_string(),
+            }
+        };
+
+        let mut match_lines = Vec::new();
+        let mut cited = Vec::new();
+        for entry in &matches {
+            let mut line = relative(entry.path);
+            cited.push(line.clone());
+            if let Some(lines) = entry.lines {
+                line = format!("{}:{}", line, lines);
+            }
+            if !entry.name.is_empty() {
+                line = format!("{} — {}", line, entry.name);
+            }
+            let line = format!("- {}", line);
+            if !match_lines.contains(&line) {
+                match_lines.push(line);
+            }
+        }
+
+        // Directories keep the order their first file was listed in
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        for file in &self.relevant_files {
+            let file = relative(file);
+            if cited.contains(&file) {
+                continue;
+            }
+            let (dir, name) = match file.rsplit_once('/') {
+                Some((dir, name)) => (dir.to_string(), name.to_string()),
+                None => (String::new(), file.clone()),
+            };
+            match groups.iter_mut().find(|(existing, _)| *existing == dir) {
+                Some((_, names)) if names.contains(&name) => {}
+                Some((_, names)) => names.push(name),
+                None => groups.push((dir, vec![name])),
+            }
+        }
+        let file_lines: Vec<String> = groups
+            .into_iter()
+            .map(|(dir, names)| match dir.as_str() {
+                "" => format!("- {}", names.join(", ")),
+                dir => format!("- {}/: {}", dir, names.join(", ")),
+            })
+            .collect();
+
+        let mut rendered = CODEBASE_HEADER.to_string();
+        let mut added = 0;
+        'sections: for (heading, lines) in [("Semantic matches:", match_lines), ("Relevant files:", file_lines)] {
+            for (i, line) in lines.iter().enumerate() {
+                let extended = if i == 0 {
+                    format!("{}\n{}\n{}", rendered, heading, line)
+                } else {
+                    format!("{}\n{}", rendered, line)
+                };
+                if token_counter.estimate_tokens(&extended) > max_tokens {
+                    break 'sections;
+                }
+                rendered = extended;
+                added += 1;
+            }
+        }
+
+        if added == 0 {
+            return String::new();
+        }
+        rendered
+    }
+}
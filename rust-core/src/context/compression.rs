@@ -1,11 +1,56 @@
 /// Context compression techniques
 
-use crate::context::{Context, Message};
+use crate::context::diff::{diff_contexts, ContextDiff};
+use crate::context::summarizer::ContextSummarizer;
+use crate::context::{Context, Message, Role};
+use crate::error::{OrchestratorError, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// Which part of an over-long message `ContextCompressor` keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TruncationMode {
+    /// First and last halves, e.g. for conversation turns
+    HeadTail,
+    /// The beginning, e.g. for documents
+    HeadOnly,
+    /// The end, e.g. for tool output where errors come last
+    TailOnly,
+    /// First and last parts around an extractive summary of the middle
+    SummaryMiddle,
+}
+
+impl TruncationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TruncationMode::HeadTail => "head_tail",
+            TruncationMode::HeadOnly => "head_only",
+            TruncationMode::TailOnly => "tail_only",
+            TruncationMode::SummaryMiddle => "summary_middle",
+        }
+    }
+    
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "head_tail" => Ok(TruncationMode::HeadTail),
+            "head_only" => Ok(TruncationMode::HeadOnly),
+            "tail_only" => Ok(TruncationMode::TailOnly),
+            "summary_middle" => Ok(TruncationMode::SummaryMiddle),
+            other => Err(OrchestratorError::InvalidInput(format!(
+                "Unknown truncation mode '{}'; expected head_tail, head_only, tail_only or summary_middle",
+                other
+            ))),
+        }
+    }
+}
 
 pub struct ContextCompressor {
     max_message_length: usize,
     remove_comments: bool,
     normalize_whitespace: bool,
+    truncation: TruncationMode, // For roles without their own mode
+    role_truncation: HashMap<Role, TruncationMode>,
 }
 
 impl ContextCompressor {
@@ -14,6 +59,8 @@ impl ContextCompressor {
             max_message_length: 2000,
             remove_comments: false, // Keep comments by default
             normalize_whitespace: true,
+            truncation: TruncationMode::HeadTail,
+            role_truncation: HashMap::new(),
         }
     }
     
@@ -27,15 +74,38 @@ impl ContextCompressor {
         self
     }
     
+    pub fn with_normalize_whitespace(mut self, normalize: bool) -> Self {
+        self.normalize_whitespace = normalize;
+        self
+    }
+    
+    /// Truncate messages of roles without their own mode with `mode`
+    pub fn with_truncation_mode(mut self, mode: TruncationMode) -> Self {
+        self.truncation = mode;
+        self
+    }
+    
+    /// Truncate messages of `role` with `mode`, e.g. tool output with `TailOnly`
+    pub fn with_role_truncation(mut self, role: Role, mode: TruncationMode) -> Self {
+        self.role_truncation.insert(role, mode);
+        self
+    }
+    
+    /// Mode used for messages of `role`
+    pub fn truncation_mode(&self, role: &Role) -> TruncationMode {
+        self.role_truncation.get(role).copied().unwrap_or(self.truncation)
+    }
+    
     /// Compress context by removing redundancy
     pub fn compress(&self, context: &mut Context) -> CompressionStats {
         let original_size = self.estimate_size(context);
+        let mut origins: Vec<usize> = (0..context.messages.len()).collect();
         
         // Remove consecutive duplicate messages
-        let duplicates_removed = self.remove_duplicates(context);
+        let duplicates_removed = self.remove_duplicates(&mut context.messages, &mut origins).len();
         
         // Remove semantically similar messages
-        let similar_removed = self.remove_similar_messages(context);
+        let similar_removed = self.remove_similar_messages(&mut context.messages, &mut origins).len();
         
         // Compress long messages
         for message in &mut context.messages {
@@ -48,32 +118,133 @@ impl ContextCompressor {
         }
         
         let compressed_size = self.estimate_size(context);
-        let compression_ratio = if original_size > 0 {
-            (1.0 - compressed_size as f32 / original_size as f32) * 100.0
-        } else {
-            0.0
-        };
         
         CompressionStats {
             original_size,
             compressed_size,
-            compression_ratio,
+            compression_ratio: compression_ratio(original_size, compressed_size),
             duplicates_removed,
             similar_removed,
         }
     }
     
+    /// `compress`, also returning what it changed
+    pub fn compress_explained(&self, context: &mut Context) -> (CompressionStats, ContextDiff) {
+        let before = context.clone();
+        let stats = self.compress(context);
+        (stats, diff_contexts(&before, context))
+    }
+    
+    /// Describe what `compress` would do without modifying the context
+    pub fn preview(&self, context: &Context) -> CompressionPlan {
+        let original_size = self.estimate_size(context);
+        let message_hashes = context.messages.iter().map(message_hash).collect();
+        
+        let mut messages = context.messages.clone();
+        let mut origins: Vec<usize> = (0..messages.len()).collect();
+        
+        let duplicates = self.remove_duplicates(&mut messages, &mut origins);
+        let similar = self.remove_similar_messages(&mut messages, &mut origins);
+        let mut removals: Vec<PlannedRemoval> = duplicates
+            .iter()
+            .map(|&index| PlannedRemoval { index, reason: RemovalReason::Duplicate })
+            .chain(similar.iter().map(|&index| PlannedRemoval { index, reason: RemovalReason::Similar }))
+            .collect();
+        removals.sort_by_key(|r| r.index);
+        
+        let mut rewrites = Vec::new();
+        for (message, &index) in messages.iter_mut().zip(&origins) {
+            let (mut content, truncated) = self.compressed_text(&message.content, &message.role);
+            if self.normalize_whitespace {
+                content = self.normalize_whitespace_text(&content);
+            }
+            if content != message.content {
+                rewrites.push(PlannedRewrite {
+                    index,
+                    original_length: message.content.len(),
+                    new_length: content.len(),
+                    truncated,
+                    content: content.clone(),
+                });
+                message.content = content;
+            }
+        }
+        
+        let compressed_size: usize = messages.iter().map(|m| m.content.len()).sum();
+        CompressionPlan {
+            message_hashes,
+            removals,
+            rewrites,
+            stats: CompressionStats {
+                original_size,
+                compressed_size,
+                compression_ratio: compression_ratio(original_size, compressed_size),
+                duplicates_removed: duplicates.len(),
+                similar_removed: similar.len(),
+            },
+        }
+    }
+    
+    /// Execute a plan from `preview`
+    ///
+    /// Messages the plan covers must be unchanged (compared by content hash);
+    /// messages appended since the preview are left alone.
+    pub fn apply_plan(&self, context: &mut Context, plan: &CompressionPlan) -> Result<CompressionStats> {
+        if context.messages.len() < plan.message_hashes.len() {
+            return Err(OrchestratorError::ConflictDetected(format!(
+                "compression plan covers {} messages but context has {}",
+                plan.message_hashes.len(),
+                context.messages.len()
+            )));
+        }
+        if let Some(index) = plan
+            .message_hashes
+            .iter()
+            .zip(&context.messages)
+            .position(|(hash, message)| *hash != message_hash(message))
+        {
+            return Err(OrchestratorError::ConflictDetected(format!(
+                "message {} changed since the compression plan was made",
+                index
+            )));
+        }
+        
+        let original_size = self.estimate_size(context);
+        for rewrite in &plan.rewrites {
+            context.messages[rewrite.index].content = rewrite.content.clone();
+        }
+        let removed: HashSet<usize> = plan.removals.iter().map(|r| r.index).collect();
+        let mut index = 0;
+        context.messages.retain(|_| {
+            let keep = !removed.contains(&index);
+            index += 1;
+            keep
+        });
+        
+        let compressed_size = self.estimate_size(context);
+        Ok(CompressionStats {
+            original_size,
+            compressed_size,
+            compression_ratio: compression_ratio(original_size, compressed_size),
+            duplicates_removed: plan.stats.duplicates_removed,
+            similar_removed: plan.stats.similar_removed,
+        })
+    }
+    
     /// Remove duplicate consecutive messages
-    fn remove_duplicates(&self, context: &mut Context) -> usize {
-        let mut removed = 0;
+    ///
+    /// `origins` tracks each message's original index and is kept in step;
+    /// returns the original indices removed.
+    fn remove_duplicates(&self, messages: &mut Vec<Message>, origins: &mut Vec<usize>) -> Vec<usize> {
+        let mut removed = Vec::new();
         let mut i = 0;
-        while i < context.messages.len().saturating_sub(1) {
-            let current = &context.messages[i];
-            let next = &context.messages[i + 1];
+        while i < messages.len().saturating_sub(1) {
+            let current = &messages[i];
+            let next = &messages[i + 1];
             
             if current.role == next.role && current.content == next.content {
-                context.messages.remove(i + 1);
-                removed += 1;
+                messages.remove(i + 1);
+                removed.push(origins.remove(i + 1));
             } else {
                 i += 1;
             }
@@ -82,25 +253,22 @@ impl ContextCompressor {
     }
     
     /// Remove semantically similar messages (simple similarity check)
-    fn remove_similar_messages(&self, context: &mut Context) -> usize {
-        let mut removed = 0;
+    fn remove_similar_messages(&self, messages: &mut Vec<Message>, origins: &mut Vec<usize>) -> Vec<usize> {
+        let mut removed = Vec::new();
         let mut i = 0;
         
-        while i < context.messages.len().saturating_sub(1) {
-            let current = &context.messages[i];
-            let next = &context.messages[i + 1];
+        while i < messages.len().saturating_sub(1) {
+            let current = &messages[i];
+            let next = &messages[i + 1];
             
             // Check if messages are similar (same role and high content similarity)
             if current.role == next.role {
                 let similarity = self.calculate_similarity(&current.content, &next.content);
                 if similarity > 0.8 {
                     // Keep the longer message
-                    if current.content.len() < next.content.len() {
-                        context.messages.remove(i);
-                    } else {
-                        context.messages.remove(i + 1);
-                    }
-                    removed += 1;
+                    let drop = if current.content.len() < next.content.len() { i } else { i + 1 };
+                    messages.remove(drop);
+                    removed.push(origins.remove(drop));
                     continue;
                 }
             }
@@ -132,7 +300,12 @@ impl ContextCompressor {
     
     /// Compress individual message
     fn compress_message(&self, message: &mut Message) {
-        let mut content = message.content.clone();
+        message.content = self.compressed_text(&message.content, &message.role).0;
+    }
+    
+    /// Compressed form of a message body, and whether it was truncated
+    fn compressed_text(&self, content: &str, role: &Role) -> (String, bool) {
+        let mut content = content.to_string();
         
         // Remove comments if enabled
         if self.remove_comments {
@@ -147,14 +320,45 @@ impl ContextCompressor {
         // Compress code blocks (remove extra whitespace but preserve structure)
         content = self.compress_code_blocks(&content);
         
-        // Limit message length (keep first and last parts if too long)
+        // Limit message length, keeping the parts the role's mode asks for
         if content.len() > self.max_message_length {
-            let first_part = &content[..self.max_message_length / 2];
-            let last_part = &content[content.len() - self.max_message_length / 2..];
-            message.content = format!("{}... [truncated {} chars] ...{}", 
-                first_part, content.len() - self.max_message_length, last_part);
+            (self.truncate(&content, self.truncation_mode(role)), true)
         } else {
-            message.content = content;
+            (content, false)
+        }
+    }
+    
+    fn truncate(&self, content: &str, mode: TruncationMode) -> String {
+        // The marker counts against the limit, so truncated text is never truncated again
+        let room = |marker: &str| {
+            self.max_message_length.saturating_sub(marker.len() + content.len().to_string().len())
+        };
+        match mode {
+            TruncationMode::HeadTail => {
+                let max = room("... [truncated  chars] ...");
+                let first_part = head(content, max / 2);
+                let last_part = tail(content, max / 2);
+                format!("{}... [truncated {} chars] ...{}",
+                    first_part, content.len() - first_part.len() - last_part.len(), last_part)
+            }
+            TruncationMode::HeadOnly => {
+                let first_part = head(content, room("... [truncated  chars]"));
+                format!("{}... [truncated {} chars]", first_part, content.len() - first_part.len())
+            }
+            TruncationMode::TailOnly => {
+                let last_part = tail(content, room("[truncated  chars] ..."));
+                format!("[truncated {} chars] ...{}", content.len() - last_part.len(), last_part)
+            }
+            TruncationMode::SummaryMiddle => {
+                // A quarter of the budget goes to the summary
+                let max = room("... [summary of  chars: ] ...");
+                let summary_length = max / 4;
+                let first_part = head(content, (max - summary_length) / 2);
+                let last_part = tail(content, (max - summary_length) / 2);
+                let middle = &content[first_part.len()..content.len() - last_part.len()];
+                let summary = ContextSummarizer::default().summarize_excerpt(middle, summary_length);
+                format!("{}... [summary of {} chars: {}] ...{}", first_part, middle.len(), summary, last_part)
+            }
         }
     }
     
@@ -231,7 +435,7 @@ impl ContextCompressor {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CompressionStats {
     pub original_size: usize,
     pub compressed_size: usize,
@@ -240,6 +444,97 @@ pub struct CompressionStats {
     pub similar_removed: usize,
 }
 
+impl CompressionStats {
+    /// Rough token savings, at the token counter's ~4 characters per token
+    pub fn estimated_tokens_saved(&self) -> usize {
+        self.original_size.saturating_sub(self.compressed_size) / 4
+    }
+}
+
+/// Why a message would be removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalReason {
+    Duplicate, // Identical to the previous message
+    Similar, // Mostly the same words as a neighbour; the longer one is kept
+}
+
+impl RemovalReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RemovalReason::Duplicate => "duplicate",
+            RemovalReason::Similar => "similar",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRemoval {
+    pub index: usize, // Index in the previewed context
+    pub reason: RemovalReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRewrite {
+    pub index: usize, // Index in the previewed context
+    pub original_length: usize,
+    pub new_length: usize,
+    pub truncated: bool, // False when only whitespace or comments change
+    pub content: String,
+}
+
+/// What `compress` would do to a context, as returned by `preview`
+#[derive(Debug, Clone)]
+pub struct CompressionPlan {
+    message_hashes: Vec<u64>, // One per previewed message, checked by `apply_plan`
+    pub removals: Vec<PlannedRemoval>,
+    pub rewrites: Vec<PlannedRewrite>,
+    pub stats: CompressionStats, // Projected
+}
+
+impl CompressionPlan {
+    /// Rewrites that cut a message down to `new_length`
+    pub fn truncations(&self) -> impl Iterator<Item = &PlannedRewrite> {
+        self.rewrites.iter().filter(|r| r.truncated)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.removals.is_empty() && self.rewrites.is_empty()
+    }
+}
+
+/// The longest prefix of `text` of at most `max` bytes ending on a character boundary
+fn head(text: &str, max: usize) -> &str {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// The longest suffix of `text` of at most `max` bytes starting on a character boundary
+fn tail(text: &str, max: usize) -> &str {
+    let mut start = text.len() - max.min(text.len());
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+fn message_hash(message: &Message) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    message.role.as_str().hash(&mut hasher);
+    message.content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn compression_ratio(original_size: usize, compressed_size: usize) -> f32 {
+    if original_size > 0 {
+        (1.0 - compressed_size as f32 / original_size as f32) * 100.0
+    } else {
+        0.0
+    }
+}
+
 impl Default for ContextCompressor {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,272 @@
+/// Diffs between two versions of a context, for debugging window management
+
+use crate::context::token_counter::TokenCounter;
+use crate::context::{Context, Message, Role, ToolCall};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+
+/// Characters of content shown per message by `render_text`
+const PREVIEW_CHARS: usize = 60;
+
+/// Per-message token overhead, as in the window manager's estimate
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// A message present on only one side of a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageRef {
+    pub index: usize, // Index in the context it belongs to
+    pub role: Role,
+    pub content_hash: u64,
+    pub tokens: usize,
+    pub preview: String,
+}
+
+/// A message kept in place but with different content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifiedMessage {
+    pub before_index: usize,
+    pub after_index: usize,
+    pub role: Role,
+    pub before_hash: u64,
+    pub after_hash: u64,
+    pub before_tokens: usize,
+    pub after_tokens: usize,
+}
+
+/// A tool call present on only one side of a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCallRef {
+    pub index: usize,
+    pub tool: String,
+    pub timestamp: i64,
+}
+
+/// What changed between two versions of a context
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextDiff {
+    pub added: Vec<MessageRef>, // Indexes into the after context
+    pub removed: Vec<MessageRef>, // Indexes into the before context
+    pub modified: Vec<ModifiedMessage>,
+    pub unchanged: usize,
+    pub tool_calls_added: Vec<ToolCallRef>,
+    pub tool_calls_removed: Vec<ToolCallRef>,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+}
+
+impl ContextDiff {
+    /// Estimated tokens gained (positive) or saved (negative)
+    pub fn token_delta(&self) -> i64 {
+        self.tokens_after as i64 - self.tokens_before as i64
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.tool_calls_added.is_empty()
+            && self.tool_calls_removed.is_empty()
+    }
+}
+
+/// Compare two versions of a context
+///
+/// Messages are matched by role and content hash along the longest common
+/// subsequence. Unmatched messages between two matches that share role and
+/// timestamp count as modified (truncation and compression keep timestamps);
+/// the rest are removed or added.
+pub fn diff_contexts(before: &Context, after: &Context) -> ContextDiff {
+    let counter = TokenCounter::new();
+    let tokens = |message: &Message| counter.estimate_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS;
+    
+    let before_keys: Vec<(&str, u64)> = before.messages.iter().map(message_key).collect();
+    let after_keys: Vec<(&str, u64)> = after.messages.iter().map(message_key).collect();
+    let matches = common_subsequence(&before_keys, &after_keys);
+    
+    let mut diff = ContextDiff {
+        unchanged: matches.len(),
+        tokens_before: before.messages.iter().map(tokens).sum(),
+        tokens_after: after.messages.iter().map(tokens).sum(),
+        ..Default::default()
+    };
+    
+    // Walk the gaps between matched messages, plus the tail after the last one
+    let mut before_start = 0;
+    let mut after_start = 0;
+    let ends = matches.iter().copied().chain(std::iter::once((before.messages.len(), after.messages.len())));
+    for (before_end, after_end) in ends {
+        let mut added: Vec<usize> = (after_start..after_end).collect();
+        for before_index in before_start..before_end {
+            let old = &before.messages[before_index];
+            let paired = added.iter().position(|&after_index| {
+                let new = &after.messages[after_index];
+                new.role == old.role && new.timestamp == old.timestamp
+            });
+            match paired {
+                Some(position) => {
+                    let after_index = added.remove(position);
+                    let new = &after.messages[after_index];
+                    diff.modified.push(ModifiedMessage {
+                        before_index,
+                        after_index,
+                        role: old.role.clone(),
+                        before_hash: content_hash(&old.content),
+                        after_hash: content_hash(&new.content),
+                        before_tokens: tokens(old),
+                        after_tokens: tokens(new),
+                    });
+                }
+                None => diff.removed.push(message_ref(before_index, old, tokens(old))),
+            }
+        }
+        diff.added.extend(added.into_iter().map(|i| message_ref(i, &after.messages[i], tokens(&after.messages[i]))));
+        
+        before_start = before_end + 1;
+        after_start = after_end + 1;
+    }
+    
+    let before_calls: Vec<u64> = before.tool_history.iter().map(tool_call_hash).collect();
+    let after_calls: Vec<u64> = after.tool_history.iter().map(tool_call_hash).collect();
+    let call_matches = common_subsequence(&before_calls, &after_calls);
+    diff.tool_calls_removed = unmatched(before.tool_history.len(), call_matches.iter().map(|m| m.0))
+        .map(|i| tool_call_ref(i, &before.tool_history[i]))
+        .collect();
+    diff.tool_calls_added = unmatched(after.tool_history.len(), call_matches.iter().map(|m| m.1))
+        .map(|i| tool_call_ref(i, &after.tool_history[i]))
+        .collect();
+    
+    diff
+}
+
+/// Human-readable summary of a diff, one line per change
+pub fn render_text(diff: &ContextDiff) -> String {
+    if diff.is_empty() {
+        return format!("No changes ({} messages, ~{} tokens)\n", diff.unchanged, diff.tokens_before);
+    }
+    
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "~{} -> ~{} tokens ({:+}); {} unchanged, {} removed, {} modified, {} added",
+        diff.tokens_before,
+        diff.tokens_after,
+        diff.token_delta(),
+        diff.unchanged,
+        diff.removed.len(),
+        diff.modified.len(),
+        diff.added.len(),
+    );
+    for removed in &diff.removed {
+        let _ = writeln!(
+            out,
+            "- [{}] {} ({} tokens, {:016x}): {}",
+            removed.index, removed.role.as_str(), removed.tokens, removed.content_hash, removed.preview
+        );
+    }
+    for modified in &diff.modified {
+        let _ = writeln!(
+            out,
+            "~ [{} -> {}] {} ({} -> {} tokens, {:016x} -> {:016x})",
+            modified.before_index,
+            modified.after_index,
+            modified.role.as_str(),
+            modified.before_tokens,
+            modified.after_tokens,
+            modified.before_hash,
+            modified.after_hash
+        );
+    }
+    for added in &diff.added {
+        let _ = writeln!(
+            out,
+            "+ [{}] {} ({} tokens, {:016x}): {}",
+            added.index, added.role.as_str(), added.tokens, added.content_hash, added.preview
+        );
+    }
+    for call in &diff.tool_calls_removed {
+        let _ = writeln!(out, "- tool call [{}] {} at {}", call.index, call.tool, call.timestamp);
+    }
+    for call in &diff.tool_calls_added {
+        let _ = writeln!(out, "+ tool call [{}] {} at {}", call.index, call.tool, call.timestamp);
+    }
+    out
+}
+
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn message_key(message: &Message) -> (&str, u64) {
+    (message.role.as_str(), content_hash(&message.content))
+}
+
+fn tool_call_hash(call: &ToolCall) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    call.tool.hash(&mut hasher);
+    call.timestamp.hash(&mut hasher);
+    call.request.hash(&mut hasher);
+    call.response.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn message_ref(index: usize, message: &Message, tokens: usize) -> MessageRef {
+    let mut preview: String = message.content.chars().take(PREVIEW_CHARS).collect();
+    if message.content.chars().count() > PREVIEW_CHARS {
+        preview.push('…');
+    }
+    MessageRef {
+        index,
+        role: message.role.clone(),
+        content_hash: content_hash(&message.content),
+        tokens,
+        preview: preview.replace('\n', " "),
+    }
+}
+
+fn tool_call_ref(index: usize, call: &ToolCall) -> ToolCallRef {
+    ToolCallRef {
+        index,
+        tool: call.tool.clone(),
+        timestamp: call.timestamp,
+    }
+}
+
+/// Indexes below `len` not in `matched`
+fn unmatched(len: usize, matched: impl Iterator<Item = usize>) -> impl Iterator<Item = usize> {
+    let matched: std::collections::HashSet<usize> = matched.collect();
+    (0..len).filter(move |i| !matched.contains(i))
+}
+
+/// Index pairs of a longest common subsequence, in order
+fn common_subsequence<T: PartialEq>(before: &[T], after: &[T]) -> Vec<(usize, usize)> {
+    let (n, m) = (before.len(), after.len());
+    // lengths[i][j] = LCS length of before[i..] and after[j..]
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if before[i] == after[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if before[i] == after[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
@@ -0,0 +1,148 @@
+/// Preprocessing of new messages before a context is saved
+
+use crate::context::{Context, Message, Role};
+use crate::error::{OrchestratorError, Result};
+use crate::security::validation::{validate_input_with, InputLimits};
+use serde::{Deserialize, Serialize};
+
+/// Characters allowed per user message when no limit is given
+pub const DEFAULT_MAX_MESSAGE_LENGTH: usize = 100_000;
+
+/// What a hook did with a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HookDecision {
+    /// Left unchanged
+    Keep,
+    /// Removed from the context; later hooks do not see it
+    Drop,
+    /// Changed in place
+    Modified,
+}
+
+impl HookDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookDecision::Keep => "keep",
+            HookDecision::Drop => "drop",
+            HookDecision::Modified => "modified",
+        }
+    }
+}
+
+/// A step run on each message added since the context was last saved
+///
+/// Hooks run in registration order. `context` holds the messages before the
+/// one being processed, already through every hook. An error aborts the save
+/// and leaves the context's messages as they were.
+pub trait MessageHook: Send + Sync {
+    /// Name reported in `HookOutcome`
+    fn name(&self) -> &str;
+
+    fn before_save(&self, message: &mut Message, context: &Context) -> Result<HookDecision>;
+}
+
+/// A hook's decision about one message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookOutcome {
+    pub hook: String,
+    /// Index of the message in the context before any was dropped
+    pub message_index: usize,
+    pub decision: HookDecision,
+}
+
+/// Result of `ContextManager::update_context`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateReport {
+    /// Decisions other than `Keep`, in the order they were made
+    pub hook_outcomes: Vec<HookOutcome>,
+    /// New messages that went through the hooks
+    pub messages_processed: usize,
+    pub messages_dropped: usize,
+}
+
+/// Run `hooks` on `context.messages[start..]`, removing dropped messages
+pub(crate) fn run_message_hooks(
+    hooks: &[Box<dyn MessageHook>],
+    context: &mut Context,
+    start: usize,
+) -> Result<UpdateReport> {
+    let mut report = UpdateReport::default();
+    if hooks.is_empty() || start >= context.messages.len() {
+        return Ok(report);
+    }
+
+    let new_messages = context.messages.split_off(start);
+    report.messages_processed = new_messages.len();
+    for (offset, message) in new_messages.iter().enumerate() {
+        let mut message = message.clone();
+        let mut dropped = false;
+        for hook in hooks {
+            let decision = match hook.before_save(&mut message, context) {
+                Ok(decision) => decision,
+                Err(e) => {
+                    context.messages.truncate(start);
+                    context.messages.extend(new_messages);
+                    return Err(e);
+                }
+            };
+            if decision != HookDecision::Keep {
+                report.hook_outcomes.push(HookOutcome {
+                    hook: hook.name().to_string(),
+                    message_index: start + offset,
+                    decision,
+                });
+            }
+            if decision == HookDecision::Drop {
+                dropped = true;
+                break;
+            }
+        }
+        if dropped {
+            report.messages_dropped += 1;
+        } else {
+            context.messages.push(message);
+        }
+    }
+    Ok(report)
+}
+
+/// Rejects user messages that fail `validate_input` and NFC-normalizes the rest
+#[derive(Debug, Clone)]
+pub struct ValidationHook {
+    limits: InputLimits,
+}
+
+impl ValidationHook {
+    /// Allow user messages of up to `max_length` characters
+    pub fn new(max_length: usize) -> Self {
+        Self::with_limits(InputLimits::new(max_length))
+    }
+
+    pub fn with_limits(limits: InputLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl MessageHook for ValidationHook {
+    fn name(&self) -> &str {
+        "validation"
+    }
+
+    fn before_save(&self, message: &mut Message, context: &Context) -> Result<HookDecision> {
+        if message.role != Role::User {
+            return Ok(HookDecision::Keep);
+        }
+        let normalized = validate_input_with(&message.content, &self.limits).map_err(|e| {
+            OrchestratorError::InvalidInput(format!(
+                "User message in conversation {} failed validation: {}",
+                context.conversation_id,
+                e
+            ))
+        })?;
+        if normalized == message.content {
+            return Ok(HookDecision::Keep);
+        }
+        message.content = normalized;
+        Ok(HookDecision::Modified)
+    }
+}
@@ -0,0 +1,124 @@
+/// Message importance scoring shared by summarization and window management
+
+use crate::context::{Message, Role};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Content lengths (bytes) earning a bonus; very short or very long messages
+/// are rarely the ones worth keeping
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LengthBand {
+    pub min: usize, // Exclusive
+    pub max: usize, // Exclusive
+    pub bonus: f32,
+}
+
+impl Default for LengthBand {
+    fn default() -> Self {
+        Self {
+            min: 50,
+            max: 2000,
+            bonus: 0.1,
+        }
+    }
+}
+
+/// Weights for `ImportanceScorer`
+///
+/// Keywords match case-insensitively anywhere in a message, and every
+/// matching keyword adds its weight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportanceConfig {
+    pub role_weights: HashMap<Role, f32>, // Roles missing here score 0.0
+    pub keyword_weights: HashMap<String, f32>,
+    pub code_block_bonus: f32,
+    pub length_band: LengthBand,
+    pub pin_proximity_bonus: f32, // For the messages right before and after a pinned one
+}
+
+impl Default for ImportanceConfig {
+    fn default() -> Self {
+        let role_weights = [(Role::System, 0.5), (Role::User, 0.3), (Role::Assistant, 0.2)]
+            .into_iter()
+            .collect();
+        let keyword_weights = [
+            "decided", "decision", "important", "note", "error", "fix", "solution",
+            "problem", "issue", "bug", "implement", "change", "update", "refactor",
+            "todo", "fixme",
+        ]
+        .into_iter()
+        .map(|keyword| (keyword.to_string(), 0.1))
+        .collect();
+        Self {
+            role_weights,
+            keyword_weights,
+            code_block_bonus: 0.2,
+            length_band: LengthBand::default(),
+            pin_proximity_bonus: 0.2,
+        }
+    }
+}
+
+/// Scores messages from 0.0 to 1.0; pinned messages always score 1.0
+#[derive(Debug, Clone, Default)]
+pub struct ImportanceScorer {
+    config: ImportanceConfig,
+}
+
+impl ImportanceScorer {
+    pub fn new(config: ImportanceConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ImportanceConfig {
+        &self.config
+    }
+
+    /// Score `messages[index]`, counting its pinned neighbours
+    pub fn score(&self, messages: &[Message], index: usize) -> f32 {
+        let message = &messages[index];
+        if message.pinned {
+            return 1.0;
+        }
+
+        let near_pin = index
+            .checked_sub(1)
+            .and_then(|before| messages.get(before))
+            .into_iter()
+            .chain(messages.get(index + 1))
+            .any(|neighbour| neighbour.pinned);
+        let bonus = if near_pin { self.config.pin_proximity_bonus } else { 0.0 };
+
+        (self.score_message(message) + bonus).min(1.0)
+    }
+
+    /// Score one message on its own, ignoring pins around it
+    pub fn score_message(&self, message: &Message) -> f32 {
+        if message.pinned {
+            return 1.0;
+        }
+
+        let mut score = self.config.role_weights.get(&message.role).copied().unwrap_or(0.0);
+
+        let content_lower = message.content.to_lowercase();
+        for (keyword, weight) in &self.config.keyword_weights {
+            if content_lower.contains(&keyword.to_lowercase()) {
+                score += weight;
+            }
+        }
+
+        if message.content.contains("```") {
+            score += self.config.code_block_bonus;
+        }
+
+        let band = &self.config.length_band;
+        let len = message.content.len();
+        if len > band.min && len < band.max {
+            score += band.bonus;
+        }
+
+        score.clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,152 @@
+/// Size limits enforced when contexts are saved
+
+use crate::context::compression::ContextCompressor;
+use crate::context::summarizer::ContextSummarizer;
+use crate::context::window::ContextWindowManager;
+use crate::context::Context;
+use crate::error::{OrchestratorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Bytes kept free under `max_single_message_bytes` for the compressor's truncation marker
+const TRUNCATION_MARKER_ROOM: usize = 64;
+
+/// The measurement a context exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SizeDimension {
+    Messages,
+    /// Serialized JSON size of the whole context
+    TotalBytes,
+    SingleMessageBytes,
+}
+
+impl SizeDimension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SizeDimension::Messages => "messages",
+            SizeDimension::TotalBytes => "total_bytes",
+            SizeDimension::SingleMessageBytes => "single_message_bytes",
+        }
+    }
+}
+
+impl fmt::Display for SizeDimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Upper bounds on a saved context; None leaves a dimension unchecked
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextLimits {
+    pub max_messages: Option<usize>,
+    pub max_total_bytes: Option<usize>,
+    pub max_single_message_bytes: Option<usize>,
+}
+
+impl ContextLimits {
+    pub fn with_max_messages(mut self, max: usize) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    pub fn with_max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    pub fn with_max_single_message_bytes(mut self, max: usize) -> Self {
+        self.max_single_message_bytes = Some(max);
+        self
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_messages.is_none() && self.max_total_bytes.is_none() && self.max_single_message_bytes.is_none()
+    }
+
+    /// Fails with `ContextTooLarge` for the first limit `context` exceeds
+    ///
+    /// Checked in order: single message, message count, total size.
+    pub fn check(&self, context: &Context) -> Result<()> {
+        if self.is_unlimited() {
+            return Ok(());
+        }
+
+        if let Some(max) = self.max_single_message_bytes {
+            let largest = context.messages.iter().map(|m| m.content.len()).max().unwrap_or(0);
+            if largest > max {
+                return Err(OrchestratorError::ContextTooLarge(SizeDimension::SingleMessageBytes, largest, max));
+            }
+        }
+        if let Some(max) = self.max_messages {
+            if context.messages.len() > max {
+                return Err(OrchestratorError::ContextTooLarge(SizeDimension::Messages, context.messages.len(), max));
+            }
+        }
+        if let Some(max) = self.max_total_bytes {
+            let size = serialized_size(context);
+            if size > max {
+                return Err(OrchestratorError::ContextTooLarge(SizeDimension::TotalBytes, size, max));
+            }
+        }
+        Ok(())
+    }
+
+    /// A summarizer that brings the message count under the limits, or None if it cannot help
+    ///
+    /// Over the byte limit, half the messages are summarized.
+    pub(crate) fn summarizer_for(&self, context: &Context) -> Option<ContextSummarizer> {
+        let len = context.messages.len();
+        let mut target = len;
+        if let Some(max) = self.max_messages {
+            target = target.min(max);
+        }
+        if self.max_total_bytes.is_some_and(|max| serialized_size(context) > max) {
+            target = target.min(len / 2);
+        }
+        if len < 2 || target >= len {
+            return None;
+        }
+
+        // Draining n messages leaves len - n + 1, counting the summary message
+        let drain = (len + 1 - target.max(1)).min(len);
+        let ratio = ((drain as f64 + 0.5) / len as f64).min(1.0);
+        Some(ContextSummarizer::new(0, ratio))
+    }
+}
+
+/// Serialized JSON size of a context, as stored
+pub fn serialized_size(context: &Context) -> usize {
+    serde_json::to_vec(context).map(|json| json.len()).unwrap_or(usize::MAX)
+}
+
+/// Pipeline `ContextManager::update_context` runs on an over-limit context in auto-manage mode
+///
+/// Steps run in order until the context fits: the compressor, then
+/// summarization (drained messages are archived), then the window manager.
+/// Messages the window manager drops are not archived.
+pub struct AutoManage {
+    pub compressor: ContextCompressor,
+    pub window_manager: ContextWindowManager,
+    pub model: String, // Window size the window manager trims to
+}
+
+impl AutoManage {
+    /// Compression that truncates messages to fit `limits`, and a gpt-4 sized window
+    pub fn for_limits(limits: &ContextLimits) -> Self {
+        let mut compressor = ContextCompressor::new();
+        if let Some(max) = limits.max_single_message_bytes {
+            compressor = compressor.with_max_length(max.saturating_sub(TRUNCATION_MARKER_ROOM).max(TRUNCATION_MARKER_ROOM));
+        }
+        Self {
+            compressor,
+            window_manager: ContextWindowManager::new(0),
+            model: "gpt-4".to_string(),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+}
@@ -0,0 +1,90 @@
+/// Per-conversation locks for serialized load-modify-save
+
+use crate::error::{OrchestratorError, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+
+/// How long `ConversationLockManager::lock` waits by default
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One async mutex per conversation, created on first use
+///
+/// Locks nobody holds or waits for are evicted on the next `lock` call, so
+/// the map only grows with the number of conversations in use at once.
+/// Clones share the same locks.
+#[derive(Clone)]
+pub struct ConversationLockManager {
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+    timeout: Duration,
+}
+
+/// Holds a conversation's lock until dropped
+pub struct ConversationGuard {
+    conversation_id: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl ConversationGuard {
+    pub fn conversation_id(&self) -> &str {
+        &self.conversation_id
+    }
+}
+
+impl ConversationLockManager {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            locks: Arc::new(Mutex::new(HashMap::new())),
+            timeout,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Wait for a conversation's lock; fails with `Timeout` after the manager's timeout
+    pub async fn lock(&self, conversation_id: &str) -> Result<ConversationGuard> {
+        self.lock_with_timeout(conversation_id, self.timeout).await
+    }
+
+    /// `lock` with its own timeout
+    pub async fn lock_with_timeout(&self, conversation_id: &str, timeout: Duration) -> Result<ConversationGuard> {
+        let mutex = {
+            let mut locks = self.locks.lock().unwrap();
+            // Only the map refers to idle locks; waiters and holders keep a clone
+            locks.retain(|_, mutex| Arc::strong_count(mutex) > 1);
+            locks
+                .entry(conversation_id.to_string())
+                .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+                .clone()
+        };
+
+        let guard = tokio::time::timeout(timeout, mutex.lock_owned()).await.map_err(|_| {
+            OrchestratorError::Timeout(format!(
+                "Lock on conversation {} not acquired within {:?}",
+                conversation_id, timeout
+            ))
+        })?;
+        Ok(ConversationGuard {
+            conversation_id: conversation_id.to_string(),
+            _guard: guard,
+        })
+    }
+
+    /// Conversations with a lock held, awaited or not yet evicted
+    pub fn len(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ConversationLockManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCK_TIMEOUT)
+    }
+}
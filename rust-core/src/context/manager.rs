@@ -1,15 +1,308 @@
-use super::{Context, ContextStorage};
-use crate::error::Result;
+use super::codebase_prompt::CODEBASE_HEADER;
+use super::compression::ContextCompressor;
+use super::hooks::{run_message_hooks, MessageHook, UpdateReport};
+use super::limits::{serialized_size, AutoManage, ContextLimits};
+use super::locks::ConversationLockManager;
+use super::memory::{MemoryStore, MEMORY_HEADER};
+use super::replay::ReplayedContext;
+use super::retention::{Clock, RetentionPolicy, RetentionReport};
+use super::store::ContextStore;
+use super::summarizer::{ContextSummarizer, DrainedSummary};
+use super::token_counter::TokenCounter;
+use super::tool_cache::{request_hash, ToolCallCache};
+use super::window::{ContextWindowManager, WindowOutcome};
+use super::title::title_from_message;
+use super::{ArchivedSummary, CodebaseContext, Context, ContextSummary, Message, Role, WindowState};
+use crate::error::{OrchestratorError, Result};
+use crate::pagination::{decode_cursor, encode_cursor, parse_field, Page};
+use crate::projects::ProjectStore;
+use crate::security::audit::{event_types, AuditLogger};
+use crate::security::authz::{Authorizer, Permission, User};
+use crate::security::prompt_guard::{GuardAction, PromptGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+/// Contexts loaded per page by the batch operations
+const BATCH_PAGE_SIZE: usize = 100;
+
+/// Outcome of a batch operation over stored contexts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchReport {
+    /// Contexts visited
+    pub processed: usize,
+    /// Contexts changed and saved
+    pub modified: usize,
+    /// (conversation_id, error) for contexts that could not be processed
+    pub errors: Vec<(String, String)>,
+}
 
 pub struct ContextManager {
-    storage: ContextStorage,
+    storage: Box<dyn ContextStore>,
+    audit_logger: Option<AuditLogger>,
+    prompt_guard: Option<PromptGuard>,
+    authorizer: Authorizer,
+    limits: ContextLimits,
+    auto_manage: Option<AutoManage>,
+    tool_cache: Option<ToolCallCache>,
+    memory_store: Option<MemoryStore>,
+    message_hooks: Vec<Box<dyn MessageHook>>,
+    project_registry: Option<ProjectStore>, // Strict mode: saved contexts may only name registered projects
+    conversation_locks: ConversationLockManager,
+    serialize_writes: bool, // update_context holds the conversation's lock while saving
+}
+
+/// Response of a tool call made through `record_tool_call_cached`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedToolCall {
+    pub response: String,
+    /// The tool was not invoked; the response came from the cache
+    pub cache_hit: bool,
 }
 
 impl ContextManager {
-    pub fn new(storage: ContextStorage) -> Self {
-        Self { storage }
+    /// Manage contexts kept in `storage`, e.g. `ContextStorage` or `InMemoryContextStore`
+    pub fn new(storage: impl ContextStore + 'static) -> Self {
+        Self {
+            storage: Box::new(storage),
+            audit_logger: None,
+            prompt_guard: None,
+            authorizer: Authorizer::default(),
+            limits: ContextLimits::default(),
+            auto_manage: None,
+            tool_cache: None,
+            memory_store: None,
+            message_hooks: Vec::new(),
+            project_registry: None,
+            conversation_locks: ConversationLockManager::default(),
+            serialize_writes: false,
+        }
+    }
+
+    /// Wait at most `timeout` for a conversation's lock before failing with `Timeout`
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.conversation_locks = ConversationLockManager::new(timeout);
+        self
+    }
+
+    /// Make `update_context` queue behind other writers of the same conversation
+    ///
+    /// Saves still fail with `ConflictDetected` if the context was loaded
+    /// before another writer saved; use `with_conversation_lock` to hold
+    /// the lock from load to save.
+    pub fn with_serialized_writes(mut self, serialize: bool) -> Self {
+        self.serialize_writes = serialize;
+        self
+    }
+
+    /// The locks `with_conversation_lock` and serialized writes take
+    ///
+    /// They are not reentrant: holding a conversation's guard and then
+    /// calling a method that locks the same conversation waits until the
+    /// lock times out.
+    pub fn conversation_locks(&self) -> &ConversationLockManager {
+        &self.conversation_locks
     }
 
+    /// Refuse to save contexts for projects not registered in `projects`
+    ///
+    /// Every path that writes a context checks it, so a context cannot be
+    /// created or moved into an unregistered project.
+    pub fn with_project_registry(mut self, projects: ProjectStore) -> Self {
+        self.project_registry = Some(projects);
+        self
+    }
+
+    /// Record deletions made by this manager in the audit log
+    pub fn with_audit_logger(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Screen user messages and tool output for prompt injection on update
+    pub fn with_prompt_guard(mut self, prompt_guard: PromptGuard) -> Self {
+        self.prompt_guard = Some(prompt_guard);
+        self
+    }
+
+    /// Role mapping and audit trail used by the `_as` operations
+    pub fn with_authorizer(mut self, authorizer: Authorizer) -> Self {
+        self.authorizer = authorizer;
+        self
+    }
+
+    /// Reject saves of contexts over `limits` with `ContextTooLarge`
+    pub fn with_limits(mut self, limits: ContextLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Shrink over-limit contexts with `auto_manage` before saving instead of rejecting them
+    pub fn with_auto_manage(mut self, auto_manage: AutoManage) -> Self {
+        self.auto_manage = Some(auto_manage);
+        self
+    }
+
+    pub fn limits(&self) -> &ContextLimits {
+        &self.limits
+    }
+
+    /// Reuse responses of repeated tool calls in `record_tool_call_cached`
+    pub fn with_tool_cache(mut self, tool_cache: ToolCallCache) -> Self {
+        self.tool_cache = Some(tool_cache);
+        self
+    }
+
+    pub fn tool_cache(&self) -> Option<&ToolCallCache> {
+        self.tool_cache.as_ref()
+    }
+
+    /// Project memories for `inject_memories`
+    pub fn with_memory_store(mut self, memory_store: MemoryStore) -> Self {
+        self.memory_store = Some(memory_store);
+        self
+    }
+
+    pub fn memory_store(&self) -> Option<&MemoryStore> {
+        self.memory_store.as_ref()
+    }
+
+    /// Prepend a system message with the project's most important memories
+    ///
+    /// Memories are taken in importance order until the next would push the
+    /// message past `max_tokens`, so a less important memory never displaces
+    /// a more important one. Memories whose content already appears in a
+    /// message of `context` (from an earlier injection, say) are skipped.
+    /// Nothing is added if no memory fits. Returns how many were injected;
+    /// fails with `InvalidConfig` without a memory store.
+    pub async fn inject_memories(&self, context: &mut Context, project_id: &str, max_tokens: usize) -> Result<usize> {
+        let store = self.memory_store.as_ref().ok_or_else(|| {
+            OrchestratorError::InvalidConfig("No memory store configured".to_string())
+        })?;
+        let counter = TokenCounter::new();
+        let mut content = MEMORY_HEADER.to_string();
+        let mut injected = 0;
+        for memory in store.list(project_id).await? {
+            if context.messages.iter().any(|m| m.content.contains(&memory.content)) {
+                continue;
+            }
+            let extended = format!("{}\n- {}", content, memory.content);
+            if counter.estimate_tokens(&extended) > max_tokens {
+                break;
+            }
+            content = extended;
+            injected += 1;
+        }
+        if injected == 0 {
+            return Ok(0);
+        }
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        context.messages.insert(0, Message {
+            role: Role::System,
+            content,
+            timestamp,
+            pinned: false,
+        });
+        Ok(injected)
+    }
+
+    /// Set the context's codebase context and keep one system message rendering it
+    ///
+    /// The message added by an earlier call is replaced in place instead of
+    /// appending another, and removed if nothing fits in `max_tokens`; see
+    /// `CodebaseContext::render_for_prompt`. Returns whether the context now
+    /// has a codebase message.
+    pub fn attach_codebase_context(&self, context: &mut Context, codebase: CodebaseContext, max_tokens: usize) -> bool {
+        let content = codebase.render_for_prompt(max_tokens, &TokenCounter::new());
+        context.codebase_context = Some(codebase);
+        let existing = context.messages.iter().position(|m| {
+            m.role == Role::System && m.content.starts_with(CODEBASE_HEADER)
+        });
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        match existing {
+            Some(index) if content.is_empty() => {
+                context.messages.remove(index);
+                false
+            }
+            Some(index) => {
+                let message = &mut context.messages[index];
+                if message.content != content {
+                    message.content = content;
+                    message.timestamp = timestamp;
+                }
+                true
+            }
+            None if content.is_empty() => false,
+            None => {
+                context.messages.insert(0, Message {
+                    role: Role::System,
+                    content,
+                    timestamp,
+                    pinned: false,
+                });
+                true
+            }
+        }
+    }
+
+    /// Run `hook` on new messages in `update_context`, after those registered before it
+    pub fn register_message_hook(&mut self, hook: Box<dyn MessageHook>) {
+        self.message_hooks.push(hook);
+    }
+
+    /// `register_message_hook` as a builder
+    pub fn with_message_hook(mut self, hook: Box<dyn MessageHook>) -> Self {
+        self.register_message_hook(hook);
+        self
+    }
+
+    /// Record a tool call in `context`, invoking the tool only on a cache miss
+    ///
+    /// Requests that differ only in whitespace share an entry. Fresh
+    /// responses are cached for `ttl` (None: until evicted); failed
+    /// invocations are neither cached nor recorded. Without a tool cache the
+    /// tool is always invoked. The caller saves the context.
+    pub async fn record_tool_call_cached<F, Fut>(
+        &self,
+        context: &mut Context,
+        tool: &str,
+        request: &str,
+        ttl: Option<Duration>,
+        invoke: F,
+    ) -> Result<CachedToolCall>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        let hash = request_hash(request);
+        if let Some(cache) = &self.tool_cache {
+            if let Some(response) = cache.get(tool, &hash).await? {
+                context.add_tool_call(tool.to_string(), request.to_string(), response.clone());
+                return Ok(CachedToolCall { response, cache_hit: true });
+            }
+        }
+
+        let response = invoke().await?;
+        if let Some(cache) = &self.tool_cache {
+            cache.put(tool, &hash, &response, ttl).await?;
+        }
+        context.add_tool_call(tool.to_string(), request.to_string(), response.clone());
+        Ok(CachedToolCall { response, cache_hit: false })
+    }
+
+    /// Load a context, or create one for `project_id`
+    ///
+    /// With a project registry, creating a context for an unregistered
+    /// project fails with `InvalidInput`.
     pub async fn get_or_create_context(
         &self,
         conversation_id: Option<String>,
@@ -21,17 +314,658 @@ impl ContextManager {
             }
         }
 
-        let context = Context::new(project_id);
+        let mut context = Context::new(project_id);
+        self.ensure_registered_project(&context).await?;
         let id = context.conversation_id.clone();
-        self.storage.save_context(&context).await?;
+        self.storage.save_context(&mut context).await?;
         Ok(context)
     }
 
-    pub async fn update_context(&self, context: &Context) -> Result<()> {
-        self.storage.save_context(context).await
+    /// Save a context; fails with `ConflictDetected` if it changed since loading
+    ///
+    /// With a prompt guard set, user messages and tool responses are scanned
+    /// first: blocked content fails with `InvalidInput` and nothing is saved,
+    /// flagged content is logged and saved.
+    ///
+    /// A context over the manager's limits fails with `ContextTooLarge`
+    /// naming the exceeded dimension, unless auto-manage is on: then it is
+    /// compressed, summarized and windowed until it fits, and only rejected
+    /// if it still does not.
+    ///
+    /// Messages added since the context was last saved first go through the
+    /// registered message hooks; the report lists what they changed or
+    /// dropped.
+    ///
+    /// With serialized writes the conversation's lock is held while saving.
+    pub async fn update_context(&self, context: &mut Context) -> Result<UpdateReport> {
+        let _guard = if self.serialize_writes {
+            Some(self.conversation_locks.lock(&context.conversation_id).await?)
+        } else {
+            None
+        };
+        Ok(self.save_updated(context, None).await?.0)
+    }
+
+    /// Load, modify and save a conversation while holding its lock
+    ///
+    /// Concurrent callers for the same conversation queue instead of
+    /// conflicting; one that waits longer than the lock timeout fails with
+    /// `Timeout`. A conversation not stored yet starts empty. The context is
+    /// saved as by `update_context` unless `modify` fails, so in strict mode
+    /// it is rejected if `modify` sets an unregistered project.
+    pub async fn with_conversation_lock<F, T>(&self, conversation_id: &str, modify: F) -> Result<T>
+    where
+        F: FnOnce(&mut Context) -> Result<T>,
+    {
+        let _guard = self.conversation_locks.lock(conversation_id).await?;
+        let mut context = match self.storage.load_context(conversation_id).await? {
+            Some(context) => context,
+            None => Context {
+                conversation_id: conversation_id.to_string(),
+                ..Context::new(None)
+            },
+        };
+        let output = modify(&mut context)?;
+        self.save_updated(&mut context, None).await?;
+        Ok(output)
+    }
+
+    /// Run the update pipeline and save, archiving `drained` in the same write
+    ///
+    /// Returns the hooks' report and the summary_id of whatever was archived,
+    /// `drained` or a summary made by auto-manage.
+    async fn save_updated(
+        &self,
+        context: &mut Context,
+        mut drained: Option<DrainedSummary>,
+    ) -> Result<(UpdateReport, Option<i64>)> {
+        self.ensure_registered_project(context).await?;
+        let stored = self.load_stored_for_update(context).await?;
+        let report = self.apply_message_hooks(context, stored.as_ref())?;
+        self.guard_context(context, stored.as_ref())?;
+        self.enforce_limits(context, &mut drained).await?;
+        let summary_id = match &drained {
+            Some(drained) => Some(self.storage.save_context_with_archive(context, drained).await?),
+            None => {
+                self.storage.save_context(context).await?;
+                None
+            }
+        };
+        Ok((report, summary_id))
+    }
+
+    /// With a project registry, fail with `InvalidInput` unless `context`'s project is registered
+    async fn ensure_registered_project(&self, context: &Context) -> Result<()> {
+        if let (Some(projects), Some(project_id)) = (&self.project_registry, &context.project_id) {
+            projects.ensure_exists(project_id).await?;
+        }
+        Ok(())
+    }
+
+    /// The stored copy of `context`, if message hooks or the guard need it
+    async fn load_stored_for_update(&self, context: &Context) -> Result<Option<Context>> {
+        if context.version == 0 || (self.message_hooks.is_empty() && self.prompt_guard.is_none()) {
+            return Ok(None);
+        }
+        self.storage.load_context(&context.conversation_id).await
+    }
+
+    fn apply_message_hooks(&self, context: &mut Context, stored: Option<&Context>) -> Result<UpdateReport> {
+        if self.message_hooks.is_empty() {
+            return Ok(UpdateReport::default());
+        }
+        let start = match stored {
+            Some(stored) => new_message_start(stored, context),
+            None => 0,
+        };
+        run_message_hooks(&self.message_hooks, context, start)
+    }
+
+    /// Auto-manage an oversized context; a summary it makes is left in `drained`
+    ///
+    /// Summarizing is skipped when `drained` already holds one, since a save
+    /// archives at most one summary.
+    async fn enforce_limits(&self, context: &mut Context, drained: &mut Option<DrainedSummary>) -> Result<()> {
+        let exceeded = match self.limits.check(context) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        let auto = match &self.auto_manage {
+            Some(auto) => auto,
+            None => return Err(exceeded),
+        };
+
+        let (messages_before, bytes_before) = (context.messages.len(), serialized_size(context));
+        let mut steps = Vec::new();
+
+        let stats = auto.compressor.compress(context);
+        if stats.compressed_size != stats.original_size || stats.duplicates_removed + stats.similar_removed > 0 {
+            steps.push(format!(
+                "compressed messages from {} to {} bytes",
+                stats.original_size, stats.compressed_size
+            ));
+        }
+
+        if self.limits.check(context).is_err() && drained.is_none() {
+            if let Some(summarizer) = self.limits.summarizer_for(context) {
+                if let Some(summary) = summarizer.summarize_and_drain(context) {
+                    steps.push(format!("summarized {} messages to archive on save", summary.messages.len()));
+                    *drained = Some(summary);
+                }
+            }
+        }
+
+        if self.limits.check(context).is_err() {
+            let before = context.messages.len();
+            auto.window_manager.manage_context(context, &auto.model);
+            steps.push(format!(
+                "trimmed to the {} window ({} -> {} messages)",
+                auto.model,
+                before,
+                context.messages.len()
+            ));
+        }
+
+        tracing::info!(
+            conversation_id = %context.conversation_id,
+            "Auto-managed oversized context ({} messages, {} bytes -> {} messages, {} bytes): {}",
+            messages_before,
+            bytes_before,
+            context.messages.len(),
+            serialized_size(context),
+            steps.join("; ")
+        );
+        self.limits.check(context)
+    }
+
+    /// Screen what was added since `stored` was saved; older content already passed
+    fn guard_context(&self, context: &Context, stored: Option<&Context>) -> Result<()> {
+        let guard = match &self.prompt_guard {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+        let (message_start, call_start) = match stored {
+            Some(stored) => {
+                let new_calls = unshared_tail(&stored.tool_history, &context.tool_history, |a, b| {
+                    a.tool == b.tool && a.timestamp == b.timestamp && a.request == b.request && a.response == b.response
+                });
+                (new_message_start(stored, context), context.tool_history.len() - new_calls.len())
+            }
+            None => (0, 0),
+        };
+
+        let messages = context
+            .messages
+            .iter()
+            .enumerate()
+            .skip(message_start)
+            .filter(|(_, m)| matches!(m.role, Role::User | Role::Tool | Role::Other(_)))
+            .map(|(i, m)| (format!("message {}", i), m.content.as_str()));
+        let tool_calls = context
+            .tool_history
+            .iter()
+            .enumerate()
+            .skip(call_start)
+            .map(|(i, call)| (format!("tool call {} ({})", i, call.tool), call.response.as_str()));
+
+        for (source, text) in messages.chain(tool_calls) {
+            let verdict = guard.check(text);
+            let kinds: Vec<&str> = verdict.findings.iter().map(|f| f.kind.as_str()).collect();
+            match verdict.action {
+                GuardAction::Block => {
+                    return Err(OrchestratorError::InvalidInput(format!(
+                        "Prompt injection blocked in {} of conversation {}: {}",
+                        source,
+                        context.conversation_id,
+                        kinds.join(", ")
+                    )));
+                }
+                GuardAction::Flag => {
+                    tracing::warn!(
+                        conversation_id = %context.conversation_id,
+                        "Possible prompt injection in {}: {}",
+                        source,
+                        kinds.join(", ")
+                    );
+                }
+                GuardAction::Allow => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Save a context, merging in concurrent changes on conflict
+    ///
+    /// Messages and tool calls are append-only, so on conflict the latest
+    /// stored context is reloaded and whatever `context` added beyond their
+    /// shared history is appended to it before retrying. Gives up after
+    /// `max_attempts` conflicts.
+    pub async fn update_context_with_retry(&self, context: &mut Context, max_attempts: u32) -> Result<()> {
+        self.ensure_registered_project(context).await?;
+        let mut attempt = 1;
+        loop {
+            match self.storage.save_context(context).await {
+                Err(OrchestratorError::ConflictDetected(_)) if attempt < max_attempts => {
+                    attempt += 1;
+                    let mut latest = match self.storage.load_context(&context.conversation_id).await? {
+                        Some(latest) => latest,
+                        None => {
+                            // Deleted meanwhile; save as a new context
+                            context.version = 0;
+                            continue;
+                        }
+                    };
+
+                    let new_messages = unshared_tail(&latest.messages, &context.messages, |a, b| {
+                        a.role == b.role && a.content == b.content && a.timestamp == b.timestamp
+                    });
+                    latest.messages.extend_from_slice(new_messages);
+                    let new_tool_calls = unshared_tail(&latest.tool_history, &context.tool_history, |a, b| {
+                        a.tool == b.tool && a.timestamp == b.timestamp && a.request == b.request
+                    });
+                    latest.tool_history.extend_from_slice(new_tool_calls);
+                    if context.project_id.is_some() {
+                        latest.project_id = context.project_id.clone();
+                    }
+                    if context.codebase_context.is_some() {
+                        latest.codebase_context = context.codebase_context.clone();
+                    }
+
+                    *context = latest;
+                }
+                result => return result,
+            }
+        }
     }
 
     pub async fn get_context(&self, conversation_id: &str) -> Result<Option<Context>> {
         self.storage.load_context(conversation_id).await
     }
+
+    /// What the conversation looked like at `timestamp` (Unix seconds), for debugging
+    ///
+    /// Fails with `InvalidInput` if the conversation is not stored. The
+    /// result says whether it is exact or rebuilt by timestamp; see
+    /// `replay::reconstruct_at` for what a rebuild cannot recover.
+    pub async fn replay(&self, conversation_id: &str, timestamp: i64) -> Result<ReplayedContext> {
+        self.storage
+            .load_context_at(conversation_id, timestamp)
+            .await?
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!(
+                "Conversation {} not found",
+                conversation_id
+            )))
+    }
+
+    pub async fn delete_context(&self, conversation_id: &str) -> Result<bool> {
+        self.storage.delete_context(conversation_id).await
+    }
+
+    /// Delete a context on behalf of `user`, who needs `DeleteContext`
+    pub async fn delete_context_as(&self, user: &User, conversation_id: &str) -> Result<bool> {
+        self.authorizer
+            .authorize(user, Permission::DeleteContext, "context", conversation_id)
+            .await?;
+        self.delete_context(conversation_id).await
+    }
+
+    /// Summarize the context if it is long enough, archiving what was replaced
+    ///
+    /// The drained messages are archived in the same write that saves the
+    /// summarized context, so the originals are never lost and a failed save
+    /// leaves no archive behind. `context` is left untouched if the write
+    /// fails. Returns the archive's summary_id, or None if the context was
+    /// below the summarizer's threshold.
+    pub async fn summarize_and_archive(
+        &self,
+        context: &mut Context,
+        summarizer: &ContextSummarizer,
+    ) -> Result<Option<i64>> {
+        let mut summarized = context.clone();
+        let drained = match summarizer.summarize_and_drain(&mut summarized) {
+            Some(drained) => drained,
+            None => return Ok(None),
+        };
+
+        let _guard = if self.serialize_writes {
+            Some(self.conversation_locks.lock(&summarized.conversation_id).await?)
+        } else {
+            None
+        };
+        let (_, summary_id) = self.save_updated(&mut summarized, Some(drained)).await?;
+
+        *context = summarized;
+        Ok(summary_id)
+    }
+
+    /// Window management history of a stored conversation; None if never managed
+    pub async fn get_window_state(&self, conversation_id: &str) -> Result<Option<WindowState>> {
+        Ok(self.load_existing(conversation_id).await?.window_state)
+    }
+
+    /// Fit `context` to `model`'s window with `window_manager`; the caller saves it
+    ///
+    /// Unlike `ContextWindowManager::manage_context`, restoration is only
+    /// suggested when the conversation has archived messages to restore.
+    pub async fn manage_window(
+        &self,
+        context: &mut Context,
+        window_manager: &ContextWindowManager,
+        model: &str,
+    ) -> Result<WindowOutcome> {
+        let mut outcome = window_manager.manage_context(context, model);
+        if outcome.suggest_restore {
+            outcome.suggest_restore = !self.storage.load_archive(&context.conversation_id, None).await?.is_empty();
+        }
+        Ok(outcome)
+    }
+
+    /// Archived summaries for a conversation, oldest first; all of them if `summary_id` is None
+    pub async fn get_archived_messages(
+        &self,
+        conversation_id: &str,
+        summary_id: Option<i64>,
+    ) -> Result<Vec<ArchivedSummary>> {
+        self.storage.load_archive(conversation_id, summary_id).await
+    }
+
+    /// Put archived messages back in place of their summary message and save
+    ///
+    /// Summaries of summaries are restored one level at a time: restoring the
+    /// newest archive brings back the previous summary message, which can then
+    /// be restored in turn.
+    pub async fn restore_from_archive(&self, conversation_id: &str, summary_id: i64) -> Result<Context> {
+        let archived = self
+            .storage
+            .load_archive(conversation_id, Some(summary_id))
+            .await?
+            .pop()
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!(
+                "No archived summary {} for conversation {}",
+                summary_id, conversation_id
+            )))?;
+        let mut context = self.load_existing(conversation_id).await?;
+
+        let position = context
+            .messages
+            .iter()
+            .position(|m| *m == archived.summary_message)
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!(
+                "Summary {} is no longer in conversation {}",
+                summary_id, conversation_id
+            )))?;
+        context.messages.splice(position..=position, archived.messages);
+
+        self.update_context(&mut context).await?;
+        Ok(context)
+    }
+
+    /// Title the conversation after its first user message and save it
+    ///
+    /// Only runs when called; a title set with `set_title` is returned as is.
+    /// Returns the conversation's title afterwards, which stays None if there
+    /// is no user message to derive one from.
+    pub async fn generate_title(&self, conversation_id: &str) -> Result<Option<String>> {
+        let mut context = self.load_existing(conversation_id).await?;
+        if context.title_user_set {
+            return Ok(context.title);
+        }
+
+        let title = context
+            .messages
+            .iter()
+            .find(|m| m.role == Role::User)
+            .and_then(|m| title_from_message(&m.content));
+        if title.is_some() && title != context.title {
+            context.title = title;
+            self.update_context(&mut context).await?;
+        }
+        Ok(context.title)
+    }
+
+    /// Set a title that `generate_title` will not replace
+    pub async fn set_title(&self, conversation_id: &str, title: &str) -> Result<()> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(OrchestratorError::InvalidInput("Title must not be empty".to_string()));
+        }
+
+        let mut context = self.load_existing(conversation_id).await?;
+        context.title = Some(title.to_string());
+        context.title_user_set = true;
+        self.update_context(&mut context).await?;
+        Ok(())
+    }
+
+    /// List contexts most recently updated first, one page at a time
+    ///
+    /// With `labels_match`, only contexts carrying all of those labels are listed.
+    pub async fn list_contexts(
+        &self,
+        project_id: Option<&str>,
+        labels_match: Option<&HashMap<String, String>>,
+        page_size: usize,
+        cursor: Option<String>,
+    ) -> Result<Page<ContextSummary>> {
+        const KIND: &str = "contexts";
+
+        let after = match cursor.as_deref() {
+            Some(cursor) => {
+                let fields = decode_cursor(KIND, cursor, 2)?;
+                Some((parse_field::<i64>(KIND, &fields[0])?, fields[1].clone()))
+            }
+            None => None,
+        };
+
+        let items = self
+            .storage
+            .list_contexts_page(
+                project_id,
+                labels_match,
+                page_size,
+                after.as_ref().map(|(updated_at, id)| (*updated_at, id.as_str())),
+            )
+            .await?;
+
+        Ok(Page::from_items(items, page_size, |c| {
+            encode_cursor(KIND, &[c.updated_at.to_string(), c.conversation_id.clone()])
+        }))
+    }
+
+    /// Delete contexts that have been inactive longer than the policy allows
+    ///
+    /// Contexts in legal-hold projects are counted but never deleted. In dry-run
+    /// mode nothing is deleted or audited; the report lists what would go.
+    pub async fn apply_retention(
+        &self,
+        policy: &RetentionPolicy,
+        clock: &dyn Clock,
+    ) -> Result<RetentionReport> {
+        let now = clock.now();
+        let mut report = RetentionReport {
+            dry_run: policy.dry_run,
+            ..Default::default()
+        };
+
+        for (conversation_id, project_id, updated_at) in self.storage.list_contexts().await? {
+            report.examined += 1;
+            let project_id = project_id.as_deref();
+
+            if !policy.is_expired(project_id, updated_at, now) {
+                continue;
+            }
+            if policy.is_held(project_id) {
+                report.held += 1;
+                continue;
+            }
+
+            if !policy.dry_run {
+                if !self.storage.delete_context(&conversation_id).await? {
+                    continue;
+                }
+                if let Some(audit_logger) = &self.audit_logger {
+                    audit_logger
+                        .log_event(
+                            event_types::RESOURCE_DELETE,
+                            Some("context"),
+                            Some(&conversation_id),
+                            serde_json::json!({
+                                "reason": "retention",
+                                "project_id": project_id,
+                                "ttl_days": policy.ttl_days(project_id),
+                                "last_updated_at": updated_at,
+                            }),
+                        )
+                        .await?;
+                }
+            }
+
+            report.deleted += 1;
+            report.deleted_ids.push(conversation_id);
+        }
+
+        Ok(report)
+    }
+
+    /// Compress every context of a project (all projects with None), saving those that changed
+    ///
+    /// Visits at most `limit` contexts, most recently updated first. A context
+    /// that fails to save is reported in `errors` and the batch carries on;
+    /// `progress` is called with the running report after each page.
+    pub async fn compress_all(
+        &self,
+        project_id: Option<&str>,
+        compressor: &ContextCompressor,
+        limit: Option<usize>,
+        progress: Option<&mut (dyn FnMut(&BatchReport) + Send)>,
+    ) -> Result<BatchReport> {
+        self.batch(project_id, limit, progress, |mut context| async move {
+            let before = context.messages.clone();
+            compressor.compress(&mut context);
+            if context.messages == before {
+                return Ok(false);
+            }
+            self.update_context(&mut context).await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// `summarize_and_archive` every context of a project that is over the summarizer's threshold
+    ///
+    /// Paged, limited and reported like `compress_all`.
+    pub async fn summarize_all(
+        &self,
+        project_id: Option<&str>,
+        summarizer: &ContextSummarizer,
+        limit: Option<usize>,
+        progress: Option<&mut (dyn FnMut(&BatchReport) + Send)>,
+    ) -> Result<BatchReport> {
+        self.batch(project_id, limit, progress, |mut context| async move {
+            Ok(self.summarize_and_archive(&mut context, summarizer).await?.is_some())
+        })
+        .await
+    }
+
+    /// Call `visit` on every context of a project, saving those it reports as changed
+    pub async fn for_each_context<F>(&self, project_id: Option<&str>, mut visit: F) -> Result<BatchReport>
+    where
+        F: FnMut(&mut Context) -> Result<bool>,
+    {
+        self.batch(project_id, None, None, |mut context| {
+            let changed = visit(&mut context);
+            async move {
+                if !changed? {
+                    return Ok(false);
+                }
+                self.update_context(&mut context).await?;
+                Ok(true)
+            }
+        })
+        .await
+    }
+
+    /// Page through contexts, applying `step` to each; `step` returns whether it saved a change
+    ///
+    /// Listing failures abort the batch; failures of `step` or of loading one
+    /// context are recorded in the report. Saving bumps `updated_at`, moving a
+    /// context ahead of the cursor, so none is visited twice.
+    async fn batch<F, Fut>(
+        &self,
+        project_id: Option<&str>,
+        limit: Option<usize>,
+        mut progress: Option<&mut (dyn FnMut(&BatchReport) + Send)>,
+        mut step: F,
+    ) -> Result<BatchReport>
+    where
+        F: FnMut(Context) -> Fut,
+        Fut: Future<Output = Result<bool>>,
+    {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut report = BatchReport::default();
+        let mut after: Option<(i64, String)> = None;
+
+        while report.processed < limit {
+            let page = self
+                .storage
+                .list_contexts_page(
+                    project_id,
+                    None,
+                    BATCH_PAGE_SIZE.min(limit - report.processed),
+                    after.as_ref().map(|(updated_at, id)| (*updated_at, id.as_str())),
+                )
+                .await?;
+            let last = match page.last() {
+                Some(last) => (last.updated_at, last.conversation_id.clone()),
+                None => break,
+            };
+
+            for summary in page {
+                report.processed += 1;
+                let outcome = match self.storage.load_context(&summary.conversation_id).await {
+                    Ok(Some(context)) => step(context).await,
+                    // Deleted since it was listed
+                    Ok(None) => Ok(false),
+                    Err(e) => Err(e),
+                };
+                match outcome {
+                    Ok(true) => report.modified += 1,
+                    Ok(false) => {}
+                    Err(e) => report.errors.push((summary.conversation_id, e.to_string())),
+                }
+            }
+
+            if let Some(progress) = progress.as_mut() {
+                progress(&report);
+            }
+            after = Some(last);
+        }
+
+        Ok(report)
+    }
+
+    async fn load_existing(&self, conversation_id: &str) -> Result<Context> {
+        self.storage
+            .load_context(conversation_id)
+            .await?
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!(
+                "Conversation {} not found",
+                conversation_id
+            )))
+    }
+}
+
+/// Index of the first message in `context` that `stored` does not have
+fn new_message_start(stored: &Context, context: &Context) -> usize {
+    context.messages.len() - unshared_tail(&stored.messages, &context.messages, |a, b| a == b).len()
+}
+
+/// Items of `ours` after the longest prefix it shares with `theirs`
+fn unshared_tail<'a, T>(theirs: &[T], ours: &'a [T], same: impl Fn(&T, &T) -> bool) -> &'a [T] {
+    let shared = theirs
+        .iter()
+        .zip(ours)
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    &ours[shared..]
 }
@@ -0,0 +1,207 @@
+/// Long-term facts about a project, kept across its conversations
+
+use super::retention::{Clock, SystemClock};
+use crate::error::{OrchestratorError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePool;
+use std::sync::Arc;
+
+/// Heading of the system message `ContextManager::inject_memories` adds
+pub const MEMORY_HEADER: &str = "Project memories:";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Memory {
+    pub project_id: String,
+    pub key: String, // Unique per project; upserting the same key replaces the memory
+    pub content: String,
+    pub importance: i64, // Higher is injected first
+    pub created_at: i64, // Unix seconds
+    pub expires_at: Option<i64>, // Unix seconds; None never expires
+}
+
+impl Memory {
+    pub fn new(project_id: impl Into<String>, key: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            project_id: project_id.into(),
+            key: key.into(),
+            content: content.into(),
+            importance: 0,
+            created_at: SystemClock.now(),
+            expires_at: None,
+        }
+    }
+
+    pub fn with_importance(mut self, importance: i64) -> Self {
+        self.importance = importance;
+        self
+    }
+
+    pub fn with_expires_at(mut self, expires_at: i64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+type MemoryRow = (String, String, String, i64, i64, Option<i64>);
+
+fn memory_from_row((project_id, key, content, importance, created_at, expires_at): MemoryRow) -> Memory {
+    Memory {
+        project_id,
+        key,
+        content,
+        importance,
+        created_at,
+        expires_at,
+    }
+}
+
+/// The `memories` table
+///
+/// Expired memories are never returned; `purge_expired` deletes them.
+#[derive(Clone)]
+pub struct MemoryStore {
+    pool: SqlitePool,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryStore {
+    /// Use an existing pool, creating the memories table if needed
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memories (
+                project_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                content TEXT NOT NULL,
+                importance INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL,
+                expires_at INTEGER,
+                PRIMARY KEY (project_id, key)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(Self {
+            pool,
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Time source for expiry; `MockClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Insert `memory`, or replace the one with the same project and key
+    ///
+    /// A replaced memory keeps its original `created_at`. Fails with
+    /// `InvalidInput` if the key or content is empty.
+    pub async fn upsert(&self, memory: &Memory) -> Result<()> {
+        if memory.key.trim().is_empty() || memory.content.trim().is_empty() {
+            return Err(OrchestratorError::InvalidInput(
+                "Memory key and content must not be empty".to_string(),
+            ));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO memories (project_id, key, content, importance, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(project_id, key) DO UPDATE SET
+                content = excluded.content,
+                importance = excluded.importance,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(&memory.project_id)
+        .bind(&memory.key)
+        .bind(&memory.content)
+        .bind(memory.importance)
+        .bind(memory.created_at)
+        .bind(memory.expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, project_id: &str, key: &str) -> Result<Option<Memory>> {
+        let row = sqlx::query_as::<_, MemoryRow>(
+            r#"
+            SELECT project_id, key, content, importance, created_at, expires_at FROM memories
+            WHERE project_id = ? AND key = ? AND (expires_at IS NULL OR expires_at > ?)
+            "#,
+        )
+        .bind(project_id)
+        .bind(key)
+        .bind(self.clock.now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(row.map(memory_from_row))
+    }
+
+    /// A project's memories, most important first, then by key
+    pub async fn list(&self, project_id: &str) -> Result<Vec<Memory>> {
+        let rows = sqlx::query_as::<_, MemoryRow>(
+            r#"
+            SELECT project_id, key, content, importance, created_at, expires_at FROM memories
+            WHERE project_id = ? AND (expires_at IS NULL OR expires_at > ?)
+            ORDER BY importance DESC, key
+            "#,
+        )
+        .bind(project_id)
+        .bind(self.clock.now())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(rows.into_iter().map(memory_from_row).collect())
+    }
+
+    /// Whether a memory was deleted
+    pub async fn delete(&self, project_id: &str, key: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM memories WHERE project_id = ? AND key = ?")
+            .bind(project_id)
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Memories whose key or content contains every word of `query`, ignoring case
+    ///
+    /// Ordered as `list`; at most `limit` are returned. A query without
+    /// words matches every memory.
+    pub async fn search(&self, project_id: &str, query: &str, limit: usize) -> Result<Vec<Memory>> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut matches = self.list(project_id).await?;
+        matches.retain(|memory| {
+            let text = format!("{}\n{}", memory.key, memory.content).to_lowercase();
+            terms.iter().all(|term| text.contains(term.as_str()))
+        });
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    /// Delete every expired memory; returns how many were deleted
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM memories WHERE expires_at IS NOT NULL AND expires_at <= ?")
+            .bind(self.clock.now())
+            .execute(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+        Ok(result.rows_affected())
+    }
+}
@@ -1,21 +1,51 @@
 pub mod manager;
 pub mod storage;
+pub mod store;
 pub mod token_counter;
 pub mod summarizer;
 pub mod window;
 pub mod compression;
+pub mod diff;
+pub mod retention;
+pub mod replay;
+pub mod role;
+pub mod title;
+pub mod limits;
+pub mod tool_cache;
+pub mod hooks;
+pub mod memory;
+pub mod importance;
+pub mod locks;
+pub mod codebase_prompt;
 
-pub use manager::ContextManager;
-pub use storage::ContextStorage;
+pub use manager::{BatchReport, CachedToolCall, ContextManager};
+pub use limits::{AutoManage, ContextLimits, SizeDimension};
+pub use storage::{ArchivedSummary, ContextStorage, ContextSummary};
+pub use store::{ContextStore, InMemoryContextStore};
+pub use role::Role;
+pub use window::WindowOutcome;
+pub use tool_cache::{request_hash, ToolCallCache};
+pub use hooks::{HookDecision, HookOutcome, MessageHook, UpdateReport, ValidationHook};
+pub use diff::{diff_contexts, render_text, ContextDiff};
+pub use retention::{Clock, MockClock, RetentionPolicy, RetentionReport, SystemClock};
+pub use replay::{ReplayFidelity, ReplayedContext};
+pub use memory::{Memory, MemoryStore};
+pub use importance::{ImportanceConfig, ImportanceScorer, LengthBand};
+pub use locks::{ConversationGuard, ConversationLockManager};
+pub use codebase_prompt::CODEBASE_HEADER;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
-    pub role: String,
+    pub role: Role,
     pub content: String,
     pub timestamp: i64,
+    /// Always scored as important; neighbours get `ImportanceConfig::pin_proximity_bonus`
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,9 +55,33 @@ pub struct Context {
     pub messages: Vec<Message>,
     pub codebase_context: Option<CodebaseContext>,
     pub tool_history: Vec<ToolCall>,
+    /// Stored version this context was loaded at; 0 if never saved
+    #[serde(default)]
+    pub version: i64,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Title was set explicitly; generated titles never replace it
+    #[serde(default)]
+    pub title_user_set: bool,
+    /// Free-form labels (team, feature, environment); see `crate::labels`
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// What the window manager has done to this conversation; None until it first runs
+    #[serde(default)]
+    pub window_state: Option<WindowState>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Window management history of a conversation, kept across model switches
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub last_model: Option<String>,
+    pub last_window: usize, // Tokens
+    /// Messages summarized or truncated away, over every `manage_context` call
+    pub dropped_messages: usize,
+    pub last_summary_at: Option<i64>, // Unix seconds
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CodebaseContext {
     pub relevant_files: Vec<String>,
     pub semantic_matches: Vec<String>,
@@ -39,6 +93,9 @@ pub struct ToolCall {
     pub timestamp: i64,
     pub request: String,
     pub response: String,
+    /// Request the call was made for; see `observability::request_id`
+    #[serde(default)]
+    pub request_id: Option<String>,
 }
 
 impl Context {
@@ -49,10 +106,15 @@ impl Context {
             messages: Vec::new(),
             codebase_context: None,
             tool_history: Vec::new(),
+            version: 0,
+            title: None,
+            title_user_set: false,
+            labels: HashMap::new(),
+            window_state: None,
         }
     }
 
-    pub fn add_message(&mut self, role: String, content: String) {
+    pub fn add_message(&mut self, role: Role, content: String) {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -62,10 +124,75 @@ impl Context {
             role,
             content,
             timestamp,
+            pinned: false,
         });
     }
 
+    /// Pin or unpin the message at `index`; false if there is none
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> bool {
+        match self.messages.get_mut(index) {
+            Some(message) => {
+                message.pinned = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add a message with a free-form role, normalizing it first
+    pub fn add_message_str(&mut self, role: &str, content: String) -> crate::error::Result<()> {
+        self.add_message(Role::parse(role)?, content);
+        Ok(())
+    }
+
+    /// Append only the messages not already at the tail of the history
+    ///
+    /// Callers often send back the full message list they were given, so the
+    /// longest prefix of `incoming` that matches the end of the stored history
+    /// (role, content and timestamp) is skipped. A timestamp of 0 means
+    /// "unknown" and matches any stored timestamp; such messages are stamped
+    /// with the current time when appended. Returns the number appended.
+    pub fn reconcile_messages(&mut self, incoming: Vec<Message>) -> usize {
+        let same = |stored: &Message, new: &Message| {
+            stored.role == new.role
+                && stored.content == new.content
+                && (new.timestamp == 0 || stored.timestamp == new.timestamp)
+        };
+        
+        let max_overlap = self.messages.len().min(incoming.len());
+        let overlap = (1..=max_overlap)
+            .rev()
+            .find(|&k| {
+                self.messages[self.messages.len() - k..]
+                    .iter()
+                    .zip(&incoming[..k])
+                    .all(|(stored, new)| same(stored, new))
+            })
+            .unwrap_or(0);
+        
+        let appended = incoming.len() - overlap;
+        for message in incoming.into_iter().skip(overlap) {
+            if message.timestamp == 0 {
+                self.add_message(message.role, message.content);
+            } else {
+                self.messages.push(message);
+            }
+        }
+        appended
+    }
+
     pub fn add_tool_call(&mut self, tool: String, request: String, response: String) {
+        self.add_tool_call_for_request(tool, request, response, None);
+    }
+
+    /// `add_tool_call` tagged with the request it was made for
+    pub fn add_tool_call_for_request(
+        &mut self,
+        tool: String,
+        request: String,
+        response: String,
+        request_id: Option<String>,
+    ) {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -76,6 +203,7 @@ impl Context {
             timestamp,
             request,
             response,
+            request_id,
         });
     }
 }
@@ -0,0 +1,75 @@
+/// Reconstruction of a conversation as it stood at an earlier time
+
+use super::{ArchivedSummary, Context};
+
+/// How faithfully a replayed context matches what was stored at the time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayFidelity {
+    /// The context was not saved again after the requested time; it is returned as stored
+    Exact,
+    /// Rebuilt from the current context by timestamp; see `reconstruct_at`
+    Reconstructed,
+}
+
+impl ReplayFidelity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplayFidelity::Exact => "exact",
+            ReplayFidelity::Reconstructed => "reconstructed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayedContext {
+    pub context: Context,
+    pub fidelity: ReplayFidelity,
+    pub timestamp: i64, // The requested time, Unix seconds
+}
+
+/// `context`, last saved at `updated_at`, as it stood at `timestamp`
+///
+/// Contexts are stored as one blob that each save overwrites, so an older
+/// state is rebuilt from the current one: summaries archived after
+/// `timestamp` are undone, newest first, and then messages and tool calls
+/// stamped after it are dropped.
+///
+/// Fidelity caveat: the blob keeps no history of its other fields. The
+/// codebase context, title, labels and window state are the latest stored,
+/// which may postdate `timestamp`, and messages edited or removed in place
+/// (rather than through an archived summary) cannot be recovered. A
+/// context saved at or before `timestamp` is returned unchanged and marked
+/// `Exact`.
+pub fn reconstruct_at(
+    context: Context,
+    updated_at: i64,
+    archives: &[ArchivedSummary],
+    timestamp: i64,
+) -> ReplayedContext {
+    if updated_at <= timestamp {
+        return ReplayedContext {
+            context,
+            fidelity: ReplayFidelity::Exact,
+            timestamp,
+        };
+    }
+
+    let mut context = context;
+    let mut later: Vec<&ArchivedSummary> = archives.iter().filter(|a| a.created_at > timestamp).collect();
+    later.sort_by_key(|a| std::cmp::Reverse(a.summary_id));
+    for archived in later {
+        // A summary no longer in the conversation was itself summarized; the
+        // newer archive undone before it has put it back if so
+        if let Some(position) = context.messages.iter().position(|m| *m == archived.summary_message) {
+            context.messages.splice(position..=position, archived.messages.iter().cloned());
+        }
+    }
+
+    context.messages.retain(|m| m.timestamp <= timestamp);
+    context.tool_history.retain(|call| call.timestamp <= timestamp);
+    ReplayedContext {
+        context,
+        fidelity: ReplayFidelity::Reconstructed,
+        timestamp,
+    }
+}
@@ -0,0 +1,120 @@
+/// Retention policies for stored conversation contexts
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Source of the current time in Unix seconds
+pub trait Clock: Send + Sync {
+    fn now(&self) -> i64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+}
+
+/// Manually controlled clock for tests and simulations
+pub struct MockClock {
+    now: AtomicI64,
+}
+
+impl MockClock {
+    pub fn new(now: i64) -> Self {
+        Self { now: AtomicI64::new(now) }
+    }
+    
+    pub fn set(&self, now: i64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+    
+    pub fn advance_days(&self, days: i64) {
+        self.now.fetch_add(days * SECONDS_PER_DAY, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Days of inactivity before a context is deleted
+    pub default_ttl_days: u32,
+    /// TTL in days for specific projects, replacing the default
+    pub per_project_overrides: HashMap<String, u32>,
+    /// Projects whose contexts are never deleted
+    pub legal_hold_projects: HashSet<String>,
+    /// Report what would be deleted without deleting anything
+    pub dry_run: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self::new(30)
+    }
+}
+
+impl RetentionPolicy {
+    pub fn new(default_ttl_days: u32) -> Self {
+        Self {
+            default_ttl_days,
+            per_project_overrides: HashMap::new(),
+            legal_hold_projects: HashSet::new(),
+            dry_run: false,
+        }
+    }
+    
+    pub fn with_project_ttl(mut self, project_id: impl Into<String>, ttl_days: u32) -> Self {
+        self.per_project_overrides.insert(project_id.into(), ttl_days);
+        self
+    }
+    
+    pub fn with_legal_hold(mut self, project_id: impl Into<String>) -> Self {
+        self.legal_hold_projects.insert(project_id.into());
+        self
+    }
+    
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    
+    pub fn ttl_days(&self, project_id: Option<&str>) -> u32 {
+        project_id
+            .and_then(|id| self.per_project_overrides.get(id))
+            .copied()
+            .unwrap_or(self.default_ttl_days)
+    }
+    
+    pub fn is_held(&self, project_id: Option<&str>) -> bool {
+        project_id.is_some_and(|id| self.legal_hold_projects.contains(id))
+    }
+    
+    /// Whether a context last updated at `updated_at` has outlived its TTL
+    pub fn is_expired(&self, project_id: Option<&str>, updated_at: i64, now: i64) -> bool {
+        now - updated_at > self.ttl_days(project_id) as i64 * SECONDS_PER_DAY
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    /// Contexts checked against the policy
+    pub examined: usize,
+    /// Contexts deleted, or that would be deleted in a dry run
+    pub deleted: usize,
+    /// Expired contexts kept because their project is on legal hold
+    pub held: usize,
+    pub dry_run: bool,
+    pub deleted_ids: Vec<String>,
+}
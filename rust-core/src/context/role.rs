@@ -0,0 +1,78 @@
+/// Message roles with alias normalization
+
+use crate::error::{OrchestratorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Who authored a message
+///
+/// Serialized as its canonical lowercase string so stored contexts keep the
+/// same format as when roles were plain strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+    /// Any other role, trimmed and lowercased
+    Other(String),
+}
+
+impl Role {
+    /// Parse a role, trimming whitespace, ignoring case and mapping aliases
+    pub fn parse(role: &str) -> Result<Self> {
+        let normalized = role.trim().to_lowercase();
+        let role = match normalized.as_str() {
+            "" => {
+                return Err(OrchestratorError::InvalidInput(
+                    "Message role must not be empty".to_string(),
+                ))
+            }
+            "system" | "developer" => Role::System,
+            "user" | "human" => Role::User,
+            "assistant" | "ai" | "bot" | "model" => Role::Assistant,
+            "tool" | "function" => Role::Tool,
+            _ => Role::Other(normalized),
+        };
+        Ok(role)
+    }
+    
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+            Role::Other(role) => role,
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = OrchestratorError;
+    
+    fn from_str(role: &str) -> Result<Self> {
+        Role::parse(role)
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = OrchestratorError;
+    
+    fn try_from(role: String) -> Result<Self> {
+        Role::parse(&role)
+    }
+}
+
+impl From<Role> for String {
+    fn from(role: Role) -> Self {
+        role.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -1,10 +1,42 @@
-use super::Context;
-use crate::error::{Result, OrchestratorError};
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use super::replay::{reconstruct_at, ReplayedContext};
+use super::summarizer::DrainedSummary;
+use super::{Context, Message};
+use crate::error::{OrchestratorError, Result, ResultExt};
+use crate::labels::{label_conditions, validate_labels};
+use crate::security::encryption::{open, seal, EncryptionKey};
+use crate::observability::MetricsCollector;
+use crate::storage::{add_column_if_missing, InstrumentedPool};
+use sqlx::sqlite::{SqliteConnection, SqlitePool, SqlitePoolOptions};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// Rows re-encrypted per transaction by `rotate_key`
+const ROTATION_BATCH_SIZE: i64 = 100;
+
 pub struct ContextStorage {
-    pool: SqlitePool,
+    pool: InstrumentedPool,
+    encryption: Option<EncryptionKey>,
+}
+
+/// Messages replaced by a summary, kept so they can be restored
+#[derive(Debug, Clone)]
+pub struct ArchivedSummary {
+    pub summary_id: i64,
+    pub conversation_id: String,
+    pub summary: String,
+    pub summary_message: Message,
+    pub messages: Vec<Message>,
+    pub created_at: i64,
+}
+
+/// A stored context without its messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextSummary {
+    pub conversation_id: String,
+    pub project_id: Option<String>,
+    pub updated_at: i64,
+    pub title: Option<String>,
+    pub labels: HashMap<String, String>,
 }
 
 impl ContextStorage {
@@ -14,8 +46,14 @@ impl ContextStorage {
             std::fs::create_dir_all(parent)?;
         }
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
+        // Every connection to ":memory:" opens its own empty database, so
+        // keep a single connection alive for the life of the pool
+        let mut options = SqlitePoolOptions::new().max_connections(5);
+        if db_path.as_os_str() == ":memory:" {
+            options = options.max_connections(1).idle_timeout(None).max_lifetime(None);
+        }
+
+        let pool = options
             .connect_with(
                 sqlx::sqlite::SqliteConnectOptions::new()
                     .filename(&db_path)
@@ -24,6 +62,14 @@ impl ContextStorage {
             .await
             .map_err(OrchestratorError::from)?;
 
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing pool (e.g. one shared with other components)
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        // One connection throughout, so `new(":memory:")` sets up a single database
+        let mut conn = pool.acquire().await.map_err(OrchestratorError::from)?;
+
         // Create tables
         sqlx::query(
             r#"
@@ -31,14 +77,22 @@ impl ContextStorage {
                 conversation_id TEXT PRIMARY KEY,
                 project_id TEXT,
                 data TEXT NOT NULL,
-                updated_at INTEGER NOT NULL
+                updated_at INTEGER NOT NULL,
+                version INTEGER NOT NULL DEFAULT 0,
+                title TEXT,
+                labels TEXT NOT NULL DEFAULT '{}'
             )
             "#,
         )
-        .execute(&pool)
+        .execute(&mut *conn)
         .await
         .map_err(OrchestratorError::from)?;
 
+        // Tables from before optimistic locking, titles and labels lack these columns
+        add_column_if_missing(&mut conn, "contexts", "version", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&mut conn, "contexts", "title", "TEXT").await?;
+        add_column_if_missing(&mut conn, "contexts", "labels", "TEXT NOT NULL DEFAULT '{}'").await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS messages (
@@ -51,53 +105,474 @@ impl ContextStorage {
             )
             "#,
         )
-        .execute(&pool)
+        .execute(&mut *conn)
         .await
         .map_err(OrchestratorError::from)?;
 
-        Ok(Self { pool })
+        // Same schema as migration m009
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS context_archive (
+                summary_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                conversation_id TEXT NOT NULL,
+                summary TEXT NOT NULL,
+                summary_message TEXT NOT NULL,
+                messages TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(Self { pool: InstrumentedPool::new(pool), encryption: None })
     }
 
-    pub async fn save_context(&self, context: &Context) -> Result<()> {
-        let data = serde_json::to_string(context)
-            .map_err(OrchestratorError::from)?;
+    /// Observe query timings in `metrics` as well as the process-wide log
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.pool = self.pool.with_metrics(metrics);
+        self
+    }
+
+    /// Encrypt context data, titles and archives written from now on
+    ///
+    /// Rows written without encryption still load; rows encrypted under a
+    /// different key fail with an `Authentication` error.
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.encryption = Some(key);
+        self
+    }
+
+    /// Save a context if nobody else has saved it since it was loaded
+    ///
+    /// On success `context.version` is bumped to the stored version. If the
+    /// stored version has moved on, returns `ConflictDetected` and writes nothing.
+    pub async fn save_context(&self, context: &mut Context) -> Result<()> {
         let updated_at = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
+        self.save_context_at(context, updated_at).await
+    }
 
-        sqlx::query(
+    /// Save a context with an explicit last-activity time (Unix seconds)
+    pub async fn save_context_at(&self, context: &mut Context, updated_at: i64) -> Result<()> {
+        let mut conn = self.pool.acquire().await.with_context(|| format!("saving context {}", context.conversation_id))?;
+        context.version = self.write_context(&mut conn, context, updated_at).await?;
+        Ok(())
+    }
+
+    /// Store messages drained by the summarizer and save the summarized context
+    ///
+    /// Both happen in one transaction, so a failed save (e.g. a version
+    /// conflict) leaves no archive behind. Returns the new summary_id.
+    pub async fn save_context_with_archive(&self, context: &mut Context, drained: &DrainedSummary) -> Result<i64> {
+        let updated_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let breadcrumb = || format!("saving context {} with its archive", context.conversation_id);
+        let mut tx = self.pool.begin().await.with_context(breadcrumb)?;
+
+        let summary_id = self
+            .insert_archive(&mut *tx, &context.conversation_id, &drained.summary, &drained.summary_message, &drained.messages)
+            .await?;
+        let version = self.write_context(&mut *tx, context, updated_at).await?;
+
+        tx.commit().await.with_context(breadcrumb)?;
+        context.version = version;
+        Ok(summary_id)
+    }
+
+    /// Write `context` if its version is current; returns the new version
+    async fn write_context(&self, conn: &mut SqliteConnection, context: &Context, updated_at: i64) -> Result<i64> {
+        validate_labels(&context.labels)?;
+        let breadcrumb = || format!("saving context {}", context.conversation_id);
+        let labels = serde_json::to_string(&context.labels).with_context(breadcrumb)?;
+        let next_version = context.version + 1;
+        let data = serde_json::to_string(&Context {
+            version: next_version,
+            ..context.clone()
+        })
+        .with_context(breadcrumb)?;
+        let data = seal(self.encryption.as_ref(), data)?;
+        let title = context
+            .title
+            .clone()
+            .map(|title| seal(self.encryption.as_ref(), title))
+            .transpose()?;
+
+        let updated = sqlx::query(
             r#"
-            INSERT OR REPLACE INTO contexts (conversation_id, project_id, data, updated_at)
-            VALUES (?1, ?2, ?3, ?4)
+            UPDATE contexts
+            SET project_id = ?2, data = ?3, updated_at = ?4, version = ?5, title = ?7, labels = ?8
+            WHERE conversation_id = ?1 AND version = ?6
             "#,
         )
         .bind(&context.conversation_id)
         .bind(&context.project_id)
         .bind(&data)
         .bind(updated_at)
-        .execute(&self.pool)
+        .bind(next_version)
+        .bind(context.version)
+        .bind(&title)
+        .bind(&labels)
+        .execute(&mut *conn)
         .await
-        .map_err(OrchestratorError::from)?;
+        .with_context(breadcrumb)?;
 
-        Ok(())
+        let mut saved = updated.rows_affected() > 0;
+
+        // Unsaved contexts start at version 0; a row that already exists means
+        // another writer created it first
+        if !saved && context.version == 0 {
+            let inserted = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO contexts (conversation_id, project_id, data, updated_at, version, title, labels)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+            )
+            .bind(&context.conversation_id)
+            .bind(&context.project_id)
+            .bind(&data)
+            .bind(updated_at)
+            .bind(next_version)
+            .bind(&title)
+            .bind(&labels)
+            .execute(&mut *conn)
+            .await
+            .with_context(breadcrumb)?;
+            saved = inserted.rows_affected() > 0;
+        }
+
+        if !saved {
+            return Err(OrchestratorError::ConflictDetected(format!(
+                "context {} changed since version {}",
+                context.conversation_id, context.version
+            )));
+        }
+
+        Ok(next_version)
     }
 
     pub async fn load_context(&self, conversation_id: &str) -> Result<Option<Context>> {
-        let row = sqlx::query_as::<_, (String,)>(
-            "SELECT data FROM contexts WHERE conversation_id = ?1",
+        let breadcrumb = || format!("loading context {}", conversation_id);
+        let row = sqlx::query_as::<_, (String, i64)>(
+            "SELECT data, version FROM contexts WHERE conversation_id = ?1",
         )
         .bind(conversation_id)
         .fetch_optional(&self.pool)
         .await
-        .map_err(OrchestratorError::from)?;
+        .with_context(breadcrumb)?;
 
-        if let Some((data,)) = row {
-            let context: Context = serde_json::from_str(&data)
-                .map_err(OrchestratorError::from)?;
+        if let Some((data, version)) = row {
+            let data = open(self.encryption.as_ref(), data)?;
+            let mut context: Context = serde_json::from_str(&data)
+                .with_context(breadcrumb)?;
+            // The column is authoritative; older blobs carry no version
+            context.version = version;
             Ok(Some(context))
         } else {
             Ok(None)
         }
     }
+
+    /// The context as it stood at `timestamp` (Unix seconds); None if it is not stored
+    ///
+    /// Rebuilt from the stored blob and the summary archive; see
+    /// `reconstruct_at` for what cannot be recovered.
+    pub async fn load_context_at(&self, conversation_id: &str, timestamp: i64) -> Result<Option<ReplayedContext>> {
+        // Tables created by the migrations declare updated_at as TEXT
+        let updated_at = sqlx::query_as::<_, (i64,)>(
+            "SELECT CAST(updated_at AS INTEGER) FROM contexts WHERE conversation_id = ?1",
+        )
+        .bind(conversation_id)
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("replaying context {}", conversation_id))?;
+        let (context, updated_at) = match (updated_at, self.load_context(conversation_id).await?) {
+            (Some((updated_at,)), Some(context)) => (context, updated_at),
+            _ => return Ok(None),
+        };
+
+        let archives = self.load_archive(conversation_id, None).await?;
+        Ok(Some(reconstruct_at(context, updated_at, &archives, timestamp)))
+    }
+
+    /// List (conversation_id, project_id, updated_at) for every stored context
+    pub async fn list_contexts(&self) -> Result<Vec<(String, Option<String>, i64)>> {
+        // Tables created by the migrations declare updated_at as TEXT
+        let rows = sqlx::query_as::<_, (String, Option<String>, i64)>(
+            r#"
+            SELECT conversation_id, project_id, CAST(updated_at AS INTEGER)
+            FROM contexts
+            ORDER BY updated_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(rows)
+    }
+
+    /// List contexts most recently updated first, resuming after `after`
+    ///
+    /// `after` is the (updated_at, conversation_id) of the last context already
+    /// returned; ties on updated_at are broken by conversation_id. Only
+    /// contexts carrying every label in `labels_match` are listed.
+    pub async fn list_contexts_page(
+        &self,
+        project_id: Option<&str>,
+        labels_match: Option<&HashMap<String, String>>,
+        limit: usize,
+        after: Option<(i64, &str)>,
+    ) -> Result<Vec<ContextSummary>> {
+        let (after_updated_at, after_id) = match after {
+            Some((updated_at, conversation_id)) => (Some(updated_at), Some(conversation_id)),
+            None => (None, None),
+        };
+        let (label_sql, label_binds) = match labels_match {
+            Some(labels_match) => label_conditions(labels_match, 5)?,
+            None => (String::new(), Vec::new()),
+        };
+
+        let query = format!(
+            r#"
+            SELECT conversation_id, project_id, CAST(updated_at AS INTEGER) AS ts, title, labels
+            FROM contexts
+            WHERE (?1 IS NULL OR project_id = ?1)
+              AND (?2 IS NULL OR CAST(updated_at AS INTEGER) < ?2
+                   OR (CAST(updated_at AS INTEGER) = ?2 AND conversation_id > ?3)){}
+            ORDER BY ts DESC, conversation_id ASC
+            LIMIT ?4
+            "#,
+            label_sql
+        );
+        let mut query_builder = sqlx::query_as::<_, (String, Option<String>, i64, Option<String>, String)>(&query)
+            .bind(project_id)
+            .bind(after_updated_at)
+            .bind(after_id)
+            .bind(limit as i64);
+        for value in &label_binds {
+            query_builder = query_builder.bind(value);
+        }
+
+        let rows = query_builder
+            .fetch_all(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+
+        rows.into_iter()
+            .map(|(conversation_id, project_id, updated_at, title, labels)| {
+                Ok(ContextSummary {
+                    conversation_id,
+                    project_id,
+                    updated_at,
+                    title: title.map(|title| open(self.encryption.as_ref(), title)).transpose()?,
+                    labels: serde_json::from_str(&labels).map_err(OrchestratorError::from)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Delete a context and its messages; returns false if it did not exist
+    pub async fn delete_context(&self, conversation_id: &str) -> Result<bool> {
+        let breadcrumb = || format!("deleting context {}", conversation_id);
+        let mut tx = self.pool.begin().await.with_context(breadcrumb)?;
+
+        sqlx::query("DELETE FROM messages WHERE conversation_id = ?1")
+            .bind(conversation_id)
+            .execute(&mut *tx)
+            .await
+            .with_context(breadcrumb)?;
+
+        sqlx::query("DELETE FROM context_archive WHERE conversation_id = ?1")
+            .bind(conversation_id)
+            .execute(&mut *tx)
+            .await
+            .with_context(breadcrumb)?;
+
+        let result = sqlx::query("DELETE FROM contexts WHERE conversation_id = ?1")
+            .bind(conversation_id)
+            .execute(&mut *tx)
+            .await
+            .with_context(breadcrumb)?;
+
+        tx.commit().await.with_context(breadcrumb)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Store messages drained by the summarizer; returns the new summary_id
+    pub async fn archive_summary(
+        &self,
+        conversation_id: &str,
+        summary: &str,
+        summary_message: &Message,
+        messages: &[Message],
+    ) -> Result<i64> {
+        let mut conn = self.pool.acquire().await.with_context(|| format!("archiving summary of context {}", conversation_id))?;
+        self.insert_archive(&mut conn, conversation_id, summary, summary_message, messages).await
+    }
+
+    async fn insert_archive(
+        &self,
+        conn: &mut SqliteConnection,
+        conversation_id: &str,
+        summary: &str,
+        summary_message: &Message,
+        messages: &[Message],
+    ) -> Result<i64> {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let breadcrumb = || format!("archiving summary of context {}", conversation_id);
+        let result = sqlx::query(
+            r#"
+            INSERT INTO context_archive (conversation_id, summary, summary_message, messages, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(seal(self.encryption.as_ref(), summary.to_string())?)
+        .bind(seal(
+            self.encryption.as_ref(),
+            serde_json::to_string(summary_message).with_context(breadcrumb)?,
+        )?)
+        .bind(seal(
+            self.encryption.as_ref(),
+            serde_json::to_string(messages).with_context(breadcrumb)?,
+        )?)
+        .bind(created_at)
+        .execute(&mut *conn)
+        .await
+        .with_context(breadcrumb)?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Load archived summaries for a conversation, oldest first
+    pub async fn load_archive(
+        &self,
+        conversation_id: &str,
+        summary_id: Option<i64>,
+    ) -> Result<Vec<ArchivedSummary>> {
+        let breadcrumb = || format!("loading archive of context {}", conversation_id);
+        let rows = sqlx::query_as::<_, (i64, String, String, String, i64)>(
+            r#"
+            SELECT summary_id, summary, summary_message, messages, created_at
+            FROM context_archive
+            WHERE conversation_id = ?1 AND (?2 IS NULL OR summary_id = ?2)
+            ORDER BY summary_id
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(summary_id)
+        .fetch_all(&self.pool)
+        .await
+        .with_context(breadcrumb)?;
+
+        rows.into_iter()
+            .map(|(summary_id, summary, summary_message, messages, created_at)| {
+                let key = self.encryption.as_ref();
+                Ok(ArchivedSummary {
+                    summary_id,
+                    conversation_id: conversation_id.to_string(),
+                    summary: open(key, summary)?,
+                    summary_message: serde_json::from_str(&open(key, summary_message)?)
+                        .with_context(breadcrumb)?,
+                    messages: serde_json::from_str(&open(key, messages)?)
+                        .with_context(breadcrumb)?,
+                    created_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Re-encrypt every stored context and archive under `new`
+    ///
+    /// Rows are decrypted with `old` (legacy plaintext rows pass through) and
+    /// rewritten in batches, one transaction per batch. Returns the number of
+    /// rows rewritten. This storage keeps its own key; construct a new one
+    /// with `new` once rotation finishes.
+    pub async fn rotate_key(&self, old: &EncryptionKey, new: &EncryptionKey) -> Result<usize> {
+        let mut rotated = 0;
+
+        let mut after = String::new();
+        loop {
+            let rows = sqlx::query_as::<_, (String, String, Option<String>)>(
+                r#"
+                SELECT conversation_id, data, title FROM contexts
+                WHERE conversation_id > ?1
+                ORDER BY conversation_id
+                LIMIT ?2
+                "#,
+            )
+            .bind(&after)
+            .bind(ROTATION_BATCH_SIZE)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+            let Some((last_id, _, _)) = rows.last() else { break };
+            after = last_id.clone();
+
+            let mut tx = self.pool.begin().await.map_err(OrchestratorError::from)?;
+            for (conversation_id, data, title) in rows {
+                let data = new.encrypt(&open(Some(old), data)?)?;
+                let title = title
+                    .map(|title| open(Some(old), title).and_then(|title| new.encrypt(&title)))
+                    .transpose()?;
+                sqlx::query("UPDATE contexts SET data = ?2, title = ?3 WHERE conversation_id = ?1")
+                    .bind(&conversation_id)
+                    .bind(&data)
+                    .bind(&title)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(OrchestratorError::from)?;
+                rotated += 1;
+            }
+            tx.commit().await.map_err(OrchestratorError::from)?;
+        }
+
+        let mut after = 0;
+        loop {
+            let rows = sqlx::query_as::<_, (i64, String, String, String)>(
+                r#"
+                SELECT summary_id, summary, summary_message, messages FROM context_archive
+                WHERE summary_id > ?1
+                ORDER BY summary_id
+                LIMIT ?2
+                "#,
+            )
+            .bind(after)
+            .bind(ROTATION_BATCH_SIZE)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+            let Some((last_id, _, _, _)) = rows.last() else { break };
+            after = *last_id;
+
+            let mut tx = self.pool.begin().await.map_err(OrchestratorError::from)?;
+            for (summary_id, summary, summary_message, messages) in rows {
+                sqlx::query(
+                    "UPDATE context_archive SET summary = ?2, summary_message = ?3, messages = ?4 WHERE summary_id = ?1",
+                )
+                .bind(summary_id)
+                .bind(new.encrypt(&open(Some(old), summary)?)?)
+                .bind(new.encrypt(&open(Some(old), summary_message)?)?)
+                .bind(new.encrypt(&open(Some(old), messages)?)?)
+                .execute(&mut *tx)
+                .await
+                .map_err(OrchestratorError::from)?;
+                rotated += 1;
+            }
+            tx.commit().await.map_err(OrchestratorError::from)?;
+        }
+
+        Ok(rotated)
+    }
 }
+
@@ -0,0 +1,323 @@
+/// Storage backends for contexts
+
+use super::replay::{reconstruct_at, ReplayedContext};
+use super::summarizer::DrainedSummary;
+use super::{ArchivedSummary, Context, ContextStorage, ContextSummary, Message};
+use crate::error::{OrchestratorError, Result};
+use crate::labels::validate_labels;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Where `ContextManager` keeps contexts and summary archives
+///
+/// Implementations must provide optimistic locking: `save_context` fails with
+/// `ConflictDetected` unless the stored version still equals
+/// `context.version`, and bumps `context.version` on success.
+#[async_trait]
+pub trait ContextStore: Send + Sync {
+    async fn save_context(&self, context: &mut Context) -> Result<()>;
+
+    async fn load_context(&self, conversation_id: &str) -> Result<Option<Context>>;
+
+    /// The context as it stood at `timestamp` (Unix seconds); see `reconstruct_at`
+    async fn load_context_at(&self, conversation_id: &str, timestamp: i64) -> Result<Option<ReplayedContext>>;
+
+    /// Delete a context and its archives; returns false if it did not exist
+    async fn delete_context(&self, conversation_id: &str) -> Result<bool>;
+
+    /// (conversation_id, project_id, updated_at) for every stored context
+    async fn list_contexts(&self) -> Result<Vec<(String, Option<String>, i64)>>;
+
+    /// Contexts most recently updated first, resuming after (updated_at, conversation_id)
+    async fn list_contexts_page(
+        &self,
+        project_id: Option<&str>,
+        labels_match: Option<&HashMap<String, String>>,
+        limit: usize,
+        after: Option<(i64, &str)>,
+    ) -> Result<Vec<ContextSummary>>;
+
+    /// Store messages drained by the summarizer; returns the new summary_id
+    async fn archive_summary(
+        &self,
+        conversation_id: &str,
+        summary: &str,
+        summary_message: &Message,
+        messages: &[Message],
+    ) -> Result<i64>;
+
+    /// Archived summaries for a conversation, oldest first
+    async fn load_archive(&self, conversation_id: &str, summary_id: Option<i64>) -> Result<Vec<ArchivedSummary>>;
+
+    /// Archive `drained` and save the summarized `context` together
+    ///
+    /// Neither is stored unless both are; in particular a version conflict
+    /// leaves no archive behind. Returns the new summary_id.
+    async fn save_context_with_archive(&self, context: &mut Context, drained: &DrainedSummary) -> Result<i64>;
+}
+
+#[async_trait]
+impl ContextStore for ContextStorage {
+    async fn save_context(&self, context: &mut Context) -> Result<()> {
+        ContextStorage::save_context(self, context).await
+    }
+
+    async fn load_context(&self, conversation_id: &str) -> Result<Option<Context>> {
+        ContextStorage::load_context(self, conversation_id).await
+    }
+
+    async fn load_context_at(&self, conversation_id: &str, timestamp: i64) -> Result<Option<ReplayedContext>> {
+        ContextStorage::load_context_at(self, conversation_id, timestamp).await
+    }
+
+    async fn delete_context(&self, conversation_id: &str) -> Result<bool> {
+        ContextStorage::delete_context(self, conversation_id).await
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<(String, Option<String>, i64)>> {
+        ContextStorage::list_contexts(self).await
+    }
+
+    async fn list_contexts_page(
+        &self,
+        project_id: Option<&str>,
+        labels_match: Option<&HashMap<String, String>>,
+        limit: usize,
+        after: Option<(i64, &str)>,
+    ) -> Result<Vec<ContextSummary>> {
+        ContextStorage::list_contexts_page(self, project_id, labels_match, limit, after).await
+    }
+
+    async fn archive_summary(
+        &self,
+        conversation_id: &str,
+        summary: &str,
+        summary_message: &Message,
+        messages: &[Message],
+    ) -> Result<i64> {
+        ContextStorage::archive_summary(self, conversation_id, summary, summary_message, messages).await
+    }
+
+    async fn load_archive(&self, conversation_id: &str, summary_id: Option<i64>) -> Result<Vec<ArchivedSummary>> {
+        ContextStorage::load_archive(self, conversation_id, summary_id).await
+    }
+
+    async fn save_context_with_archive(&self, context: &mut Context, drained: &DrainedSummary) -> Result<i64> {
+        ContextStorage::save_context_with_archive(self, context, drained).await
+    }
+}
+
+/// Contexts held in process memory, for tests and ephemeral sessions
+///
+/// Behaves like `ContextStorage` (versioning, label validation, ordering)
+/// but everything is lost when the store is dropped.
+#[derive(Default)]
+pub struct InMemoryContextStore {
+    state: Mutex<InMemoryState>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    contexts: HashMap<String, StoredContext>,
+    archives: Vec<ArchivedSummary>,
+    next_summary_id: i64,
+}
+
+struct StoredContext {
+    context: Context,
+    updated_at: i64,
+}
+
+impl InMemoryContextStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save with an explicit last-activity time (Unix seconds)
+    pub fn save_context_at(&self, context: &mut Context, updated_at: i64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        Self::save_locked(&mut state, context, updated_at)
+    }
+
+    fn save_locked(state: &mut InMemoryState, context: &mut Context, updated_at: i64) -> Result<()> {
+        validate_labels(&context.labels)?;
+        let stored_version = state
+            .contexts
+            .get(&context.conversation_id)
+            .map(|stored| stored.context.version);
+        let current = match stored_version {
+            Some(version) => version == context.version,
+            None => context.version == 0,
+        };
+        if !current {
+            return Err(OrchestratorError::ConflictDetected(format!(
+                "context {} changed since version {}",
+                context.conversation_id, context.version
+            )));
+        }
+
+        context.version += 1;
+        state.contexts.insert(
+            context.conversation_id.clone(),
+            StoredContext {
+                context: context.clone(),
+                updated_at,
+            },
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ContextStore for InMemoryContextStore {
+    async fn save_context(&self, context: &mut Context) -> Result<()> {
+        self.save_context_at(context, unix_now())
+    }
+
+    async fn load_context(&self, conversation_id: &str) -> Result<Option<Context>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.contexts.get(conversation_id).map(|stored| stored.context.clone()))
+    }
+
+    async fn load_context_at(&self, conversation_id: &str, timestamp: i64) -> Result<Option<ReplayedContext>> {
+        let state = self.state.lock().unwrap();
+        let archives: Vec<ArchivedSummary> = state
+            .archives
+            .iter()
+            .filter(|archive| archive.conversation_id == conversation_id)
+            .cloned()
+            .collect();
+        Ok(state
+            .contexts
+            .get(conversation_id)
+            .map(|stored| reconstruct_at(stored.context.clone(), stored.updated_at, &archives, timestamp)))
+    }
+
+    async fn delete_context(&self, conversation_id: &str) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        state.archives.retain(|archive| archive.conversation_id != conversation_id);
+        Ok(state.contexts.remove(conversation_id).is_some())
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<(String, Option<String>, i64)>> {
+        let state = self.state.lock().unwrap();
+        let mut contexts: Vec<(String, Option<String>, i64)> = state
+            .contexts
+            .values()
+            .map(|stored| {
+                (
+                    stored.context.conversation_id.clone(),
+                    stored.context.project_id.clone(),
+                    stored.updated_at,
+                )
+            })
+            .collect();
+        contexts.sort_by_key(|(_, _, updated_at)| *updated_at);
+        Ok(contexts)
+    }
+
+    async fn list_contexts_page(
+        &self,
+        project_id: Option<&str>,
+        labels_match: Option<&HashMap<String, String>>,
+        limit: usize,
+        after: Option<(i64, &str)>,
+    ) -> Result<Vec<ContextSummary>> {
+        if let Some(labels_match) = labels_match {
+            validate_labels(labels_match)?;
+        }
+
+        let state = self.state.lock().unwrap();
+        let mut summaries: Vec<ContextSummary> = state
+            .contexts
+            .values()
+            .filter(|stored| project_id.is_none() || stored.context.project_id.as_deref() == project_id)
+            .filter(|stored| {
+                labels_match.is_none_or(|labels_match| {
+                    labels_match.iter().all(|(key, value)| stored.context.labels.get(key) == Some(value))
+                })
+            })
+            .filter(|stored| match after {
+                Some((updated_at, conversation_id)) => {
+                    stored.updated_at < updated_at
+                        || (stored.updated_at == updated_at
+                            && stored.context.conversation_id.as_str() > conversation_id)
+                }
+                None => true,
+            })
+            .map(|stored| ContextSummary {
+                conversation_id: stored.context.conversation_id.clone(),
+                project_id: stored.context.project_id.clone(),
+                updated_at: stored.updated_at,
+                title: stored.context.title.clone(),
+                labels: stored.context.labels.clone(),
+            })
+            .collect();
+
+        summaries.sort_by(|a, b| {
+            b.updated_at
+                .cmp(&a.updated_at)
+                .then_with(|| a.conversation_id.cmp(&b.conversation_id))
+        });
+        summaries.truncate(limit);
+        Ok(summaries)
+    }
+
+    async fn archive_summary(
+        &self,
+        conversation_id: &str,
+        summary: &str,
+        summary_message: &Message,
+        messages: &[Message],
+    ) -> Result<i64> {
+        let mut state = self.state.lock().unwrap();
+        state.next_summary_id += 1;
+        let summary_id = state.next_summary_id;
+        state.archives.push(ArchivedSummary {
+            summary_id,
+            conversation_id: conversation_id.to_string(),
+            summary: summary.to_string(),
+            summary_message: summary_message.clone(),
+            messages: messages.to_vec(),
+            created_at: unix_now(),
+        });
+        Ok(summary_id)
+    }
+
+    async fn load_archive(&self, conversation_id: &str, summary_id: Option<i64>) -> Result<Vec<ArchivedSummary>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .archives
+            .iter()
+            .filter(|archive| archive.conversation_id == conversation_id)
+            .filter(|archive| summary_id.is_none_or(|id| archive.summary_id == id))
+            .cloned()
+            .collect())
+    }
+
+    async fn save_context_with_archive(&self, context: &mut Context, drained: &DrainedSummary) -> Result<i64> {
+        // One lock for both, and the save (which can conflict) goes first
+        let mut state = self.state.lock().unwrap();
+        let now = unix_now();
+        Self::save_locked(&mut state, context, now)?;
+        state.next_summary_id += 1;
+        let summary_id = state.next_summary_id;
+        state.archives.push(ArchivedSummary {
+            summary_id,
+            conversation_id: context.conversation_id.clone(),
+            summary: drained.summary.clone(),
+            summary_message: drained.summary_message.clone(),
+            messages: drained.messages.clone(),
+            created_at: now,
+        });
+        Ok(summary_id)
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
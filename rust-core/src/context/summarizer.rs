@@ -1,7 +1,7 @@
 /// Context summarization for long conversation histories
 
-use crate::context::{Context, Message};
-use std::collections::HashMap;
+use crate::context::importance::{ImportanceConfig, ImportanceScorer};
+use crate::context::{Context, Message, Role};
 
 #[derive(Clone, Copy)]
 pub enum SummarizationStrategy {
@@ -10,11 +10,20 @@ pub enum SummarizationStrategy {
     Hybrid,
 }
 
+/// A summary and the messages it replaced
+#[derive(Debug, Clone)]
+pub struct DrainedSummary {
+    pub summary: String,
+    pub summary_message: Message, // Inserted at the start of the context
+    pub messages: Vec<Message>, // Oldest first
+}
+
 pub struct ContextSummarizer {
     message_threshold: usize,
     summary_ratio: f64, // Ratio of messages to summarize (e.g., 0.8 = summarize oldest 80%)
     strategy: SummarizationStrategy,
     abstractive_threshold: usize, // Use abstractive for conversations > this many messages
+    importance: ImportanceScorer,
 }
 
 impl ContextSummarizer {
@@ -24,6 +33,7 @@ impl ContextSummarizer {
             summary_ratio,
             strategy: SummarizationStrategy::Hybrid,
             abstractive_threshold: 100,
+            importance: ImportanceScorer::default(),
         }
     }
     
@@ -32,8 +42,28 @@ impl ContextSummarizer {
         self
     }
     
+    pub fn with_abstractive_threshold(mut self, threshold: usize) -> Self {
+        self.abstractive_threshold = threshold;
+        self
+    }
+    
+    pub fn with_importance(mut self, config: ImportanceConfig) -> Self {
+        self.importance = ImportanceScorer::new(config);
+        self
+    }
+    
+    /// Scorer deciding which messages make it into summaries
+    pub fn importance(&self) -> &ImportanceScorer {
+        &self.importance
+    }
+    
     /// Summarize context if it exceeds threshold
     pub fn summarize_if_needed(&self, context: &mut Context) -> Option<String> {
+        self.summarize_and_drain(context).map(|drained| drained.summary)
+    }
+    
+    /// Summarize context if it exceeds threshold, returning the drained messages
+    pub fn summarize_and_drain(&self, context: &mut Context) -> Option<DrainedSummary> {
         if context.messages.len() <= self.message_threshold {
             return None;
         }
@@ -71,18 +101,23 @@ impl ContextSummarizer {
         
         // Create summary message
         let summary_message = Message {
-            role: "system".to_string(),
+            role: Role::System,
             content: format!("Previous conversation summary: {}", summary),
             timestamp: messages_to_summarize
                 .first()
                 .map(|m| m.timestamp)
                 .unwrap_or(0),
+            pinned: false,
         };
         
         // Insert summary at the beginning
-        context.messages.insert(0, summary_message);
+        context.messages.insert(0, summary_message.clone());
         
-        Some(summary)
+        Some(DrainedSummary {
+            summary,
+            summary_message,
+            messages: messages_to_summarize,
+        })
     }
     
     /// Generate summary from messages (extractive summarization with importance scoring)
@@ -91,7 +126,7 @@ impl ContextSummarizer {
         let mut scored_messages: Vec<(usize, f32, &Message)> = messages
             .iter()
             .enumerate()
-            .map(|(idx, msg)| (idx, self.score_message_importance(msg), msg))
+            .map(|(idx, msg)| (idx, self.importance.score(messages, idx), msg))
             .collect();
         
         // Sort by importance (highest first)
@@ -128,46 +163,20 @@ impl ContextSummarizer {
         }
     }
     
-    /// Score message importance (0.0 to 1.0)
-    fn score_message_importance(&self, message: &Message) -> f32 {
-        let mut score = 0.0;
-        let content_lower = message.content.to_lowercase();
-        
-        // Role-based scoring
-        match message.role.as_str() {
-            "system" => score += 0.5,
-            "user" => score += 0.3,
-            "assistant" => score += 0.2,
-            _ => {}
+    /// Short extractive summary of a passage, at most `max_length` bytes
+    ///
+    /// Uses the same sentence selection as message summaries: sentences
+    /// with decision or problem keywords, else the first one.
+    pub fn summarize_excerpt(&self, text: &str, max_length: usize) -> String {
+        let summary = self.extract_important_sentences(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+        if summary.len() <= max_length {
+            return summary;
         }
-        
-        // Keyword-based scoring
-        let important_keywords = vec![
-            "decided", "decision", "important", "note", "error", "fix", "solution",
-            "problem", "issue", "bug", "implement", "change", "update", "refactor"
-        ];
-        
-        for keyword in important_keywords {
-            if content_lower.contains(keyword) {
-                score += 0.1;
-            }
+        let mut end = max_length;
+        while !summary.is_char_boundary(end) {
+            end -= 1;
         }
-        
-        // Code block presence
-        if message.content.contains("```") {
-            score += 0.2;
-        }
-        
-        // Recency weighting (would need timestamp comparison in real implementation)
-        // For now, assume all messages are equally recent
-        
-        // Length-based (very short or very long might be less important)
-        let len = message.content.len();
-        if len > 50 && len < 2000 {
-            score += 0.1;
-        }
-        
-        score.min(1.0)
+        summary[..end].trim_end().to_string()
     }
     
     /// Extract important sentences from content
@@ -208,8 +217,8 @@ impl ContextSummarizer {
         let mut summary_parts = Vec::new();
         
         // Extract key themes and decisions
-        for message in messages {
-            let score = self.score_message_importance(message);
+        for (idx, message) in messages.iter().enumerate() {
+            let score = self.importance.score(messages, idx);
             if score > 0.5 {
                 let important_sentences = self.extract_important_sentences(&message.content);
                 if !important_sentences.is_empty() {
@@ -0,0 +1,60 @@
+/// Heuristic conversation titles
+
+/// Longest generated title, in characters, including the ellipsis
+pub const MAX_TITLE_CHARS: usize = 60;
+
+/// Derive a short title from a message
+///
+/// Fenced code blocks are dropped and whitespace collapsed; if nothing but
+/// code remains, the first line of code is used instead. Long titles are cut
+/// at a word boundary and end with an ellipsis. Returns None for blank input.
+pub fn title_from_message(content: &str) -> Option<String> {
+    let mut prose = Vec::new();
+    let mut first_code_line = None;
+    let mut in_code_block = false;
+    
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if in_code_block {
+            if first_code_line.is_none() && !trimmed.is_empty() {
+                first_code_line = Some(trimmed);
+            }
+        } else {
+            prose.push(trimmed);
+        }
+    }
+    
+    let mut text = collapse_whitespace(&prose.join(" "));
+    if text.is_empty() {
+        text = collapse_whitespace(first_code_line?);
+    }
+    Some(truncate_at_word(&text, MAX_TITLE_CHARS))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cut `text` to at most `max_chars` characters, preferring a word boundary
+fn truncate_at_word(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    
+    // Leave room for the ellipsis
+    let limit = max_chars.saturating_sub(1);
+    let cut: String = text.chars().take(limit).collect();
+    let next_is_space = text.chars().nth(limit).is_none_or(char::is_whitespace);
+    
+    let head = if next_is_space {
+        cut.as_str()
+    } else {
+        match cut.rfind(' ') {
+            Some(pos) if pos > 0 => &cut[..pos],
+            _ => cut.as_str(),
+        }
+    };
+    format!("{}…", head.trim_end_matches(|c: char| c.is_whitespace() || ",;:-".contains(c)))
+}
@@ -1,5 +1,6 @@
 /// Token counting utilities
 
+use crate::models::ModelCatalog;
 use std::collections::HashMap;
 
 /// Model context window sizes (approximate)
@@ -15,8 +16,13 @@ pub const MODEL_CONTEXT_WINDOWS: &[(&str, usize)] = &[
     ("claude-3-5-sonnet", 200000),
 ];
 
+/// Window assumed for models matching no entry
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
+#[derive(Debug, Clone)]
 pub struct TokenCounter {
     context_windows: HashMap<String, usize>,
+    catalog: ModelCatalog, // Aliases accepted in place of model names
 }
 
 impl TokenCounter {
@@ -25,15 +31,57 @@ impl TokenCounter {
         for (model, size) in MODEL_CONTEXT_WINDOWS {
             context_windows.insert(model.to_string(), *size);
         }
-        Self { context_windows }
+        Self {
+            context_windows,
+            catalog: ModelCatalog::new(),
+        }
+    }
+    
+    /// Accept `catalog` aliases, using their context windows where given
+    pub fn with_catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+    
+    /// Set the window of `model`, and of any dated or suffixed variant without its own entry
+    pub fn register_model(&mut self, model: impl Into<String>, window: usize) {
+        self.context_windows.insert(model.into(), window);
     }
     
     /// Get context window size for a model
+    ///
+    /// Falls back to the longest known model name `model` starts with, so
+    /// "claude-3-5-sonnet-20241022" gets the "claude-3-5-sonnet" window. Models
+    /// matching nothing get `DEFAULT_CONTEXT_WINDOW` and a warning. Catalog
+    /// aliases get their entry's window, or that of the model they name.
     pub fn get_context_window(&self, model: &str) -> usize {
-        self.context_windows
-            .get(model)
-            .copied()
-            .unwrap_or(8192) // Default
+        let model = match self.catalog.lookup(model) {
+            Some(entry) => match entry.context_window {
+                Some(window) => return window,
+                None => entry.model.as_str(),
+            },
+            None => model,
+        };
+        
+        if let Some(window) = self.context_windows.get(model) {
+            return *window;
+        }
+        
+        let prefix_match = self.context_windows
+            .iter()
+            .filter(|(known, _)| model.starts_with(known.as_str()))
+            .max_by_key(|(known, _)| known.len());
+        match prefix_match {
+            Some((_, window)) => *window,
+            None => {
+                tracing::warn!(
+                    model,
+                    window = DEFAULT_CONTEXT_WINDOW,
+                    "Unknown model, assuming the default context window; register it to use its real window"
+                );
+                DEFAULT_CONTEXT_WINDOW
+            }
+        }
     }
     
     /// Estimate token count (rough approximation: 1 token ≈ 4 characters)
@@ -0,0 +1,222 @@
+/// Cache of tool responses, keyed by tool and request hash
+
+use super::retention::{Clock, SystemClock};
+use crate::error::{OrchestratorError, Result};
+use sqlx::sqlite::SqlitePool;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Entries kept in memory before the least recently used is evicted, by default
+pub const DEFAULT_TOOL_CACHE_CAPACITY: usize = 1000;
+
+/// Hash of a request with runs of whitespace collapsed and the ends trimmed
+///
+/// Stable across processes, so it can key persisted entries.
+pub fn request_hash(request: &str) -> String {
+    let normalized = request.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{:x}", md5::compute(normalized))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CachedResponse {
+    response: String,
+    expires_at: Option<i64>, // Unix seconds; None never expires
+}
+
+impl CachedResponse {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+type CacheKey = (String, String); // (tool, request hash)
+
+/// Least recently used entries, in memory
+struct Lru {
+    entries: HashMap<CacheKey, CachedResponse>,
+    order: VecDeque<CacheKey>, // Least recently used first
+    capacity: usize,
+}
+
+impl Lru {
+    fn get(&mut self, key: &CacheKey) -> Option<CachedResponse> {
+        let entry = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(entry)
+    }
+
+    fn put(&mut self, key: CacheKey, entry: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), entry).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if self.entries.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(position) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
+
+/// Responses of earlier tool calls, reused when the same request is made again
+///
+/// Entries live in an in-memory LRU. With a database (`from_pool`) they are
+/// also written through to the `tool_cache` table, so they survive restarts;
+/// memory misses fall back to it.
+pub struct ToolCallCache {
+    lru: Mutex<Lru>,
+    pool: Option<SqlitePool>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ToolCallCache {
+    /// In-memory cache of at most `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lru: Mutex::new(Lru {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+                capacity,
+            }),
+            pool: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Cache persisted in `pool`, with the default in-memory capacity
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS tool_cache (
+                tool TEXT NOT NULL,
+                request_hash TEXT NOT NULL,
+                response TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                expires_at INTEGER,
+                PRIMARY KEY (tool, request_hash)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        let mut cache = Self::new(DEFAULT_TOOL_CACHE_CAPACITY);
+        cache.pool = Some(pool);
+        Ok(cache)
+    }
+
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        self.lru.lock().unwrap().capacity = capacity;
+        self
+    }
+
+    /// Time source for expiry; `MockClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Cached response of `tool` for a request with this `request_hash`, if fresh
+    pub async fn get(&self, tool: &str, request_hash: &str) -> Result<Option<String>> {
+        let key = (tool.to_string(), request_hash.to_string());
+        let now = self.clock.now();
+
+        let cached = self.lru.lock().unwrap().get(&key);
+        if let Some(entry) = cached {
+            if !entry.is_expired(now) {
+                return Ok(Some(entry.response));
+            }
+            self.remove(&key).await?;
+            return Ok(None);
+        }
+
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return Ok(None),
+        };
+        let row: Option<(String, Option<i64>)> = sqlx::query_as(
+            "SELECT response, expires_at FROM tool_cache WHERE tool = ? AND request_hash = ?",
+        )
+        .bind(tool)
+        .bind(request_hash)
+        .fetch_optional(pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        let entry = match row {
+            Some((response, expires_at)) => CachedResponse { response, expires_at },
+            None => return Ok(None),
+        };
+        if entry.is_expired(now) {
+            self.remove(&key).await?;
+            return Ok(None);
+        }
+        self.lru.lock().unwrap().put(key, entry.clone());
+        Ok(Some(entry.response))
+    }
+
+    /// Cache `response` for `ttl`, or until evicted if None
+    pub async fn put(&self, tool: &str, request_hash: &str, response: &str, ttl: Option<Duration>) -> Result<()> {
+        let now = self.clock.now();
+        let entry = CachedResponse {
+            response: response.to_string(),
+            expires_at: ttl.map(|ttl| now + ttl.as_secs() as i64),
+        };
+
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO tool_cache (tool, request_hash, response, created_at, expires_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(tool)
+            .bind(request_hash)
+            .bind(response)
+            .bind(now)
+            .bind(entry.expires_at)
+            .execute(pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+        }
+
+        self.lru
+            .lock()
+            .unwrap()
+            .put((tool.to_string(), request_hash.to_string()), entry);
+        Ok(())
+    }
+
+    async fn remove(&self, key: &CacheKey) -> Result<()> {
+        self.lru.lock().unwrap().remove(key);
+        if let Some(pool) = &self.pool {
+            sqlx::query("DELETE FROM tool_cache WHERE tool = ? AND request_hash = ?")
+                .bind(&key.0)
+                .bind(&key.1)
+                .execute(pool)
+                .await
+                .map_err(OrchestratorError::from)?;
+        }
+        Ok(())
+    }
+}
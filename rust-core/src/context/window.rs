@@ -1,39 +1,180 @@
 /// Context window management
 
-use crate::context::{Context, Message};
+use crate::context::{Context, Message, Role, WindowState};
 use crate::context::token_counter::TokenCounter;
 use crate::context::summarizer::ContextSummarizer;
+use crate::context::importance::{ImportanceConfig, ImportanceScorer};
+use crate::context::diff::{diff_contexts, ContextDiff};
+use crate::models::ModelCatalog;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Tokens of a model's window kept free of conversation messages
+///
+/// A plain number converts to an output-only budget, so
+/// `ContextWindowManager::new(1000)` reserves 1000 tokens for the response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReservedBudget {
+    pub output: usize, // The model's response
+    pub system_suffix: usize, // System prompt the caller appends after window management
+    pub tool_schemas: usize, // Tool definitions sent with the request
+    pub safety_margin: usize, // Slack for token estimation error
+}
+
+impl ReservedBudget {
+    pub fn output(output: usize) -> Self {
+        Self {
+            output,
+            ..Self::default()
+        }
+    }
+    
+    /// Every part summed; the tokens unavailable to messages
+    pub fn total(&self) -> usize {
+        self.output
+            .saturating_add(self.system_suffix)
+            .saturating_add(self.tool_schemas)
+            .saturating_add(self.safety_margin)
+    }
+}
+
+impl From<usize> for ReservedBudget {
+    fn from(output: usize) -> Self {
+        Self::output(output)
+    }
+}
+
+/// What one `manage_context` call did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowOutcome {
+    pub summarized: bool,
+    pub dropped_messages: usize, // Net messages removed by summarization and truncation
+    /// The model's window is larger than the previous model's and messages
+    /// were dropped before, so restoring archived messages may be worthwhile
+    pub suggest_restore: bool,
+}
 
 pub struct ContextWindowManager {
     token_counter: TokenCounter,
     summarizer: ContextSummarizer,
-    reserved_tokens: usize, // Reserve tokens for response
+    reserved: ReservedBudget, // For models without their own budget
+    model_reserved: HashMap<String, ReservedBudget>,
 }
 
 impl ContextWindowManager {
-    pub fn new(reserved_tokens: usize) -> Self {
+    /// Reserve `reserved` tokens of every window; a plain number is the output budget
+    pub fn new(reserved: impl Into<ReservedBudget>) -> Self {
         Self {
             token_counter: TokenCounter::new(),
             summarizer: ContextSummarizer::default(),
-            reserved_tokens,
+            reserved: reserved.into(),
+            model_reserved: HashMap::new(),
         }
     }
     
+    pub fn with_summarizer(mut self, summarizer: ContextSummarizer) -> Self {
+        self.summarizer = summarizer;
+        self
+    }
+    
+    /// Score messages with `config` when summarizing and truncating
+    pub fn with_importance(mut self, config: ImportanceConfig) -> Self {
+        self.summarizer = std::mem::take(&mut self.summarizer).with_importance(config);
+        self
+    }
+    
+    /// The summarizer's scorer, which truncation also uses
+    pub fn importance(&self) -> &ImportanceScorer {
+        self.summarizer.importance()
+    }
+    
+    /// Accept model aliases from `catalog`; see `TokenCounter::with_catalog`
+    pub fn with_model_catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.token_counter = std::mem::take(&mut self.token_counter).with_catalog(catalog);
+        self
+    }
+    
+    /// Use `window` tokens for `model`; see `TokenCounter::register_model`
+    pub fn register_model(&mut self, model: impl Into<String>, window: usize) {
+        self.token_counter.register_model(model, window);
+    }
+    
+    /// Reserve `reserved` of `model`'s window instead of the default budget
+    ///
+    /// Like windows, the budget also applies to dated or suffixed variants
+    /// of `model` without their own.
+    pub fn register_model_reserved(&mut self, model: impl Into<String>, reserved: ReservedBudget) {
+        self.model_reserved.insert(model.into(), reserved);
+    }
+    
+    pub fn context_window(&self, model: &str) -> usize {
+        self.token_counter.get_context_window(model)
+    }
+    
+    /// The budget reserved for `model`: its own, the longest registered prefix's, or the default
+    pub fn reserved_budget(&self, model: &str) -> ReservedBudget {
+        if let Some(reserved) = self.model_reserved.get(model) {
+            return *reserved;
+        }
+        self.model_reserved
+            .iter()
+            .filter(|(known, _)| model.starts_with(known.as_str()))
+            .max_by_key(|(known, _)| known.len())
+            .map(|(_, reserved)| *reserved)
+            .unwrap_or(self.reserved)
+    }
+    
+    /// Tokens of `model`'s window left for messages once its budget is reserved
+    pub fn available_tokens(&self, model: &str) -> usize {
+        self.context_window(model).saturating_sub(self.reserved_budget(model).total())
+    }
+    
     /// Manage context window for a model
-    pub fn manage_context(&self, context: &mut Context, model: &str) {
+    ///
+    /// Updates the context's `window_state` with the model and what was dropped.
+    pub fn manage_context(&self, context: &mut Context, model: &str) -> WindowOutcome {
+        let messages_before = context.messages.len();
+        let previous = context.window_state.clone().unwrap_or_default();
+        
         // First, try summarization if needed
-        self.summarizer.summarize_if_needed(context);
+        let summarized = self.summarizer.summarize_if_needed(context).is_some();
         
         // Then check token limits
         let window_size = self.token_counter.get_context_window(model);
+        let available_tokens = self.available_tokens(model);
         let current_tokens = self.estimate_context_tokens(context);
         
-        if current_tokens + self.reserved_tokens > window_size {
+        if current_tokens > available_tokens {
             // Need to truncate
-            self.truncate_context(context, model, window_size);
+            self.truncate_context(context, available_tokens);
+        }
+        
+        let dropped_messages = messages_before.saturating_sub(context.messages.len());
+        let state = context.window_state.get_or_insert_with(WindowState::default);
+        state.dropped_messages += dropped_messages;
+        if summarized {
+            state.last_summary_at = Some(chrono::Utc::now().timestamp());
+        }
+        state.last_model = Some(model.to_string());
+        state.last_window = window_size;
+        
+        WindowOutcome {
+            summarized,
+            dropped_messages,
+            suggest_restore: previous.last_model.is_some()
+                && window_size > previous.last_window
+                && previous.dropped_messages > 0,
         }
     }
     
+    /// `manage_context`, also returning what it changed
+    pub fn manage_context_explained(&self, context: &mut Context, model: &str) -> ContextDiff {
+        let before = context.clone();
+        self.manage_context(context, model);
+        diff_contexts(&before, context)
+    }
+    
     /// Estimate total tokens in context
     fn estimate_context_tokens(&self, context: &Context) -> usize {
         let mut total = 0;
@@ -47,15 +188,13 @@ impl ContextWindowManager {
     }
     
     /// Truncate context to fit within window with importance-based retention
-    fn truncate_context(&self, context: &mut Context, model: &str, window_size: usize) {
-        let available_tokens = window_size - self.reserved_tokens;
-        
+    fn truncate_context(&self, context: &mut Context, available_tokens: usize) {
         // Score messages by importance
         let mut scored_messages: Vec<(usize, f32, Message)> = context.messages
             .iter()
             .enumerate()
             .map(|(idx, msg)| {
-                let importance = self.score_message_importance(msg, idx, context.messages.len());
+                let importance = self.score_message_importance(&context.messages, idx);
                 (idx, importance, msg.clone())
             })
             .collect();
@@ -79,14 +218,8 @@ impl ContextWindowManager {
             
             let tokens = self.token_counter.estimate_tokens(&message.content) + 4;
             
-            // Always keep system messages if possible
-            if message.role == "system" && token_count + tokens <= available_tokens {
-                kept_messages.push((*idx, message.clone()));
-                kept_indices.insert(*idx);
-                token_count += tokens;
-            }
-            // Keep high-importance messages
-            else if importance > 0.7 && token_count + tokens <= available_tokens {
+            // Always keep system messages if possible, then high-importance ones
+            if (message.role == Role::System || *importance > 0.7) && token_count + tokens <= available_tokens {
                 kept_messages.push((*idx, message.clone()));
                 kept_indices.insert(*idx);
                 token_count += tokens;
@@ -119,36 +252,16 @@ impl ContextWindowManager {
         context.messages = kept_messages.into_iter().map(|(_, msg)| msg).collect();
     }
     
-    /// Score message importance (0.0 to 1.0)
-    fn score_message_importance(&self, message: &Message, position: usize, total: usize) -> f32 {
-        let mut score = 0.5; // Base score
-        
-        // System messages are always important
-        if message.role == "system" {
-            score = 1.0;
+    /// Score message importance (0.0 to 1.0), weighting by position
+    ///
+    /// System messages score 1.0 so the first pass reaches them before any
+    /// other message can use up the budget.
+    fn score_message_importance(&self, messages: &[Message], position: usize) -> f32 {
+        if messages[position].role == Role::System {
+            return 1.0;
         }
-        
-        // Recent messages are more important
-        let recency = 1.0 - (position as f32 / total as f32);
-        score += recency * 0.3;
-        
-        // Messages with code blocks are important
-        if message.content.contains("```") {
-            score += 0.2;
-        }
-        
-        // Messages with keywords indicating importance
-        let content_lower = message.content.to_lowercase();
-        let important_keywords = ["error", "bug", "fix", "important", "decided", "decision", "todo", "fixme"];
-        for keyword in important_keywords {
-            if content_lower.contains(keyword) {
-                score += 0.1;
-                break;
-            }
-        }
-        
-        // Cap at 1.0
-        score.min(1.0)
+        let recency = 1.0 - (position as f32 / messages.len() as f32);
+        (self.importance().score(messages, position) + recency * 0.3).min(1.0)
     }
 }
 
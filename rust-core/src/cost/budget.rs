@@ -0,0 +1,87 @@
+/// Spending limits applied before dispatching a request
+
+use super::calculator::CostCalculator;
+use super::storage::CostStorage;
+use crate::error::Result;
+use crate::projects::ProjectStore;
+use chrono::{Datelike, NaiveTime, Utc};
+
+/// Caps output tokens so a request cannot overrun a project's budget
+pub struct BudgetManager {
+    storage: CostStorage,
+    calculator: CostCalculator,
+    daily_budget_usd: f64, // 0.0 = unlimited
+    monthly_budget_usd: f64, // 0.0 = unlimited
+    project_registry: Option<ProjectStore>, // Projects' own `budget_usd` replaces the monthly budget
+}
+
+impl BudgetManager {
+    pub fn new(storage: CostStorage, calculator: CostCalculator) -> Self {
+        Self {
+            storage,
+            calculator,
+            daily_budget_usd: 0.0,
+            monthly_budget_usd: 0.0,
+            project_registry: None,
+        }
+    }
+    
+    pub fn with_daily_budget(mut self, budget_usd: f64) -> Self {
+        self.daily_budget_usd = budget_usd;
+        self
+    }
+    
+    pub fn with_monthly_budget(mut self, budget_usd: f64) -> Self {
+        self.monthly_budget_usd = budget_usd;
+        self
+    }
+    
+    pub fn with_project_registry(mut self, projects: ProjectStore) -> Self {
+        self.project_registry = Some(projects);
+        self
+    }
+    
+    pub fn calculator(&self) -> &CostCalculator {
+        &self.calculator
+    }
+    
+    /// Budget left for a project today and this month (UTC), whichever is less
+    ///
+    /// None if neither budget is limited. Negative once a budget is overrun.
+    pub async fn remaining_budget(&self, project_id: &str) -> Result<Option<f64>> {
+        let mut monthly_budget = self.monthly_budget_usd;
+        if let Some(projects) = &self.project_registry {
+            if let Some(budget) = projects.get(project_id).await?.and_then(|p| p.settings.budget_usd) {
+                monthly_budget = budget;
+            }
+        }
+        
+        let now = Utc::now();
+        let today = now.date_naive();
+        let mut remaining: Option<f64> = None;
+        for (budget, since) in [
+            (self.daily_budget_usd, today),
+            (monthly_budget, today.with_day(1).unwrap_or(today)),
+        ] {
+            if budget <= 0.0 {
+                continue;
+            }
+            let start = since.and_time(NaiveTime::MIN).and_utc();
+            let spent = self.storage.get_total_cost(start, now, None, Some(project_id), None).await?;
+            let left = budget - spent;
+            remaining = Some(remaining.map_or(left, |r| r.min(left)));
+        }
+        Ok(remaining)
+    }
+    
+    /// Output token cap for a request about to be sent for `project_id`
+    ///
+    /// `u32::MAX` when the project's budgets are unlimited. None if `model`
+    /// has no known pricing; see `CostCalculator::max_affordable_output_tokens`.
+    pub async fn affordable_output(&self, project_id: &str, model: &str, input_tokens: u32) -> Result<Option<u32>> {
+        match self.remaining_budget(project_id).await? {
+            Some(remaining) => Ok(self.calculator.max_affordable_output_tokens(model, remaining, input_tokens)),
+            None => Ok(self.calculator.pricing_table().get_model_pricing(model).map(|_| u32::MAX)),
+        }
+    }
+}
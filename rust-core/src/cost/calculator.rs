@@ -11,6 +11,15 @@ impl CostCalculator {
         }
     }
     
+    pub fn with_pricing_table(mut self, pricing_table: PricingTable) -> Self {
+        self.pricing_table = pricing_table;
+        self
+    }
+    
+    pub fn pricing_table(&self) -> &PricingTable {
+        &self.pricing_table
+    }
+    
     pub fn calculate(
         &self,
         tool: &str,
@@ -20,6 +29,29 @@ impl CostCalculator {
     ) -> f64 {
         self.pricing_table.calculate_cost(tool, model, input_tokens, output_tokens)
     }
+    
+    /// Most output tokens affordable with what the input leaves of `remaining_budget_usd`
+    ///
+    /// 0 if the input alone uses up the budget, `u32::MAX` for models with
+    /// free output. None if the model has no known pricing, so callers
+    /// cannot mistake an unpriced model for an unlimited one.
+    pub fn max_affordable_output_tokens(
+        &self,
+        model: &str,
+        remaining_budget_usd: f64,
+        input_tokens: u32,
+    ) -> Option<u32> {
+        let pricing = self.pricing_table.get_model_pricing(model)?;
+        // In millionths of a dollar, so per-1M prices apply per token
+        let left = remaining_budget_usd * 1_000_000.0 - input_tokens as f64 * pricing.input_price_per_1m;
+        if left <= 0.0 {
+            return Some(0);
+        }
+        if pricing.output_price_per_1m <= 0.0 {
+            return Some(u32::MAX);
+        }
+        Some((left / pricing.output_price_per_1m).floor().min(u32::MAX as f64) as u32)
+    }
 }
 
 impl Default for CostCalculator {
@@ -1,7 +1,9 @@
 pub mod calculator;
 pub mod storage;
 pub mod pricing;
+pub mod budget;
 
 pub use calculator::CostCalculator;
 pub use storage::CostStorage;
 pub use pricing::PricingTable;
+pub use budget::BudgetManager;
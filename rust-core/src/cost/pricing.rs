@@ -1,3 +1,4 @@
+use crate::models::{ModelCatalog, ModelEntry};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +11,7 @@ pub struct ModelPricing {
 #[derive(Debug, Clone)]
 pub struct PricingTable {
     prices: HashMap<String, ModelPricing>,
+    catalog: ModelCatalog, // Aliases accepted in place of model names
 }
 
 impl PricingTable {
@@ -87,10 +89,22 @@ impl PricingTable {
             },
         );
         
-        Self { prices }
+        Self {
+            prices,
+            catalog: ModelCatalog::new(),
+        }
+    }
+    
+    /// Price catalog aliases, and models with a pricing reference, by their pricing entry
+    pub fn with_catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.catalog = catalog;
+        self
     }
     
+    /// Pricing of `model`, which may be a catalog alias, as served by `tool`
     pub fn get_pricing(&self, tool: &str, model: &str) -> Option<&ModelPricing> {
+        let model = self.catalog.lookup(model).map_or(model, ModelEntry::pricing_model);
+        
         // Try tool-model combination first
         let key = format!("{}-{}", tool, model);
         if let Some(pricing) = self.prices.get(&key) {
@@ -101,6 +115,12 @@ impl PricingTable {
         self.prices.get(model)
     }
     
+    /// Pricing of `model`, which may be a catalog alias, whatever tool serves it
+    pub fn get_model_pricing(&self, model: &str) -> Option<&ModelPricing> {
+        let model = self.catalog.lookup(model).map_or(model, ModelEntry::pricing_model);
+        self.prices.get(model)
+    }
+    
     pub fn calculate_cost(
         &self,
         tool: &str,
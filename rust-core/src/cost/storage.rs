@@ -1,12 +1,22 @@
-use crate::error::{Result, OrchestratorError};
+use crate::error::{OrchestratorError, Result, ResultExt};
+use crate::labels::{label_conditions, label_path, validate_labels};
+use crate::pagination::{decode_cursor, encode_cursor, parse_field, Page};
+use crate::observability::MetricsCollector;
+use crate::projects::ProjectStore;
+use crate::storage::{add_column_if_missing, InstrumentedPool};
 use chrono::{DateTime, Utc};
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use tracing::Instrument;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CostRecord {
     pub id: Option<i64>,
+    /// Request that incurred the cost, when known
+    #[serde(default)]
+    pub request_id: Option<String>,
     pub tool: String,
     pub model: String,
     pub input_tokens: u32,
@@ -16,10 +26,13 @@ pub struct CostRecord {
     pub user_id: Option<String>,
     pub project_id: Option<String>,
     pub conversation_id: Option<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
 }
 
 pub struct CostStorage {
-    pool: SqlitePool,
+    pool: InstrumentedPool,
+    project_registry: Option<ProjectStore>, // Strict mode: records must name a registered project
 }
 
 impl CostStorage {
@@ -29,8 +42,14 @@ impl CostStorage {
                 .map_err(OrchestratorError::from)?;
         }
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
+        // Every connection to ":memory:" opens its own empty database, so
+        // keep a single connection alive for the life of the pool
+        let mut options = SqlitePoolOptions::new().max_connections(5);
+        if db_path.as_os_str() == ":memory:" {
+            options = options.max_connections(1).idle_timeout(None).max_lifetime(None);
+        }
+
+        let pool = options
             .connect_with(
                 sqlx::sqlite::SqliteConnectOptions::new()
                     .filename(&db_path)
@@ -39,11 +58,22 @@ impl CostStorage {
             .await
             .map_err(OrchestratorError::from)?;
 
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing pool, creating the cost_records table if needed
+    ///
+    /// A table created by migration 2 has different columns; migration 13
+    /// must run before such a database is used here.
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        let mut conn = pool.acquire().await.map_err(OrchestratorError::from)?;
+
         // Create cost_records table
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS cost_records (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                request_id TEXT,
                 tool TEXT NOT NULL,
                 model TEXT NOT NULL,
                 input_tokens INTEGER NOT NULL,
@@ -52,47 +82,71 @@ impl CostStorage {
                 timestamp INTEGER NOT NULL,
                 user_id TEXT,
                 project_id TEXT,
-                conversation_id TEXT
+                conversation_id TEXT,
+                labels TEXT NOT NULL DEFAULT '{}'
             )
             "#,
         )
-        .execute(&pool)
+        .execute(&mut *conn)
         .await
         .map_err(OrchestratorError::from)?;
+        add_column_if_missing(&mut conn, "cost_records", "labels", "TEXT NOT NULL DEFAULT '{}'").await?;
+        add_column_if_missing(&mut conn, "cost_records", "request_id", "TEXT").await?;
 
         // Create indexes
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_cost_timestamp ON cost_records(timestamp)"
         )
-        .execute(&pool)
+        .execute(&mut *conn)
         .await
         .map_err(OrchestratorError::from)?;
 
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_cost_tool ON cost_records(tool)"
         )
-        .execute(&pool)
+        .execute(&mut *conn)
         .await
         .map_err(OrchestratorError::from)?;
 
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_cost_user ON cost_records(user_id)"
         )
-        .execute(&pool)
+        .execute(&mut *conn)
         .await
         .map_err(OrchestratorError::from)?;
 
-        Ok(Self { pool })
+        Ok(Self { pool: InstrumentedPool::new(pool), project_registry: None })
+    }
+
+    /// Observe query timings in `metrics` as well as the process-wide log
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.pool = self.pool.with_metrics(metrics);
+        self
+    }
+
+    /// Reject records whose project is not registered in `projects`
+    pub fn with_project_registry(mut self, projects: ProjectStore) -> Self {
+        self.project_registry = Some(projects);
+        self
     }
 
     pub async fn record_cost(&self, record: &CostRecord) -> Result<()> {
+        validate_labels(&record.labels)?;
+        if let (Some(projects), Some(project_id)) = (&self.project_registry, &record.project_id) {
+            projects.ensure_exists(project_id).await?;
+        }
+        let breadcrumb = || format!("recording {} cost of request {:?}", record.tool, record.request_id);
+        let labels = serde_json::to_string(&record.labels).with_context(breadcrumb)?;
+        let span = tracing::info_span!("record_cost", request_id = record.request_id.as_deref(), tool = %record.tool);
+
         sqlx::query(
             r#"
             INSERT INTO cost_records 
-            (tool, model, input_tokens, output_tokens, cost_usd, timestamp, user_id, project_id, conversation_id)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            (request_id, tool, model, input_tokens, output_tokens, cost_usd, timestamp, user_id, project_id, conversation_id, labels)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
             "#,
         )
+        .bind(&record.request_id)
         .bind(&record.tool)
         .bind(&record.model)
         .bind(record.input_tokens as i64)
@@ -102,19 +156,32 @@ impl CostStorage {
         .bind(&record.user_id)
         .bind(&record.project_id)
         .bind(&record.conversation_id)
+        .bind(&labels)
         .execute(&self.pool)
+        .instrument(span)
         .await
-        .map_err(OrchestratorError::from)?;
+        .with_context(breadcrumb)?;
 
         Ok(())
     }
 
+    /// Delete every record of a project, returning how many were deleted
+    pub async fn delete_project_records(&self, project_id: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM cost_records WHERE project_id = ?")
+            .bind(project_id)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("deleting cost records of project {}", project_id))?;
+        Ok(result.rows_affected())
+    }
+
     pub async fn get_total_cost(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
         user_id: Option<&str>,
         project_id: Option<&str>,
+        labels_match: Option<&HashMap<String, String>>,
     ) -> Result<f64> {
         // Build query string first, accounting for optional filters
         let mut query = "SELECT SUM(cost_usd) as total FROM cost_records WHERE timestamp >= ?1 AND timestamp <= ?2".to_string();
@@ -130,6 +197,12 @@ impl CostStorage {
             query.push_str(&format!(" AND project_id = ?{}", param_count));
         }
         
+        let (label_sql, label_binds) = match labels_match {
+            Some(labels_match) => label_conditions(labels_match, param_count + 1)?,
+            None => (String::new(), Vec::new()),
+        };
+        query.push_str(&label_sql);
+        
         // Now create query builder with the complete query string
         let mut query_builder = sqlx::query_as::<_, (Option<f64>,)>(&query)
             .bind(start.timestamp())
@@ -143,11 +216,99 @@ impl CostStorage {
             query_builder = query_builder.bind(pid);
         }
 
+        for value in &label_binds {
+            query_builder = query_builder.bind(value);
+        }
+
         let row = query_builder
             .fetch_one(&self.pool)
             .await
-            .map_err(OrchestratorError::from)?;
+            .with_context(|| format!("totalling costs from {} to {}", start, end))?;
 
         Ok(row.0.unwrap_or(0.0))
     }
+
+    /// Total cost per value of one label; records without the label are skipped
+    pub async fn get_cost_by_label(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        label_key: &str,
+    ) -> Result<HashMap<String, f64>> {
+        let rows = sqlx::query_as::<_, (String, f64)>(
+            r#"
+            SELECT json_extract(labels, ?3) AS label_value, SUM(cost_usd)
+            FROM cost_records
+            WHERE timestamp >= ?1 AND timestamp <= ?2 AND json_extract(labels, ?3) IS NOT NULL
+            GROUP BY label_value
+            "#,
+        )
+        .bind(start.timestamp())
+        .bind(end.timestamp())
+        .bind(label_path(label_key)?)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// List cost records newest first, one page at a time
+    pub async fn list_records(
+        &self,
+        project_id: Option<&str>,
+        page_size: usize,
+        cursor: Option<String>,
+    ) -> Result<Page<CostRecord>> {
+        const KIND: &str = "cost_records";
+
+        let after_id = match cursor.as_deref() {
+            Some(cursor) => {
+                let fields = decode_cursor(KIND, cursor, 1)?;
+                Some(parse_field::<i64>(KIND, &fields[0])?)
+            }
+            None => None,
+        };
+
+        let rows = sqlx::query_as::<_, (i64, Option<String>, String, String, i64, i64, f64, i64, Option<String>, Option<String>, Option<String>, String)>(
+            r#"
+            SELECT id, request_id, tool, model, input_tokens, output_tokens, cost_usd, timestamp, user_id, project_id, conversation_id, labels
+            FROM cost_records
+            WHERE (?1 IS NULL OR project_id = ?1)
+              AND (?2 IS NULL OR id < ?2)
+            ORDER BY id DESC
+            LIMIT ?3
+            "#,
+        )
+        .bind(project_id)
+        .bind(after_id)
+        .bind(page_size as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        let items = rows
+            .into_iter()
+            .map(|(id, request_id, tool, model, input_tokens, output_tokens, cost_usd, timestamp, user_id, project_id, conversation_id, labels)| {
+                Ok(CostRecord {
+                    id: Some(id),
+                    request_id,
+                    tool,
+                    model,
+                    input_tokens: input_tokens as u32,
+                    output_tokens: output_tokens as u32,
+                    cost_usd,
+                    timestamp: DateTime::from_timestamp(timestamp, 0).unwrap_or_default(),
+                    user_id,
+                    project_id,
+                    conversation_id,
+                    labels: serde_json::from_str(&labels).map_err(OrchestratorError::from)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Page::from_items(items, page_size, |r| {
+            encode_cursor(KIND, &[r.id.unwrap_or_default().to_string()])
+        }))
+    }
 }
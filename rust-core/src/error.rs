@@ -3,6 +3,7 @@ use sqlx::Error as SqlxError;
 use reqwest::Error as ReqwestError;
 use serde_json::Error as JsonError;
 use std::io::Error as IoError;
+use crate::context::limits::SizeDimension;
 
 #[derive(Error, Debug)]
 pub enum OrchestratorError {
@@ -24,8 +25,8 @@ pub enum OrchestratorError {
     #[error("Rate limit exceeded: {0}")]
     RateLimitExceeded(String),
     
-    #[error("Context too large: {0} tokens (max: {1})")]
-    ContextTooLarge(usize, usize),
+    #[error("Context too large: {0} is {1} (max: {2})")]
+    ContextTooLarge(SizeDimension, usize, usize),
     
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
@@ -42,14 +43,83 @@ pub enum OrchestratorError {
     #[error("Circuit breaker open: {0}")]
     CircuitBreakerOpen(String),
     
+    #[error("Bulkhead full: {0}")]
+    BulkheadFull(String),
+    
+    #[error("All hedged attempts failed: {0}")]
+    HedgeFailed(String),
+    
     #[error("Invalid input: {0}")]
     InvalidInput(String),
     
     #[error("Indexing error: {0}")]
     Indexing(String),
     
+    #[error("Conflict detected: {0}")]
+    ConflictDetected(String),
+    
     #[error("Unknown error: {0}")]
     Unknown(String),
+    
+    /// An error with the operations that led to it, outermost first
+    #[error("{}: {}", .context.join(": "), .source)]
+    Contextualized {
+        context: Vec<String>,
+        source: Box<OrchestratorError>,
+    },
+}
+
+impl OrchestratorError {
+    /// Record the operation that failed, e.g. "loading context abc"
+    ///
+    /// Breadcrumbs added later are rendered first, so the outermost
+    /// operation leads the message.
+    pub fn with_context(self, msg: impl Into<String>) -> Self {
+        match self {
+            OrchestratorError::Contextualized { mut context, source } => {
+                context.insert(0, msg.into());
+                OrchestratorError::Contextualized { context, source }
+            }
+            err => OrchestratorError::Contextualized {
+                context: vec![msg.into()],
+                source: Box::new(err),
+            },
+        }
+    }
+    
+    /// The error without its breadcrumbs
+    pub fn root_cause(&self) -> &OrchestratorError {
+        match self {
+            OrchestratorError::Contextualized { source, .. } => source.root_cause(),
+            err => err,
+        }
+    }
+    
+    /// Operations that led to the error, outermost first; empty without context
+    pub fn breadcrumbs(&self) -> &[String] {
+        match self {
+            OrchestratorError::Contextualized { context, .. } => context,
+            _ => &[],
+        }
+    }
+}
+
+/// Attach breadcrumbs to any error convertible to `OrchestratorError`
+pub trait ResultExt<T> {
+    fn context(self, msg: impl Into<String>) -> Result<T>;
+    
+    /// `context`, building the message only on error
+    fn with_context<F: FnOnce() -> String>(self, msg: F) -> Result<T>;
+}
+
+impl<T, E: Into<OrchestratorError>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, msg: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().with_context(msg))
+    }
+    
+    fn with_context<F: FnOnce() -> String>(self, msg: F) -> Result<T> {
+        self.map_err(|e| e.into().with_context(msg()))
+    }
 }
 
 impl From<anyhow::Error> for OrchestratorError {
@@ -58,26 +128,39 @@ impl From<anyhow::Error> for OrchestratorError {
     }
 }
 
+pyo3::create_exception!(rust_core, ConflictError, pyo3::exceptions::PyRuntimeError);
+
 impl From<OrchestratorError> for pyo3::PyErr {
     fn from(err: OrchestratorError) -> Self {
         use pyo3::exceptions::*;
         match err {
+            OrchestratorError::Contextualized { context, source } => {
+                // Same exception type as the root cause, breadcrumbs leading the message
+                let breadcrumbs = context.join(": ");
+                pyo3::Python::with_gil(|py| {
+                    let inner = pyo3::PyErr::from(*source);
+                    pyo3::PyErr::from_type(inner.get_type(py), format!("{}: {}", breadcrumbs, inner.value(py)))
+                })
+            }
             OrchestratorError::Storage(e) => PyRuntimeError::new_err(format!("Storage error: {}", e)),
             OrchestratorError::Network(e) => PyConnectionError::new_err(format!("Network error: {}", e)),
             OrchestratorError::Serialization(e) => PyValueError::new_err(format!("Serialization error: {}", e)),
             OrchestratorError::Io(e) => PyIOError::new_err(format!("IO error: {}", e)),
             OrchestratorError::ToolUnavailable(msg) => PyRuntimeError::new_err(msg),
             OrchestratorError::RateLimitExceeded(msg) => PyRuntimeError::new_err(msg),
-            OrchestratorError::ContextTooLarge(current, max) => {
-                PyValueError::new_err(format!("Context too large: {} tokens (max: {})", current, max))
+            OrchestratorError::ContextTooLarge(dimension, current, max) => {
+                PyValueError::new_err(format!("Context too large: {} is {} (max: {})", dimension, current, max))
             }
             OrchestratorError::InvalidConfig(msg) => PyValueError::new_err(format!("Invalid config: {}", msg)),
             OrchestratorError::Authentication(msg) => PyPermissionError::new_err(format!("Authentication failed: {}", msg)),
             OrchestratorError::Authorization(msg) => PyPermissionError::new_err(format!("Authorization denied: {}", msg)),
             OrchestratorError::Timeout(msg) => PyTimeoutError::new_err(msg),
             OrchestratorError::CircuitBreakerOpen(msg) => PyRuntimeError::new_err(format!("Circuit breaker open: {}", msg)),
+            OrchestratorError::BulkheadFull(msg) => PyRuntimeError::new_err(format!("Bulkhead full: {}", msg)),
+            OrchestratorError::HedgeFailed(msg) => PyRuntimeError::new_err(format!("All hedged attempts failed: {}", msg)),
             OrchestratorError::InvalidInput(msg) => PyValueError::new_err(format!("Invalid input: {}", msg)),
             OrchestratorError::Indexing(msg) => PyRuntimeError::new_err(format!("Indexing error: {}", msg)),
+            OrchestratorError::ConflictDetected(msg) => ConflictError::new_err(format!("Conflict detected: {}", msg)),
             OrchestratorError::Unknown(msg) => PyRuntimeError::new_err(format!("Unknown error: {}", msg)),
         }
     }
@@ -1,17 +1,63 @@
 /// Codebase indexing logic
 
-use crate::indexer::parser::{ASTParser, CodeBlock};
-use crate::indexer::storage::IndexStorage;
-use std::path::{Path, PathBuf};
+use crate::indexer::diagnostics::{DiagnosticSource, Diagnostics, Severity};
+use crate::indexer::discovery::discover_via_git;
+use crate::indexer::embed_pool::{BlockEmbedder, EmbeddingPool, QUEUE_PER_WORKER};
+use crate::indexer::extraction_rules::ExtractionRules;
+use crate::indexer::import;
+use crate::indexer::parser::{retain_blocks, ASTParser, CodeBlock, ParseOutcome};
+use crate::indexer::semantic::EmbeddingGenerator;
+use crate::indexer::source::{decode_source, SkipReason, DEFAULT_MAX_REPLACEMENT_RATIO};
+use crate::indexer::storage::{content_hash, IndexStorage};
+use crate::indexer::summary::{extractive_summary, FileSummarizer};
+use crate::projects::ProjectStore;
+use crate::security::authz::{Authorizer, Permission, User};
+use std::path::{Component, Path, PathBuf};
 use std::collections::HashMap;
 use std::time::SystemTime;
 
+/// Result of indexing a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexOutcome {
+    Indexed,
+    /// Not indexed; any blocks stored for the file before were removed
+    Skipped(SkipReason),
+}
+
+/// Result of `CodebaseIndexer::index_directory_report`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexReport {
+    pub files_indexed: usize,
+    /// Embeddings computed and stored; 0 without `with_embedding_workers`
+    pub embeddings_completed: usize,
+    /// Blocks queued for embedding whose embedding was not stored
+    pub embeddings_pending: usize,
+}
+
+/// Embedding workers running during `index_directory_report`
+struct EmbeddingRun {
+    pool: EmbeddingPool,
+    model: Option<String>,
+    stored: usize,
+}
+
 pub struct CodebaseIndexer {
     parser: ASTParser,
     storage: IndexStorage,
     project_id: String,
+    root_path: Option<PathBuf>, // Paths are stored relative to this root
     indexed_files: HashMap<String, SystemTime>, // Track indexed files and their modification times
     skip_patterns: Vec<String>, // Patterns to skip (e.g., "*.log", "node_modules/**")
+    use_git_discovery: bool, // List directories with `git ls-files` instead of walking them
+    embedding_dim: Option<usize>, // Expected embedding size, checked by validate_index
+    embedding_gen: Option<EmbeddingGenerator>, // Used by reembed_all
+    summarizer: Option<Box<dyn FileSummarizer>>, // Replaces extractive summaries in summarize_files
+    authorizer: Authorizer, // Checks callers of the `_as` operations
+    diagnostics: Diagnostics, // Shared with the embedding generator and any watcher
+    max_replacement_ratio: f64, // Files with more invalid UTF-8 than this are skipped as binary
+    embedding_workers: Option<usize>, // Embed blocks while indexing directories on this many threads
+    embedding_run: Option<EmbeddingRun>,
+    project_registry: Option<ProjectStore>, // Strict mode: the project must be registered to index
 }
 
 impl CodebaseIndexer {
@@ -20,72 +66,356 @@ impl CodebaseIndexer {
             parser: ASTParser::new(),
             storage,
             project_id,
+            root_path: None,
             indexed_files: HashMap::new(),
-            skip_patterns: vec![
-                "node_modules".to_string(),
-                "target".to_string(),
-                ".git".to_string(),
-                "__pycache__".to_string(),
-                ".venv".to_string(),
-                "venv".to_string(),
-                ".env".to_string(),
-                "*.log".to_string(),
-                "*.tmp".to_string(),
-            ],
+            skip_patterns: default_skip_patterns(),
+            use_git_discovery: false,
+            embedding_dim: None,
+            embedding_gen: None,
+            summarizer: None,
+            authorizer: Authorizer::default(),
+            diagnostics: Diagnostics::new(),
+            max_replacement_ratio: DEFAULT_MAX_REPLACEMENT_RATIO,
+            embedding_workers: None,
+            embedding_run: None,
+            project_registry: None,
         }
     }
     
+    /// An indexer for `project_id` on the same storage, with this one's settings
+    ///
+    /// Skip patterns, extraction rules, discovery, embedding settings, the
+    /// authorizer, diagnostics and project registry carry over; the root,
+    /// the summarizer and what was indexed so far do not.
+    pub fn for_project(&self, project_id: String) -> Self {
+        Self {
+            parser: self.parser.fork(),
+            storage: self.storage.clone(),
+            project_id,
+            root_path: None,
+            indexed_files: HashMap::new(),
+            skip_patterns: self.skip_patterns.clone(),
+            use_git_discovery: self.use_git_discovery,
+            embedding_dim: self.embedding_dim,
+            embedding_gen: self.embedding_gen.as_ref().map(EmbeddingGenerator::fork),
+            summarizer: None,
+            authorizer: self.authorizer.clone(),
+            diagnostics: self.diagnostics.clone(),
+            max_replacement_ratio: self.max_replacement_ratio,
+            embedding_workers: self.embedding_workers,
+            embedding_run: None,
+            project_registry: self.project_registry.clone(),
+        }
+    }
+    
+    pub fn with_parser(mut self, parser: ASTParser) -> Self {
+        self.parser = parser;
+        self
+    }
+    
+    /// Parse with `rules` instead of the built-in node kinds
+    pub fn with_extraction_rules(mut self, rules: ExtractionRules) -> Self {
+        self.parser = self.parser.with_rules(rules);
+        self
+    }
+    
     pub fn with_skip_patterns(mut self, patterns: Vec<String>) -> Self {
         self.skip_patterns = patterns;
         self
     }
     
+    /// Find files with `git ls-files` when indexing a directory, falling back
+    /// to the directory walker outside a git work tree
+    pub fn with_git_discovery(mut self, enabled: bool) -> Self {
+        self.use_git_discovery = enabled;
+        self
+    }
+    
+    pub fn set_git_discovery(&mut self, enabled: bool) {
+        self.use_git_discovery = enabled;
+    }
+    
+    pub fn with_root(mut self, root_path: PathBuf) -> Self {
+        self.root_path = Some(canonical_root(&root_path));
+        self
+    }
+    
+    /// Flag stored embeddings that are not `embedding_dim` floats long
+    pub fn with_embedding_dim(mut self, embedding_dim: usize) -> Self {
+        self.embedding_dim = Some(embedding_dim);
+        self
+    }
+    
+    /// Embed the blocks stored by `index_directory` on `threads` worker threads
+    ///
+    /// Each worker gets a copy of the `with_embedding_generator` generator.
+    /// Parsing waits while the workers are behind, and indexing returns once
+    /// every embedding is stored. See `embed_pool::default_worker_count`.
+    pub fn with_embedding_workers(mut self, threads: usize) -> Self {
+        self.embedding_workers = Some(threads.max(1));
+        self
+    }
+    
+    /// Refuse to index unless the project is registered in `projects`
+    pub fn with_project_registry(mut self, projects: ProjectStore) -> Self {
+        self.project_registry = Some(projects);
+        self
+    }
+    
+    /// Generator used by `reembed_all` and embedding workers; defaults to hash embeddings of `embedding_dim` floats
+    pub fn with_embedding_generator(mut self, embedding_gen: EmbeddingGenerator) -> Self {
+        self.embedding_gen = Some(embedding_gen.with_diagnostics(self.diagnostics.clone()));
+        self
+    }
+    
+    /// Summarize files with `summarizer` instead of extractively
+    pub fn with_summarizer(mut self, summarizer: Box<dyn FileSummarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+    
+    /// Role mapping and audit trail used by `clear_index_as`
+    pub fn with_authorizer(mut self, authorizer: Authorizer) -> Self {
+        self.authorizer = authorizer;
+        self
+    }
+    
+    /// Record problems in `diagnostics` instead of a sink of the indexer's own
+    pub fn with_diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.embedding_gen = self.embedding_gen.map(|g| g.with_diagnostics(diagnostics.clone()));
+        self.diagnostics = diagnostics;
+        self
+    }
+    
+    /// Share of characters that may be invalid UTF-8 before a file is skipped as binary
+    pub fn with_max_replacement_ratio(mut self, ratio: f64) -> Self {
+        self.max_replacement_ratio = ratio;
+        self
+    }
+    
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+    
+    /// The generator set with `with_embedding_generator`, or created on first use
+    pub fn embedding_generator(&self) -> Option<&EmbeddingGenerator> {
+        self.embedding_gen.as_ref()
+    }
+    
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+    
+    pub fn storage(&self) -> &IndexStorage {
+        &self.storage
+    }
+    
+    pub fn root_path(&self) -> Option<&Path> {
+        self.root_path.as_deref()
+    }
+    
+    /// Path as stored in the index: relative to the root, with '/' separators
+    fn relative_path(&self, file_path: &Path) -> String {
+        match &self.root_path {
+            Some(root) => relative_to_root(&root.to_string_lossy(), &canonical_parent(file_path).to_string_lossy()),
+            None => normalize_path(&file_path.to_string_lossy()),
+        }
+    }
+    
+    /// On-disk location of a path stored in the index
+    fn disk_path(&self, stored_path: &str) -> PathBuf {
+        match &self.root_path {
+            Some(root) => root.join(stored_path),
+            None => PathBuf::from(stored_path),
+        }
+    }
+    
     pub async fn index_directory(&mut self, root_path: &Path) -> Result<usize, String> {
+        Ok(self.index_directory_report(root_path).await?.files_indexed)
+    }
+    
+    /// `index_directory`, also embedding the stored blocks if embedding workers are configured
+    pub async fn index_directory_report(&mut self, root_path: &Path) -> Result<IndexReport, String> {
+        self.ensure_project_registered().await?;
+        self.start_embedding_run();
+        let indexed = self.index_path(root_path).await;
+        let run = self.embedding_run.take();
+        let mut report = IndexReport {
+            files_indexed: indexed?,
+            ..Default::default()
+        };
+        
+        if let Some(run) = run {
+            let submitted = run.pool.stats().submitted;
+            let remaining = run.pool.finish().await?;
+            let stored = store_embeddings(&self.storage, &self.diagnostics, run.model.as_deref(), remaining).await;
+            report.embeddings_completed = run.stored + stored;
+            report.embeddings_pending = submitted - report.embeddings_completed;
+        }
+        
+        Ok(report)
+    }
+    
+    /// With a project registry, fail unless this indexer's project is registered
+    async fn ensure_project_registered(&self) -> Result<(), String> {
+        match &self.project_registry {
+            Some(projects) => projects.ensure_exists(&self.project_id).await.map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+    
+    /// Start embedding workers for `index_directory_report`, if configured
+    fn start_embedding_run(&mut self) {
+        let threads = match self.embedding_workers {
+            Some(threads) => threads,
+            None => return,
+        };
+        let embedding_dim = self.embedding_dim;
+        let diagnostics = self.diagnostics.clone();
+        let embedding_gen = self.embedding_gen.get_or_insert_with(|| {
+            embedding_dim.map(EmbeddingGenerator::new).unwrap_or_default().with_diagnostics(diagnostics)
+        });
+        let model = embedding_gen.model_name();
+        let pool = EmbeddingPool::new(threads, threads * QUEUE_PER_WORKER, || {
+            Box::new(embedding_gen.fork()) as Box<dyn BlockEmbedder>
+        });
+        self.embedding_run = Some(EmbeddingRun { pool, model, stored: 0 });
+    }
+    
+    /// Hand a stored file's unembedded blocks to the running workers and store what they finished
+    async fn queue_embeddings(&mut self, relative_path: &str) -> Result<(), String> {
+        let run = match self.embedding_run.as_mut() {
+            Some(run) => run,
+            None => return Ok(()),
+        };
+        let blocks = self.storage.blocks_without_embedding(&self.project_id, relative_path).await
+            .map_err(|e| format!("Failed to load blocks to embed: {}", e))?;
+        for (block_id, block) in blocks {
+            run.pool.submit(block_id, block).await?;
+        }
+        
+        let ready = run.pool.take_completed();
+        let stored = store_embeddings(&self.storage, &self.diagnostics, run.model.as_deref(), ready).await;
+        run.stored += stored;
+        Ok(())
+    }
+    
+    async fn index_path(&mut self, root_path: &Path) -> Result<usize, String> {
         let mut indexed_count = 0;
         let mut errors = Vec::new();
         
+        if self.root_path.is_none() && root_path.is_dir() {
+            self.root_path = Some(canonical_root(root_path));
+        }
+        
         // Walk directory and index files
         if root_path.is_dir() {
-            self.index_directory_recursive(root_path, &mut indexed_count, &mut errors).await?;
+            match self.discover_files(root_path).await {
+                Some(files) => {
+                    self.index_listed_files(files.into_iter(), Some(root_path), &mut indexed_count, &mut errors).await
+                }
+                None => self.index_directory_recursive(root_path, &mut indexed_count, &mut errors).await?,
+            }
         } else if root_path.is_file() {
             match self.index_file(root_path).await {
-                Ok(_) => indexed_count += 1,
+                Ok(IndexOutcome::Indexed) => indexed_count += 1,
+                Ok(IndexOutcome::Skipped(_)) => {}
                 Err(e) => errors.push(format!("Failed to index {}: {}", root_path.display(), e)),
             }
         }
         
-        // Log errors but don't fail completely
-        if !errors.is_empty() {
-            eprintln!("Indexing completed with {} errors:", errors.len());
-            for error in &errors[..errors.len().min(10)] {
-                eprintln!("  {}", error);
-            }
-            if errors.len() > 10 {
-                eprintln!("  ... and {} more errors", errors.len() - 10);
-            }
+        // Report errors but don't fail completely
+        for error in errors {
+            self.diagnostics.error(DiagnosticSource::Indexer, None, error);
+        }
+        
+        Ok(indexed_count)
+    }
+    
+    /// Index the given files instead of walking a directory
+    ///
+    /// Hidden paths, skip patterns and unknown languages are filtered out as
+    /// in `index_directory`; paths that are not files are ignored.
+    pub async fn index_from_file_list(&mut self, paths: impl Iterator<Item = PathBuf>) -> Result<usize, String> {
+        self.ensure_project_registered().await?;
+        let mut indexed_count = 0;
+        let mut errors = Vec::new();
+        
+        let root = self.root_path.clone();
+        self.index_listed_files(paths, root.as_deref(), &mut indexed_count, &mut errors).await;
+        
+        for error in errors {
+            self.diagnostics.error(DiagnosticSource::Indexer, None, error);
         }
         
         Ok(indexed_count)
     }
     
+    /// Files under `root_path` from git, if git discovery is enabled and works there
+    async fn discover_files(&self, root_path: &Path) -> Option<Vec<PathBuf>> {
+        if !self.use_git_discovery {
+            return None;
+        }
+        match discover_via_git(root_path).await {
+            Ok(files) => Some(files),
+            Err(e) => {
+                self.diagnostics.warn(
+                    DiagnosticSource::Indexer,
+                    None,
+                    format!("Git discovery unavailable, walking {} instead: {}", root_path.display(), e),
+                );
+                None
+            }
+        }
+    }
+    
+    /// Index `paths`, judging hidden names below `root`
+    async fn index_listed_files(
+        &mut self,
+        paths: impl Iterator<Item = PathBuf>,
+        root: Option<&Path>,
+        count: &mut usize,
+        errors: &mut Vec<String>,
+    ) {
+        for path in paths {
+            if is_hidden(&path, root) || self.should_skip_file(&path) || !path.is_file() {
+                continue;
+            }
+            if ASTParser::detect_language(&path).is_none() {
+                continue;
+            }
+            match self.index_file(&path).await {
+                Ok(IndexOutcome::Indexed) => *count += 1,
+                Ok(IndexOutcome::Skipped(_)) => {}
+                Err(e) => errors.push(format!("Failed to index {}: {}", path.display(), e)),
+            }
+        }
+    }
+    
     /// Incremental indexing - only index changed files
     pub async fn index_incremental(&mut self, root_path: &Path) -> Result<usize, String> {
+        self.ensure_project_registered().await?;
         let mut indexed_count = 0;
         let mut errors = Vec::new();
         
+        if self.root_path.is_none() && root_path.is_dir() {
+            self.root_path = Some(canonical_root(root_path));
+        }
+        
         if root_path.is_dir() {
             self.index_directory_recursive_incremental(root_path, &mut indexed_count, &mut errors).await?;
         } else if root_path.is_file() {
             if self.should_index_file(root_path).await? {
                 match self.index_file(root_path).await {
-                    Ok(_) => indexed_count += 1,
+                    Ok(IndexOutcome::Indexed) => indexed_count += 1,
+                    Ok(IndexOutcome::Skipped(_)) => {}
                     Err(e) => errors.push(format!("Failed to index {}: {}", root_path.display(), e)),
                 }
             }
         }
         
-        if !errors.is_empty() {
-            eprintln!("Incremental indexing completed with {} errors", errors.len());
+        for error in errors {
+            self.diagnostics.error(DiagnosticSource::Indexer, None, error);
         }
         
         Ok(indexed_count)
@@ -104,7 +434,7 @@ impl CodebaseIndexer {
         let modified_time = metadata.modified()
             .map_err(|e| format!("Failed to get modification time: {}", e))?;
         
-        let file_key = file_path.to_string_lossy().to_string();
+        let file_key = self.relative_path(file_path);
         if let Some(&last_indexed) = self.indexed_files.get(&file_key) {
             // Only re-index if file was modified after last indexing
             return Ok(modified_time > last_indexed);
@@ -181,7 +511,8 @@ impl CodebaseIndexer {
             } else if path.is_file() {
                 if let Some(language) = ASTParser::detect_language(&path) {
                     match self.index_file(&path).await {
-                        Ok(_) => *count += 1,
+                        Ok(IndexOutcome::Indexed) => *count += 1,
+                        Ok(IndexOutcome::Skipped(_)) => {}
                         Err(e) => {
                             errors.push(format!("Failed to index {}: {}", path.display(), e));
                             // Continue with other files
@@ -230,7 +561,8 @@ impl CodebaseIndexer {
                 if let Some(_language) = ASTParser::detect_language(&path) {
                     if let Ok(true) = self.should_index_file(&path).await {
                         match self.index_file(&path).await {
-                            Ok(_) => *count += 1,
+                            Ok(IndexOutcome::Indexed) => *count += 1,
+                            Ok(IndexOutcome::Skipped(_)) => {}
                             Err(e) => errors.push(format!("Failed to index {}: {}", path.display(), e)),
                         }
                     }
@@ -241,54 +573,109 @@ impl CodebaseIndexer {
         Ok(())
     }
     
-    pub async fn index_file(&mut self, file_path: &Path) -> Result<(), String> {
+    /// Read a file as text; Err(reason) if it is binary
+    ///
+    /// Files decoded lossily are recorded as a warning diagnostic.
+    fn read_source(&self, file_path: &Path, relative_path: &str) -> Result<Result<String, SkipReason>, String> {
+        let bytes = std::fs::read(file_path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        
+        match decode_source(&bytes, self.max_replacement_ratio) {
+            Ok(source) => {
+                if source.is_lossy() {
+                    self.diagnostics.warn(
+                        DiagnosticSource::Indexer,
+                        Some(relative_path),
+                        format!("Indexed lossily: replaced {} invalid UTF-8 sequences or NUL bytes", source.replacements),
+                    );
+                }
+                Ok(Ok(source.content))
+            }
+            Err(reason) => Ok(Err(reason)),
+        }
+    }
+    
+    /// Index a file; binary files are skipped and recorded as an info diagnostic
+    pub async fn index_file(&mut self, file_path: &Path) -> Result<IndexOutcome, String> {
+        self.ensure_project_registered().await?;
         let language = ASTParser::detect_language(file_path)
             .ok_or_else(|| "Unknown language".to_string())?;
+        let relative_path = self.relative_path(file_path);
         
         // Read file content
-        let content = std::fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let content = match self.read_source(file_path, &relative_path)? {
+            Ok(content) => content,
+            Err(reason) => return self.skip_file(relative_path, reason).await,
+        };
         
         // Parse AST (with error recovery)
-        let blocks = match self.parser.parse_file(&content, &language) {
+        let blocks = match self.parser.parse_file_cached(&relative_path, &content, &language) {
             Ok(blocks) => blocks,
             Err(e) => {
                 // If parsing fails, still try to index as a single block
-                eprintln!("Warning: AST parsing failed for {}: {}", file_path.display(), e);
+                self.diagnostics.warn(
+                    DiagnosticSource::Parser,
+                    Some(&relative_path),
+                    format!("AST parsing failed, indexing as a single block: {}", e),
+                );
                 vec![CodeBlock {
                     block_type: "file".to_string(),
                     name: file_path.file_name().and_then(|n| n.to_str()).map(|s| s.to_string()),
+                    short_name: file_path.file_name().and_then(|n| n.to_str()).map(|s| s.to_string()),
                     content: content.clone(),
                     start_line: 0,
                     end_line: content.lines().count(),
                     language: language.clone(),
                     docstring: None,
                     decorators: Vec::new(),
+                    parent_index: None,
                 }]
             }
         };
         
         // Validate blocks before storing
-        let valid_blocks: Vec<CodeBlock> = blocks.into_iter()
-            .filter(|block| {
-                // Filter out blocks that are too small or invalid
-                block.content.len() >= 10 && !block.content.trim().is_empty()
-            })
-            .collect();
+        let valid_blocks: Vec<CodeBlock> = retain_blocks(blocks, is_storable_block);
         
         if valid_blocks.is_empty() {
+            self.parser.invalidate(&relative_path);
             return Err("No valid blocks found in file".to_string());
         }
         
         // Store in database
-        let relative_path = file_path.to_string_lossy().to_string();
-        self.storage.store_file(
+        if let Err(e) = self.storage.store_file(
             &self.project_id,
             &relative_path,
             &language,
             &valid_blocks,
-        ).await
-            .map_err(|e| format!("Failed to store: {}", e))?;
+        ).await {
+            self.parser.invalidate(&relative_path);
+            self.diagnostics.error(DiagnosticSource::Storage, Some(&relative_path), format!("Failed to store blocks: {}", e));
+            return Err(format!("Failed to store: {}", e));
+        }
+        
+        self.queue_embeddings(&relative_path).await?;
+        self.track_indexed(file_path, relative_path, &content).await?;
+        Ok(IndexOutcome::Indexed)
+    }
+    
+    /// Drop anything stored for a file that is no longer indexable
+    async fn skip_file(&mut self, relative_path: String, reason: SkipReason) -> Result<IndexOutcome, String> {
+        self.diagnostics.record(
+            Severity::Info,
+            DiagnosticSource::Indexer,
+            Some(&relative_path),
+            format!("Skipped: {}", reason.as_str()),
+        );
+        self.storage.remove_file(&self.project_id, &relative_path).await
+            .map_err(|e| format!("Failed to remove old entries: {}", e))?;
+        self.indexed_files.remove(&relative_path);
+        self.parser.invalidate(&relative_path);
+        Ok(IndexOutcome::Skipped(reason))
+    }
+    
+    async fn track_indexed(&mut self, file_path: &Path, relative_path: String, content: &str) -> Result<(), String> {
+        self.storage.set_file_hash(&self.project_id, &relative_path, &content_hash(content)).await
+            .map_err(|e| format!("Failed to store file hash: {}", e))?;
         
         // Track indexed file
         let metadata = std::fs::metadata(file_path)
@@ -300,9 +687,62 @@ impl CodebaseIndexer {
         Ok(())
     }
     
-    pub async fn update_file(&mut self, file_path: &Path) -> Result<(), String> {
+    pub async fn update_file(&mut self, file_path: &Path) -> Result<IndexOutcome, String> {
+        self.ensure_project_registered().await?;
+        let relative_path = self.relative_path(file_path);
+        
+        // Try an incremental re-parse against the cached tree first
+        if let Some(language) = ASTParser::detect_language(file_path) {
+            let content = match self.read_source(file_path, &relative_path)? {
+                Ok(content) => content,
+                Err(reason) => return self.skip_file(relative_path, reason).await,
+            };
+            
+            match self.parser.parse_file_incremental(&relative_path, &content, &language) {
+                Ok(ParseOutcome::Unchanged) => {
+                    self.track_indexed(file_path, relative_path, &content).await?;
+                    return Ok(IndexOutcome::Indexed);
+                }
+                Ok(ParseOutcome::Incremental { blocks, changed }) => {
+                    let valid_blocks: Vec<CodeBlock> = retain_blocks(blocks, is_storable_block);
+                    let result = self.storage.update_blocks_in_range(
+                        &self.project_id,
+                        &relative_path,
+                        changed.start_line,
+                        changed.old_end_line,
+                        changed.line_delta(),
+                        &valid_blocks,
+                    ).await;
+                    
+                    match result {
+                        Ok(_) => {
+                            self.track_indexed(file_path, relative_path, &content).await?;
+                            return Ok(IndexOutcome::Indexed);
+                        }
+                        Err(_) => {
+                            // Stored rows no longer match the cached tree; rebuild fully
+                            self.parser.invalidate(&relative_path);
+                        }
+                    }
+                }
+                Ok(ParseOutcome::Full(blocks)) => {
+                    let valid_blocks: Vec<CodeBlock> = retain_blocks(blocks, is_storable_block);
+                    // store_file replaces all existing blocks for the file
+                    if !valid_blocks.is_empty()
+                        && self.storage.store_file(&self.project_id, &relative_path, &language, &valid_blocks).await.is_ok()
+                    {
+                        self.track_indexed(file_path, relative_path, &content).await?;
+                        return Ok(IndexOutcome::Indexed);
+                    }
+                    self.parser.invalidate(&relative_path);
+                }
+                Err(_) => {
+                    // Fall through to a full re-index with error recovery
+                }
+            }
+        }
+        
         // Remove old entries and re-index
-        let relative_path = file_path.to_string_lossy().to_string();
         self.storage.remove_file(&self.project_id, &relative_path).await
             .map_err(|e| format!("Failed to remove old entries: {}", e))?;
         
@@ -310,44 +750,410 @@ impl CodebaseIndexer {
     }
     
     pub async fn remove_file(&mut self, file_path: &Path) -> Result<(), String> {
-        let relative_path = file_path.to_string_lossy().to_string();
+        let relative_path = self.relative_path(file_path);
         self.storage.remove_file(&self.project_id, &relative_path).await
             .map_err(|e| format!("Failed to remove: {}", e))?;
         
         // Remove from tracked files
         self.indexed_files.remove(&relative_path);
+        self.parser.invalidate(&relative_path);
         
         Ok(())
     }
     
+    /// Remove all indexed files under a directory
+    ///
+    /// The root itself is refused; use `clear_index` to drop everything.
+    pub async fn remove_directory(&mut self, dir_path: &Path) -> Result<usize, String> {
+        let relative_dir = self.relative_path(dir_path);
+        if relative_dir.is_empty() {
+            return Err(format!("Refusing to remove the project root {}", dir_path.display()));
+        }
+        let prefix = format!("{}/", relative_dir.trim_end_matches('/'));
+        let removed = self.storage.remove_files_with_prefix(&self.project_id, &prefix).await
+            .map_err(|e| format!("Failed to remove directory: {}", e))?;
+        
+        // Remove from tracked files
+        let removed_paths: Vec<String> = self.indexed_files.keys()
+            .filter(|path| path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for path in removed_paths {
+            self.indexed_files.remove(&path);
+            self.parser.invalidate(&path);
+        }
+        
+        Ok(removed)
+    }
+    
+    /// Remove every indexed file of the project; returns the number removed
+    pub async fn clear_index(&mut self) -> Result<usize, String> {
+        let removed = self.storage.remove_files_with_prefix(&self.project_id, "").await
+            .map_err(|e| format!("Failed to clear index: {}", e))?;
+        
+        for path in self.indexed_files.keys() {
+            self.parser.invalidate(path);
+        }
+        self.indexed_files.clear();
+        
+        Ok(removed)
+    }
+    
+    /// Clear the index on behalf of `user`, who needs `ManageIndex`
+    pub async fn clear_index_as(&mut self, user: &User) -> crate::error::Result<usize> {
+        self.authorizer
+            .authorize(user, Permission::ManageIndex, "index", &self.project_id)
+            .await?;
+        self.clear_index().await.map_err(crate::error::OrchestratorError::Indexing)
+    }
+    
+    /// Collect indexable files under a directory, respecting skip patterns
+    pub fn collect_indexable_files(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        self.collect_indexable_files_recursive(dir, &mut files);
+        files
+    }
+    
+    fn collect_indexable_files_recursive(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        
+        for entry in entries.flatten() {
+            let path = entry.path();
+            
+            // Skip hidden files and directories
+            if path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with('.'))
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            
+            if self.should_skip_file(&path) {
+                continue;
+            }
+            
+            if path.is_dir() {
+                self.collect_indexable_files_recursive(&path, files);
+            } else if path.is_file() && ASTParser::detect_language(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    
     /// Validate index integrity
+    ///
+    /// Reports blocks without a file row, files that no longer exist on disk,
+    /// files whose content changed since they were indexed and, when an
+    /// embedding size is configured, embeddings of the wrong size.
     pub async fn validate_index(&self) -> Result<IndexValidationResult, String> {
-        let mut result = IndexValidationResult {
-            total_files: 0,
-            total_blocks: 0,
-            orphaned_blocks: 0,
-            missing_files: Vec::new(),
-            errors: Vec::new(),
-        };
+        let mut result = IndexValidationResult::default();
+        
+        result.orphaned_blocks = self.storage.find_orphaned_blocks().await
+            .map_err(|e| format!("Failed to find orphaned blocks: {}", e))?;
+        
+        let files = self.storage.list_files_with_hash(&self.project_id).await
+            .map_err(|e| format!("Failed to list indexed files: {}", e))?;
+        result.total_files = files.len();
+        
+        for (stored_path, stored_hash) in files {
+            let path = self.disk_path(&stored_path);
+            if !path.is_file() {
+                result.missing_files.push(stored_path);
+                continue;
+            }
+            
+            // Hashes are of the decoded text, as indexed
+            match std::fs::read(&path) {
+                Ok(bytes) => match decode_source(&bytes, self.max_replacement_ratio) {
+                    Ok(source) if stored_hash.as_deref() == Some(content_hash(&source.content).as_str()) => {}
+                    _ => result.out_of_date_files.push(stored_path),
+                },
+                Err(e) => result.errors.push(format!("Failed to read {}: {}", path.display(), e)),
+            }
+        }
+        
+        let (_, total_blocks) = self.storage.embedding_coverage(&self.project_id).await
+            .map_err(|e| format!("Failed to count blocks: {}", e))?;
+        result.total_blocks = total_blocks as usize;
+        
+        if let Some(dim) = self.embedding_dim {
+            result.mismatched_embeddings = self.storage.find_mismatched_embeddings(&self.project_id, dim).await
+                .map_err(|e| format!("Failed to check embeddings: {}", e))?;
+        }
         
-        // This would require additional storage methods to check integrity
-        // For now, return a basic validation
         Ok(result)
     }
     
-    /// Repair index (remove orphaned entries, etc.)
-    pub async fn repair_index(&mut self) -> Result<usize, String> {
-        // Implementation would clean up orphaned blocks, missing files, etc.
-        // For now, return 0 (no repairs needed)
-        Ok(0)
+    /// Repair index problems found by `validate_index`
+    ///
+    /// Orphaned blocks are deleted, out-of-date files re-indexed and
+    /// mismatched embeddings cleared for regeneration. Rows for files missing
+    /// from disk are only removed when `remove_missing` is set, since the file
+    /// may be on an unmounted volume.
+    pub async fn repair_index(&mut self, remove_missing: bool) -> Result<IndexRepairReport, String> {
+        let validation = self.validate_index().await?;
+        let mut report = IndexRepairReport {
+            errors: validation.errors,
+            ..Default::default()
+        };
+        
+        report.orphaned_blocks_removed = self.storage.delete_blocks(&validation.orphaned_blocks).await
+            .map_err(|e| format!("Failed to delete orphaned blocks: {}", e))?;
+        
+        if remove_missing {
+            for stored_path in &validation.missing_files {
+                self.storage.remove_file(&self.project_id, stored_path).await
+                    .map_err(|e| format!("Failed to remove {}: {}", stored_path, e))?;
+                self.indexed_files.remove(stored_path);
+                self.parser.invalidate(stored_path);
+                report.missing_files_removed += 1;
+            }
+        }
+        
+        for stored_path in &validation.out_of_date_files {
+            let path = self.disk_path(stored_path);
+            match self.update_file(&path).await {
+                Ok(_) => report.files_reindexed += 1,
+                Err(e) => report.errors.push(format!("Failed to re-index {}: {}", stored_path, e)),
+            }
+        }
+        
+        report.embeddings_cleared = self.storage.clear_embeddings(&validation.mismatched_embeddings).await
+            .map_err(|e| format!("Failed to clear embeddings: {}", e))?;
+        
+        Ok(report)
+    }
+    
+    /// Store a summary for every file that lacks one
+    ///
+    /// Re-indexing a file clears its summary, so repeated runs only
+    /// summarize new and changed files. Summaries are extractive unless a
+    /// summarizer is set; files it fails on get the extractive summary.
+    /// Returns the number of files summarized.
+    pub async fn summarize_files(&mut self) -> Result<usize, String> {
+        let paths = self.storage.list_files_without_summary(&self.project_id).await
+            .map_err(|e| format!("Failed to list files: {}", e))?;
+        
+        let mut summarized = 0;
+        for path in paths {
+            let (language, blocks) = match self.storage.get_file_blocks(&self.project_id, &path).await
+                .map_err(|e| format!("Failed to load blocks of {}: {}", path, e))? {
+                Some(file) => file,
+                None => continue,
+            };
+            
+            let extractive = extractive_summary(&path, &language, &blocks);
+            let summary = match &self.summarizer {
+                Some(summarizer) => match summarizer.summarize(&path, &blocks, &extractive).await {
+                    Ok(summary) => summary,
+                    Err(e) => {
+                        self.diagnostics.warn(
+                            DiagnosticSource::Summarizer,
+                            Some(&path),
+                            format!("Summarizer failed, using the extractive summary: {}", e),
+                        );
+                        extractive
+                    }
+                },
+                None => extractive,
+            };
+            
+            self.storage.set_file_summary(&self.project_id, &path, &summary).await
+                .map_err(|e| format!("Failed to store summary of {}: {}", path, e))?;
+            summarized += 1;
+        }
+        
+        Ok(summarized)
+    }
+    
+    /// Import symbols from a universal-ctags JSON file; see `import::import_ctags`
+    pub async fn import_ctags(&mut self, ctags_json_path: &Path) -> Result<usize, String> {
+        self.ensure_project_registered().await?;
+        import::import_ctags(&self.project_id, ctags_json_path, &self.storage).await
+            .map_err(|e| format!("Failed to import {}: {}", ctags_json_path.display(), e))
+    }
+    
+    /// Import definitions from an LSIF dump; see `import::import_lsif`
+    pub async fn import_lsif(&mut self, lsif_path: &Path) -> Result<usize, String> {
+        self.ensure_project_registered().await?;
+        import::import_lsif(&self.project_id, lsif_path, &self.storage).await
+            .map_err(|e| format!("Failed to import {}: {}", lsif_path.display(), e))
+    }
+    
+    /// Regenerate every embedding in the project, `batch_size` blocks at a time
+    ///
+    /// Existing embeddings are cleared and the project's recorded model and
+    /// size replaced first, which is how a project moves to a model with a
+    /// different embedding size. Returns the number of blocks embedded.
+    pub async fn reembed_all(&mut self, batch_size: usize) -> Result<usize, String> {
+        let batch_size = batch_size.max(1);
+        let embedding_dim = self.embedding_dim;
+        let embedding_gen = self.embedding_gen
+            .get_or_insert_with(|| embedding_dim.map(EmbeddingGenerator::new).unwrap_or_default());
+        let model = embedding_gen.model_name();
+        
+        self.storage.reset_embeddings(&self.project_id, model.as_deref(), embedding_gen.embedding_dim()).await
+            .map_err(|e| format!("Failed to clear embeddings: {}", e))?;
+        
+        let mut embedded = 0;
+        let mut after_id = 0;
+        loop {
+            let batch = self.storage.blocks_after(&self.project_id, after_id, batch_size).await
+                .map_err(|e| format!("Failed to load blocks: {}", e))?;
+            let (block_ids, blocks): (Vec<i64>, Vec<CodeBlock>) = batch.into_iter().unzip();
+            after_id = match block_ids.last() {
+                Some(last) => *last,
+                None => break,
+            };
+            
+            let embeddings = embedding_gen.generate_embeddings_batch(&blocks);
+            for (block_id, embedding) in block_ids.into_iter().zip(embeddings) {
+                self.storage.store_embedding_with_model(block_id, &embedding, model.as_deref()).await
+                    .map_err(|e| format!("Failed to store embedding for block {}: {}", block_id, e))?;
+                embedded += 1;
+            }
+        }
+        
+        Ok(embedded)
+    }
+}
+
+/// Store finished embeddings, returning how many were stored
+///
+/// Failures (e.g. a size differing from the project's) are recorded as
+/// diagnostics rather than stopping the indexing run.
+async fn store_embeddings(
+    storage: &IndexStorage,
+    diagnostics: &Diagnostics,
+    model: Option<&str>,
+    embeddings: Vec<(i64, Vec<f32>)>,
+) -> usize {
+    let mut stored = 0;
+    for (block_id, embedding) in embeddings {
+        match storage.store_embedding_with_model(block_id, &embedding, model).await {
+            Ok(()) => stored += 1,
+            Err(e) => diagnostics.error(
+                DiagnosticSource::Storage,
+                None,
+                format!("Failed to store embedding for block {}: {}", block_id, e),
+            ),
+        }
+    }
+    stored
+}
+
+/// Directories and files skipped unless overridden with `with_skip_patterns`
+pub fn default_skip_patterns() -> Vec<String> {
+    vec![
+        "node_modules".to_string(),
+        "target".to_string(),
+        ".git".to_string(),
+        "__pycache__".to_string(),
+        ".venv".to_string(),
+        "venv".to_string(),
+        ".env".to_string(),
+        "*.log".to_string(),
+        "*.tmp".to_string(),
+    ]
+}
+
+/// Filter out blocks that are too small or invalid
+fn is_storable_block(block: &CodeBlock) -> bool {
+    block.content.len() >= 10 && !block.content.trim().is_empty()
+}
+
+/// Whether any directory or file name below `root` starts with a dot,
+/// matching what the directory walker skips
+fn is_hidden(file_path: &Path, root: Option<&Path>) -> bool {
+    let relative = match root {
+        Some(root) => file_path.strip_prefix(root).unwrap_or(file_path),
+        None => file_path,
+    };
+    relative.components().any(|component| match component {
+        Component::Normal(name) => name.as_encoded_bytes().starts_with(b"."),
+        _ => false,
+    })
+}
+
+/// `root` with symlinks and `.`/`..` resolved, or as given if that fails
+fn canonical_root(root: &Path) -> PathBuf {
+    root.canonicalize().unwrap_or_else(|_| root.to_path_buf())
+}
+
+/// `path` with its parent directory canonicalized
+///
+/// Works for files that were already deleted and leaves a symlinked file
+/// itself unresolved, so it stays under the root it was found in.
+fn canonical_parent(path: &Path) -> PathBuf {
+    match (path.parent().filter(|p| !p.as_os_str().is_empty()), path.file_name()) {
+        (Some(parent), Some(name)) => match parent.canonicalize() {
+            Ok(dir) => dir.join(name),
+            Err(_) => path.to_path_buf(),
+        },
+        (None, Some(name)) => match std::env::current_dir() {
+            Ok(dir) => canonical_root(&dir).join(name),
+            Err(_) => path.to_path_buf(),
+        },
+        _ => canonical_root(path),
+    }
+}
+
+/// Normalize path separators to '/'
+pub fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// Express `path` relative to `root`, normalizing separators to '/'
+///
+/// Paths outside the root are returned normalized but otherwise unchanged.
+pub fn relative_to_root(root: &str, path: &str) -> String {
+    let root = normalize_path(root);
+    let path = normalize_path(path);
+    let root = root.trim_end_matches('/');
+    
+    match path.strip_prefix(root) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            rest.trim_start_matches('/').to_string()
+        }
+        _ => path,
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct IndexValidationResult {
     pub total_files: usize,
     pub total_blocks: usize,
-    pub orphaned_blocks: usize,
+    /// IDs of blocks whose file row no longer exists
+    pub orphaned_blocks: Vec<i64>,
+    /// Indexed files no longer present on disk
     pub missing_files: Vec<String>,
+    /// Indexed files whose content changed since they were indexed
+    pub out_of_date_files: Vec<String>,
+    /// IDs of blocks whose embedding has the wrong size
+    pub mismatched_embeddings: Vec<i64>,
+    pub errors: Vec<String>,
+}
+
+impl IndexValidationResult {
+    pub fn is_healthy(&self) -> bool {
+        self.orphaned_blocks.is_empty()
+            && self.missing_files.is_empty()
+            && self.out_of_date_files.is_empty()
+            && self.mismatched_embeddings.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+/// Counts of repairs made by `repair_index`
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexRepairReport {
+    pub orphaned_blocks_removed: usize,
+    pub missing_files_removed: usize,
+    pub files_reindexed: usize,
+    pub embeddings_cleared: usize,
     pub errors: Vec<String>,
 }
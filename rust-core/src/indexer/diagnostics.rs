@@ -0,0 +1,167 @@
+/// Problems reported by the indexer, file watcher and embedding generator
+
+use crate::error::{OrchestratorError, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Diagnostics kept before the oldest are dropped, by default
+pub const DEFAULT_DIAGNOSTICS_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 3] = [Severity::Info, Severity::Warning, Severity::Error];
+    
+    pub fn parse(severity: &str) -> Result<Self> {
+        Severity::ALL
+            .into_iter()
+            .find(|s| s.as_str() == severity)
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!("Unknown severity: {}", severity)))
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Component that reported a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DiagnosticSource {
+    /// Directory walks and per-file indexing
+    Indexer,
+    Parser,
+    Storage,
+    Watcher,
+    Embedding,
+    Summarizer,
+}
+
+impl DiagnosticSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticSource::Indexer => "indexer",
+            DiagnosticSource::Parser => "parser",
+            DiagnosticSource::Storage => "storage",
+            DiagnosticSource::Watcher => "watcher",
+            DiagnosticSource::Embedding => "embedding",
+            DiagnosticSource::Summarizer => "summarizer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub source: DiagnosticSource,
+    pub path: Option<String>, // File the problem concerns, if any
+    pub message: String,
+    pub timestamp: i64, // Unix seconds
+}
+
+struct Buffer {
+    entries: VecDeque<Diagnostic>,
+    capacity: usize,
+    min_severity: Severity,
+}
+
+/// Bounded, shared sink for diagnostics
+///
+/// Clones record into the same buffer, so an indexer, its watcher and its
+/// embedding generator can share one sink. Once full, the oldest entries are
+/// dropped. Every recorded diagnostic is also emitted as a tracing event;
+/// those below the minimum severity are traced but not kept.
+#[derive(Clone)]
+pub struct Diagnostics {
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_DIAGNOSTICS_CAPACITY)
+    }
+    
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Arc::new(Mutex::new(Buffer {
+                entries: VecDeque::new(),
+                capacity,
+                min_severity: Severity::Warning,
+            })),
+        }
+    }
+    
+    /// Keep only diagnostics at or above `min_severity` (Warning by default)
+    pub fn with_min_severity(self, min_severity: Severity) -> Self {
+        self.set_min_severity(min_severity);
+        self
+    }
+    
+    pub fn set_min_severity(&self, min_severity: Severity) {
+        self.buffer.lock().unwrap().min_severity = min_severity;
+    }
+    
+    pub fn min_severity(&self) -> Severity {
+        self.buffer.lock().unwrap().min_severity
+    }
+    
+    pub fn record(&self, severity: Severity, source: DiagnosticSource, path: Option<&str>, message: impl Into<String>) {
+        let message = message.into();
+        match severity {
+            Severity::Info => tracing::info!(source = source.as_str(), path, "{}", message),
+            Severity::Warning => tracing::warn!(source = source.as_str(), path, "{}", message),
+            Severity::Error => tracing::error!(source = source.as_str(), path, "{}", message),
+        }
+        
+        let mut buffer = self.buffer.lock().unwrap();
+        if severity < buffer.min_severity || buffer.capacity == 0 {
+            return;
+        }
+        if buffer.entries.len() == buffer.capacity {
+            buffer.entries.pop_front();
+        }
+        buffer.entries.push_back(Diagnostic {
+            severity,
+            source,
+            path: path.map(str::to_string),
+            message,
+            timestamp: Utc::now().timestamp(),
+        });
+    }
+    
+    pub fn warn(&self, source: DiagnosticSource, path: Option<&str>, message: impl Into<String>) {
+        self.record(Severity::Warning, source, path, message);
+    }
+    
+    pub fn error(&self, source: DiagnosticSource, path: Option<&str>, message: impl Into<String>) {
+        self.record(Severity::Error, source, path, message);
+    }
+    
+    /// The newest `n` diagnostics, oldest first
+    pub fn recent(&self, n: usize) -> Vec<Diagnostic> {
+        let buffer = self.buffer.lock().unwrap();
+        let skip = buffer.entries.len().saturating_sub(n);
+        buffer.entries.iter().skip(skip).cloned().collect()
+    }
+    
+    /// Remove and return every kept diagnostic, oldest first
+    pub fn drain(&self) -> Vec<Diagnostic> {
+        self.buffer.lock().unwrap().entries.drain(..).collect()
+    }
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
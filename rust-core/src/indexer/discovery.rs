@@ -0,0 +1,48 @@
+/// File discovery for cold-start indexing of large repositories
+
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// List the files of a git work tree with `git ls-files`
+///
+/// Includes tracked files and untracked ones not excluded by `.gitignore`,
+/// so ignored build output is never walked. Fails when git is missing or
+/// `root` is not inside a work tree.
+pub async fn discover_via_git(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z", "--cached", "--others", "--exclude-standard"])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git in {}: {}", root.display(), e))?;
+    
+    if !output.status.success() {
+        return Err(format!(
+            "git ls-files failed in {}: {}",
+            root.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    // Paths are relative to `root`, even when it is a subdirectory of the work tree
+    Ok(output.stdout
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| root.join(path_from_bytes(path)))
+        .collect())
+}
+
+/// A path as git printed it; with `-z` the bytes are not quoted or re-encoded
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+/// Git writes UTF-8 paths on platforms whose paths are not bytes
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
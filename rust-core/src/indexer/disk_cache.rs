@@ -0,0 +1,232 @@
+/// Embeddings persisted on disk across runs, evicted least recently used first
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Embeddings kept on disk when no cap is given
+pub const DEFAULT_EMBEDDING_CACHE_ENTRIES: usize = 100_000;
+
+/// How long `DiskEmbeddingCache::open` waits for another cache to release the directory
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Where `EmbeddingGenerator` found its embeddings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingCacheStats {
+    pub memory_hits: u64,
+    pub disk_hits: u64,
+    pub computed: u64, // Generated by the model or the hash fallback
+}
+
+/// Counters shared by a generator and its forks
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CacheCounters {
+    memory_hits: Arc<AtomicU64>,
+    disk_hits: Arc<AtomicU64>,
+    computed: Arc<AtomicU64>,
+}
+
+impl CacheCounters {
+    pub(crate) fn memory_hit(&self) {
+        self.memory_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn disk_hit(&self) {
+        self.disk_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn computed(&self) {
+        self.computed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            memory_hits: self.memory_hits.load(Ordering::Relaxed),
+            disk_hits: self.disk_hits.load(Ordering::Relaxed),
+            computed: self.computed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Embeddings keyed by a hash of what they were generated from
+///
+/// Two sled trees hold the cache: `entries` maps a key to its last-use
+/// sequence number and the embedding's little-endian floats, and `recency`
+/// maps sequence numbers back to keys, so its first entry is the least
+/// recently used. Sequence numbers come from sled's persistent id generator
+/// and keep growing across runs. Clones share the database and flush it
+/// when dropped.
+#[derive(Clone)]
+pub struct DiskEmbeddingCache {
+    db: sled::Db,
+    entries: sled::Tree,
+    recency: sled::Tree,
+    len: Arc<AtomicUsize>, // Entries stored; sled counts by scanning
+    max_entries: usize,
+}
+
+impl DiskEmbeddingCache {
+    /// Open or create the cache directory at `path`, keeping at most `max_entries` embeddings
+    ///
+    /// Fails if the directory is in use by another open cache. A cache that
+    /// was just dropped can hold sled's file lock for a moment while it
+    /// shuts down, so a locked directory is retried for up to `LOCK_WAIT`.
+    pub fn open(path: &Path, max_entries: usize) -> Result<Self, String> {
+        let db = open_db(path)
+            .map_err(|e| format!("Failed to open embedding cache at {}: {}", path.display(), e))?;
+        let entries = db.open_tree("entries")
+            .map_err(|e| format!("Failed to open embedding cache entries: {}", e))?;
+        let recency = db.open_tree("recency")
+            .map_err(|e| format!("Failed to open embedding cache recency: {}", e))?;
+
+        let cache = Self {
+            db,
+            len: Arc::new(AtomicUsize::new(entries.len())),
+            entries,
+            recency,
+            max_entries: max_entries.max(1),
+        };
+        // A smaller cap than the cache was written with takes effect at once
+        cache.evict()?;
+        Ok(cache)
+    }
+
+    /// The embedding stored for `key`, marking it most recently used
+    pub fn get(&self, key: &str) -> Result<Option<Vec<f32>>, String> {
+        let stored = match self.entries.get(key).map_err(cache_error)? {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        let (sequence, embedding) = match decode_entry(&stored) {
+            Some(entry) => entry,
+            None => {
+                // Unreadable entries are dropped and regenerated
+                self.remove(key.as_bytes())?;
+                return Ok(None);
+            }
+        };
+
+        self.recency.remove(sequence.to_be_bytes()).map_err(cache_error)?;
+        self.write(key, &embedding)?;
+        Ok(Some(embedding))
+    }
+
+    /// Store `embedding` for `key`, evicting the least recently used beyond the cap
+    pub fn put(&self, key: &str, embedding: &[f32]) -> Result<(), String> {
+        if let Some(previous) = self.entries.get(key).map_err(cache_error)? {
+            if let Some((sequence, _)) = decode_entry(&previous) {
+                self.recency.remove(sequence.to_be_bytes()).map_err(cache_error)?;
+            }
+        }
+        self.write(key, embedding)?;
+        self.evict()
+    }
+
+    /// Number of embeddings stored
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Write pending changes to disk
+    pub fn flush(&self) -> Result<(), String> {
+        self.db.flush().map_err(cache_error)?;
+        Ok(())
+    }
+
+    fn write(&self, key: &str, embedding: &[f32]) -> Result<(), String> {
+        let sequence = self.db.generate_id().map_err(cache_error)?;
+        let mut value = Vec::with_capacity(8 + embedding.len() * 4);
+        value.extend_from_slice(&sequence.to_be_bytes());
+        for x in embedding {
+            value.extend_from_slice(&x.to_le_bytes());
+        }
+        if self.entries.insert(key, value).map_err(cache_error)?.is_none() {
+            self.len.fetch_add(1, Ordering::SeqCst);
+        }
+        self.recency.insert(sequence.to_be_bytes(), key.as_bytes()).map_err(cache_error)?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<(), String> {
+        if self.entries.remove(key).map_err(cache_error)?.is_some() {
+            self.len.fetch_sub(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    fn evict(&self) -> Result<(), String> {
+        while self.len() > self.max_entries {
+            let (sequence, key) = match self.recency.pop_min().map_err(cache_error)? {
+                Some(oldest) => oldest,
+                None => break,
+            };
+            // Skip recency records left behind by an interrupted write
+            let current = self.entries.get(&key).map_err(cache_error)?;
+            let current_sequence = current.as_deref().and_then(decode_entry).map(|(s, _)| s);
+            if current_sequence == Some(u64::from_be_bytes(sequence_bytes(&sequence))) {
+                self.remove(&key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DiskEmbeddingCache {
+    fn drop(&mut self) {
+        // Every clone flushes; only the last one has anything left to write
+        let _ = self.db.flush();
+    }
+}
+
+fn open_db(path: &Path) -> sled::Result<sled::Db> {
+    let mut waited = Duration::ZERO;
+    loop {
+        match sled::open(path) {
+            Err(e) if is_lock_error(&e) && waited < LOCK_WAIT => {
+                std::thread::sleep(LOCK_RETRY_INTERVAL);
+                waited += LOCK_RETRY_INTERVAL;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// sled reports a held file lock as an `Other` I/O error with this message
+fn is_lock_error(e: &sled::Error) -> bool {
+    matches!(e, sled::Error::Io(io) if io.to_string().contains("could not acquire lock"))
+}
+
+fn sequence_bytes(bytes: &[u8]) -> [u8; 8] {
+    let mut sequence = [0; 8];
+    if bytes.len() == 8 {
+        sequence.copy_from_slice(bytes);
+    }
+    sequence
+}
+
+/// (sequence, embedding) of a stored entry; None if it is malformed
+fn decode_entry(value: &[u8]) -> Option<(u64, Vec<f32>)> {
+    if value.len() < 8 || !(value.len() - 8).is_multiple_of(4) {
+        return None;
+    }
+    let sequence = u64::from_be_bytes(sequence_bytes(&value[..8]));
+    let embedding = value[8..]
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    Some((sequence, embedding))
+}
+
+fn cache_error(e: sled::Error) -> String {
+    format!("Embedding cache error: {}", e)
+}
@@ -0,0 +1,159 @@
+/// Embedding computation on a pool of worker threads
+
+use crate::indexer::parser::CodeBlock;
+use crate::indexer::semantic::EmbeddingGenerator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tokio::sync::mpsc as async_mpsc;
+
+/// Blocks allowed to wait per worker before `EmbeddingPool::submit` blocks
+pub const QUEUE_PER_WORKER: usize = 4;
+
+/// Computes block embeddings on one worker thread
+pub trait BlockEmbedder: Send {
+    fn embed(&mut self, block: &CodeBlock) -> Vec<f32>;
+}
+
+impl BlockEmbedder for EmbeddingGenerator {
+    fn embed(&mut self, block: &CodeBlock) -> Vec<f32> {
+        self.generate_embedding(block)
+    }
+}
+
+/// Half the available cores, and at least one
+pub fn default_worker_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| cores.get() / 2)
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Counts of blocks handed to an `EmbeddingPool`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingStats {
+    pub submitted: usize,
+    pub completed: usize, // Embedded by a worker, collected or not
+    pub max_pending: usize, // Most blocks queued or being embedded at once
+}
+
+impl EmbeddingStats {
+    /// Blocks submitted and not yet embedded
+    pub fn pending(&self) -> usize {
+        self.submitted.saturating_sub(self.completed)
+    }
+}
+
+/// Worker threads embedding blocks fed through a bounded queue
+///
+/// `submit` waits while the queue is full, so a producer that outruns the
+/// workers holds at most `queue_capacity` blocks plus one per worker in
+/// flight. Embeddings are collected with `take_completed` while submitting
+/// and with `finish` at the end. Both wait without blocking the runtime's
+/// thread, so the pool can be driven from async code.
+pub struct EmbeddingPool {
+    jobs: Option<async_mpsc::Sender<(i64, CodeBlock)>>, // None once closed
+    results: Mutex<Receiver<(i64, Vec<f32>)>>, // Mutex only to make the pool, and indexers holding one, Sync
+    workers: Vec<JoinHandle<()>>,
+    completed: Arc<AtomicUsize>, // Incremented by workers
+    submitted: usize,
+    max_pending: usize,
+}
+
+impl EmbeddingPool {
+    /// Start `threads` workers (at least one), each with its own embedder from `make_embedder`
+    pub fn new(threads: usize, queue_capacity: usize, make_embedder: impl Fn() -> Box<dyn BlockEmbedder>) -> Self {
+        let (jobs, job_rx) = async_mpsc::channel::<(i64, CodeBlock)>(queue_capacity.max(1));
+        let (result_tx, results) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let completed = Arc::new(AtomicUsize::new(0));
+        
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let mut embedder = make_embedder();
+                let job_rx = job_rx.clone();
+                let result_tx = result_tx.clone();
+                let completed = completed.clone();
+                std::thread::spawn(move || loop {
+                    // The lock is released before embedding, so workers only wait on each other for the next job
+                    let job = match job_rx.lock() {
+                        Ok(mut rx) => rx.blocking_recv(),
+                        Err(_) => break,
+                    };
+                    let (block_id, block) = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let embedding = embedder.embed(&block);
+                    completed.fetch_add(1, Ordering::SeqCst);
+                    if result_tx.send((block_id, embedding)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+        
+        Self {
+            jobs: Some(jobs),
+            results: Mutex::new(results),
+            workers,
+            completed,
+            submitted: 0,
+            max_pending: 0,
+        }
+    }
+    
+    /// Queue a block, waiting while the queue is full
+    pub async fn submit(&mut self, block_id: i64, block: CodeBlock) -> Result<(), String> {
+        let jobs = self.jobs.as_ref().ok_or_else(|| "Embedding pool is closed".to_string())?;
+        jobs.send((block_id, block)).await
+            .map_err(|_| "Embedding workers have stopped".to_string())?;
+        self.submitted += 1;
+        let pending = self.submitted.saturating_sub(self.completed.load(Ordering::SeqCst));
+        self.max_pending = self.max_pending.max(pending);
+        Ok(())
+    }
+    
+    /// Embeddings finished since the last call, as (block id, embedding)
+    pub fn take_completed(&mut self) -> Vec<(i64, Vec<f32>)> {
+        match self.results.get_mut() {
+            Ok(results) => results.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+    
+    /// Close the queue, wait for the workers and return the embeddings not yet taken
+    ///
+    /// Fails if a worker panicked; embeddings from the other workers are lost.
+    pub async fn finish(mut self) -> Result<Vec<(i64, Vec<f32>)>, String> {
+        self.jobs = None;
+        let workers = std::mem::take(&mut self.workers);
+        let panicked = tokio::task::spawn_blocking(move || {
+            workers.into_iter().filter(|worker| worker.join().is_err()).count()
+        })
+        .await
+        .map_err(|e| format!("Failed to wait for embedding workers: {}", e))?;
+        if panicked > 0 {
+            return Err(format!("{} embedding worker(s) panicked", panicked));
+        }
+        let results = self.results.get_mut().map_err(|_| "Embedding results lock poisoned".to_string())?;
+        Ok(results.try_iter().collect())
+    }
+    
+    pub fn stats(&self) -> EmbeddingStats {
+        EmbeddingStats {
+            submitted: self.submitted,
+            completed: self.completed.load(Ordering::SeqCst),
+            max_pending: self.max_pending,
+        }
+    }
+}
+
+impl Drop for EmbeddingPool {
+    fn drop(&mut self) {
+        // Closing the queue stops the workers once it is drained; they are
+        // not joined, since the pool may be dropped on a runtime thread
+        self.jobs = None;
+    }
+}
@@ -0,0 +1,86 @@
+/// Per-project cache of block embeddings for search
+
+use crate::error::Result;
+use crate::indexer::storage::{EmbeddingFingerprint, IndexStorage};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Block embeddings of one project, keyed by block id
+pub type EmbeddingMap = HashMap<i64, Vec<f32>>;
+
+/// Where cached embeddings are loaded from
+#[async_trait]
+pub trait EmbeddingSource: Send + Sync {
+    async fn embedding_fingerprint(&self, project_id: &str) -> Result<EmbeddingFingerprint>;
+    
+    async fn get_block_embeddings(&self, project_id: &str) -> Result<Vec<(i64, Vec<f32>)>>;
+}
+
+#[async_trait]
+impl EmbeddingSource for IndexStorage {
+    async fn embedding_fingerprint(&self, project_id: &str) -> Result<EmbeddingFingerprint> {
+        IndexStorage::embedding_fingerprint(self, project_id).await
+    }
+    
+    async fn get_block_embeddings(&self, project_id: &str) -> Result<Vec<(i64, Vec<f32>)>> {
+        IndexStorage::get_block_embeddings(self, project_id).await
+    }
+}
+
+struct CachedEmbeddings {
+    fingerprint: EmbeddingFingerprint,
+    embeddings: Arc<EmbeddingMap>,
+}
+
+/// Embeddings loaded once per project and reused until the index changes
+///
+/// Every lookup runs the source's fingerprint probe and reloads when it no
+/// longer matches, so re-indexing is picked up without explicit invalidation.
+#[derive(Default)]
+pub struct EmbeddingCache {
+    entries: HashMap<String, CachedEmbeddings>,
+    loads: usize,
+}
+
+impl EmbeddingCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Cached embeddings for a project, loading them if missing or stale
+    pub async fn get(&mut self, source: &impl EmbeddingSource, project_id: &str) -> Result<Arc<EmbeddingMap>> {
+        let fingerprint = source.embedding_fingerprint(project_id).await?;
+        if let Some(cached) = self.entries.get(project_id) {
+            if cached.fingerprint == fingerprint {
+                return Ok(cached.embeddings.clone());
+            }
+        }
+        
+        // Probed before loading: a write in between makes the next probe differ
+        let embeddings: Arc<EmbeddingMap> = Arc::new(source.get_block_embeddings(project_id).await?.into_iter().collect());
+        self.loads += 1;
+        self.entries.insert(
+            project_id.to_string(),
+            CachedEmbeddings {
+                fingerprint,
+                embeddings: embeddings.clone(),
+            },
+        );
+        Ok(embeddings)
+    }
+    
+    /// Drop a project's embeddings; returns whether any were cached
+    pub fn invalidate(&mut self, project_id: &str) -> bool {
+        self.entries.remove(project_id).is_some()
+    }
+    
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+    
+    /// Number of times embeddings were fetched from the source
+    pub fn loads(&self) -> usize {
+        self.loads
+    }
+}
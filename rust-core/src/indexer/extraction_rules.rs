@@ -0,0 +1,162 @@
+/// Per-language rules for which syntax nodes become code blocks
+///
+/// Rules files are TOML with one table per language:
+///
+/// ```toml
+/// [rust]
+/// capture = ["function_item", "struct_item", "macro_definition"]
+/// descend = ["declaration_list", "mod_item"]
+/// min_content_length = 20
+/// ```
+///
+/// Each key replaces the built-in value for that language; omitted keys
+/// and languages keep the built-ins.
+
+use crate::error::{OrchestratorError, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Languages the parser has grammars for
+pub const RULE_LANGUAGES: &[&str] = &["python", "rust", "javascript", "typescript", "tsx"];
+
+/// Blocks shorter than this many bytes are not indexed
+const DEFAULT_MIN_CONTENT_LENGTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageRules {
+    pub capture: Vec<String>, // tree-sitter node kinds emitted as blocks
+    pub descend: Option<Vec<String>>, // Kinds walked into but not captured; None walks every node
+    pub min_content_length: usize, // Captured nodes with less content are dropped
+}
+
+impl LanguageRules {
+    fn new(capture: &[&str]) -> Self {
+        Self {
+            capture: capture.iter().map(|kind| kind.to_string()).collect(),
+            descend: None,
+            min_content_length: DEFAULT_MIN_CONTENT_LENGTH,
+        }
+    }
+    
+    pub fn captures(&self, kind: &str) -> bool {
+        self.capture.iter().any(|k| k == kind)
+    }
+    
+    /// Whether the children of a `kind` node are searched for blocks
+    ///
+    /// With `descend` set, only the root, captured kinds and the listed
+    /// kinds are walked into, which prunes subtrees that cannot hold blocks.
+    /// The parser checks the root itself.
+    pub fn descends(&self, kind: &str) -> bool {
+        match &self.descend {
+            Some(descend) => self.captures(kind) || descend.iter().any(|k| k == kind),
+            None => true,
+        }
+    }
+}
+
+/// Extraction rules for every language, defaulting to the built-ins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractionRules {
+    languages: HashMap<String, LanguageRules>,
+    fallback: LanguageRules, // For languages without their own rules
+}
+
+impl ExtractionRules {
+    /// Parse a rules file; fails with `InvalidConfig` on malformed TOML
+    ///
+    /// Tables for languages the parser has no grammar for are kept but can
+    /// never apply; `unknown_languages` lists them and loading logs a warning.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let file: HashMap<String, LanguageRulesFile> = toml::from_str(contents)
+            .map_err(|e| OrchestratorError::InvalidConfig(format!("Invalid extraction rules: {}", e)))?;
+        
+        let mut rules = Self::default();
+        for (language, overrides) in file {
+            let mut language_rules = rules.for_language(&language).clone();
+            if let Some(capture) = overrides.capture {
+                language_rules.capture = capture;
+            }
+            if overrides.descend.is_some() {
+                language_rules.descend = overrides.descend;
+            }
+            if let Some(min_content_length) = overrides.min_content_length {
+                language_rules.min_content_length = min_content_length;
+            }
+            rules.languages.insert(language, language_rules);
+        }
+        for language in rules.unknown_languages() {
+            tracing::warn!(
+                language = language.as_str(),
+                "Extraction rules for unknown language ignored (expected one of {})",
+                RULE_LANGUAGES.join(", ")
+            );
+        }
+        Ok(rules)
+    }
+    
+    /// Read and parse a rules file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+    
+    pub fn for_language(&self, language: &str) -> &LanguageRules {
+        self.languages.get(language).unwrap_or(&self.fallback)
+    }
+    
+    /// Languages with rules but no grammar, sorted
+    pub fn unknown_languages(&self) -> Vec<String> {
+        let mut unknown: Vec<String> = self
+            .languages
+            .keys()
+            .filter(|language| !RULE_LANGUAGES.contains(&language.as_str()))
+            .cloned()
+            .collect();
+        unknown.sort();
+        unknown
+    }
+}
+
+impl Default for ExtractionRules {
+    fn default() -> Self {
+        let js = LanguageRules::new(&[
+            "function_declaration",
+            "class_declaration",
+            "method_definition",
+            "arrow_function",
+            "function",
+            // Anonymous `function () {}`; older grammars call it "function"
+            "function_expression",
+            "async_function_declaration",
+            // Covers `const App = () => ...` components
+            "lexical_declaration",
+        ]);
+        
+        let mut languages = HashMap::new();
+        // decorated_definition is not captured itself; its inner definition carries the decorators
+        languages.insert("python".to_string(), LanguageRules::new(&["function_definition", "class_definition", "lambda"]));
+        languages.insert(
+            "rust".to_string(),
+            LanguageRules::new(&["function_item", "struct_item", "impl_item", "trait_item", "enum_item", "mod_item"]),
+        );
+        languages.insert("javascript".to_string(), js.clone());
+        languages.insert("typescript".to_string(), js.clone());
+        languages.insert("tsx".to_string(), js);
+        
+        Self {
+            languages,
+            fallback: LanguageRules::new(&["function", "class", "method"]),
+        }
+    }
+}
+
+/// One language's table in a rules file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguageRulesFile {
+    capture: Option<Vec<String>>,
+    descend: Option<Vec<String>>,
+    min_content_length: Option<usize>,
+}
@@ -0,0 +1,103 @@
+/// Content fingerprints for spotting copies of the same code
+
+/// Hash of a block's code ignoring whitespace and comments
+///
+/// Blocks that differ only in indentation, line breaks or comments get the
+/// same fingerprint. Which comment syntaxes are stripped depends on
+/// `language`, so e.g. Rust attributes are not mistaken for `#` comments.
+pub fn block_fingerprint(content: &str, language: &str) -> String {
+    format!("{:x}", md5::compute(normalize_code(content, language)))
+}
+
+/// `content` without comments, with each run of whitespace reduced to one space
+pub fn normalize_code(content: &str, language: &str) -> String {
+    let syntax = comment_syntax(language);
+    let mut code = String::with_capacity(content.len());
+    let mut rest = content;
+    while !rest.is_empty() {
+        if let Some((open, close)) = syntax.block {
+            if let Some(after) = rest.strip_prefix(open) {
+                rest = after.find(close).map_or("", |end| &after[end + close.len()..]);
+                code.push(' ');
+                continue;
+            }
+        }
+        if let Some(prefix) = syntax.line.iter().find(|prefix| rest.starts_with(**prefix)) {
+            rest = rest[prefix.len()..].find('\n').map_or("", |end| &rest[prefix.len() + end..]);
+            continue;
+        }
+        if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            // Comment markers inside strings are code
+            let end = string_end(rest, quote);
+            code.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        code.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+fn comment_syntax(language: &str) -> CommentSyntax {
+    match language.to_lowercase().as_str() {
+        "python" | "ruby" | "shell" | "bash" | "sh" | "yaml" | "toml" | "perl" | "r" => CommentSyntax {
+            line: &["#"],
+            block: None,
+        },
+        "sql" | "lua" | "haskell" => CommentSyntax {
+            line: &["--"],
+            block: None,
+        },
+        "html" | "xml" => CommentSyntax {
+            line: &[],
+            block: Some(("<!--", "-->")),
+        },
+        _ => CommentSyntax {
+            line: &["//"],
+            block: Some(("/*", "*/")),
+        },
+    }
+}
+
+/// Byte length of the string literal at the start of `text`, quotes included
+///
+/// An unterminated literal runs to the end of the line.
+fn string_end(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '\n' => return index,
+            c if c == quote && !escaped => return index + c.len_utf8(),
+            _ => escaped = false,
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_whitespace_and_comments_are_ignored() {
+        let original = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}";
+        let reformatted = "fn add(a: i32, b: i32) -> i32 {\n    // Sum\n        a + b /* inline */\n}\n";
+        assert_eq!(block_fingerprint(original, "rust"), block_fingerprint(reformatted, "rust"));
+        assert_ne!(block_fingerprint(original, "rust"), block_fingerprint("fn add(a: i32, b: i32) -> i32 { a - b }", "rust"));
+    }
+    
+    #[test]
+    fn test_comment_markers_depend_on_language() {
+        assert_eq!(normalize_code("x = 1  # one\ny = 2", "python"), "x = 1 y = 2");
+        assert_eq!(normalize_code("#[derive(Debug)]\nstruct A;", "rust"), "#[derive(Debug)] struct A;");
+        assert_eq!(normalize_code("let url = \"http://x\"; // site", "rust"), "let url = \"http://x\";");
+    }
+}
@@ -0,0 +1,299 @@
+/// Import of symbols indexed by external tools (ctags, LSIF)
+
+use crate::error::{OrchestratorError, Result};
+use crate::indexer::codebase::{normalize_path, relative_to_root};
+use crate::indexer::parser::CodeBlock;
+use crate::indexer::source::{decode_source, DEFAULT_MAX_REPLACEMENT_RATIO};
+use crate::indexer::storage::{content_hash, BlockSource, IndexStorage};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// A symbol reported by an external indexer, before nesting
+#[derive(Debug, Clone)]
+struct ImportedSymbol {
+    block_type: String,
+    name: String,
+    scope: Option<String>, // Enclosing symbol as the tool reports it (e.g. "Storage")
+    start_line: usize, // 0-based, like parsed blocks
+    end_line: usize,
+}
+
+/// File path -> (language, symbols)
+type ImportedFiles = BTreeMap<String, (String, Vec<ImportedSymbol>)>;
+
+/// One line of `ctags --output-format=json`
+#[derive(Debug, Deserialize)]
+struct CtagsEntry {
+    #[serde(rename = "_type", default)]
+    entry_type: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    line: Option<usize>, // 1-based
+    #[serde(default)]
+    end: Option<usize>, // Only with `--fields=+e`
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Store the tags of a universal-ctags JSON file as blocks of `project_id`
+///
+/// Paths in the tags are taken relative to the directory of the tags file,
+/// where ctags is expected to have run. Blocks get their content only when
+/// the tagged file can be read from there. Symbols previously imported from
+/// ctags are replaced; blocks parsed from the AST are kept. Returns the
+/// number of blocks stored.
+pub async fn import_ctags(project_id: &str, ctags_json_path: &Path, storage: &IndexStorage) -> Result<usize> {
+    let text = std::fs::read_to_string(ctags_json_path)?;
+    let base = base_dir(ctags_json_path);
+    
+    let mut files = ImportedFiles::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: CtagsEntry = serde_json::from_str(line).map_err(|e| OrchestratorError::InvalidInput(format!(
+            "{}:{}: invalid ctags entry: {}",
+            ctags_json_path.display(),
+            number + 1,
+            e
+        )))?;
+        if entry.entry_type != "tag" {
+            continue;
+        }
+        let (name, path, line) = match (entry.name, entry.path, entry.line) {
+            (Some(name), Some(path), Some(line)) => (name, path, line),
+            _ => continue,
+        };
+        
+        let start_line = line.saturating_sub(1);
+        let symbol = ImportedSymbol {
+            block_type: entry.kind.unwrap_or_else(|| "symbol".to_string()),
+            name,
+            scope: entry.scope,
+            start_line,
+            end_line: entry.end.map_or(start_line, |end| end.saturating_sub(1).max(start_line)),
+        };
+        let language = entry.language.map_or_else(|| "unknown".to_string(), |l| l.to_lowercase());
+        files
+            .entry(stored_path(&base, &path))
+            .or_insert_with(|| (language, Vec::new()))
+            .1
+            .push(symbol);
+    }
+    
+    store(project_id, storage, BlockSource::Ctags, &base, files).await
+}
+
+/// Store the definitions of an LSIF dump as blocks of `project_id`
+///
+/// Definitions are the `range` vertices tagged `definition` that a document
+/// `contains`; the tag's full range, when present, gives the line range.
+/// Document URIs are taken relative to the dump's `projectRoot`, or to the
+/// directory of the dump if it has none. Otherwise behaves like `import_ctags`.
+pub async fn import_lsif(project_id: &str, lsif_path: &Path, storage: &IndexStorage) -> Result<usize> {
+    let text = std::fs::read_to_string(lsif_path)?;
+    let mut base = base_dir(lsif_path);
+    
+    let mut documents: HashMap<String, (String, String)> = HashMap::new(); // id -> (uri, language)
+    let mut definitions: HashMap<String, ImportedSymbol> = HashMap::new(); // range id -> symbol
+    let mut contains: Vec<(String, Vec<String>)> = Vec::new(); // document id -> range ids
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let element: Value = serde_json::from_str(line).map_err(|e| OrchestratorError::InvalidInput(format!(
+            "{}:{}: invalid LSIF element: {}",
+            lsif_path.display(),
+            number + 1,
+            e
+        )))?;
+        let id = element["id"].to_string();
+        
+        match element["label"].as_str().unwrap_or_default() {
+            "metaData" => {
+                if let Some(root) = element["projectRoot"].as_str() {
+                    base = PathBuf::from(uri_path(root));
+                }
+            }
+            "document" => {
+                let uri = element["uri"].as_str().unwrap_or_default().to_string();
+                let language = element["languageId"].as_str().unwrap_or("unknown").to_lowercase();
+                documents.insert(id, (uri, language));
+            }
+            "range" => {
+                let tag = &element["tag"];
+                if tag["type"].as_str() != Some("definition") {
+                    continue;
+                }
+                let name = match tag["text"].as_str() {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                };
+                let range = if tag["fullRange"].is_object() { &tag["fullRange"] } else { &element };
+                let start_line = range["start"]["line"].as_u64().unwrap_or(0) as usize;
+                let end_line = range["end"]["line"].as_u64().map_or(start_line, |end| (end as usize).max(start_line));
+                definitions.insert(
+                    id,
+                    ImportedSymbol {
+                        block_type: symbol_kind(tag["kind"].as_u64()).to_string(),
+                        name,
+                        scope: None,
+                        start_line,
+                        end_line,
+                    },
+                );
+            }
+            "contains" => {
+                let ranges = element["inVs"]
+                    .as_array()
+                    .map(|ids| ids.iter().map(Value::to_string).collect())
+                    .unwrap_or_default();
+                contains.push((element["outV"].to_string(), ranges));
+            }
+            _ => {}
+        }
+    }
+    
+    let mut files = ImportedFiles::new();
+    for (document_id, range_ids) in contains {
+        let (uri, language) = match documents.get(&document_id) {
+            Some(document) => document,
+            None => continue,
+        };
+        let symbols: Vec<ImportedSymbol> = range_ids.iter().filter_map(|id| definitions.remove(id)).collect();
+        if symbols.is_empty() {
+            continue;
+        }
+        files
+            .entry(stored_path(&base, &uri_path(uri)))
+            .or_insert_with(|| (language.clone(), Vec::new()))
+            .1
+            .extend(symbols);
+    }
+    
+    store(project_id, storage, BlockSource::Lsif, &base, files).await
+}
+
+async fn store(
+    project_id: &str,
+    storage: &IndexStorage,
+    source: BlockSource,
+    base: &Path,
+    files: ImportedFiles,
+) -> Result<usize> {
+    let mut rows = Vec::with_capacity(files.len());
+    for (file_path, (language, symbols)) in files {
+        let text = std::fs::read(base.join(&file_path))
+            .ok()
+            .and_then(|bytes| decode_source(&bytes, DEFAULT_MAX_REPLACEMENT_RATIO).ok())
+            .map(|source| source.content);
+        let file_hash = text.as_deref().map(content_hash);
+        let blocks = nest_symbols(symbols, &language, text.as_deref());
+        rows.push((file_path, language, file_hash, blocks));
+    }
+    
+    storage.replace_imported_blocks(project_id, source, &rows).await
+}
+
+/// Blocks for one file's symbols, parents first
+///
+/// A symbol's parent is the innermost symbol whose line range strictly
+/// contains it. Names are qualified with the reported scope, or else with the
+/// parent's name, as parsed blocks are (e.g. "Storage.save").
+fn nest_symbols(mut symbols: Vec<ImportedSymbol>, language: &str, content: Option<&str>) -> Vec<CodeBlock> {
+    symbols.sort_by(|a, b| a.start_line.cmp(&b.start_line).then(b.end_line.cmp(&a.end_line)));
+    let lines: Vec<&str> = content.map(|content| content.lines().collect()).unwrap_or_default();
+    
+    let mut blocks: Vec<CodeBlock> = Vec::with_capacity(symbols.len());
+    let mut open: Vec<usize> = Vec::new(); // Indexes of blocks enclosing the current line
+    for symbol in symbols {
+        while let Some(&index) = open.last() {
+            let parent = &blocks[index];
+            let same_range = parent.start_line == symbol.start_line && parent.end_line == symbol.end_line;
+            if parent.end_line >= symbol.end_line && !same_range {
+                break;
+            }
+            open.pop();
+        }
+        let parent_index = open.last().copied();
+        
+        let scope = symbol
+            .scope
+            .or_else(|| parent_index.and_then(|index| blocks[index].name.clone()));
+        let name = match scope {
+            Some(scope) => format!("{}.{}", scope, symbol.name),
+            None => symbol.name.clone(),
+        };
+        let content = if symbol.start_line < lines.len() {
+            lines[symbol.start_line..=symbol.end_line.min(lines.len() - 1)].join("\n")
+        } else {
+            String::new()
+        };
+        
+        open.push(blocks.len());
+        blocks.push(CodeBlock {
+            block_type: symbol.block_type,
+            name: Some(name),
+            short_name: Some(symbol.name),
+            content,
+            start_line: symbol.start_line,
+            end_line: symbol.end_line,
+            language: language.to_string(),
+            docstring: None,
+            decorators: Vec::new(),
+            parent_index,
+        });
+    }
+    blocks
+}
+
+/// Block type for an LSP `SymbolKind`
+fn symbol_kind(kind: Option<u64>) -> &'static str {
+    match kind {
+        Some(2) => "module",
+        Some(3) => "namespace",
+        Some(4) => "package",
+        Some(5) => "class",
+        Some(6) => "method",
+        Some(7) => "property",
+        Some(8) => "field",
+        Some(9) => "constructor",
+        Some(10) => "enum",
+        Some(11) => "interface",
+        Some(12) => "function",
+        Some(13) => "variable",
+        Some(14) => "constant",
+        Some(22) => "enum_member",
+        Some(23) => "struct",
+        Some(26) => "type_parameter",
+        _ => "symbol",
+    }
+}
+
+fn base_dir(index_path: &Path) -> PathBuf {
+    index_path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Path of a `file://` URI; other strings are returned unchanged
+fn uri_path(uri: &str) -> String {
+    uri.strip_prefix("file://").unwrap_or(uri).to_string()
+}
+
+/// Path as stored in `indexed_files`: relative to `base` when absolute
+fn stored_path(base: &Path, path: &str) -> String {
+    let path = if Path::new(path).is_absolute() {
+        relative_to_root(&base.to_string_lossy(), path)
+    } else {
+        normalize_path(path)
+    };
+    path.trim_start_matches("./").to_string()
+}
@@ -0,0 +1,151 @@
+/// Durable record of file changes the watcher has not yet indexed
+
+use crate::error::{OrchestratorError, Result};
+use chrono::Utc;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Entries older than this are purged rather than replayed by default
+pub const DEFAULT_JOURNAL_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// What happened to a watched path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// Created or modified; a directory is re-indexed file by file
+    Update,
+    RemoveFile,
+    RemoveDir,
+    /// Removed, but the platform did not say whether it was a file or directory
+    Remove,
+}
+
+impl ChangeKind {
+    pub const ALL: [ChangeKind; 4] = [
+        ChangeKind::Update,
+        ChangeKind::RemoveFile,
+        ChangeKind::RemoveDir,
+        ChangeKind::Remove,
+    ];
+    
+    pub fn parse(kind: &str) -> Result<Self> {
+        ChangeKind::ALL
+            .into_iter()
+            .find(|k| k.as_str() == kind)
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!("Unknown change kind: {}", kind)))
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Update => "update",
+            ChangeKind::RemoveFile => "remove_file",
+            ChangeKind::RemoveDir => "remove_dir",
+            ChangeKind::Remove => "remove",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    pub recorded_at: i64, // Unix seconds
+}
+
+/// Per-project journal in the `watcher_journal` table
+///
+/// The watcher appends a row when an event arrives and deletes the path's
+/// rows once it has been indexed or removed, so rows left after a crash
+/// name exactly the changes that were lost.
+#[derive(Clone)]
+pub struct WatcherJournal {
+    pool: SqlitePool,
+    project_id: String,
+    max_age: Duration,
+}
+
+impl WatcherJournal {
+    pub fn new(pool: SqlitePool, project_id: impl Into<String>) -> Self {
+        Self {
+            pool,
+            project_id: project_id.into(),
+            max_age: DEFAULT_JOURNAL_MAX_AGE,
+        }
+    }
+    
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+    
+    pub async fn append(&self, path: &Path, kind: ChangeKind) -> Result<()> {
+        self.append_at(path, kind, Utc::now().timestamp()).await
+    }
+    
+    /// Append with an explicit timestamp (Unix seconds)
+    pub async fn append_at(&self, path: &Path, kind: ChangeKind, recorded_at: i64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO watcher_journal (project_id, path, kind, recorded_at) VALUES (?, ?, ?, ?)"
+        )
+        .bind(&self.project_id)
+        .bind(path.to_string_lossy().as_ref())
+        .bind(kind.as_str())
+        .bind(recorded_at)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Forget a path once its change has been applied
+    pub async fn complete(&self, path: &Path) -> Result<()> {
+        sqlx::query("DELETE FROM watcher_journal WHERE project_id = ? AND path = ?")
+            .bind(&self.project_id)
+            .bind(path.to_string_lossy().as_ref())
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Unapplied changes, one per path with the newest kind, oldest path first
+    pub async fn pending(&self) -> Result<Vec<JournalEntry>> {
+        let rows = sqlx::query_as::<_, (String, String, i64)>(
+            "SELECT path, kind, recorded_at FROM watcher_journal WHERE project_id = ? ORDER BY id"
+        )
+        .bind(&self.project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut entries: Vec<JournalEntry> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (path, kind, recorded_at) in rows {
+            let entry = JournalEntry {
+                path: PathBuf::from(&path),
+                kind: ChangeKind::parse(&kind)?,
+                recorded_at,
+            };
+            match positions.get(&path) {
+                Some(&i) => entries[i] = entry,
+                None => {
+                    positions.insert(path, entries.len());
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+    
+    /// Delete entries older than the max age; returns how many were deleted
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let cutoff = Utc::now().timestamp() - self.max_age.as_secs() as i64;
+        let result = sqlx::query("DELETE FROM watcher_journal WHERE project_id = ? AND recorded_at < ?")
+            .bind(&self.project_id)
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.rows_affected() as usize)
+    }
+}
@@ -2,13 +2,42 @@
 
 pub mod parser;
 pub mod codebase;
+pub mod diagnostics;
+pub mod discovery;
+pub mod disk_cache;
+pub mod embed_pool;
+pub mod embedding_cache;
+pub mod extraction_rules;
+pub mod fingerprint;
+pub mod import;
 pub mod semantic;
+pub mod source;
 pub mod watcher;
+pub mod journal;
+pub mod query;
 pub mod search;
+pub mod rerank;
 pub mod storage;
+pub mod summary;
+pub mod testgen;
+pub mod vector;
 
-pub use codebase::CodebaseIndexer;
+pub use codebase::{CodebaseIndexer, IndexOutcome, IndexReport};
+pub use diagnostics::{Diagnostic, DiagnosticSource, Diagnostics, Severity};
+pub use discovery::discover_via_git;
+pub use disk_cache::{DiskEmbeddingCache, EmbeddingCacheStats};
+pub use embed_pool::{BlockEmbedder, EmbeddingPool, EmbeddingStats};
+pub use embedding_cache::{EmbeddingCache, EmbeddingSource};
+pub use extraction_rules::{ExtractionRules, LanguageRules};
+pub use fingerprint::block_fingerprint;
+pub use import::{import_ctags, import_lsif};
 pub use parser::ASTParser;
 pub use semantic::EmbeddingGenerator;
-pub use watcher::FileWatcher;
-pub use search::SemanticSearch;
+pub use source::{decode_source, SkipReason, SourceText};
+pub use watcher::{FileWatcher, IndexEventPolicy};
+pub use journal::{ChangeKind, WatcherJournal};
+pub use query::{parse_query, ParsedQuery};
+pub use rerank::{HttpReranker, NoopReranker, Reranker};
+pub use search::{render_explanation, Explanation, FileResult, SearchCapabilities, SearchMode, SearchResponse, SemanticSearch};
+pub use summary::{extractive_summary, FileSummarizer};
+pub use vector::{cosine_similarity, l2_normalize, l2_normalized, mean_pool, top_k_similar};
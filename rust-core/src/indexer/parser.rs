@@ -1,7 +1,8 @@
 /// AST parsing using tree-sitter
 
-use tree_sitter::{Language, Parser, Tree};
-use std::collections::HashMap;
+use crate::indexer::extraction_rules::ExtractionRules;
+use tree_sitter::{InputEdit, Language, Parser, Point, Tree};
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 
 // Import tree-sitter language grammars
@@ -13,98 +14,200 @@ use tree_sitter_typescript;
 #[derive(Debug, Clone)]
 pub struct CodeBlock {
     pub block_type: String, // function, class, method, etc.
-    pub name: Option<String>,
+    pub name: Option<String>, // Qualified with enclosing class/impl/trait (e.g. "Storage.save")
+    pub short_name: Option<String>, // Bare name without qualification
     pub content: String,
     pub start_line: usize,
     pub end_line: usize,
     pub language: String,
     pub docstring: Option<String>, // Docstring or leading comments
     pub decorators: Vec<String>, // Python decorators or Rust attributes
+    pub parent_index: Option<usize>, // Index of the enclosing block in the same parse output
 }
 
+/// Result of parsing a file against its cached tree
+pub enum ParseOutcome {
+    /// Whole file was parsed; blocks cover the entire file
+    Full(Vec<CodeBlock>),
+    /// Only `blocks` intersecting the changed region were re-extracted
+    Incremental {
+        blocks: Vec<CodeBlock>,
+        changed: ChangedRegion,
+    },
+    /// Content is identical to the cached version
+    Unchanged,
+}
+
+/// Line range affected by an edit, in old and new coordinates
+///
+/// Lines before `start_line` are unchanged; lines after `old_end_line` in the
+/// old content correspond to lines after `new_end_line` in the new content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangedRegion {
+    pub start_line: usize,
+    pub old_end_line: usize,
+    pub new_end_line: usize,
+}
+
+impl ChangedRegion {
+    pub fn line_delta(&self) -> i64 {
+        self.new_end_line as i64 - self.old_end_line as i64
+    }
+}
+
+/// Parsing is `&self`: a fresh tree-sitter `Parser` is created per call, so
+/// one `ASTParser` can be shared across threads. Only the incremental tree
+/// cache needs `&mut self`.
 pub struct ASTParser {
-    parsers: HashMap<String, Parser>,
+    tree_cache: HashMap<String, (String, Tree)>, // Last parsed content and tree per file
+    cache_order: VecDeque<String>,
+    max_cached_trees: usize,
+    min_anonymous_function_lines: usize, // Shorter unnamed arrows/lambdas are not indexed
+    rules: ExtractionRules,
 }
 
 impl ASTParser {
     pub fn new() -> Self {
-        let mut parsers = HashMap::new();
-        
-        // Initialize parsers for supported languages with tree-sitter grammars
-        unsafe {
-            // Python
-            let mut python_parser = Parser::new();
-            if python_parser.set_language(tree_sitter_python()).is_ok() {
-                parsers.insert("python".to_string(), python_parser);
-            }
-            
-            // Rust
-            let mut rust_parser = Parser::new();
-            if rust_parser.set_language(tree_sitter_rust()).is_ok() {
-                parsers.insert("rust".to_string(), rust_parser);
-            }
-            
-            // JavaScript
-            let mut js_parser = Parser::new();
-            if js_parser.set_language(tree_sitter_javascript()).is_ok() {
-                parsers.insert("javascript".to_string(), js_parser);
-            }
-            
-            // TypeScript
-            let mut ts_parser = Parser::new();
-            if ts_parser.set_language(tree_sitter_typescript()).is_ok() {
-                parsers.insert("typescript".to_string(), ts_parser);
-            }
+        Self {
+            tree_cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+            max_cached_trees: 128,
+            min_anonymous_function_lines: 2,
+            rules: ExtractionRules::default(),
+        }
+    }
+    
+    pub fn with_max_cached_trees(mut self, max: usize) -> Self {
+        self.max_cached_trees = max;
+        self
+    }
+    
+    pub fn with_min_anonymous_function_lines(mut self, lines: usize) -> Self {
+        self.min_anonymous_function_lines = lines;
+        self
+    }
+    
+    /// Extract blocks by `rules` instead of the built-in node kinds
+    pub fn with_rules(mut self, rules: ExtractionRules) -> Self {
+        self.rules = rules;
+        self
+    }
+    
+    /// A parser with the same settings and an empty tree cache
+    pub fn fork(&self) -> Self {
+        Self {
+            tree_cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+            max_cached_trees: self.max_cached_trees,
+            min_anonymous_function_lines: self.min_anonymous_function_lines,
+            rules: self.rules.clone(),
         }
+    }
+    
+    pub fn parse_file(&self, content: &str, language: &str) -> Result<Vec<CodeBlock>, String> {
+        let tree = self.parse_tree(content, language, None)?;
         
-        Self { parsers }
+        // Extract code blocks
+        self.extract_blocks(&tree, content, language)
     }
     
-    pub fn parse_file(&mut self, content: &str, language: &str) -> Result<Vec<CodeBlock>, String> {
-        // Get or create parser for language
-        let parser = self.parsers
-            .entry(language.to_string())
-            .or_insert_with(|| {
-                // Try to initialize parser for this language
-                let mut p = Parser::new();
-                match language {
-                    "python" => {
-                        if p.set_language(tree_sitter_python::language()).is_ok() {
-                            return p;
-                        }
-                    }
-                    "rust" => {
-                        if p.set_language(tree_sitter_rust::language()).is_ok() {
-                            return p;
-                        }
-                    }
-                    "javascript" => {
-                        if p.set_language(tree_sitter_javascript::language()).is_ok() {
-                            return p;
-                        }
-                    }
-                    "typescript" => {
-                        if p.set_language(tree_sitter_typescript::language()).is_ok() {
-                            return p;
-                        }
-                    }
-                    _ => {}
-                }
-                // Return uninitialized parser if language not supported
-                p
-            });
+    /// Parse a file fully and cache its tree for later incremental updates
+    pub fn parse_file_cached(&mut self, key: &str, content: &str, language: &str) -> Result<Vec<CodeBlock>, String> {
+        let tree = self.parse_tree(content, language, None)?;
+        let blocks = self.extract_blocks(&tree, content, language)?;
+        self.cache_tree(key, content, tree);
+        Ok(blocks)
+    }
+    
+    /// Re-parse a file using its cached tree and tree-sitter's edit API
+    ///
+    /// Falls back to a full parse when no tree is cached or the edit covers
+    /// too much of the file.
+    pub fn parse_file_incremental(&mut self, key: &str, content: &str, language: &str) -> Result<ParseOutcome, String> {
+        let (old_content, mut old_tree) = match self.tree_cache.remove(key) {
+            Some(cached) => cached,
+            None => return self.parse_file_cached(key, content, language).map(ParseOutcome::Full),
+        };
+        
+        let edit = match compute_edit(&old_content, content) {
+            Some(edit) => edit,
+            None => {
+                self.cache_tree(key, content, old_tree);
+                return Ok(ParseOutcome::Unchanged);
+            }
+        };
         
-        // Check if parser has a language set
-        if parser.language().is_none() {
-            return Err(format!("Language '{}' not supported or grammar failed to load", language));
+        let edited_bytes = (edit.old_end_byte - edit.start_byte).max(edit.new_end_byte - edit.start_byte);
+        if edited_bytes * 2 > content.len().max(old_content.len()) {
+            return self.parse_file_cached(key, content, language).map(ParseOutcome::Full);
         }
         
-        // Parse the content
-        let tree = parser.parse(content, None)
-            .ok_or_else(|| format!("Failed to parse {} code", language))?;
+        old_tree.edit(&edit);
+        let tree = self.parse_tree(content, language, Some(&old_tree))?;
         
-        // Extract code blocks
-        self.extract_blocks(&tree, content, language)
+        // Widen the textual edit by any syntactic changes tree-sitter reports
+        let mut start_line = edit.start_position.row;
+        let mut new_end_line = edit.new_end_position.row;
+        for range in old_tree.changed_ranges(&tree) {
+            start_line = start_line.min(range.start_point.row);
+            new_end_line = new_end_line.max(range.end_point.row);
+        }
+        
+        // Items following the edit may own doc comments/attributes inside it
+        new_end_line = extend_over_leading_trivia(content, new_end_line);
+        
+        let line_delta = edit.new_end_position.row as i64 - edit.old_end_position.row as i64;
+        let changed = ChangedRegion {
+            start_line,
+            old_end_line: (new_end_line as i64 - line_delta).max(start_line as i64) as usize,
+            new_end_line,
+        };
+        
+        let total_lines = content.lines().count().max(1);
+        if (changed.new_end_line - changed.start_line) * 2 > total_lines {
+            return self.parse_file_cached(key, content, language).map(ParseOutcome::Full);
+        }
+        
+        let blocks = retain_blocks(self.extract_blocks(&tree, content, language)?, |b| {
+            b.end_line >= changed.start_line && b.start_line <= changed.new_end_line
+        });
+        self.cache_tree(key, content, tree);
+        
+        Ok(ParseOutcome::Incremental { blocks, changed })
+    }
+    
+    /// Drop the cached tree for a file
+    pub fn invalidate(&mut self, key: &str) {
+        self.tree_cache.remove(key);
+        self.cache_order.retain(|k| k != key);
+    }
+    
+    fn cache_tree(&mut self, key: &str, content: &str, tree: Tree) {
+        if self.max_cached_trees == 0 {
+            return;
+        }
+        self.cache_order.retain(|k| k != key);
+        self.cache_order.push_back(key.to_string());
+        self.tree_cache.insert(key.to_string(), (content.to_string(), tree));
+        
+        while self.cache_order.len() > self.max_cached_trees {
+            if let Some(evicted) = self.cache_order.pop_front() {
+                self.tree_cache.remove(&evicted);
+            }
+        }
+    }
+    
+    fn parse_tree(&self, content: &str, language: &str, old_tree: Option<&Tree>) -> Result<Tree, String> {
+        // Parsers are cheap to create; a fresh one per call keeps parsing &self
+        let mut parser = Parser::new();
+        let grammar = grammar_for(language)
+            .ok_or_else(|| format!("Language '{}' not supported", language))?;
+        parser
+            .set_language(grammar)
+            .map_err(|e| format!("Grammar for '{}' failed to load: {}", language, e))?;
+        
+        parser.parse(content, old_tree)
+            .ok_or_else(|| format!("Failed to parse {} code", language))
     }
     
     fn extract_blocks(&self, tree: &Tree, content: &str, language: &str) -> Result<Vec<CodeBlock>, String> {
@@ -115,7 +218,7 @@ impl ASTParser {
         match language {
             "python" => self.extract_python_blocks(&root_node, content, &mut blocks),
             "rust" => self.extract_rust_blocks(&root_node, content, &mut blocks),
-            "javascript" | "typescript" => self.extract_js_blocks(&root_node, content, &mut blocks),
+            "javascript" | "typescript" | "tsx" => self.extract_js_blocks(&root_node, content, &mut blocks, language),
             _ => {
                 // Generic extraction: find function-like structures
                 self.extract_generic_blocks(&root_node, content, &mut blocks, language);
@@ -128,24 +231,24 @@ impl ASTParser {
     fn extract_python_blocks(&self, node: &tree_sitter::Node, content: &str, blocks: &mut Vec<CodeBlock>) {
         // Extract functions and classes
         let mut cursor = node.walk();
-        self.traverse_node(&mut cursor, content, blocks, "python");
+        self.traverse_node(&mut cursor, content, blocks, "python", &mut Vec::new(), &mut Vec::new());
     }
     
     fn extract_rust_blocks(&self, node: &tree_sitter::Node, content: &str, blocks: &mut Vec<CodeBlock>) {
         // Extract functions, structs, impls, etc.
         let mut cursor = node.walk();
-        self.traverse_node(&mut cursor, content, blocks, "rust");
+        self.traverse_node(&mut cursor, content, blocks, "rust", &mut Vec::new(), &mut Vec::new());
     }
     
-    fn extract_js_blocks(&self, node: &tree_sitter::Node, content: &str, blocks: &mut Vec<CodeBlock>) {
+    fn extract_js_blocks(&self, node: &tree_sitter::Node, content: &str, blocks: &mut Vec<CodeBlock>, language: &str) {
         // Extract functions, classes, methods
         let mut cursor = node.walk();
-        self.traverse_node(&mut cursor, content, blocks, "javascript");
+        self.traverse_node(&mut cursor, content, blocks, language, &mut Vec::new(), &mut Vec::new());
     }
     
     fn extract_generic_blocks(&self, node: &tree_sitter::Node, content: &str, blocks: &mut Vec<CodeBlock>, language: &str) {
         let mut cursor = node.walk();
-        self.traverse_node(&mut cursor, content, blocks, language);
+        self.traverse_node(&mut cursor, content, blocks, language, &mut Vec::new(), &mut Vec::new());
     }
     
     fn traverse_node(
@@ -154,19 +257,23 @@ impl ASTParser {
         content: &str,
         blocks: &mut Vec<CodeBlock>,
         language: &str,
+        scope: &mut Vec<String>, // Names of enclosing classes/impls/traits
+        parents: &mut Vec<usize>, // Indices in `blocks` of enclosing emitted blocks
     ) {
         let node = cursor.node();
         let node_type = node.kind();
+        let mut emitted = None;
+        
+        let rules = self.rules.for_language(language);
         
-        // Extract relevant node types (actual tree-sitter node types)
-        let relevant_types = match language {
-            "python" => vec!["function_definition", "class_definition", "decorated_definition", "async_function_definition"],
-            "rust" => vec!["function_item", "struct_item", "impl_item", "trait_item", "enum_item", "mod_item"],
-            "javascript" | "typescript" => vec!["function_declaration", "class_declaration", "method_definition", "arrow_function", "function", "async_function_declaration"],
-            _ => vec!["function", "class", "method"],
+        let is_relevant = match node_type {
+            "lexical_declaration" => declared_function(&node).is_some(),
+            // Functions bound by a const/let are emitted through their declaration
+            "arrow_function" | "function" | "function_expression" => !is_declared_function(&node),
+            _ => true,
         };
         
-        if relevant_types.contains(&node_type) {
+        if is_relevant && rules.captures(node_type) {
             let start_byte = node.start_byte();
             let end_byte = node.end_byte();
             let start_line = node.start_position().row;
@@ -175,7 +282,14 @@ impl ASTParser {
             let block_content = &content[start_byte..end_byte];
             
             // Try to extract name (with nested structure support)
-            let name = self.extract_name(&node, content);
+            let short_name = self.extract_name(&node, content);
+            let name = match &short_name {
+                Some(short) if !scope.is_empty() => {
+                    let separator = scope_separator(language);
+                    Some(format!("{}{}{}", scope.join(separator), separator, short))
+                }
+                _ => short_name.clone(),
+            };
             
             // Extract docstring/comments
             let docstring = self.extract_docstring(&node, content, language);
@@ -186,33 +300,82 @@ impl ASTParser {
             let block = CodeBlock {
                 block_type: node_type.to_string(),
                 name,
+                short_name,
                 content: block_content.to_string(),
                 start_line,
                 end_line,
                 language: language.to_string(),
                 docstring,
                 decorators,
+                parent_index: parents.last().copied(),
             };
             
             // Validate block before adding
-            if self.validate_block(&block) {
+            if self.validate_block(&block) && !self.is_trivial_inline_function(&block) {
+                emitted = Some(blocks.len());
                 blocks.push(block);
             }
         }
         
+        // Classes, impls and traits qualify the names of blocks nested inside them
+        let scope_name = if is_scope_node(node_type, language) {
+            self.extract_name(&node, content)
+        } else {
+            None
+        };
+        if let Some(ref name) = scope_name {
+            scope.push(name.clone());
+        }
+        if let Some(index) = emitted {
+            parents.push(index);
+        }
+        
         // Traverse children
-        if cursor.goto_first_child() {
+        let descend = node.parent().is_none() || rules.descends(node_type);
+        if descend && cursor.goto_first_child() {
             loop {
-                self.traverse_node(cursor, content, blocks, language);
+                self.traverse_node(cursor, content, blocks, language, scope, parents);
                 if !cursor.goto_next_sibling() {
                     break;
                 }
             }
             cursor.goto_parent();
         }
+        
+        if scope_name.is_some() {
+            scope.pop();
+        }
+        if emitted.is_some() {
+            parents.pop();
+        }
     }
     
     fn extract_name(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
+        // Prefer the grammar's name field
+        if let Some(name_node) = node.child_by_field_name("name") {
+            return Some(content[name_node.start_byte()..name_node.end_byte()].to_string());
+        }
+        
+        // Function expressions and lambdas take the name they are bound to
+        if matches!(node.kind(), "arrow_function" | "function" | "function_expression" | "lambda") {
+            return binding_name(node, content);
+        }
+        
+        // `const App = () => ...` is named after its declarator
+        if node.kind() == "lexical_declaration" {
+            if let Some(name_node) = node.named_child(0).and_then(|d| d.child_by_field_name("name")) {
+                return Some(content[name_node.start_byte()..name_node.end_byte()].to_string());
+            }
+        }
+        
+        // Rust impl blocks are named after the implemented type (without generics)
+        if node.kind() == "impl_item" {
+            if let Some(type_node) = node.child_by_field_name("type") {
+                let type_name = &content[type_node.start_byte()..type_node.end_byte()];
+                return Some(type_name.split('<').next().unwrap_or(type_name).trim().to_string());
+            }
+        }
+        
         // Try to find name node - handle nested structures
         let mut cursor = node.walk();
         
@@ -313,28 +476,66 @@ impl ASTParser {
                 }
             }
             "rust" => {
-                // Rust doc comments are /// or //!
-                // Look for doc comments before the node
-                let before_content = &content[..node_start.min(content.len())];
-                let lines: Vec<&str> = before_content.lines().rev().take(10).collect();
-                let mut doc_lines = Vec::new();
+                // Outer doc comments (/// or /** */) are preceding siblings of the
+                // item, possibly separated from it by attributes
+                let mut doc_parts = Vec::new();
+                let mut sibling = node.prev_sibling();
+                while let Some(prev) = sibling {
+                    match prev.kind() {
+                        "attribute_item" => {}
+                        "line_comment" | "block_comment" => {
+                            let text = &content[prev.start_byte()..prev.end_byte()];
+                            if is_inner_doc_comment(text) {
+                                // //! documents the enclosing module, not this item
+                                break;
+                            }
+                            if let Some(doc) = clean_doc_comment(text) {
+                                doc_parts.push(doc);
+                            }
+                        }
+                        _ => break,
+                    }
+                    sibling = prev.prev_sibling();
+                }
+                
+                if !doc_parts.is_empty() {
+                    doc_parts.reverse();
+                    return Some(doc_parts.join("\n"));
+                }
                 
-                for line in lines.iter().rev() {
-                    let trimmed = line.trim();
-                    if trimmed.starts_with("///") || trimmed.starts_with("//!") {
-                        let doc_line = trimmed.trim_start_matches("///").trim_start_matches("//!").trim();
-                        if !doc_line.is_empty() {
-                            doc_lines.push(doc_line.to_string());
+                // Modules may be documented from inside with inner doc comments
+                if node.kind() == "mod_item" {
+                    if let Some(body) = node.child_by_field_name("body") {
+                        let mut cursor = body.walk();
+                        let inner_docs: Vec<String> = body.named_children(&mut cursor)
+                            .take_while(|child| matches!(child.kind(), "line_comment" | "block_comment"))
+                            .map(|child| &content[child.start_byte()..child.end_byte()])
+                            .filter(|text| is_inner_doc_comment(text))
+                            .filter_map(clean_doc_comment)
+                            .collect();
+                        if !inner_docs.is_empty() {
+                            return Some(inner_docs.join("\n"));
                         }
-                    } else if trimmed.is_empty() || trimmed.starts_with("//") {
-                        continue;
-                    } else {
-                        break;
+                    }
+                }
+            }
+            "javascript" | "typescript" | "tsx" => {
+                // JSDoc is a /** */ comment immediately preceding the declaration
+                // (or the export statement wrapping it)
+                let mut target = *node;
+                if let Some(parent) = node.parent() {
+                    if parent.kind() == "export_statement" {
+                        target = parent;
                     }
                 }
                 
-                if !doc_lines.is_empty() {
-                    return Some(doc_lines.join("\n"));
+                if let Some(prev) = target.prev_sibling() {
+                    if prev.kind() == "comment" {
+                        let text = &content[prev.start_byte()..prev.end_byte()];
+                        if text.starts_with("/**") && !text.starts_with("/**/") {
+                            return clean_doc_comment(text);
+                        }
+                    }
                 }
             }
             _ => {
@@ -360,38 +561,34 @@ impl ASTParser {
         
         match language {
             "python" => {
-                // Python decorators are before function/class definitions
-                let mut cursor = node.walk();
-                if cursor.goto_first_child() {
-                    loop {
-                        let child = cursor.node();
-                        if child.kind() == "decorator" {
-                            let start = child.start_byte();
-                            let end = child.end_byte();
-                            decorators.push(content[start..end].trim().to_string());
-                        }
-                        if !cursor.goto_next_sibling() {
-                            break;
+                // Decorators are children of the decorated_definition wrapping
+                // the function/class, not of the definition itself
+                if let Some(parent) = node.parent() {
+                    if parent.kind() == "decorated_definition" {
+                        let mut cursor = parent.walk();
+                        for child in parent.children(&mut cursor) {
+                            if child.kind() == "decorator" {
+                                decorators.push(content[child.start_byte()..child.end_byte()].trim().to_string());
+                            }
                         }
                     }
                 }
             }
             "rust" => {
-                // Rust attributes are #[...] or #![...]
-                let mut cursor = node.walk();
-                if cursor.goto_first_child() {
-                    loop {
-                        let child = cursor.node();
-                        if child.kind() == "attribute_item" {
-                            let start = child.start_byte();
-                            let end = child.end_byte();
-                            decorators.push(content[start..end].trim().to_string());
-                        }
-                        if !cursor.goto_next_sibling() {
-                            break;
+                // Rust attributes (#[...]) are preceding siblings of the item,
+                // possibly interleaved with doc comments
+                let mut sibling = node.prev_sibling();
+                while let Some(prev) = sibling {
+                    match prev.kind() {
+                        "attribute_item" => {
+                            decorators.push(content[prev.start_byte()..prev.end_byte()].trim().to_string());
                         }
+                        "line_comment" | "block_comment" => {}
+                        _ => break,
                     }
+                    sibling = prev.prev_sibling();
                 }
+                decorators.reverse();
             }
             _ => {}
         }
@@ -399,9 +596,16 @@ impl ASTParser {
         decorators
     }
     
+    /// Unnamed one-off callbacks such as `.map(x => x + 1)`
+    fn is_trivial_inline_function(&self, block: &CodeBlock) -> bool {
+        matches!(block.block_type.as_str(), "arrow_function" | "function" | "function_expression" | "lambda")
+            && block.name.is_none()
+            && block.end_line - block.start_line + 1 < self.min_anonymous_function_lines
+    }
+    
     fn validate_block(&self, block: &CodeBlock) -> bool {
         // Minimum size validation
-        if block.content.len() < 10 {
+        if block.content.len() < self.rules.for_language(&block.language).min_content_length {
             return false;
         }
         
@@ -434,7 +638,7 @@ impl ASTParser {
             "rs" => Some("rust".to_string()),
             "js" => Some("javascript".to_string()),
             "ts" => Some("typescript".to_string()),
-            "tsx" => Some("typescript".to_string()),
+            "tsx" => Some("tsx".to_string()),
             "go" => Some("go".to_string()),
             "java" => Some("java".to_string()),
             "cpp" | "cc" | "cxx" => Some("cpp".to_string()),
@@ -444,6 +648,218 @@ impl ASTParser {
     }
 }
 
+/// Keep blocks matching `keep`, re-pointing `parent_index` at the nearest kept ancestor
+pub fn retain_blocks<F: Fn(&CodeBlock) -> bool>(blocks: Vec<CodeBlock>, keep: F) -> Vec<CodeBlock> {
+    let parents: Vec<Option<usize>> = blocks.iter().map(|b| b.parent_index).collect();
+    let mut new_index: Vec<Option<usize>> = Vec::with_capacity(blocks.len());
+    let mut kept = Vec::new();
+    
+    for mut block in blocks {
+        if !keep(&block) {
+            new_index.push(None);
+            continue;
+        }
+        
+        // Parents always precede their children, so their new index is known
+        let mut ancestor = block.parent_index;
+        block.parent_index = None;
+        while let Some(index) = ancestor {
+            if let Some(mapped) = new_index[index] {
+                block.parent_index = Some(mapped);
+                break;
+            }
+            ancestor = parents[index];
+        }
+        
+        new_index.push(Some(kept.len()));
+        kept.push(block);
+    }
+    
+    kept
+}
+
+/// Function value of a single-declarator `const`/`let` (e.g. a React component)
+fn declared_function<'a>(node: &tree_sitter::Node<'a>) -> Option<tree_sitter::Node<'a>> {
+    if node.kind() != "lexical_declaration" || node.named_child_count() != 1 {
+        return None;
+    }
+    let value = node.named_child(0)?.child_by_field_name("value")?;
+    match value.kind() {
+        "arrow_function" | "function" | "function_expression" => Some(value),
+        _ => None,
+    }
+}
+
+/// Whether a function node is the value of a declaration emitted in its place
+fn is_declared_function(node: &tree_sitter::Node) -> bool {
+    node.parent()
+        .and_then(|declarator| declarator.parent())
+        .and_then(|declaration| declared_function(&declaration))
+        .is_some_and(|value| value.id() == node.id())
+}
+
+/// Name a function expression or lambda is bound to
+///
+/// Covers `var f = function () {}`, `module.exports.f = () => {}`,
+/// `{ f: () => {} }`, class fields and Python `f = lambda: ...`.
+fn binding_name(node: &tree_sitter::Node, content: &str) -> Option<String> {
+    let parent = node.parent()?;
+    let target = match parent.kind() {
+        "variable_declarator" => parent.child_by_field_name("name"),
+        "assignment_expression" | "assignment" => parent.child_by_field_name("left"),
+        "pair" => parent.child_by_field_name("key"),
+        "field_definition" => parent.child_by_field_name("property"),
+        "public_field_definition" => parent.child_by_field_name("name"),
+        _ => None,
+    }?;
+    
+    let name = content[target.start_byte()..target.end_byte()]
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`');
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Node types whose name qualifies the blocks nested inside them
+fn is_scope_node(node_type: &str, language: &str) -> bool {
+    match language {
+        "python" => node_type == "class_definition",
+        "rust" => matches!(node_type, "impl_item" | "trait_item"),
+        "javascript" | "typescript" | "tsx" => matches!(node_type, "class_declaration" | "class"),
+        _ => false,
+    }
+}
+
+/// Separator between scope and item in qualified names
+fn scope_separator(language: &str) -> &'static str {
+    match language {
+        "rust" => "::",
+        _ => ".",
+    }
+}
+
+/// Whether a comment is an inner doc comment (//! or /*! */)
+fn is_inner_doc_comment(text: &str) -> bool {
+    text.starts_with("//!") || text.starts_with("/*!")
+}
+
+/// Strip comment markers from a doc comment, returning None for plain comments
+fn clean_doc_comment(text: &str) -> Option<String> {
+    let text = text.trim();
+    
+    let cleaned = if let Some(rest) = text.strip_prefix("///").or_else(|| text.strip_prefix("//!")) {
+        if rest.starts_with('/') {
+            // //// is an ordinary comment
+            return None;
+        }
+        rest.trim().to_string()
+    } else if text.starts_with("/**") || text.starts_with("/*!") {
+        if text == "/**/" {
+            return None;
+        }
+        let inner = text[3..].strip_suffix("*/").unwrap_or(&text[3..]);
+        inner.lines()
+            .map(|line| {
+                let line = line.trim();
+                line.strip_prefix('*').map(|l| l.trim_start()).unwrap_or(line)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    } else {
+        return None;
+    };
+    
+    if cleaned.is_empty() {
+        None
+    } else {
+        Some(cleaned)
+    }
+}
+
+/// tree-sitter grammar for a language name, if supported
+fn grammar_for(language: &str) -> Option<Language> {
+    match language {
+        "python" => Some(tree_sitter_python::language()),
+        "rust" => Some(tree_sitter_rust::language()),
+        "javascript" => Some(tree_sitter_javascript::language()),
+        "typescript" => Some(tree_sitter_typescript::language_typescript()),
+        // JSX syntax needs the separate TSX grammar
+        "tsx" => Some(tree_sitter_typescript::language_tsx()),
+        _ => None,
+    }
+}
+
+/// Compute the tree-sitter edit turning `old` into `new` (None if identical)
+fn compute_edit(old: &str, new: &str) -> Option<InputEdit> {
+    if old == new {
+        return None;
+    }
+    
+    let old_bytes = old.as_bytes();
+    let new_bytes = new.as_bytes();
+    let max_common = old_bytes.len().min(new_bytes.len());
+    
+    let mut prefix = old_bytes.iter().zip(new_bytes).take_while(|(a, b)| a == b).count();
+    while !new.is_char_boundary(prefix) || !old.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    
+    let mut suffix = old_bytes.iter().rev()
+        .zip(new_bytes.iter().rev())
+        .take(max_common - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !new.is_char_boundary(new.len() - suffix) || !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+    
+    let old_end_byte = old.len() - suffix;
+    let new_end_byte = new.len() - suffix;
+    
+    Some(InputEdit {
+        start_byte: prefix,
+        old_end_byte,
+        new_end_byte,
+        start_position: point_at(new, prefix),
+        old_end_position: point_at(old, old_end_byte),
+        new_end_position: point_at(new, new_end_byte),
+    })
+}
+
+fn point_at(content: &str, byte: usize) -> Point {
+    let before = &content[..byte];
+    let row = before.matches('\n').count();
+    let column = match before.rfind('\n') {
+        Some(pos) => byte - pos - 1,
+        None => byte,
+    };
+    Point { row, column }
+}
+
+/// Extend `line` forward over blank, comment, attribute and decorator lines,
+/// then include the first line of the item they belong to
+fn extend_over_leading_trivia(content: &str, line: usize) -> usize {
+    let mut end = line;
+    for text in content.lines().skip(line + 1) {
+        end += 1;
+        let trimmed = text.trim_start();
+        let is_trivia = trimmed.is_empty()
+            || trimmed.starts_with("//")
+            || trimmed.starts_with("/*")
+            || trimmed.starts_with('*')
+            || trimmed.starts_with('#')
+            || trimmed.starts_with('@');
+        if !is_trivia {
+            break;
+        }
+    }
+    end
+}
+
 impl Default for ASTParser {
     fn default() -> Self {
         Self::new()
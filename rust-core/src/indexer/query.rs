@@ -0,0 +1,193 @@
+/// Search query parsing
+///
+/// Grammar, with items separated by whitespace:
+///
+/// ```text
+/// query  := item*
+/// item   := "-"? (phrase | term)
+/// phrase := '"' any characters except '"' '"'
+/// term   := any characters except whitespace and '"'
+/// ```
+///
+/// Every term and phrase must match (implicit AND); a leading `-` excludes
+/// blocks matching the term or phrase instead. A `-` inside a term is
+/// literal, so `foo-bar` is one term, and a lone `-` is ignored. Matching
+/// is case-insensitive substring matching; the words of a phrase must be
+/// adjacent, separated by a single space. An unclosed quote is an error.
+
+use crate::error::{OrchestratorError, Result};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedQuery {
+    pub terms: Vec<String>, // Bare terms, in query order
+    pub phrases: Vec<String>, // Quoted phrases, whitespace collapsed to single spaces
+    pub excluded: Vec<String>, // Terms and phrases after a `-`
+}
+
+impl ParsedQuery {
+    /// Phrases and terms that must match, phrases first
+    pub fn positive(&self) -> Vec<&str> {
+        self.phrases.iter().chain(&self.terms).map(String::as_str).collect()
+    }
+    
+    /// The positive terms joined by spaces, for name ranking and embedding
+    pub fn positive_text(&self) -> String {
+        self.positive().join(" ")
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.phrases.is_empty() && self.excluded.is_empty()
+    }
+    
+    /// Whether an excluded term or phrase occurs in `content` or `name`
+    pub fn excludes(&self, content: &str, name: Option<&str>) -> bool {
+        let content = content.to_ascii_lowercase();
+        let name = name.map(|n| n.to_ascii_lowercase()).unwrap_or_default();
+        self.excluded.iter().any(|excluded| {
+            let excluded = excluded.to_ascii_lowercase();
+            content.contains(&excluded) || name.contains(&excluded)
+        })
+    }
+    
+    /// The query in SQLite FTS5 MATCH syntax
+    ///
+    /// None when nothing must match, since FTS cannot express a query made
+    /// only of exclusions.
+    pub fn to_fts_match(&self) -> Option<String> {
+        let positive = self.positive();
+        if positive.is_empty() {
+            return None;
+        }
+        let mut expression = positive.iter().map(|p| fts_string(p)).collect::<Vec<_>>().join(" ");
+        for excluded in &self.excluded {
+            expression.push_str(" NOT ");
+            expression.push_str(&fts_string(excluded));
+        }
+        Some(expression)
+    }
+    
+    /// How the query was interpreted, e.g. `phrases: "token bucket"; excluding: test`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.terms.is_empty() {
+            parts.push(format!("terms: {}", self.terms.join(", ")));
+        }
+        if !self.phrases.is_empty() {
+            let phrases: Vec<String> = self.phrases.iter().map(|p| format!("\"{}\"", p)).collect();
+            parts.push(format!("phrases: {}", phrases.join(", ")));
+        }
+        if !self.excluded.is_empty() {
+            parts.push(format!("excluding: {}", self.excluded.join(", ")));
+        }
+        if parts.is_empty() {
+            return "everything".to_string();
+        }
+        parts.join("; ")
+    }
+}
+
+/// Parse a search query; see the module docs for the grammar
+///
+/// Fails with `InvalidInput` if a quote is not closed.
+pub fn parse_query(query: &str) -> Result<ParsedQuery> {
+    let mut parsed = ParsedQuery::default();
+    let mut chars = query.chars().peekable();
+    
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+        
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let mut phrase = String::new();
+            let mut closed = false;
+            for c in chars.by_ref() {
+                if c == '"' {
+                    closed = true;
+                    break;
+                }
+                phrase.push(c);
+            }
+            if !closed {
+                return Err(OrchestratorError::InvalidInput(format!(
+                    "Unbalanced quote in search query: {}",
+                    query
+                )));
+            }
+            let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+            if phrase.is_empty() {
+                continue;
+            }
+            push_unique(if negated { &mut parsed.excluded } else { &mut parsed.phrases }, phrase);
+        } else {
+            let mut term = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                term.push(c);
+                chars.next();
+            }
+            if term.is_empty() {
+                continue;
+            }
+            push_unique(if negated { &mut parsed.excluded } else { &mut parsed.terms }, term);
+        }
+    }
+    
+    Ok(parsed)
+}
+
+fn push_unique(items: &mut Vec<String>, item: String) {
+    if !items.iter().any(|existing| existing.eq_ignore_ascii_case(&item)) {
+        items.push(item);
+    }
+}
+
+/// An FTS5 string literal, which matches its words as a phrase
+fn fts_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parses_terms_phrases_and_exclusions() {
+        let parsed = parse_query(r#""token  bucket" refill -test -"mock clock" foo-bar"#).unwrap();
+        assert_eq!(parsed.terms, vec!["refill", "foo-bar"]);
+        assert_eq!(parsed.phrases, vec!["token bucket"]);
+        assert_eq!(parsed.excluded, vec!["test", "mock clock"]);
+        assert_eq!(parsed.positive_text(), "token bucket refill foo-bar");
+        assert_eq!(
+            parsed.describe(),
+            r#"terms: refill, foo-bar; phrases: "token bucket"; excluding: test, mock clock"#
+        );
+    }
+    
+    #[test]
+    fn test_fts_match_syntax() {
+        let parsed = parse_query(r#""token bucket" refill -test"#).unwrap();
+        assert_eq!(parsed.to_fts_match().as_deref(), Some(r#""token bucket" "refill" NOT "test""#));
+        assert_eq!(parse_query("-test").unwrap().to_fts_match(), None);
+    }
+    
+    #[test]
+    fn test_lone_dash_and_empty_phrase_are_ignored() {
+        assert!(parse_query(r#" - "" "#).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_unbalanced_quote_is_invalid_input() {
+        assert!(matches!(parse_query(r#""token bucket"#), Err(OrchestratorError::InvalidInput(_))));
+        assert!(matches!(parse_query(r#"refill -""#), Err(OrchestratorError::InvalidInput(_))));
+    }
+}
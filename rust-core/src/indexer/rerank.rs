@@ -0,0 +1,139 @@
+/// Second-stage reordering of search candidates
+
+use crate::error::{OrchestratorError, Result};
+use crate::indexer::search::SearchResult;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Candidates passed to a reranker unless configured otherwise
+pub const DEFAULT_RERANK_CANDIDATES: usize = 50;
+
+/// Scores search candidates against the query, e.g. with a cross-encoder
+///
+/// Returns `(index into candidates, score)` pairs, best first. Candidates
+/// left out keep their relative order after the ranked ones.
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    async fn rerank(&self, query: &str, candidates: &[SearchResult]) -> Result<Vec<(usize, f32)>>;
+}
+
+/// Keeps the hybrid order and scores
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopReranker;
+
+#[async_trait]
+impl Reranker for NoopReranker {
+    async fn rerank(&self, _query: &str, candidates: &[SearchResult]) -> Result<Vec<(usize, f32)>> {
+        Ok(candidates.iter().enumerate().map(|(i, c)| (i, c.score)).collect())
+    }
+}
+
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    query: &'a str,
+    documents: Vec<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankScore>,
+}
+
+#[derive(Deserialize)]
+struct RerankScore {
+    index: usize,
+    #[serde(alias = "relevance_score")]
+    score: f32,
+}
+
+/// Reranks through an HTTP service
+///
+/// Posts `{"query": ..., "documents": [...]}` with each candidate's content
+/// and expects `{"results": [{"index": 0, "score": 0.9}, ...]}` back;
+/// `relevance_score` is accepted in place of `score`.
+pub struct HttpReranker {
+    client: reqwest::Client,
+    endpoint: String,
+    timeout: Duration,
+}
+
+impl HttpReranker {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+    
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl Reranker for HttpReranker {
+    async fn rerank(&self, query: &str, candidates: &[SearchResult]) -> Result<Vec<(usize, f32)>> {
+        let request = RerankRequest {
+            query,
+            documents: candidates.iter().map(|c| c.content.as_str()).collect(),
+        };
+        let response = self.client
+            .post(&self.endpoint)
+            .timeout(self.timeout)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| if e.is_timeout() {
+                OrchestratorError::Timeout(format!("Reranker did not respond within {:?}", self.timeout))
+            } else {
+                OrchestratorError::Network(e)
+            })?
+            .error_for_status()?;
+        
+        let mut ranking: Vec<(usize, f32)> = response
+            .json::<RerankResponse>()
+            .await?
+            .results
+            .into_iter()
+            .map(|r| (r.index, r.score))
+            .collect();
+        // Ties keep the hybrid order
+        ranking.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ranking)
+    }
+}
+
+/// Reorder `candidates` by `ranking`, setting each ranked result's score
+///
+/// Fails, leaving `candidates` untouched, if the ranking names an index out
+/// of range or the same index twice.
+pub(crate) fn apply_ranking(candidates: &mut Vec<SearchResult>, ranking: &[(usize, f32)]) -> Result<()> {
+    let mut taken = vec![false; candidates.len()];
+    for &(index, score) in ranking {
+        if index >= candidates.len() || taken[index] || !score.is_finite() {
+            return Err(OrchestratorError::InvalidInput(format!(
+                "Reranker returned an invalid entry ({}, {}) for {} candidates",
+                index,
+                score,
+                candidates.len()
+            )));
+        }
+        taken[index] = true;
+    }
+    
+    let mut slots: Vec<Option<SearchResult>> = std::mem::take(candidates).into_iter().map(Some).collect();
+    for &(index, score) in ranking {
+        if let Some(mut result) = slots[index].take() {
+            if let Some(explanation) = &mut result.explanation {
+                explanation.rerank_score = Some(score);
+            }
+            result.score = score;
+            candidates.push(result);
+        }
+    }
+    candidates.extend(slots.into_iter().flatten());
+    Ok(())
+}
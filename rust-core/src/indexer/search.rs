@@ -1,12 +1,35 @@
 /// Semantic search engine
 
-use crate::indexer::storage::IndexStorage;
+use crate::indexer::embedding_cache::{EmbeddingCache, EmbeddingMap};
+use crate::indexer::query::{parse_query, ParsedQuery};
+use crate::indexer::rerank::{apply_ranking, Reranker, DEFAULT_RERANK_CANDIDATES};
+use crate::indexer::storage::{IndexStorage, MatchKind};
 use crate::indexer::semantic::EmbeddingGenerator;
+use crate::indexer::vector;
 use crate::error::Result;
+use crate::pagination::{decode_cursor, encode_cursor, parse_field, Page};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Results ranked per query when paging; pages are slices of this ranking
+const MAX_PAGED_RESULTS: usize = 1000;
+const SEARCH_CURSOR: &str = "search";
+
+/// Weights of the semantic and keyword scores when both are available
+const SEMANTIC_WEIGHT: f32 = 0.7;
+const KEYWORD_WEIGHT: f32 = 0.3;
 
 pub struct SemanticSearch {
     storage: IndexStorage,
     embedding_gen: EmbeddingGenerator,
+    candidate_multiplier: usize, // Keyword candidates fetched per requested result
+    display_root: Option<PathBuf>, // Re-joined with stored relative paths in results
+    embedding_cache: EmbeddingCache,
+    reranker: Option<Box<dyn Reranker>>, // Reorders the top hybrid results
+    rerank_candidates: usize,
+    dedup_content: bool, // Collapse results with the same normalized content
 }
 
 impl SemanticSearch {
@@ -14,6 +37,12 @@ impl SemanticSearch {
         Self {
             storage,
             embedding_gen: EmbeddingGenerator::default(),
+            candidate_multiplier: 5,
+            display_root: None,
+            embedding_cache: EmbeddingCache::new(),
+            reranker: None,
+            rerank_candidates: DEFAULT_RERANK_CANDIDATES,
+            dedup_content: false,
         }
     }
     
@@ -21,70 +50,275 @@ impl SemanticSearch {
         Self {
             storage,
             embedding_gen,
+            candidate_multiplier: 5,
+            display_root: None,
+            embedding_cache: EmbeddingCache::new(),
+            reranker: None,
+            rerank_candidates: DEFAULT_RERANK_CANDIDATES,
+            dedup_content: false,
         }
     }
     
+    pub fn with_candidate_multiplier(mut self, multiplier: usize) -> Self {
+        self.candidate_multiplier = multiplier.max(1);
+        self
+    }
+    
+    /// Reorder the top results of `search` with a reranker
+    ///
+    /// If the reranker fails the hybrid order is kept and the response
+    /// carries a warning. `search_page` does not rerank, since its cursors
+    /// depend on the hybrid score order.
+    pub fn with_reranker(mut self, reranker: Box<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+    
+    /// Number of top results handed to the reranker
+    pub fn with_rerank_candidates(mut self, candidates: usize) -> Self {
+        self.rerank_candidates = candidates.max(1);
+        self
+    }
+    
+    /// Return one result per distinct block content
+    ///
+    /// Blocks whose code differs only in whitespace and comments collapse
+    /// into the highest-ranked one, which lists the other copies' paths in
+    /// `duplicates`.
+    pub fn with_dedup_content(mut self, dedup: bool) -> Self {
+        self.dedup_content = dedup;
+        self
+    }
+    
+    pub fn with_display_root(mut self, root: PathBuf) -> Self {
+        self.display_root = Some(root);
+        self
+    }
+    
+    /// Re-join a stored relative path with the display root, if configured
+    fn display_path(&self, file_path: String) -> String {
+        match &self.display_root {
+            Some(root) => root.join(&file_path).to_string_lossy().to_string(),
+            None => file_path,
+        }
+    }
+    
+    /// Load a project's embeddings now rather than on its first query
+    ///
+    /// Returns the number of embeddings cached.
+    pub async fn preload(&mut self, project_id: &str) -> Result<usize> {
+        Ok(self.embedding_cache.get(&self.storage, project_id).await?.len())
+    }
+    
+    /// Drop a project's cached embeddings; the next query reloads them
+    pub fn invalidate(&mut self, project_id: &str) -> bool {
+        self.embedding_cache.invalidate(project_id)
+    }
+    
+    /// Number of times embeddings were loaded from storage
+    pub fn embedding_loads(&self) -> usize {
+        self.embedding_cache.loads()
+    }
+    
+    /// Warning for loaded embeddings whose size differs from query embeddings
+    fn stale_warning<'a>(&self, embeddings: impl Iterator<Item = &'a Vec<f32>>) -> Option<String> {
+        let embedding_dim = self.embedding_gen.embedding_dim();
+        dimension_warning(embeddings.filter(|e| e.len() != embedding_dim).count(), embedding_dim)
+    }
+    
+    /// Report which search features are usable for a project
+    pub async fn capabilities(&self, project_id: &str) -> Result<SearchCapabilities> {
+        let (with_embeddings, total) = self.storage.embedding_coverage(project_id).await?;
+        let embedding_dim = self.embedding_gen.embedding_dim();
+        let stale_embeddings = self.storage.find_mismatched_embeddings(project_id, embedding_dim).await?.len();
+        Ok(SearchCapabilities {
+            embeddings_available: with_embeddings > 0,
+            embedding_model: self.embedding_gen.model_name(),
+            embedding_dim,
+            blocks_with_embeddings: with_embeddings as usize,
+            blocks_total: total as usize,
+            fts_enabled: self.storage.fts_enabled().await?,
+            stale_embeddings,
+            warning: dimension_warning(stale_embeddings, embedding_dim),
+        })
+    }
+    
     /// Search for code blocks using hybrid search (semantic + keyword)
+    ///
+    /// The query may quote phrases and exclude terms with `-` (see
+    /// `indexer::query`); only its positive terms are embedded. Falls back to keyword-only ranking when the project has no embeddings;
+    /// the response's `mode` and `degraded` fields say when that happened.
     pub async fn search(
         &mut self,
         project_id: &str,
         query: &str,
         limit: usize,
-    ) -> Result<Vec<SearchResult>> {
-        // Generate query embedding
-        let query_embedding = self.embedding_gen.generate_query_embedding(query);
+    ) -> Result<SearchResponse> {
+        self.search_with_explain(project_id, query, limit, false).await
+    }
+    
+    /// `search`, attaching an `Explanation` of its score to each result if `explain` is set
+    pub async fn search_with_explain(
+        &mut self,
+        project_id: &str,
+        query: &str,
+        limit: usize,
+        explain: bool,
+    ) -> Result<SearchResponse> {
+        self.hybrid_search(project_id, query, limit, explain, true).await
+    }
+    
+    /// Hybrid search for several queries, loading the project's embeddings once
+    ///
+    /// Query embeddings are generated as one batch. Returns each query's
+    /// results in query order, the same results `search` would return for it.
+    pub async fn search_batch(
+        &mut self,
+        project_id: &str,
+        queries: &[&str],
+        limit_per_query: usize,
+    ) -> Result<Vec<Vec<SearchResult>>> {
+        self.search_batch_with_dedup(project_id, queries, limit_per_query, false).await
+    }
+    
+    /// `search_batch`, suppressing blocks an earlier query already returned if `dedup_across_queries` is set
+    ///
+    /// Suppressed blocks make room for the next-best results, so every
+    /// query still gets up to `limit_per_query` results.
+    pub async fn search_batch_with_dedup(
+        &mut self,
+        project_id: &str,
+        queries: &[&str],
+        limit_per_query: usize,
+        dedup_across_queries: bool,
+    ) -> Result<Vec<Vec<SearchResult>>> {
+        let parsed = queries.iter().map(|query| parse_query(query)).collect::<Result<Vec<_>>>()?;
+        let positive_texts: Vec<String> = parsed.iter().map(|query| query.positive_text()).collect();
+        let positive_texts: Vec<&str> = positive_texts.iter().map(String::as_str).collect();
+        let query_embeddings = self.embedding_gen.generate_query_embeddings_batch(&positive_texts);
+        let embeddings = self.load_embeddings(project_id).await?;
         
-        // Get all block embeddings for semantic search
-        let block_embeddings = self.storage.get_block_embeddings(project_id).await?;
-        let embedding_map: std::collections::HashMap<i64, Vec<f32>> = 
-            block_embeddings.into_iter().collect();
+        let options = HybridOptions {
+            limit: limit_per_query,
+            explain: false,
+            rerank: true,
+        };
+        let mut returned = HashSet::new();
+        let mut batch = Vec::with_capacity(queries.len());
+        for (parsed, query_embedding) in parsed.into_iter().zip(&query_embeddings) {
+            let response = self
+                .rank_hybrid(project_id, parsed, query_embedding, &embeddings, options, &returned)
+                .await?;
+            if dedup_across_queries {
+                returned.extend(response.results.iter().filter_map(|r| r.block_id));
+            }
+            batch.push(response.results);
+        }
+        Ok(batch)
+    }
+    
+    /// Block embeddings for semantic search, cached across queries
+    async fn load_embeddings(&mut self, project_id: &str) -> Result<ProjectEmbeddings> {
+        let map = self.embedding_cache.get(&self.storage, project_id).await?;
+        let (_, blocks_total) = self.storage.embedding_coverage(project_id).await?;
+        Ok(ProjectEmbeddings {
+            map,
+            blocks_total: blocks_total as usize,
+        })
+    }
+    
+    async fn hybrid_search(
+        &mut self,
+        project_id: &str,
+        query: &str,
+        limit: usize,
+        explain: bool,
+        rerank: bool,
+    ) -> Result<SearchResponse> {
+        let parsed = parse_query(query)?;
+        
+        // Generate query embedding from the terms that must match
+        let query_embedding = self.embedding_gen.generate_query_embedding(&parsed.positive_text());
+        let embeddings = self.load_embeddings(project_id).await?;
+        
+        let options = HybridOptions { limit, explain, rerank };
+        self.rank_hybrid(project_id, parsed, &query_embedding, &embeddings, options, &HashSet::new())
+            .await
+    }
+    
+    /// Rank one parsed query against loaded embeddings, leaving out `suppressed` block ids
+    async fn rank_hybrid(
+        &self,
+        project_id: &str,
+        parsed: ParsedQuery,
+        query_embedding: &[f32],
+        embeddings: &ProjectEmbeddings,
+        options: HybridOptions,
+        suppressed: &HashSet<i64>,
+    ) -> Result<SearchResponse> {
+        let HybridOptions { limit, explain, rerank } = options;
+        let positive_text = parsed.positive_text();
+        let interpretation = parsed.describe();
+        let embedding_map = &embeddings.map;
         
         // Perform keyword search to get candidate blocks
-        let keyword_results = self.storage.search_blocks(project_id, query, limit * 5).await?;
+        let keyword_results = self.storage
+            .search_parsed_blocks(project_id, &parsed, limit * self.candidate_multiplier)
+            .await?;
         
         // Get block details with IDs for keyword results
         let mut keyword_results_with_ids = Vec::new();
-        for (file_path, block_type, name, start_line, end_line) in keyword_results {
+        for (file_path, block_type, name, start_line, end_line, content, match_kind, parent_name, parent_block_type) in keyword_results {
             if let Some(block_id) = self.storage.get_block_id(
                 project_id,
                 &file_path,
                 name.as_deref()
             ).await.ok().flatten() {
-                keyword_results_with_ids.push((block_id, file_path, block_type, name, start_line, end_line));
+                keyword_results_with_ids.push((block_id, file_path, block_type, name, start_line, end_line, content, match_kind, parent_name, parent_block_type));
             }
         }
         
+        let query_terms = query_terms(&parsed);
+        
         // Calculate scores for keyword results
         let mut results: Vec<SearchResult> = keyword_results_with_ids
             .into_iter()
-            .map(|(block_id, file_path, block_type, name, start_line, end_line)| {
-                // Keyword match score
-                let mut keyword_score = 0.5;
-                
-                // Boost if name matches exactly
-                if let Some(ref n) = name {
-                    let query_lower = query.to_lowercase();
-                    let name_lower = n.to_lowercase();
-                    if name_lower == query_lower {
-                        keyword_score = 1.0;
-                    } else if name_lower.contains(&query_lower) {
-                        keyword_score += 0.3;
-                    }
-                }
+            .map(|(block_id, file_path, block_type, name, start_line, end_line, content, match_kind, parent_name, parent_block_type)| {
+                // Keyword match score from SQL-level match ranking
+                let keyword_score = match_kind.keyword_score();
                 
                 // Calculate semantic similarity if embedding exists
                 let semantic_score = embedding_map.get(&block_id)
-                    .map(|block_embedding| {
-                        cosine_similarity(&query_embedding, block_embedding)
+                    .and_then(|block_embedding| {
+                        // Stale embeddings of another dimension score nothing; stale_warning reports them
+                        vector::cosine_similarity(query_embedding, block_embedding).ok()
                     })
                     .unwrap_or(0.0);
                 
                 // Combine scores (70% semantic if available, 30% keyword)
-                let combined_score = if semantic_score > 0.0 {
-                    semantic_score * 0.7 + keyword_score * 0.3
+                let (semantic_weight, keyword_weight) = if semantic_score > 0.0 {
+                    (SEMANTIC_WEIGHT, KEYWORD_WEIGHT)
                 } else {
-                    keyword_score
+                    (0.0, 1.0)
                 };
+                let combined_score = semantic_score * semantic_weight + keyword_score * keyword_weight;
+                
+                let match_ranges = find_match_ranges(&content, &query_terms);
+                let matched_terms = find_matched_terms(&content, name.as_deref(), &query_terms);
+                let explanation = explain.then(|| Explanation {
+                    source: ResultSource::KeywordCandidate,
+                    match_kind: Some(match_kind),
+                    exact_name_match: match_kind == MatchKind::ExactName,
+                    name_contains: match_kind != MatchKind::ContentContains,
+                    content_hit: !match_ranges.is_empty(),
+                    keyword_score,
+                    semantic_score,
+                    keyword_weight,
+                    semantic_weight,
+                    pre_dedup_rank: 0,
+                    rerank_score: None,
+                    query: interpretation.clone(),
+                });
                 
                 SearchResult {
                     file_path,
@@ -94,6 +328,13 @@ impl SemanticSearch {
                     end_line: end_line as usize,
                     score: combined_score,
                     block_id: Some(block_id),
+                    content,
+                    match_ranges,
+                    matched_terms,
+                    parent_name,
+                    parent_block_type,
+                    explanation,
+                    duplicates: Vec::new(),
                 }
             })
             .collect();
@@ -103,14 +344,15 @@ impl SemanticSearch {
             let mut semantic_results: Vec<(i64, f32)> = embedding_map
                 .iter()
                 .map(|(block_id, block_embedding)| {
-                    let similarity = cosine_similarity(&query_embedding, block_embedding);
+                    let similarity = vector::cosine_similarity(query_embedding, block_embedding).unwrap_or(0.0);
                     (*block_id, similarity)
                 })
                 .filter(|(_, similarity)| *similarity > 0.5) // Threshold for semantic matches
+                .filter(|(block_id, _)| !suppressed.contains(block_id))
                 .collect();
             
-            // Sort by similarity
-            semantic_results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            // Sort by similarity, ties by block id so map order never decides which are taken
+            sort_by_similarity(&mut semantic_results);
             
             // Get block details for top semantic matches not already in results
             let existing_block_ids: std::collections::HashSet<i64> = results
@@ -120,16 +362,40 @@ impl SemanticSearch {
             
             for (block_id, similarity) in semantic_results.into_iter().take(limit) {
                 if !existing_block_ids.contains(&block_id) {
-                    // Get block details by ID
+                    // Get block details by ID, skipping those the query excludes
                     if let Some(block_details) = self.storage.get_block_by_id(block_id).await.ok().flatten() {
+                        if parsed.excludes(&block_details.5, block_details.2.as_deref()) {
+                            continue;
+                        }
+                        let matched_terms = find_matched_terms(&block_details.5, block_details.2.as_deref(), &query_terms);
                         results.push(SearchResult {
                             file_path: block_details.0,
                             block_type: block_details.1,
                             name: block_details.2,
                             start_line: block_details.3 as usize,
                             end_line: block_details.4 as usize,
-                            score: similarity * 0.7, // Pure semantic score
+                            score: similarity * SEMANTIC_WEIGHT, // Pure semantic score
                             block_id: Some(block_id),
+                            content: block_details.5,
+                            match_ranges: Vec::new(),
+                            matched_terms,
+                            parent_name: block_details.6,
+                            parent_block_type: block_details.7,
+                            explanation: explain.then(|| Explanation {
+                                source: ResultSource::SemanticOnly,
+                                match_kind: None,
+                                exact_name_match: false,
+                                name_contains: false,
+                                content_hit: false,
+                                keyword_score: 0.0,
+                                semantic_score: similarity,
+                                keyword_weight: 0.0,
+                                semantic_weight: SEMANTIC_WEIGHT,
+                                pre_dedup_rank: 0,
+                                rerank_score: None,
+                                query: interpretation.clone(),
+                            }),
+                            duplicates: Vec::new(),
                         });
                     }
                 }
@@ -137,34 +403,95 @@ impl SemanticSearch {
         }
         
         // Deduplicate results (by block_id if available, otherwise by file_path + name + start_line)
-        results.sort_by(|a, b| {
-            // Sort by score first
-            b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
-                .then_with(|| {
-                    // Then by block_id if available
-                    match (a.block_id, b.block_id) {
-                        (Some(id_a), Some(id_b)) => id_a.cmp(&id_b),
-                        _ => std::cmp::Ordering::Equal,
-                    }
-                })
-        });
+        results.sort_by(compare_results);
         
-        // Remove duplicates
-        let mut seen_ids = std::collections::HashSet::new();
-        let mut seen_keys = std::collections::HashSet::new();
+        for (rank, result) in results.iter_mut().enumerate() {
+            if let Some(explanation) = &mut result.explanation {
+                explanation.pre_dedup_rank = rank + 1;
+            }
+        }
+        
+        // Remove duplicates and blocks suppressed by earlier queries of a batch
+        let mut seen_ids = HashSet::new();
+        let mut seen_keys = HashSet::new();
         results.retain(|r| {
             if let Some(block_id) = r.block_id {
-                seen_ids.insert(block_id)
+                !suppressed.contains(&block_id) && seen_ids.insert(block_id)
             } else {
                 let key = format!("{}:{:?}:{}", r.file_path, r.name, r.start_line);
                 seen_keys.insert(key)
             }
         });
         
+        if self.dedup_content {
+            self.collapse_duplicate_content(&mut results).await?;
+        }
+        
+        let mut warnings = Vec::new();
+        if let Some(reranker) = self.reranker.as_ref().filter(|_| rerank) {
+            let window = results.len().min(self.rerank_candidates.max(limit));
+            let mut candidates: Vec<SearchResult> = results.drain(..window).collect();
+            let outcome = match reranker.rerank(&positive_text, &candidates).await {
+                Ok(ranking) => apply_ranking(&mut candidates, &ranking),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = outcome {
+                tracing::warn!(error = %e, "Reranking failed, keeping hybrid order");
+                warnings.push(format!("Reranking failed, results are in hybrid order: {}", e));
+            }
+            results.splice(0..0, candidates);
+        }
+        
         // Limit results
         results.truncate(limit);
         
-        Ok(results)
+        for result in &mut results {
+            result.file_path = self.display_path(std::mem::take(&mut result.file_path));
+            result.duplicates = std::mem::take(&mut result.duplicates)
+                .into_iter()
+                .map(|path| self.display_path(path))
+                .collect();
+        }
+        
+        let mode = if embedding_map.is_empty() {
+            SearchMode::KeywordOnly
+        } else {
+            SearchMode::Hybrid
+        };
+        Ok(SearchResponse {
+            results,
+            mode,
+            degraded: embedding_map.len() < embeddings.blocks_total,
+            next_cursor: None,
+            warning: self.stale_warning(embedding_map.values())
+                .into_iter()
+                .chain(warnings)
+                .reduce(|a, b| format!("{}; {}", a, b)),
+            query: parsed,
+        })
+    }
+    
+    /// Fold ranked results sharing a stored content hash into the first of them
+    async fn collapse_duplicate_content(&self, results: &mut Vec<SearchResult>) -> Result<()> {
+        let block_ids: Vec<i64> = results.iter().filter_map(|r| r.block_id).collect();
+        let hashes = self.storage.content_hashes(&block_ids).await?;
+        
+        let mut kept: HashMap<&str, usize> = HashMap::new();
+        let mut collapsed: Vec<SearchResult> = Vec::with_capacity(results.len());
+        for result in results.drain(..) {
+            let hash = result.block_id.and_then(|id| hashes.get(&id));
+            match hash.and_then(|hash| kept.get(hash.as_str())) {
+                Some(&index) => collapsed[index].duplicates.push(result.file_path),
+                None => {
+                    if let Some(hash) = hash {
+                        kept.insert(hash.as_str(), collapsed.len());
+                    }
+                    collapsed.push(result);
+                }
+            }
+        }
+        *results = collapsed;
+        Ok(())
     }
     
     /// Semantic-only search (when keyword search fails or is not desired)
@@ -174,57 +501,306 @@ impl SemanticSearch {
         query: &str,
         limit: usize,
         threshold: f32,
-    ) -> Result<Vec<SearchResult>> {
-        let query_embedding = self.embedding_gen.generate_query_embedding(query);
-        let block_embeddings = self.storage.get_block_embeddings(project_id).await?;
+    ) -> Result<SearchResponse> {
+        let parsed = parse_query(query)?;
+        let query_embedding = self.embedding_gen.generate_query_embedding(&parsed.positive_text());
+        let block_embeddings = self.embedding_cache.get(&self.storage, project_id).await?;
+        let (_, blocks_total) = self.storage.embedding_coverage(project_id).await?;
+        let degraded = block_embeddings.len() < blocks_total as usize;
         
         let mut results: Vec<(i64, f32)> = block_embeddings
-            .into_iter()
+            .iter()
             .map(|(block_id, block_embedding)| {
-                let similarity = cosine_similarity(&query_embedding, &block_embedding);
-                (block_id, similarity)
+                let similarity = vector::cosine_similarity(&query_embedding, block_embedding).unwrap_or(0.0);
+                (*block_id, similarity)
             })
             .filter(|(_, similarity)| *similarity >= threshold)
             .collect();
         
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        sort_by_similarity(&mut results);
         
+        let query_terms = query_terms(&parsed);
         let mut search_results = Vec::new();
-        for (block_id, similarity) in results.into_iter().take(limit) {
+        for (block_id, similarity) in results.into_iter() {
+            if search_results.len() == limit {
+                break;
+            }
             if let Some(block_details) = self.storage.get_block_by_id(block_id).await.ok().flatten() {
+                if parsed.excludes(&block_details.5, block_details.2.as_deref()) {
+                    continue;
+                }
+                let matched_terms = find_matched_terms(&block_details.5, block_details.2.as_deref(), &query_terms);
                 search_results.push(SearchResult {
-                    file_path: block_details.0,
+                    file_path: self.display_path(block_details.0),
                     block_type: block_details.1,
                     name: block_details.2,
                     start_line: block_details.3 as usize,
                     end_line: block_details.4 as usize,
                     score: similarity,
                     block_id: Some(block_id),
+                    content: block_details.5,
+                    match_ranges: Vec::new(),
+                    matched_terms,
+                    parent_name: block_details.6,
+                    parent_block_type: block_details.7,
+                    explanation: None,
+                    duplicates: Vec::new(),
                 });
             }
         }
         
-        Ok(search_results)
+        search_results.sort_by(compare_results);
+        
+        Ok(SearchResponse {
+            results: search_results,
+            mode: SearchMode::SemanticOnly,
+            degraded,
+            next_cursor: None,
+            warning: self.stale_warning(block_embeddings.values()),
+            query: parsed,
+        })
+    }
+    
+    /// Find files whose path or summary mentions the query terms
+    ///
+    /// Path hits weigh twice as much as summary hits. Files without a
+    /// summary (see `CodebaseIndexer::summarize_files`) match on path only.
+    /// Files whose path or summary contains an excluded term are skipped.
+    pub async fn search_files(&self, project_id: &str, query: &str, limit: usize) -> Result<Vec<FileResult>> {
+        let parsed = parse_query(query)?;
+        let terms = query_terms(&parsed);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let mut results: Vec<FileResult> = self.storage
+            .list_file_summaries(project_id)
+            .await?
+            .into_iter()
+            .filter(|(file_path, summary)| !parsed.excludes(summary.as_deref().unwrap_or(""), Some(file_path)))
+            .filter_map(|(file_path, summary)| {
+                let path_lower = file_path.to_ascii_lowercase();
+                let summary_lower = summary.as_deref().unwrap_or("").to_ascii_lowercase();
+                let mut matched_terms = Vec::new();
+                let mut hits = 0;
+                for term in &terms {
+                    let in_path = path_lower.contains(term.as_str());
+                    let in_summary = summary_lower.contains(term.as_str());
+                    hits += 2 * in_path as usize + in_summary as usize;
+                    if in_path || in_summary {
+                        matched_terms.push(term.clone());
+                    }
+                }
+                (hits > 0).then(|| FileResult {
+                    file_path,
+                    summary,
+                    score: hits as f32 / (3 * terms.len()) as f32,
+                    matched_terms,
+                })
+            })
+            .collect();
+        
+        results.sort_by(|a, b| {
+            b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal)
+                .then_with(|| a.file_path.cmp(&b.file_path))
+        });
+        results.truncate(limit);
+        for result in &mut results {
+            result.file_path = self.display_path(std::mem::take(&mut result.file_path));
+        }
+        Ok(results)
     }
+    
+    /// Hybrid search returning one page of results
+    ///
+    /// The cursor encodes the ordering key of the last result (see
+    /// `SearchResult`), so it stays valid for repeated identical queries
+    /// against the same index.
+    pub async fn search_page(
+        &mut self,
+        project_id: &str,
+        query: &str,
+        page_size: usize,
+        cursor: Option<String>,
+    ) -> Result<SearchResponse> {
+        // The file path goes last since it may contain the cursor separator
+        let after = match cursor.as_deref() {
+            Some(cursor) => {
+                let fields = decode_cursor(SEARCH_CURSOR, cursor, 4)?;
+                let score_bits: u32 = parse_field(SEARCH_CURSOR, &fields[0])?;
+                let start_line: usize = parse_field(SEARCH_CURSOR, &fields[1])?;
+                let block_id: i64 = parse_field(SEARCH_CURSOR, &fields[2])?;
+                Some((f32::from_bits(score_bits), fields[3].clone(), start_line, block_id))
+            }
+            None => None,
+        };
+        
+        let mut response = self.hybrid_search(project_id, query, MAX_PAGED_RESULTS, false, false).await?;
+        let results = std::mem::take(&mut response.results);
+        
+        // Results are in `compare_results` order; skip up to the cursor
+        let items: Vec<SearchResult> = results
+            .into_iter()
+            .filter(|r| match &after {
+                Some((score, file_path, start_line, block_id)) => {
+                    compare_keys(result_key(r), (*score, file_path, *start_line, *block_id)) == Ordering::Greater
+                }
+                None => true,
+            })
+            .take(page_size)
+            .collect();
+        
+        let page = Page::from_items(items, page_size, |r| {
+            encode_cursor(
+                SEARCH_CURSOR,
+                &[
+                    r.score.to_bits().to_string(),
+                    r.start_line.to_string(),
+                    r.block_id.unwrap_or_default().to_string(),
+                    r.file_path.clone(),
+                ],
+            )
+        });
+        response.results = page.items;
+        response.next_cursor = page.next_cursor;
+        Ok(response)
+    }
+}
+
+/// A project's block embeddings and block count, loaded once per search or batch
+struct ProjectEmbeddings {
+    map: Arc<EmbeddingMap>,
+    blocks_total: usize,
+}
+
+/// Per-query settings of `SemanticSearch::rank_hybrid`
+#[derive(Clone, Copy)]
+struct HybridOptions {
+    limit: usize,
+    explain: bool,
+    rerank: bool,
+}
+
+/// Why a result scored what it did, as text
+///
+/// Results searched without `explain` only get their score.
+pub fn render_explanation(result: &SearchResult) -> String {
+    let location = format!(
+        "{} {}:{}-{} score {:.3}",
+        result.name.as_deref().unwrap_or("<anonymous>"),
+        result.file_path,
+        result.start_line,
+        result.end_line,
+        result.score
+    );
+    let explanation = match &result.explanation {
+        Some(explanation) => explanation,
+        None => return format!("{} (no explanation)", location),
+    };
+    
+    let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+    let reranked = match explanation.rerank_score {
+        Some(score) => format!("\n  reranked: {:.3}", score),
+        None => String::new(),
+    };
+    format!(
+        "#{} {}\n  = {:.2} x semantic {:.3} + {:.2} x keyword {:.3}\n  source: {}, match: {}, exact name: {}, name contains: {}, content hit: {}\n  query: {}{}",
+        explanation.pre_dedup_rank,
+        location,
+        explanation.semantic_weight,
+        explanation.semantic_score,
+        explanation.keyword_weight,
+        explanation.keyword_score,
+        explanation.source.as_str(),
+        explanation.match_kind.map(|kind| kind.as_str()).unwrap_or("none"),
+        yes_no(explanation.exact_name_match),
+        yes_no(explanation.name_contains),
+        yes_no(explanation.content_hit),
+        explanation.query,
+        reranked,
+    )
+}
+
+/// The result ordering contract; see `SearchResult`
+fn compare_results(a: &SearchResult, b: &SearchResult) -> Ordering {
+    compare_keys(result_key(a), result_key(b))
 }
 
-/// Calculate cosine similarity between two vectors
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    if a.len() != b.len() {
-        return 0.0;
+/// (score, file path, start line, block id), with 0 standing in for a missing block id
+fn result_key(result: &SearchResult) -> (f32, &str, usize, i64) {
+    (result.score, &result.file_path, result.start_line, result.block_id.unwrap_or_default())
+}
+
+/// Score descending, then file path, start line and block id ascending
+fn compare_keys(a: (f32, &str, usize, i64), b: (f32, &str, usize, i64)) -> Ordering {
+    b.0.total_cmp(&a.0)
+        .then_with(|| a.1.cmp(b.1))
+        .then_with(|| a.2.cmp(&b.2))
+        .then_with(|| a.3.cmp(&b.3))
+}
+
+/// Sort (block id, similarity) pairs best first, ties by block id
+fn sort_by_similarity(scores: &mut [(i64, f32)]) {
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+}
+
+/// The phrases and terms a query must match, lowercase and de-duplicated
+fn query_terms(query: &ParsedQuery) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for positive in query.positive() {
+        let term = positive.to_ascii_lowercase();
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
     }
+    terms
+}
+
+/// Locate case-insensitive occurrences of query terms in content
+///
+/// Returns sorted, merged byte ranges `(start, end)` into `content`. ASCII
+/// lowercasing keeps byte offsets identical to the original string.
+fn find_match_ranges(content: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let haystack = content.to_ascii_lowercase();
+    let mut ranges = Vec::new();
     
-    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    for term in terms {
+        let mut offset = 0;
+        while let Some(pos) = haystack[offset..].find(term.as_str()) {
+            let start = offset + pos;
+            let end = start + term.len();
+            ranges.push((start, end));
+            offset = end;
+        }
+    }
     
-    if norm_a > 0.0 && norm_b > 0.0 {
-        dot_product / (norm_a * norm_b)
-    } else {
-        0.0
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
     }
+    merged
+}
+
+/// Query terms present in the block content or name
+fn find_matched_terms(content: &str, name: Option<&str>, terms: &[String]) -> Vec<String> {
+    let content_lower = content.to_ascii_lowercase();
+    let name_lower = name.map(|n| n.to_ascii_lowercase()).unwrap_or_default();
+    terms.iter()
+        .filter(|term| content_lower.contains(term.as_str()) || name_lower.contains(term.as_str()))
+        .cloned()
+        .collect()
 }
 
+/// A block found by a search
+///
+/// Searches return results in a fixed order: score descending, then
+/// `file_path`, `start_line` and `block_id` ascending, so equal scores
+/// never reorder between runs. The exception is the head of a reranked
+/// search, which is in the reranker's order.
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub file_path: String,
@@ -234,4 +810,105 @@ pub struct SearchResult {
     pub end_line: usize,
     pub score: f32,
     pub block_id: Option<i64>, // For deduplication and reference
+    pub content: String,
+    pub match_ranges: Vec<(usize, usize)>, // Byte offsets into `content` (keyword hits only)
+    pub matched_terms: Vec<String>, // Query terms found in content or name
+    pub parent_name: Option<String>, // Enclosing block (e.g. the class of a method)
+    pub parent_block_type: Option<String>,
+    pub explanation: Option<Explanation>, // Set when searched with `explain`
+    pub duplicates: Vec<String>, // Paths of collapsed copies; see `with_dedup_content`
+}
+
+/// A file matched by `SemanticSearch::search_files`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileResult {
+    pub file_path: String,
+    pub summary: Option<String>,
+    pub score: f32, // 0.0 to 1.0; 1.0 when every term is in both path and summary
+    pub matched_terms: Vec<String>,
+}
+
+/// Which pass of a hybrid search produced a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultSource {
+    KeywordCandidate,
+    SemanticOnly,
+}
+
+impl ResultSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultSource::KeywordCandidate => "keyword_candidate",
+            ResultSource::SemanticOnly => "semantic_only",
+        }
+    }
+}
+
+/// Components of a result's score
+///
+/// `score` is `semantic_weight * semantic_score + keyword_weight * keyword_score`,
+/// or `rerank_score` when a reranker reordered the result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub source: ResultSource,
+    pub match_kind: Option<MatchKind>, // None for semantic-only results
+    pub exact_name_match: bool,
+    pub name_contains: bool, // Name matched exactly, by prefix or as a substring
+    pub content_hit: bool, // A query term occurs in the content
+    pub keyword_score: f32,
+    pub semantic_score: f32,
+    pub keyword_weight: f32,
+    pub semantic_weight: f32,
+    pub pre_dedup_rank: usize, // 1-based position before duplicates were removed
+    pub rerank_score: Option<f32>, // Set when a reranker scored the result
+    pub query: String, // How the query was interpreted; see `ParsedQuery::describe`
+}
+
+/// How a search ranked its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Hybrid,
+    KeywordOnly,
+    SemanticOnly,
+}
+
+impl SearchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchMode::Hybrid => "hybrid",
+            SearchMode::KeywordOnly => "keyword_only",
+            SearchMode::SemanticOnly => "semantic_only",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    pub mode: SearchMode,
+    pub degraded: bool, // Some or all blocks lack embeddings, so semantic ranking is partial
+    pub next_cursor: Option<String>, // Set by `search_page` when more results may follow
+    pub warning: Option<String>, // Set when stored embeddings do not match the query embedding size
+    pub query: ParsedQuery, // The query as parsed, phrases and exclusions separated
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchCapabilities {
+    pub embeddings_available: bool, // Any block in the project has an embedding
+    pub embedding_model: Option<String>, // None when embeddings are hash-based
+    pub embedding_dim: usize,
+    pub blocks_with_embeddings: usize,
+    pub blocks_total: usize,
+    pub fts_enabled: bool,
+    pub stale_embeddings: usize, // Blocks whose embedding is not `embedding_dim` floats long
+    pub warning: Option<String>, // Set when `stale_embeddings` is nonzero
+}
+
+/// Stale embeddings score 0 against every query, so say how many there are
+fn dimension_warning(stale_embeddings: usize, embedding_dim: usize) -> Option<String> {
+    (stale_embeddings > 0).then(|| format!(
+        "{} stale embeddings are not {}-dimensional and are ignored by semantic ranking; re-embed the project",
+        stale_embeddings,
+        embedding_dim
+    ))
 }
@@ -1,8 +1,12 @@
 /// Semantic embedding generation
 
+use crate::indexer::diagnostics::{DiagnosticSource, Diagnostics};
+use crate::indexer::disk_cache::{CacheCounters, DiskEmbeddingCache, EmbeddingCacheStats};
 use crate::indexer::parser::CodeBlock;
+use crate::indexer::storage::content_hash;
+use crate::indexer::vector::l2_normalize;
 use std::sync::Arc;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 
 #[cfg(feature = "onnx-embeddings")]
@@ -14,6 +18,9 @@ pub struct EmbeddingGenerator {
     #[cfg(feature = "onnx-embeddings")]
     model_session: Option<Arc<Session>>,
     embedding_cache: HashMap<String, Vec<f32>>, // Simple in-memory cache
+    disk_cache: Option<DiskEmbeddingCache>, // Block embeddings kept across runs, written through
+    counters: CacheCounters,
+    diagnostics: Diagnostics,
 }
 
 impl EmbeddingGenerator {
@@ -24,6 +31,9 @@ impl EmbeddingGenerator {
             #[cfg(feature = "onnx-embeddings")]
             model_session: None,
             embedding_cache: HashMap::new(),
+            disk_cache: None,
+            counters: CacheCounters::default(),
+            diagnostics: Diagnostics::new(),
         }
     }
     
@@ -63,6 +73,9 @@ impl EmbeddingGenerator {
                 model_path: Some(model_path),
                 model_session: Some(Arc::new(session)),
                 embedding_cache: HashMap::new(),
+                disk_cache: None,
+                counters: CacheCounters::default(),
+                diagnostics: Diagnostics::new(),
             })
         }
         
@@ -74,10 +87,60 @@ impl EmbeddingGenerator {
                 embedding_dim,
                 model_path: Some(model_path),
                 embedding_cache: HashMap::new(),
+                disk_cache: None,
+                counters: CacheCounters::default(),
+                diagnostics: Diagnostics::new(),
             })
         }
     }
     
+    /// A generator sharing this one's model, diagnostics, disk cache and stats, with an empty memory cache
+    ///
+    /// Gives each embedding worker thread its own generator.
+    pub fn fork(&self) -> Self {
+        Self {
+            embedding_dim: self.embedding_dim,
+            model_path: self.model_path.clone(),
+            #[cfg(feature = "onnx-embeddings")]
+            model_session: self.model_session.clone(),
+            embedding_cache: HashMap::new(),
+            disk_cache: self.disk_cache.clone(),
+            counters: self.counters.clone(),
+            diagnostics: self.diagnostics.clone(),
+        }
+    }
+    
+    /// Keep block embeddings in a cache directory at `path` across runs
+    ///
+    /// Embeddings missing from memory are looked up there before being
+    /// generated, and generated ones are written through. Beyond
+    /// `max_entries`, the least recently used are evicted. Entries are keyed
+    /// by a hash of the block, the model name and the embedding size, so a
+    /// cache can be shared across models. Query embeddings stay in memory.
+    pub fn with_persistent_cache(mut self, path: impl AsRef<Path>, max_entries: usize) -> Result<Self, String> {
+        self.disk_cache = Some(DiskEmbeddingCache::open(path.as_ref(), max_entries)?);
+        Ok(self)
+    }
+    
+    pub fn persistent_cache(&self) -> Option<&DiskEmbeddingCache> {
+        self.disk_cache.as_ref()
+    }
+    
+    /// Where block embeddings came from, over this generator and its forks
+    pub fn cache_stats(&self) -> EmbeddingCacheStats {
+        self.counters.snapshot()
+    }
+    
+    /// Report model failures to `diagnostics`
+    pub fn with_diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+    
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+    
     #[cfg(feature = "onnx-embeddings")]
     fn load_model_if_needed(&mut self) -> Result<(), String> {
         if self.model_session.is_none() {
@@ -97,33 +160,65 @@ impl EmbeddingGenerator {
     /// Generate embedding for a code block
     /// 
     /// Uses ONNX model if available (when feature enabled and model loaded),
-    /// otherwise falls back to improved hash-based approach.
+    /// otherwise falls back to improved hash-based approach. Cached
+    /// embeddings are taken from memory, then from the persistent cache.
     pub fn generate_embedding(&mut self, block: &CodeBlock) -> Vec<f32> {
         // Check cache first
         let cache_key = format!("{}{:?}{}", block.content, block.name, block.block_type);
         if let Some(cached) = self.embedding_cache.get(&cache_key) {
+            self.counters.memory_hit();
             return cached.clone();
         }
         
+        let disk_key = self.disk_cache.as_ref().map(|_| self.disk_key(&cache_key));
+        if let (Some(disk_cache), Some(disk_key)) = (&self.disk_cache, &disk_key) {
+            match disk_cache.get(disk_key) {
+                Ok(Some(embedding)) => {
+                    self.counters.disk_hit();
+                    self.embedding_cache.insert(cache_key, embedding.clone());
+                    return embedding;
+                }
+                Ok(None) => {}
+                Err(e) => self.diagnostics.warn(DiagnosticSource::Embedding, None, e),
+            }
+        }
+        
+        let embedding = self.compute_embedding(block);
+        self.counters.computed();
+        if let (Some(disk_cache), Some(disk_key)) = (&self.disk_cache, &disk_key) {
+            if let Err(e) = disk_cache.put(disk_key, &embedding) {
+                self.diagnostics.warn(DiagnosticSource::Embedding, None, e);
+            }
+        }
+        self.embedding_cache.insert(cache_key, embedding.clone());
+        embedding
+    }
+    
+    /// Key of a block's embedding in the persistent cache
+    fn disk_key(&self, cache_key: &str) -> String {
+        let model = self.model_name().unwrap_or_else(|| "hash".to_string());
+        content_hash(&format!("{}\0{}\0{}", model, self.embedding_dim, cache_key))
+    }
+    
+    fn compute_embedding(&self, block: &CodeBlock) -> Vec<f32> {
         #[cfg(feature = "onnx-embeddings")]
         {
             if let Some(ref session) = self.model_session {
                 match self.generate_embedding_onnx(session, block) {
-                    Ok(embedding) => {
-                        self.embedding_cache.insert(cache_key, embedding.clone());
-                        return embedding;
-                    }
+                    Ok(embedding) => return embedding,
                     Err(e) => {
-                        eprintln!("ONNX embedding generation failed: {}, falling back to hash", e);
+                        self.diagnostics.warn(
+                            DiagnosticSource::Embedding,
+                            None,
+                            format!("ONNX embedding generation failed, falling back to hash: {}", e),
+                        );
                     }
                 }
             }
         }
         
         // Fallback to hash-based approach
-        let embedding = self.generate_embedding_hash(block);
-        self.embedding_cache.insert(cache_key, embedding.clone());
-        embedding
+        self.generate_embedding_hash(block)
     }
     
     #[cfg(feature = "onnx-embeddings")]
@@ -160,15 +255,11 @@ impl EmbeddingGenerator {
         let output_tensor = outputs[0].try_extract_tensor::<f32>()
             .map_err(|e| format!("Failed to extract output tensor: {}", e))?;
         
-        let embedding: Vec<f32> = output_tensor.iter().cloned().collect();
+        let mut embedding: Vec<f32> = output_tensor.iter().cloned().collect();
         
         // Normalize
-        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            Ok(embedding.iter().map(|x| x / norm).collect())
-        } else {
-            Ok(embedding)
-        }
+        l2_normalize(&mut embedding);
+        Ok(embedding)
     }
     
     /// Generate embeddings in batch (more efficient)
@@ -210,12 +301,7 @@ impl EmbeddingGenerator {
         }
         
         // Normalize to unit vector (important for similarity calculations)
-        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            for e in &mut embedding {
-                *e /= norm;
-            }
-        }
+        l2_normalize(&mut embedding);
         
         embedding
     }
@@ -229,6 +315,13 @@ impl EmbeddingGenerator {
         hasher.finish()
     }
     
+    /// Generate embeddings for several query texts, in order
+    pub fn generate_query_embeddings_batch(&mut self, queries: &[&str]) -> Vec<Vec<f32>> {
+        queries.iter()
+            .map(|query| self.generate_query_embedding(query))
+            .collect()
+    }
+    
     /// Generate embedding for query text
     pub fn generate_query_embedding(&mut self, query: &str) -> Vec<f32> {
         // Check cache
@@ -245,7 +338,11 @@ impl EmbeddingGenerator {
                         return embedding;
                     }
                     Err(e) => {
-                        eprintln!("ONNX query embedding failed: {}, falling back to hash", e);
+                        self.diagnostics.warn(
+                            crate::indexer::diagnostics::DiagnosticSource::Embedding,
+                            None,
+                            format!("ONNX query embedding failed, falling back to hash: {}", e),
+                        );
                     }
                 }
             }
@@ -278,14 +375,10 @@ impl EmbeddingGenerator {
         let output_tensor = outputs[0].try_extract_tensor::<f32>()
             .map_err(|e| format!("Failed to extract output tensor: {}", e))?;
         
-        let embedding: Vec<f32> = output_tensor.iter().cloned().collect();
+        let mut embedding: Vec<f32> = output_tensor.iter().cloned().collect();
         
-        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            Ok(embedding.iter().map(|x| x / norm).collect())
-        } else {
-            Ok(embedding)
-        }
+        l2_normalize(&mut embedding);
+        Ok(embedding)
     }
     
     fn generate_query_embedding_hash(&self, query: &str) -> Vec<f32> {
@@ -315,16 +408,25 @@ impl EmbeddingGenerator {
             embedding[i] = angle.sin() * 0.5 + angle.cos() * 0.5;
         }
         
-        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            for e in &mut embedding {
-                *e /= norm;
-            }
-        }
+        l2_normalize(&mut embedding);
         
         embedding
     }
     
+    pub fn embedding_dim(&self) -> usize {
+        self.embedding_dim
+    }
+    
+    /// Name of the loaded model (its file stem); None when using hash embeddings
+    pub fn model_name(&self) -> Option<String> {
+        if !self.has_model() {
+            return None;
+        }
+        self.model_path.as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+    }
+    
     /// Check if a real model is available
     pub fn has_model(&self) -> bool {
         #[cfg(feature = "onnx-embeddings")]
@@ -339,7 +441,7 @@ impl EmbeddingGenerator {
         }
     }
     
-    /// Clear embedding cache
+    /// Clear the in-memory embedding cache; the persistent cache is kept
     pub fn clear_cache(&mut self) {
         self.embedding_cache.clear();
     }
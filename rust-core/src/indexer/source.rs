@@ -0,0 +1,71 @@
+/// Decoding of source files that are not clean UTF-8
+
+/// Largest share of replaced characters a file may have and still be indexed, by default
+pub const DEFAULT_MAX_REPLACEMENT_RATIO: f64 = 0.1;
+
+/// Why a file was not indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// Too much of the file is invalid UTF-8 or NUL bytes to be text
+    Binary,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::Binary => "binary",
+        }
+    }
+}
+
+/// Text decoded from a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceText {
+    pub content: String,
+    pub replacements: usize, // Invalid sequences and NUL bytes replaced with U+FFFD
+}
+
+impl SourceText {
+    pub fn is_lossy(&self) -> bool {
+        self.replacements > 0
+    }
+}
+
+/// Decode file bytes for indexing
+///
+/// A UTF-8 byte order mark is stripped and `\r\n` normalized to `\n`, so
+/// line numbers match the file as editors show it. Invalid UTF-8 and NUL
+/// bytes become U+FFFD; if more than `max_replacement_ratio` of the
+/// characters had to be replaced, the file is treated as binary.
+pub fn decode_source(bytes: &[u8], max_replacement_ratio: f64) -> Result<SourceText, SkipReason> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    
+    let (text, mut replacements) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), 0),
+        Err(_) => {
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            let replacements = text.chars().filter(|&c| c == char::REPLACEMENT_CHARACTER).count();
+            (text, replacements)
+        }
+    };
+    
+    let nul_count = text.bytes().filter(|&b| b == 0).count();
+    let text = if nul_count > 0 {
+        replacements += nul_count;
+        text.replace('\0', "\u{FFFD}")
+    } else {
+        text
+    };
+    
+    let chars = text.chars().count();
+    if chars > 0 && replacements as f64 / chars as f64 > max_replacement_ratio {
+        return Err(SkipReason::Binary);
+    }
+    
+    let content = if text.contains("\r\n") {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    };
+    Ok(SourceText { content, replacements })
+}
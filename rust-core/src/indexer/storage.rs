@@ -1,16 +1,240 @@
 /// Index storage and persistence
 
+use crate::indexer::fingerprint::block_fingerprint;
+use crate::indexer::journal::ChangeKind;
 use crate::indexer::parser::CodeBlock;
-use sqlx::sqlite::SqlitePool;
-use crate::error::Result;
+use crate::indexer::query::{parse_query, ParsedQuery};
+use crate::indexer::summary::signature;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use crate::error::{OrchestratorError, Result, ResultExt};
+use crate::observability::MetricsCollector;
+use crate::security::validation::validate_like_pattern;
+use crate::storage::InstrumentedPool;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
+const MAX_BOUND_IDS: usize = 999;
+
+/// How a keyword search row matched the query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    ExactName,
+    NamePrefix,
+    NameContains,
+    ContentContains,
+}
+
+impl MatchKind {
+    fn from_rank(rank: i64) -> Self {
+        match rank {
+            0 => MatchKind::ExactName,
+            1 => MatchKind::NamePrefix,
+            2 => MatchKind::NameContains,
+            _ => MatchKind::ContentContains,
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchKind::ExactName => "exact_name",
+            MatchKind::NamePrefix => "name_prefix",
+            MatchKind::NameContains => "name_contains",
+            MatchKind::ContentContains => "content_contains",
+        }
+    }
+    
+    /// Keyword relevance score (0.0 to 1.0) used by hybrid search
+    pub fn keyword_score(&self) -> f32 {
+        match self {
+            MatchKind::ExactName => 1.0,
+            MatchKind::NamePrefix => 0.85,
+            MatchKind::NameContains => 0.7,
+            MatchKind::ContentContains => 0.5,
+        }
+    }
+}
+
+/// What produced a stored block
+///
+/// Each source only replaces its own blocks, so re-indexing a file from its
+/// AST keeps symbols imported from ctags or LSIF, and a new import keeps
+/// parsed blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSource {
+    Ast,
+    Ctags,
+    Lsif,
+}
+
+impl BlockSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockSource::Ast => "ast",
+            BlockSource::Ctags => "ctags",
+            BlockSource::Lsif => "lsif",
+        }
+    }
+}
+
+/// A stored block and its nested child blocks
+#[derive(Debug, Clone)]
+pub struct BlockNode {
+    pub id: i64,
+    pub block_type: String,
+    pub name: Option<String>,
+    pub start_line: i64,
+    pub end_line: i64,
+    pub children: Vec<BlockNode>,
+}
+
+/// An indexed file as listed by `IndexStorage::list_files`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFile {
+    pub file_path: String,
+    pub language: Option<String>,
+    pub block_count: i64,
+    pub indexed_at: String, // SQLite timestamp, UTC
+    pub has_summary: bool,
+}
+
+/// A stored block as listed by `IndexStorage::list_blocks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockListing {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub block_type: String,
+    pub name: Option<String>,
+    pub start_line: i64,
+    pub end_line: i64,
+    pub docstring: Option<String>,
+    pub signature: Option<String>, // First line of the block's content; None if it is blank
+}
+
+/// A stored block whose normalized content also appears elsewhere in the project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateBlock {
+    pub id: i64,
+    pub file_path: String,
+    pub name: Option<String>,
+    pub start_line: i64,
+    pub end_line: i64,
+}
+
+/// Blocks sharing one `content_hash`; see `IndexStorage::find_duplicate_blocks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub content_hash: String,
+    pub blocks: Vec<DuplicateBlock>, // Ordered by file path, then line
+}
+
+/// Summary of a project's stored embeddings; see `IndexStorage::embedding_fingerprint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingFingerprint {
+    pub embedded_blocks: i64,
+    pub max_block_id: Option<i64>,
+    pub last_indexed_at: Option<String>,
+}
+
+/// Model and size of a project's stored embeddings, recorded in `index_meta`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingMeta {
+    pub model: Option<String>, // None for hash-based embeddings
+    pub dim: usize,
+}
+
+/// How a watcher-driven change to a path turned out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexEventOutcome {
+    Indexed,
+    Removed,
+    /// Not indexed, e.g. a binary file; the event's `error` says why
+    Skipped,
+    Failed,
+}
+
+impl IndexEventOutcome {
+    pub const ALL: [IndexEventOutcome; 4] = [
+        IndexEventOutcome::Indexed,
+        IndexEventOutcome::Removed,
+        IndexEventOutcome::Skipped,
+        IndexEventOutcome::Failed,
+    ];
+    
+    pub fn parse(outcome: &str) -> Result<Self> {
+        IndexEventOutcome::ALL
+            .into_iter()
+            .find(|o| o.as_str() == outcome)
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!("Unknown index event outcome: {}", outcome)))
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexEventOutcome::Indexed => "indexed",
+            IndexEventOutcome::Removed => "removed",
+            IndexEventOutcome::Skipped => "skipped",
+            IndexEventOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// A row of the `index_events` table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEvent {
+    pub path: String,
+    pub action: ChangeKind,
+    pub outcome: IndexEventOutcome,
+    pub error: Option<String>, // Failure message or skip reason
+    pub recorded_at: i64, // Unix seconds
+}
+
+#[derive(Clone)]
 pub struct IndexStorage {
-    pool: SqlitePool,
+    pool: InstrumentedPool,
+    read_only: bool,
 }
 
 impl IndexStorage {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { pool: InstrumentedPool::new(pool), read_only: false }
+    }
+    
+    /// Observe query timings in `metrics` as well as the process-wide log
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.pool = self.pool.with_metrics(metrics);
+        self
+    }
+    
+    /// Open an existing index file for searching only
+    ///
+    /// The connection is opened read-only and the file is never created, so
+    /// this works on read-only mounts. Every mutating method fails with
+    /// `InvalidConfig` before touching the database.
+    pub async fn open_read_only(db_path: &Path) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(db_path)
+                    .read_only(true)
+                    .create_if_missing(false),
+            )
+            .await
+            .map_err(|e| OrchestratorError::InvalidConfig(format!(
+                "Failed to open index {} read-only: {}",
+                db_path.display(),
+                e
+            )))?;
+        Ok(Self { pool: InstrumentedPool::new(pool), read_only: true })
+    }
+    
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+    
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(OrchestratorError::InvalidConfig("index is read-only".to_string()));
+        }
+        Ok(())
     }
     
     pub async fn store_file(
@@ -20,6 +244,8 @@ impl IndexStorage {
         language: &str,
         blocks: &[CodeBlock],
     ) -> Result<()> {
+        self.ensure_writable()?;
+        let breadcrumb = || format!("storing file {} in project {}", file_path, project_id);
         // Calculate file hash (simple for now)
         let file_hash = format!("{:x}", md5::compute(format!("{}{}", project_id, file_path)));
         
@@ -31,6 +257,7 @@ impl IndexStorage {
             ON CONFLICT(project_id, file_path) DO UPDATE SET
                 language = ?,
                 file_hash = ?,
+                summary = NULL,
                 indexed_at = CURRENT_TIMESTAMP
             "#,
         )
@@ -41,7 +268,8 @@ impl IndexStorage {
         .bind(language)
         .bind(&file_hash)
         .execute(&self.pool)
-        .await?;
+        .await
+        .with_context(breadcrumb)?;
         
         // Get file ID
         let file_id: (i64,) = sqlx::query_as(
@@ -50,41 +278,121 @@ impl IndexStorage {
         .bind(project_id)
         .bind(file_path)
         .fetch_one(&self.pool)
-        .await?;
+        .await
+        .with_context(breadcrumb)?;
         
-        // Delete old blocks
-        sqlx::query("DELETE FROM code_blocks WHERE file_id = ?")
+        // Delete old blocks; imported ones are kept
+        sqlx::query("DELETE FROM code_blocks WHERE file_id = ? AND source = ?")
             .bind(file_id.0)
+            .bind(BlockSource::Ast.as_str())
             .execute(&self.pool)
-            .await?;
+            .await
+            .with_context(breadcrumb)?;
         
         // Insert new blocks (embeddings will be added separately if needed)
+        let mut block_ids = Vec::with_capacity(blocks.len());
         for block in blocks {
-            // Serialize decorators as JSON
-            let decorators_json = serde_json::to_string(&block.decorators).unwrap_or_else(|_| "[]".to_string());
-            
+            // Parents precede their children, so their row id is already known
+            let parent_id = block.parent_index.and_then(|i| block_ids.get(i).copied());
+            block_ids.push(insert_block(&self.pool, file_id.0, block, parent_id, BlockSource::Ast).await.with_context(breadcrumb)?);
+        }
+        
+        Ok(())
+    }
+    
+    /// Replace only the blocks of a file that intersect an edited line range
+    ///
+    /// Blocks overlapping `old_start_line..=old_end_line` are deleted and
+    /// `blocks` inserted in their place; blocks after the range are shifted
+    /// by `line_delta`. Returns the number of rows deleted and inserted.
+    pub async fn update_blocks_in_range(
+        &self,
+        project_id: &str,
+        file_path: &str,
+        old_start_line: usize,
+        old_end_line: usize,
+        line_delta: i64,
+        blocks: &[CodeBlock],
+    ) -> Result<usize> {
+        self.ensure_writable()?;
+        let breadcrumb = || format!("updating blocks of file {} in project {}", file_path, project_id);
+        let mut tx = self.pool.begin().await.with_context(breadcrumb)?;
+        
+        let file_id: (i64,) = sqlx::query_as(
+            "SELECT id FROM indexed_files WHERE project_id = ? AND file_path = ?"
+        )
+        .bind(project_id)
+        .bind(file_path)
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(breadcrumb)?;
+        
+        let deleted = sqlx::query(
+            "DELETE FROM code_blocks WHERE file_id = ? AND source = ? AND end_line >= ? AND start_line <= ?"
+        )
+        .bind(file_id.0)
+        .bind(BlockSource::Ast.as_str())
+        .bind(old_start_line as i64)
+        .bind(old_end_line as i64)
+        .execute(&mut *tx)
+        .await
+        .with_context(breadcrumb)?
+        .rows_affected() as usize;
+        
+        if line_delta != 0 {
             sqlx::query(
-                r#"
-                INSERT INTO code_blocks (file_id, block_type, name, content, start_line, end_line, embedding, docstring, decorators)
-                VALUES (?, ?, ?, ?, ?, ?, NULL, ?, ?)
-                "#,
+                "UPDATE code_blocks SET start_line = start_line + ?, end_line = end_line + ? WHERE file_id = ? AND start_line > ?"
             )
+            .bind(line_delta)
+            .bind(line_delta)
             .bind(file_id.0)
-            .bind(&block.block_type)
-            .bind(&block.name)
-            .bind(&block.content)
-            .bind(block.start_line as i64)
-            .bind(block.end_line as i64)
-            .bind(&block.docstring)
-            .bind(&decorators_json)
-            .execute(&self.pool)
-            .await?;
+            .bind(old_end_line as i64)
+            .execute(&mut *tx)
+            .await
+            .with_context(breadcrumb)?;
         }
         
-        Ok(())
+        let mut block_ids = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let parent_id = block.parent_index.and_then(|i| block_ids.get(i).copied());
+            block_ids.push(insert_block(&mut *tx, file_id.0, block, parent_id, BlockSource::Ast).await.with_context(breadcrumb)?);
+        }
+        
+        // Blocks outside the range whose parent was replaced (e.g. untouched
+        // methods of an edited class) move to the innermost new enclosing block
+        sqlx::query(
+            r#"
+            UPDATE code_blocks SET parent_block_id = (
+                SELECT p.id FROM code_blocks p
+                WHERE p.file_id = code_blocks.file_id AND p.id >= ?
+                AND p.start_line <= code_blocks.start_line AND p.end_line >= code_blocks.end_line
+                ORDER BY p.end_line - p.start_line, p.id DESC
+                LIMIT 1
+            )
+            WHERE file_id = ? AND parent_block_id IS NOT NULL
+            AND parent_block_id NOT IN (SELECT id FROM code_blocks WHERE file_id = ?)
+            "#,
+        )
+        .bind(block_ids.first().copied().unwrap_or(i64::MAX))
+        .bind(file_id.0)
+        .bind(file_id.0)
+        .execute(&mut *tx)
+        .await
+        .with_context(breadcrumb)?;
+        
+        sqlx::query("UPDATE indexed_files SET indexed_at = CURRENT_TIMESTAMP, summary = NULL WHERE id = ?")
+            .bind(file_id.0)
+            .execute(&mut *tx)
+            .await
+            .with_context(breadcrumb)?;
+        
+        tx.commit().await.with_context(breadcrumb)?;
+        
+        Ok(deleted + blocks.len())
     }
     
     pub async fn remove_file(&self, project_id: &str, file_path: &str) -> Result<()> {
+        self.ensure_writable()?;
         // Get file ID
         let file_id_result: Option<(i64,)> = sqlx::query_as(
             "SELECT id FROM indexed_files WHERE project_id = ? AND file_path = ?"
@@ -111,31 +419,568 @@ impl IndexStorage {
         Ok(())
     }
     
-    pub async fn search_blocks(
+    /// Record the content hash of an indexed file
+    pub async fn set_file_hash(&self, project_id: &str, file_path: &str, file_hash: &str) -> Result<()> {
+        self.ensure_writable()?;
+        sqlx::query("UPDATE indexed_files SET file_hash = ? WHERE project_id = ? AND file_path = ?")
+            .bind(file_hash)
+            .bind(project_id)
+            .bind(file_path)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Store the summary of an indexed file; it is cleared when the file is re-indexed
+    pub async fn set_file_summary(&self, project_id: &str, file_path: &str, summary: &str) -> Result<()> {
+        self.ensure_writable()?;
+        sqlx::query("UPDATE indexed_files SET summary = ? WHERE project_id = ? AND file_path = ?")
+            .bind(summary)
+            .bind(project_id)
+            .bind(file_path)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    pub async fn get_file_summary(&self, project_id: &str, file_path: &str) -> Result<Option<String>> {
+        let summary: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT summary FROM indexed_files WHERE project_id = ? AND file_path = ?"
+        )
+        .bind(project_id)
+        .bind(file_path)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(summary.flatten())
+    }
+    
+    /// Paths of a project's files that have no summary yet
+    pub async fn list_files_without_summary(&self, project_id: &str) -> Result<Vec<String>> {
+        let paths = sqlx::query_scalar(
+            "SELECT file_path FROM indexed_files WHERE project_id = ? AND summary IS NULL ORDER BY file_path"
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(paths)
+    }
+    
+    /// Path and summary of every indexed file in a project
+    pub async fn list_file_summaries(&self, project_id: &str) -> Result<Vec<(String, Option<String>)>> {
+        let files = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT file_path, summary FROM indexed_files WHERE project_id = ? ORDER BY file_path"
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(files)
+    }
+    
+    /// A page of a project's indexed files with their block counts, by path
+    ///
+    /// `path_prefix` limits the listing to paths starting with it, e.g. "src/".
+    pub async fn list_files(
         &self,
         project_id: &str,
-        query: &str,
+        path_prefix: Option<&str>,
         limit: usize,
-    ) -> Result<Vec<(String, String, String, i64, i64)>> {
-        // Simple keyword search for now (will be enhanced with semantic search)
-        let results = sqlx::query_as::<_, (String, String, String, i64, i64)>(
+        offset: usize,
+    ) -> Result<Vec<IndexedFile>> {
+        let prefix = path_prefix.unwrap_or("");
+        let rows = sqlx::query_as::<_, (String, Option<String>, i64, String, bool)>(
             r#"
-            SELECT f.file_path, c.block_type, c.name, c.start_line, c.end_line
+            SELECT f.file_path, f.language, COUNT(c.id), f.indexed_at, f.summary IS NOT NULL
+            FROM indexed_files f
+            LEFT JOIN code_blocks c ON c.file_id = f.id
+            WHERE f.project_id = ? AND substr(f.file_path, 1, length(?)) = ?
+            GROUP BY f.id
+            ORDER BY f.file_path
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(project_id)
+        .bind(prefix)
+        .bind(prefix)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows
+            .into_iter()
+            .map(|(file_path, language, block_count, indexed_at, has_summary)| IndexedFile {
+                file_path,
+                language,
+                block_count,
+                indexed_at,
+                has_summary,
+            })
+            .collect())
+    }
+    
+    /// Every stored block of a file, by start line; empty if the file is not indexed
+    pub async fn list_blocks(&self, project_id: &str, file_path: &str) -> Result<Vec<BlockListing>> {
+        let rows = sqlx::query_as::<_, (i64, Option<i64>, String, Option<String>, i64, i64, Option<String>, String)>(
+            r#"
+            SELECT c.id, c.parent_block_id, c.block_type, c.name, c.start_line, c.end_line, c.docstring, c.content
             FROM code_blocks c
             JOIN indexed_files f ON c.file_id = f.id
-            WHERE f.project_id = ?
-            AND (c.content LIKE ? OR c.name LIKE ?)
-            LIMIT ?
+            WHERE f.project_id = ? AND f.file_path = ?
+            ORDER BY c.start_line, c.id
             "#,
         )
         .bind(project_id)
-        .bind(format!("%{}%", query))
-        .bind(format!("%{}%", query))
-        .bind(limit as i64)
+        .bind(file_path)
         .fetch_all(&self.pool)
         .await?;
         
-        Ok(results)
+        Ok(rows
+            .into_iter()
+            .map(|(id, parent_id, block_type, name, start_line, end_line, docstring, content)| BlockListing {
+                id,
+                parent_id,
+                block_type,
+                name,
+                start_line,
+                end_line,
+                docstring,
+                signature: Some(signature(&content)).filter(|s| !s.is_empty()),
+            })
+            .collect())
+    }
+    
+    /// The stored blocks of a file in source order, with parent indexes restored
+    ///
+    /// Returns the file's language with them; None if the file is not indexed.
+    pub async fn get_file_blocks(&self, project_id: &str, file_path: &str) -> Result<Option<(String, Vec<CodeBlock>)>> {
+        let file = sqlx::query_as::<_, (i64, Option<String>)>(
+            "SELECT id, language FROM indexed_files WHERE project_id = ? AND file_path = ?"
+        )
+        .bind(project_id)
+        .bind(file_path)
+        .fetch_optional(&self.pool)
+        .await?;
+        let (file_id, language) = match file {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let language = language.unwrap_or_default();
+        
+        let rows = sqlx::query_as::<_, (i64, Option<i64>, String, Option<String>, Option<String>, String, Option<i64>, Option<i64>, Option<String>, Option<String>)>(
+            r#"
+            SELECT id, parent_block_id, block_type, name, short_name, content, start_line, end_line, docstring, decorators
+            FROM code_blocks
+            WHERE file_id = ?
+            ORDER BY start_line, id
+            "#,
+        )
+        .bind(file_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let positions: HashMap<i64, usize> = rows.iter().enumerate().map(|(i, row)| (row.0, i)).collect();
+        let blocks = rows
+            .into_iter()
+            .map(|(_, parent_id, block_type, name, short_name, content, start_line, end_line, docstring, decorators)| CodeBlock {
+                block_type,
+                name,
+                short_name,
+                content,
+                start_line: start_line.unwrap_or(0) as usize,
+                end_line: end_line.unwrap_or(0) as usize,
+                language: language.clone(),
+                docstring,
+                decorators: decorators
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                parent_index: parent_id.and_then(|id| positions.get(&id).copied()),
+            })
+            .collect();
+        
+        Ok(Some((language, blocks)))
+    }
+    
+    /// Stored path and hash of every indexed file in a project
+    pub async fn list_files_with_hash(&self, project_id: &str) -> Result<Vec<(String, Option<String>)>> {
+        let files = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT file_path, file_hash FROM indexed_files WHERE project_id = ? ORDER BY file_path"
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(files)
+    }
+    
+    /// IDs of blocks whose file row no longer exists
+    ///
+    /// Only possible when rows were deleted with foreign keys disabled, so
+    /// this is not scoped to a project.
+    pub async fn find_orphaned_blocks(&self) -> Result<Vec<i64>> {
+        let rows = sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT c.id
+            FROM code_blocks c
+            LEFT JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.id IS NULL
+            ORDER BY c.id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+    
+    /// Groups of blocks in a project with the same normalized content
+    ///
+    /// Blocks shorter than `min_lines` are ignored, so one-line getters and
+    /// the like don't show up as copies. Groups are ordered by size, largest
+    /// first.
+    pub async fn find_duplicate_blocks(&self, project_id: &str, min_lines: usize) -> Result<Vec<DuplicateGroup>> {
+        let rows = sqlx::query_as::<_, (String, i64, String, Option<String>, i64, i64)>(
+            r#"
+            SELECT c.content_hash, c.id, f.file_path, c.name, c.start_line, c.end_line
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.project_id = ?1
+            AND c.end_line - c.start_line + 1 >= ?2
+            AND c.content_hash IN (
+                SELECT c2.content_hash
+                FROM code_blocks c2
+                JOIN indexed_files f2 ON c2.file_id = f2.id
+                WHERE f2.project_id = ?1
+                AND c2.content_hash IS NOT NULL
+                AND c2.end_line - c2.start_line + 1 >= ?2
+                GROUP BY c2.content_hash
+                HAVING COUNT(*) > 1
+            )
+            ORDER BY c.content_hash, f.file_path, c.start_line, c.id
+            "#,
+        )
+        .bind(project_id)
+        .bind(min_lines as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        for (content_hash, id, file_path, name, start_line, end_line) in rows {
+            let block = DuplicateBlock { id, file_path, name, start_line, end_line };
+            match groups.last_mut() {
+                Some(group) if group.content_hash == content_hash => group.blocks.push(block),
+                _ => groups.push(DuplicateGroup { content_hash, blocks: vec![block] }),
+            }
+        }
+        groups.sort_by(|a, b| b.blocks.len().cmp(&a.blocks.len()).then_with(|| a.content_hash.cmp(&b.content_hash)));
+        
+        Ok(groups)
+    }
+    
+    /// IDs of blocks in a project whose stored embedding is not `dim` floats long
+    pub async fn find_mismatched_embeddings(&self, project_id: &str, dim: usize) -> Result<Vec<i64>> {
+        let rows = sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT c.id
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.project_id = ? AND c.embedding IS NOT NULL AND length(c.embedding) != ?
+            ORDER BY c.id
+            "#,
+        )
+        .bind(project_id)
+        .bind((dim * std::mem::size_of::<f32>()) as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+    
+    /// Stored `content_hash` of each block; blocks without one are left out
+    pub async fn content_hashes(&self, block_ids: &[i64]) -> Result<HashMap<i64, String>> {
+        let mut hashes = HashMap::new();
+        // Kept under SQLite's default limit on bound parameters
+        for chunk in block_ids.chunks(MAX_BOUND_IDS) {
+            let sql = format!(
+                "SELECT id, content_hash FROM code_blocks WHERE id IN ({}) AND content_hash IS NOT NULL",
+                vec!["?"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query_as::<_, (i64, String)>(&sql);
+            for block_id in chunk {
+                query = query.bind(block_id);
+            }
+            hashes.extend(query.fetch_all(&self.pool).await?);
+        }
+        
+        Ok(hashes)
+    }
+    
+    /// Delete blocks by ID
+    pub async fn delete_blocks(&self, block_ids: &[i64]) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut deleted = 0;
+        for block_id in block_ids {
+            let result = sqlx::query("DELETE FROM code_blocks WHERE id = ?")
+                .bind(block_id)
+                .execute(&self.pool)
+                .await?;
+            deleted += result.rows_affected() as usize;
+        }
+        
+        Ok(deleted)
+    }
+    
+    /// Drop stored embeddings so they are regenerated on the next pass
+    pub async fn clear_embeddings(&self, block_ids: &[i64]) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut cleared = 0;
+        for block_id in block_ids {
+            let result = sqlx::query("UPDATE code_blocks SET embedding = NULL WHERE id = ?")
+                .bind(block_id)
+                .execute(&self.pool)
+                .await?;
+            cleared += result.rows_affected() as usize;
+        }
+        
+        Ok(cleared)
+    }
+    
+    /// Replace a project's blocks imported from `source` with `files`
+    ///
+    /// Each entry is (file path, language, content hash if the file could be
+    /// read, blocks). Blocks from other sources are kept; file rows left
+    /// with no blocks and no content hash are removed. Returns the number of
+    /// blocks stored.
+    pub async fn replace_imported_blocks(
+        &self,
+        project_id: &str,
+        source: BlockSource,
+        files: &[(String, String, Option<String>, Vec<CodeBlock>)],
+    ) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut tx = self.pool.begin().await?;
+        
+        sqlx::query(
+            r#"
+            DELETE FROM code_blocks WHERE source = ?
+            AND file_id IN (SELECT id FROM indexed_files WHERE project_id = ?)
+            "#,
+        )
+        .bind(source.as_str())
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+        
+        let mut stored = 0;
+        for (file_path, language, file_hash, blocks) in files {
+            // Files indexed from their AST keep their language and hash
+            sqlx::query(
+                r#"
+                INSERT INTO indexed_files (project_id, file_path, language, file_hash)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT(project_id, file_path) DO UPDATE SET
+                    file_hash = COALESCE(indexed_files.file_hash, excluded.file_hash)
+                "#,
+            )
+            .bind(project_id)
+            .bind(file_path)
+            .bind(language)
+            .bind(file_hash)
+            .execute(&mut *tx)
+            .await?;
+            
+            let file_id: (i64,) = sqlx::query_as(
+                "SELECT id FROM indexed_files WHERE project_id = ? AND file_path = ?"
+            )
+            .bind(project_id)
+            .bind(file_path)
+            .fetch_one(&mut *tx)
+            .await?;
+            
+            let mut block_ids = Vec::with_capacity(blocks.len());
+            for block in blocks {
+                let parent_id = block.parent_index.and_then(|i| block_ids.get(i).copied());
+                block_ids.push(insert_block(&mut *tx, file_id.0, block, parent_id, source).await?);
+            }
+            stored += blocks.len();
+        }
+        
+        sqlx::query(
+            r#"
+            DELETE FROM indexed_files WHERE project_id = ? AND file_hash IS NULL
+            AND id NOT IN (SELECT file_id FROM code_blocks)
+            "#,
+        )
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        Ok(stored)
+    }
+    
+    /// Remove every indexed file (and its blocks) whose path starts with `prefix`
+    pub async fn remove_files_with_prefix(&self, project_id: &str, prefix: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        sqlx::query(
+            r#"
+            DELETE FROM code_blocks WHERE file_id IN (
+                SELECT id FROM indexed_files
+                WHERE project_id = ? AND substr(file_path, 1, length(?)) = ?
+            )
+            "#,
+        )
+        .bind(project_id)
+        .bind(prefix)
+        .bind(prefix)
+        .execute(&self.pool)
+        .await?;
+        
+        let result = sqlx::query(
+            "DELETE FROM indexed_files WHERE project_id = ? AND substr(file_path, 1, length(?)) = ?"
+        )
+        .bind(project_id)
+        .bind(prefix)
+        .bind(prefix)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.rows_affected() as usize)
+    }
+    
+    /// Rewrite stored file paths starting with `from` to start with `to`
+    ///
+    /// Used to convert indexes built with absolute paths into relative ones;
+    /// separators in the rewritten remainder are normalized to '/'.
+    pub async fn rewrite_path_prefix(&self, project_id: &str, from: &str, to: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        let result = sqlx::query(
+            r#"
+            UPDATE indexed_files
+            SET file_path = ? || replace(substr(file_path, length(?) + 1), '\', '/')
+            WHERE project_id = ? AND substr(file_path, 1, length(?)) = ?
+            "#,
+        )
+        .bind(to)
+        .bind(from)
+        .bind(project_id)
+        .bind(from)
+        .bind(from)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.rows_affected() as usize)
+    }
+    
+    /// Keyword search ranked by match quality
+    ///
+    /// Rows are ordered exact name match first, then name prefix, name
+    /// substring and finally content-only matches, so the most relevant
+    /// blocks survive the `LIMIT` even in large projects. Names match on
+    /// either the qualified name (`Storage.save`) or the bare short name.
+    /// `query` may use phrases and exclusions (see `indexer::query`); `%`
+    /// and `_` in it match literally.
+    pub async fn search_blocks(
+        &self,
+        project_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, String, Option<String>, i64, i64, String, MatchKind, Option<String>, Option<String>)>> {
+        let query = parse_query(query)?;
+        self.search_parsed_blocks(project_id, &query, limit).await
+    }
+    
+    /// `search_blocks` for an already parsed query
+    ///
+    /// Every term and phrase must occur in the content or name, and no
+    /// excluded one may. Names are ranked against the positive terms joined
+    /// by spaces. With a `code_blocks_fts` table, whose rowids are block
+    /// ids, the terms are matched through FTS instead of LIKE.
+    pub async fn search_parsed_blocks(
+        &self,
+        project_id: &str,
+        query: &ParsedQuery,
+        limit: usize,
+    ) -> Result<Vec<(String, String, Option<String>, i64, i64, String, MatchKind, Option<String>, Option<String>)>> {
+        let text = query.positive_text();
+        let pattern = like_escape(&text)?;
+        
+        // ?1 to ?5 are bound below; condition parameters follow from ?6
+        let mut conditions = String::new();
+        let mut params = Vec::new();
+        let fts_match = match query.to_fts_match() {
+            Some(expression) if self.fts_enabled().await? => Some(expression),
+            _ => None,
+        };
+        match fts_match {
+            Some(expression) => {
+                params.push(expression);
+                conditions.push_str(&format!(
+                    " AND c.id IN (SELECT rowid FROM code_blocks_fts WHERE code_blocks_fts MATCH ?{})",
+                    5 + params.len()
+                ));
+            }
+            None => {
+                for positive in query.positive() {
+                    params.push(format!("%{}%", like_escape(positive)?));
+                    let n = 5 + params.len();
+                    conditions.push_str(&format!(
+                        r" AND (c.content LIKE ?{} ESCAPE '\' OR c.name LIKE ?{} ESCAPE '\')",
+                        n, n
+                    ));
+                }
+                for excluded in &query.excluded {
+                    params.push(format!("%{}%", like_escape(excluded)?));
+                    let n = 5 + params.len();
+                    conditions.push_str(&format!(
+                        r" AND NOT (c.content LIKE ?{} ESCAPE '\' OR COALESCE(c.name, '') LIKE ?{} ESCAPE '\')",
+                        n, n
+                    ));
+                }
+            }
+        }
+        
+        let sql = format!(
+            r#"
+            SELECT f.file_path, c.block_type, c.name, c.start_line, c.end_line, c.content,
+                CASE
+                    WHEN LOWER(c.name) = LOWER(?1) OR LOWER(c.short_name) = LOWER(?1) THEN 0
+                    WHEN c.name LIKE ?2 ESCAPE '\' OR c.short_name LIKE ?2 ESCAPE '\' THEN 1
+                    WHEN c.name LIKE ?3 ESCAPE '\' THEN 2
+                    ELSE 3
+                END AS match_rank,
+                p.name, p.block_type
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            LEFT JOIN code_blocks p ON c.parent_block_id = p.id
+            WHERE f.project_id = ?4{}
+            ORDER BY match_rank, c.id
+            LIMIT ?5
+            "#,
+            conditions
+        );
+        let mut statement = sqlx::query_as::<_, (String, String, Option<String>, i64, i64, String, i64, Option<String>, Option<String>)>(&sql)
+            .bind(&text)
+            .bind(format!("{}%", pattern))
+            .bind(format!("%{}%", pattern))
+            .bind(project_id)
+            .bind(limit as i64);
+        for param in &params {
+            statement = statement.bind(param);
+        }
+        let results = statement.fetch_all(&self.pool).await?;
+        
+        Ok(results
+            .into_iter()
+            .map(|(file_path, block_type, name, start_line, end_line, content, rank, parent_name, parent_type)| {
+                (file_path, block_type, name, start_line, end_line, content, MatchKind::from_rank(rank), parent_name, parent_type)
+            })
+            .collect())
     }
     
     /// Store embedding for a code block
@@ -144,6 +989,42 @@ impl IndexStorage {
         block_id: i64,
         embedding: &[f32],
     ) -> Result<()> {
+        self.store_embedding_with_model(block_id, embedding, None).await
+    }
+    
+    /// Store embedding for a code block, recording `model` with the project's first
+    ///
+    /// Fails with `InvalidInput` if the project's recorded embedding size
+    /// differs; use `CodebaseIndexer::reembed_all` to change models.
+    pub async fn store_embedding_with_model(
+        &self,
+        block_id: i64,
+        embedding: &[f32],
+        model: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_writable()?;
+        let project_id: Option<String> = sqlx::query_scalar(
+            "SELECT f.project_id FROM code_blocks c JOIN indexed_files f ON c.file_id = f.id WHERE c.id = ?"
+        )
+        .bind(block_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        if let Some(project_id) = &project_id {
+            match self.embedding_meta(project_id).await? {
+                Some(meta) if meta.dim != embedding.len() => {
+                    return Err(OrchestratorError::InvalidInput(format!(
+                        "Embedding has {} dimensions but project {} stores {}-dimensional embeddings; re-embed the project to change models",
+                        embedding.len(),
+                        project_id,
+                        meta.dim
+                    )));
+                }
+                Some(_) => {}
+                None => self.set_embedding_meta(project_id, model, embedding.len()).await?,
+            }
+        }
+        
         // Serialize embedding as BLOB (using simple binary format)
         let embedding_bytes: Vec<u8> = embedding.iter()
             .flat_map(|f| f.to_le_bytes().to_vec())
@@ -160,6 +1041,90 @@ impl IndexStorage {
         Ok(())
     }
     
+    /// Recorded model and size of a project's embeddings; None before the first is stored
+    pub async fn embedding_meta(&self, project_id: &str) -> Result<Option<EmbeddingMeta>> {
+        let row = sqlx::query_as::<_, (Option<String>, i64)>(
+            "SELECT embedding_model, embedding_dim FROM index_meta WHERE project_id = ?"
+        )
+        .bind(project_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(row.map(|(model, dim)| EmbeddingMeta { model, dim: dim as usize }))
+    }
+    
+    /// Record the model and size of a project's embeddings, replacing any recorded
+    pub async fn set_embedding_meta(&self, project_id: &str, model: Option<&str>, dim: usize) -> Result<()> {
+        self.ensure_writable()?;
+        set_embedding_meta(&self.pool, project_id, model, dim).await?;
+        Ok(())
+    }
+    
+    /// Clear a project's embeddings and record a new model and size, atomically
+    ///
+    /// Returns the number of embeddings cleared.
+    pub async fn reset_embeddings(&self, project_id: &str, model: Option<&str>, dim: usize) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut tx = self.pool.begin().await?;
+        
+        let result = sqlx::query(
+            r#"
+            UPDATE code_blocks SET embedding = NULL
+            WHERE embedding IS NOT NULL
+              AND file_id IN (SELECT id FROM indexed_files WHERE project_id = ?)
+            "#,
+        )
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+        set_embedding_meta(&mut *tx, project_id, model, dim).await?;
+        
+        tx.commit().await?;
+        Ok(result.rows_affected() as usize)
+    }
+    
+    /// Up to `limit` of a project's blocks with IDs above `after_id`, in ID order
+    pub async fn blocks_after(&self, project_id: &str, after_id: i64, limit: usize) -> Result<Vec<(i64, CodeBlock)>> {
+        let rows = sqlx::query_as::<_, BlockRow>(
+            r#"
+            SELECT c.id, c.block_type, c.name, c.short_name, c.content, c.start_line, c.end_line,
+                   f.language, c.docstring, c.decorators
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.project_id = ? AND c.id > ?
+            ORDER BY c.id
+            LIMIT ?
+            "#,
+        )
+        .bind(project_id)
+        .bind(after_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().map(block_from_row).collect())
+    }
+    
+    /// Blocks of one file that have no embedding yet, by ID
+    pub async fn blocks_without_embedding(&self, project_id: &str, file_path: &str) -> Result<Vec<(i64, CodeBlock)>> {
+        let rows = sqlx::query_as::<_, BlockRow>(
+            r#"
+            SELECT c.id, c.block_type, c.name, c.short_name, c.content, c.start_line, c.end_line,
+                   f.language, c.docstring, c.decorators
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.project_id = ? AND f.file_path = ? AND c.embedding IS NULL
+            ORDER BY c.id
+            "#,
+        )
+        .bind(project_id)
+        .bind(file_path)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().map(block_from_row).collect())
+    }
+    
     /// Retrieve embeddings for semantic search
     pub async fn get_block_embeddings(
         &self,
@@ -199,6 +1164,59 @@ impl IndexStorage {
         Ok(embeddings)
     }
     
+    /// Cheap probe of a project's embeddings, for detecting stale caches
+    ///
+    /// Changes whenever a file is indexed, re-indexed or removed, or a block
+    /// gains an embedding. Overwriting an existing embedding in place is not
+    /// detected.
+    pub async fn embedding_fingerprint(&self, project_id: &str) -> Result<EmbeddingFingerprint> {
+        let (embedded_blocks, max_block_id, last_indexed_at) = sqlx::query_as::<_, (i64, Option<i64>, Option<String>)>(
+            r#"
+            SELECT COUNT(c.embedding), MAX(c.id), CAST(MAX(f.indexed_at) AS TEXT)
+            FROM indexed_files f
+            LEFT JOIN code_blocks c ON c.file_id = f.id
+            WHERE f.project_id = ?
+            "#,
+        )
+        .bind(project_id)
+        .fetch_one(&self.pool)
+        .await?;
+        
+        Ok(EmbeddingFingerprint {
+            embedded_blocks,
+            max_block_id,
+            last_indexed_at,
+        })
+    }
+    
+    /// Count (blocks with an embedding, all blocks) in a project
+    pub async fn embedding_coverage(&self, project_id: &str) -> Result<(i64, i64)> {
+        let counts = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT COUNT(c.embedding), COUNT(*)
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.project_id = ?
+            "#,
+        )
+        .bind(project_id)
+        .fetch_one(&self.pool)
+        .await?;
+        
+        Ok(counts)
+    }
+    
+    /// Whether a full-text index over code blocks exists
+    pub async fn fts_enabled(&self) -> Result<bool> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'code_blocks_fts'",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        
+        Ok(count > 0)
+    }
+    
     /// Get block ID for a file path and block name
     pub async fn get_block_id(
         &self,
@@ -213,6 +1231,7 @@ impl IndexStorage {
                 FROM code_blocks c
                 JOIN indexed_files f ON c.file_id = f.id
                 WHERE f.project_id = ? AND f.file_path = ? AND c.name = ?
+                ORDER BY c.id
                 LIMIT 1
                 "#,
             )
@@ -229,15 +1248,18 @@ impl IndexStorage {
     }
     
     /// Get block details by block ID
+    ///
+    /// The last two fields are the parent block's name and type, if any.
     pub async fn get_block_by_id(
         &self,
         block_id: i64,
-    ) -> Result<Option<(String, String, Option<String>, i64, i64)>> {
-        let result = sqlx::query_as::<_, (String, String, Option<String>, i64, i64)>(
+    ) -> Result<Option<(String, String, Option<String>, i64, i64, String, Option<String>, Option<String>)>> {
+        let result = sqlx::query_as::<_, (String, String, Option<String>, i64, i64, String, Option<String>, Option<String>)>(
             r#"
-            SELECT f.file_path, c.block_type, c.name, c.start_line, c.end_line
+            SELECT f.file_path, c.block_type, c.name, c.start_line, c.end_line, c.content, p.name, p.block_type
             FROM code_blocks c
             JOIN indexed_files f ON c.file_id = f.id
+            LEFT JOIN code_blocks p ON c.parent_block_id = p.id
             WHERE c.id = ?
             LIMIT 1
             "#,
@@ -248,4 +1270,239 @@ impl IndexStorage {
         
         Ok(result)
     }
+    
+    /// Direct children of a block: (id, block_type, name, start_line, end_line)
+    pub async fn get_children(
+        &self,
+        block_id: i64,
+    ) -> Result<Vec<(i64, String, Option<String>, i64, i64)>> {
+        let results = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64)>(
+            r#"
+            SELECT id, block_type, name, start_line, end_line
+            FROM code_blocks
+            WHERE parent_block_id = ?
+            ORDER BY start_line, id
+            "#,
+        )
+        .bind(block_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(results)
+    }
+    
+    /// All blocks of a file nested under their parents, top-level blocks first
+    pub async fn get_block_tree(
+        &self,
+        project_id: &str,
+        file_path: &str,
+    ) -> Result<Vec<BlockNode>> {
+        let rows = sqlx::query_as::<_, (i64, Option<i64>, String, Option<String>, i64, i64)>(
+            r#"
+            SELECT c.id, c.parent_block_id, c.block_type, c.name, c.start_line, c.end_line
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            WHERE f.project_id = ? AND f.file_path = ?
+            ORDER BY c.start_line, c.id
+            "#,
+        )
+        .bind(project_id)
+        .bind(file_path)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let ids: HashSet<i64> = rows.iter().map(|row| row.0).collect();
+        let mut children: HashMap<Option<i64>, Vec<BlockNode>> = HashMap::new();
+        for (id, parent_id, block_type, name, start_line, end_line) in rows {
+            // Dangling parents are treated as top-level
+            let parent_id = parent_id.filter(|p| ids.contains(p));
+            children.entry(parent_id).or_default().push(BlockNode {
+                id,
+                block_type,
+                name,
+                start_line,
+                end_line,
+                children: Vec::new(),
+            });
+        }
+        
+        Ok(build_block_tree(None, &mut children))
+    }
+    
+    pub async fn record_index_event(&self, project_id: &str, event: &IndexEvent) -> Result<()> {
+        self.ensure_writable()?;
+        sqlx::query(
+            r#"
+            INSERT INTO index_events (project_id, path, action, outcome, error, recorded_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(project_id)
+        .bind(&event.path)
+        .bind(event.action.as_str())
+        .bind(event.outcome.as_str())
+        .bind(&event.error)
+        .bind(event.recorded_at)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// The project's latest index events, newest first
+    pub async fn recent_index_events(&self, project_id: &str, limit: usize) -> Result<Vec<IndexEvent>> {
+        let rows = sqlx::query_as::<_, (String, String, String, Option<String>, i64)>(
+            r#"
+            SELECT path, action, outcome, error, recorded_at FROM index_events
+            WHERE project_id = ?
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(project_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.into_iter()
+            .map(|(path, action, outcome, error, recorded_at)| {
+                Ok(IndexEvent {
+                    path,
+                    action: ChangeKind::parse(&action)?,
+                    outcome: IndexEventOutcome::parse(&outcome)?,
+                    error,
+                    recorded_at,
+                })
+            })
+            .collect()
+    }
+    
+    /// Delete events recorded before `cutoff` (Unix seconds), then all but the newest `keep`
+    ///
+    /// Returns how many were deleted.
+    pub async fn prune_index_events(&self, project_id: &str, cutoff: i64, keep: usize) -> Result<usize> {
+        self.ensure_writable()?;
+        let expired = sqlx::query("DELETE FROM index_events WHERE project_id = ? AND recorded_at < ?")
+            .bind(project_id)
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        
+        let excess = sqlx::query(
+            r#"
+            DELETE FROM index_events WHERE project_id = ? AND id NOT IN (
+                SELECT id FROM index_events WHERE project_id = ? ORDER BY id DESC LIMIT ?
+            )
+            "#,
+        )
+        .bind(project_id)
+        .bind(project_id)
+        .bind(keep as i64)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok((expired.rows_affected() + excess.rows_affected()) as usize)
+    }
+}
+
+/// Attach children to their parents recursively, preserving line order
+fn build_block_tree(parent_id: Option<i64>, children: &mut HashMap<Option<i64>, Vec<BlockNode>>) -> Vec<BlockNode> {
+    let mut nodes = children.remove(&parent_id).unwrap_or_default();
+    for node in &mut nodes {
+        node.children = build_block_tree(Some(node.id), children);
+    }
+    nodes
+}
+
+/// (id, block_type, name, short_name, content, start_line, end_line, language, docstring, decorators)
+type BlockRow = (i64, String, Option<String>, Option<String>, String, Option<i64>, Option<i64>, Option<String>, Option<String>, Option<String>);
+
+fn block_from_row(row: BlockRow) -> (i64, CodeBlock) {
+    let (id, block_type, name, short_name, content, start_line, end_line, language, docstring, decorators) = row;
+    (id, CodeBlock {
+        block_type,
+        name,
+        short_name,
+        content,
+        start_line: start_line.unwrap_or(0) as usize,
+        end_line: end_line.unwrap_or(0) as usize,
+        language: language.unwrap_or_default(),
+        docstring,
+        decorators: decorators
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        parent_index: None,
+    })
+}
+
+/// Insert a single parsed block for a file, returning its row id
+async fn insert_block<'e, E>(
+    executor: E,
+    file_id: i64,
+    block: &CodeBlock,
+    parent_id: Option<i64>,
+    source: BlockSource,
+) -> Result<i64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    // Serialize decorators as JSON
+    let decorators_json = serde_json::to_string(&block.decorators).unwrap_or_else(|_| "[]".to_string());
+    
+    let result = sqlx::query(
+        r#"
+        INSERT INTO code_blocks (file_id, parent_block_id, block_type, name, short_name, content, start_line, end_line, embedding, docstring, decorators, source, content_hash)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, NULL, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(file_id)
+    .bind(parent_id)
+    .bind(&block.block_type)
+    .bind(&block.name)
+    .bind(&block.short_name)
+    .bind(&block.content)
+    .bind(block.start_line as i64)
+    .bind(block.end_line as i64)
+    .bind(&block.docstring)
+    .bind(&decorators_json)
+    .bind(source.as_str())
+    .bind(block_fingerprint(&block.content, &block.language))
+    .execute(executor)
+    .await?;
+    
+    Ok(result.last_insert_rowid())
+}
+
+/// Upsert a project's row in `index_meta`
+async fn set_embedding_meta<'e, E>(executor: E, project_id: &str, model: Option<&str>, dim: usize) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO index_meta (project_id, embedding_model, embedding_dim, updated_at)
+        VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+        ON CONFLICT(project_id) DO UPDATE SET
+            embedding_model = excluded.embedding_model,
+            embedding_dim = excluded.embedding_dim,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(model)
+    .bind(dim as i64)
+    .execute(executor)
+    .await?;
+    
+    Ok(())
+}
+
+/// Hash of file content as stored in `indexed_files.file_hash`
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", md5::compute(content))
+}
+
+/// `text` escaped for use inside a LIKE pattern with `ESCAPE '\'`
+fn like_escape(text: &str) -> Result<String> {
+    validate_like_pattern(text).map_err(|e| OrchestratorError::InvalidInput(e.to_string()))
 }
@@ -0,0 +1,111 @@
+/// Per-file summaries built from indexed blocks
+
+use crate::error::Result;
+use crate::indexer::parser::CodeBlock;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+
+/// Top-level blocks listed in a summary before the rest are counted
+const MAX_LISTED_BLOCKS: usize = 8;
+/// Docstring sentences quoted in a summary
+const MAX_DOC_SENTENCES: usize = 3;
+const MAX_SIGNATURE_LEN: usize = 100;
+
+/// Writes a file summary, e.g. by prompting an LLM
+///
+/// `extractive` is the summary `extractive_summary` produced for the file,
+/// useful as a prompt or as a fallback.
+#[async_trait]
+pub trait FileSummarizer: Send + Sync {
+    async fn summarize(&self, file_path: &str, blocks: &[CodeBlock], extractive: &str) -> Result<String>;
+}
+
+/// One-paragraph summary of a file from its blocks, without a model
+///
+/// Lists block counts by kind, the signatures of top-level blocks and the
+/// first sentence of their docstrings. The same blocks always give the same
+/// summary.
+pub fn extractive_summary(file_path: &str, language: &str, blocks: &[CodeBlock]) -> String {
+    if blocks.is_empty() {
+        return format!("{} ({}): no indexed blocks.", file_path, language);
+    }
+    
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for block in blocks {
+        *counts.entry(kind_label(&block.block_type)).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let counts: Vec<String> = counts
+        .into_iter()
+        .map(|(kind, count)| format!("{} {}", count, if count == 1 { kind } else { plural(&kind) }))
+        .collect();
+    
+    let top_level: Vec<&CodeBlock> = blocks.iter().filter(|b| b.parent_index.is_none()).collect();
+    let mut listed: Vec<String> = top_level.iter().take(MAX_LISTED_BLOCKS).map(|b| signature(&b.content)).collect();
+    if top_level.len() > MAX_LISTED_BLOCKS {
+        listed.push(format!("and {} more", top_level.len() - MAX_LISTED_BLOCKS));
+    }
+    
+    let docs: Vec<String> = top_level
+        .iter()
+        .filter_map(|b| b.docstring.as_deref().and_then(first_sentence))
+        .take(MAX_DOC_SENTENCES)
+        .collect();
+    
+    let mut summary = format!("{} ({}): {}.", file_path, language, counts.join(", "));
+    if !listed.is_empty() {
+        summary.push_str(&format!(" Top-level: {}.", listed.join("; ")));
+    }
+    if !docs.is_empty() {
+        summary.push_str(&format!(" Notes: {}", docs.join(" ")));
+    }
+    summary
+}
+
+/// "function_item" -> "function", "class_definition" -> "class"
+fn kind_label(block_type: &str) -> String {
+    let kind = ["_item", "_definition", "_declaration", "_statement"]
+        .iter()
+        .find_map(|suffix| block_type.strip_suffix(suffix))
+        .unwrap_or(block_type);
+    kind.replace('_', " ")
+}
+
+fn plural(kind: &str) -> String {
+    if kind.ends_with('s') {
+        format!("{}es", kind)
+    } else {
+        format!("{}s", kind)
+    }
+}
+
+/// First line of a block's content without its opening brace or colon, whitespace collapsed
+pub(crate) fn signature(content: &str) -> String {
+    let line = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let line = line.trim_end_matches(|c: char| c == '{' || c == ':' || c.is_whitespace());
+    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > MAX_SIGNATURE_LEN {
+        format!("{}...", line.chars().take(MAX_SIGNATURE_LEN).collect::<String>())
+    } else {
+        line
+    }
+}
+
+/// Up to the first period followed by whitespace, or the first line
+fn first_sentence(docstring: &str) -> Option<String> {
+    let text = docstring.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    let end = text
+        .match_indices(". ")
+        .next()
+        .map(|(i, _)| i + 1)
+        .unwrap_or(text.len());
+    let mut sentence = text[..end].to_string();
+    if !sentence.ends_with('.') {
+        sentence.push('.');
+    }
+    Some(sentence)
+}
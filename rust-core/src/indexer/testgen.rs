@@ -0,0 +1,204 @@
+/// Reproducible synthetic corpora for benchmarks and tests
+
+use crate::indexer::parser::CodeBlock;
+use std::path::Path;
+
+/// Languages `generate_corpus` can write, by extension
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("rust", "rs"),
+    ("python", "py"),
+    ("javascript", "js"),
+    ("typescript", "ts"),
+];
+
+const VERBS: &[&str] = &[
+    "load", "save", "parse", "render", "validate", "merge", "index", "search",
+    "compress", "retry", "route", "cache", "encode", "decode", "flush", "scan",
+];
+
+const NOUNS: &[&str] = &[
+    "config", "session", "token", "context", "block", "embedding", "request", "response",
+    "budget", "journal", "summary", "cursor", "window", "schema", "project", "message",
+];
+
+/// Small deterministic generator (SplitMix64); the same seed always yields the same sequence
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    /// Uniform in `0..n`; `n` must be non-zero
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+    
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// Shape of a generated corpus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusSpec {
+    pub files: usize,
+    pub functions_per_file: usize,
+    pub languages: Vec<String>, // Names from `LANGUAGES`; files cycle through them
+    pub seed: u64,
+}
+
+impl CorpusSpec {
+    /// `files` files of `functions_per_file` functions each, in every supported language
+    pub fn new(files: usize, functions_per_file: usize) -> Self {
+        Self {
+            files,
+            functions_per_file,
+            languages: LANGUAGES.iter().map(|(language, _)| language.to_string()).collect(),
+            seed: 0,
+        }
+    }
+    
+    pub fn with_languages(mut self, languages: &[&str]) -> Self {
+        self.languages = languages.iter().map(|l| l.to_string()).collect();
+        self
+    }
+    
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    pub relative_path: String, // '/' separated
+    pub language: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corpus {
+    pub files: Vec<GeneratedFile>,
+}
+
+impl Corpus {
+    /// Write every file under `root`, creating directories as needed
+    pub fn write_to(&self, root: &Path) -> std::io::Result<()> {
+        for file in &self.files {
+            let path = root.join(&file.relative_path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, &file.content)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generate source files for `spec`
+///
+/// Files are spread over `pkgN/` directories of 50 files each. Function
+/// names combine a verb and a noun from a fixed vocabulary, so searches for
+/// words like "config" or "retry" have hits at any corpus size.
+pub fn generate_corpus(spec: &CorpusSpec) -> Corpus {
+    let mut rng = SeededRng::new(spec.seed);
+    let mut files = Vec::with_capacity(spec.files);
+    for i in 0..spec.files {
+        if spec.languages.is_empty() {
+            break;
+        }
+        let language = &spec.languages[i % spec.languages.len()];
+        let extension = LANGUAGES
+            .iter()
+            .find(|(name, _)| *name == language.as_str())
+            .map(|(_, ext)| *ext)
+            .unwrap_or("txt");
+        
+        let content: String = (0..spec.functions_per_file)
+            .map(|f| function_source(&mut rng, language, i * spec.functions_per_file + f))
+            .collect::<Vec<_>>()
+            .join("\n");
+        files.push(GeneratedFile {
+            relative_path: format!("pkg{}/module_{}.{}", i / 50, i, extension),
+            language: language.clone(),
+            content,
+        });
+    }
+    Corpus { files }
+}
+
+/// `count` function blocks as the parser would produce them, for storing without parsing
+///
+/// Blocks cycle through `LANGUAGES`; useful for search benchmarks over
+/// corpora too large to write and parse.
+pub fn generate_blocks(count: usize, seed: u64) -> Vec<CodeBlock> {
+    let mut rng = SeededRng::new(seed);
+    (0..count)
+        .map(|i| {
+            let (language, _) = LANGUAGES[i % LANGUAGES.len()];
+            let verb = rng.pick(VERBS);
+            let noun = rng.pick(NOUNS);
+            let name = format!("{}_{}_{}", verb, noun, i);
+            let content = function_body(&mut rng, language, &name, verb, noun);
+            CodeBlock {
+                block_type: "function".to_string(),
+                name: Some(name.clone()),
+                short_name: Some(name),
+                start_line: 0,
+                end_line: content.lines().count(),
+                content,
+                language: language.to_string(),
+                docstring: Some(format!("Handles {} {}.", noun, verb)),
+                decorators: Vec::new(),
+                parent_index: None,
+            }
+        })
+        .collect()
+}
+
+fn function_source(rng: &mut SeededRng, language: &str, index: usize) -> String {
+    let verb = rng.pick(VERBS);
+    let noun = rng.pick(NOUNS);
+    let name = format!("{}_{}_{}", verb, noun, index);
+    let body = function_body(rng, language, &name, verb, noun);
+    match language {
+        "python" => body,
+        "rust" => format!("/// Handles {} {}.\n{}", noun, verb, body),
+        _ => format!("// Handles {} {}.\n{}", noun, verb, body),
+    }
+}
+
+fn function_body(rng: &mut SeededRng, language: &str, name: &str, verb: &str, noun: &str) -> String {
+    let factor = rng.below(97) + 2;
+    let offset = rng.below(1000);
+    match language {
+        "rust" => format!(
+            "fn {}(input: usize) -> usize {{\n    let {} = input * {};\n    {} + {}\n}}\n",
+            name, noun, factor, noun, offset
+        ),
+        "python" => format!(
+            "def {}(value):\n    \"\"\"Handles {} {}.\"\"\"\n    {} = value * {}\n    return {} + {}\n",
+            name, noun, verb, noun, factor, noun, offset
+        ),
+        "typescript" => format!(
+            "function {}(value: number): number {{\n  const {} = value * {};\n  return {} + {};\n}}\n",
+            name, noun, factor, noun, offset
+        ),
+        _ => format!(
+            "function {}(value) {{\n  const {} = value * {};\n  return {} + {};\n}}\n",
+            name, noun, factor, noun, offset
+        ),
+    }
+}
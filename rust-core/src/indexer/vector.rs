@@ -0,0 +1,97 @@
+/// Vector math for embeddings
+
+use crate::error::{OrchestratorError, Result};
+
+/// Dot product; fails with `InvalidInput` if the lengths differ
+pub fn dot(a: &[f32], b: &[f32]) -> Result<f32> {
+    check_dimensions(a.len(), b.len())?;
+    Ok(a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+/// Euclidean length
+pub fn l2_norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Cosine of the angle between two vectors, from -1.0 to 1.0
+///
+/// Fails with `InvalidInput` if the lengths differ or either vector is
+/// zero, since a zero vector has no direction to compare.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Result<f32> {
+    let dot_product = dot(a, b)?;
+    let (norm_a, norm_b) = (l2_norm(a), l2_norm(b));
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return Err(OrchestratorError::InvalidInput(
+            "Cosine similarity is undefined for a zero vector".to_string(),
+        ));
+    }
+    Ok((dot_product / (norm_a * norm_b)).clamp(-1.0, 1.0))
+}
+
+/// Scale `v` to unit length in place; a zero vector is left as is
+pub fn l2_normalize(v: &mut [f32]) {
+    let norm = l2_norm(v);
+    if norm > 0.0 {
+        for x in v.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
+
+/// `v` scaled to unit length; a zero vector is returned as is
+pub fn l2_normalized(v: &[f32]) -> Vec<f32> {
+    let mut normalized = v.to_vec();
+    l2_normalize(&mut normalized);
+    normalized
+}
+
+/// Element-wise mean of equally sized vectors
+///
+/// Fails with `InvalidInput` if there are none or their lengths differ.
+pub fn mean_pool(vectors: &[Vec<f32>]) -> Result<Vec<f32>> {
+    let first = vectors.first().ok_or_else(|| {
+        OrchestratorError::InvalidInput("Cannot mean-pool zero vectors".to_string())
+    })?;
+    let mut sum = vec![0.0; first.len()];
+    for v in vectors {
+        check_dimensions(first.len(), v.len())?;
+        for (total, x) in sum.iter_mut().zip(v) {
+            *total += x;
+        }
+    }
+    let count = vectors.len() as f32;
+    Ok(sum.into_iter().map(|total| total / count).collect())
+}
+
+/// The `k` corpus vectors most similar to `query`, as (index, similarity), best first
+///
+/// Ties go to the lower index. Zero vectors in the corpus have no
+/// similarity and are skipped. Fails with `InvalidInput` if `query` is zero
+/// or any corpus vector differs in length from it.
+pub fn top_k_similar(query: &[f32], corpus: &[Vec<f32>], k: usize) -> Result<Vec<(usize, f32)>> {
+    if l2_norm(query) == 0.0 {
+        return Err(OrchestratorError::InvalidInput(
+            "Cannot rank by similarity to a zero vector".to_string(),
+        ));
+    }
+    let mut scored = Vec::with_capacity(corpus.len());
+    for (index, v) in corpus.iter().enumerate() {
+        check_dimensions(query.len(), v.len())?;
+        if l2_norm(v) > 0.0 {
+            scored.push((index, cosine_similarity(query, v)?));
+        }
+    }
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scored.truncate(k);
+    Ok(scored)
+}
+
+fn check_dimensions(expected: usize, actual: usize) -> Result<()> {
+    if expected == actual {
+        return Ok(());
+    }
+    Err(OrchestratorError::InvalidInput(format!(
+        "Vector dimensions differ: {} and {}",
+        expected, actual
+    )))
+}
@@ -1,18 +1,50 @@
 /// File system watcher for incremental indexing
 
 use notify::{Watcher, RecursiveMode, Event, EventKind};
-use std::path::PathBuf;
-use std::sync::{mpsc, Arc};
+use notify::event::RemoveKind;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
-use crate::indexer::codebase::CodebaseIndexer;
+use std::time::{Duration, Instant};
+use crate::indexer::codebase::{CodebaseIndexer, IndexOutcome};
+use crate::indexer::diagnostics::{DiagnosticSource, Diagnostics};
+use crate::indexer::journal::{ChangeKind, WatcherJournal};
+use crate::indexer::storage::{IndexEvent, IndexEventOutcome};
+
+/// Which outcomes of watcher-driven indexing are kept in `index_events`, and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEventPolicy {
+    pub success_sample_rate: usize, // Record 1 in N successful changes; failures are always recorded
+    pub max_age: Duration,
+    pub max_events: usize, // Per project, newest kept
+}
+
+impl Default for IndexEventPolicy {
+    fn default() -> Self {
+        Self {
+            success_sample_rate: 10,
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+            max_events: 1000,
+        }
+    }
+}
 
 pub struct FileWatcher {
     watcher: notify::RecommendedWatcher,
     receiver: mpsc::Receiver<Result<Event, notify::Error>>,
-    indexer: CodebaseIndexer,
+    indexer: CodebaseIndexer, // Indexes changes outside every project root
+    projects: Vec<(PathBuf, CodebaseIndexer)>, // Project roots registered with `watch_project`
     debounce_duration: Duration,
     shutdown: Arc<AtomicBool>,
+    journal: Option<WatcherJournal>,
+    diagnostics: Diagnostics, // The indexer's sink
+    control: WatcherControl,
+    auto_pause_threshold: Option<usize>, // Events per second that pause the watcher
+    event_window: (Instant, usize), // Start of the current one-second window and its event count
+    event_policy: Option<IndexEventPolicy>, // None records no index events
+    successes_seen: usize, // For sampling successful changes
 }
 
 impl FileWatcher {
@@ -23,53 +55,187 @@ impl FileWatcher {
             tx.send(res).unwrap();
         })?;
         
+        let diagnostics = indexer.diagnostics().clone();
         Ok(Self {
             watcher,
             receiver: rx,
             indexer,
+            projects: Vec::new(),
             debounce_duration: Duration::from_millis(500),
             shutdown: Arc::new(AtomicBool::new(false)),
+            journal: None,
+            diagnostics,
+            control: WatcherControl::default(),
+            auto_pause_threshold: None,
+            event_window: (Instant::now(), 0),
+            event_policy: Some(IndexEventPolicy::default()),
+            successes_seen: 0,
         })
     }
     
+    /// Pause when more than `events_per_sec` events arrive within a second
+    ///
+    /// Meant for checkouts and mass reformatting, where a bulk re-index is
+    /// cheaper than applying every change. A warning diagnostic suggests
+    /// re-indexing and then resuming with `drop_backlog`.
+    pub fn with_auto_pause(mut self, events_per_sec: usize) -> Self {
+        self.auto_pause_threshold = Some(events_per_sec.max(1));
+        self
+    }
+    
+    /// Record indexing outcomes in `index_events` by `policy` instead of the default one
+    ///
+    /// With None, nothing is recorded; failures still reach the diagnostics.
+    pub fn with_index_events(mut self, policy: Option<IndexEventPolicy>) -> Self {
+        self.event_policy = policy.map(|policy| IndexEventPolicy {
+            success_sample_rate: policy.success_sample_rate.max(1),
+            ..policy
+        });
+        self
+    }
+    
+    /// Journal events so changes pending at a crash can be replayed by `recover_pending`
+    pub fn with_journal(mut self, journal: WatcherJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+    
+    /// Apply changes journaled but never indexed, e.g. before a crash
+    ///
+    /// Call at startup before `process_events`. Entries older than the
+    /// journal's max age are purged instead. Returns the number of paths
+    /// replayed; 0 without a journal.
+    pub async fn recover_pending(&mut self) -> Result<usize, String> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return Ok(0),
+        };
+        journal.purge_expired().await
+            .map_err(|e| format!("Failed to purge watcher journal: {}", e))?;
+        let entries = journal.pending().await
+            .map_err(|e| format!("Failed to read watcher journal: {}", e))?;
+        
+        let replayed = entries.len();
+        self.apply_changes(entries.into_iter().map(|entry| (entry.path, entry.kind)).collect()).await;
+        Ok(replayed)
+    }
+    
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+    
     pub fn shutdown_signal(&self) -> Arc<AtomicBool> {
         self.shutdown.clone()
     }
     
+    /// Handle for pausing and resuming while `process_events` runs
+    pub fn control(&self) -> WatcherControl {
+        self.control.clone()
+    }
+    
+    /// Record changes without applying them until `resume`
+    pub fn pause(&self) {
+        self.control.pause();
+    }
+    
+    /// Apply the changes recorded while paused, or discard them with `drop_backlog`
+    ///
+    /// Discarded changes are also cleared from the journal, since the bulk
+    /// operation that caused them is expected to re-index instead.
+    pub async fn resume(&mut self, drop_backlog: bool) {
+        self.control.resume(drop_backlog);
+        self.apply_resume().await;
+    }
+    
     pub fn watch(&mut self, path: PathBuf) -> Result<(), notify::Error> {
         self.watcher.watch(&path, RecursiveMode::Recursive)?;
         Ok(())
     }
     
+    /// An indexer for `project_id` configured like the watcher's own, for `watch_project`
+    pub fn project_indexer(&self, project_id: String) -> CodebaseIndexer {
+        self.indexer.for_project(project_id)
+    }
+    
+    /// Watch `path`, indexing changes under it with `indexer`
+    ///
+    /// For several projects in one workspace: each change goes to the
+    /// indexer of the most specific registered root containing it, and
+    /// changes outside every root to the watcher's own indexer. `indexer`
+    /// should share the watcher indexer's storage; it reports to the
+    /// watcher's diagnostics. The root is canonicalized and watched as such,
+    /// so event paths can be matched against it.
+    pub fn watch_project(&mut self, path: PathBuf, indexer: CodebaseIndexer) -> Result<(), notify::Error> {
+        let path = path.canonicalize().unwrap_or(path);
+        self.watcher.watch(&path, RecursiveMode::Recursive)?;
+        let indexer = indexer.with_diagnostics(self.diagnostics.clone());
+        self.projects.retain(|(root, _)| *root != path);
+        self.projects.push((path, indexer));
+        Ok(())
+    }
+    
+    /// Apply file changes until shut down
+    ///
+    /// While paused (see `control`), changes are journaled and recorded but
+    /// not applied; a `resume` is picked up on the next poll.
     pub async fn process_events(&mut self) -> Result<(), String> {
-        // Collect events with debouncing
-        let mut pending_events = Vec::new();
-        let mut last_event_time = std::time::Instant::now();
+        // Collect changes with debouncing
+        let mut pending_changes = Vec::new();
+        let mut last_event_time = Instant::now();
         
         loop {
             // Check for shutdown signal (no lock needed for atomic read)
             if self.shutdown.load(Ordering::Relaxed) {
                 return Ok(());
             }
+            self.apply_resume().await;
             
             // Check for events with timeout (receiver doesn't need mutex)
             match self.receiver.try_recv() {
                 Ok(Ok(event)) => {
-                    pending_events.push(event);
-                    last_event_time = std::time::Instant::now();
+                    let changes = changes_of(&event);
+                    if let Some(journal) = &self.journal {
+                        for (path, kind) in &changes {
+                            if let Err(e) = journal.append(path, *kind).await {
+                                self.diagnostics.error(
+                                    DiagnosticSource::Watcher,
+                                    Some(&path.to_string_lossy()),
+                                    format!("Failed to journal change: {}", e),
+                                );
+                            }
+                        }
+                    }
+                    if self.exceeds_event_rate() && !self.control.is_paused() {
+                        self.auto_pause(&mut pending_changes);
+                    }
+                    if self.control.is_paused() {
+                        self.control.record(changes);
+                    } else {
+                        pending_changes.extend(changes);
+                        last_event_time = Instant::now();
+                    }
                 }
                 Ok(Err(e)) => {
-                    eprintln!("Watcher error: {}", e);
+                    self.diagnostics.error(DiagnosticSource::Watcher, None, format!("Watcher error: {}", e));
                     // Continue processing despite errors
                 }
                 Err(mpsc::TryRecvError::Empty) => {
+                    // Changes still debouncing when paused wait for the resume too
+                    if self.control.is_paused() && !pending_changes.is_empty() {
+                        self.control.record(pending_changes.drain(..));
+                    }
+                    
                     // If we have pending events and enough time has passed, process them
                     // Only hold the lock during actual processing
-                    if !pending_events.is_empty() 
+                    if !pending_changes.is_empty() 
                         && last_event_time.elapsed() >= self.debounce_duration 
                     {
-                        if let Err(e) = self.process_pending_events(&mut pending_events).await {
-                            eprintln!("Error processing file events: {}", e);
+                        if let Err(e) = self.process_pending_changes(&mut pending_changes).await {
+                            self.diagnostics.error(
+                                DiagnosticSource::Watcher,
+                                None,
+                                format!("Error processing file events: {}", e),
+                            );
                             // Continue watching despite processing errors
                         }
                     }
@@ -92,68 +258,310 @@ impl FileWatcher {
         Ok(())
     }
     
-    async fn process_pending_events(&mut self, events: &mut Vec<Event>) -> Result<(), String> {
-        // Group events by path to avoid duplicate processing
+    async fn process_pending_changes(&mut self, changes: &mut Vec<(PathBuf, ChangeKind)>) -> Result<(), String> {
+        let changes = std::mem::take(changes);
+        self.apply_changes(changes).await;
+        
+        if let Some(journal) = &self.journal {
+            journal.purge_expired().await
+                .map_err(|e| format!("Failed to purge watcher journal: {}", e))?;
+        }
+        Ok(())
+    }
+    
+    /// Count an event; true once more than the auto-pause threshold arrived within a second
+    fn exceeds_event_rate(&mut self) -> bool {
+        let threshold = match self.auto_pause_threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+        let (window_start, count) = &mut self.event_window;
+        if window_start.elapsed() >= Duration::from_secs(1) {
+            *window_start = Instant::now();
+            *count = 0;
+        }
+        *count += 1;
+        *count > threshold
+    }
+    
+    /// Pause, moving changes waiting out their debounce into the backlog
+    fn auto_pause(&mut self, pending: &mut Vec<(PathBuf, ChangeKind)>) {
+        self.control.pause();
+        self.control.record(pending.drain(..));
+        self.diagnostics.warn(
+            DiagnosticSource::Watcher,
+            None,
+            format!(
+                "More than {} file events per second; watcher paused. Run a bulk re-index, then resume with drop_backlog",
+                self.auto_pause_threshold.unwrap_or_default()
+            ),
+        );
+    }
+    
+    /// Apply or discard the backlog of a `resume` not yet handled
+    async fn apply_resume(&mut self) {
+        let (drop_backlog, backlog) = match self.control.take_resumed() {
+            Some(resumed) => resumed,
+            None => return,
+        };
+        // The burst that caused an auto-pause should not count against later events
+        self.event_window = (Instant::now(), 0);
+        if drop_backlog {
+            for (path, _) in &backlog {
+                self.complete(path).await;
+            }
+        } else {
+            self.apply_changes(backlog).await;
+        }
+    }
+    
+    /// Index or remove changed paths, clearing each from the journal once applied
+    ///
+    /// Failures are logged and their journal rows kept for the next recovery.
+    async fn apply_changes(&mut self, changes: Vec<(PathBuf, ChangeKind)>) {
+        // Group changes by path to avoid duplicate processing
         let mut paths_to_update = std::collections::HashSet::new();
         let mut paths_to_remove = std::collections::HashSet::new();
+        let mut dirs_to_remove = std::collections::HashSet::new();
         
-        for event in events.drain(..) {
-            match event.kind {
-                EventKind::Create(_) | EventKind::Modify(_) => {
-                    for path in event.paths {
-                        if path.is_file() {
-                            // Only index supported languages
-                            if crate::indexer::parser::ASTParser::detect_language(&path).is_some() {
-                                paths_to_update.insert(path);
-                            }
-                        } else if path.is_dir() {
-                            // For directories, we might want to index new files
-                            // But for now, we'll skip directory creation events
-                        }
-                    }
+        for (path, kind) in changes {
+            match kind {
+                ChangeKind::Update => {
+                    paths_to_update.insert(path);
                 }
-                EventKind::Remove(_) => {
-                    for path in event.paths {
-                        if path.is_file() {
-                            paths_to_remove.insert(path);
-                        }
-                    }
+                ChangeKind::RemoveFile => {
+                    paths_to_remove.insert(path);
+                }
+                ChangeKind::RemoveDir => {
+                    dirs_to_remove.insert(path);
+                }
+                ChangeKind::Remove => {
+                    paths_to_remove.insert(path.clone());
+                    dirs_to_remove.insert(path);
                 }
-                _ => {}
             }
         }
         
         // Remove files from index first
-        for path in paths_to_remove {
-            if let Err(e) = self.indexer.remove_file(&path).await {
-                eprintln!("Failed to remove {} from index: {}", path.display(), e);
+        for path in &paths_to_remove {
+            match self.indexer_for(path).remove_file(path).await {
+                Ok(()) => {
+                    self.record_event(path, ChangeKind::RemoveFile, IndexEventOutcome::Removed, None).await;
+                    self.complete(path).await;
+                }
                 // Continue processing other files
+                Err(e) => self.fail(path, ChangeKind::RemoveFile, format!("Failed to remove from index: {}", e)).await,
+            }
+        }
+        
+        for path in &dirs_to_remove {
+            match self.indexer_for(path).remove_directory(path).await {
+                Ok(_) => {
+                    self.record_event(path, ChangeKind::RemoveDir, IndexEventOutcome::Removed, None).await;
+                    self.complete(path).await;
+                }
+                Err(e) => self.fail(path, ChangeKind::RemoveDir, format!("Failed to remove directory from index: {}", e)).await,
             }
         }
         
-        // Update indexed files (incremental indexing)
         for path in paths_to_update {
-            // Skip if file doesn't exist (might have been deleted)
-            if !path.exists() {
-                continue;
+            if path.is_dir() {
+                // New directory (e.g. from a checkout): index its contents,
+                // since files created with it may not emit their own events
+                let mut failed = false;
+                for file in self.indexer_for(&path).collect_indexable_files(&path) {
+                    failed |= !self.update_path(&file).await;
+                }
+                if !failed {
+                    self.complete(&path).await;
+                }
+            } else if self.update_path(&path).await {
+                self.complete(&path).await;
             }
-            
-            // Use incremental indexing to check if file needs updating
-            match self.indexer.should_index_file(&path).await {
-                Ok(true) => {
-                    if let Err(e) = self.indexer.update_file(&path).await {
-                        eprintln!("Failed to index {}: {}", path.display(), e);
-                    }
+        }
+        
+        self.prune_events().await;
+    }
+    
+    /// Re-index a file if it changed; false if that failed
+    async fn update_path(&mut self, path: &Path) -> bool {
+        // Skip files that no longer exist or are not in a supported language
+        if !path.is_file() || crate::indexer::parser::ASTParser::detect_language(path).is_none() {
+            return true;
+        }
+        
+        // Use incremental indexing to check if file needs updating
+        match self.indexer_for(path).should_index_file(path).await {
+            Ok(true) => match self.indexer_for(path).update_file(path).await {
+                Ok(IndexOutcome::Indexed) => {
+                    self.record_event(path, ChangeKind::Update, IndexEventOutcome::Indexed, None).await;
+                    true
                 }
-                Ok(false) => {
-                    // File hasn't changed, skip
+                Ok(IndexOutcome::Skipped(reason)) => {
+                    let reason = Some(reason.as_str().to_string());
+                    self.record_event(path, ChangeKind::Update, IndexEventOutcome::Skipped, reason).await;
+                    true
                 }
                 Err(e) => {
-                    eprintln!("Error checking if {} should be indexed: {}", path.display(), e);
+                    self.fail(path, ChangeKind::Update, format!("Failed to index: {}", e)).await;
+                    false
                 }
+            },
+            // File hasn't changed, skip
+            Ok(false) => true,
+            Err(e) => {
+                self.fail(path, ChangeKind::Update, format!("Error checking if the file should be indexed: {}", e)).await;
+                false
+            }
+        }
+    }
+    
+    /// Clear a path from the journal
+    ///
+    /// Takes `&mut self` like every async method here: the receiver is not
+    /// `Sync`, so holding `&FileWatcher` across an await would make
+    /// `process_events` impossible to spawn.
+    async fn complete(&mut self, path: &Path) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.complete(path).await {
+                self.report(path, format!("Failed to clear from watcher journal: {}", e));
+            }
+        }
+    }
+    
+    /// Report a failed change and record it in `index_events`
+    async fn fail(&mut self, path: &Path, action: ChangeKind, message: String) {
+        self.report(path, message.clone());
+        self.record_event(path, action, IndexEventOutcome::Failed, Some(message)).await;
+    }
+    
+    /// Record an outcome in `index_events`, sampling everything but failures
+    async fn record_event(&mut self, path: &Path, action: ChangeKind, outcome: IndexEventOutcome, error: Option<String>) {
+        let policy = match self.event_policy {
+            Some(policy) => policy,
+            None => return,
+        };
+        if outcome != IndexEventOutcome::Failed {
+            self.successes_seen += 1;
+            if !(self.successes_seen - 1).is_multiple_of(policy.success_sample_rate) {
+                return;
             }
         }
         
-        Ok(())
+        let event = IndexEvent {
+            path: path.to_string_lossy().into_owned(),
+            action,
+            outcome,
+            error,
+            recorded_at: Utc::now().timestamp(),
+        };
+        let indexer = self.indexer_for(path);
+        if let Err(e) = indexer.storage().record_index_event(indexer.project_id(), &event).await {
+            self.report(path, format!("Failed to record index event: {}", e));
+        }
     }
+    
+    /// Trim each project's `index_events` to the policy's max age and count
+    async fn prune_events(&mut self) {
+        let policy = match self.event_policy {
+            Some(policy) => policy,
+            None => return,
+        };
+        let cutoff = Utc::now().timestamp() - policy.max_age.as_secs() as i64;
+        let indexers = std::iter::once(&self.indexer).chain(self.projects.iter().map(|(_, indexer)| indexer));
+        for indexer in indexers {
+            let pruned = indexer.storage()
+                .prune_index_events(indexer.project_id(), cutoff, policy.max_events)
+                .await;
+            if let Err(e) = pruned {
+                self.diagnostics.error(DiagnosticSource::Watcher, None, format!("Failed to prune index events: {}", e));
+            }
+        }
+    }
+    
+    /// The indexer of the most specific project root containing `path`, or the watcher's own
+    fn indexer_for(&mut self, path: &Path) -> &mut CodebaseIndexer {
+        let project = self.projects
+            .iter()
+            .enumerate()
+            .filter(|(_, (root, _))| path.starts_with(root))
+            .max_by_key(|(_, (root, _))| root.components().count())
+            .map(|(i, _)| i);
+        match project {
+            Some(i) => &mut self.projects[i].1,
+            None => &mut self.indexer,
+        }
+    }
+    
+    fn report(&self, path: &Path, message: String) {
+        self.diagnostics.error(DiagnosticSource::Watcher, Some(&path.to_string_lossy()), message);
+    }
+}
+
+/// Pauses and resumes a `FileWatcher`, including while `process_events` runs
+///
+/// Clones share state, so a handle taken with `FileWatcher::control` before
+/// moving the watcher into its event loop keeps working.
+#[derive(Clone, Default)]
+pub struct WatcherControl {
+    state: Arc<Mutex<PauseState>>,
+}
+
+#[derive(Default)]
+struct PauseState {
+    paused: bool,
+    backlog: HashMap<PathBuf, ChangeKind>, // Latest change per path while paused
+    resumed: Option<bool>, // `drop_backlog` of a resume the watcher has not handled yet
+}
+
+impl WatcherControl {
+    /// Record changes without applying them until `resume`
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = true;
+        state.resumed = None;
+    }
+    
+    /// Apply the recorded changes, or discard them with `drop_backlog`
+    ///
+    /// The event loop handles the backlog on its next poll.
+    pub fn resume(&self, drop_backlog: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = false;
+        state.resumed = Some(drop_backlog || state.resumed.unwrap_or(false));
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+    
+    /// Paths with changes recorded and not yet applied or discarded
+    pub fn backlog_len(&self) -> usize {
+        self.state.lock().unwrap().backlog.len()
+    }
+    
+    /// Add changes to the backlog; a later change to a path replaces an earlier one
+    fn record(&self, changes: impl IntoIterator<Item = (PathBuf, ChangeKind)>) {
+        self.state.lock().unwrap().backlog.extend(changes);
+    }
+    
+    /// The pending resume's `drop_backlog` and the backlog it applies to
+    fn take_resumed(&self) -> Option<(bool, Vec<(PathBuf, ChangeKind)>)> {
+        let mut state = self.state.lock().unwrap();
+        let drop_backlog = state.resumed.take()?;
+        Some((drop_backlog, state.backlog.drain().collect()))
+    }
+}
+
+/// The changes an event reports, one per path
+fn changes_of(event: &Event) -> Vec<(PathBuf, ChangeKind)> {
+    let kind = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => ChangeKind::Update,
+        // Removed paths no longer exist, so rely on the event kind
+        EventKind::Remove(RemoveKind::File) => ChangeKind::RemoveFile,
+        EventKind::Remove(RemoveKind::Folder) => ChangeKind::RemoveDir,
+        EventKind::Remove(_) => ChangeKind::Remove,
+        _ => return Vec::new(),
+    };
+    event.paths.iter().map(|path| (path.clone(), kind)).collect()
 }
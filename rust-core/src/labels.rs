@@ -0,0 +1,76 @@
+/// Free-form key/value labels for contexts and cost records
+
+use crate::error::{OrchestratorError, Result};
+use std::collections::HashMap;
+
+pub const MAX_LABELS: usize = 32;
+pub const MAX_KEY_LENGTH: usize = 64;
+pub const MAX_VALUE_LENGTH: usize = 256;
+
+/// Check label count, key and value lengths, and key characters
+///
+/// Keys are limited to ASCII letters, digits and `_ - . /` so they can be
+/// used in JSON paths when filtering.
+pub fn validate_labels(labels: &HashMap<String, String>) -> Result<()> {
+    if labels.len() > MAX_LABELS {
+        return Err(OrchestratorError::InvalidInput(format!(
+            "Too many labels: {} (max: {})",
+            labels.len(),
+            MAX_LABELS
+        )));
+    }
+    for (key, value) in labels {
+        validate_key(key)?;
+        if value.chars().count() > MAX_VALUE_LENGTH {
+            return Err(OrchestratorError::InvalidInput(format!(
+                "Label value for {} is longer than {} characters",
+                key, MAX_VALUE_LENGTH
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(OrchestratorError::InvalidInput(format!(
+            "Label keys must be 1 to {} characters (got {:?})",
+            MAX_KEY_LENGTH, key
+        )));
+    }
+    if !key.chars().all(|c| c.is_ascii_alphanumeric() || "_-./".contains(c)) {
+        return Err(OrchestratorError::InvalidInput(format!(
+            "Label key {:?} may only contain letters, digits and _ - . /",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// JSON path selecting `key` from a labels column
+pub(crate) fn label_path(key: &str) -> Result<String> {
+    validate_key(key)?;
+    Ok(format!("$.\"{}\"", key))
+}
+
+/// SQL requiring every label in `labels_match` on a JSON `labels` column
+///
+/// Returns one ` AND ...` clause per label, numbered from `first_param`, and
+/// the values to bind in order (path, value, path, value, ...).
+pub(crate) fn label_conditions(
+    labels_match: &HashMap<String, String>,
+    first_param: usize,
+) -> Result<(String, Vec<String>)> {
+    let mut keys: Vec<&String> = labels_match.keys().collect();
+    keys.sort();
+    
+    let mut sql = String::new();
+    let mut binds = Vec::new();
+    for (i, key) in keys.into_iter().enumerate() {
+        let param = first_param + i * 2;
+        sql.push_str(&format!(" AND json_extract(labels, ?{}) = ?{}", param, param + 1));
+        binds.push(label_path(key)?);
+        binds.push(labels_match[key].clone());
+    }
+    Ok((sql, binds))
+}
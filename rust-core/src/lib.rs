@@ -1,5 +1,7 @@
+pub mod config;
 pub mod router;
 pub mod context;
+pub mod cost;
 pub mod storage;
 pub mod composer;
 pub mod error;
@@ -8,8 +10,15 @@ pub mod observability;
 pub mod security;
 pub mod migrations;
 pub mod indexer;
+pub mod labels;
+pub mod models;
+pub mod pagination;
+pub mod projects;
 
+pub use config::OrchestratorConfig;
 pub use router::Router;
 pub use context::ContextManager;
 pub use storage::Storage;
-pub use error::{OrchestratorError, Result};
\ No newline at end of file
+pub use error::{OrchestratorError, Result};
+pub use models::{ModelCatalog, ModelEntry};
+pub use pagination::Page;
\ No newline at end of file
@@ -0,0 +1,252 @@
+/// Schema snapshots and drift detection against the migration history
+
+use super::runner::{MigrationError, MigrationRunner};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// Declared type, uppercased
+    pub data_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    /// Position in the primary key, 0 if not part of it
+    pub primary_key: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableSchema {
+    /// In declaration order
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexSchema {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+/// Tables and explicitly created indexes of a database, keyed by name
+///
+/// Internal `sqlite_*` objects, indexes implied by UNIQUE and PRIMARY KEY
+/// constraints, and the migration bookkeeping tables are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaSnapshot {
+    pub tables: BTreeMap<String, TableSchema>,
+    pub indexes: BTreeMap<String, IndexSchema>,
+}
+
+/// A table, column or index named in a `SchemaDiff`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaObject {
+    Table(String),
+    Column { table: String, column: String },
+    Index(String),
+}
+
+impl fmt::Display for SchemaObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaObject::Table(name) => write!(f, "table {}", name),
+            SchemaObject::Column { table, column } => write!(f, "column {}.{}", table, column),
+            SchemaObject::Index(name) => write!(f, "index {}", name),
+        }
+    }
+}
+
+/// An object present on both sides with a different definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    pub object: SchemaObject,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Differences between a database and the schema its migrations produce
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// In the database but not produced by the migrations
+    pub added: Vec<SchemaObject>,
+    /// Produced by the migrations but absent from the database
+    pub missing: Vec<SchemaObject>,
+    pub mismatched: Vec<SchemaMismatch>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No schema drift");
+        }
+        writeln!(f, "Schema drift detected:")?;
+        for object in &self.missing {
+            writeln!(f, "  missing {}", object)?;
+        }
+        for object in &self.added {
+            writeln!(f, "  unexpected {}", object)?;
+        }
+        for mismatch in &self.mismatched {
+            writeln!(f, "  {} differs: expected {}, found {}", mismatch.object, mismatch.expected, mismatch.actual)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the current schema of a database
+pub async fn snapshot_schema(pool: &SqlitePool) -> Result<SchemaSnapshot, MigrationError> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT name FROM sqlite_master
+        WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+            AND name NOT IN ('schema_migrations', 'data_migrations', 'data_migration_paths')
+        ORDER BY name
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let mut snapshot = SchemaSnapshot::default();
+    for table in tables {
+        let columns = sqlx::query_as::<_, (String, String, bool, Option<String>, i64)>(
+            "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1) ORDER BY cid",
+        )
+        .bind(&table)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(name, data_type, not_null, default, primary_key)| ColumnSchema {
+            name,
+            data_type: data_type.to_uppercase(),
+            not_null,
+            default: default.map(|d| d.trim().to_string()),
+            primary_key: primary_key as u32,
+        })
+        .collect();
+        
+        // origin 'c' marks indexes made by CREATE INDEX rather than constraints
+        let indexes: Vec<(String, bool)> = sqlx::query_as(
+            "SELECT name, \"unique\" FROM pragma_index_list(?1) WHERE origin = 'c'",
+        )
+        .bind(&table)
+        .fetch_all(pool)
+        .await?;
+        for (name, unique) in indexes {
+            let columns: Vec<Option<String>> = sqlx::query_scalar(
+                "SELECT name FROM pragma_index_info(?1) ORDER BY seqno",
+            )
+            .bind(&name)
+            .fetch_all(pool)
+            .await?;
+            snapshot.indexes.insert(
+                name,
+                IndexSchema {
+                    table: table.clone(),
+                    // Expression columns have no name
+                    columns: columns.into_iter().map(|c| c.unwrap_or_else(|| "<expr>".to_string())).collect(),
+                    unique,
+                },
+            );
+        }
+        
+        snapshot.tables.insert(table, TableSchema { columns });
+    }
+    Ok(snapshot)
+}
+
+/// Schema produced by the standard migrations up to `version`
+pub async fn expected_schema_for(version: u32) -> Result<SchemaSnapshot, MigrationError> {
+    let mut runner = MigrationRunner::new(scratch_pool().await?);
+    super::register_migrations(&mut runner);
+    runner.expected_schema(Some(version)).await
+}
+
+/// An empty in-memory database to replay migrations on
+pub(crate) async fn scratch_pool() -> Result<SqlitePool, MigrationError> {
+    Ok(SqlitePoolOptions::new().max_connections(1).connect(":memory:").await?)
+}
+
+/// Compare a database's schema with the expected one
+pub fn diff_schema(actual: &SchemaSnapshot, expected: &SchemaSnapshot) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+    
+    for (name, expected_table) in &expected.tables {
+        let actual_table = match actual.tables.get(name) {
+            Some(table) => table,
+            None => {
+                diff.missing.push(SchemaObject::Table(name.clone()));
+                continue;
+            }
+        };
+        let column = |column: &str| SchemaObject::Column {
+            table: name.clone(),
+            column: column.to_string(),
+        };
+        for expected_column in &expected_table.columns {
+            match actual_table.columns.iter().find(|c| c.name == expected_column.name) {
+                Some(actual_column) if actual_column != expected_column => diff.mismatched.push(SchemaMismatch {
+                    object: column(&expected_column.name),
+                    expected: describe_column(expected_column),
+                    actual: describe_column(actual_column),
+                }),
+                Some(_) => {}
+                None => diff.missing.push(column(&expected_column.name)),
+            }
+        }
+        for actual_column in &actual_table.columns {
+            if !expected_table.columns.iter().any(|c| c.name == actual_column.name) {
+                diff.added.push(column(&actual_column.name));
+            }
+        }
+    }
+    for name in actual.tables.keys().filter(|name| !expected.tables.contains_key(*name)) {
+        diff.added.push(SchemaObject::Table(name.clone()));
+    }
+    
+    for (name, expected_index) in &expected.indexes {
+        match actual.indexes.get(name) {
+            Some(actual_index) if actual_index != expected_index => diff.mismatched.push(SchemaMismatch {
+                object: SchemaObject::Index(name.clone()),
+                expected: describe_index(expected_index),
+                actual: describe_index(actual_index),
+            }),
+            Some(_) => {}
+            None => diff.missing.push(SchemaObject::Index(name.clone())),
+        }
+    }
+    for name in actual.indexes.keys().filter(|name| !expected.indexes.contains_key(*name)) {
+        diff.added.push(SchemaObject::Index(name.clone()));
+    }
+    
+    diff
+}
+
+fn describe_column(column: &ColumnSchema) -> String {
+    let mut description = if column.data_type.is_empty() { "ANY".to_string() } else { column.data_type.clone() };
+    if column.not_null {
+        description.push_str(" NOT NULL");
+    }
+    if let Some(default) = &column.default {
+        description.push_str(&format!(" DEFAULT {}", default));
+    }
+    if column.primary_key > 0 {
+        description.push_str(" PRIMARY KEY");
+    }
+    description
+}
+
+fn describe_index(index: &IndexSchema) -> String {
+    format!(
+        "{}ON {}({})",
+        if index.unique { "UNIQUE " } else { "" },
+        index.table,
+        index.columns.join(", ")
+    )
+}
@@ -1,10 +1,14 @@
 /// Database migration system
 
+pub mod introspect;
 pub mod runner;
+pub mod set;
 
 mod migrations;
 
-pub use runner::{MigrationRunner, Migration, MigrationError};
+pub use runner::{DataMigration, MigrationRunner, Migration, MigrationError, MigrationFn, MigrationFuture};
+pub use introspect::{diff_schema, expected_schema_for, snapshot_schema, SchemaDiff, SchemaObject, SchemaSnapshot};
+pub use set::{DatabaseMigrationReport, DatabaseStatus, MigrationSet, MigrationSetReport};
 
 use sqlx::sqlite::SqlitePool;
 
@@ -12,40 +16,312 @@ use sqlx::sqlite::SqlitePool;
 pub fn register_migrations(runner: &mut MigrationRunner) {
     use migrations::*;
     
-    runner.add_migration(Migration {
-        version: 1,
-        name: "initial_schema".to_string(),
-        up: Box::new(|pool| Box::pin(m001_initial_schema::up(pool))),
-        down: Box::new(|pool| Box::pin(m001_initial_schema::down(pool))),
-    });
-    
-    runner.add_migration(Migration {
-        version: 2,
-        name: "add_cost_tracking".to_string(),
-        up: Box::new(|pool| Box::pin(m002_add_cost_tracking::up(pool))),
-        down: Box::new(|pool| Box::pin(m002_add_cost_tracking::down(pool))),
-    });
-    
-    runner.add_migration(Migration {
-        version: 3,
-        name: "add_indexing".to_string(),
-        up: Box::new(|pool| Box::pin(m003_add_indexing::up(pool))),
-        down: Box::new(|pool| Box::pin(m003_add_indexing::down(pool))),
-    });
-    
-    runner.add_migration(Migration {
-        version: 4,
-        name: "add_security".to_string(),
-        up: Box::new(|pool| Box::pin(m004_add_security::up(pool))),
-        down: Box::new(|pool| Box::pin(m004_add_security::down(pool))),
-    });
-    
-    runner.add_migration(Migration {
-        version: 5,
-        name: "add_codeblock_metadata".to_string(),
-        up: Box::new(|pool| Box::pin(m005_add_codeblock_metadata::up(pool))),
-        down: Box::new(|pool| Box::pin(m005_add_codeblock_metadata::down(pool))),
-    });
+    runner.add_migration(Migration::new(
+        1,
+        "initial_schema",
+        |pool| Box::pin(m001_initial_schema::up(pool)),
+        |pool| Box::pin(m001_initial_schema::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        2,
+        "add_cost_tracking",
+        |pool| Box::pin(m002_add_cost_tracking::up(pool)),
+        |pool| Box::pin(m002_add_cost_tracking::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        3,
+        "add_indexing",
+        |pool| Box::pin(m003_add_indexing::up(pool)),
+        |pool| Box::pin(m003_add_indexing::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        4,
+        "add_security",
+        |pool| Box::pin(m004_add_security::up(pool)),
+        |pool| Box::pin(m004_add_security::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        5,
+        "add_codeblock_metadata",
+        |pool| Box::pin(m005_add_codeblock_metadata::up(pool)),
+        |pool| Box::pin(m005_add_codeblock_metadata::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        6,
+        "add_block_short_name",
+        |pool| Box::pin(m006_add_block_short_name::up(pool)),
+        |pool| Box::pin(m006_add_block_short_name::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        7,
+        "add_block_parent",
+        |pool| Box::pin(m007_add_block_parent::up(pool)),
+        |pool| Box::pin(m007_add_block_parent::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        8,
+        "add_context_version",
+        |pool| Box::pin(m008_add_context_version::up(pool)),
+        |pool| Box::pin(m008_add_context_version::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        9,
+        "add_context_archive",
+        |pool| Box::pin(m009_add_context_archive::up(pool)),
+        |pool| Box::pin(m009_add_context_archive::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        10,
+        "add_context_title",
+        |pool| Box::pin(m010_add_context_title::up(pool)),
+        |pool| Box::pin(m010_add_context_title::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        11,
+        "add_labels",
+        |pool| Box::pin(m011_add_labels::up(pool)),
+        |pool| Box::pin(m011_add_labels::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        12,
+        "add_responses",
+        |pool| Box::pin(m012_add_responses::up(pool)),
+        |pool| Box::pin(m012_add_responses::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        13,
+        "reconcile_cost_records",
+        |pool| Box::pin(m013_reconcile_cost_records::up(pool)),
+        |pool| Box::pin(m013_reconcile_cost_records::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        14,
+        "add_index_meta",
+        |pool| Box::pin(m014_add_index_meta::up(pool)),
+        |pool| Box::pin(m014_add_index_meta::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        15,
+        "add_file_summaries",
+        |pool| Box::pin(m015_add_file_summaries::up(pool)),
+        |pool| Box::pin(m015_add_file_summaries::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        16,
+        "add_watcher_journal",
+        |pool| Box::pin(m016_add_watcher_journal::up(pool)),
+        |pool| Box::pin(m016_add_watcher_journal::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        17,
+        "add_block_source",
+        |pool| Box::pin(m017_add_block_source::up(pool)),
+        |pool| Box::pin(m017_add_block_source::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        18,
+        "add_audit_request_id",
+        |pool| Box::pin(m018_add_audit_request_id::up(pool)),
+        |pool| Box::pin(m018_add_audit_request_id::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        19,
+        "add_block_content_hash",
+        |pool| Box::pin(m019_add_block_content_hash::up(pool)),
+        |pool| Box::pin(m019_add_block_content_hash::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        20,
+        "add_projects",
+        |pool| Box::pin(m020_add_projects::up(pool)),
+        |pool| Box::pin(m020_add_projects::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        21,
+        "add_index_events",
+        |pool| Box::pin(m021_add_index_events::up(pool)),
+        |pool| Box::pin(m021_add_index_events::down(pool)),
+    ));
+    
+    runner.add_migration(Migration::new(
+        22,
+        "add_memories",
+        |pool| Box::pin(m022_add_memories::up(pool)),
+        |pool| Box::pin(m022_add_memories::down(pool)),
+    ));
+}
+
+/// Register a migration that depends on deployment configuration
+///
+/// Data migrations are tracked by name, apart from the schema versions, so
+/// the name must identify the change: one applied under a name never runs
+/// again, whatever its parameters.
+pub fn register_data_migration(runner: &mut MigrationRunner, migration: DataMigration) {
+    runner.add_data_migration(migration);
+}
+
+/// Data migrations built from runtime parameters, for `register_data_migration`
+pub mod data {
+    use super::{DataMigration, MigrationFuture};
+    use sqlx::sqlite::SqlitePool;
+    use std::sync::Arc;
+    
+    /// Assign `project_id` to cost records recorded without a project
+    ///
+    /// Rolling back leaves the assigned project in place, since records
+    /// that had it already cannot be told apart.
+    pub fn backfill_cost_project(name: impl Into<String>, project_id: impl Into<String>) -> DataMigration {
+        let project_id: Arc<str> = project_id.into().into();
+        DataMigration::new(
+            name,
+            move |pool| {
+                let project_id = project_id.clone();
+                Box::pin(async move {
+                    sqlx::query("UPDATE cost_records SET project_id = ?1 WHERE project_id IS NULL")
+                        .bind(project_id.as_ref())
+                        .execute(pool)
+                        .await?;
+                    Ok(())
+                })
+            },
+            |_pool| Box::pin(async { Ok(()) }),
+        )
+    }
+    
+    /// Rewrite indexed file paths of `project_id` starting with `from` to start with `to`
+    ///
+    /// For moving an index built with absolute paths onto relative ones
+    /// (`to` empty). The original paths are kept in `data_migration_paths`,
+    /// so rolling back restores exactly the files that were rewritten.
+    pub fn rewrite_index_paths(
+        name: impl Into<String>,
+        project_id: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> DataMigration {
+        let name: Arc<str> = name.into().into();
+        let project_id: Arc<str> = project_id.into().into();
+        let (from, to): (Arc<str>, Arc<str>) = (from.into().into(), to.into().into());
+        DataMigration::new(
+            name.to_string(),
+            rewrite_paths(name.clone(), project_id, from, to),
+            restore_paths(name),
+        )
+    }
+    
+    fn rewrite_paths(
+        name: Arc<str>,
+        project_id: Arc<str>,
+        from: Arc<str>,
+        to: Arc<str>,
+    ) -> impl for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync + 'static {
+        move |pool| {
+            let (name, project_id, from, to) = (name.clone(), project_id.clone(), from.clone(), to.clone());
+            Box::pin(async move {
+                let mut tx = pool.begin().await?;
+                sqlx::query(
+                    r#"
+                    CREATE TABLE IF NOT EXISTS data_migration_paths (
+                        migration TEXT NOT NULL,
+                        file_id INTEGER NOT NULL,
+                        original_path TEXT NOT NULL,
+                        PRIMARY KEY (migration, file_id)
+                    )
+                    "#,
+                )
+                .execute(&mut *tx)
+                .await?;
+                
+                sqlx::query(
+                    r#"
+                    INSERT OR REPLACE INTO data_migration_paths (migration, file_id, original_path)
+                    SELECT ?1, id, file_path FROM indexed_files
+                    WHERE project_id = ?2 AND substr(file_path, 1, length(?3)) = ?3
+                    "#,
+                )
+                .bind(name.as_ref())
+                .bind(project_id.as_ref())
+                .bind(from.as_ref())
+                .execute(&mut *tx)
+                .await?;
+                
+                sqlx::query(
+                    r#"
+                    UPDATE indexed_files
+                    SET file_path = ?1 || substr(file_path, length(?2) + 1)
+                    WHERE id IN (SELECT file_id FROM data_migration_paths WHERE migration = ?3)
+                    "#,
+                )
+                .bind(to.as_ref())
+                .bind(from.as_ref())
+                .bind(name.as_ref())
+                .execute(&mut *tx)
+                .await?;
+                
+                tx.commit().await?;
+                Ok(())
+            })
+        }
+    }
+    
+    fn restore_paths(name: Arc<str>) -> impl for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync + 'static {
+        move |pool| {
+            let name = name.clone();
+            Box::pin(async move {
+                let (exists,): (bool,) = sqlx::query_as(
+                    "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'data_migration_paths'"
+                )
+                .fetch_one(pool)
+                .await?;
+                if !exists {
+                    return Ok(());
+                }
+                
+                let mut tx = pool.begin().await?;
+                sqlx::query(
+                    r#"
+                    UPDATE indexed_files
+                    SET file_path = (
+                        SELECT original_path FROM data_migration_paths
+                        WHERE migration = ?1 AND file_id = indexed_files.id
+                    )
+                    WHERE id IN (SELECT file_id FROM data_migration_paths WHERE migration = ?1)
+                    "#,
+                )
+                .bind(name.as_ref())
+                .execute(&mut *tx)
+                .await?;
+                
+                sqlx::query("DELETE FROM data_migration_paths WHERE migration = ?1")
+                    .bind(name.as_ref())
+                    .execute(&mut *tx)
+                    .await?;
+                
+                tx.commit().await?;
+                Ok(())
+            })
+        }
+    }
 }
 
 mod migrations {
@@ -128,12 +404,22 @@ mod migrations {
             .execute(pool)
             .await?;
             
-            sqlx::query(
-                "CREATE INDEX IF NOT EXISTS idx_cost_records_created_at ON cost_records(created_at)"
+            // CostStorage may have created the table first, without created_at;
+            // migration 13 reconciles the two shapes
+            let (has_created_at,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('cost_records') WHERE name = 'created_at'"
             )
-            .execute(pool)
+            .fetch_one(pool)
             .await?;
             
+            if has_created_at {
+                sqlx::query(
+                    "CREATE INDEX IF NOT EXISTS idx_cost_records_created_at ON cost_records(created_at)"
+                )
+                .execute(pool)
+                .await?;
+            }
+            
             Ok(())
         }
         
@@ -415,4 +701,713 @@ mod migrations {
             Ok(())
         }
     }
+
+    pub mod m006_add_block_short_name {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Bare block name; `name` holds the qualified name (e.g. "Storage.save")
+            sqlx::query(
+                "ALTER TABLE code_blocks ADD COLUMN short_name TEXT"
+            )
+            .execute(pool)
+            .await?;
+            
+            // Existing rows were stored unqualified
+            sqlx::query(
+                "UPDATE code_blocks SET short_name = name WHERE short_name IS NULL"
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_code_blocks_short_name ON code_blocks(short_name)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_code_blocks_short_name")
+                .execute(pool)
+                .await?;
+            
+            // SQLite doesn't support DROP COLUMN directly; the short_name column
+            // is left in place (see m005)
+            Ok(())
+        }
+    }
+
+    pub mod m007_add_block_parent {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Enclosing block (e.g. the class of a method); NULL for top-level blocks
+            sqlx::query(
+                "ALTER TABLE code_blocks ADD COLUMN parent_block_id INTEGER"
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_code_blocks_parent ON code_blocks(parent_block_id)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_code_blocks_parent")
+                .execute(pool)
+                .await?;
+            
+            // SQLite doesn't support DROP COLUMN directly; the parent_block_id
+            // column is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m008_add_context_version {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Optimistic locking for context saves; ContextStorage may already
+            // have added the column when it created the table
+            let (has_version,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('contexts') WHERE name = 'version'"
+            )
+            .fetch_one(pool)
+            .await?;
+            
+            if !has_version {
+                sqlx::query(
+                    "ALTER TABLE contexts ADD COLUMN version INTEGER NOT NULL DEFAULT 0"
+                )
+                .execute(pool)
+                .await?;
+            }
+            
+            Ok(())
+        }
+        
+        pub async fn down(_pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // SQLite doesn't support DROP COLUMN directly; the version column
+            // is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m009_add_context_archive {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Messages drained by the summarizer, one row per summary
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS context_archive (
+                    summary_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    conversation_id TEXT NOT NULL,
+                    summary TEXT NOT NULL,
+                    summary_message TEXT NOT NULL,
+                    messages TEXT NOT NULL,
+                    created_at INTEGER NOT NULL
+                )
+                "#
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_context_archive_conversation ON context_archive(conversation_id)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_context_archive_conversation")
+                .execute(pool)
+                .await?;
+            
+            sqlx::query("DROP TABLE IF EXISTS context_archive")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
+    
+    pub mod m010_add_context_title {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Titles are copied out of the context blob so listings need not
+            // deserialize it; ContextStorage may already have added the column
+            let (has_title,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('contexts') WHERE name = 'title'"
+            )
+            .fetch_one(pool)
+            .await?;
+            
+            if !has_title {
+                sqlx::query("ALTER TABLE contexts ADD COLUMN title TEXT")
+                    .execute(pool)
+                    .await?;
+            }
+            
+            Ok(())
+        }
+        
+        pub async fn down(_pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // SQLite doesn't support DROP COLUMN directly; the title column
+            // is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m011_add_labels {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // JSON object of free-form labels; the owning stores may already
+            // have added the column when they created their tables
+            for table in ["contexts", "cost_records"] {
+                let (has_labels,): (bool,) = sqlx::query_as(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = 'labels'"
+                )
+                .bind(table)
+                .fetch_one(pool)
+                .await?;
+                
+                if !has_labels {
+                    sqlx::query(&format!(
+                        "ALTER TABLE {} ADD COLUMN labels TEXT NOT NULL DEFAULT '{{}}'",
+                        table
+                    ))
+                    .execute(pool)
+                    .await?;
+                }
+            }
+            
+            Ok(())
+        }
+        
+        pub async fn down(_pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // SQLite doesn't support DROP COLUMN directly; the labels columns
+            // are left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m012_add_responses {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Composed responses; raw tool responses are gzipped JSON
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS responses (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    conversation_id TEXT NOT NULL,
+                    request_id TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    strategy TEXT NOT NULL,
+                    sources TEXT NOT NULL,
+                    metadata TEXT,
+                    raw_responses BLOB NOT NULL,
+                    created_at INTEGER NOT NULL
+                )
+                "#
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_responses_conversation ON responses(conversation_id, created_at)"
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_responses_request ON responses(request_id)"
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_responses_created_at ON responses(created_at)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_responses_created_at")
+                .execute(pool)
+                .await?;
+            
+            sqlx::query("DROP INDEX IF EXISTS idx_responses_request")
+                .execute(pool)
+                .await?;
+            
+            sqlx::query("DROP INDEX IF EXISTS idx_responses_conversation")
+                .execute(pool)
+                .await?;
+            
+            sqlx::query("DROP TABLE IF EXISTS responses")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
+    
+    pub mod m013_reconcile_cost_records {
+        use sqlx::sqlite::SqlitePool;
+        
+        /// Canonical columns and the legacy columns that may hold their data,
+        /// in order of preference, with the value used when none exist
+        const COLUMNS: &[(&str, &[&str], &str)] = &[
+            ("id", &["id"], "NULL"),
+            ("request_id", &["request_id"], "NULL"),
+            ("tool", &["tool"], "''"),
+            ("model", &["model"], "''"),
+            ("input_tokens", &["input_tokens", "tokens_input"], "0"),
+            ("output_tokens", &["output_tokens", "tokens_output"], "0"),
+            ("cost_usd", &["cost_usd"], "0"),
+            ("timestamp", &["timestamp", "created_at"], "0"),
+            ("user_id", &["user_id"], "NULL"),
+            ("project_id", &["project_id"], "NULL"),
+            ("conversation_id", &["conversation_id"], "NULL"),
+            ("labels", &["labels"], "'{}'"),
+        ];
+        
+        /// Expression reading a legacy column; migration 2 stored text timestamps
+        fn read(column: &str) -> String {
+            match column {
+                "created_at" => "CAST(strftime('%s', created_at) AS INTEGER)".to_string(),
+                other => other.to_string(),
+            }
+        }
+        
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Migration 2 and CostStorage created cost_records with different
+            // columns (tokens_input/created_at vs input_tokens/timestamp), and
+            // whichever ran first won. Rebuild the table in the shape
+            // CostStorage uses, with request_id added, copying legacy columns.
+            let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('cost_records')")
+                .fetch_all(pool)
+                .await?;
+            
+            let canonical = COLUMNS.iter().all(|(name, _, _)| existing.iter().any(|c| c == name))
+                && !existing.iter().any(|c| c == "tokens_input" || c == "created_at");
+            if canonical {
+                return Ok(());
+            }
+            
+            let mut tx = pool.begin().await?;
+            sqlx::query(
+                r#"
+                CREATE TABLE cost_records_canonical (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    request_id TEXT,
+                    tool TEXT NOT NULL,
+                    model TEXT NOT NULL,
+                    input_tokens INTEGER NOT NULL,
+                    output_tokens INTEGER NOT NULL,
+                    cost_usd REAL NOT NULL,
+                    timestamp INTEGER NOT NULL,
+                    user_id TEXT,
+                    project_id TEXT,
+                    conversation_id TEXT,
+                    labels TEXT NOT NULL DEFAULT '{}'
+                )
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?;
+            
+            if !existing.is_empty() {
+                let names: Vec<&str> = COLUMNS.iter().map(|(name, _, _)| *name).collect();
+                let sources: Vec<String> = COLUMNS
+                    .iter()
+                    .map(|(_, candidates, fallback)| {
+                        let mut present: Vec<String> = candidates
+                            .iter()
+                            .filter(|column| existing.iter().any(|c| c == *column))
+                            .map(|column| read(column))
+                            .collect();
+                        present.push(fallback.to_string());
+                        // SQLite rejects COALESCE with a single argument
+                        match present.len() {
+                            1 => present.remove(0),
+                            _ => format!("COALESCE({})", present.join(", ")),
+                        }
+                    })
+                    .collect();
+                sqlx::query(&format!(
+                    "INSERT INTO cost_records_canonical ({}) SELECT {} FROM cost_records",
+                    names.join(", "),
+                    sources.join(", ")
+                ))
+                .execute(&mut *tx)
+                .await?;
+                
+                sqlx::query("DROP TABLE cost_records")
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            
+            sqlx::query("ALTER TABLE cost_records_canonical RENAME TO cost_records")
+                .execute(&mut *tx)
+                .await?;
+            
+            for (index, column) in [
+                ("idx_cost_timestamp", "timestamp"),
+                ("idx_cost_tool", "tool"),
+                ("idx_cost_user", "user_id"),
+                ("idx_cost_records_project_id", "project_id"),
+            ] {
+                sqlx::query(&format!("CREATE INDEX IF NOT EXISTS {} ON cost_records({})", index, column))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            
+            tx.commit().await
+        }
+        
+        pub async fn down(_pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // The legacy shapes cannot be told apart once merged; the
+            // canonical table is left in place
+            Ok(())
+        }
+    }
+    
+    pub mod m014_add_index_meta {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Model and size of each project's stored embeddings
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS index_meta (
+                    project_id TEXT PRIMARY KEY,
+                    embedding_model TEXT,
+                    embedding_dim INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL
+                )
+                "#
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP TABLE IF EXISTS index_meta")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
+    
+    pub mod m015_add_file_summaries {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Per-file summaries, cleared whenever the file is re-indexed;
+            // the column survives a rollback, so it may already exist
+            let (has_summary,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('indexed_files') WHERE name = 'summary'"
+            )
+            .fetch_one(pool)
+            .await?;
+            
+            if !has_summary {
+                sqlx::query("ALTER TABLE indexed_files ADD COLUMN summary TEXT")
+                    .execute(pool)
+                    .await?;
+            }
+            
+            Ok(())
+        }
+        
+        pub async fn down(_pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // SQLite doesn't support DROP COLUMN directly; the summary column
+            // is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m016_add_watcher_journal {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // File changes the watcher has seen but not yet applied
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS watcher_journal (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    project_id TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    recorded_at INTEGER NOT NULL
+                )
+                "#
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_watcher_journal_project_path ON watcher_journal(project_id, path)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP TABLE IF EXISTS watcher_journal")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
+    
+    pub mod m017_add_block_source {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // What produced each block ("ast", "ctags" or "lsif"); the column
+            // survives a rollback, so it may already exist
+            let (has_source,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('code_blocks') WHERE name = 'source'"
+            )
+            .fetch_one(pool)
+            .await?;
+            
+            if !has_source {
+                sqlx::query("ALTER TABLE code_blocks ADD COLUMN source TEXT NOT NULL DEFAULT 'ast'")
+                    .execute(pool)
+                    .await?;
+            }
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_code_blocks_source ON code_blocks(source)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_code_blocks_source")
+                .execute(pool)
+                .await?;
+            
+            // SQLite doesn't support DROP COLUMN directly; the source column
+            // is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m018_add_audit_request_id {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Request the event happened in; the column survives a rollback,
+            // so it may already exist
+            let (has_request_id,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('audit_logs') WHERE name = 'request_id'"
+            )
+            .fetch_one(pool)
+            .await?;
+            
+            if !has_request_id {
+                sqlx::query("ALTER TABLE audit_logs ADD COLUMN request_id TEXT")
+                    .execute(pool)
+                    .await?;
+            }
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_audit_logs_request_id ON audit_logs(request_id)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_audit_logs_request_id")
+                .execute(pool)
+                .await?;
+            
+            // SQLite doesn't support DROP COLUMN directly; the request_id
+            // column is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m019_add_block_content_hash {
+        use crate::indexer::fingerprint::block_fingerprint;
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Whitespace- and comment-insensitive hash of each block's
+            // content; the column survives a rollback, so it may already exist
+            let (has_content_hash,): (bool,) = sqlx::query_as(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('code_blocks') WHERE name = 'content_hash'"
+            )
+            .fetch_one(pool)
+            .await?;
+            
+            if !has_content_hash {
+                sqlx::query("ALTER TABLE code_blocks ADD COLUMN content_hash TEXT")
+                    .execute(pool)
+                    .await?;
+            }
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_code_blocks_content_hash ON code_blocks(content_hash)"
+            )
+            .execute(pool)
+            .await?;
+            
+            // The hash depends on the language's comment syntax, so it is
+            // computed here rather than in SQL
+            let blocks: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+                r#"
+                SELECT c.id, c.content, f.language
+                FROM code_blocks c
+                JOIN indexed_files f ON c.file_id = f.id
+                WHERE c.content_hash IS NULL
+                "#,
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            let mut tx = pool.begin().await?;
+            for (id, content, language) in blocks {
+                sqlx::query("UPDATE code_blocks SET content_hash = ? WHERE id = ?")
+                    .bind(block_fingerprint(&content, language.as_deref().unwrap_or_default()))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP INDEX IF EXISTS idx_code_blocks_content_hash")
+                .execute(pool)
+                .await?;
+            
+            // SQLite doesn't support DROP COLUMN directly; the content_hash
+            // column is left in place (see m005)
+            Ok(())
+        }
+    }
+    
+    pub mod m020_add_projects {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Settings are a JSON object of ProjectSettings
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS projects (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    root_path TEXT,
+                    created_at INTEGER NOT NULL,
+                    settings TEXT NOT NULL DEFAULT '{}'
+                )
+                "#,
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP TABLE IF EXISTS projects")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
+
+    pub mod m021_add_index_events {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Outcomes of watcher-driven indexing; pruned by age and count
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS index_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    project_id TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    action TEXT NOT NULL,
+                    outcome TEXT NOT NULL,
+                    error TEXT,
+                    recorded_at INTEGER NOT NULL
+                )
+                "#
+            )
+            .execute(pool)
+            .await?;
+            
+            sqlx::query(
+                "CREATE INDEX IF NOT EXISTS idx_index_events_project_recorded ON index_events(project_id, recorded_at)"
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP TABLE IF EXISTS index_events")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
+    
+    pub mod m022_add_memories {
+        use sqlx::sqlite::SqlitePool;
+        pub async fn up(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            // Long-term facts about a project, injected into its conversations
+            sqlx::query(
+                r#"
+                CREATE TABLE IF NOT EXISTS memories (
+                    project_id TEXT NOT NULL,
+                    key TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    importance INTEGER NOT NULL DEFAULT 0,
+                    created_at INTEGER NOT NULL,
+                    expires_at INTEGER,
+                    PRIMARY KEY (project_id, key)
+                )
+                "#
+            )
+            .execute(pool)
+            .await?;
+            
+            Ok(())
+        }
+        
+        pub async fn down(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+            sqlx::query("DROP TABLE IF EXISTS memories")
+                .execute(pool)
+                .await?;
+            
+            Ok(())
+        }
+    }
 }
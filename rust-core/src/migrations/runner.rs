@@ -1,7 +1,10 @@
 /// Migration runner for database schema versioning
 
+use super::introspect::{diff_schema, scratch_pool, snapshot_schema, SchemaDiff, SchemaSnapshot};
 use sqlx::{sqlite::SqlitePool, Executor};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -22,16 +25,64 @@ pub enum MigrationError {
     InvalidMigration(String),
 }
 
+/// Future returned by a migration step, borrowing the pool it runs on
+pub type MigrationFuture<'a> = Pin<Box<dyn Future<Output = Result<(), sqlx::Error>> + Send + 'a>>;
+
+/// One direction of a migration; closures may capture configuration
+pub type MigrationFn = Box<dyn for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync>;
+
 pub struct Migration {
     pub version: u32,
     pub name: String,
-    pub up: fn(&SqlitePool) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), sqlx::Error>> + Send>>,
-    pub down: fn(&SqlitePool) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), sqlx::Error>> + Send>>,
+    pub up: MigrationFn,
+    pub down: MigrationFn,
+}
+
+impl Migration {
+    /// Build a migration from two steps, e.g. `|pool| Box::pin(up(pool))`
+    pub fn new<U, D>(version: u32, name: impl Into<String>, up: U, down: D) -> Self
+    where
+        U: for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync + 'static,
+        D: for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync + 'static,
+    {
+        Self {
+            version,
+            name: name.into(),
+            up: Box::new(up),
+            down: Box::new(down),
+        }
+    }
+}
+
+/// A migration of existing data, tracked by name instead of schema version
+///
+/// Data migrations depend on deployment configuration, so they stay out of
+/// the schema version sequence: they run once the schema is fully migrated
+/// and are recorded in `data_migrations`.
+pub struct DataMigration {
+    pub name: String,
+    pub up: MigrationFn,
+    pub down: MigrationFn,
+}
+
+impl DataMigration {
+    pub fn new<U, D>(name: impl Into<String>, up: U, down: D) -> Self
+    where
+        U: for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync + 'static,
+        D: for<'a> Fn(&'a SqlitePool) -> MigrationFuture<'a> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            up: Box::new(up),
+            down: Box::new(down),
+        }
+    }
 }
 
 pub struct MigrationRunner {
     pool: SqlitePool,
     migrations: Vec<Migration>,
+    data_migrations: Vec<DataMigration>, // In registration order
 }
 
 impl MigrationRunner {
@@ -39,6 +90,7 @@ impl MigrationRunner {
         Self {
             pool,
             migrations: Vec::new(),
+            data_migrations: Vec::new(),
         }
     }
     
@@ -48,6 +100,11 @@ impl MigrationRunner {
         self.migrations.sort_by_key(|m| m.version);
     }
     
+    /// Register a data migration; they run in registration order
+    pub fn add_data_migration(&mut self, migration: DataMigration) {
+        self.data_migrations.push(migration);
+    }
+    
     pub async fn ensure_migrations_table(&self) -> Result<(), MigrationError> {
         sqlx::query(
             r#"
@@ -61,19 +118,31 @@ impl MigrationRunner {
         .execute(&self.pool)
         .await?;
         
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS data_migrations (
+                name TEXT PRIMARY KEY,
+                applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        
         Ok(())
     }
     
     pub async fn get_current_version(&self) -> Result<Option<u32>, MigrationError> {
         self.ensure_migrations_table().await?;
         
-        let result = sqlx::query_as::<_, (i64,)>(
+        // MAX() yields a single NULL row when nothing has been applied
+        let (version,) = sqlx::query_as::<_, (Option<i64>,)>(
             "SELECT MAX(version) FROM schema_migrations"
         )
-        .fetch_optional(&self.pool)
+        .fetch_one(&self.pool)
         .await?;
         
-        Ok(result.map(|(v,)| v as u32))
+        Ok(version.map(|v| v as u32))
     }
     
     pub async fn get_applied_migrations(&self) -> Result<HashMap<u32, String>, MigrationError> {
@@ -88,10 +157,21 @@ impl MigrationRunner {
         Ok(rows.into_iter().map(|(v, n)| (v as u32, n)).collect())
     }
     
+    /// Names of the data migrations applied to this database
+    pub async fn get_applied_data_migrations(&self) -> Result<HashSet<String>, MigrationError> {
+        self.ensure_migrations_table().await?;
+        
+        let names = sqlx::query_scalar::<_, String>("SELECT name FROM data_migrations")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(names.into_iter().collect())
+    }
+    
     pub async fn migrate_up(&self, target_version: Option<u32>) -> Result<(), MigrationError> {
         self.ensure_migrations_table().await?;
         
-        let current_version = self.get_current_version().await?;
+        let mut current_version = self.get_current_version().await?;
         let applied = self.get_applied_migrations().await?;
         
         let target = target_version.unwrap_or_else(|| {
@@ -138,16 +218,56 @@ impl MigrationRunner {
                 .bind(&migration.name)
                 .execute(&self.pool)
                 .await?;
+                current_version = Some(migration.version);
             }
         }
         
+        // Data migrations expect the latest schema
+        let latest = self.migrations.last().map_or(0, |m| m.version);
+        if current_version.unwrap_or(0) >= latest {
+            self.apply_data_migrations().await?;
+        }
+        
+        Ok(())
+    }
+    
+    async fn apply_data_migrations(&self) -> Result<(), MigrationError> {
+        let applied = self.get_applied_data_migrations().await?;
+        for migration in self.data_migrations.iter().filter(|m| !applied.contains(&m.name)) {
+            (migration.up)(&self.pool).await.map_err(|e| {
+                MigrationError::ExecutionFailed(format!("Data migration {} failed: {}", migration.name, e))
+            })?;
+            
+            sqlx::query("INSERT INTO data_migrations (name) VALUES (?)")
+                .bind(&migration.name)
+                .execute(&self.pool)
+                .await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Roll back every applied data migration, latest registered first
+    pub async fn rollback_data_migrations(&self) -> Result<(), MigrationError> {
+        let applied = self.get_applied_data_migrations().await?;
+        for migration in self.data_migrations.iter().rev().filter(|m| applied.contains(&m.name)) {
+            (migration.down)(&self.pool).await.map_err(|e| {
+                MigrationError::ExecutionFailed(format!("Rollback of data migration {} failed: {}", migration.name, e))
+            })?;
+            
+            sqlx::query("DELETE FROM data_migrations WHERE name = ?")
+                .bind(&migration.name)
+                .execute(&self.pool)
+                .await?;
+        }
+        
         Ok(())
     }
     
     /// Validate migrations for conflicts and issues
     pub fn validate_migrations(&self) -> Result<(), MigrationError> {
         // Check for duplicate versions
-        let mut seen_versions = std::collections::HashSet::new();
+        let mut seen_versions = HashSet::new();
         for migration in &self.migrations {
             if seen_versions.contains(&migration.version) {
                 return Err(MigrationError::InvalidMigration(
@@ -157,6 +277,15 @@ impl MigrationRunner {
             seen_versions.insert(migration.version);
         }
         
+        let mut seen_names = HashSet::new();
+        for migration in &self.data_migrations {
+            if !seen_names.insert(migration.name.as_str()) {
+                return Err(MigrationError::InvalidMigration(
+                    format!("Duplicate data migration name: {}", migration.name)
+                ));
+            }
+        }
+        
         // Check for gaps in versions
         if !self.migrations.is_empty() {
             let mut sorted_versions: Vec<u32> = self.migrations.iter().map(|m| m.version).collect();
@@ -237,6 +366,11 @@ impl MigrationRunner {
             .filter(|m| m.version > target_version && m.version <= current_version)
             .collect();
         
+        // Data migrations were applied on top of the latest schema
+        if !migrations_to_rollback.is_empty() {
+            self.rollback_data_migrations().await?;
+        }
+        
         for migration in migrations_to_rollback {
             // Execute rollback
             (migration.down)(&self.pool).await.map_err(|e| {
@@ -256,6 +390,30 @@ impl MigrationRunner {
         Ok(())
     }
     
+    /// Schema these migrations produce up to `version` (default: all)
+    ///
+    /// The migrations are replayed on a scratch in-memory database.
+    pub async fn expected_schema(&self, version: Option<u32>) -> Result<SchemaSnapshot, MigrationError> {
+        let scratch = scratch_pool().await?;
+        for migration in self.migrations.iter().filter(|m| version.is_none_or(|v| m.version <= v)) {
+            (migration.up)(&scratch).await.map_err(|e| {
+                MigrationError::ExecutionFailed(format!(
+                    "Replaying migration {} ({}) failed: {}",
+                    migration.version, migration.name, e
+                ))
+            })?;
+        }
+        snapshot_schema(&scratch).await
+    }
+    
+    /// Compare the database schema with what the applied migrations produce
+    pub async fn check_drift(&self) -> Result<SchemaDiff, MigrationError> {
+        let current = self.get_current_version().await?.unwrap_or(0);
+        let expected = self.expected_schema(Some(current)).await?;
+        let actual = snapshot_schema(&self.pool).await?;
+        Ok(diff_schema(&actual, &expected))
+    }
+    
     pub async fn status(&self) -> Result<Vec<(u32, String, bool)>, MigrationError> {
         self.ensure_migrations_table().await?;
         
@@ -0,0 +1,157 @@
+/// Migrations across several named databases
+
+use super::runner::{MigrationError, MigrationRunner};
+use std::collections::HashMap;
+
+/// Outcome of migrating one database in a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseMigrationReport {
+    pub name: String,
+    /// Version before the batch ran
+    pub from_version: Option<u32>,
+    /// Version after the batch, rollbacks included
+    pub to_version: Option<u32>,
+    pub error: Option<String>,
+    /// Migrations applied in this batch were rolled back
+    pub rolled_back: bool,
+    pub rollback_error: Option<String>,
+}
+
+/// Outcome of `MigrationSet::migrate_all`, in registration order
+///
+/// Databases after the first failure are not attempted and are absent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationSetReport {
+    pub databases: Vec<DatabaseMigrationReport>,
+}
+
+impl MigrationSetReport {
+    pub fn is_ok(&self) -> bool {
+        self.databases.iter().all(|db| db.error.is_none())
+    }
+    
+    pub fn get(&self, name: &str) -> Option<&DatabaseMigrationReport> {
+        self.databases.iter().find(|db| db.name == name)
+    }
+}
+
+/// Migration status of one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseStatus {
+    pub name: String,
+    pub current_version: Option<u32>,
+    /// (version, name, applied) for every registered migration
+    pub migrations: Vec<(u32, String, bool)>,
+}
+
+/// Named databases, e.g. "contexts", "costs" and "index", each with its
+/// own pool and migrations, migrated together in registration order
+///
+/// In all-or-nothing mode a failure rolls back, best effort, everything the
+/// batch applied: first in the failing database, then in the databases
+/// migrated before it, latest first.
+pub struct MigrationSet {
+    databases: Vec<(String, MigrationRunner)>,
+    all_or_nothing: bool,
+}
+
+impl Default for MigrationSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MigrationSet {
+    pub fn new() -> Self {
+        Self {
+            databases: Vec::new(),
+            all_or_nothing: false,
+        }
+    }
+    
+    pub fn with_all_or_nothing(mut self, all_or_nothing: bool) -> Self {
+        self.all_or_nothing = all_or_nothing;
+        self
+    }
+    
+    /// Add a database; a runner already registered under `name` is replaced
+    pub fn add_database(&mut self, name: impl Into<String>, runner: MigrationRunner) {
+        let name = name.into();
+        match self.databases.iter_mut().find(|(existing, _)| *existing == name) {
+            Some(entry) => entry.1 = runner,
+            None => self.databases.push((name, runner)),
+        }
+    }
+    
+    pub fn names(&self) -> Vec<&str> {
+        self.databases.iter().map(|(name, _)| name.as_str()).collect()
+    }
+    
+    /// Migrate every database up, to its entry in `targets` or to its latest
+    ///
+    /// Databases missing from `targets` are migrated to their latest version.
+    /// Stops at the first database that fails; the failure is reported rather
+    /// than returned. Errors only if `targets` names an unknown database.
+    pub async fn migrate_all(&self, targets: Option<HashMap<String, u32>>) -> Result<MigrationSetReport, MigrationError> {
+        let targets = targets.unwrap_or_default();
+        if let Some(unknown) = targets.keys().find(|name| !self.databases.iter().any(|(n, _)| n == *name)) {
+            return Err(MigrationError::InvalidMigration(format!("Unknown database: {}", unknown)));
+        }
+        
+        let mut report = MigrationSetReport::default();
+        for (name, runner) in &self.databases {
+            let from_version = runner.get_current_version().await?;
+            let result = runner.migrate_up(targets.get(name).copied()).await;
+            let to_version = runner.get_current_version().await?;
+            report.databases.push(DatabaseMigrationReport {
+                name: name.clone(),
+                from_version,
+                to_version,
+                error: result.as_ref().err().map(|e| e.to_string()),
+                rolled_back: false,
+                rollback_error: None,
+            });
+            
+            if result.is_err() {
+                if self.all_or_nothing {
+                    self.roll_back(&mut report).await;
+                }
+                break;
+            }
+        }
+        Ok(report)
+    }
+    
+    /// Undo what the batch applied, latest database first
+    async fn roll_back(&self, report: &mut MigrationSetReport) {
+        for db in report.databases.iter_mut().rev() {
+            if db.to_version == db.from_version {
+                continue;
+            }
+            let runner = match self.databases.iter().find(|(name, _)| *name == db.name) {
+                Some((_, runner)) => runner,
+                None => continue,
+            };
+            
+            match runner.migrate_down(db.from_version.unwrap_or(0)).await {
+                Ok(()) => db.rolled_back = true,
+                Err(e) => db.rollback_error = Some(e.to_string()),
+            }
+            if let Ok(version) = runner.get_current_version().await {
+                db.to_version = version;
+            }
+        }
+    }
+    
+    pub async fn status_all(&self) -> Result<Vec<DatabaseStatus>, MigrationError> {
+        let mut statuses = Vec::with_capacity(self.databases.len());
+        for (name, runner) in &self.databases {
+            statuses.push(DatabaseStatus {
+                name: name.clone(),
+                current_version: runner.get_current_version().await?,
+                migrations: runner.status().await?,
+            });
+        }
+        Ok(statuses)
+    }
+}
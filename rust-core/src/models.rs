@@ -0,0 +1,123 @@
+/// Model aliases shared by pricing, token counting and routing
+
+use crate::cost::pricing::PricingTable;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What a model alias such as "fast" stands for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub model: String, // Canonical name, e.g. "claude-3-haiku-20240307"
+    pub tool: String, // Tool serving the model, e.g. "claude"
+    #[serde(default)]
+    pub context_window: Option<usize>, // None uses the token counter's window for the model
+    #[serde(default)]
+    pub pricing: Option<String>, // Pricing table entry; None uses the model name
+}
+
+impl ModelEntry {
+    pub fn new(model: impl Into<String>, tool: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            tool: tool.into(),
+            context_window: None,
+            pricing: None,
+        }
+    }
+
+    pub fn with_context_window(mut self, window: usize) -> Self {
+        self.context_window = Some(window);
+        self
+    }
+
+    /// Charge the model by another pricing table entry
+    pub fn with_pricing(mut self, pricing: impl Into<String>) -> Self {
+        self.pricing = Some(pricing.into());
+        self
+    }
+
+    /// The pricing table entry the model is charged by
+    pub fn pricing_model(&self) -> &str {
+        self.pricing.as_deref().unwrap_or(&self.model)
+    }
+}
+
+/// Model aliases, each naming a canonical model and the tool serving it
+///
+/// Lookups take an alias or a canonical model name, so `PricingTable`,
+/// `TokenCounter` and `Router` can be handed either.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelCatalog {
+    aliases: HashMap<String, ModelEntry>,
+}
+
+impl ModelCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_alias(mut self, alias: impl Into<String>, entry: ModelEntry) -> Self {
+        self.insert(alias, entry);
+        self
+    }
+
+    /// Add an alias, replacing any entry it had
+    pub fn insert(&mut self, alias: impl Into<String>, entry: ModelEntry) {
+        self.aliases.insert(alias.into(), entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// The entry of an alias, or of an alias for the canonical model `name`
+    ///
+    /// When several aliases name the model, the alphabetically first is used.
+    pub fn lookup(&self, name: &str) -> Option<&ModelEntry> {
+        if let Some(entry) = self.aliases.get(name) {
+            return Some(entry);
+        }
+        self.aliases
+            .iter()
+            .filter(|(_, entry)| entry.model == name)
+            .min_by_key(|(alias, _)| alias.as_str())
+            .map(|(_, entry)| entry)
+    }
+
+    /// The canonical model an alias stands for; other names are returned unchanged
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, |entry| entry.model.as_str())
+    }
+
+    /// Aliases and their entries, sorted by alias
+    pub fn aliases(&self) -> Vec<(&str, &ModelEntry)> {
+        let mut aliases: Vec<(&str, &ModelEntry)> = self
+            .aliases
+            .iter()
+            .map(|(alias, entry)| (alias.as_str(), entry))
+            .collect();
+        aliases.sort_by_key(|(alias, _)| *alias);
+        aliases
+    }
+
+    /// A warning for each alias whose pricing entry `pricing` lacks
+    ///
+    /// Costs of such models are recorded as 0.
+    pub fn pricing_warnings(&self, pricing: &PricingTable) -> Vec<String> {
+        self.aliases()
+            .into_iter()
+            .filter(|(_, entry)| pricing.get_pricing(&entry.tool, entry.pricing_model()).is_none())
+            .map(|(alias, entry)| {
+                format!(
+                    "models.{}: no pricing for '{}'; its cost will be recorded as 0",
+                    alias,
+                    entry.pricing_model()
+                )
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,7 @@
-use prometheus::{Counter, Histogram, Gauge, Registry, Encoder, TextEncoder};
+use prometheus::proto::MetricFamily;
+use prometheus::{Counter, CounterVec, Histogram, HistogramVec, Gauge, GaugeVec, Registry, Encoder, TextEncoder};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +37,12 @@ pub struct MetricsCollector {
     request_tokens_output: Counter,
     error_counter: Counter,
     active_requests: Gauge,
+    rate_limiter_waits: CounterVec,
+    rate_limiter_rejections: CounterVec,
+    rate_limiter_waiting: GaugeVec,
+    db_query_duration: HistogramVec,
+    timeouts: CounterVec,
+    circuit_breaker_trips: CounterVec,
 }
 
 impl MetricsCollector {
@@ -72,6 +79,37 @@ impl MetricsCollector {
             prometheus::Opts::new("uai_active_requests", "Number of active requests")
         ).unwrap();
         
+        let rate_limiter_waits = CounterVec::new(
+            prometheus::Opts::new("uai_rate_limiter_waits_total", "Acquisitions that waited for rate limiter tokens"),
+            &["limiter"],
+        ).unwrap();
+        
+        let rate_limiter_rejections = CounterVec::new(
+            prometheus::Opts::new("uai_rate_limiter_rejections_total", "Requests rejected by a rate limiter"),
+            &["limiter"],
+        ).unwrap();
+        
+        let rate_limiter_waiting = GaugeVec::new(
+            prometheus::Opts::new("uai_rate_limiter_waiting", "Tasks waiting for rate limiter tokens"),
+            &["limiter"],
+        ).unwrap();
+        
+        let db_query_duration = HistogramVec::new(
+            prometheus::HistogramOpts::new("uai_db_query_duration_seconds", "Database query duration in seconds by statement fingerprint")
+                .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]),
+            &["fingerprint"],
+        ).unwrap();
+        
+        let timeouts = CounterVec::new(
+            prometheus::Opts::new("uai_timeouts_total", "Operations that ran out of time"),
+            &["operation"],
+        ).unwrap();
+        
+        let circuit_breaker_trips = CounterVec::new(
+            prometheus::Opts::new("uai_circuit_breaker_trips_total", "Times a circuit breaker opened"),
+            &["breaker"],
+        ).unwrap();
+        
         registry.register(Box::new(request_counter.clone())).unwrap();
         registry.register(Box::new(request_duration.clone())).unwrap();
         registry.register(Box::new(request_cost.clone())).unwrap();
@@ -79,6 +117,12 @@ impl MetricsCollector {
         registry.register(Box::new(request_tokens_output.clone())).unwrap();
         registry.register(Box::new(error_counter.clone())).unwrap();
         registry.register(Box::new(active_requests.clone())).unwrap();
+        registry.register(Box::new(rate_limiter_waits.clone())).unwrap();
+        registry.register(Box::new(rate_limiter_rejections.clone())).unwrap();
+        registry.register(Box::new(rate_limiter_waiting.clone())).unwrap();
+        registry.register(Box::new(db_query_duration.clone())).unwrap();
+        registry.register(Box::new(timeouts.clone())).unwrap();
+        registry.register(Box::new(circuit_breaker_trips.clone())).unwrap();
         
         Self {
             registry: Arc::new(registry),
@@ -89,10 +133,17 @@ impl MetricsCollector {
             request_tokens_output,
             error_counter,
             active_requests,
+            rate_limiter_waits,
+            rate_limiter_rejections,
+            rate_limiter_waiting,
+            db_query_duration,
+            timeouts,
+            circuit_breaker_trips,
         }
     }
     
     pub fn record_request(&self, metrics: RequestMetrics) {
+        let _span = tracing::info_span!("record_request", request_id = %metrics.request_id, tool = %metrics.tool).entered();
         let labels = &[metrics.tool.as_str()];
         
         self.request_counter.inc();
@@ -123,10 +174,43 @@ impl MetricsCollector {
         self.active_requests.dec();
     }
     
+    pub fn record_rate_limiter_wait(&self, limiter: &str) {
+        self.rate_limiter_waits.with_label_values(&[limiter]).inc();
+    }
+    
+    pub fn record_rate_limiter_rejection(&self, limiter: &str) {
+        self.rate_limiter_rejections.with_label_values(&[limiter]).inc();
+    }
+    
+    pub fn increment_rate_limiter_waiting(&self, limiter: &str) {
+        self.rate_limiter_waiting.with_label_values(&[limiter]).inc();
+    }
+    
+    pub fn decrement_rate_limiter_waiting(&self, limiter: &str) {
+        self.rate_limiter_waiting.with_label_values(&[limiter]).dec();
+    }
+    
+    pub fn record_db_query(&self, fingerprint: &str, duration: Duration) {
+        self.db_query_duration.with_label_values(&[fingerprint]).observe(duration.as_secs_f64());
+    }
+    
+    pub fn record_timeout(&self, operation: &str) {
+        self.timeouts.with_label_values(&[operation]).inc();
+    }
+    
+    pub fn record_circuit_breaker_trip(&self, breaker: &str) {
+        self.circuit_breaker_trips.with_label_values(&[breaker]).inc();
+    }
+    
+    /// Current values of every registered metric
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+    
     pub fn export(&self) -> String {
         let encoder = TextEncoder::new();
         let mut buffer = Vec::new();
-        encoder.encode(&self.registry.gather(), &mut buffer).unwrap();
+        encoder.encode(&self.gather(), &mut buffer).unwrap();
         String::from_utf8(buffer).unwrap()
     }
     
@@ -1,7 +1,11 @@
 pub mod logging;
 pub mod metrics;
+pub mod request;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod tracing;
 
 pub use logging::setup_logging;
 pub use metrics::{MetricsCollector, RequestMetrics, ToolStats};
+pub use request::{ensure_request_id, request_id};
 pub use tracing::setup_tracing;
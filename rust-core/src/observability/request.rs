@@ -0,0 +1,16 @@
+/// Request IDs tying routing, context, tool calls, cost and metrics together
+
+use uuid::Uuid;
+
+/// A new request ID: a UUID v7, so IDs sort by creation time
+pub fn request_id() -> String {
+    Uuid::now_v7().to_string()
+}
+
+/// `existing`, or a new ID when the caller did not supply one
+pub fn ensure_request_id(existing: Option<&str>) -> String {
+    match existing {
+        Some(id) if !id.is_empty() => id.to_string(),
+        _ => request_id(),
+    }
+}
@@ -0,0 +1,270 @@
+/// In-memory metrics and span capture for tests
+///
+/// Nothing here touches the global subscriber or the `OnceLock`s behind
+/// `setup_logging` and `setup_tracing`: metrics go to a private
+/// `MetricsCollector`, and spans are captured by a subscriber installed as
+/// the thread's default for as long as a `CaptureGuard` lives, or for one
+/// closure with `CapturingSpanExporter::with_default`. Thread-local
+/// defaults only see work on the installing thread, so async tests should
+/// use the current-thread runtime `#[tokio::test]` gives by default.
+///
+/// Compiled for the crate's own tests and with the `testing` feature.
+
+use super::MetricsCollector;
+use prometheus::proto::MetricType;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::DefaultGuard;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// A `MetricsCollector` whose values can be read back
+#[derive(Clone, Default)]
+pub struct CapturingMetrics {
+    collector: MetricsCollector,
+}
+
+/// One sample, named as in the Prometheus text format
+///
+/// Histograms give a `_count` and a `_sum` sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSample {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+/// Every sample at the time of `CapturingMetrics::snapshot`
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub samples: Vec<MetricSample>,
+}
+
+impl CapturingMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The collector to hand to the code under test; clones share values
+    pub fn collector(&self) -> MetricsCollector {
+        self.collector.clone()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut samples = Vec::new();
+        for family in self.collector.gather() {
+            for metric in family.get_metric() {
+                let labels: BTreeMap<String, String> = metric
+                    .get_label()
+                    .iter()
+                    .map(|pair| (pair.get_name().to_string(), pair.get_value().to_string()))
+                    .collect();
+                let mut push = |name: String, value: f64| {
+                    samples.push(MetricSample { name, labels: labels.clone(), value });
+                };
+                match family.get_field_type() {
+                    MetricType::COUNTER => push(family.get_name().to_string(), metric.get_counter().get_value()),
+                    MetricType::GAUGE => push(family.get_name().to_string(), metric.get_gauge().get_value()),
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        push(format!("{}_count", family.get_name()), histogram.get_sample_count() as f64);
+                        push(format!("{}_sum", family.get_name()), histogram.get_sample_sum());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        MetricsSnapshot { samples }
+    }
+}
+
+impl MetricsSnapshot {
+    /// Value of the sample with exactly these labels, if it was recorded
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        self.samples
+            .iter()
+            .find(|sample| {
+                sample.name == name
+                    && sample.labels.len() == labels.len()
+                    && labels.iter().all(|(key, value)| sample.labels.get(*key).map(String::as_str) == Some(*value))
+            })
+            .map(|sample| sample.value)
+    }
+
+    /// `get`, with a sample never recorded counting as 0
+    pub fn value(&self, name: &str, labels: &[(&str, &str)]) -> f64 {
+        self.get(name, labels).unwrap_or(0.0)
+    }
+}
+
+/// A span seen by `CapturingSpanExporter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedSpan {
+    pub name: String,
+    pub target: String,
+    pub parent: Option<String>, // Name of the enclosing span
+    pub fields: BTreeMap<String, String>, // Including those recorded after creation
+    pub closed: bool,
+}
+
+/// An event seen by `CapturingSpanExporter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedEvent {
+    pub level: Level,
+    pub target: String,
+    pub span: Option<String>, // Name of the span it was emitted in
+    pub fields: BTreeMap<String, String>,
+}
+
+impl CapturedEvent {
+    pub fn message(&self) -> Option<&str> {
+        self.field("message")
+    }
+
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Default)]
+struct Captured {
+    spans: Vec<CapturedSpan>,
+    open: HashMap<u64, usize>, // Span id to index in `spans`
+    events: Vec<CapturedEvent>,
+}
+
+/// Records spans and events in memory; clones share what was captured
+#[derive(Clone, Default)]
+pub struct CapturingSpanExporter {
+    captured: Arc<Mutex<Captured>>,
+}
+
+/// Keeps a `CapturingSpanExporter` installed on this thread until dropped
+pub struct CaptureGuard {
+    _default: DefaultGuard,
+}
+
+impl CaptureGuard {
+    /// Restore the subscriber that was the default before `install`
+    pub fn uninstall(self) {}
+}
+
+impl CapturingSpanExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture everything traced on this thread until the guard is dropped
+    pub fn install(&self) -> CaptureGuard {
+        CaptureGuard {
+            _default: tracing::subscriber::set_default(self.subscriber()),
+        }
+    }
+
+    /// Capture everything traced on this thread while `f` runs
+    pub fn with_default<T>(&self, f: impl FnOnce() -> T) -> T {
+        tracing::subscriber::with_default(self.subscriber(), f)
+    }
+
+    /// A subscriber exporting to this capture, for use with `tracing::subscriber` directly
+    pub fn subscriber(&self) -> impl Subscriber + Send + Sync + 'static {
+        tracing_subscriber::registry().with(self.clone())
+    }
+
+    pub fn spans(&self) -> Vec<CapturedSpan> {
+        self.captured.lock().unwrap().spans.clone()
+    }
+
+    pub fn spans_named(&self, name: &str) -> Vec<CapturedSpan> {
+        self.spans().into_iter().filter(|span| span.name == name).collect()
+    }
+
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.captured.lock().unwrap().events.clone()
+    }
+
+    pub fn events_at(&self, level: Level) -> Vec<CapturedEvent> {
+        self.events().into_iter().filter(|event| event.level == level).collect()
+    }
+
+    /// Forget what was captured so far; spans still open keep recording
+    pub fn clear(&self) {
+        let mut captured = self.captured.lock().unwrap();
+        captured.events.clear();
+        let open: Vec<(u64, CapturedSpan)> = captured
+            .open
+            .iter()
+            .map(|(id, index)| (*id, captured.spans[*index].clone()))
+            .collect();
+        captured.spans.clear();
+        captured.open.clear();
+        for (id, span) in open {
+            let index = captured.spans.len();
+            captured.open.insert(id, index);
+            captured.spans.push(span);
+        }
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for CapturingSpanExporter {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let parent = ctx.span(id).and_then(|span| span.parent()).map(|parent| parent.name().to_string());
+
+        let mut captured = self.captured.lock().unwrap();
+        let index = captured.spans.len();
+        captured.spans.push(CapturedSpan {
+            name: attrs.metadata().name().to_string(),
+            target: attrs.metadata().target().to_string(),
+            parent,
+            fields,
+            closed: false,
+        });
+        captured.open.insert(id.into_u64(), index);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut captured = self.captured.lock().unwrap();
+        if let Some(index) = captured.open.get(&id.into_u64()).copied() {
+            values.record(&mut FieldVisitor(&mut captured.spans[index].fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        let span = ctx.event_span(event).map(|span| span.name().to_string());
+
+        self.captured.lock().unwrap().events.push(CapturedEvent {
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            span,
+            fields,
+        });
+    }
+
+    fn on_close(&self, id: Id, _ctx: Context<'_, S>) {
+        let mut captured = self.captured.lock().unwrap();
+        if let Some(index) = captured.open.remove(&id.into_u64()) {
+            captured.spans[index].closed = true;
+        }
+    }
+}
@@ -0,0 +1,68 @@
+/// Cursor-based pagination shared by search and listing APIs
+
+use crate::error::{OrchestratorError, Result};
+
+/// One page of results
+///
+/// `next_cursor` is set when the page is full; passing it back returns the
+/// following page, which is empty once the results are exhausted.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Build a page, emitting a cursor from the last item if the page is full
+    pub(crate) fn from_items(items: Vec<T>, page_size: usize, cursor_for: impl Fn(&T) -> String) -> Self {
+        let next_cursor = if page_size > 0 && items.len() >= page_size {
+            items.last().map(cursor_for)
+        } else {
+            None
+        };
+        Self { items, next_cursor }
+    }
+}
+
+/// Encode cursor fields as an opaque token tagged with the listing it belongs to
+///
+/// The last field may contain any text; earlier fields must not contain '|'.
+pub(crate) fn encode_cursor(kind: &str, fields: &[String]) -> String {
+    let raw = std::iter::once(kind.to_string())
+        .chain(fields.iter().cloned())
+        .collect::<Vec<_>>()
+        .join("|");
+    raw.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a cursor made by `encode_cursor` for the same `kind`
+pub(crate) fn decode_cursor(kind: &str, cursor: &str, field_count: usize) -> Result<Vec<String>> {
+    let invalid = || OrchestratorError::InvalidInput(format!("Invalid {} cursor", kind));
+    
+    if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+        return Err(invalid());
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| invalid())?;
+    let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+    
+    let mut parts = raw.splitn(field_count + 1, '|');
+    if parts.next() != Some(kind) {
+        return Err(invalid());
+    }
+    let fields: Vec<String> = parts.map(str::to_string).collect();
+    if fields.len() != field_count {
+        return Err(invalid());
+    }
+    Ok(fields)
+}
+
+/// Parse a decoded cursor field, rejecting the cursor if it is malformed
+pub(crate) fn parse_field<T: std::str::FromStr>(kind: &str, field: &str) -> Result<T> {
+    field
+        .parse()
+        .map_err(|_| OrchestratorError::InvalidInput(format!("Invalid {} cursor", kind)))
+}
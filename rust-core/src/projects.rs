@@ -0,0 +1,254 @@
+/// Registry of projects and their default settings
+
+use crate::context::ContextManager;
+use crate::cost::CostStorage;
+use crate::error::{OrchestratorError, Result};
+use crate::indexer::storage::IndexStorage;
+use crate::router::{unknown_rule_keys, RULE_KEYS};
+use crate::security::validation::validate_identifier;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+
+/// Contexts listed per query when deleting a project's contexts
+const CASCADE_PAGE_SIZE: usize = 100;
+
+/// Per-project defaults read by the config layer's `build_project_*` methods
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    pub skip_patterns: Option<Vec<String>>, // Replaces the configured skip patterns when set
+    pub routing_overrides: HashMap<String, Vec<String>>, // Rule key (e.g. `code_editing`) -> tools, over the configured rules
+    pub budget_usd: Option<f64>, // Monthly budget; None for the configured one
+}
+
+impl ProjectSettings {
+    /// Fails with `InvalidInput` if `routing_overrides` has a key no task type is routed by
+    pub fn validate(&self) -> Result<()> {
+        let unknown = unknown_rule_keys(self.routing_overrides.keys());
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        Err(OrchestratorError::InvalidInput(format!(
+            "Unknown routing rule keys {} (expected one of {})",
+            unknown.join(", "),
+            RULE_KEYS.join(", ")
+        )))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub root_path: Option<String>,
+    pub created_at: i64, // Unix seconds
+    pub settings: ProjectSettings,
+}
+
+impl Project {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            root_path: None,
+            created_at: Utc::now().timestamp(),
+            settings: ProjectSettings::default(),
+        }
+    }
+
+    pub fn with_root_path(mut self, root_path: impl Into<String>) -> Self {
+        self.root_path = Some(root_path.into());
+        self
+    }
+
+    pub fn with_settings(mut self, settings: ProjectSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+/// Stores whose data for a project `ProjectStore::delete` also removes
+#[derive(Default, Clone, Copy)]
+pub struct ProjectCascade<'a> {
+    pub contexts: Option<&'a ContextManager>,
+    pub index: Option<&'a IndexStorage>,
+    pub costs: Option<&'a CostStorage>,
+}
+
+/// What `ProjectStore::delete` removed along with the project
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectDeletion {
+    pub contexts_deleted: usize,
+    pub files_removed: usize,
+    pub cost_records_deleted: u64,
+}
+
+/// The `projects` table
+///
+/// Managers given a store with `with_project_registry` reject project ids
+/// that are not registered here.
+#[derive(Clone)]
+pub struct ProjectStore {
+    pool: SqlitePool,
+}
+
+impl ProjectStore {
+    /// Use an existing pool; the `add_projects` migration must have run
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        let table: Option<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'projects'"
+        )
+        .fetch_optional(&pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+        if table.is_none() {
+            return Err(OrchestratorError::InvalidConfig(
+                "No projects table; run the database migrations first".to_string(),
+            ));
+        }
+
+        Ok(Self { pool })
+    }
+
+    /// Register a project; fails with `InvalidInput` if the id is taken or not a valid identifier
+    pub async fn create(&self, project: &Project) -> Result<()> {
+        validate_identifier(&project.id).map_err(|e| OrchestratorError::InvalidInput(e.to_string()))?;
+        project.settings.validate()?;
+        if self.exists(&project.id).await? {
+            return Err(OrchestratorError::InvalidInput(format!("Project {} already exists", project.id)));
+        }
+        let settings = serde_json::to_string(&project.settings).map_err(OrchestratorError::from)?;
+
+        sqlx::query(
+            "INSERT INTO projects (id, name, root_path, created_at, settings) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(&project.id)
+        .bind(&project.name)
+        .bind(&project.root_path)
+        .bind(project.created_at)
+        .bind(&settings)
+        .execute(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Project>> {
+        let row = sqlx::query_as::<_, ProjectRow>(
+            "SELECT id, name, root_path, created_at, settings FROM projects WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        row.map(project_from_row).transpose()
+    }
+
+    /// All projects, by id
+    pub async fn list(&self) -> Result<Vec<Project>> {
+        let rows = sqlx::query_as::<_, ProjectRow>(
+            "SELECT id, name, root_path, created_at, settings FROM projects ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        rows.into_iter().map(project_from_row).collect()
+    }
+
+    pub async fn exists(&self, id: &str) -> Result<bool> {
+        let (exists,): (bool,) = sqlx::query_as("SELECT COUNT(*) > 0 FROM projects WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+        Ok(exists)
+    }
+
+    /// Fail with `InvalidInput` unless `id` is a registered project
+    pub async fn ensure_exists(&self, id: &str) -> Result<()> {
+        if self.exists(id).await? {
+            return Ok(());
+        }
+        Err(OrchestratorError::InvalidInput(format!("Unknown project: {}", id)))
+    }
+
+    /// Replace a project's name, root path and settings; false if it does not exist
+    pub async fn update(&self, project: &Project) -> Result<bool> {
+        project.settings.validate()?;
+        let settings = serde_json::to_string(&project.settings).map_err(OrchestratorError::from)?;
+        let result = sqlx::query(
+            "UPDATE projects SET name = ?, root_path = ?, settings = ? WHERE id = ?"
+        )
+        .bind(&project.name)
+        .bind(&project.root_path)
+        .bind(&settings)
+        .bind(&project.id)
+        .execute(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a project, and its data from each store in `cascade`
+    ///
+    /// Returns None if the project does not exist, in which case nothing
+    /// else is touched. Data is removed before the project row, so a failed
+    /// cascade can be retried.
+    pub async fn delete(&self, id: &str, cascade: ProjectCascade<'_>) -> Result<Option<ProjectDeletion>> {
+        if !self.exists(id).await? {
+            return Ok(None);
+        }
+
+        let mut deletion = ProjectDeletion::default();
+        if let Some(contexts) = cascade.contexts {
+            let mut conversation_ids = Vec::new();
+            let mut cursor = None;
+            loop {
+                let page = contexts.list_contexts(Some(id), None, CASCADE_PAGE_SIZE, cursor).await?;
+                conversation_ids.extend(page.items.into_iter().map(|c| c.conversation_id));
+                cursor = match page.next_cursor {
+                    Some(next) => Some(next),
+                    None => break,
+                };
+            }
+            for conversation_id in conversation_ids {
+                if contexts.delete_context(&conversation_id).await? {
+                    deletion.contexts_deleted += 1;
+                }
+            }
+        }
+        if let Some(index) = cascade.index {
+            deletion.files_removed = index.remove_files_with_prefix(id, "").await?;
+        }
+        if let Some(costs) = cascade.costs {
+            deletion.cost_records_deleted = costs.delete_project_records(id).await?;
+        }
+
+        sqlx::query("DELETE FROM projects WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(OrchestratorError::from)?;
+
+        Ok(Some(deletion))
+    }
+}
+
+/// (id, name, root_path, created_at, settings JSON)
+type ProjectRow = (String, String, Option<String>, i64, String);
+
+fn project_from_row((id, name, root_path, created_at, settings): ProjectRow) -> Result<Project> {
+    Ok(Project {
+        id,
+        name,
+        root_path,
+        created_at,
+        settings: serde_json::from_str(&settings).map_err(OrchestratorError::from)?,
+    })
+}
@@ -0,0 +1,121 @@
+use crate::error::{OrchestratorError, Result};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Snapshot of a bulkhead's load
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkheadStats {
+    pub max_concurrent: usize,
+    pub max_queue: usize,
+    /// Permits currently held
+    pub in_flight: usize,
+    /// Callers waiting for a permit
+    pub queued: usize,
+    /// Calls rejected because the bulkhead and its queue were full
+    pub rejected: u64,
+}
+
+#[derive(Debug)]
+struct BulkheadInner {
+    name: String,
+    max_concurrent: usize,
+    max_queue: usize,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Caps concurrent calls so one slow dependency can't take every worker
+#[derive(Debug, Clone)]
+pub struct Bulkhead {
+    inner: Arc<BulkheadInner>,
+}
+
+/// Held while a call runs; releases its slot when dropped
+#[derive(Debug)]
+pub struct BulkheadPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Frees a queue slot when the waiter gets a permit or gives up
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Bulkhead {
+    pub fn new(name: impl Into<String>, max_concurrent: usize, max_queue: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            inner: Arc::new(BulkheadInner {
+                name: name.into(),
+                max_concurrent,
+                max_queue,
+                semaphore: Arc::new(Semaphore::new(max_concurrent)),
+                queued: AtomicUsize::new(0),
+                rejected: AtomicU64::new(0),
+            }),
+        }
+    }
+    
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+    
+    /// Wait for a slot, queueing behind other callers
+    ///
+    /// Fails with `BulkheadFull` if all slots are taken and `max_queue`
+    /// callers are already waiting.
+    pub async fn acquire(&self) -> Result<BulkheadPermit> {
+        if let Ok(permit) = self.inner.semaphore.clone().try_acquire_owned() {
+            return Ok(BulkheadPermit { _permit: permit });
+        }
+        
+        let max_queue = self.inner.max_queue;
+        let reserved = self.inner.queued.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+            (queued < max_queue).then_some(queued + 1)
+        });
+        if reserved.is_err() {
+            return Err(self.reject());
+        }
+        
+        let _slot = QueueSlot(&self.inner.queued);
+        let permit = self.inner.semaphore.clone()
+            .acquire_owned()
+            .await
+            .expect("bulkhead semaphore is never closed");
+        Ok(BulkheadPermit { _permit: permit })
+    }
+    
+    /// Take a slot only if one is free right now
+    pub fn try_acquire(&self) -> Result<BulkheadPermit> {
+        match self.inner.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => Ok(BulkheadPermit { _permit: permit }),
+            Err(_) => Err(self.reject()),
+        }
+    }
+    
+    pub fn stats(&self) -> BulkheadStats {
+        BulkheadStats {
+            max_concurrent: self.inner.max_concurrent,
+            max_queue: self.inner.max_queue,
+            in_flight: self.inner.max_concurrent - self.inner.semaphore.available_permits(),
+            queued: self.inner.queued.load(Ordering::SeqCst),
+            rejected: self.inner.rejected.load(Ordering::SeqCst),
+        }
+    }
+    
+    fn reject(&self) -> OrchestratorError {
+        self.inner.rejected.fetch_add(1, Ordering::SeqCst);
+        OrchestratorError::BulkheadFull(format!(
+            "Bulkhead {} is full ({} in flight, {} queued)",
+            self.inner.name,
+            self.inner.max_concurrent,
+            self.inner.queued.load(Ordering::SeqCst)
+        ))
+    }
+}
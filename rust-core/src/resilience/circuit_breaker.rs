@@ -1,4 +1,6 @@
 use crate::error::{OrchestratorError, Result};
+use crate::observability::MetricsCollector;
+use std::fmt;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -47,20 +49,24 @@ impl CircuitBreakerInner {
         }
     }
     
-    fn on_failure(&mut self) {
+    /// Count a failure; true if it opened the circuit
+    fn on_failure(&mut self) -> bool {
         self.failures += 1;
         self.last_failure_time = Some(Instant::now());
         
         match self.state {
             CircuitState::HalfOpen => {
                 self.state = CircuitState::Open;
+                true
             }
             CircuitState::Closed => {
                 if self.failures >= self.failure_threshold {
                     self.state = CircuitState::Open;
+                    return true;
                 }
+                false
             }
-            _ => {}
+            _ => false,
         }
     }
     
@@ -89,10 +95,24 @@ impl CircuitBreakerInner {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Fails calls fast after repeated failures
+///
+/// With a metrics collector attached every trip to open increments
+/// `uai_circuit_breaker_trips_total` labelled with the breaker's name.
+#[derive(Clone)]
 pub struct CircuitBreaker {
     inner: Arc<Mutex<CircuitBreakerInner>>,
     name: String,
+    metrics: Option<MetricsCollector>,
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("name", &self.name)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
 }
 
 impl CircuitBreaker {
@@ -100,13 +120,28 @@ impl CircuitBreaker {
         Self {
             inner: Arc::new(Mutex::new(CircuitBreakerInner::new(failure_threshold, timeout))),
             name: name.into(),
+            metrics: None,
         }
     }
     
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
     pub fn state(&self) -> CircuitState {
         self.inner.lock().unwrap().state
     }
     
+    /// Fail fast if the circuit is open, without making a call
+    pub fn check(&self) -> Result<()> {
+        self.inner.lock().unwrap().check_state()
+    }
+    
     pub async fn call<T, F, Fut>(&self, f: F) -> Result<T>
     where
         F: FnOnce() -> Fut,
@@ -125,8 +160,13 @@ impl CircuitBreaker {
                 Ok(result)
             }
             Err(e) => {
-                let mut inner = self.inner.lock().unwrap();
-                inner.on_failure();
+                let tripped = self.inner.lock().unwrap().on_failure();
+                if tripped {
+                    tracing::warn!(breaker = %self.name, error = %e, "circuit breaker opened");
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_circuit_breaker_trip(&self.name);
+                    }
+                }
                 Err(e)
             }
         }
@@ -0,0 +1,23 @@
+use super::{Bulkhead, CircuitBreaker, RateLimiter};
+use crate::error::Result;
+use std::future::Future;
+
+/// Run `fut` behind a circuit breaker, bulkhead and rate limiter
+///
+/// An open circuit fails fast before queueing for the bulkhead, and the rate
+/// limit token is only taken once a slot is held, so rejected calls never
+/// spend one. Only the outcome of `fut` itself counts towards the breaker.
+pub async fn guarded<T, Fut>(
+    bulkhead: &Bulkhead,
+    breaker: &CircuitBreaker,
+    limiter: &RateLimiter,
+    fut: Fut,
+) -> Result<T>
+where
+    Fut: Future<Output = Result<T>>,
+{
+    breaker.check()?;
+    let _permit = bulkhead.acquire().await?;
+    limiter.acquire(1).await?;
+    breaker.call(|| fut).await
+}
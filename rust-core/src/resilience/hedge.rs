@@ -0,0 +1,120 @@
+use crate::error::{OrchestratorError, Result};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+/// Which attempt of a hedged call produced the result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HedgeOutcome {
+    /// Index of the winning attempt; 0 is the primary, 1.. are backups
+    pub winner: usize,
+    /// Number of backup attempts that were started
+    pub backups_started: usize,
+}
+
+impl HedgeOutcome {
+    pub fn primary_won(&self) -> bool {
+        self.winner == 0
+    }
+    
+    pub fn backup_started(&self) -> bool {
+        self.backups_started > 0
+    }
+}
+
+/// Run `primary`, starting a backup if it hasn't succeeded within `delay`
+///
+/// Whichever attempt succeeds first wins and the other is dropped. A backup
+/// also starts straight away if the primary fails early. If both fail the
+/// errors are combined into `HedgeFailed`.
+pub async fn hedged_call<'a, T, P, B, F>(
+    primary: P,
+    backup_factory: F,
+    delay: Duration,
+) -> Result<(T, HedgeOutcome)>
+where
+    P: Future<Output = Result<T>> + Send + 'a,
+    B: Future<Output = Result<T>> + Send + 'a,
+    F: FnOnce() -> B + Send,
+    T: Send,
+{
+    hedged_call_n(primary, vec![backup_factory], delay).await
+}
+
+/// N-way hedging: start the next backup every `delay` until one succeeds
+///
+/// Backups start in order, each `delay` after the previous attempt, or
+/// immediately once every running attempt has failed.
+pub async fn hedged_call_n<'a, T, P, B, F>(
+    primary: P,
+    backup_factories: Vec<F>,
+    delay: Duration,
+) -> Result<(T, HedgeOutcome)>
+where
+    P: Future<Output = Result<T>> + Send + 'a,
+    B: Future<Output = Result<T>> + Send + 'a,
+    F: FnOnce() -> B + Send,
+    T: Send,
+{
+    // Boxed as Send so the hedged call can itself be spawned
+    type Attempt<'f, R> = (usize, Pin<Box<dyn Future<Output = Result<R>> + Send + 'f>>);
+    
+    let mut running: Vec<Attempt<'a, T>> = vec![(0, Box::pin(primary))];
+    let mut factories: VecDeque<F> = backup_factories.into();
+    let mut backups_started = 0;
+    let mut errors: Vec<(usize, OrchestratorError)> = Vec::new();
+    let hedge_timer = tokio::time::sleep(delay);
+    tokio::pin!(hedge_timer);
+    
+    std::future::poll_fn(|cx| loop {
+        let mut i = 0;
+        while i < running.len() {
+            match running[i].1.as_mut().poll(cx) {
+                Poll::Ready(Ok(value)) => {
+                    // Returning drops the remaining attempts, cancelling them
+                    let outcome = HedgeOutcome {
+                        winner: running[i].0,
+                        backups_started,
+                    };
+                    return Poll::Ready(Ok((value, outcome)));
+                }
+                Poll::Ready(Err(e)) => {
+                    let (index, _) = running.remove(i);
+                    errors.push((index, e));
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+        
+        if factories.is_empty() {
+            if running.is_empty() {
+                return Poll::Ready(Err(combine_errors(std::mem::take(&mut errors))));
+            }
+            return Poll::Pending;
+        }
+        
+        if !running.is_empty() && hedge_timer.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        
+        let factory = factories.pop_front().expect("checked non-empty");
+        backups_started += 1;
+        running.push((backups_started, Box::pin(factory())));
+        hedge_timer.as_mut().reset(tokio::time::Instant::now() + delay);
+    })
+    .await
+}
+
+fn combine_errors(mut errors: Vec<(usize, OrchestratorError)>) -> OrchestratorError {
+    errors.sort_by_key(|(index, _)| *index);
+    let messages: Vec<String> = errors
+        .iter()
+        .map(|(index, e)| match index {
+            0 => format!("primary: {}", e),
+            n => format!("backup {}: {}", n, e),
+        })
+        .collect();
+    OrchestratorError::HedgeFailed(messages.join("; "))
+}
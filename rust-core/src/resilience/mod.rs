@@ -1,7 +1,17 @@
 pub mod retry;
 pub mod circuit_breaker;
 pub mod rate_limiter;
+pub mod bulkhead;
+pub mod guarded;
+pub mod hedge;
+pub mod scheduler;
+pub mod timeout;
 
 pub use retry::{RetryPolicy, ExponentialBackoffRetry};
 pub use circuit_breaker::{CircuitBreaker, CircuitState};
-pub use rate_limiter::{RateLimiter, TokenBucket};
+pub use rate_limiter::{RateLimiter, RateLimiterConfig, RateLimiterStats, TokenBucket};
+pub use bulkhead::{Bulkhead, BulkheadPermit, BulkheadStats};
+pub use guarded::guarded;
+pub use hedge::{hedged_call, hedged_call_n, HedgeOutcome};
+pub use scheduler::{ClassStats, Priority, PriorityScheduler, SchedulerConfig, SchedulerPermit, SchedulerStats};
+pub use timeout::{with_timeout, TimeoutConfig, Timeouts};
@@ -1,98 +1,314 @@
 use crate::error::{OrchestratorError, Result};
+use crate::observability::MetricsCollector;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::Instrument;
+
+/// Token bucket settings
+#[derive(Debug, Clone)]
+pub struct RateLimiterConfig {
+    pub capacity: u32,
+    pub refill_rate: f64, // tokens per second
+    /// Tokens available at start; defaults to `capacity`
+    pub initial_tokens: u32,
+    /// Ramp the refill rate linearly from 0 to `refill_rate` over this period
+    pub warmup: Option<Duration>,
+}
+
+impl RateLimiterConfig {
+    pub fn new(capacity: u32, refill_rate: f64) -> Self {
+        Self {
+            capacity,
+            refill_rate,
+            initial_tokens: capacity,
+            warmup: None,
+        }
+    }
+    
+    pub fn with_initial_tokens(mut self, initial_tokens: u32) -> Self {
+        self.initial_tokens = initial_tokens.min(self.capacity);
+        self
+    }
+    
+    pub fn with_warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = Some(warmup).filter(|w| !w.is_zero());
+        self
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct TokenBucket {
     capacity: u32,
-    tokens: u32,
+    tokens: f64,
     refill_rate: f64, // tokens per second
+    warmup: Option<Duration>,
+    started: Instant,
     last_refill: Instant,
 }
 
 impl TokenBucket {
     pub fn new(capacity: u32, refill_rate: f64) -> Self {
+        Self::from_config(&RateLimiterConfig::new(capacity, refill_rate))
+    }
+    
+    pub fn from_config(config: &RateLimiterConfig) -> Self {
+        let now = Instant::now();
         Self {
-            capacity,
-            tokens: capacity,
-            refill_rate,
-            last_refill: Instant::now(),
+            capacity: config.capacity,
+            tokens: config.initial_tokens.min(config.capacity) as f64,
+            refill_rate: config.refill_rate,
+            warmup: config.warmup,
+            started: now,
+            last_refill: now,
+        }
+    }
+    
+    /// Tokens refilled between start and `elapsed` seconds after it
+    fn refilled_by(&self, elapsed: f64) -> f64 {
+        match self.warmup {
+            Some(warmup) => {
+                let warmup = warmup.as_secs_f64();
+                if elapsed <= warmup {
+                    self.refill_rate * elapsed * elapsed / (2.0 * warmup)
+                } else {
+                    self.refill_rate * (warmup / 2.0 + elapsed - warmup)
+                }
+            }
+            None => self.refill_rate * elapsed,
+        }
+    }
+    
+    /// Seconds after start at which `refilled_by` reaches `target`
+    fn elapsed_for(&self, target: f64) -> f64 {
+        match self.warmup {
+            Some(warmup) => {
+                let warmup = warmup.as_secs_f64();
+                let during_warmup = self.refill_rate * warmup / 2.0;
+                if target <= during_warmup {
+                    (2.0 * warmup * target / self.refill_rate).sqrt()
+                } else {
+                    warmup + (target - during_warmup) / self.refill_rate
+                }
+            }
+            None => target / self.refill_rate,
         }
     }
     
     fn refill(&mut self) {
         let now = Instant::now();
-        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
-        let tokens_to_add = (elapsed * self.refill_rate) as u32;
+        let before = self.last_refill.duration_since(self.started).as_secs_f64();
+        let after = now.duration_since(self.started).as_secs_f64();
         
-        if tokens_to_add > 0 {
-            self.tokens = (self.tokens + tokens_to_add).min(self.capacity);
-            self.last_refill = now;
-        }
+        let tokens_to_add = self.refilled_by(after) - self.refilled_by(before);
+        self.tokens = (self.tokens + tokens_to_add).min(self.capacity as f64);
+        self.last_refill = now;
     }
     
     pub fn try_acquire(&mut self, tokens: u32) -> bool {
         self.refill();
-        if self.tokens >= tokens {
-            self.tokens -= tokens;
+        if self.tokens >= tokens as f64 {
+            self.tokens -= tokens as f64;
             true
         } else {
             false
         }
     }
     
+    /// Tokens currently available, rounded down
+    pub fn available(&mut self) -> u32 {
+        self.refill();
+        self.tokens as u32
+    }
+    
     pub fn wait_time(&self) -> Duration {
-        if self.tokens >= 1 {
-            Duration::ZERO
-        } else {
-            let tokens_needed = 1.0 - self.tokens as f64;
-            let seconds = tokens_needed / self.refill_rate;
-            Duration::from_secs_f64(seconds)
+        self.wait_time_for(1)
+    }
+    
+    /// Time until `tokens` are available, assuming no other consumers
+    pub fn wait_time_for(&self, tokens: u32) -> Duration {
+        let tokens_needed = tokens as f64 - self.tokens;
+        if tokens_needed <= 0.0 {
+            return Duration::ZERO;
         }
+        
+        let elapsed = self.last_refill.duration_since(self.started).as_secs_f64();
+        let ready_at = self.elapsed_for(self.refilled_by(elapsed) + tokens_needed);
+        Duration::from_secs_f64((ready_at - elapsed).max(0.0))
     }
 }
 
-#[derive(Debug, Clone)]
+/// Counters since the limiter was created
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimiterStats {
+    /// Successful acquisitions, waited for or not
+    pub acquired: u64,
+    /// `try_acquire` calls refused for lack of tokens
+    pub rejected: u64,
+    /// `acquire_with_deadline` calls that gave up
+    pub deadline_exceeded: u64,
+    /// Time `acquire` spent waiting for tokens
+    pub total_wait_time: Duration,
+    /// Tasks currently sleeping in `acquire` until tokens refill
+    pub waiting: usize,
+}
+
+#[derive(Clone)]
 pub struct RateLimiter {
     bucket: Arc<Mutex<TokenBucket>>,
+    stats: Arc<Mutex<RateLimiterStats>>,
+    waiting: Arc<AtomicUsize>,
+    metrics: Option<MetricsCollector>,
     name: String,
 }
 
+/// Counts a task as waiting until it has its tokens or gives up
+struct Waiter<'a>(&'a RateLimiter);
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.0.waiting.fetch_sub(1, Ordering::SeqCst);
+        if let Some(metrics) = &self.0.metrics {
+            metrics.decrement_rate_limiter_waiting(&self.0.name);
+        }
+    }
+}
+
 impl RateLimiter {
     pub fn new(name: impl Into<String>, capacity: u32, refill_rate: f64) -> Self {
+        Self::from_config(name, RateLimiterConfig::new(capacity, refill_rate))
+    }
+    
+    pub fn from_config(name: impl Into<String>, config: RateLimiterConfig) -> Self {
         Self {
-            bucket: Arc::new(Mutex::new(TokenBucket::new(capacity, refill_rate))),
+            bucket: Arc::new(Mutex::new(TokenBucket::from_config(&config))),
+            stats: Arc::new(Mutex::new(RateLimiterStats::default())),
+            waiting: Arc::new(AtomicUsize::new(0)),
+            metrics: None,
             name: name.into(),
         }
     }
     
+    /// Report waits, waiters and rejections to a metrics collector
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    pub fn stats(&self) -> RateLimiterStats {
+        let mut stats = self.stats.lock().unwrap().clone();
+        stats.waiting = self.waiting.load(Ordering::SeqCst);
+        stats
+    }
+    
+    /// Wait until `tokens` are available and take them
+    ///
+    /// Runs in a `rate_limiter_acquire` span recording the limiter, the
+    /// tokens requested and the total wait in `wait_ms`.
     pub async fn acquire(&self, tokens: u32) -> Result<()> {
+        self.acquire_until(tokens, None).await
+    }
+    
+    /// Like `acquire`, but fails with `RateLimitExceeded` rather than wait past `deadline`
+    ///
+    /// Gives up as soon as the tokens cannot refill by the deadline, emitting
+    /// a "rate limiter deadline exceeded" warning.
+    pub async fn acquire_with_deadline(&self, tokens: u32, deadline: Instant) -> Result<()> {
+        self.acquire_until(tokens, Some(deadline)).await
+    }
+    
+    async fn acquire_until(&self, tokens: u32, deadline: Option<Instant>) -> Result<()> {
+        let span = tracing::info_span!(
+            "rate_limiter_acquire",
+            limiter = %self.name,
+            tokens,
+            wait_ms = tracing::field::Empty
+        );
+        self.wait_for_tokens(tokens, deadline).instrument(span).await
+    }
+    
+    async fn wait_for_tokens(&self, tokens: u32, deadline: Option<Instant>) -> Result<()> {
+        let mut waited = Duration::ZERO;
         loop {
-            let acquired = {
+            let wait_time = {
                 let mut bucket = self.bucket.lock().unwrap();
-                bucket.try_acquire(tokens)
+                if bucket.try_acquire(tokens) {
+                    None
+                } else {
+                    Some(bucket.wait_time_for(tokens))
+                }
             };
             
-            if acquired {
-                return Ok(());
-            }
-            
-            let wait_time = {
-                let bucket = self.bucket.lock().unwrap();
-                bucket.wait_time()
+            let wait_time = match wait_time {
+                Some(wait_time) => wait_time,
+                None => break,
             };
             
+            if let Some(deadline) = deadline {
+                if Instant::now() + wait_time > deadline {
+                    tracing::Span::current().record("wait_ms", waited.as_millis() as u64);
+                    tracing::warn!(
+                        limiter = %self.name,
+                        tokens,
+                        wait_ms = waited.as_millis() as u64,
+                        "rate limiter deadline exceeded"
+                    );
+                    {
+                        let mut stats = self.stats.lock().unwrap();
+                        stats.deadline_exceeded += 1;
+                        stats.total_wait_time += waited;
+                    }
+                    return Err(OrchestratorError::RateLimitExceeded(
+                        format!("Rate limit for {} not available before the deadline", self.name)
+                    ));
+                }
+            }
+            
             if wait_time > Duration::ZERO {
+                let _waiter = self.start_waiting();
+                let start = Instant::now();
                 tokio::time::sleep(wait_time).await;
+                waited += start.elapsed();
+            } else {
+                tokio::task::yield_now().await;
             }
         }
+        
+        tracing::Span::current().record("wait_ms", waited.as_millis() as u64);
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.acquired += 1;
+            stats.total_wait_time += waited;
+        }
+        if !waited.is_zero() {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_rate_limiter_wait(&self.name);
+            }
+        }
+        Ok(())
+    }
+    
+    fn start_waiting(&self) -> Waiter<'_> {
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        if let Some(metrics) = &self.metrics {
+            metrics.increment_rate_limiter_waiting(&self.name);
+        }
+        Waiter(self)
     }
     
     pub fn try_acquire(&self, tokens: u32) -> Result<()> {
-        let mut bucket = self.bucket.lock().unwrap();
-        if bucket.try_acquire(tokens) {
+        let acquired = self.bucket.lock().unwrap().try_acquire(tokens);
+        
+        let mut stats = self.stats.lock().unwrap();
+        if acquired {
+            stats.acquired += 1;
             Ok(())
         } else {
+            stats.rejected += 1;
+            if let Some(metrics) = &self.metrics {
+                metrics.record_rate_limiter_rejection(&self.name);
+            }
             Err(OrchestratorError::RateLimitExceeded(
                 format!("Rate limit exceeded for {}", self.name)
             ))
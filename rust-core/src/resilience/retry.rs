@@ -41,7 +41,7 @@ impl RetryPolicy for ExponentialBackoffRetry {
             return false;
         }
         
-        match error {
+        match error.root_cause() {
             OrchestratorError::Network(_) => true,
             OrchestratorError::RateLimitExceeded(_) => true,
             OrchestratorError::Timeout(_) => true,
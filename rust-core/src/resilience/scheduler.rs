@@ -0,0 +1,329 @@
+use crate::error::{OrchestratorError, Result};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+/// Scheduling class of a request, highest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Priority {
+    /// A user is waiting on the result
+    Interactive,
+    Normal,
+    /// Indexing, batch summarization and other work nobody is waiting on
+    Background,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::Interactive, Priority::Normal, Priority::Background];
+    
+    pub fn parse(priority: &str) -> Result<Self> {
+        match priority.trim().to_lowercase().as_str() {
+            "interactive" => Ok(Priority::Interactive),
+            "normal" => Ok(Priority::Normal),
+            "background" => Ok(Priority::Background),
+            other => Err(OrchestratorError::InvalidInput(format!(
+                "Unknown priority: {} (expected interactive, normal or background)",
+                other
+            ))),
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Interactive => "interactive",
+            Priority::Normal => "normal",
+            Priority::Background => "background",
+        }
+    }
+    
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Slot limits for a `PriorityScheduler`
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Slots shared by all classes
+    pub max_concurrent: usize,
+    /// Per-class caps, indexed like `Priority::ALL`
+    pub class_limits: [usize; 3],
+    /// Background work queued this long is dispatched ahead of other classes
+    pub background_aging: Duration,
+}
+
+impl SchedulerConfig {
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            max_concurrent,
+            class_limits: [max_concurrent; 3],
+            background_aging: Duration::from_secs(30),
+        }
+    }
+    
+    pub fn with_class_limit(mut self, priority: Priority, limit: usize) -> Self {
+        self.class_limits[priority.index()] = limit.max(1);
+        self
+    }
+    
+    pub fn with_background_aging(mut self, aging: Duration) -> Self {
+        self.background_aging = aging;
+        self
+    }
+}
+
+/// Queued and running work for one class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassStats {
+    pub priority: Priority,
+    pub limit: usize,
+    pub queued: usize,
+    pub running: usize,
+    /// Slots granted so far
+    pub dispatched: u64,
+    /// Background slots granted ahead of other classes because of aging
+    pub aged: u64,
+}
+
+/// Snapshot of a scheduler's load
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerStats {
+    pub max_concurrent: usize,
+    pub running: usize,
+    pub classes: Vec<ClassStats>, // Ordered like `Priority::ALL`
+}
+
+struct Waiter {
+    id: u64,
+    enqueued_at: Instant,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct ClassState {
+    waiters: VecDeque<Waiter>,
+    running: usize,
+    dispatched: u64,
+    aged: u64,
+}
+
+struct SchedulerState {
+    classes: [ClassState; 3],
+    running: usize,
+    next_id: u64,
+}
+
+struct SchedulerInner {
+    config: SchedulerConfig,
+    state: Mutex<SchedulerState>,
+}
+
+/// Shares a fixed number of slots between priority classes
+///
+/// When a slot frees up it goes to the oldest waiter of the highest class
+/// that is under its own cap. Background work that has waited longer than
+/// `background_aging` is served first so a steady interactive load cannot
+/// starve it.
+#[derive(Clone)]
+pub struct PriorityScheduler {
+    inner: Arc<SchedulerInner>,
+}
+
+/// Held while scheduled work runs; frees its slot when dropped
+pub struct SchedulerPermit {
+    inner: Arc<SchedulerInner>,
+    priority: Priority,
+}
+
+impl SchedulerPermit {
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        self.inner.release(self.priority);
+    }
+}
+
+/// Removes a waiter that gives up, or frees the slot it was just granted
+struct PendingSlot<'a> {
+    inner: &'a SchedulerInner,
+    priority: Priority,
+    id: u64,
+    done: bool,
+}
+
+impl Drop for PendingSlot<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let mut state = self.inner.state.lock().unwrap();
+        let waiters = &mut state.classes[self.priority.index()].waiters;
+        match waiters.iter().position(|w| w.id == self.id) {
+            Some(pos) => {
+                waiters.remove(pos);
+            }
+            None => {
+                drop(state);
+                self.inner.release(self.priority);
+            }
+        }
+    }
+}
+
+impl SchedulerInner {
+    /// Hand free slots to waiters; called with the state locked
+    fn dispatch(&self, state: &mut SchedulerState) {
+        let now = Instant::now();
+        while state.running < self.config.max_concurrent {
+            let (priority, aged) = match self.next_class(state, now) {
+                Some(next) => next,
+                None => break,
+            };
+            let class = &mut state.classes[priority.index()];
+            let waiter = class.waiters.pop_front().expect("next_class only picks non-empty classes");
+            class.running += 1;
+            class.dispatched += 1;
+            if aged {
+                class.aged += 1;
+            }
+            state.running += 1;
+            // A dropped receiver means the waiter is leaving; its PendingSlot frees the slot
+            let _ = waiter.grant.send(());
+        }
+    }
+    
+    fn next_class(&self, state: &SchedulerState, now: Instant) -> Option<(Priority, bool)> {
+        let has_room = |priority: Priority| {
+            let class = &state.classes[priority.index()];
+            !class.waiters.is_empty() && class.running < self.config.class_limits[priority.index()]
+        };
+        
+        let background = &state.classes[Priority::Background.index()];
+        if has_room(Priority::Background)
+            && now.duration_since(background.waiters[0].enqueued_at) >= self.config.background_aging
+        {
+            let outranked = Priority::ALL[..2].iter().any(|p| has_room(*p));
+            return Some((Priority::Background, outranked));
+        }
+        Priority::ALL.into_iter().find(|p| has_room(*p)).map(|p| (p, false))
+    }
+    
+    fn release(&self, priority: Priority) {
+        let mut state = self.state.lock().unwrap();
+        state.classes[priority.index()].running -= 1;
+        state.running -= 1;
+        self.dispatch(&mut state);
+    }
+}
+
+impl PriorityScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            inner: Arc::new(SchedulerInner {
+                config,
+                state: Mutex::new(SchedulerState {
+                    classes: Default::default(),
+                    running: 0,
+                    next_id: 0,
+                }),
+            }),
+        }
+    }
+    
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.inner.config
+    }
+    
+    /// Wait for a slot in the given class
+    ///
+    /// Dropping the returned future while it waits gives up the place in
+    /// the queue.
+    pub async fn acquire(&self, priority: Priority) -> SchedulerPermit {
+        let (grant, granted) = oneshot::channel();
+        let id = {
+            let mut state = self.inner.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.classes[priority.index()].waiters.push_back(Waiter {
+                id,
+                enqueued_at: Instant::now(),
+                grant,
+            });
+            self.inner.dispatch(&mut state);
+            id
+        };
+        
+        let mut pending = PendingSlot {
+            inner: &self.inner,
+            priority,
+            id,
+            done: false,
+        };
+        granted.await.expect("waiters are only removed by dispatch or their own PendingSlot");
+        pending.done = true;
+        SchedulerPermit {
+            inner: self.inner.clone(),
+            priority,
+        }
+    }
+    
+    /// Take a slot only if one is free and no waiter of this or a higher class is queued
+    pub fn try_acquire(&self, priority: Priority) -> Option<SchedulerPermit> {
+        let mut state = self.inner.state.lock().unwrap();
+        let queued_ahead = Priority::ALL[..=priority.index()]
+            .iter()
+            .any(|p| !state.classes[p.index()].waiters.is_empty());
+        let class = &state.classes[priority.index()];
+        if queued_ahead
+            || state.running >= self.inner.config.max_concurrent
+            || class.running >= self.inner.config.class_limits[priority.index()]
+        {
+            return None;
+        }
+        
+        let class = &mut state.classes[priority.index()];
+        class.running += 1;
+        class.dispatched += 1;
+        state.running += 1;
+        Some(SchedulerPermit {
+            inner: self.inner.clone(),
+            priority,
+        })
+    }
+    
+    /// Run `fut` once a slot in `priority` is free
+    pub async fn submit<F: Future>(&self, priority: Priority, fut: F) -> F::Output {
+        let _permit = self.acquire(priority).await;
+        fut.await
+    }
+    
+    pub fn stats(&self) -> SchedulerStats {
+        let state = self.inner.state.lock().unwrap();
+        SchedulerStats {
+            max_concurrent: self.inner.config.max_concurrent,
+            running: state.running,
+            classes: Priority::ALL
+                .iter()
+                .map(|p| {
+                    let class = &state.classes[p.index()];
+                    ClassStats {
+                        priority: *p,
+                        limit: self.inner.config.class_limits[p.index()],
+                        queued: class.waiters.len(),
+                        running: class.running,
+                        dispatched: class.dispatched,
+                        aged: class.aged,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::{OrchestratorError, Result};
+use crate::observability::MetricsCollector;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+/// How long tool calls may take, in seconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub default_secs: u64, // For tools missing from `tools`
+    pub tools: HashMap<String, u64>, // Tool name -> seconds, e.g. claude = 300
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            default_secs: 120,
+            tools: HashMap::new(),
+        }
+    }
+}
+
+impl TimeoutConfig {
+    /// The timeout configured for `tool`, or the default
+    pub fn for_tool(&self, tool: &str) -> Duration {
+        Duration::from_secs(self.tools.get(tool).copied().unwrap_or(self.default_secs))
+    }
+}
+
+/// Run `fut`, failing with `Timeout` if it takes longer than `duration`
+///
+/// The error names the operation and the configured duration. `fut` is
+/// dropped when the time runs out.
+pub async fn with_timeout<T, Fut>(name: &str, duration: Duration, fut: Fut) -> Result<T>
+where
+    Fut: Future<Output = Result<T>>,
+{
+    tokio::time::timeout(duration, fut)
+        .await
+        .unwrap_or_else(|_| Err(timed_out(name, duration)))
+}
+
+fn timed_out(name: &str, duration: Duration) -> OrchestratorError {
+    OrchestratorError::Timeout(format!("{} did not complete within the configured {:?}", name, duration))
+}
+
+/// Per-tool timeouts from configuration
+///
+/// With a metrics collector attached every timeout increments
+/// `uai_timeouts_total` labelled with the operation.
+#[derive(Clone, Default)]
+pub struct Timeouts {
+    config: TimeoutConfig,
+    metrics: Option<MetricsCollector>,
+}
+
+impl Timeouts {
+    pub fn new(config: TimeoutConfig) -> Self {
+        Self { config, metrics: None }
+    }
+    
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    pub fn config(&self) -> &TimeoutConfig {
+        &self.config
+    }
+    
+    pub fn for_tool(&self, tool: &str) -> Duration {
+        self.config.for_tool(tool)
+    }
+    
+    /// `with_timeout` using the timeout configured for `tool`
+    pub async fn run<T, Fut>(&self, tool: &str, fut: Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run_for(tool, self.for_tool(tool), fut).await
+    }
+    
+    /// `with_timeout` with an explicit duration, still counted in metrics
+    pub async fn run_for<T, Fut>(&self, name: &str, duration: Duration, fut: Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        // Timeouts returned by `fut` itself are not counted
+        match tokio::time::timeout(duration, fut).await {
+            Ok(result) => result,
+            Err(_) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_timeout(name);
+                }
+                Err(timed_out(name, duration))
+            }
+        }
+    }
+}
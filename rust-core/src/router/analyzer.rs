@@ -1,4 +1,6 @@
+use crate::indexer::parser::ASTParser;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TaskType {
@@ -10,53 +12,343 @@ pub enum TaskType {
     Unknown,
 }
 
+impl TaskType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskType::CodeEditing => "code_editing",
+            TaskType::Research => "research",
+            TaskType::GeneralChat => "general_chat",
+            TaskType::CodeGeneration => "code_generation",
+            TaskType::TerminalAutomation => "terminal_automation",
+            TaskType::Unknown => "unknown",
+        }
+    }
+}
+
+/// How a message was classified
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestAnalysis {
+    pub task_type: TaskType,
+    /// Keywords of the chosen task type found in the message
+    pub keyword_hits: Vec<String>,
+    /// Share of all keyword hits that belong to the chosen task type; 0 when nothing matched
+    pub confidence: f64,
+}
+
+const CODE_KEYWORDS: &[&str] = &[
+    "refactor", "edit", "fix", "bug", "function", "class", "import",
+    "code", "file", "module", "package", "syntax", "error", "compile",
+    "test", "debug", "implement", "rewrite", "optimize",
+];
+
+const RESEARCH_KEYWORDS: &[&str] = &[
+    "research", "find", "search", "what is", "explain", "how does",
+    "information", "article", "paper", "source", "citation", "reference",
+    "learn about", "tell me about", "investigate",
+];
+
+const TERMINAL_KEYWORDS: &[&str] = &[
+    "run", "execute", "command", "terminal", "shell", "script",
+    "automate", "workflow", "cli", "bash", "zsh",
+];
+
+const GENERATION_KEYWORDS: &[&str] = &[
+    "generate", "create", "write", "make", "build", "new",
+    "scaffold", "boilerplate", "template",
+];
+
+/// Task types in the order they are checked
+const CLASSIFIERS: &[(TaskType, &[&str])] = &[
+    (TaskType::CodeEditing, CODE_KEYWORDS),
+    (TaskType::Research, RESEARCH_KEYWORDS),
+    (TaskType::TerminalAutomation, TERMINAL_KEYWORDS),
+    (TaskType::CodeGeneration, GENERATION_KEYWORDS),
+];
+
 pub fn analyze_request(message: &str) -> TaskType {
+    analyze(message).task_type
+}
+
+/// Classify a message, keeping the keywords that decided it
+pub fn analyze(message: &str) -> RequestAnalysis {
     let lower = message.to_lowercase();
     
-    // Simple keyword-based classification
-    if contains_code_keywords(&lower) {
-        TaskType::CodeEditing
-    } else if contains_research_keywords(&lower) {
-        TaskType::Research
-    } else if contains_terminal_keywords(&lower) {
-        TaskType::TerminalAutomation
-    } else if contains_generation_keywords(&lower) {
-        TaskType::CodeGeneration
-    } else {
-        TaskType::GeneralChat
-    }
-}
-
-fn contains_code_keywords(text: &str) -> bool {
-    let keywords = [
-        "refactor", "edit", "fix", "bug", "function", "class", "import",
-        "code", "file", "module", "package", "syntax", "error", "compile",
-        "test", "debug", "implement", "rewrite", "optimize",
-    ];
-    keywords.iter().any(|kw| text.contains(kw))
-}
-
-fn contains_research_keywords(text: &str) -> bool {
-    let keywords = [
-        "research", "find", "search", "what is", "explain", "how does",
-        "information", "article", "paper", "source", "citation", "reference",
-        "learn about", "tell me about", "investigate",
-    ];
-    keywords.iter().any(|kw| text.contains(kw))
-}
-
-fn contains_terminal_keywords(text: &str) -> bool {
-    let keywords = [
-        "run", "execute", "command", "terminal", "shell", "script",
-        "automate", "workflow", "cli", "bash", "zsh",
-    ];
-    keywords.iter().any(|kw| text.contains(kw))
-}
-
-fn contains_generation_keywords(text: &str) -> bool {
-    let keywords = [
-        "generate", "create", "write", "make", "build", "new",
-        "scaffold", "boilerplate", "template",
-    ];
-    keywords.iter().any(|kw| text.contains(kw))
+    // Simple keyword-based classification: the first type with any hit wins
+    let hits: Vec<Vec<String>> = CLASSIFIERS
+        .iter()
+        .map(|(_, keywords)| keyword_hits(&lower, keywords))
+        .collect();
+    let total: usize = hits.iter().map(Vec::len).sum();
+    
+    match hits.iter().position(|h| !h.is_empty()) {
+        Some(i) => RequestAnalysis {
+            task_type: CLASSIFIERS[i].0.clone(),
+            confidence: hits[i].len() as f64 / total as f64,
+            keyword_hits: hits[i].clone(),
+        },
+        None => RequestAnalysis {
+            task_type: TaskType::GeneralChat,
+            keyword_hits: Vec::new(),
+            confidence: 0.0,
+        },
+    }
+}
+
+fn keyword_hits(text: &str, keywords: &[&str]) -> Vec<String> {
+    keywords
+        .iter()
+        .filter(|kw| text.contains(*kw))
+        .map(|kw| kw.to_string())
+        .collect()
+}
+
+/// Rough effort a request asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Complexity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Complexity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Complexity::Low => "low",
+            Complexity::Medium => "medium",
+            Complexity::High => "high",
+        }
+    }
+}
+
+/// A fenced code block in a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeFence {
+    pub language: Option<String>, // Lowercased info string; None if the fence has none
+    pub content_len: usize, // Bytes between the fences
+}
+
+/// Everything `analyze_request_detailed` finds in a message, for building prompts
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailedAnalysis {
+    /// The classification `analyze` makes
+    pub classification: RequestAnalysis,
+    /// Every task type with a keyword hit, in the order they are checked
+    pub task_types: Vec<TaskType>,
+    /// From fence info strings, file extensions and error traces; sorted
+    pub detected_languages: Vec<String>,
+    /// File paths, in order of first mention, without line numbers
+    pub mentioned_paths: Vec<String>,
+    pub code_fences: Vec<CodeFence>,
+    /// Questions asked outside code fences
+    pub question_count: usize,
+    /// Verbs opening a sentence outside code fences, in order of first use
+    pub imperative_verbs: Vec<String>,
+    pub estimated_complexity: Complexity,
+    /// A Python traceback, Rust panic or JavaScript error stack, fenced or not
+    pub contains_error_trace: bool,
+}
+
+const IMPERATIVE_VERBS: &[&str] = &[
+    "add", "build", "change", "check", "convert", "create", "debug", "delete",
+    "deploy", "document", "explain", "find", "fix", "generate", "implement",
+    "install", "list", "make", "migrate", "move", "optimize", "refactor",
+    "remove", "rename", "review", "rewrite", "run", "show", "test", "update",
+    "write",
+];
+
+/// Extensions of files worth mentioning that have no parser language
+const OTHER_FILE_EXTENSIONS: &[&str] = &[
+    "cfg", "css", "html", "ini", "json", "lock", "md", "sh", "sql", "toml",
+    "txt", "yaml", "yml",
+];
+
+/// Classify a message and pull out the structure prompt templates need
+pub fn analyze_request_detailed(message: &str) -> DetailedAnalysis {
+    let lower = message.to_lowercase();
+    let task_types = CLASSIFIERS
+        .iter()
+        .filter(|(_, keywords)| !keyword_hits(&lower, keywords).is_empty())
+        .map(|(task_type, _)| task_type.clone())
+        .collect();
+    
+    let (code_fences, prose) = split_code_fences(message);
+    let mentioned_paths = mentioned_paths(message);
+    let trace_languages = error_trace_languages(message);
+    
+    let mut detected_languages: Vec<String> = code_fences
+        .iter()
+        .filter_map(|fence| fence.language.clone())
+        .chain(mentioned_paths.iter().filter_map(|path| {
+            ASTParser::detect_language(Path::new(path))
+        }))
+        .chain(trace_languages.iter().map(|language| language.to_string()))
+        .collect();
+    detected_languages.sort();
+    detected_languages.dedup();
+    
+    let mut analysis = DetailedAnalysis {
+        classification: analyze(message),
+        task_types,
+        detected_languages,
+        mentioned_paths,
+        code_fences,
+        question_count: question_count(&prose),
+        imperative_verbs: imperative_verbs(&prose),
+        estimated_complexity: Complexity::Low,
+        contains_error_trace: !trace_languages.is_empty(),
+    };
+    analysis.estimated_complexity = estimate_complexity(message, &analysis);
+    analysis
+}
+
+/// Languages of the error traces in `text`: "python", "rust" or "javascript"
+///
+/// Recognizes Python tracebacks (`Traceback (most recent call last):` or
+/// `File "...", line N` frames), Rust panics (`thread '...' panicked at`)
+/// and JavaScript error stacks (an `...Error` line followed by `at ...`
+/// frames with a line number).
+pub fn error_trace_languages(text: &str) -> Vec<&'static str> {
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    let mut languages = Vec::new();
+    
+    let python_frame = |line: &&str| {
+        line.starts_with("File \"") && line.contains("\", line ")
+    };
+    if lines.iter().any(|line| line.starts_with("Traceback (most recent call last)")) || lines.iter().any(python_frame) {
+        languages.push("python");
+    }
+    
+    if lines.iter().any(|line| line.contains("thread '") && line.contains("' panicked at")) {
+        languages.push("rust");
+    }
+    
+    let js_frame = |line: &str| {
+        line.starts_with("at ") && line.rsplit(':').next().is_some_and(|column| {
+            let column = column.trim_end_matches(')');
+            !column.is_empty() && column.chars().all(|c| c.is_ascii_digit())
+        })
+    };
+    let js_stack = lines.windows(2).any(|pair| {
+        let head = pair[0].split(':').next().unwrap_or("");
+        head.ends_with("Error") && !head.contains(' ') && js_frame(pair[1])
+    });
+    if js_stack {
+        languages.push("javascript");
+    }
+    languages
+}
+
+/// The fenced code blocks of `message`, and the text outside them
+///
+/// An unclosed fence runs to the end of the message.
+fn split_code_fences(message: &str) -> (Vec<CodeFence>, String) {
+    let fence = |language: Option<String>, lines: &[&str]| CodeFence {
+        language,
+        content_len: lines.join("\n").len(),
+    };
+    
+    let mut fences = Vec::new();
+    let mut prose = String::new();
+    let mut open: Option<(Option<String>, Vec<&str>)> = None;
+    for line in message.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        match &mut open {
+            Some((language, lines)) => {
+                if is_fence {
+                    fences.push(fence(language.take(), lines));
+                    open = None;
+                } else {
+                    lines.push(line);
+                }
+            }
+            None if is_fence => {
+                let info = line.trim_start().trim_start_matches('`').split_whitespace().next();
+                open = Some((info.map(str::to_lowercase), Vec::new()));
+            }
+            None => {
+                prose.push_str(line);
+                prose.push('\n');
+            }
+        }
+    }
+    if let Some((language, lines)) = open {
+        fences.push(fence(language, &lines));
+    }
+    (fences, prose)
+}
+
+/// Tokens of `message` that look like file paths, trimmed of quotes and line numbers
+///
+/// A path has an extension the parser knows or one of `OTHER_FILE_EXTENSIONS`,
+/// or starts with `./`, `../` or `~/`. URLs are not paths.
+fn mentioned_paths(message: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for token in message.split(|c: char| c.is_whitespace() || matches!(c, '`' | '"' | '\'' | '(' | ')' | '<' | '>' | ',')) {
+        if token.contains("://") {
+            continue;
+        }
+        // src/main.rs:10:5 -> src/main.rs
+        let mut path = token.trim_end_matches(['.', ':', ';', '!', '?']);
+        while let Some((head, tail)) = path.rsplit_once(':') {
+            if tail.is_empty() || !tail.chars().all(|c| c.is_ascii_digit()) {
+                break;
+            }
+            path = head;
+        }
+        
+        let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+        let named = path.chars().any(|c| c.is_alphanumeric());
+        let is_path = named
+            && (ASTParser::detect_language(Path::new(path)).is_some()
+                || OTHER_FILE_EXTENSIONS.contains(&extension)
+                || ["./", "../", "~/"].iter().any(|prefix| path.starts_with(prefix)));
+        if is_path && !paths.iter().any(|p| p == path) {
+            paths.push(path.to_string());
+        }
+    }
+    paths
+}
+
+/// Runs of question marks in `prose`
+fn question_count(prose: &str) -> usize {
+    prose
+        .split(|c: char| c != '?')
+        .filter(|run| !run.is_empty())
+        .count()
+}
+
+/// Verbs from `IMPERATIVE_VERBS` opening a sentence of `prose`, after an optional "please"
+fn imperative_verbs(prose: &str) -> Vec<String> {
+    let mut verbs: Vec<String> = Vec::new();
+    for sentence in prose.split(['.', '!', '?', '\n']) {
+        let mut words = sentence
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase());
+        let first = match words.next() {
+            Some(word) if word == "please" => words.next(),
+            word => word,
+        };
+        if let Some(verb) = first {
+            if IMPERATIVE_VERBS.contains(&verb.as_str()) && !verbs.contains(&verb) {
+                verbs.push(verb);
+            }
+        }
+    }
+    verbs
+}
+
+/// One point per 100 words, code fence, mentioned path, extra task type,
+/// extra imperative verb and error trace: Low up to 1, Medium up to 4
+fn estimate_complexity(message: &str, analysis: &DetailedAnalysis) -> Complexity {
+    let score = message.split_whitespace().count() / 100
+        + analysis.code_fences.len()
+        + analysis.mentioned_paths.len()
+        + analysis.task_types.len().saturating_sub(1)
+        + analysis.imperative_verbs.len().saturating_sub(1)
+        + usize::from(analysis.contains_error_trace);
+    match score {
+        0..=1 => Complexity::Low,
+        2..=4 => Complexity::Medium,
+        _ => Complexity::High,
+    }
 }
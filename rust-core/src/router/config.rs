@@ -0,0 +1,255 @@
+/// Routing rules loaded from a file, with hot reload
+
+use super::{ProjectRouting, Router, RoutingDecision, RoutingRequest, SelectionPolicy};
+use crate::config::RoutingConfig;
+use crate::error::{OrchestratorError, Result};
+use arc_swap::ArcSwap;
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Task types the selector looks up rules for, and the rule for messages with error traces
+pub const RULE_TASKS: &[&str] = &["code_editing", "research", "general_chat", "debugging"];
+
+/// Tools to route one task type to, in order of preference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingRule {
+    pub task: String,
+    pub tools: Vec<String>,
+}
+
+/// Validated contents of a rules file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingRules {
+    pub default_tool: String,
+    pub rules: Vec<RoutingRule>, // Sorted by task
+}
+
+impl RoutingRules {
+    /// Parse rules written like the `[routing]` section of the main config
+    ///
+    /// The file holds `default_tool` and one `task = ["tool", ...]` entry per
+    /// task type, either at the top level or under a `[routing]` table.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let invalid = |e: toml::de::Error| OrchestratorError::InvalidConfig(format!("Invalid routing rules: {}", e));
+        let mut value: toml::Value = toml::from_str(contents).map_err(invalid)?;
+        if let Some(routing) = value.as_table_mut().and_then(|table| table.remove("routing")) {
+            value = routing;
+        }
+        let config: RoutingConfig = value.try_into().map_err(invalid)?;
+        Self::from_config(&config)
+    }
+    
+    /// Check a routing config, reporting every problem found
+    pub fn from_config(config: &RoutingConfig) -> Result<Self> {
+        let mut errors = Vec::new();
+        if config.default_tool.trim().is_empty() {
+            errors.push("default_tool must not be empty".to_string());
+        }
+        
+        let mut rules: Vec<RoutingRule> = config
+            .rules
+            .iter()
+            .map(|(task, tools)| RoutingRule {
+                task: task.clone(),
+                tools: tools.clone(),
+            })
+            .collect();
+        rules.sort_by(|a, b| a.task.cmp(&b.task));
+        for rule in &rules {
+            if !RULE_TASKS.contains(&rule.task.as_str()) {
+                errors.push(format!("unknown task '{}' (expected one of {})", rule.task, RULE_TASKS.join(", ")));
+            }
+            if rule.tools.is_empty() || rule.tools.iter().any(|tool| tool.trim().is_empty()) {
+                errors.push(format!("{} must list at least one tool, none empty", rule.task));
+            }
+        }
+        
+        if !errors.is_empty() {
+            return Err(OrchestratorError::InvalidConfig(format!(
+                "Invalid routing rules: {}",
+                errors.join("; ")
+            )));
+        }
+        Ok(Self {
+            default_tool: config.default_tool.clone(),
+            rules,
+        })
+    }
+    
+    pub fn build_router(&self) -> Router {
+        let rules: HashMap<String, Vec<String>> = self
+            .rules
+            .iter()
+            .map(|rule| (rule.task.clone(), rule.tools.clone()))
+            .collect();
+        Router::new(rules, self.default_tool.clone())
+    }
+}
+
+/// Read and validate a rules file
+pub fn load_rules(path: &Path) -> Result<RoutingRules> {
+    let contents = std::fs::read_to_string(path)?;
+    RoutingRules::parse(&contents)
+}
+
+/// A router whose rules can be swapped while it is in use
+///
+/// Routing reads the current rules without locking. A reload that fails
+/// leaves the previous rules in place and records the error.
+pub struct ReloadableRouter {
+    path: Option<PathBuf>,
+    router: ArcSwap<Router>,
+    last_loaded_at: AtomicI64, // Unix seconds
+    last_error: Mutex<Option<String>>,
+}
+
+impl ReloadableRouter {
+    /// Load rules from `path`; `reload` re-reads the same file
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let router = load_rules(&path)?.build_router();
+        Ok(Self {
+            path: Some(path),
+            router: ArcSwap::from_pointee(router),
+            last_loaded_at: AtomicI64::new(now_secs()),
+            last_error: Mutex::new(None),
+        })
+    }
+    
+    /// Wrap fixed rules; `reload` fails since there is no file
+    pub fn from_router(router: Router) -> Self {
+        Self {
+            path: None,
+            router: ArcSwap::from_pointee(router),
+            last_loaded_at: AtomicI64::new(now_secs()),
+            last_error: Mutex::new(None),
+        }
+    }
+    
+    pub fn route(&self, request: &RoutingRequest) -> RoutingDecision {
+        self.router.load().route(request)
+    }
+    
+    /// Layer `routing` over the rules for requests from `project_id`; kept across reloads
+    pub fn set_project_override(&self, project_id: &str, routing: ProjectRouting) {
+        self.router.rcu(|current| {
+            let mut router = Router::clone(current);
+            router.set_project_override(project_id, routing.clone());
+            router
+        });
+    }
+    
+    /// Drop a project's override; returns it if there was one
+    pub fn remove_project_override(&self, project_id: &str) -> Option<ProjectRouting> {
+        let previous = self.router.rcu(|current| {
+            let mut router = Router::clone(current);
+            router.remove_project_override(project_id);
+            router
+        });
+        previous.project_override(project_id).cloned()
+    }
+    
+    /// Pick the first tool of `rule` with `policy`; kept across reloads
+    pub fn set_selection_policy(&self, rule: &str, policy: SelectionPolicy) {
+        self.router.rcu(|current| {
+            let mut router = Router::clone(current);
+            router.set_selection_policy(rule, policy.clone());
+            router
+        });
+    }
+    
+    /// The current router; later reloads do not affect the returned handle
+    pub fn current(&self) -> Arc<Router> {
+        self.router.load_full()
+    }
+    
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+    
+    /// Re-read the rules file and swap in its rules if valid
+    pub fn reload(&self) -> Result<()> {
+        let path = self.path.as_deref().ok_or_else(|| {
+            OrchestratorError::InvalidConfig("Router was not loaded from a rules file".to_string())
+        })?;
+        
+        match load_rules(path) {
+            Ok(rules) => {
+                let router = rules.build_router();
+                // Project overrides are set at runtime and outlive the file's rules
+                self.router.rcu(|current| router.clone().with_overrides_of(current));
+                self.last_loaded_at.store(now_secs(), Ordering::SeqCst);
+                *self.last_error.lock().unwrap() = None;
+                Ok(())
+            }
+            Err(e) => {
+                *self.last_error.lock().unwrap() = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+    
+    /// When the current rules were loaded (Unix seconds)
+    pub fn last_loaded_at(&self) -> i64 {
+        self.last_loaded_at.load(Ordering::SeqCst)
+    }
+    
+    /// Why the most recent reload failed; None once a reload succeeds
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+    
+    /// Reload whenever the rules file changes, until the returned guard is dropped
+    ///
+    /// The parent directory is watched so editors that replace the file
+    /// (write to a temp file, then rename) are picked up too.
+    pub fn watch(self: &Arc<Self>) -> Result<RulesWatcher> {
+        let path = self.path.clone().ok_or_else(|| {
+            OrchestratorError::InvalidConfig("Router was not loaded from a rules file".to_string())
+        })?;
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        
+        let router = Arc::downgrade(self);
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            if !(event.kind.is_create() || event.kind.is_modify()) {
+                return;
+            }
+            if !event.paths.iter().any(|p| p.file_name().map(|n| n.to_os_string()) == file_name) {
+                return;
+            }
+            if let Some(router) = router.upgrade() {
+                // Errors are kept in last_error; the old rules stay active
+                let _ = router.reload();
+            }
+        })
+        .map_err(|e| OrchestratorError::Unknown(format!("Failed to create rules watcher: {}", e)))?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| OrchestratorError::Unknown(format!("Failed to watch {}: {}", dir.display(), e)))?;
+        
+        Ok(RulesWatcher { _watcher: watcher })
+    }
+}
+
+/// Keeps a `ReloadableRouter` watching its rules file
+pub struct RulesWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
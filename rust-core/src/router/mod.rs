@@ -1,8 +1,18 @@
 pub mod analyzer;
+pub mod config;
 pub mod selector;
 
+pub use analyzer::{analyze_request_detailed, CodeFence, Complexity, DetailedAnalysis, RequestAnalysis, TaskType};
+pub use config::{load_rules, ReloadableRouter, RoutingRule, RoutingRules, RulesWatcher};
+pub use selector::{unknown_rule_keys, SelectionPolicy, DEBUGGING_RULE, RULE_KEYS};
+
+use crate::error::{OrchestratorError, Result};
+use crate::models::ModelCatalog;
+use crate::observability::ensure_request_id;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingRequest {
@@ -10,17 +20,128 @@ pub struct RoutingRequest {
     pub conversation_id: Option<String>,
     pub project_id: Option<String>,
     pub explicit_tool: Option<String>,
+    /// Carried through context, cost and metrics records; generated if None
+    #[serde(default)]
+    pub request_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingDecision {
     pub selected_tools: Vec<String>,
-    pub reasoning: String,
+    pub reasoning: String, // `reasoning_details.render(&selected_tools)`
+    pub reasoning_details: RoutingReasoning,
+    /// Canonical model of each selected tool picked by model alias or name; None where the tool was named
+    #[serde(default)]
+    pub selected_models: Vec<Option<String>>,
+    /// The request's ID, or the one generated for it
+    #[serde(default)]
+    pub request_id: String,
+}
+
+/// Why a router picked its tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingReasoning {
+    /// Tool the caller asked for; when set, no rule was consulted
+    pub explicit_tool: Option<String>,
+    /// Routing rule the tools came from; None when the default tool was used
+    pub matched_rule: Option<String>,
+    pub task_type: TaskType,
+    /// Keywords that decided the task type
+    pub keyword_hits: Vec<String>,
+    pub confidence: f64,
+    /// True when no rule covered the task type and the default tool was used
+    pub fallback_used: bool,
+    /// Tools passed over, with the reason
+    pub skipped_tools: Vec<(String, String)>,
+    /// Project whose routing override picked the tools; None if the global rules did
+    #[serde(default)]
+    pub project_override: Option<String>,
+    /// Policy that picked the first tool, when the rule has one other than first match
+    #[serde(default)]
+    pub selection_policy: Option<String>,
+    /// The message contains an error trace, so a `debugging` rule is preferred
+    #[serde(default)]
+    pub error_trace: bool,
+}
+
+impl RoutingReasoning {
+    /// Human-readable form, as stored in `RoutingDecision::reasoning`
+    pub fn render(&self, selected_tools: &[String]) -> String {
+        if let Some(tool) = &self.explicit_tool {
+            return format!("Explicit tool selection: {}", tool);
+        }
+        
+        let mut text = format!("Task type: {:?}, Selected tools: {:?}", self.task_type, selected_tools);
+        match &self.matched_rule {
+            Some(rule) => text.push_str(&format!("; rule: {}", rule)),
+            None if self.fallback_used => text.push_str("; no matching rule, used default tool"),
+            None => {}
+        }
+        if self.error_trace {
+            text.push_str("; error trace detected");
+        }
+        if let Some(project) = &self.project_override {
+            text.push_str(&format!("; project override: {}", project));
+        }
+        if let Some(policy) = &self.selection_policy {
+            text.push_str(&format!("; picked by {}", policy));
+        }
+        if !self.keyword_hits.is_empty() {
+            text.push_str(&format!(
+                "; keywords: {} (confidence {:.2})",
+                self.keyword_hits.join(", "),
+                self.confidence
+            ));
+        }
+        for (tool, reason) in &self.skipped_tools {
+            text.push_str(&format!("; skipped {}: {}", tool, reason));
+        }
+        text
+    }
+}
+
+/// Routing tweaks for one project, layered over the global rules
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectRouting {
+    /// Used instead of the global default when no rule covers the task type
+    pub default_tool: Option<String>,
+    /// Rule key (one of `RULE_KEYS`, e.g. `code_editing`) -> tools; each
+    /// replaces the global rule with that key
+    #[serde(default)]
+    pub extra_rules: HashMap<String, Vec<String>>,
 }
 
+impl ProjectRouting {
+    /// Fails with `InvalidInput` if `extra_rules` has a key no task type is routed by
+    pub fn validate(&self) -> Result<()> {
+        let unknown = unknown_rule_keys(self.extra_rules.keys());
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        Err(OrchestratorError::InvalidInput(format!(
+            "Unknown routing rule keys {} (expected one of {})",
+            unknown.join(", "),
+            RULE_KEYS.join(", ")
+        )))
+    }
+}
+
+/// A rule's selection policy and its round-robin position
+#[derive(Clone)]
+struct RuleSelection {
+    policy: SelectionPolicy,
+    next: Arc<AtomicUsize>, // Shared by clones, so reloads keep the rotation
+}
+
+#[derive(Clone)]
 pub struct Router {
     routing_rules: HashMap<String, Vec<String>>,
     default_tool: String,
+    project_overrides: HashMap<String, ProjectRouting>,
+    disabled_tools: HashSet<String>, // Never selected through a project override
+    selection: HashMap<String, RuleSelection>, // Rule name -> policy; first match if absent
+    rng: Arc<AtomicU64>, // SplitMix64 state for weighted random selection
+    catalog: ModelCatalog, // Model aliases and names that rules and requests may use for tools
 }
 
 impl Router {
@@ -28,27 +149,227 @@ impl Router {
         Self {
             routing_rules,
             default_tool,
+            project_overrides: HashMap::new(),
+            disabled_tools: HashSet::new(),
+            selection: HashMap::new(),
+            rng: Arc::new(AtomicU64::new(time_seed())),
+            catalog: ModelCatalog::new(),
         }
     }
+    
+    pub fn with_project_overrides(mut self, project_overrides: HashMap<String, ProjectRouting>) -> Self {
+        self.project_overrides = project_overrides;
+        self
+    }
+    
+    /// Tools a project override may not route to; the global rules apply instead
+    pub fn with_disabled_tools(mut self, tools: impl IntoIterator<Item = String>) -> Self {
+        self.disabled_tools = tools.into_iter().collect();
+        self
+    }
+    
+    /// Pick the first tool of `rule` (e.g. "code_editing") with `policy`
+    pub fn with_selection_policy(mut self, rule: impl Into<String>, policy: SelectionPolicy) -> Self {
+        self.set_selection_policy(rule, policy);
+        self
+    }
+    
+    /// Let rules and explicit tools name a model by alias or canonical name
+    ///
+    /// The tool serving the model is selected, and the model is reported in
+    /// `RoutingDecision::selected_models`.
+    pub fn with_model_catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+    
+    /// Seed weighted random selection, for reproducible routing in tests
+    pub fn with_selection_seed(mut self, seed: u64) -> Self {
+        self.rng = Arc::new(AtomicU64::new(seed));
+        self
+    }
+    
+    /// Keep the project overrides, disabled tools, selection policies and model catalog of `other`, e.g. across a rules reload
+    pub fn with_overrides_of(mut self, other: &Router) -> Self {
+        self.project_overrides = other.project_overrides.clone();
+        self.disabled_tools = other.disabled_tools.clone();
+        self.selection = other.selection.clone();
+        self.rng = other.rng.clone();
+        self.catalog = other.catalog.clone();
+        self
+    }
+    
+    /// Replaces the rule's policy and restarts its rotation
+    pub fn set_selection_policy(&mut self, rule: impl Into<String>, policy: SelectionPolicy) {
+        self.selection.insert(
+            rule.into(),
+            RuleSelection {
+                policy,
+                next: Arc::new(AtomicUsize::new(0)),
+            },
+        );
+    }
+    
+    pub fn selection_policy(&self, rule: &str) -> SelectionPolicy {
+        self.selection.get(rule).map(|s| s.policy.clone()).unwrap_or_default()
+    }
+    
+    pub fn set_project_override(&mut self, project_id: impl Into<String>, routing: ProjectRouting) {
+        self.project_overrides.insert(project_id.into(), routing);
+    }
+    
+    pub fn remove_project_override(&mut self, project_id: &str) -> Option<ProjectRouting> {
+        self.project_overrides.remove(project_id)
+    }
+    
+    pub fn project_override(&self, project_id: &str) -> Option<&ProjectRouting> {
+        self.project_overrides.get(project_id)
+    }
 
     pub fn route(&self, request: &RoutingRequest) -> RoutingDecision {
-        // If explicit tool requested, use it
-        if let Some(tool) = &request.explicit_tool {
-            return RoutingDecision {
-                selected_tools: vec![tool.clone()],
-                reasoning: format!("Explicit tool selection: {}", tool),
-            };
-        }
-
+        let request_id = ensure_request_id(request.request_id.as_deref());
+        let _span = tracing::info_span!("route", request_id = %request_id).entered();
+        
         // Analyze request to determine task type
-        let task_type = analyzer::analyze_request(&request.message);
+        let analysis = analyzer::analyze(&request.message);
+        let mut details = RoutingReasoning {
+            explicit_tool: None,
+            matched_rule: None,
+            task_type: analysis.task_type,
+            keyword_hits: analysis.keyword_hits,
+            confidence: analysis.confidence,
+            fallback_used: false,
+            skipped_tools: Vec::new(),
+            project_override: None,
+            selection_policy: None,
+            error_trace: !analyzer::error_trace_languages(&request.message).is_empty(),
+        };
+        
+        // If explicit tool requested, use it
+        let tools = if let Some(tool) = &request.explicit_tool {
+            details.explicit_tool = Some(tool.clone());
+            vec![tool.clone()]
+        } else {
+            let overridden = request
+                .project_id
+                .as_deref()
+                .and_then(|project_id| self.project_overrides.get(project_id).map(|routing| (project_id, routing)));
+            // Only reported when the override, not the global rules, picked the tools
+            let project_tools = overridden.and_then(|(project_id, routing)| {
+                let tools = self.project_tools(routing, &mut details)?;
+                details.project_override = Some(project_id.to_string());
+                Some(tools)
+            });
+            
+            match project_tools {
+                Some(tools) => tools,
+                None if details.error_trace && self.routing_rules.contains_key(DEBUGGING_RULE) => {
+                    details.matched_rule = Some(DEBUGGING_RULE.to_string());
+                    self.routing_rules[DEBUGGING_RULE].clone()
+                }
+                None => {
+                    // Select tools based on task type
+                    let rule = selector::rule_key(&details.task_type);
+                    if self.routing_rules.contains_key(rule) {
+                        details.matched_rule = Some(rule.to_string());
+                    } else {
+                        details.fallback_used = true;
+                    }
+                    selector::select_tools(&details.task_type, &self.routing_rules, &self.default_tool)
+                }
+            }
+        };
+        
+        // Rules listing several tools share load according to their policy
+        let tools = match details.matched_rule.as_ref().and_then(|rule| self.selection.get(rule)) {
+            Some(selection) if tools.len() > 1 && selection.policy != SelectionPolicy::FirstMatch => {
+                details.selection_policy = Some(selection.policy.as_str().to_string());
+                selector::apply_policy(tools, &selection.policy, &selection.next, &self.rng)
+            }
+            _ => tools,
+        };
         
-        // Select tools based on task type
-        let tools = selector::select_tools(&task_type, &self.routing_rules, &self.default_tool);
+        // Models route to the tool serving them
+        let (tools, models): (Vec<String>, Vec<Option<String>>) = tools
+            .into_iter()
+            .map(|tool| match self.catalog.lookup(&tool) {
+                Some(entry) => (entry.tool.clone(), Some(entry.model.clone())),
+                None => (tool, None),
+            })
+            .unzip();
         
         RoutingDecision {
-            selected_tools: tools.clone(),
-            reasoning: format!("Task type: {:?}, Selected tools: {:?}", task_type, tools),
+            reasoning: details.render(&tools),
+            selected_tools: tools,
+            reasoning_details: details,
+            selected_models: models,
+            request_id,
         }
     }
+    
+    /// Tools a project override picks, or None to use the global rules
+    ///
+    /// Disabled tools are dropped and recorded as skipped; a rule or default
+    /// left with no enabled tool defers to the global rules. For messages with
+    /// an error trace, a `debugging` rule of the project, then of the global
+    /// rules, comes first.
+    fn project_tools(&self, routing: &ProjectRouting, details: &mut RoutingReasoning) -> Option<Vec<String>> {
+        if details.error_trace {
+            if let Some(rule_tools) = routing.extra_rules.get(DEBUGGING_RULE) {
+                let tools = self.enabled_tools(rule_tools, details);
+                if !tools.is_empty() {
+                    details.matched_rule = Some(DEBUGGING_RULE.to_string());
+                    return Some(tools);
+                }
+            }
+            if self.routing_rules.contains_key(DEBUGGING_RULE) {
+                return None;
+            }
+        }
+        
+        let rule = selector::rule_key(&details.task_type);
+        if let Some(rule_tools) = routing.extra_rules.get(rule) {
+            let tools = self.enabled_tools(rule_tools, details);
+            if !tools.is_empty() {
+                details.matched_rule = Some(rule.to_string());
+                return Some(tools);
+            }
+        }
+        
+        // A global rule for the task type takes precedence over the project default
+        if self.routing_rules.contains_key(rule) {
+            return None;
+        }
+        let default_tool = routing.default_tool.as_ref()?;
+        if self.disabled_tools.contains(default_tool) {
+            details.skipped_tools.push((
+                default_tool.clone(),
+                "disabled; used global default tool".to_string(),
+            ));
+            return None;
+        }
+        details.fallback_used = true;
+        Some(vec![default_tool.clone()])
+    }
+    
+    fn enabled_tools(&self, tools: &[String], details: &mut RoutingReasoning) -> Vec<String> {
+        let mut enabled = Vec::new();
+        for tool in tools {
+            let serving_tool = self.catalog.lookup(tool).map_or(tool.as_str(), |entry| entry.tool.as_str());
+            if self.disabled_tools.contains(serving_tool) {
+                details.skipped_tools.push((tool.clone(), "disabled; ignored project override".to_string()));
+            } else {
+                enabled.push(tool.clone());
+            }
+        }
+        enabled
+    }
+}
+
+/// Seed for weighted random selection when none is given
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
 }
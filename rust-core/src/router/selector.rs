@@ -1,22 +1,118 @@
 use super::analyzer::TaskType;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
-pub fn select_tools(
-    task_type: &TaskType,
-    routing_rules: &HashMap<String, Vec<String>>,
-    default_tool: &str,
-) -> Vec<String> {
-    let rule_key = match task_type {
+/// How the tool to try first is picked from a rule listing several
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum SelectionPolicy {
+    /// The rule's first tool, always
+    #[default]
+    FirstMatch,
+    /// Each of the rule's tools in turn
+    RoundRobin,
+    /// At random, in proportion to each tool's weight; unlisted tools weigh 0
+    WeightedRandom(HashMap<String, f32>),
+}
+
+impl SelectionPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SelectionPolicy::FirstMatch => "first_match",
+            SelectionPolicy::RoundRobin => "round_robin",
+            SelectionPolicy::WeightedRandom(_) => "weighted_random",
+        }
+    }
+}
+
+/// Rule consulted before the task type's when a message contains an error trace
+pub const DEBUGGING_RULE: &str = "debugging";
+
+/// Every key a routing rule can be looked up by: those of `rule_key`, and `DEBUGGING_RULE`
+pub const RULE_KEYS: &[&str] = &["code_editing", "research", "general_chat", DEBUGGING_RULE];
+
+/// Keys of `rules` that no task type is routed by, sorted
+pub fn unknown_rule_keys<'a>(rules: impl IntoIterator<Item = &'a String>) -> Vec<&'a str> {
+    let mut unknown: Vec<&str> = rules
+        .into_iter()
+        .map(String::as_str)
+        .filter(|key| !RULE_KEYS.contains(key))
+        .collect();
+    unknown.sort();
+    unknown
+}
+
+/// The routing rule consulted for a task type
+pub fn rule_key(task_type: &TaskType) -> &'static str {
+    match task_type {
         TaskType::CodeEditing => "code_editing",
         TaskType::Research => "research",
         TaskType::GeneralChat => "general_chat",
         TaskType::CodeGeneration => "code_editing", // Use code_editing rules
         TaskType::TerminalAutomation => "general_chat", // Fallback
         TaskType::Unknown => "general_chat",
-    };
+    }
+}
 
+pub fn select_tools(
+    task_type: &TaskType,
+    routing_rules: &HashMap<String, Vec<String>>,
+    default_tool: &str,
+) -> Vec<String> {
     routing_rules
-        .get(rule_key)
+        .get(rule_key(task_type))
         .cloned()
         .unwrap_or_else(|| vec![default_tool.to_string()])
 }
+
+/// `tools` with the policy's pick moved to the front; the rest stay in order as fallbacks
+///
+/// `next` counts round-robin picks and `rng` is SplitMix64 state; both are
+/// atomics so routers sharing them can route concurrently.
+pub fn apply_policy(
+    mut tools: Vec<String>,
+    policy: &SelectionPolicy,
+    next: &AtomicUsize,
+    rng: &AtomicU64,
+) -> Vec<String> {
+    if tools.len() < 2 {
+        return tools;
+    }
+    
+    let picked = match policy {
+        SelectionPolicy::FirstMatch => 0,
+        SelectionPolicy::RoundRobin => next.fetch_add(1, Ordering::Relaxed) % tools.len(),
+        SelectionPolicy::WeightedRandom(weights) => {
+            let weight_of = |tool: &String| {
+                weights.get(tool).copied().filter(|w| w.is_finite() && *w > 0.0).unwrap_or(0.0) as f64
+            };
+            let total: f64 = tools.iter().map(weight_of).sum();
+            if total <= 0.0 {
+                0
+            } else {
+                let mut target = random_unit(rng) * total;
+                tools
+                    .iter()
+                    .position(|tool| {
+                        target -= weight_of(tool);
+                        target < 0.0
+                    })
+                    .unwrap_or_else(|| tools.iter().rposition(|tool| weight_of(tool) > 0.0).unwrap_or(0))
+            }
+        }
+    };
+    
+    let tool = tools.remove(picked);
+    tools.insert(0, tool);
+    tools
+}
+
+/// Uniform in [0, 1), advancing SplitMix64 state held in `rng`
+fn random_unit(rng: &AtomicU64) -> f64 {
+    const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut z = rng.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
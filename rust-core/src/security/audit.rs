@@ -0,0 +1,102 @@
+/// Audit logging to the `audit_logs` table
+
+use super::limits::{apply_json_limits, JsonLimits, LimitPolicy};
+use crate::error::{OrchestratorError, Result};
+use sqlx::sqlite::SqlitePool;
+
+/// Event types shared with the Python audit logger
+pub mod event_types {
+    pub const RESOURCE_DELETE: &str = "resource.delete";
+    pub const AUTHZ_ALLOW: &str = "authz.allow";
+    pub const AUTHZ_DENY: &str = "authz.deny";
+}
+
+/// Writes audit events; the table is created by migration 4
+///
+/// Event details are held to `JsonLimits`; by default oversized details
+/// are truncated rather than dropping the event.
+#[derive(Clone)]
+pub struct AuditLogger {
+    pool: SqlitePool,
+    limits: JsonLimits,
+    limit_policy: LimitPolicy,
+    request_id: Option<String>, // Stored with each event; needs migration 18
+}
+
+impl AuditLogger {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            limits: JsonLimits::default(),
+            limit_policy: LimitPolicy::Truncate,
+            request_id: None,
+        }
+    }
+    
+    pub fn with_limits(mut self, limits: JsonLimits, policy: LimitPolicy) -> Self {
+        self.limits = limits;
+        self.limit_policy = policy;
+        self
+    }
+    
+    /// A logger recording its events as part of request `request_id`
+    pub fn for_request(&self, request_id: &str) -> Self {
+        Self {
+            request_id: Some(request_id.to_string()),
+            ..self.clone()
+        }
+    }
+    
+    /// Record an event performed by the system rather than a user
+    pub async fn log_event(
+        &self,
+        event_type: &str,
+        resource_type: Option<&str>,
+        resource_id: Option<&str>,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        self.insert(None, event_type, resource_type, resource_id, details).await
+    }
+    
+    /// Record an event performed by `user_id`
+    pub async fn log_user_event(
+        &self,
+        user_id: &str,
+        event_type: &str,
+        resource_type: Option<&str>,
+        resource_id: Option<&str>,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        self.insert(Some(user_id), event_type, resource_type, resource_id, details).await
+    }
+    
+    async fn insert(
+        &self,
+        user_id: Option<&str>,
+        event_type: &str,
+        resource_type: Option<&str>,
+        resource_id: Option<&str>,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        let details = apply_json_limits(details, &self.limits, self.limit_policy)
+            .map_err(|e| OrchestratorError::InvalidInput(format!("Audit details rejected: {}", e)))?;
+        
+        sqlx::query(
+            r#"
+            INSERT INTO audit_logs (event_type, user_id, resource_type, resource_id, details, request_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(event_type)
+        .bind(user_id)
+        .bind(resource_type)
+        .bind(resource_id)
+        .bind(details.to_string())
+        .bind(&self.request_id)
+        .execute(&self.pool)
+        .await
+        .map_err(OrchestratorError::from)?;
+        
+        Ok(())
+    }
+}
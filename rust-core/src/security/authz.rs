@@ -0,0 +1,233 @@
+/// Role-based authorization for destructive operations
+
+use super::audit::{event_types, AuditLogger};
+use crate::error::{OrchestratorError, Result};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    ReadContext,
+    WriteContext,
+    DeleteContext,
+    ManageIndex,
+    ViewCosts,
+    ManageBudgets,
+    /// Implies every other permission
+    Admin,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 7] = [
+        Permission::ReadContext,
+        Permission::WriteContext,
+        Permission::DeleteContext,
+        Permission::ManageIndex,
+        Permission::ViewCosts,
+        Permission::ManageBudgets,
+        Permission::Admin,
+    ];
+    
+    pub fn parse(permission: &str) -> Result<Self> {
+        Permission::ALL
+            .into_iter()
+            .find(|p| p.as_str() == permission.trim().to_lowercase())
+            .ok_or_else(|| OrchestratorError::InvalidInput(format!("Unknown permission: {}", permission)))
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ReadContext => "read_context",
+            Permission::WriteContext => "write_context",
+            Permission::DeleteContext => "delete_context",
+            Permission::ManageIndex => "manage_index",
+            Permission::ViewCosts => "view_costs",
+            Permission::ManageBudgets => "manage_budgets",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+/// The caller of an operation, as stored in the `users` table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub id: String,
+    pub role: String,
+}
+
+impl User {
+    pub fn new(id: impl Into<String>, role: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            role: role.into(),
+        }
+    }
+}
+
+/// Permissions granted to each role; unknown roles are granted nothing
+///
+/// The default mapping has three roles: `admin` holds `Admin`, `user` can
+/// read, write and delete contexts and view costs, and `viewer` can only
+/// read contexts and view costs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RolePermissions {
+    roles: HashMap<String, HashSet<Permission>>,
+}
+
+impl Default for RolePermissions {
+    fn default() -> Self {
+        Self::empty()
+            .with_role("admin", [Permission::Admin])
+            .with_role(
+                "user",
+                [
+                    Permission::ReadContext,
+                    Permission::WriteContext,
+                    Permission::DeleteContext,
+                    Permission::ViewCosts,
+                ],
+            )
+            .with_role("viewer", [Permission::ReadContext, Permission::ViewCosts])
+    }
+}
+
+impl RolePermissions {
+    pub fn empty() -> Self {
+        Self { roles: HashMap::new() }
+    }
+    
+    /// Set the permissions of `role`, replacing any it had
+    pub fn with_role(mut self, role: &str, permissions: impl IntoIterator<Item = Permission>) -> Self {
+        self.roles.insert(role.to_lowercase(), permissions.into_iter().collect());
+        self
+    }
+    
+    pub fn allows(&self, role: &str, permission: Permission) -> bool {
+        match self.roles.get(&role.trim().to_lowercase()) {
+            Some(granted) => granted.contains(&Permission::Admin) || granted.contains(&permission),
+            None => false,
+        }
+    }
+}
+
+/// Check a role against the default mapping
+pub fn check_permission(user_role: &str, permission: Permission) -> Result<()> {
+    deny_unless(RolePermissions::default().allows(user_role, permission), user_role, permission)
+}
+
+fn deny_unless(allowed: bool, role: &str, permission: Permission) -> Result<()> {
+    if allowed {
+        Ok(())
+    } else {
+        Err(OrchestratorError::Authorization(format!(
+            "role '{}' lacks permission {}",
+            role,
+            permission.as_str()
+        )))
+    }
+}
+
+/// Permission checks that leave an audit trail
+///
+/// `authorize` writes an `authz.allow` or `authz.deny` event for every
+/// decision when an audit logger is set.
+#[derive(Clone, Default)]
+pub struct Authorizer {
+    roles: RolePermissions,
+    audit_logger: Option<AuditLogger>,
+}
+
+impl Authorizer {
+    pub fn new(roles: RolePermissions) -> Self {
+        Self {
+            roles,
+            audit_logger: None,
+        }
+    }
+    
+    pub fn with_audit_logger(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+    
+    pub fn roles(&self) -> &RolePermissions {
+        &self.roles
+    }
+    
+    /// Check without auditing
+    pub fn check(&self, user: &User, permission: Permission) -> Result<()> {
+        deny_unless(self.roles.allows(&user.role, permission), &user.role, permission)
+    }
+    
+    /// Check `user` may perform an operation on a resource, auditing the decision
+    pub async fn authorize(
+        &self,
+        user: &User,
+        permission: Permission,
+        resource_type: &str,
+        resource_id: &str,
+    ) -> Result<()> {
+        let decision = self.check(user, permission);
+        if let Some(audit_logger) = &self.audit_logger {
+            let event_type = if decision.is_ok() { event_types::AUTHZ_ALLOW } else { event_types::AUTHZ_DENY };
+            audit_logger
+                .log_user_event(
+                    &user.id,
+                    event_type,
+                    Some(resource_type),
+                    Some(resource_id),
+                    serde_json::json!({
+                        "role": user.role,
+                        "permission": permission.as_str(),
+                    }),
+                )
+                .await?;
+        }
+        decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_default_roles_against_every_permission() {
+        use Permission::*;
+        let expected: [(&str, &[Permission]); 4] = [
+            ("admin", &Permission::ALL),
+            ("user", &[ReadContext, WriteContext, DeleteContext, ViewCosts]),
+            ("viewer", &[ReadContext, ViewCosts]),
+            ("guest", &[]),
+        ];
+        for (role, granted) in expected {
+            for permission in Permission::ALL {
+                let result = check_permission(role, permission);
+                assert_eq!(result.is_ok(), granted.contains(&permission), "{} / {}", role, permission.as_str());
+                if let Err(e) = result {
+                    assert!(matches!(e, OrchestratorError::Authorization(_)));
+                }
+            }
+        }
+        
+        // Role names are case-insensitive
+        assert!(check_permission("Admin", ManageBudgets).is_ok());
+    }
+    
+    #[test]
+    fn test_custom_mapping() {
+        let roles = RolePermissions::default().with_role("indexer", [Permission::ManageIndex]);
+        let authorizer = Authorizer::new(roles);
+        assert!(authorizer.check(&User::new("bot", "indexer"), Permission::ManageIndex).is_ok());
+        assert!(authorizer.check(&User::new("bot", "indexer"), Permission::DeleteContext).is_err());
+        assert!(authorizer.check(&User::new("u1", "user"), Permission::ManageIndex).is_err());
+    }
+    
+    #[test]
+    fn test_permission_parse() {
+        for permission in Permission::ALL {
+            assert_eq!(Permission::parse(permission.as_str()).unwrap(), permission);
+        }
+        assert_eq!(Permission::parse(" Manage_Index ").unwrap(), Permission::ManageIndex);
+        assert!(Permission::parse("root").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+/// Application-level encryption of data at rest
+
+use crate::error::{OrchestratorError, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::fmt;
+use std::path::Path;
+
+/// Key length in bytes
+pub const KEY_LEN: usize = 32;
+
+/// XChaCha20 nonce length in bytes
+const NONCE_LEN: usize = 24;
+
+/// Marks a stored value as encrypted; anything else is legacy plaintext
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// 32-byte key for XChaCha20-Poly1305
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; KEY_LEN]);
+
+impl EncryptionKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let key: [u8; KEY_LEN] = bytes.try_into().map_err(|_| {
+            OrchestratorError::InvalidConfig(format!(
+                "Encryption key must be {} bytes, got {}",
+                KEY_LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(Self(key))
+    }
+    
+    /// Parse a key written as 64 hex characters
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = decode_hex(hex.trim()).ok_or_else(|| {
+            OrchestratorError::InvalidConfig("Encryption key is not valid hex".to_string())
+        })?;
+        Self::from_bytes(&bytes)
+    }
+    
+    /// Read a hex-encoded key from an environment variable
+    pub fn from_env(var: &str) -> Result<Self> {
+        let hex = std::env::var(var).map_err(|_| {
+            OrchestratorError::InvalidConfig(format!("Environment variable {} is not set", var))
+        })?;
+        Self::from_hex(&hex)
+    }
+    
+    /// Read a key file holding either 32 raw bytes or 64 hex characters
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        if bytes.len() == KEY_LEN {
+            return Self::from_bytes(&bytes);
+        }
+        let text = std::str::from_utf8(&bytes).map_err(|_| {
+            OrchestratorError::InvalidConfig(format!("Key file {} is neither raw nor hex", path.display()))
+        })?;
+        Self::from_hex(text)
+    }
+    
+    /// A new random key
+    pub fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+    
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+    
+    /// Encrypt under a fresh random nonce, stored in front of the ciphertext
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let cipher = XChaCha20Poly1305::new((&self.0).into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| OrchestratorError::Unknown("Encryption failed".to_string()))?;
+        
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, encode_hex(&sealed)))
+    }
+    
+    /// Decrypt a value made by `encrypt`
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let invalid = || OrchestratorError::Authentication(
+            "Failed to decrypt stored data (wrong key or corrupted value)".to_string(),
+        );
+        
+        let sealed = stored
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(decode_hex)
+            .filter(|sealed| sealed.len() >= NONCE_LEN)
+            .ok_or_else(invalid)?;
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        
+        let cipher = XChaCha20Poly1305::new((&self.0).into());
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| invalid())?;
+        String::from_utf8(plaintext).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// Whether a stored value was written by `EncryptionKey::encrypt`
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Encrypt `plaintext` when a key is configured
+pub fn seal(key: Option<&EncryptionKey>, plaintext: String) -> Result<String> {
+    match key {
+        Some(key) => key.encrypt(&plaintext),
+        None => Ok(plaintext),
+    }
+}
+
+/// Decrypt a stored value; legacy plaintext passes through unchanged
+///
+/// Encrypted values without a key fail with an `Authentication` error.
+pub fn open(key: Option<&EncryptionKey>, stored: String) -> Result<String> {
+    if !is_encrypted(&stored) {
+        return Ok(stored);
+    }
+    match key {
+        Some(key) => key.decrypt(&stored),
+        None => Err(OrchestratorError::Authentication(
+            "Stored data is encrypted but no encryption key is configured".to_string(),
+        )),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,327 @@
+/// Size and shape limits for JSON payloads such as audit details
+
+use super::validation::ValidationError;
+use serde_json::{Map, Value};
+
+/// Put in place of anything cut by `truncate_json`
+pub const TRUNCATION_MARKER: &str = "[truncated]";
+
+/// Bounds on a JSON value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// Compact serialized size
+    pub max_bytes: usize,
+    /// Levels of nested arrays and objects; the root container is level 1
+    pub max_depth: usize,
+    /// Elements per array and entries per object
+    pub max_array_len: usize,
+    /// Bytes per string, object keys included
+    pub max_string_len: usize,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024,
+            max_depth: 32,
+            max_array_len: 1_000,
+            max_string_len: 8 * 1024,
+        }
+    }
+}
+
+/// What to do with a payload over its limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitPolicy {
+    Reject,
+    /// Cut the payload down, leaving `TRUNCATION_MARKER` where data was dropped
+    Truncate,
+}
+
+/// Check a value against `limits`, reporting the first violation found
+pub fn enforce_json_limits(value: &Value, limits: &JsonLimits) -> Result<(), ValidationError> {
+    check_shape(value, limits, 1, &mut String::from("$"))?;
+    
+    let size = estimate_json_size_capped(value, limits.max_bytes.saturating_add(1));
+    if size > limits.max_bytes {
+        return Err(ValidationError::JsonLimitExceeded {
+            kind: "size",
+            path: "$".to_string(),
+            max: limits.max_bytes,
+            actual: estimate_json_size(value),
+        });
+    }
+    Ok(())
+}
+
+/// Enforce `limits` according to `policy`, returning the value to keep
+pub fn apply_json_limits(value: Value, limits: &JsonLimits, policy: LimitPolicy) -> Result<Value, ValidationError> {
+    match policy {
+        LimitPolicy::Reject => enforce_json_limits(&value, limits).map(|_| value),
+        LimitPolicy::Truncate => Ok(truncate_json(value, limits).0),
+    }
+}
+
+/// Cut a value down to `limits`; the flag is true if anything was dropped
+///
+/// Long strings keep their prefix followed by the marker. Long arrays and
+/// objects keep their first entries and end with a marker entry. Containers
+/// nested too deeply become the marker. If the result is still over
+/// `max_bytes`, the whole value is replaced by a marked preview string.
+pub fn truncate_json(value: Value, limits: &JsonLimits) -> (Value, bool) {
+    if enforce_json_limits(&value, limits).is_ok() {
+        return (value, false);
+    }
+    
+    let value = truncate_shape(value, limits, 1);
+    if estimate_json_size_capped(&value, limits.max_bytes.saturating_add(1)) <= limits.max_bytes {
+        return (value, true);
+    }
+    
+    // Still too large: keep a preview of the serialized form
+    let text = value.to_string();
+    let mut keep = limits
+        .max_bytes
+        .saturating_sub(TRUNCATION_MARKER.len() + 2)
+        .min(text.len());
+    loop {
+        keep = floor_char_boundary(&text, keep);
+        let preview = Value::String(format!("{}{}", &text[..keep], TRUNCATION_MARKER));
+        let size = estimate_json_size(&preview);
+        if size <= limits.max_bytes || keep == 0 {
+            return (preview, true);
+        }
+        keep = keep.saturating_sub(size - limits.max_bytes);
+    }
+}
+
+/// Compact serialized size of a value, computed without serializing it
+pub fn estimate_json_size(value: &Value) -> usize {
+    estimate_json_size_capped(value, usize::MAX)
+}
+
+/// Like `estimate_json_size`, but stops walking once the size passes `cap`
+pub fn estimate_json_size_capped(value: &Value, cap: usize) -> usize {
+    let mut size = 0usize;
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        size = size.saturating_add(match value {
+            Value::Null => 4,
+            Value::Bool(true) => 4,
+            Value::Bool(false) => 5,
+            Value::Number(n) => n.to_string().len(),
+            Value::String(s) => string_size(s),
+            Value::Array(items) => {
+                stack.extend(items.iter());
+                2 + items.len().saturating_sub(1)
+            }
+            Value::Object(map) => {
+                stack.extend(map.values());
+                // Braces, commas, and a colon per entry
+                2 + map.len().saturating_sub(1)
+                    + map.keys().map(|key| string_size(key) + 1).sum::<usize>()
+            }
+        });
+        if size > cap {
+            return size;
+        }
+    }
+    size
+}
+
+/// Serialized size of a string, quotes and escapes included
+fn string_size(s: &str) -> usize {
+    2 + s
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{8}' | '\u{c}' => 2,
+            c if (c as u32) < 0x20 => 6,
+            c => c.len_utf8(),
+        })
+        .sum::<usize>()
+}
+
+fn check_shape(value: &Value, limits: &JsonLimits, depth: usize, path: &mut String) -> Result<(), ValidationError> {
+    let exceeded = |kind: &'static str, path: &str, max: usize, actual: usize| ValidationError::JsonLimitExceeded {
+        kind,
+        path: path.to_string(),
+        max,
+        actual,
+    };
+    
+    match value {
+        Value::String(s) if s.len() > limits.max_string_len => {
+            Err(exceeded("string length", path.as_str(), limits.max_string_len, s.len()))
+        }
+        Value::Array(_) | Value::Object(_) if depth > limits.max_depth => {
+            Err(exceeded("depth", path.as_str(), limits.max_depth, depth))
+        }
+        Value::Array(items) => {
+            if items.len() > limits.max_array_len {
+                return Err(exceeded("array length", path.as_str(), limits.max_array_len, items.len()));
+            }
+            for (i, item) in items.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                check_shape(item, limits, depth + 1, path)?;
+                path.truncate(len);
+            }
+            Ok(())
+        }
+        Value::Object(map) => {
+            if map.len() > limits.max_array_len {
+                return Err(exceeded("object length", path.as_str(), limits.max_array_len, map.len()));
+            }
+            for (key, item) in map {
+                let len = path.len();
+                path.push('.');
+                path.push_str(key);
+                if key.len() > limits.max_string_len {
+                    return Err(exceeded("key length", path.as_str(), limits.max_string_len, key.len()));
+                }
+                check_shape(item, limits, depth + 1, path)?;
+                path.truncate(len);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn truncate_shape(value: Value, limits: &JsonLimits, depth: usize) -> Value {
+    match value {
+        Value::String(s) => Value::String(truncate_string(s, limits.max_string_len)),
+        Value::Array(_) | Value::Object(_) if depth > limits.max_depth => Value::String(TRUNCATION_MARKER.to_string()),
+        Value::Array(items) => {
+            let total = items.len();
+            let keep = if total > limits.max_array_len { limits.max_array_len.saturating_sub(1) } else { total };
+            let mut truncated: Vec<Value> = items
+                .into_iter()
+                .take(keep)
+                .map(|item| truncate_shape(item, limits, depth + 1))
+                .collect();
+            if keep < total {
+                truncated.push(Value::String(format!("{} {} more items", TRUNCATION_MARKER, total - keep)));
+            }
+            Value::Array(truncated)
+        }
+        Value::Object(map) => {
+            let total = map.len();
+            let keep = if total > limits.max_array_len { limits.max_array_len.saturating_sub(1) } else { total };
+            let mut truncated: Map<String, Value> = map
+                .into_iter()
+                .take(keep)
+                .map(|(key, item)| (truncate_string(key, limits.max_string_len), truncate_shape(item, limits, depth + 1)))
+                .collect();
+            if keep < total {
+                truncated.insert(
+                    TRUNCATION_MARKER.to_string(),
+                    Value::String(format!("{} more entries", total - keep)),
+                );
+            }
+            Value::Object(truncated)
+        }
+        other => other,
+    }
+}
+
+/// Cut a string so it fits `max_len` bytes with the marker appended
+fn truncate_string(s: String, max_len: usize) -> String {
+    if s.len() <= max_len {
+        return s;
+    }
+    let keep = floor_char_boundary(&s, max_len.saturating_sub(TRUNCATION_MARKER.len()));
+    format!("{}{}", &s[..keep], TRUNCATION_MARKER)
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    let mut index = index;
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn nested(depth: usize) -> Value {
+        (0..depth).fold(json!("leaf"), |inner, _| json!({ "child": inner }))
+    }
+    
+    #[test]
+    fn test_estimate_matches_serialized_size() {
+        let value = json!({
+            "name": "quote \" and \\ and \n and \u{1}",
+            "items": [1, -2.5, true, false, null, "ünïcode"],
+            "nested": { "empty": {}, "list": [] }
+        });
+        assert_eq!(estimate_json_size(&value), value.to_string().len());
+        
+        // The capped walk stops at the first value that crosses the cap
+        let large = json!(["a".repeat(1000), "b".repeat(1000)]);
+        assert!(estimate_json_size_capped(&large, 10) < estimate_json_size(&large));
+    }
+    
+    #[test]
+    fn test_deep_nesting() {
+        let limits = JsonLimits { max_depth: 4, ..Default::default() };
+        assert!(enforce_json_limits(&nested(4), &limits).is_ok());
+        match enforce_json_limits(&nested(6), &limits) {
+            Err(ValidationError::JsonLimitExceeded { kind, path, .. }) => {
+                assert_eq!(kind, "depth");
+                assert_eq!(path, "$.child.child.child.child");
+            }
+            other => panic!("expected depth violation, got {:?}", other),
+        }
+        
+        let (truncated, changed) = truncate_json(nested(6), &limits);
+        assert!(changed);
+        assert_eq!(truncated, json!({ "child": { "child": { "child": { "child": TRUNCATION_MARKER } } } }));
+        assert!(enforce_json_limits(&truncated, &limits).is_ok());
+    }
+    
+    #[test]
+    fn test_huge_arrays() {
+        let limits = JsonLimits { max_array_len: 5, ..Default::default() };
+        let value = json!({ "ids": (0..100).collect::<Vec<_>>() });
+        assert!(matches!(
+            enforce_json_limits(&value, &limits),
+            Err(ValidationError::JsonLimitExceeded { kind: "array length", max: 5, actual: 100, .. })
+        ));
+        assert!(apply_json_limits(value.clone(), &limits, LimitPolicy::Reject).is_err());
+        
+        // The marker takes the last kept slot so the array stays within its limit
+        let truncated = apply_json_limits(value, &limits, LimitPolicy::Truncate).unwrap();
+        assert_eq!(truncated["ids"], json!([0, 1, 2, 3, "[truncated] 96 more items"]));
+    }
+    
+    #[test]
+    fn test_truncation_marker_placement() {
+        let limits = JsonLimits { max_string_len: 16, ..Default::default() };
+        let (value, changed) = truncate_json(json!({ "msg": "é".repeat(20) }), &limits);
+        assert!(changed);
+        let msg = value["msg"].as_str().unwrap();
+        assert!(msg.ends_with(TRUNCATION_MARKER));
+        assert_eq!(msg, "éé[truncated]");
+        assert!(msg.len() <= 16);
+        
+        // Over the byte limit after shape truncation: a marked preview of the whole value
+        let limits = JsonLimits { max_bytes: 40, ..Default::default() };
+        let (value, changed) = truncate_json(json!({ "a": "x".repeat(30), "b": "y".repeat(30) }), &limits);
+        assert!(changed);
+        let preview = value.as_str().unwrap();
+        assert!(preview.starts_with("{\"a\""));
+        assert!(preview.ends_with(TRUNCATION_MARKER));
+        assert!(estimate_json_size(&value) <= 40);
+        
+        // Values within limits are untouched
+        let small = json!({ "ok": [1, 2, 3] });
+        assert_eq!(truncate_json(small.clone(), &JsonLimits::default()), (small, false));
+    }
+}
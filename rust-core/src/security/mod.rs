@@ -1,5 +1,17 @@
 /// Security module for input validation and security utilities
 
+pub mod audit;
+pub mod authz;
+pub mod encryption;
+pub mod limits;
+pub mod prompt_guard;
 pub mod validation;
 
-pub use validation::{validate_input, sanitize_path, ValidationError};
+pub use audit::AuditLogger;
+pub use authz::{check_permission, Authorizer, Permission, RolePermissions, User};
+pub use encryption::EncryptionKey;
+pub use limits::{enforce_json_limits, estimate_json_size, truncate_json, JsonLimits, LimitPolicy};
+pub use prompt_guard::{scan, GuardAction, GuardPolicy, InjectionFinding, PromptGuard, Severity};
+pub use validation::{
+    sanitize_path, validate_identifier, validate_input, validate_like_pattern, validate_order_by, ValidationError,
+};
@@ -1,16 +1,25 @@
 /// Input validation and sanitization utilities
 
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
     #[error("Input too long: max {max} characters, got {actual}")]
     InputTooLong { max: usize, actual: usize },
     
+    #[error("Input too large: max {max} bytes, got {actual}")]
+    InputTooLarge { max: usize, actual: usize },
+    
     #[error("Invalid characters detected")]
     InvalidCharacters,
     
+    #[error("Suspicious unicode: {0}")]
+    SuspiciousUnicode(String),
+    
     #[error("Path traversal detected: {0}")]
     PathTraversal(String),
     
@@ -19,34 +28,122 @@ pub enum ValidationError {
     
     #[error("Invalid format: {0}")]
     InvalidFormat(String),
+    
+    #[error("JSON {kind} limit exceeded at {path}: max {max}, got {actual}")]
+    JsonLimitExceeded {
+        kind: &'static str,
+        path: String,
+        max: usize,
+        actual: usize,
+    },
+}
+
+/// Length limits and permitted control characters for `validate_input_with`
+#[derive(Debug, Clone)]
+pub struct InputLimits {
+    /// User-perceived characters (grapheme clusters)
+    pub max_graphemes: usize,
+    /// Bytes of the NFC-normalized UTF-8 text
+    pub max_bytes: usize,
+    /// Control and format characters let through
+    pub allowed_controls: HashSet<char>,
+}
+
+impl InputLimits {
+    /// Allows newline, tab, carriage return and the zero-width (non-)joiners
+    /// used in emoji and Indic scripts; `max_bytes` defaults to eight bytes
+    /// per character, room for CJK text and most emoji
+    pub fn new(max_graphemes: usize) -> Self {
+        Self {
+            max_graphemes,
+            max_bytes: max_graphemes.saturating_mul(8),
+            allowed_controls: ['\n', '\t', '\r', '\u{200c}', '\u{200d}'].into_iter().collect(),
+        }
+    }
+    
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+    
+    pub fn allow(mut self, c: char) -> Self {
+        self.allowed_controls.insert(c);
+        self
+    }
+}
+
+/// Validate user input for security, returning it NFC-normalized
+///
+/// `max_length` counts grapheme clusters, so "é" or a family emoji is one
+/// character however many code points it takes.
+pub fn validate_input(input: &str, max_length: usize) -> Result<String, ValidationError> {
+    validate_input_with(input, &InputLimits::new(max_length))
 }
 
-/// Validate user input for security
-pub fn validate_input(input: &str, max_length: usize) -> Result<(), ValidationError> {
+/// `validate_input` with explicit limits
+pub fn validate_input_with(input: &str, limits: &InputLimits) -> Result<String, ValidationError> {
     if input.is_empty() {
         return Err(ValidationError::EmptyInput);
     }
     
-    if input.len() > max_length {
-        return Err(ValidationError::InputTooLong {
-            max: max_length,
-            actual: input.len(),
-        });
-    }
+    let normalized: String = input.nfc().collect();
     
-    // Check for null bytes (potential injection)
-    if input.contains('\0') {
-        return Err(ValidationError::InvalidCharacters);
+    // Bidi overrides can make text display differently from how it is read
+    if let Some((index, c)) = normalized.chars().enumerate().find(|(_, c)| is_bidi_control(*c)) {
+        return Err(ValidationError::SuspiciousUnicode(format!(
+            "bidirectional control U+{:04X} at character {}",
+            c as u32, index
+        )));
     }
     
-    // Check for control characters (except newline, tab, carriage return)
-    if input.chars().any(|c| {
-        c.is_control() && c != '\n' && c != '\t' && c != '\r'
+    // Check for null bytes, control and invisible format characters
+    if normalized.chars().any(|c| {
+        (c.is_control() || is_format_char(c)) && !limits.allowed_controls.contains(&c)
     }) {
         return Err(ValidationError::InvalidCharacters);
     }
     
-    Ok(())
+    if normalized.len() > limits.max_bytes {
+        return Err(ValidationError::InputTooLarge {
+            max: limits.max_bytes,
+            actual: normalized.len(),
+        });
+    }
+    
+    let graphemes = normalized.graphemes(true).count();
+    if graphemes > limits.max_graphemes {
+        return Err(ValidationError::InputTooLong {
+            max: limits.max_graphemes,
+            actual: graphemes,
+        });
+    }
+    
+    Ok(normalized)
+}
+
+/// Embeddings and overrides used in "Trojan Source" style attacks
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Invisible format characters (Unicode category Cf)
+fn is_format_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{ad}'
+            | '\u{600}'..='\u{605}'
+            | '\u{61c}'
+            | '\u{6dd}'
+            | '\u{70f}'
+            | '\u{180e}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{206a}'..='\u{206f}'
+            | '\u{feff}'
+            | '\u{fff9}'..='\u{fffb}'
+            | '\u{e0001}'
+            | '\u{e0020}'..='\u{e007f}'
+    )
 }
 
 /// Sanitize and validate file paths to prevent path traversal
@@ -75,7 +172,73 @@ pub fn sanitize_path(base_path: &Path, user_path: &str) -> Result<PathBuf, Valid
     Ok(canonical)
 }
 
+/// Longest name accepted by `validate_identifier`
+pub const MAX_IDENTIFIER_LENGTH: usize = 128;
+
+/// Check a table, column or project id before it is used as a name
+///
+/// Only ASCII letters, digits, `_`, `-` and `.` are allowed, so quotes,
+/// whitespace and statement separators can never reach the SQL text.
+pub fn validate_identifier(input: &str) -> Result<&str, ValidationError> {
+    if input.is_empty() {
+        return Err(ValidationError::EmptyInput);
+    }
+    if input.len() > MAX_IDENTIFIER_LENGTH {
+        return Err(ValidationError::InputTooLong {
+            max: MAX_IDENTIFIER_LENGTH,
+            actual: input.len(),
+        });
+    }
+    if let Some(c) = input.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))) {
+        return Err(ValidationError::InvalidFormat(format!(
+            "identifier {:?} contains {:?}; only letters, digits, '_', '-' and '.' are allowed",
+            input, c
+        )));
+    }
+    Ok(input)
+}
+
+/// Escape `input` for use inside a `LIKE` pattern with `ESCAPE '\'`
+///
+/// `%`, `_` and `\` are matched literally, so a search for "100%" finds
+/// that text rather than everything starting with "100". Callers add their
+/// own wildcards around the result.
+pub fn validate_like_pattern(input: &str) -> Result<String, ValidationError> {
+    if input.contains('\0') {
+        return Err(ValidationError::InvalidCharacters);
+    }
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Ok(escaped)
+}
+
+/// The entry of `allowed` naming `column`, for use in an `ORDER BY` clause
+///
+/// Matching ignores ASCII case; the whitelisted spelling is returned so the
+/// caller's input never reaches the SQL text.
+pub fn validate_order_by<'a>(column: &str, allowed: &[&'a str]) -> Result<&'a str, ValidationError> {
+    allowed
+        .iter()
+        .find(|candidate| candidate.eq_ignore_ascii_case(column))
+        .copied()
+        .ok_or_else(|| ValidationError::InvalidFormat(format!(
+            "cannot sort by {:?}; expected one of {}",
+            column,
+            allowed.join(", ")
+        )))
+}
+
 /// Validate SQL injection patterns (basic check)
+///
+/// Rejects ordinary text such as code comments, and queries use bound
+/// parameters anyway; check names with `validate_identifier` or
+/// `validate_order_by` and escape search text with `validate_like_pattern`.
+#[deprecated(note = "use validate_identifier, validate_like_pattern or validate_order_by; never apply to chat text")]
 pub fn validate_sql_safe(input: &str) -> Result<(), ValidationError> {
     // Check for common SQL injection patterns
     let dangerous_patterns = [
@@ -140,6 +303,62 @@ mod tests {
         assert!(validate_input("hello", 100).is_ok());
         assert!(validate_input("", 100).is_err());
         assert!(validate_input(&"a".repeat(101), 100).is_err());
+        assert!(matches!(validate_input("a\u{1}b", 100), Err(ValidationError::InvalidCharacters)));
+        assert!(validate_input("line\nnext\ttab", 100).is_ok());
+    }
+    
+    #[test]
+    fn test_emoji_zwj_sequences_count_as_one_character() {
+        // Family: man, ZWJ, woman, ZWJ, girl; seven code points, one grapheme
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(validate_input(family, 3).unwrap(), family);
+        
+        let limits = InputLimits::new(3).with_max_bytes(100);
+        assert!(validate_input_with(&family.repeat(3), &limits).is_ok());
+        assert!(matches!(
+            validate_input_with(&family.repeat(4), &limits),
+            Err(ValidationError::InputTooLong { max: 3, actual: 4 })
+        ));
+        
+        // A zero-width space is not in the default allow list
+        assert!(matches!(validate_input("a\u{200b}b", 10), Err(ValidationError::InvalidCharacters)));
+        let permissive = InputLimits::new(10).allow('\u{200b}');
+        assert!(validate_input_with("a\u{200b}b", &permissive).is_ok());
+    }
+    
+    #[test]
+    fn test_cjk_length_counts_characters_not_bytes() {
+        let message = "日本語".repeat(333) + "日";
+        assert_eq!(message.len(), 3_000);
+        assert!(validate_input(&message, 2_000).is_ok());
+        assert!(validate_input(&message, 999).is_err());
+        
+        let limits = InputLimits::new(2_000).with_max_bytes(2_000);
+        assert!(matches!(
+            validate_input_with(&message, &limits),
+            Err(ValidationError::InputTooLarge { max: 2_000, actual: 3_000 })
+        ));
+    }
+    
+    #[test]
+    fn test_rtl_override_is_suspicious() {
+        let payload = "access_level = \"user\u{202e} \u{2066}// Check if admin\u{2069} \u{2066}\"";
+        match validate_input(payload, 1_000) {
+            Err(ValidationError::SuspiciousUnicode(detail)) => assert!(detail.contains("U+202E")),
+            other => panic!("expected SuspiciousUnicode, got {:?}", other),
+        }
+        // Allowing control characters does not let bidi overrides through
+        let limits = InputLimits::new(1_000).allow('\u{202e}');
+        assert!(matches!(validate_input_with(payload, &limits), Err(ValidationError::SuspiciousUnicode(_))));
+    }
+    
+    #[test]
+    fn test_input_is_returned_nfc_normalized() {
+        // "e" + combining acute accent becomes the precomposed "é"
+        let decomposed = "cafe\u{301}";
+        let normalized = validate_input(decomposed, 4).unwrap();
+        assert_eq!(normalized, "caf\u{e9}");
+        assert_eq!(normalized.chars().count(), 4);
     }
     
     #[test]
@@ -154,6 +373,33 @@ mod tests {
     }
     
     #[test]
+    fn test_validate_identifier() {
+        assert_eq!(validate_identifier("code_blocks").unwrap(), "code_blocks");
+        assert!(validate_identifier("my-project.v2").is_ok());
+        assert!(matches!(validate_identifier("users\"; DROP TABLE x"), Err(ValidationError::InvalidFormat(_))));
+        assert!(validate_identifier("it's").is_err());
+        assert!(validate_identifier("two words").is_err());
+        assert!(matches!(validate_identifier(""), Err(ValidationError::EmptyInput)));
+        assert!(validate_identifier(&"a".repeat(MAX_IDENTIFIER_LENGTH + 1)).is_err());
+    }
+    
+    #[test]
+    fn test_validate_like_pattern() {
+        assert_eq!(validate_like_pattern("100%").unwrap(), "100\\%");
+        assert_eq!(validate_like_pattern("snake_case").unwrap(), "snake\\_case");
+        assert_eq!(validate_like_pattern("a\\b").unwrap(), "a\\\\b");
+        assert_eq!(validate_like_pattern("-- comment /* ok */").unwrap(), "-- comment /* ok */");
+    }
+    
+    #[test]
+    fn test_validate_order_by() {
+        let allowed = ["updated_at", "created_at"];
+        assert_eq!(validate_order_by("UPDATED_AT", &allowed).unwrap(), "updated_at");
+        assert!(validate_order_by("updated_at; DROP TABLE contexts", &allowed).is_err());
+    }
+    
+    #[test]
+    #[allow(deprecated)]
     fn test_validate_sql_safe() {
         assert!(validate_sql_safe("SELECT * FROM users").is_ok());
         assert!(validate_sql_safe("'; DROP TABLE users--").is_err());
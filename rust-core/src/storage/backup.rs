@@ -0,0 +1,195 @@
+/// Online backup, verification and restore of SQLite databases
+
+use crate::error::{OrchestratorError, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// First bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Outcome of a backup
+#[derive(Debug, Clone)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    /// Size of the backup file as written
+    pub bytes: u64,
+    pub compressed: bool,
+    pub duration: Duration,
+}
+
+/// Outcome of `verify_backup`
+#[derive(Debug, Clone)]
+pub struct BackupVerification {
+    pub compressed: bool,
+    /// Rows returned by `PRAGMA integrity_check`; `["ok"]` when healthy
+    pub integrity: Vec<String>,
+    pub tables: usize,
+}
+
+impl BackupVerification {
+    pub fn is_ok(&self) -> bool {
+        self.integrity.len() == 1 && self.integrity[0] == "ok"
+    }
+}
+
+/// Write a consistent copy of the database behind `pool` to `dest`
+///
+/// Uses `VACUUM INTO`, which reads inside a single transaction, so the copy
+/// is a snapshot even while other connections keep writing. An existing
+/// file at `dest` is replaced only once the new backup is complete.
+pub async fn backup_to(pool: &SqlitePool, dest: &Path, compress: bool) -> Result<BackupReport> {
+    let started = Instant::now();
+    let snapshot = temp_sibling(dest);
+    let packed = temp_sibling(dest);
+
+    let result = async {
+        sqlx::query("VACUUM INTO ?")
+            .bind(snapshot.to_string_lossy().to_string())
+            .execute(pool)
+            .await?;
+
+        if compress {
+            let mut encoder = GzEncoder::new(File::create(&packed)?, Compression::default());
+            std::io::copy(&mut File::open(&snapshot)?, &mut encoder)?;
+            encoder.finish()?;
+            std::fs::remove_file(&snapshot)?;
+            std::fs::rename(&packed, dest)?;
+        } else {
+            std::fs::rename(&snapshot, dest)?;
+        }
+        Ok::<(), OrchestratorError>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        std::fs::remove_file(&snapshot).ok();
+        std::fs::remove_file(&packed).ok();
+        return Err(e);
+    }
+
+    Ok(BackupReport {
+        path: dest.to_path_buf(),
+        bytes: std::fs::metadata(dest)?.len(),
+        compressed: compress,
+        duration: started.elapsed(),
+    })
+}
+
+/// Back up the database file at `src` without an existing pool
+pub async fn backup_path(src: &Path, dest: &Path, compress: bool) -> Result<BackupReport> {
+    let pool = open_read_only(src).await?;
+    let report = backup_to(&pool, dest, compress).await;
+    pool.close().await;
+    report
+}
+
+/// Run an integrity check on a backup file, compressed or not
+pub async fn verify_backup(path: &Path) -> Result<BackupVerification> {
+    let compressed = is_gzip(path)?;
+    let unpacked = if compressed {
+        let unpacked = temp_sibling(path);
+        decompress(path, &unpacked)?;
+        Some(unpacked)
+    } else {
+        None
+    };
+
+    let result = async {
+        let pool = open_read_only(unpacked.as_deref().unwrap_or(path)).await?;
+        let integrity: Vec<(String,)> = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_all(&pool)
+            .await?;
+        let (tables,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'")
+            .fetch_one(&pool)
+            .await?;
+        pool.close().await;
+
+        Ok(BackupVerification {
+            compressed,
+            integrity: integrity.into_iter().map(|(row,)| row).collect(),
+            tables: tables as usize,
+        })
+    }
+    .await;
+
+    if let Some(unpacked) = unpacked {
+        std::fs::remove_file(unpacked).ok();
+    }
+    result
+}
+
+/// Restore the backup at `src` to `dest`
+///
+/// Refuses with `ConflictDetected` when `dest` already exists unless
+/// `force` is set; connections still open on `dest` must be closed by the
+/// caller. The backup is verified before anything is replaced.
+pub async fn restore_from(src: &Path, dest: &Path, force: bool) -> Result<BackupVerification> {
+    if dest.exists() && !force {
+        return Err(OrchestratorError::ConflictDetected(format!(
+            "Refusing to overwrite existing database {}",
+            dest.display()
+        )));
+    }
+
+    let verification = verify_backup(src).await?;
+    if !verification.is_ok() {
+        return Err(OrchestratorError::InvalidInput(format!(
+            "Backup {} failed integrity check: {}",
+            src.display(),
+            verification.integrity.join("; ")
+        )));
+    }
+
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let staged = temp_sibling(dest);
+    if verification.compressed {
+        decompress(src, &staged)?;
+    } else {
+        std::fs::copy(src, &staged)?;
+    }
+    std::fs::rename(&staged, dest)?;
+
+    // Journals left by the replaced database would be replayed against the restored one
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut journal = dest.as_os_str().to_owned();
+        journal.push(suffix);
+        std::fs::remove_file(PathBuf::from(journal)).ok();
+    }
+
+    Ok(verification)
+}
+
+/// Whether a file starts with the gzip magic bytes
+fn is_gzip(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 2];
+    let read = File::open(path)?.read(&mut magic)?;
+    Ok(read == magic.len() && magic == GZIP_MAGIC)
+}
+
+fn decompress(src: &Path, dest: &Path) -> Result<()> {
+    let mut decoder = GzDecoder::new(File::open(src)?);
+    std::io::copy(&mut decoder, &mut File::create(dest)?)?;
+    Ok(())
+}
+
+/// Unique scratch path next to `path`, so renames stay on one filesystem
+fn temp_sibling(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()))
+}
+
+async fn open_read_only(path: &Path) -> Result<SqlitePool> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::new().filename(path).read_only(true))
+        .await?;
+    Ok(pool)
+}
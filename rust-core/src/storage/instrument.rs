@@ -0,0 +1,384 @@
+/// Per-query timing for SQLite pools
+
+use crate::observability::MetricsCollector;
+use futures_core::future::BoxFuture;
+use futures_core::stream::{BoxStream, Stream};
+use sqlx::database::HasStatement;
+use sqlx::pool::PoolConnection;
+use sqlx::query::{Query, QueryAs};
+use sqlx::sqlite::{Sqlite, SqliteArguments, SqlitePool, SqliteQueryResult, SqliteRow, SqliteTypeInfo};
+use sqlx::{Describe, Either, Execute, Executor, FromRow, Transaction};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Queries slower than this are logged at warn level by default
+pub const DEFAULT_SLOW_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Accumulated timings for one statement fingerprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryStats {
+    pub fingerprint: String,
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+    /// Rows affected or returned, summed over all runs
+    pub rows: u64,
+}
+
+impl QueryStats {
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+/// Timings per fingerprint, shared by every pool that records into it
+#[derive(Debug, Default)]
+pub struct QueryLog {
+    stats: Mutex<HashMap<String, QueryStats>>,
+}
+
+impl QueryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, fingerprint: &str, duration: Duration, rows: u64) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(fingerprint.to_string()).or_insert_with(|| QueryStats {
+            fingerprint: fingerprint.to_string(),
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+            rows: 0,
+        });
+        entry.count += 1;
+        entry.total += duration;
+        entry.max = entry.max.max(duration);
+        entry.rows += rows;
+    }
+
+    /// The `n` fingerprints with the highest total time, worst first
+    pub fn top_queries(&self, n: usize) -> Vec<QueryStats> {
+        let mut stats: Vec<QueryStats> = self.stats.lock().unwrap().values().cloned().collect();
+        stats.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.fingerprint.cmp(&b.fingerprint)));
+        stats.truncate(n);
+        stats
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<QueryStats> {
+        self.stats.lock().unwrap().get(fingerprint).cloned()
+    }
+
+    pub fn clear(&self) {
+        self.stats.lock().unwrap().clear();
+    }
+}
+
+static GLOBAL_LOG: OnceLock<Arc<QueryLog>> = OnceLock::new();
+
+/// The process-wide log used by pools built with `InstrumentedPool::new`
+pub fn global_log() -> Arc<QueryLog> {
+    GLOBAL_LOG.get_or_init(|| Arc::new(QueryLog::new())).clone()
+}
+
+/// Worst offenders recorded in the process-wide log since start
+pub fn top_queries(n: usize) -> Vec<QueryStats> {
+    global_log().top_queries(n)
+}
+
+/// Normalize a statement so runs with different literals group together
+///
+/// String and numeric literals become `?`, bound parameters are kept as
+/// written, and whitespace is collapsed.
+pub fn fingerprint(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    // Whether the previous character can continue an identifier
+    let mut in_word = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // '' inside a literal is an escaped quote
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push('?');
+                in_word = false;
+            }
+            '?' => {
+                out.push('?');
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    out.push(*d);
+                    chars.next();
+                }
+                in_word = false;
+            }
+            c if c.is_ascii_digit() && !in_word => {
+                while chars.peek().is_some_and(|d| d.is_ascii_alphanumeric() || *d == '.') {
+                    chars.next();
+                }
+                out.push('?');
+            }
+            c if c.is_whitespace() => {
+                while chars.peek().is_some_and(|d| d.is_whitespace()) {
+                    chars.next();
+                }
+                if !out.is_empty() {
+                    out.push(' ');
+                }
+                in_word = false;
+            }
+            c => {
+                out.push(c);
+                in_word = c.is_alphanumeric() || c == '_';
+            }
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// A pool that times every query run through it
+///
+/// Each query is recorded under its fingerprint in a `QueryLog`, observed in
+/// the `uai_db_query_duration_seconds` histogram when a metrics collector is
+/// set, and logged at warn level when slower than the slow threshold.
+///
+/// `&InstrumentedPool` is an `Executor`, so `query.execute(&pool)` is timed
+/// like `pool.execute(query)`. Statements on connections taken with
+/// `acquire` or `begin` are not timed.
+#[derive(Clone)]
+pub struct InstrumentedPool {
+    pool: SqlitePool,
+    log: Arc<QueryLog>,
+    metrics: Option<MetricsCollector>,
+    slow_threshold: Duration,
+}
+
+impl InstrumentedPool {
+    /// Wrap `pool`, recording into the process-wide log
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            log: global_log(),
+            metrics: None,
+            slow_threshold: DEFAULT_SLOW_THRESHOLD,
+        }
+    }
+
+    pub fn with_log(mut self, log: Arc<QueryLog>) -> Self {
+        self.log = log;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_slow_threshold(mut self, slow_threshold: Duration) -> Self {
+        self.slow_threshold = slow_threshold;
+        self
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub fn log(&self) -> &QueryLog {
+        &self.log
+    }
+
+    /// A connection from the pool; queries run on it directly are not timed
+    pub async fn acquire(&self) -> Result<PoolConnection<Sqlite>, sqlx::Error> {
+        self.pool.acquire().await
+    }
+
+    /// A transaction from the pool; queries run in it are not timed
+    pub async fn begin(&self) -> Result<Transaction<'static, Sqlite>, sqlx::Error> {
+        self.pool.begin().await
+    }
+
+    pub async fn execute<'q>(
+        &self,
+        query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        query.execute(self).await
+    }
+
+    pub async fn fetch_all<'q, O>(
+        &self,
+        query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    ) -> Result<Vec<O>, sqlx::Error>
+    where
+        O: Send + Unpin + for<'r> FromRow<'r, SqliteRow>,
+    {
+        query.fetch_all(self).await
+    }
+
+    pub async fn fetch_one<'q, O>(
+        &self,
+        query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    ) -> Result<O, sqlx::Error>
+    where
+        O: Send + Unpin + for<'r> FromRow<'r, SqliteRow>,
+    {
+        query.fetch_one(self).await
+    }
+
+    pub async fn fetch_optional<'q, O>(
+        &self,
+        query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    ) -> Result<Option<O>, sqlx::Error>
+    where
+        O: Send + Unpin + for<'r> FromRow<'r, SqliteRow>,
+    {
+        query.fetch_optional(self).await
+    }
+
+    /// Time any future running `sql`; failed queries are recorded with 0 rows
+    pub async fn time<T, F>(
+        &self,
+        sql: &str,
+        rows: impl FnOnce(&T) -> u64,
+        query: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let started = Instant::now();
+        let result = query.await;
+        self.record(sql, started.elapsed(), result.as_ref().map_or(0, rows));
+        result
+    }
+
+    fn record(&self, sql: &str, duration: Duration, rows: u64) {
+        let fingerprint = fingerprint(sql);
+        self.log.record(&fingerprint, duration, rows);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_db_query(&fingerprint, duration);
+        }
+        if duration >= self.slow_threshold {
+            tracing::warn!(
+                fingerprint = %fingerprint,
+                duration_ms = duration.as_millis() as u64,
+                rows,
+                "slow query"
+            );
+        }
+    }
+}
+
+impl fmt::Debug for InstrumentedPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstrumentedPool")
+            .field("pool", &self.pool)
+            .field("slow_threshold", &self.slow_threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'p> Executor<'p> for &'_ InstrumentedPool {
+    type Database = Sqlite;
+
+    fn fetch_many<'e, 'q: 'e, E: 'q>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<SqliteQueryResult, SqliteRow>, sqlx::Error>>
+    where
+        E: Execute<'q, Sqlite>,
+    {
+        let sql = query.sql();
+        Box::pin(TimedStream {
+            pool: self.clone(),
+            sql,
+            inner: self.pool.fetch_many(query),
+            started: None,
+            rows: 0,
+            failed: false,
+        })
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E: 'q>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<SqliteRow>, sqlx::Error>>
+    where
+        E: Execute<'q, Sqlite>,
+    {
+        let pool = self.clone();
+        let sql = query.sql();
+        Box::pin(async move {
+            let fetch = pool.pool.fetch_optional(query);
+            pool.time(sql, |row| row.is_some() as u64, fetch).await
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [SqliteTypeInfo],
+    ) -> BoxFuture<'e, Result<<Sqlite as HasStatement<'q>>::Statement, sqlx::Error>> {
+        self.pool.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<Sqlite>, sqlx::Error>> {
+        self.pool.describe(sql)
+    }
+}
+
+/// Results of one query, recorded when exhausted or dropped
+struct TimedStream<'e> {
+    pool: InstrumentedPool,
+    sql: &'e str,
+    inner: BoxStream<'e, Result<Either<SqliteQueryResult, SqliteRow>, sqlx::Error>>,
+    started: Option<Instant>, // Set on first poll; taken once recorded
+    rows: u64,                // Rows affected or returned so far
+    failed: bool,
+}
+
+impl TimedStream<'_> {
+    fn finish(&mut self) {
+        if let Some(started) = self.started.take() {
+            let rows = if self.failed { 0 } else { self.rows };
+            self.pool.record(self.sql, started.elapsed(), rows);
+        }
+    }
+}
+
+impl Stream for TimedStream<'_> {
+    type Item = Result<Either<SqliteQueryResult, SqliteRow>, sqlx::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        this.started.get_or_insert_with(Instant::now);
+        let item = ready!(this.inner.as_mut().poll_next(cx));
+        match &item {
+            Some(Ok(Either::Left(result))) => this.rows += result.rows_affected(),
+            Some(Ok(Either::Right(_))) => this.rows += 1,
+            Some(Err(_)) => this.failed = true,
+            None => this.finish(),
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for TimedStream<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
@@ -0,0 +1,232 @@
+/// SQLite maintenance: vacuum, analyze, integrity check and WAL checkpoint
+
+use crate::error::{OrchestratorError, Result};
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{Sqlite, SqliteConnection, SqlitePool};
+use std::time::{Duration, Instant};
+
+/// How long a full VACUUM waits for other writers before giving up
+const EXCLUSIVE_PROBE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How freed pages are reclaimed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VacuumMode {
+    /// Leave free pages in the file
+    None,
+    /// `PRAGMA incremental_vacuum`; only reclaims pages when the database
+    /// uses `auto_vacuum = INCREMENTAL`
+    Incremental,
+    /// Rebuild the whole file with `VACUUM`
+    Full,
+}
+
+impl VacuumMode {
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "none" => Ok(VacuumMode::None),
+            "incremental" => Ok(VacuumMode::Incremental),
+            "full" => Ok(VacuumMode::Full),
+            other => Err(OrchestratorError::InvalidInput(format!(
+                "Unknown vacuum mode: {} (expected none, incremental or full)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VacuumMode::None => "none",
+            VacuumMode::Incremental => "incremental",
+            VacuumMode::Full => "full",
+        }
+    }
+}
+
+/// Which maintenance steps `maintain` runs
+#[derive(Debug, Clone)]
+pub struct MaintenanceOptions {
+    pub vacuum: VacuumMode,
+    pub analyze: bool,
+    pub integrity_check: bool,
+    pub checkpoint: bool,
+}
+
+impl Default for MaintenanceOptions {
+    fn default() -> Self {
+        Self {
+            vacuum: VacuumMode::Incremental,
+            analyze: true,
+            integrity_check: true,
+            checkpoint: true,
+        }
+    }
+}
+
+impl MaintenanceOptions {
+    pub fn with_vacuum(mut self, vacuum: VacuumMode) -> Self {
+        self.vacuum = vacuum;
+        self
+    }
+
+    pub fn with_analyze(mut self, analyze: bool) -> Self {
+        self.analyze = analyze;
+        self
+    }
+
+    pub fn with_integrity_check(mut self, integrity_check: bool) -> Self {
+        self.integrity_check = integrity_check;
+        self
+    }
+
+    pub fn with_checkpoint(mut self, checkpoint: bool) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+}
+
+/// Outcome of a `maintain` run
+#[derive(Debug, Clone)]
+pub struct MaintenanceReport {
+    /// Database size in bytes (page count times page size) before and after
+    pub size_before: u64,
+    pub size_after: u64,
+    /// Free pages before and after vacuuming
+    pub freelist_before: u64,
+    pub freelist_after: u64,
+    pub vacuum: VacuumMode,
+    pub analyzed: bool,
+    /// Rows returned by `PRAGMA integrity_check`; `["ok"]` when healthy
+    pub integrity: Option<Vec<String>>,
+    /// WAL frames checkpointed, or `None` when not in WAL mode or skipped
+    pub wal_frames_checkpointed: Option<i64>,
+    pub duration: Duration,
+}
+
+impl MaintenanceReport {
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+
+    /// False only when an integrity check ran and reported problems
+    pub fn integrity_ok(&self) -> bool {
+        match &self.integrity {
+            Some(rows) => rows.len() == 1 && rows[0] == "ok",
+            None => true,
+        }
+    }
+}
+
+/// Run the configured maintenance steps on one connection from `pool`
+///
+/// A full VACUUM needs exclusive access, so it is refused with
+/// `ConflictDetected` while another connection, from this pool or another
+/// process, holds a write lock.
+pub async fn maintain(pool: &SqlitePool, options: &MaintenanceOptions) -> Result<MaintenanceReport> {
+    let started = Instant::now();
+    let mut conn = pool.acquire().await?;
+
+    if options.vacuum == VacuumMode::Full {
+        ensure_no_writers(&mut conn).await?;
+    }
+
+    let (size_before, freelist_before) = database_size(&mut conn).await?;
+
+    match options.vacuum {
+        VacuumMode::None => {}
+        VacuumMode::Incremental => {
+            // Returns no rows but must be stepped to completion
+            sqlx::query("PRAGMA incremental_vacuum").fetch_all(&mut *conn).await?;
+        }
+        VacuumMode::Full => {
+            // A writer may have started since the probe
+            sqlx::query("VACUUM").execute(&mut *conn).await.map_err(|e| {
+                if is_busy(&e) {
+                    vacuum_conflict()
+                } else {
+                    OrchestratorError::from(e)
+                }
+            })?;
+        }
+    }
+
+    if options.analyze {
+        sqlx::query("ANALYZE").execute(&mut *conn).await?;
+    }
+
+    let integrity = if options.integrity_check {
+        let rows: Vec<(String,)> = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_all(&mut *conn)
+            .await?;
+        Some(rows.into_iter().map(|(row,)| row).collect())
+    } else {
+        None
+    };
+
+    let wal_frames_checkpointed = if options.checkpoint {
+        let (_busy, _log, checkpointed): (i64, i64, i64) =
+            sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+                .fetch_one(&mut *conn)
+                .await?;
+        Some(checkpointed).filter(|frames| *frames >= 0)
+    } else {
+        None
+    };
+
+    let (size_after, freelist_after) = database_size(&mut conn).await?;
+
+    Ok(MaintenanceReport {
+        size_before,
+        size_after,
+        freelist_before,
+        freelist_after,
+        vacuum: options.vacuum,
+        analyzed: options.analyze,
+        integrity,
+        wal_frames_checkpointed,
+        duration: started.elapsed(),
+    })
+}
+
+/// SQLITE_BUSY or one of its extended codes
+fn is_busy(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|e| e.code())
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| code & 0xff == 5)
+}
+
+fn vacuum_conflict() -> OrchestratorError {
+    OrchestratorError::ConflictDetected("Refusing full VACUUM while another connection is writing".to_string())
+}
+
+/// Fail with `ConflictDetected` if a write lock cannot be taken within `EXCLUSIVE_PROBE_TIMEOUT`
+async fn ensure_no_writers(conn: &mut SqliteConnection) -> Result<()> {
+    let (previous,): (i64,) = sqlx::query_as("PRAGMA busy_timeout").fetch_one(&mut *conn).await?;
+    sqlx::query(&format!("PRAGMA busy_timeout = {}", EXCLUSIVE_PROBE_TIMEOUT.as_millis()))
+        .execute(&mut *conn)
+        .await?;
+    let probe = sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await;
+    if probe.is_ok() {
+        sqlx::query("ROLLBACK").execute(&mut *conn).await?;
+    }
+    sqlx::query(&format!("PRAGMA busy_timeout = {}", previous))
+        .execute(&mut *conn)
+        .await?;
+
+    match probe {
+        Ok(_) => Ok(()),
+        Err(e) if is_busy(&e) => Err(vacuum_conflict()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// (size in bytes, free pages) of the main database
+async fn database_size(conn: &mut PoolConnection<Sqlite>) -> Result<(u64, u64)> {
+    let (page_count, page_size, freelist): (i64, i64, i64) = sqlx::query_as(
+        "SELECT page_count, page_size, freelist_count FROM pragma_page_count, pragma_page_size, pragma_freelist_count",
+    )
+    .fetch_one(&mut **conn)
+    .await?;
+
+    Ok(((page_count * page_size) as u64, freelist as u64))
+}
@@ -1,5 +1,48 @@
+pub mod backup;
 pub mod db;
+pub mod instrument;
 pub mod kv;
+pub mod maintenance;
 
+pub use backup::{backup_path, backup_to, restore_from, verify_backup, BackupReport, BackupVerification};
 pub use db::Database;
+pub use instrument::{fingerprint, top_queries, InstrumentedPool, QueryLog, QueryStats};
 pub use kv::KeyValueStore;
+pub use maintenance::{maintain, MaintenanceOptions, MaintenanceReport, VacuumMode};
+
+use crate::error::{OrchestratorError, Result};
+use crate::security::validation::validate_identifier;
+use sqlx::sqlite::SqliteConnection;
+
+/// Add a column to `table` unless it already exists
+///
+/// For components that create their own tables outside the migrations.
+/// `table` and `column` must pass `validate_identifier`. The check and the
+/// ALTER run on `conn`, so both see the same database even where pooled
+/// connections do not, as with `:memory:`.
+pub(crate) async fn add_column_if_missing(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    for name in [table, column] {
+        validate_identifier(name).map_err(|e| OrchestratorError::InvalidInput(e.to_string()))?;
+    }
+    let (exists,): (bool,) = sqlx::query_as(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(OrchestratorError::from)?;
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(&mut *conn)
+            .await
+            .map_err(OrchestratorError::from)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+/// Tests for the uai-admin maintenance binary
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::Command;
+    use serde_json::Value;
+    use std::path::{Path, PathBuf};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uai-admin-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn admin() -> Command {
+        Command::cargo_bin("uai-admin").unwrap()
+    }
+
+    /// Run with `--json` and parse stdout, asserting success
+    fn run_json(args: &[&str]) -> Value {
+        let output = admin().arg("--json").args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    fn path_str(path: &Path) -> &str {
+        path.to_str().unwrap()
+    }
+
+    #[test]
+    fn test_migrate_plan_up_status_and_down() {
+        let dir = temp_dir();
+        let db = dir.join("orchestrator.db");
+
+        // Read-only commands do not create a database
+        admin().args(["migrate", "--db", path_str(&db), "status"]).assert().code(2);
+        assert!(!db.exists());
+
+        let up = run_json(&["migrate", "--db", path_str(&db), "up", "--to", "3"]);
+        assert_eq!(up["previous_version"], Value::Null);
+        assert_eq!(up["current_version"], 3);
+
+        let plan = run_json(&["migrate", "--db", path_str(&db), "plan", "--to", "5"]);
+        let pending: Vec<u64> = plan["pending"].as_array().unwrap().iter().map(|m| m["version"].as_u64().unwrap()).collect();
+        assert_eq!(pending, vec![4, 5]);
+
+        let up = run_json(&["migrate", "--db", path_str(&db), "up"]);
+        let latest = up["current_version"].as_u64().unwrap();
+        assert!(latest > 5);
+        let status = run_json(&["migrate", "--db", path_str(&db), "status"]);
+        assert!(status["migrations"].as_array().unwrap().iter().all(|m| m["applied"] == true));
+
+        let down = run_json(&["migrate", "--db", path_str(&db), "down", "--to", "2"]);
+        assert_eq!(down["current_version"], 2);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_index_build_stats_and_search() {
+        let dir = temp_dir();
+        let db = dir.join("index.db");
+        let src = dir.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("config.rs"), "fn load_config() -> Config {\n    read_settings()\n}\n").unwrap();
+        std::fs::write(src.join("retry.rs"), "fn retry_with_backoff() {\n    sleep_between_attempts();\n}\n").unwrap();
+
+        let built = run_json(&["index", "--db", path_str(&db), "--project", "proj", "build", path_str(&src)]);
+        assert_eq!(built["indexed_files"], 2);
+
+        let stats = run_json(&["index", "--db", path_str(&db), "--project", "proj", "stats"]);
+        assert_eq!(stats["files"], 2);
+        assert!(stats["blocks"].as_u64().unwrap() >= 2);
+
+        let found = run_json(&["search", "--db", path_str(&db), "--project", "proj", "load_config"]);
+        let results = found["results"].as_array().unwrap();
+        assert_eq!(results[0]["name"], "load_config");
+        assert_eq!(results[0]["file_path"], "config.rs");
+
+        // Plain output is one line per result
+        let output = admin()
+            .args(["search", "--db", path_str(&db), "--project", "proj", "retry_with_backoff"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).lines().next().unwrap().contains("retry.rs"));
+
+        let validated = run_json(&["index", "--db", path_str(&db), "--project", "proj", "validate", path_str(&src)]);
+        assert_eq!(validated["healthy"], true);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_exit_codes_separate_usage_errors_from_failures() {
+        let dir = temp_dir();
+        let missing = dir.join("missing.db");
+
+        // Unknown subcommand and missing database are usage errors
+        admin().args(["frobnicate"]).assert().code(2);
+        let output = admin()
+            .args(["--json", "search", "--db", path_str(&missing), "--project", "proj", "x"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+        assert_eq!(error["kind"], "usage");
+
+        let db = dir.join("orchestrator.db");
+        run_json(&["migrate", "--db", path_str(&db), "up"]);
+        admin()
+            .args(["cost", "--db", path_str(&db), "--since", "yesterday"])
+            .assert()
+            .code(2);
+        let report = run_json(&["cost", "--db", path_str(&db), "--since", "2024-01-01", "--until", "2024-02-01"]);
+        assert_eq!(report["total_usd"], 0.0);
+
+        // A database file that is not SQLite fails the operation itself
+        let corrupt = dir.join("corrupt.db");
+        std::fs::write(&corrupt, "not a database").unwrap();
+        admin().args(["db", "--db", path_str(&corrupt), "maintain"]).assert().code(1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,120 @@
+/// Tests for authorization-aware destructive operations
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{ContextManager, ContextStorage, Role};
+    use rust_core::indexer::storage::IndexStorage;
+    use rust_core::indexer::CodebaseIndexer;
+    use rust_core::migrations::{MigrationRunner, register_migrations};
+    use rust_core::security::{AuditLogger, Authorizer, RolePermissions, User};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+
+        pool
+    }
+
+    fn authorizer(pool: &SqlitePool) -> Authorizer {
+        Authorizer::new(RolePermissions::default()).with_audit_logger(AuditLogger::new(pool.clone()))
+    }
+
+    /// (event_type, user_id, resource_type, resource_id) of every audit event
+    async fn audit_events(pool: &SqlitePool) -> Vec<(String, String, String, String)> {
+        sqlx::query_as("SELECT event_type, user_id, resource_type, resource_id FROM audit_logs ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delete_context_as_checks_role_and_audits() {
+        let pool = create_test_pool().await;
+        let manager = ContextManager::new(ContextStorage::from_pool(pool.clone()).await.unwrap())
+            .with_authorizer(authorizer(&pool));
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        context.add_message(Role::User, "hello".to_string());
+        manager.update_context(&mut context).await.unwrap();
+        let id = context.conversation_id.clone();
+
+        let denied = manager.delete_context_as(&User::new("v1", "viewer"), &id).await;
+        assert!(matches!(denied, Err(OrchestratorError::Authorization(_))));
+        assert!(manager.get_context(&id).await.unwrap().is_some());
+
+        assert!(manager.delete_context_as(&User::new("u1", "user"), &id).await.unwrap());
+        assert!(manager.get_context(&id).await.unwrap().is_none());
+
+        let context_row = |event: &str, user: &str| {
+            (event.to_string(), user.to_string(), "context".to_string(), id.clone())
+        };
+        assert_eq!(
+            audit_events(&pool).await,
+            vec![context_row("authz.deny", "v1"), context_row("authz.allow", "u1")]
+        );
+
+        let (details,): (String,) = sqlx::query_as("SELECT details FROM audit_logs WHERE user_id = 'v1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let details: serde_json::Value = serde_json::from_str(&details).unwrap();
+        assert_eq!(details["role"], "viewer");
+        assert_eq!(details["permission"], "delete_context");
+    }
+
+    #[tokio::test]
+    async fn test_clear_index_as_requires_manage_index() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        storage.store_file("proj", "src/a.rs", "rust", &[]).await.unwrap();
+        storage.store_file("proj", "src/b.rs", "rust", &[]).await.unwrap();
+        storage.store_file("other", "src/a.rs", "rust", &[]).await.unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_authorizer(authorizer(&pool));
+
+        // Regular users cannot manage the index by default
+        let denied = indexer.clear_index_as(&User::new("u1", "user")).await;
+        assert!(matches!(denied, Err(OrchestratorError::Authorization(_))));
+        assert_eq!(indexer.clear_index_as(&User::new("root", "admin")).await.unwrap(), 2);
+
+        let (remaining,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM indexed_files")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 1);
+
+        let events: Vec<(String, String)> = audit_events(&pool)
+            .await
+            .into_iter()
+            .map(|(event, user, resource_type, resource_id)| {
+                assert_eq!((resource_type.as_str(), resource_id.as_str()), ("index", "proj"));
+                (event, user)
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("authz.deny".to_string(), "u1".to_string()),
+                ("authz.allow".to_string(), "root".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_audit_without_logger() {
+        let pool = create_test_pool().await;
+        let manager = ContextManager::new(ContextStorage::from_pool(pool.clone()).await.unwrap());
+        let denied = manager.delete_context_as(&User::new("g", "guest"), "missing").await;
+        assert!(matches!(denied, Err(OrchestratorError::Authorization(_))));
+        assert!(audit_events(&pool).await.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+/// Tests for database backup and restore
+
+#[cfg(test)]
+mod tests {
+    use rust_core::storage::{backup_to, restore_from, verify_backup};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uai-backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn open_pool(path: &Path) -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true))
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    async fn seed(pool: &SqlitePool, rows: usize) {
+        sqlx::query("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL)")
+            .execute(pool)
+            .await
+            .unwrap();
+        for i in 0..rows {
+            sqlx::query("INSERT INTO notes (body) VALUES (?)")
+                .bind(format!("note {}", i))
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn notes(pool: &SqlitePool) -> Vec<(i64, String)> {
+        sqlx::query_as("SELECT id, body FROM notes ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_backup_while_writing_and_restore() {
+        let dir = temp_dir();
+        let pool = open_pool(&dir.join("live.db")).await;
+        seed(&pool, 100).await;
+
+        // Keep writing from another task while the backup runs
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let pool = pool.clone();
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                let mut written = 0;
+                while !stop.load(Ordering::SeqCst) {
+                    sqlx::query("INSERT INTO notes (body) VALUES ('concurrent')")
+                        .execute(&pool)
+                        .await
+                        .unwrap();
+                    written += 1;
+                    tokio::task::yield_now().await;
+                }
+                written
+            })
+        };
+
+        while notes(&pool).await.len() == 100 {
+            tokio::task::yield_now().await;
+        }
+
+        let report = backup_to(&pool, &dir.join("snapshot.db"), false).await.unwrap();
+        stop.store(true, Ordering::SeqCst);
+        let written: usize = writer.await.unwrap();
+        assert!(written > 0);
+        assert!(!report.compressed);
+        assert!(report.bytes > 0);
+
+        let verification = verify_backup(&report.path).await.unwrap();
+        assert!(verification.is_ok());
+        assert_eq!(verification.tables, 1);
+
+        restore_from(&report.path, &dir.join("restored.db"), false).await.unwrap();
+        let restored = open_pool(&dir.join("restored.db")).await;
+        let snapshot = notes(&restored).await;
+
+        // A consistent snapshot: every seeded row plus a gap-free prefix of the concurrent ones
+        let live = notes(&pool).await;
+        assert!(snapshot.len() >= 100);
+        assert_eq!(snapshot[..], live[..snapshot.len()]);
+
+        restored.close().await;
+        pool.close().await;
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_compressed_backup_round_trip() {
+        let dir = temp_dir();
+        let pool = open_pool(&dir.join("live.db")).await;
+        seed(&pool, 500).await;
+
+        let report = backup_to(&pool, &dir.join("nightly.db.gz"), true).await.unwrap();
+        assert!(report.compressed);
+        let raw = std::fs::read(&report.path).unwrap();
+        assert_eq!(&raw[..2], &[0x1f, 0x8b]);
+
+        let verification = verify_backup(&report.path).await.unwrap();
+        assert!(verification.compressed);
+        assert!(verification.is_ok());
+
+        restore_from(&report.path, &dir.join("restored.db"), false).await.unwrap();
+        let restored = open_pool(&dir.join("restored.db")).await;
+        assert_eq!(notes(&restored).await, notes(&pool).await);
+
+        restored.close().await;
+        pool.close().await;
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_restore_refuses_existing_database_without_force() {
+        let dir = temp_dir();
+        let pool = open_pool(&dir.join("live.db")).await;
+        seed(&pool, 10).await;
+        let report = backup_to(&pool, &dir.join("snapshot.db"), false).await.unwrap();
+
+        sqlx::query("DELETE FROM notes").execute(&pool).await.unwrap();
+        pool.close().await;
+
+        let err = restore_from(&report.path, &dir.join("live.db"), false).await.unwrap_err();
+        assert!(matches!(err, OrchestratorError::ConflictDetected(_)));
+
+        restore_from(&report.path, &dir.join("live.db"), true).await.unwrap();
+        let pool = open_pool(&dir.join("live.db")).await;
+        assert_eq!(notes(&pool).await.len(), 10);
+
+        pool.close().await;
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_non_database() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("garbage.db"), vec![0x42; 8192]).unwrap();
+
+        assert!(verify_backup(&dir.join("garbage.db")).await.is_err());
+        assert!(restore_from(&dir.join("garbage.db"), &dir.join("restored.db"), false).await.is_err());
+        assert!(!dir.join("restored.db").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,75 @@
+/// Generous timing ceilings for indexing, search and context management
+///
+/// Run with `cargo test -p rust-core --features bench-smoke`. The ceilings
+/// are far above normal timings so only order-of-magnitude regressions fail.
+#[cfg(all(test, feature = "bench-smoke"))]
+mod tests {
+    use rust_core::context::window::ContextWindowManager;
+    use rust_core::context::{Context, Role};
+    use rust_core::indexer::codebase::CodebaseIndexer;
+    use rust_core::indexer::search::SemanticSearch;
+    use rust_core::indexer::storage::IndexStorage;
+    use rust_core::indexer::testgen::{generate_blocks, generate_corpus, CorpusSpec};
+    use rust_core::migrations::{register_migrations, MigrationRunner};
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::time::{Duration, Instant};
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+        pool
+    }
+
+    fn assert_under(what: &str, elapsed: Duration, ceiling: Duration) {
+        assert!(elapsed < ceiling, "{} took {:?}, ceiling is {:?}", what, elapsed, ceiling);
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_stays_under_ceiling() {
+        let root = std::env::temp_dir().join(format!("uai-smoke-{}", uuid::Uuid::new_v4()));
+        let spec = CorpusSpec::new(100, 10).with_seed(1);
+        generate_corpus(&spec).write_to(&root).unwrap();
+
+        let mut indexer = CodebaseIndexer::new("smoke".to_string(), IndexStorage::new(create_test_pool().await));
+        let started = Instant::now();
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 100);
+        assert_under("Indexing 100 files", started.elapsed(), Duration::from_secs(30));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_search_over_10k_blocks_stays_under_ceiling() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        for (i, chunk) in generate_blocks(10_000, 7).chunks(100).enumerate() {
+            storage.store_file("smoke", &format!("gen/file_{}.src", i), &chunk[0].language, chunk).await.unwrap();
+        }
+
+        let mut search = SemanticSearch::new(storage);
+        let started = Instant::now();
+        for _ in 0..5 {
+            let results = search.search("smoke", "retry config", 10).await.unwrap().results;
+            assert!(!results.is_empty());
+        }
+        assert_under("5 searches over 10k blocks", started.elapsed(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_manage_context_stays_under_ceiling() {
+        let mut context = Context::new(None);
+        for i in 0..5_000 {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            context.add_message(role, format!("message {} about the config loader and its retry budget", i));
+        }
+
+        let started = Instant::now();
+        ContextWindowManager::new(1000).manage_context(&mut context, "gpt-4");
+        assert_under("Managing a 5000-message context", started.elapsed(), Duration::from_secs(5));
+        assert!(context.messages.len() < 5_000);
+    }
+}
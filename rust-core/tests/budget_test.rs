@@ -0,0 +1,76 @@
+/// Tests for budget-aware output token caps
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::cost::storage::{CostRecord, CostStorage};
+    use rust_core::cost::{BudgetManager, CostCalculator};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::HashMap;
+
+    async fn create_storage() -> CostStorage {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+        CostStorage::from_pool(pool).await.unwrap()
+    }
+
+    fn spend(project_id: &str, cost_usd: f64) -> CostRecord {
+        CostRecord {
+            id: None,
+            request_id: None,
+            tool: "chatgpt".to_string(),
+            model: "gpt-3.5-turbo".to_string(),
+            input_tokens: 1000,
+            output_tokens: 1000,
+            cost_usd,
+            timestamp: Utc::now(),
+            user_id: None,
+            project_id: Some(project_id.to_string()),
+            conversation_id: None,
+            labels: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_max_affordable_output_tokens_math() {
+        let calculator = CostCalculator::new();
+        // $1.00 - 100k input at $3/1M = $0.70 left, at $15/1M output
+        assert_eq!(
+            calculator.max_affordable_output_tokens("claude-3-5-sonnet-20241022", 1.0, 100_000),
+            Some(46_666)
+        );
+        // $0.50 - 1k input at $30/1M = $0.47 left, at $60/1M output
+        assert_eq!(calculator.max_affordable_output_tokens("gpt-4", 0.5, 1000), Some(7833));
+        // The input alone costs $0.03
+        assert_eq!(calculator.max_affordable_output_tokens("gpt-4", 0.01, 1000), Some(0));
+        assert_eq!(calculator.max_affordable_output_tokens("no-such-model", 100.0, 1000), None);
+    }
+
+    #[tokio::test]
+    async fn test_nearly_exhausted_budget_leaves_small_cap() {
+        let storage = create_storage().await;
+        storage.record_cost(&spend("proj", 9.999)).await.unwrap();
+        let mut config = OrchestratorConfig::default();
+        config.cost.monthly_budget_usd = 10.0;
+        let budgets = config.build_budget_manager(storage);
+
+        let remaining = budgets.remaining_budget("proj").await.unwrap().unwrap();
+        assert!((remaining - 0.001).abs() < 1e-9);
+        // $0.001 - 1k input at $0.5/1M = $0.0005 left, at $1.5/1M output
+        assert_eq!(budgets.affordable_output("proj", "gpt-3.5-turbo", 1000).await.unwrap(), Some(333));
+        assert_eq!(budgets.affordable_output("proj", "gpt-4", 1000).await.unwrap(), Some(0));
+        assert_eq!(budgets.affordable_output("proj", "no-such-model", 1000).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_budget_caps_only_priced_models() {
+        let budgets = BudgetManager::new(create_storage().await, CostCalculator::new());
+        assert_eq!(budgets.remaining_budget("proj").await.unwrap(), None);
+        assert_eq!(budgets.affordable_output("proj", "gpt-4", 1000).await.unwrap(), Some(u32::MAX));
+        assert_eq!(budgets.affordable_output("proj", "no-such-model", 1000).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,108 @@
+/// Tests for the bulkhead and guarded calls
+
+#[cfg(test)]
+mod tests {
+    use rust_core::error::OrchestratorError;
+    use rust_core::resilience::{guarded, Bulkhead, CircuitBreaker, RateLimiter};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_max_concurrent_is_never_exceeded() {
+        let bulkhead = Bulkhead::new("tool", 3, 100);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let bulkhead = bulkhead.clone();
+                let running = running.clone();
+                let peak = peak.clone();
+                tokio::spawn(async move {
+                    let _permit = bulkhead.acquire().await.unwrap();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        let stats = bulkhead.stats();
+        assert_eq!((stats.in_flight, stats.queued, stats.rejected), (0, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_when_queue_is_full() {
+        let bulkhead = Bulkhead::new("tool", 1, 1);
+        let held = bulkhead.acquire().await.unwrap();
+
+        let waiter = {
+            let bulkhead = bulkhead.clone();
+            tokio::spawn(async move { bulkhead.acquire().await.map(|_| ()) })
+        };
+        while bulkhead.stats().queued == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        match bulkhead.acquire().await {
+            Err(OrchestratorError::BulkheadFull(_)) => {}
+            other => panic!("expected BulkheadFull, got {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(bulkhead.try_acquire(), Err(OrchestratorError::BulkheadFull(_))));
+
+        let stats = bulkhead.stats();
+        assert_eq!((stats.in_flight, stats.queued, stats.rejected), (1, 1, 2));
+
+        // Releasing the held permit lets the queued caller through
+        drop(held);
+        waiter.await.unwrap().unwrap();
+        let stats = bulkhead.stats();
+        assert_eq!((stats.in_flight, stats.queued), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_frees_queue_slot() {
+        let bulkhead = Bulkhead::new("tool", 1, 1);
+        let _held = bulkhead.acquire().await.unwrap();
+
+        let timed_out = tokio::time::timeout(Duration::from_millis(10), bulkhead.acquire()).await;
+        assert!(timed_out.is_err());
+        assert_eq!(bulkhead.stats().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_guarded_applies_breaker_bulkhead_and_limiter() {
+        let bulkhead = Bulkhead::new("tool", 1, 0);
+        let breaker = CircuitBreaker::new("tool", 1, Duration::from_secs(60));
+        let limiter = RateLimiter::new("tool", 10, 1.0);
+
+        let value = guarded(&bulkhead, &breaker, &limiter, async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+        assert_eq!(limiter.stats().acquired, 1);
+
+        // A full bulkhead rejects without taking a token or tripping the breaker
+        let held = bulkhead.acquire().await.unwrap();
+        let rejected = guarded(&bulkhead, &breaker, &limiter, async { Ok(()) }).await;
+        assert!(matches!(rejected, Err(OrchestratorError::BulkheadFull(_))));
+        assert_eq!(limiter.stats().acquired, 1);
+        drop(held);
+
+        // A failing call opens the breaker; later calls fail before the bulkhead
+        let failed: Result<(), _> = guarded(&bulkhead, &breaker, &limiter, async {
+            Err(OrchestratorError::Timeout("slow tool".to_string()))
+        })
+        .await;
+        assert!(failed.is_err());
+
+        let _held = bulkhead.acquire().await.unwrap();
+        let blocked = guarded(&bulkhead, &breaker, &limiter, async { Ok(()) }).await;
+        assert!(matches!(blocked, Err(OrchestratorError::CircuitBreakerOpen(_))));
+        assert_eq!(bulkhead.stats().rejected, 1);
+    }
+}
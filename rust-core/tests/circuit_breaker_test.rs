@@ -0,0 +1,70 @@
+/// Tests for circuit breaker trips and their telemetry
+
+#[cfg(test)]
+mod tests {
+    use rust_core::observability::testing::{CapturingMetrics, CapturingSpanExporter};
+    use rust_core::resilience::{CircuitBreaker, CircuitState};
+    use rust_core::OrchestratorError;
+    use std::time::Duration;
+    use tracing::Level;
+
+    async fn fail(breaker: &CircuitBreaker) -> Result<(), OrchestratorError> {
+        breaker
+            .call(|| async { Err::<(), _>(OrchestratorError::ToolUnavailable("upstream down".to_string())) })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_trip_is_counted_and_logged() {
+        let capture = CapturingSpanExporter::new();
+        let _guard = capture.install();
+        let metrics = CapturingMetrics::new();
+        let breaker = CircuitBreaker::new("claude", 2, Duration::from_millis(20)).with_metrics(metrics.collector());
+        let trips = |metrics: &CapturingMetrics| {
+            metrics.snapshot().value("uai_circuit_breaker_trips_total", &[("breaker", "claude")])
+        };
+
+        assert!(fail(&breaker).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(trips(&metrics), 0.0);
+
+        assert!(fail(&breaker).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(trips(&metrics), 1.0);
+
+        // Calls rejected while open are not trips
+        assert!(matches!(fail(&breaker).await, Err(OrchestratorError::CircuitBreakerOpen(_))));
+        assert_eq!(trips(&metrics), 1.0);
+
+        // A failed probe after the timeout opens it again
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(matches!(fail(&breaker).await, Err(OrchestratorError::ToolUnavailable(_))));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(trips(&metrics), 2.0);
+
+        let warnings = capture.events_at(Level::WARN);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().all(|event| event.message() == Some("circuit breaker opened")));
+        assert!(warnings.iter().all(|event| event.field("breaker") == Some("claude")));
+        assert!(warnings[0].field("error").unwrap().contains("upstream down"));
+    }
+
+    #[test]
+    fn test_capture_is_scoped_to_the_closure() {
+        let capture = CapturingSpanExporter::new();
+        capture.with_default(|| {
+            let _span = tracing::info_span!("probe", breaker = "gpt").entered();
+            tracing::warn!("inside");
+        });
+        tracing::warn!("outside");
+
+        let spans = capture.spans_named("probe");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].fields.get("breaker").map(String::as_str), Some("gpt"));
+        assert!(spans[0].closed);
+        let events = capture.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message(), Some("inside"));
+        assert_eq!(events[0].span.as_deref(), Some("probe"));
+    }
+}
@@ -0,0 +1,78 @@
+/// Tests for rendering codebase context into prompts
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::token_counter::TokenCounter;
+    use rust_core::context::{CodebaseContext, Context, ContextManager, InMemoryContextStore, Role, CODEBASE_HEADER};
+
+    fn repo_context() -> CodebaseContext {
+        CodebaseContext {
+            relevant_files: vec![
+                "/home/dev/repo/src/indexer/search.rs".to_string(),
+                "/home/dev/repo/src/indexer/codebase.rs".to_string(),
+                "/home/dev/repo/src/context/mod.rs".to_string(),
+                "/home/dev/repo/src/indexer/codebase.rs".to_string(),
+                "/home/dev/repo/README.md".to_string(),
+            ],
+            semantic_matches: vec![
+                "/home/dev/repo/src/indexer/search.rs:805-820 SearchResult".to_string(),
+                "/home/dev/repo/src/indexer/search.rs:805-820 — SearchResult".to_string(),
+            ],
+        }
+    }
+
+    const FULL: &str = "Codebase context:
+Semantic matches:
+- src/indexer/search.rs:805-820 — SearchResult
+Relevant files:
+- src/indexer/: codebase.rs
+- src/context/: mod.rs
+- README.md";
+
+    #[test]
+    fn test_render_dedups_and_relativizes() {
+        let rendered = repo_context().render_for_prompt(1000, &TokenCounter::new());
+        assert_eq!(rendered, FULL);
+    }
+
+    #[test]
+    fn test_render_stays_within_budget() {
+        let counter = TokenCounter::new();
+        let prefix = FULL.lines().take(5).collect::<Vec<_>>().join("\n");
+        let budget = counter.estimate_tokens(&prefix);
+
+        let rendered = repo_context().render_for_prompt(budget, &counter);
+        assert_eq!(rendered, prefix);
+        assert!(counter.estimate_tokens(&rendered) <= budget);
+
+        // Not even the first match fits
+        assert_eq!(repo_context().render_for_prompt(5, &counter), "");
+    }
+
+    #[test]
+    fn test_attach_replaces_previous_codebase_message() {
+        let manager = ContextManager::new(InMemoryContextStore::new());
+        let mut context = Context::new(Some("proj".to_string()));
+        context.add_message(Role::User, "Where are search results built?".to_string());
+
+        assert!(manager.attach_codebase_context(&mut context, repo_context(), 1000));
+        assert_eq!(context.messages.len(), 2);
+        assert_eq!(context.messages[0].role, Role::System);
+        assert_eq!(context.messages[0].content, FULL);
+
+        let narrower = CodebaseContext {
+            relevant_files: vec!["src/lib.rs".to_string()],
+            semantic_matches: Vec::new(),
+        };
+        assert!(manager.attach_codebase_context(&mut context, narrower.clone(), 1000));
+        assert_eq!(context.messages.len(), 2);
+        assert_eq!(context.messages[0].content, format!("{}\nRelevant files:\n- src/: lib.rs", CODEBASE_HEADER));
+        assert_eq!(context.messages[1].content, "Where are search results built?");
+        assert_eq!(context.codebase_context, Some(narrower.clone()));
+
+        // A budget nothing fits in removes the message
+        assert!(!manager.attach_codebase_context(&mut context, narrower, 1));
+        assert_eq!(context.messages.len(), 1);
+        assert_eq!(context.messages[0].role, Role::User);
+    }
+}
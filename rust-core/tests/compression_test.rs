@@ -0,0 +1,173 @@
+/// Tests for context compression previews
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::compression::{ContextCompressor, RemovalReason, TruncationMode};
+    use rust_core::context::{Context, Role};
+    use rust_core::OrchestratorError;
+
+    fn fixture() -> Context {
+        let mut context = Context::new(None);
+        context.add_message(Role::User, "how do I parse a config file".to_string());
+        context.add_message(Role::User, "how do I parse a config file".to_string());
+        context.add_message(Role::Assistant, "Use   toml::from_str\n\n  on the contents".to_string());
+        context.add_message(Role::User, "thanks that works great for the main config file in my project now".to_string());
+        context.add_message(Role::User, "thanks that works great for the main config file in my project now!".to_string());
+        context.add_message(Role::Assistant, "x".repeat(300));
+        context
+    }
+
+    fn contents(context: &Context) -> Vec<(Role, String)> {
+        context.messages.iter().map(|m| (m.role.clone(), m.content.clone())).collect()
+    }
+
+    #[test]
+    fn test_preview_then_apply_matches_compress() {
+        let compressor = ContextCompressor::new().with_max_length(100);
+        let original = fixture();
+
+        let mut direct = original.clone();
+        let direct_stats = compressor.compress(&mut direct);
+
+        let plan = compressor.preview(&original);
+        assert_eq!(plan.stats, direct_stats);
+        let reasons: Vec<(usize, RemovalReason)> = plan.removals.iter().map(|r| (r.index, r.reason)).collect();
+        assert_eq!(reasons, vec![(1, RemovalReason::Duplicate), (3, RemovalReason::Similar)]);
+        let truncations: Vec<(usize, usize)> = plan.truncations().map(|t| (t.index, t.original_length)).collect();
+        assert_eq!(truncations, vec![(5, 300)]);
+        assert!(plan.stats.estimated_tokens_saved() > 0);
+
+        let mut planned = original.clone();
+        let applied_stats = compressor.apply_plan(&mut planned, &plan).unwrap();
+        assert_eq!(contents(&planned), contents(&direct));
+        assert_eq!(applied_stats, direct_stats);
+    }
+
+    #[test]
+    fn test_preview_does_not_modify_context() {
+        let context = fixture();
+        let before = contents(&context);
+        let plan = ContextCompressor::new().with_max_length(100).preview(&context);
+        assert!(!plan.is_empty());
+        assert_eq!(contents(&context), before);
+    }
+
+    #[test]
+    fn test_plan_survives_appended_messages() {
+        let compressor = ContextCompressor::new().with_max_length(100);
+        let mut context = fixture();
+        let plan = compressor.preview(&context);
+
+        context.add_message(Role::User, "one more   question".to_string());
+        compressor.apply_plan(&mut context, &plan).unwrap();
+        assert_eq!(context.messages.len(), 5);
+        // Messages the plan did not cover are left as they are
+        assert_eq!(context.messages[4].content, "one more   question");
+    }
+
+    #[test]
+    fn test_stale_plan_is_rejected() {
+        let compressor = ContextCompressor::new().with_max_length(100);
+        let mut context = fixture();
+        let plan = compressor.preview(&context);
+
+        context.messages[2].content = "edited after preview".to_string();
+        let before = contents(&context);
+        match compressor.apply_plan(&mut context, &plan) {
+            Err(OrchestratorError::ConflictDetected(_)) => {}
+            other => panic!("expected ConflictDetected, got {:?}", other),
+        }
+        assert_eq!(contents(&context), before);
+
+        let mut shortened = fixture();
+        shortened.messages.pop();
+        assert!(matches!(
+            compressor.apply_plan(&mut shortened, &plan),
+            Err(OrchestratorError::ConflictDetected(_))
+        ));
+    }
+
+    /// 100 sentences of 40 bytes, one of them about the fix
+    fn long_log() -> String {
+        (0..100)
+            .map(|i| match i {
+                50 => "The fix was to retry the write on lock.".to_string(),
+                _ => format!("Step {:03} finished without any output.", i),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn truncated(mode: TruncationMode, content: &str) -> String {
+        let compressor = ContextCompressor::new().with_max_length(200).with_truncation_mode(mode);
+        let mut context = Context::new(None);
+        context.add_message(Role::User, content.to_string());
+        compressor.compress(&mut context);
+        context.messages[0].content.clone()
+    }
+
+    #[test]
+    fn test_truncation_modes() {
+        let log = long_log();
+
+        // Markers count against the 200 bytes, so truncating again changes nothing
+        let room = |marker: &str| 200 - marker.len() - log.len().to_string().len();
+
+        let head_tail = truncated(TruncationMode::HeadTail, &log);
+        let half = room("... [truncated  chars] ...") / 2;
+        assert!(head_tail.starts_with(&log[..half]));
+        assert!(head_tail.ends_with(&log[log.len() - half..]));
+        assert!(head_tail.contains(&format!("[truncated {} chars]", log.len() - 2 * half)));
+        assert!(head_tail.len() <= 200);
+        assert_eq!(truncated(TruncationMode::HeadTail, &head_tail), head_tail);
+
+        let head_only = truncated(TruncationMode::HeadOnly, &log);
+        let kept = room("... [truncated  chars]");
+        assert!(head_only.starts_with(&log[..kept]));
+        assert!(head_only.ends_with(&format!("[truncated {} chars]", log.len() - kept)));
+        assert!(head_only.len() <= 200);
+
+        let tail_only = truncated(TruncationMode::TailOnly, &log);
+        let kept = room("[truncated  chars] ...");
+        assert!(tail_only.starts_with(&format!("[truncated {} chars]", log.len() - kept)));
+        assert!(tail_only.ends_with(&log[log.len() - kept..]));
+        assert!(tail_only.len() <= 200);
+
+        // The middle is replaced by its most important sentence, not a count
+        let summarized = truncated(TruncationMode::SummaryMiddle, &log);
+        let max = room("... [summary of  chars: ] ...");
+        let part = (max - max / 4) / 2;
+        assert!(summarized.starts_with(&log[..part]));
+        assert!(summarized.ends_with(&log[log.len() - part..]));
+        assert!(summarized.contains("The fix was to retry the write on lock"));
+        assert!(!summarized.contains("[truncated"));
+        assert!(summarized.len() <= 200);
+    }
+
+    #[test]
+    fn test_truncation_mode_is_chosen_per_role() {
+        let log = long_log();
+        let compressor = ContextCompressor::new()
+            .with_max_length(200)
+            .with_role_truncation(Role::Tool, TruncationMode::TailOnly)
+            .with_role_truncation(Role::System, TruncationMode::HeadOnly);
+        assert_eq!(compressor.truncation_mode(&Role::User), TruncationMode::HeadTail);
+
+        let mut context = Context::new(None);
+        context.add_message(Role::User, log.clone());
+        context.add_message(Role::Tool, log.clone());
+        context.add_message(Role::System, log.clone());
+        context.add_message(Role::Assistant, "short".to_string());
+        compressor.compress(&mut context);
+
+        assert_eq!(context.messages.len(), 4);
+        let (user, tool, system, assistant) = (&context.messages[0], &context.messages[1], &context.messages[2], &context.messages[3]);
+        assert!(user.content.starts_with(&log[..80]) && user.content.ends_with(&log[log.len() - 80..]));
+        assert!(tool.content.starts_with("[truncated") && tool.content.ends_with(&log[log.len() - 160..]));
+        assert!(system.content.starts_with(&log[..160]) && system.content.ends_with("chars]"));
+        assert_eq!(assistant.content, "short");
+
+        assert_eq!(TruncationMode::parse("Tail_Only").unwrap(), TruncationMode::TailOnly);
+        assert!(matches!(TruncationMode::parse("middle"), Err(OrchestratorError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,138 @@
+/// Tests for orchestrator configuration loading
+
+#[cfg(test)]
+mod tests {
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::error::OrchestratorError;
+
+    const FILE_CONFIG: &str = r#"
+[routing]
+default_tool = "gpt"
+code_editing = ["claude"]
+
+[context]
+reserved_tokens = 2000
+
+[context.summarizer]
+summary_ratio = 0.5
+
+[resilience]
+retry_max_attempts = 5
+"#;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn validation_message(config: &OrchestratorConfig) -> String {
+        match config.validate() {
+            Err(OrchestratorError::InvalidConfig(msg)) => msg,
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_env_overrides_file_overrides_defaults() {
+        let mut config = OrchestratorConfig::from_toml_str(FILE_CONFIG).unwrap();
+
+        // File values replace defaults; unspecified fields keep them
+        assert_eq!(config.routing.default_tool, "gpt");
+        assert_eq!(config.context.reserved_tokens, 2000);
+        assert_eq!(config.context.summarizer.message_threshold, 50);
+        assert_eq!(config.resilience.retry_max_attempts, 5);
+
+        config.apply_env_overrides(env(&[
+            ("UAI_CONTEXT_RESERVED_TOKENS", "4000"),
+            ("UAI_CONTEXT_SUMMARIZER_MESSAGE_THRESHOLD", "20"),
+            ("UAI_ROUTING_CODE_EDITING", "claude, gpt"),
+            ("UAI_INDEXER_SKIP_PATTERNS", "dist,build"),
+            ("UAI_RESILIENCE_RETRY_JITTER", "false"),
+            ("UAI_UNRELATED_SETTING", "ignored"),
+            ("PATH", "/usr/bin"),
+        ])).unwrap();
+
+        assert_eq!(config.context.reserved_tokens, 4000);
+        assert_eq!(config.context.summarizer.message_threshold, 20);
+        assert_eq!(config.context.summarizer.summary_ratio, 0.5);
+        assert_eq!(config.routing.default_tool, "gpt");
+        assert_eq!(config.routing.rules["code_editing"], vec!["claude", "gpt"]);
+        assert_eq!(config.indexer.skip_patterns, vec!["dist", "build"]);
+        assert!(!config.resilience.retry_jitter);
+        assert_eq!(config.resilience.retry_max_attempts, 5);
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_from_file_applies_process_env() {
+        let path = std::env::temp_dir().join(format!("uai-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, FILE_CONFIG).unwrap();
+
+        std::env::set_var("UAI_INDEXER_MAX_CACHED_TREES", "16");
+        let config = OrchestratorConfig::from_file(&path);
+        std::env::remove_var("UAI_INDEXER_MAX_CACHED_TREES");
+        std::fs::remove_file(&path).ok();
+
+        let config = config.unwrap();
+        assert_eq!(config.indexer.max_cached_trees, 16);
+        assert_eq!(config.context.reserved_tokens, 2000);
+    }
+
+    #[test]
+    fn test_invalid_env_value_is_reported() {
+        let mut config = OrchestratorConfig::default();
+        let err = config
+            .apply_env_overrides(env(&[("UAI_CONTEXT_RESERVED_TOKENS", "lots")]))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("UAI_CONTEXT_RESERVED_TOKENS: expected an integer, got 'lots'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_validation_reports_out_of_range_values() {
+        let mut config = OrchestratorConfig::from_toml_str(FILE_CONFIG).unwrap();
+        config.context.summarizer.summary_ratio = 1.5;
+        config.context.reserved_tokens = 500_000;
+        config.resilience.retry_initial_delay_ms = 20_000;
+        config.cost.daily_budget_usd = -1.0;
+
+        let message = validation_message(&config);
+        assert!(message.contains("context.summarizer.summary_ratio must be in (0.0, 1.0] (got 1.5)"), "{}", message);
+        assert!(message.contains("context.reserved_tokens must be at most 200000 (got 500000)"), "{}", message);
+        assert!(message.contains("resilience.retry_initial_delay_ms (20000) must not exceed"), "{}", message);
+        assert!(message.contains("cost.daily_budget_usd must not be negative (got -1)"), "{}", message);
+    }
+
+    #[test]
+    fn test_validation_rejects_empty_routing_rule() {
+        let config = OrchestratorConfig::from_toml_str("[routing]\nresearch = []\n").unwrap();
+        assert_eq!(validation_message(&config), "routing.research must list at least one tool");
+    }
+
+    #[test]
+    fn test_builders_use_config_values() {
+        let config = OrchestratorConfig::from_toml_str(FILE_CONFIG).unwrap();
+        let router = config.build_router();
+        let decision = router.route(&rust_core::router::RoutingRequest {
+            message: "hello".to_string(),
+            conversation_id: None,
+            project_id: None,
+            explicit_tool: Some("gpt".to_string()),
+            request_id: None,
+        });
+        assert_eq!(decision.selected_tools, vec!["gpt"]);
+
+        use rust_core::resilience::RetryPolicy;
+        assert_eq!(config.build_retry_policy().max_attempts(), 5);
+    }
+
+    #[test]
+    fn test_default_config_file_is_valid() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../config/default.toml");
+        let config = OrchestratorConfig::from_toml_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.routing.rules["general_chat"], vec!["claude", "gpt"]);
+    }
+}
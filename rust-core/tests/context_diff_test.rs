@@ -0,0 +1,94 @@
+/// Tests for context diffs and explain mode
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::compression::ContextCompressor;
+    use rust_core::context::diff::content_hash;
+    use rust_core::context::window::ContextWindowManager;
+    use rust_core::context::{diff_contexts, render_text, Context, Message, Role};
+
+    fn message(role: Role, content: &str, timestamp: i64) -> Message {
+        Message {
+            role,
+            content: content.to_string(),
+            timestamp,
+            pinned: false,
+        }
+    }
+
+    fn fixture() -> Context {
+        let mut context = Context::new(None);
+        context.messages = vec![
+            message(Role::User, "how do I parse a config file", 1),
+            message(Role::User, "how do I parse a config file", 2),
+            message(Role::Assistant, "Use toml::from_str on the contents", 3),
+            message(Role::User, "thanks that works great for the main config file in my project now", 4),
+            message(Role::User, "thanks that works great for the main config file in my project now!", 5),
+            message(Role::Assistant, &"x".repeat(300), 6),
+        ];
+        context
+    }
+
+    #[test]
+    fn test_truncation_drops_two_messages_and_modifies_a_third() {
+        let compressor = ContextCompressor::new().with_max_length(100);
+        let mut context = fixture();
+        let (stats, diff) = compressor.compress_explained(&mut context);
+
+        assert_eq!(stats.duplicates_removed + stats.similar_removed, 2);
+        let removed: Vec<usize> = diff.removed.iter().map(|m| m.index).collect();
+        assert_eq!(removed, vec![1, 3]);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.unchanged, 3);
+
+        assert_eq!(diff.modified.len(), 1);
+        let modified = &diff.modified[0];
+        assert_eq!((modified.before_index, modified.after_index), (5, 3));
+        assert_eq!(modified.role, Role::Assistant);
+        assert_eq!(modified.before_hash, content_hash(&"x".repeat(300)));
+        assert_eq!(modified.after_hash, content_hash(&context.messages[3].content));
+        assert!(modified.after_tokens < modified.before_tokens);
+
+        assert!(diff.token_delta() < 0);
+
+        let text = render_text(&diff);
+        assert!(text.contains("2 removed, 1 modified, 0 added"));
+        assert!(text.contains("- [1] user"));
+        assert!(text.contains("- [3] user"));
+        assert!(text.contains("~ [5 -> 3] assistant"));
+    }
+
+    #[test]
+    fn test_added_messages_and_tool_calls() {
+        let before = fixture();
+        let mut after = before.clone();
+        after.messages.insert(0, message(Role::System, "Summary of earlier conversation", 7));
+        after.add_tool_call("claude".to_string(), "req".to_string(), "resp".to_string());
+
+        let diff = diff_contexts(&before, &after);
+        let added: Vec<usize> = diff.added.iter().map(|m| m.index).collect();
+        assert_eq!(added, vec![0]);
+        assert_eq!(diff.added[0].preview, "Summary of earlier conversation");
+        assert!(diff.removed.is_empty() && diff.modified.is_empty());
+        assert_eq!(diff.unchanged, before.messages.len());
+        assert_eq!(diff.tool_calls_added.len(), 1);
+        assert_eq!(diff.tool_calls_added[0].tool, "claude");
+        assert!(render_text(&diff).contains("+ tool call [0] claude"));
+
+        let reverse = diff_contexts(&after, &before);
+        assert_eq!(reverse.removed.len(), 1);
+        assert_eq!(reverse.tool_calls_removed.len(), 1);
+        assert_eq!(reverse.token_delta(), -diff.token_delta());
+    }
+
+    #[test]
+    fn test_window_manager_explain_reports_no_change_when_context_fits() {
+        let manager = ContextWindowManager::default();
+        let mut context = fixture();
+        let diff = manager.manage_context_explained(&mut context, "gpt-4");
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged, 6);
+        assert!(render_text(&diff).starts_with("No changes (6 messages"));
+    }
+}
@@ -0,0 +1,117 @@
+/// Tests for ContextManager message hooks
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{
+        Context, ContextManager, HookDecision, HookOutcome, InMemoryContextStore, Message, MessageHook, Role,
+        ValidationHook,
+    };
+    use rust_core::error::Result;
+    use rust_core::OrchestratorError;
+
+    /// Drops messages containing `keyword`
+    struct DropKeyword {
+        keyword: &'static str,
+    }
+
+    impl MessageHook for DropKeyword {
+        fn name(&self) -> &str {
+            "drop_keyword"
+        }
+
+        fn before_save(&self, message: &mut Message, _context: &Context) -> Result<HookDecision> {
+            if message.content.contains(self.keyword) {
+                return Ok(HookDecision::Drop);
+            }
+            Ok(HookDecision::Keep)
+        }
+    }
+
+    /// Appends `suffix` to every message
+    struct AppendSuffix {
+        name: &'static str,
+        suffix: &'static str,
+    }
+
+    impl MessageHook for AppendSuffix {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn before_save(&self, message: &mut Message, _context: &Context) -> Result<HookDecision> {
+            message.content.push_str(self.suffix);
+            Ok(HookDecision::Modified)
+        }
+    }
+
+    fn contents(context: &Context) -> Vec<&str> {
+        context.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_hook_drops_messages_with_keyword() {
+        let mut manager = ContextManager::new(InMemoryContextStore::new());
+        manager.register_message_hook(Box::new(DropKeyword { keyword: "password" }));
+
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        context.add_message(Role::User, "hello".to_string());
+        context.add_message(Role::User, "my password is hunter2".to_string());
+        context.add_message(Role::Assistant, "hi".to_string());
+        let report = manager.update_context(&mut context).await.unwrap();
+
+        assert_eq!(report.messages_processed, 3);
+        assert_eq!(report.messages_dropped, 1);
+        assert_eq!(
+            report.hook_outcomes,
+            vec![HookOutcome {
+                hook: "drop_keyword".to_string(),
+                message_index: 1,
+                decision: HookDecision::Drop,
+            }]
+        );
+        let stored = manager.get_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(contents(&stored), vec!["hello", "hi"]);
+
+        // Only messages added since the last save go through the hooks again
+        context.add_message(Role::User, "another password".to_string());
+        context.add_message(Role::User, "bye".to_string());
+        let report = manager.update_context(&mut context).await.unwrap();
+        assert_eq!(report.messages_processed, 2);
+        assert_eq!(report.messages_dropped, 1);
+        assert_eq!(report.hook_outcomes[0].message_index, 2);
+        let stored = manager.get_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(contents(&stored), vec!["hello", "hi", "bye"]);
+    }
+
+    #[tokio::test]
+    async fn test_modifying_hooks_run_in_registration_order() {
+        let manager = ContextManager::new(InMemoryContextStore::new())
+            .with_message_hook(Box::new(AppendSuffix { name: "first", suffix: " [a]" }))
+            .with_message_hook(Box::new(AppendSuffix { name: "second", suffix: " [b]" }));
+
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        context.add_message(Role::User, "question".to_string());
+        let report = manager.update_context(&mut context).await.unwrap();
+
+        assert_eq!(contents(&context), vec!["question [a] [b]"]);
+        let hooks: Vec<&str> = report.hook_outcomes.iter().map(|o| o.hook.as_str()).collect();
+        assert_eq!(hooks, vec!["first", "second"]);
+        assert!(report.hook_outcomes.iter().all(|o| o.decision == HookDecision::Modified));
+    }
+
+    #[tokio::test]
+    async fn test_hook_error_aborts_save() {
+        let manager = ContextManager::new(InMemoryContextStore::new())
+            .with_message_hook(Box::new(ValidationHook::new(10)));
+
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        context.add_message(Role::User, "short".to_string());
+        context.add_message(Role::User, "far too long for the limit".to_string());
+        let result = manager.update_context(&mut context).await;
+
+        assert!(matches!(result, Err(OrchestratorError::InvalidInput(_))));
+        assert_eq!(context.messages.len(), 2);
+        let stored = manager.get_context(&context.conversation_id).await.unwrap().unwrap();
+        assert!(stored.messages.is_empty());
+    }
+}
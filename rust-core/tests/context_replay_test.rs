@@ -0,0 +1,142 @@
+/// Tests for replaying a conversation as of an earlier time
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{
+        Clock, CodebaseContext, Context, ContextManager, ContextStorage, ContextStore, InMemoryContextStore, Message,
+        MockClock, ReplayFidelity, Role, ToolCall,
+    };
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+    const T0: i64 = 1_700_000_000;
+
+    async fn create_test_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    fn message(role: Role, content: &str, timestamp: i64) -> Message {
+        Message {
+            role,
+            content: content.to_string(),
+            timestamp,
+            pinned: false,
+        }
+    }
+
+    /// The conversation after each of its saves on a mock clock, with the save time
+    fn snapshots() -> Vec<(Context, i64)> {
+        let clock = MockClock::new(T0);
+        let mut snapshots = Vec::new();
+        let mut context = Context::new(Some("proj".to_string()));
+        context.messages.push(message(Role::User, "How do I parse TOML?", clock.now()));
+        snapshots.push((context.clone(), clock.now()));
+
+        clock.set(T0 + 60);
+        context.messages.push(message(Role::Assistant, "Use the toml crate.", clock.now()));
+        context.tool_history.push(ToolCall {
+            tool: "search".to_string(),
+            timestamp: clock.now(),
+            request: "toml".to_string(),
+            response: "toml = \"0.8\"".to_string(),
+            request_id: None,
+        });
+        context.codebase_context = Some(CodebaseContext {
+            relevant_files: vec!["Cargo.toml".to_string()],
+            semantic_matches: Vec::new(),
+        });
+        snapshots.push((context.clone(), clock.now()));
+
+        clock.set(T0 + 120);
+        context.messages.push(message(Role::User, "Thanks!", clock.now()));
+        snapshots.push((context, clock.now()));
+        snapshots
+    }
+
+    fn contents(context: &Context) -> Vec<&str> {
+        context.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    async fn assert_boundaries(store: &dyn ContextStore, conversation_id: &str) {
+        let at = |timestamp| store.load_context_at(conversation_id, timestamp);
+
+        let before = at(T0 - 1).await.unwrap().unwrap();
+        assert_eq!(before.fidelity, ReplayFidelity::Reconstructed);
+        assert!(before.context.messages.is_empty());
+
+        let first = at(T0).await.unwrap().unwrap();
+        assert_eq!(contents(&first.context), vec!["How do I parse TOML?"]);
+        assert!(first.context.tool_history.is_empty());
+
+        for timestamp in [T0 + 60, T0 + 119] {
+            let second = at(timestamp).await.unwrap().unwrap();
+            assert_eq!(second.fidelity, ReplayFidelity::Reconstructed);
+            assert_eq!(contents(&second.context), vec!["How do I parse TOML?", "Use the toml crate."]);
+            assert_eq!(second.context.tool_history.len(), 1);
+            assert_eq!(second.timestamp, timestamp);
+        }
+
+        for timestamp in [T0 + 120, T0 + 3600] {
+            let latest = at(timestamp).await.unwrap().unwrap();
+            assert_eq!(latest.fidelity, ReplayFidelity::Exact);
+            assert_eq!(latest.context.messages.len(), 3);
+        }
+
+        // Fields without history are the latest stored
+        assert_eq!(before.context.codebase_context.unwrap().relevant_files, vec!["Cargo.toml"]);
+        assert!(store.load_context_at("missing", T0).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_at_each_save_boundary() {
+        let storage = ContextStorage::from_pool(create_test_pool().await).await.unwrap();
+        let memory = InMemoryContextStore::new();
+        let (mut stored, mut in_memory) = (Context::new(None), Context::new(None));
+        for (snapshot, at) in snapshots() {
+            stored = Context { version: stored.version, ..snapshot.clone() };
+            storage.save_context_at(&mut stored, at).await.unwrap();
+            in_memory = Context { version: in_memory.version, ..snapshot };
+            memory.save_context_at(&mut in_memory, at).unwrap();
+        }
+
+        assert_boundaries(&storage, &stored.conversation_id).await;
+        assert_boundaries(&memory, &in_memory.conversation_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_undoes_later_summaries() {
+        let pool = create_test_pool().await;
+        let storage = ContextStorage::from_pool(pool.clone()).await.unwrap();
+        let mut context = Context::new(None);
+        context.messages = vec![
+            message(Role::User, "first", T0),
+            message(Role::Assistant, "second", T0 + 10),
+            message(Role::User, "third", T0 + 20),
+        ];
+        storage.save_context_at(&mut context, T0 + 20).await.unwrap();
+
+        // Summarized after T0 + 20: the first two messages are replaced
+        let summary = message(Role::System, "Summary: first, second", T0 + 30);
+        storage
+            .archive_summary(&context.conversation_id, "first, second", &summary, &context.messages[..2])
+            .await
+            .unwrap();
+        context.messages.splice(0..2, [summary]);
+        storage.save_context_at(&mut context, T0 + 30).await.unwrap();
+
+        let manager = ContextManager::new(ContextStorage::from_pool(pool).await.unwrap());
+        let replayed = manager.replay(&context.conversation_id, T0 + 25).await.unwrap();
+        assert_eq!(replayed.fidelity, ReplayFidelity::Reconstructed);
+        assert_eq!(contents(&replayed.context), vec!["first", "second", "third"]);
+        let exact = manager.replay(&context.conversation_id, T0 + 30).await.unwrap();
+        assert_eq!(exact.fidelity, ReplayFidelity::Exact);
+        assert_eq!(contents(&exact.context), vec!["Summary: first, second", "third"]);
+
+        let missing = manager.replay("missing", T0).await;
+        assert!(matches!(missing, Err(OrchestratorError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,150 @@
+/// Tests for cost record storage
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_core::cost::storage::{CostRecord, CostStorage};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::HashMap;
+
+    async fn create_storage() -> CostStorage {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+        CostStorage::from_pool(pool).await.unwrap()
+    }
+
+    fn record(i: i64, project_id: &str) -> CostRecord {
+        CostRecord {
+            id: None,
+            request_id: None,
+            tool: "claude".to_string(),
+            model: "claude-3-haiku".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd: 0.001 * i as f64,
+            timestamp: Utc.timestamp_opt(1_700_000_000 + i, 0).unwrap(),
+            user_id: None,
+            project_id: Some(project_id.to_string()),
+            conversation_id: None,
+            labels: HashMap::new(),
+        }
+    }
+
+    fn labelled(i: i64, labels: &[(&str, &str)]) -> CostRecord {
+        CostRecord {
+            labels: labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..record(i, "proj")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_records_pages_without_gaps() {
+        let storage = create_storage().await;
+        for i in 0..25 {
+            storage.record_cost(&record(i, "proj")).await.unwrap();
+        }
+        storage.record_cost(&record(99, "other")).await.unwrap();
+
+        let mut ids = Vec::new();
+        let mut page_sizes = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = storage.list_records(Some("proj"), 10, cursor).await.unwrap();
+            page_sizes.push(page.items.len());
+            ids.extend(page.items.iter().map(|r| r.id.unwrap()));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(page_sizes, vec![10, 10, 5]);
+
+        // Newest first, each record exactly once
+        let expected: Vec<i64> = (1..=25).rev().collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[tokio::test]
+    async fn test_list_records_exhausted_and_invalid_cursors() {
+        let storage = create_storage().await;
+        for i in 0..10 {
+            storage.record_cost(&record(i, "proj")).await.unwrap();
+        }
+
+        let first = storage.list_records(None, 10, None).await.unwrap();
+        assert_eq!(first.items.len(), 10);
+        let rest = storage.list_records(None, 10, first.next_cursor).await.unwrap();
+        assert!(rest.items.is_empty());
+        assert!(rest.next_cursor.is_none());
+
+        for garbled in ["xyz", "abc", "00ff", ""] {
+            match storage.list_records(None, 10, Some(garbled.to_string())).await {
+                Err(OrchestratorError::InvalidInput(_)) => {}
+                other => panic!("expected InvalidInput for {:?}, got {:?}", garbled, other.map(|p| p.items.len())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cost_by_label() {
+        let storage = create_storage().await;
+        storage.record_cost(&labelled(1, &[("team", "search"), ("env", "prod")])).await.unwrap();
+        storage.record_cost(&labelled(2, &[("team", "search"), ("env", "dev")])).await.unwrap();
+        storage.record_cost(&labelled(4, &[("team", "billing"), ("env", "prod")])).await.unwrap();
+        storage.record_cost(&labelled(8, &[])).await.unwrap();
+
+        let start = Utc.timestamp_opt(0, 0).unwrap();
+        let end = Utc.timestamp_opt(1_800_000_000, 0).unwrap();
+
+        let by_team = storage.get_cost_by_label(start, end, "team").await.unwrap();
+        assert_eq!(by_team.len(), 2);
+        assert!((by_team["search"] - 0.003).abs() < 1e-9);
+        assert!((by_team["billing"] - 0.004).abs() < 1e-9);
+
+        let prod: HashMap<String, String> = [("env".to_string(), "prod".to_string())].into();
+        let total = storage.get_total_cost(start, end, None, None, Some(&prod)).await.unwrap();
+        assert!((total - 0.005).abs() < 1e-9);
+
+        let search_prod: HashMap<String, String> = [
+            ("env".to_string(), "prod".to_string()),
+            ("team".to_string(), "search".to_string()),
+        ]
+        .into();
+        let total = storage.get_total_cost(start, end, None, Some("proj"), Some(&search_prod)).await.unwrap();
+        assert!((total - 0.001).abs() < 1e-9);
+
+        let all = storage.get_total_cost(start, end, None, None, None).await.unwrap();
+        assert!((all - 0.015).abs() < 1e-9);
+
+        // Labels round-trip through listing
+        let page = storage.list_records(None, 10, None).await.unwrap();
+        assert_eq!(page.items[3].labels.get("team").map(String::as_str), Some("search"));
+    }
+
+    #[tokio::test]
+    async fn test_too_many_labels_are_rejected() {
+        let storage = create_storage().await;
+        let labels: Vec<(String, String)> = (0..100).map(|i| (format!("key{}", i), "v".to_string())).collect();
+        let record = CostRecord {
+            labels: labels.into_iter().collect(),
+            ..record(1, "proj")
+        };
+        match storage.record_cost(&record).await {
+            Err(OrchestratorError::InvalidInput(_)) => {}
+            other => panic!("expected InvalidInput, got {:?}", other),
+        }
+
+        let bad_key = labelled(1, &[("team name", "search")]);
+        assert!(matches!(storage.record_cost(&bad_key).await, Err(OrchestratorError::InvalidInput(_))));
+        let long_value = "v".repeat(1000);
+        let bad_value = labelled(1, &[("team", long_value.as_str())]);
+        assert!(matches!(storage.record_cost(&bad_value).await, Err(OrchestratorError::InvalidInput(_))));
+
+        let page = storage.list_records(None, 10, None).await.unwrap();
+        assert!(page.items.is_empty());
+    }
+}
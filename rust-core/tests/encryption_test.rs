@@ -0,0 +1,151 @@
+/// Tests for encrypted context storage
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{Context, ContextStorage, Message, Role};
+    use rust_core::security::EncryptionKey;
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+    async fn create_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    async fn storage(pool: &SqlitePool, key: Option<&EncryptionKey>) -> ContextStorage {
+        let storage = ContextStorage::from_pool(pool.clone()).await.unwrap();
+        match key {
+            Some(key) => storage.with_encryption(key.clone()),
+            None => storage,
+        }
+    }
+
+    async fn save_secret(storage: &ContextStorage, secret: &str) -> Context {
+        let mut context = Context::new(Some("proj".to_string()));
+        context.add_message(Role::User, secret.to_string());
+        context.title = Some(format!("About {}", secret));
+        storage.save_context(&mut context).await.unwrap();
+        context
+    }
+
+    async fn raw_data(pool: &SqlitePool, conversation_id: &str) -> (String, Option<String>) {
+        sqlx::query_as("SELECT data, title FROM contexts WHERE conversation_id = ?1")
+            .bind(conversation_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_round_trip() {
+        let pool = create_pool().await;
+        let key = EncryptionKey::generate();
+        let storage = storage(&pool, Some(&key)).await;
+        let context = save_secret(&storage, "hunter2").await;
+
+        let (data, title) = raw_data(&pool, &context.conversation_id).await;
+        assert!(data.starts_with("enc:v1:"));
+        assert!(!data.contains("hunter2"));
+        assert!(!title.unwrap().contains("hunter2"));
+
+        let loaded = storage.load_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(loaded.messages, context.messages);
+        assert_eq!(loaded.title.as_deref(), Some("About hunter2"));
+
+        let listed = storage.list_contexts_page(None, None, 10, None).await.unwrap();
+        assert_eq!(listed[0].title.as_deref(), Some("About hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_or_missing_key_fails_with_authentication_error() {
+        let pool = create_pool().await;
+        let key = EncryptionKey::generate();
+        let context = save_secret(&storage(&pool, Some(&key)).await, "hunter2").await;
+
+        let wrong = storage(&pool, Some(&EncryptionKey::generate())).await;
+        let err = wrong.load_context(&context.conversation_id).await.unwrap_err();
+        assert!(matches!(err, OrchestratorError::Authentication(_)));
+
+        let keyless = storage(&pool, None).await;
+        let err = keyless.load_context(&context.conversation_id).await.unwrap_err();
+        assert!(matches!(err, OrchestratorError::Authentication(_)));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key_re_encrypts_contexts_and_archives() {
+        let pool = create_pool().await;
+        let old = EncryptionKey::generate();
+        let new = EncryptionKey::generate();
+        let storage_old = storage(&pool, Some(&old)).await;
+
+        let mut ids = Vec::new();
+        for i in 0..150 {
+            ids.push(save_secret(&storage_old, &format!("secret {}", i)).await.conversation_id);
+        }
+        let drained = vec![Message { role: Role::User, content: "drained".to_string(), timestamp: 1, pinned: false }];
+        let summary_message = Message { role: Role::System, content: "summary".to_string(), timestamp: 2, pinned: false };
+        storage_old
+            .archive_summary(&ids[0], "summary", &summary_message, &drained)
+            .await
+            .unwrap();
+
+        // Two batches of contexts plus the archive row
+        assert_eq!(storage_old.rotate_key(&old, &new).await.unwrap(), 151);
+
+        let storage_new = storage(&pool, Some(&new)).await;
+        for (i, id) in ids.iter().enumerate() {
+            let loaded = storage_new.load_context(id).await.unwrap().unwrap();
+            assert_eq!(loaded.messages[0].content, format!("secret {}", i));
+        }
+        let archive = storage_new.load_archive(&ids[0], None).await.unwrap();
+        assert_eq!(archive[0].messages, drained);
+        assert_eq!(archive[0].summary_message, summary_message);
+
+        assert!(matches!(
+            storage_old.load_context(&ids[0]).await,
+            Err(OrchestratorError::Authentication(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_plaintext_rows_load_alongside_encrypted_ones() {
+        let pool = create_pool().await;
+        let key = EncryptionKey::generate();
+        let legacy = save_secret(&storage(&pool, None).await, "plain").await;
+        let encrypted_storage = storage(&pool, Some(&key)).await;
+        let encrypted = save_secret(&encrypted_storage, "sealed").await;
+
+        let (data, _) = raw_data(&pool, &legacy.conversation_id).await;
+        assert!(data.contains("plain"));
+
+        let loaded = encrypted_storage.load_context(&legacy.conversation_id).await.unwrap().unwrap();
+        assert_eq!(loaded.messages[0].content, "plain");
+        let loaded = encrypted_storage.load_context(&encrypted.conversation_id).await.unwrap().unwrap();
+        assert_eq!(loaded.messages[0].content, "sealed");
+
+        // Rotation also encrypts the legacy row
+        assert_eq!(encrypted_storage.rotate_key(&key, &key).await.unwrap(), 2);
+        let (data, _) = raw_data(&pool, &legacy.conversation_id).await;
+        assert!(data.starts_with("enc:v1:"));
+        let loaded = encrypted_storage.load_context(&legacy.conversation_id).await.unwrap().unwrap();
+        assert_eq!(loaded.messages[0].content, "plain");
+    }
+
+    #[test]
+    fn test_key_parsing() {
+        let key = EncryptionKey::generate();
+        assert_eq!(EncryptionKey::from_hex(&key.to_hex()).unwrap(), key);
+        assert!(matches!(EncryptionKey::from_hex("abcd"), Err(OrchestratorError::InvalidConfig(_))));
+        assert!(matches!(EncryptionKey::from_hex("zz"), Err(OrchestratorError::InvalidConfig(_))));
+
+        let path = std::env::temp_dir().join(format!("uai-key-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, [7u8; 32]).unwrap();
+        assert_eq!(EncryptionKey::from_file(&path).unwrap(), EncryptionKey::from_bytes(&[7u8; 32]).unwrap());
+        std::fs::write(&path, format!("{}\n", key.to_hex())).unwrap();
+        assert_eq!(EncryptionKey::from_file(&path).unwrap(), key);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,130 @@
+/// Tests for hedged calls
+
+#[cfg(test)]
+mod tests {
+    use rust_core::error::{OrchestratorError, Result};
+    use rust_core::resilience::{hedged_call, hedged_call_n};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn respond(after_ms: u64, value: &'static str) -> Result<&'static str> {
+        tokio::time::sleep(Duration::from_millis(after_ms)).await;
+        Ok(value)
+    }
+
+    async fn fail(after_ms: u64, message: &str) -> Result<&'static str> {
+        tokio::time::sleep(Duration::from_millis(after_ms)).await;
+        Err(OrchestratorError::ToolUnavailable(message.to_string()))
+    }
+
+    type BackupFactory = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = Result<&'static str>> + Send>> + Send>;
+
+    /// Sets the flag if dropped before finishing
+    struct CancelFlag(Arc<AtomicBool>, bool);
+
+    impl Drop for CancelFlag {
+        fn drop(&mut self) {
+            if !self.1 {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fast_primary_never_starts_backup() {
+        let backup_started = Arc::new(AtomicBool::new(false));
+        let started = backup_started.clone();
+
+        let (value, outcome) = hedged_call(
+            respond(50, "primary"),
+            move || {
+                started.store(true, Ordering::SeqCst);
+                respond(10, "backup")
+            },
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(value, "primary");
+        assert!(outcome.primary_won());
+        assert!(!outcome.backup_started());
+        assert!(!backup_started.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_primary_loses_to_backup_and_is_cancelled() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let primary = async move {
+            let mut guard = CancelFlag(flag, false);
+            let result = respond(1_000, "primary").await;
+            guard.1 = true;
+            result
+        };
+
+        let start = tokio::time::Instant::now();
+        let (value, outcome) = hedged_call(primary, || respond(50, "backup"), Duration::from_millis(100))
+            .await
+            .unwrap();
+
+        assert_eq!(value, "backup");
+        assert_eq!(outcome.winner, 1);
+        assert_eq!(outcome.backups_started, 1);
+        assert_eq!(start.elapsed(), Duration::from_millis(150));
+        assert!(cancelled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_both_failing_returns_combined_error() {
+        let result = hedged_call(
+            fail(200, "primary down"),
+            || fail(50, "backup down"),
+            Duration::from_millis(100),
+        )
+        .await;
+
+        match result {
+            Err(OrchestratorError::HedgeFailed(message)) => {
+                assert!(message.contains("primary: Tool unavailable: primary down"), "{}", message);
+                assert!(message.contains("backup 1: Tool unavailable: backup down"), "{}", message);
+            }
+            other => panic!("expected HedgeFailed, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_early_primary_failure_starts_backup_immediately() {
+        let start = tokio::time::Instant::now();
+        let (value, outcome) = hedged_call(fail(10, "primary down"), || respond(20, "backup"), Duration::from_millis(100))
+            .await
+            .unwrap();
+
+        assert_eq!(value, "backup");
+        assert!(outcome.backup_started());
+        assert_eq!(start.elapsed(), Duration::from_millis(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_n_way_hedging_starts_backups_in_turn() {
+        let factories: Vec<BackupFactory> = vec![
+            Box::new(|| Box::pin(respond(1_000, "first backup"))),
+            Box::new(|| Box::pin(respond(30, "second backup"))),
+            Box::new(|| Box::pin(respond(10, "third backup"))),
+        ];
+
+        // Spawned, so the hedged call has to be Send
+        let (value, outcome) = tokio::spawn(hedged_call_n(respond(1_000, "primary"), factories, Duration::from_millis(100)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Second backup starts at 200ms and finishes before the third starts
+        assert_eq!(value, "second backup");
+        assert_eq!(outcome.winner, 2);
+        assert_eq!(outcome.backups_started, 2);
+    }
+}
@@ -0,0 +1,107 @@
+/// Tests for configurable message importance scoring
+
+#[cfg(test)]
+mod tests {
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::context::summarizer::{ContextSummarizer, SummarizationStrategy};
+    use rust_core::context::{Context, ImportanceConfig, ImportanceScorer, Role};
+
+    const DOMAIN_CONFIG: &str = r#"
+[context.summarizer]
+message_threshold = 10
+summary_ratio = 0.8
+
+[context.importance.keyword_weights]
+migration = 0.5
+incident = 0.5
+"#;
+
+    /// Short assistant chatter with one migration note among it
+    fn chatter_with_migration() -> Context {
+        let mut context = Context::new(None);
+        for i in 0..20 {
+            if i == 3 {
+                context.add_message(Role::Assistant, "The migration ran overnight".to_string());
+            } else {
+                context.add_message(Role::Assistant, format!("Okay {}", i));
+            }
+        }
+        context
+    }
+
+    #[test]
+    fn test_custom_keyword_elevates_message_into_summary() {
+        let summary_of = |summarizer: ContextSummarizer| {
+            let mut context = chatter_with_migration();
+            summarizer
+                .with_strategy(SummarizationStrategy::Extractive)
+                .summarize_if_needed(&mut context)
+                .unwrap()
+        };
+
+        // Short assistant messages score 0.2, below the 0.3 summary cutoff
+        let default_summary = summary_of(ContextSummarizer::new(10, 0.8));
+        assert!(!default_summary.contains("migration"));
+
+        let config = OrchestratorConfig::from_toml_str(DOMAIN_CONFIG).unwrap();
+        let custom_summary = summary_of(config.build_summarizer());
+        assert!(custom_summary.contains("The migration ran overnight"));
+    }
+
+    #[test]
+    fn test_pin_proximity_bonus() {
+        let mut context = Context::new(None);
+        for content in ["one", "two", "three", "four"] {
+            context.add_message(Role::Assistant, content.to_string());
+        }
+        assert!(context.set_pinned(1, true));
+        assert!(!context.set_pinned(10, true));
+
+        let scorer = ImportanceScorer::new(ImportanceConfig {
+            pin_proximity_bonus: 0.25,
+            ..ImportanceConfig::default()
+        });
+        let scores: Vec<f32> = (0..4).map(|i| scorer.score(&context.messages, i)).collect();
+        assert_eq!(scores[1], 1.0);
+        assert!((scores[0] - 0.45).abs() < 1e-6);
+        assert!((scores[2] - 0.45).abs() < 1e-6);
+        assert!((scores[3] - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_summarizer_and_window_manager_share_scorer() {
+        let mut config = OrchestratorConfig::from_toml_str(DOMAIN_CONFIG).unwrap();
+        // A threshold no test reaches, so only truncation drops messages
+        config.context.summarizer.message_threshold = 10_000;
+        let summarizer = config.build_summarizer();
+        let manager = config.build_window_manager();
+        assert_eq!(summarizer.importance().config(), &config.context.importance);
+        assert_eq!(manager.importance().config(), &config.context.importance);
+
+        // An old incident report outlives truncation only with the custom keyword
+        let conversation = || {
+            let mut context = Context::new(None);
+            for i in 0..40 {
+                let content = if i == 5 {
+                    format!("incident report {}", "word ".repeat(400))
+                } else {
+                    format!("turn {} {}", i, "word ".repeat(400))
+                };
+                context.add_message(Role::Assistant, content);
+            }
+            context
+        };
+        let kept_incident = |context: &Context| {
+            context.messages.iter().any(|m| m.content.starts_with("incident report"))
+        };
+
+        let mut context = conversation();
+        manager.manage_context(&mut context, "gpt-4");
+        assert!(kept_incident(&context));
+
+        let mut context = conversation();
+        let default_manager = OrchestratorConfig::default().build_window_manager();
+        default_manager.manage_context(&mut context, "gpt-4");
+        assert!(!kept_incident(&context));
+    }
+}
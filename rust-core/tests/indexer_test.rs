@@ -0,0 +1,2026 @@
+/// Tests for the codebase indexer and search
+
+#[cfg(test)]
+mod tests {
+    use rust_core::indexer::codebase::{relative_to_root, CodebaseIndexer, IndexOutcome};
+    use rust_core::indexer::diagnostics::{DiagnosticSource, Diagnostics, Severity};
+    use rust_core::indexer::disk_cache::DiskEmbeddingCache;
+    use rust_core::indexer::embed_pool::{BlockEmbedder, EmbeddingPool};
+    use rust_core::indexer::embedding_cache::{EmbeddingCache, EmbeddingSource};
+    use rust_core::indexer::import::{import_ctags, import_lsif};
+    use rust_core::indexer::journal::{ChangeKind, WatcherJournal};
+    use rust_core::indexer::parser::{ASTParser, CodeBlock, ParseOutcome};
+    use rust_core::indexer::rerank::{HttpReranker, NoopReranker, Reranker};
+    use rust_core::indexer::search::{render_explanation, ResultSource, SearchMode, SearchResult, SemanticSearch};
+    use rust_core::indexer::semantic::EmbeddingGenerator;
+    use rust_core::indexer::source::{decode_source, SkipReason};
+    use rust_core::indexer::storage::{
+        BlockNode, EmbeddingFingerprint, EmbeddingMeta, IndexEvent, IndexEventOutcome, IndexStorage, MatchKind,
+    };
+    use rust_core::indexer::watcher::{FileWatcher, IndexEventPolicy};
+    use rust_core::migrations::{MigrationRunner, register_migrations};
+    use rust_core::error::ResultExt;
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(Duration::from_secs(5))
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+
+        pool
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uai-indexer-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn indexed_file_count(pool: &SqlitePool) -> i64 {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM indexed_files")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    async fn wait_for_file_count(pool: &SqlitePool, expected: i64) -> bool {
+        for _ in 0..50 {
+            if indexed_file_count(pool).await == expected {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    fn block(name: &str, content: &str) -> CodeBlock {
+        CodeBlock {
+            block_type: "function_item".to_string(),
+            name: Some(name.to_string()),
+            short_name: Some(name.rsplit(['.', ':']).next().unwrap_or(name).to_string()),
+            content: content.to_string(),
+            start_line: 0,
+            end_line: content.lines().count(),
+            language: "rust".to_string(),
+            docstring: None,
+            decorators: Vec::new(),
+            parent_index: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_ranks_exact_name_first() {
+        let storage = IndexStorage::new(create_test_pool().await);
+
+        // 999 blocks that only mention the query in their body
+        let noise: Vec<CodeBlock> = (0..999)
+            .map(|i| block(&format!("caller_{}", i), "fn caller() { load_config(); }"))
+            .collect();
+        storage.store_file("proj", "src/callers.rs", "rust", &noise).await.unwrap();
+
+        // The exact match is inserted last
+        let exact = vec![block("load_config", "fn load_config() -> Config { todo!() }")];
+        storage.store_file("proj", "src/config.rs", "rust", &exact).await.unwrap();
+
+        let rows = storage.search_blocks("proj", "load_config", 10).await.unwrap();
+        assert_eq!(rows.len(), 10);
+        assert_eq!(rows[0].2.as_deref(), Some("load_config"));
+        assert_eq!(rows[0].6, MatchKind::ExactName);
+        assert!(rows[1..].iter().all(|r| r.6 == MatchKind::ContentContains));
+
+        let mut search = SemanticSearch::new(storage).with_candidate_multiplier(2);
+        let results = search.search("proj", "load_config", 5).await.unwrap().results;
+        assert_eq!(results[0].name.as_deref(), Some("load_config"));
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_matches_short_name_exactly() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = [
+            block("ContextStorage::save", "pub async fn save(&self) -> Result<()> { Ok(()) }"),
+            block("save_all", "fn save_all() { for s in stores { s.flush(); } }"),
+        ];
+        storage.store_file("proj", "src/storage.rs", "rust", &blocks).await.unwrap();
+
+        let rows = storage.search_blocks("proj", "save", 10).await.unwrap();
+        assert_eq!(rows[0].2.as_deref(), Some("ContextStorage::save"));
+        assert_eq!(rows[0].6, MatchKind::ExactName);
+        assert_eq!(rows[1].6, MatchKind::NamePrefix);
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_matches_like_wildcards_literally() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = [
+            block("format_percent", "fn format_percent(v: f64) -> String { format!(\"{}%\", v) }"),
+            block("format_ratio", "fn format_ratio(v: f64) -> String { v.to_string() }"),
+            block("formatXratio", "fn formatXratio() {}"),
+        ];
+        storage.store_file("proj", "src/format.rs", "rust", &blocks).await.unwrap();
+
+        // "%" alone would match every block if passed through as a wildcard
+        let rows = storage.search_blocks("proj", "%", 10).await.unwrap();
+        let names: Vec<_> = rows.iter().map(|r| r.2.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["format_percent"]);
+
+        // "_" only matches an underscore, not any single character
+        let rows = storage.search_blocks("proj", "format_r", 10).await.unwrap();
+        let names: Vec<_> = rows.iter().map(|r| r.2.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["format_ratio"]);
+    }
+
+    #[tokio::test]
+    async fn test_quoted_phrase_matches_only_adjacent_words() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = [
+            block("refill", "// Refill the token bucket\nfn refill(b: &mut Bucket) {}"),
+            block("take", "// Take a token from the bucket\nfn take(b: &mut Bucket) {}"),
+        ];
+        storage.store_file("proj", "src/limiter.rs", "rust", &blocks).await.unwrap();
+
+        let rows = storage.search_blocks("proj", "token bucket", 10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let rows = storage.search_blocks("proj", "\"token bucket\"", 10).await.unwrap();
+        let names: Vec<_> = rows.iter().map(|r| r.2.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["refill"]);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_term_removes_matching_blocks() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = [
+            block("refill", "fn refill(bucket: &mut Bucket) { bucket.tokens = bucket.capacity; }"),
+            block("test_refill", "fn test_refill() { let mut bucket = Bucket::new(); refill(&mut bucket); }"),
+        ];
+        storage.store_file("proj", "src/limiter.rs", "rust", &blocks).await.unwrap();
+
+        let rows = storage.search_blocks("proj", "bucket -test", 10).await.unwrap();
+        let names: Vec<_> = rows.iter().map(|r| r.2.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["refill"]);
+
+        let mut search = SemanticSearch::new(storage);
+        let response = search.search_with_explain("proj", "bucket -test", 10, true).await.unwrap();
+        let names: Vec<_> = response.results.iter().map(|r| r.name.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["refill"]);
+        assert_eq!(response.query.terms, vec!["bucket"]);
+        assert_eq!(response.query.excluded, vec!["test"]);
+        assert!(render_explanation(&response.results[0]).contains("query: terms: bucket; excluding: test"));
+    }
+
+    #[tokio::test]
+    async fn test_list_files_and_blocks() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let at = |name: &str, content: &str, start_line: usize| CodeBlock {
+            start_line,
+            end_line: start_line + content.lines().count(),
+            ..block(name, content)
+        };
+        let blocks = vec![
+            at("third", "fn third() {}", 10),
+            CodeBlock {
+                docstring: Some("The first one".to_string()),
+                ..at("first", "fn first() -> u8 {\n    1\n}", 1)
+            },
+            at("second", "fn second() {}", 5),
+        ];
+        storage.store_file("proj", "src/b.rs", "rust", &[block("b", "fn b() {}")]).await.unwrap();
+        storage.store_file("proj", "tests/t.rs", "rust", &[]).await.unwrap();
+        storage.store_file("proj", "src/nested/c.rs", "rust", &[block("c", "fn c() {}")]).await.unwrap();
+        storage.store_file("proj", "src/a.rs", "rust", &blocks).await.unwrap();
+        storage.store_file("other", "src/z.rs", "rust", &[]).await.unwrap();
+
+        let paths = |files: Vec<rust_core::indexer::storage::IndexedFile>| {
+            files.into_iter().map(|f| f.file_path).collect::<Vec<_>>()
+        };
+        let all = storage.list_files("proj", None, 100, 0).await.unwrap();
+        assert_eq!(all[0].block_count, 3);
+        assert_eq!(all[0].language.as_deref(), Some("rust"));
+        assert_eq!(all[3].block_count, 0);
+        assert_eq!(paths(all), vec!["src/a.rs", "src/b.rs", "src/nested/c.rs", "tests/t.rs"]);
+
+        // Pages of the prefix-filtered listing
+        assert_eq!(paths(storage.list_files("proj", Some("src/"), 2, 0).await.unwrap()), vec!["src/a.rs", "src/b.rs"]);
+        assert_eq!(paths(storage.list_files("proj", Some("src/"), 2, 2).await.unwrap()), vec!["src/nested/c.rs"]);
+        assert!(storage.list_files("proj", Some("src/"), 2, 4).await.unwrap().is_empty());
+        assert_eq!(paths(storage.list_files("proj", Some("tests"), 10, 0).await.unwrap()), vec!["tests/t.rs"]);
+
+        let listed = storage.list_blocks("proj", "src/a.rs").await.unwrap();
+        let names: Vec<_> = listed.iter().map(|b| b.name.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["first", "second", "third"]);
+        assert_eq!((listed[0].start_line, listed[0].end_line), (1, 4));
+        assert_eq!(listed[0].docstring.as_deref(), Some("The first one"));
+        assert_eq!(listed[0].signature.as_deref(), Some("fn first() -> u8"));
+        assert_eq!(listed[1].signature.as_deref(), Some("fn second() {}"));
+        assert!(storage.list_blocks("proj", "src/missing.rs").await.unwrap().is_empty());
+        assert!(storage.list_blocks("other", "src/a.rs").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unbalanced_quote_is_invalid_input() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        storage.store_file("proj", "src/limiter.rs", "rust", &[block("refill", "fn refill() {}")]).await.unwrap();
+
+        let result = storage.search_blocks("proj", "\"token bucket", 10).await;
+        assert!(matches!(result, Err(OrchestratorError::InvalidInput(_))));
+
+        let mut search = SemanticSearch::new(storage);
+        let result = search.search("proj", "refill -\"token", 10).await;
+        assert!(matches!(result, Err(OrchestratorError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_match_ranges_multiple_occurrences() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let content = "fn retry() { Retry::new(); retry_later(); }";
+        storage.store_file("proj", "src/retry.rs", "rust", &[block("retry", content)]).await.unwrap();
+
+        let mut search = SemanticSearch::new(storage);
+        let results = search.search("proj", "retry", 5).await.unwrap().results;
+        let result = &results[0];
+
+        assert_eq!(result.content, content);
+        assert_eq!(result.match_ranges, vec![(3, 8), (13, 18), (27, 32)]);
+        for (start, end) in &result.match_ranges {
+            assert_eq!(result.content[*start..*end].to_lowercase(), "retry");
+        }
+        assert_eq!(result.matched_terms, vec!["retry".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_search_term_only_in_name() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        storage.store_file(
+            "proj",
+            "src/handlers.py",
+            "python",
+            &[block("on_shutdown", "def handler(): pass")],
+        ).await.unwrap();
+
+        let mut search = SemanticSearch::new(storage);
+        let results = search.search("proj", "shutdown", 5).await.unwrap().results;
+        let result = &results[0];
+
+        assert!(result.match_ranges.is_empty());
+        assert_eq!(result.matched_terms, vec!["shutdown".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_copied_function_is_grouped_and_collapsed_in_search() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let original = "fn clamp_retries(n: u32) -> u32 {\n    if n > 5 { 5 } else { n }\n}";
+        let reformatted = "fn clamp_retries(n: u32) -> u32 {\n    // Never more than five\n    if n > 5 {\n        5\n    } else { n }\n}";
+        storage.store_file("proj", "pkg_a/util.rs", "rust", &[block("clamp_retries", original)]).await.unwrap();
+        storage.store_file("proj", "pkg_b/util.rs", "rust", &[block("clamp_retries", original)]).await.unwrap();
+        storage.store_file("proj", "pkg_c/util.rs", "rust", &[block("clamp_retries", reformatted)]).await.unwrap();
+        storage
+            .store_file("proj", "pkg_d/util.rs", "rust", &[block("clamp_retries", "fn clamp_retries(n: u32) -> u32 {\n    n.min(3)\n}")])
+            .await
+            .unwrap();
+
+        let groups = storage.find_duplicate_blocks("proj", 3).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        let paths: Vec<_> = groups[0].blocks.iter().map(|b| b.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["pkg_a/util.rs", "pkg_b/util.rs", "pkg_c/util.rs"]);
+        assert!(storage.find_duplicate_blocks("proj", 10).await.unwrap().is_empty());
+        assert!(storage.find_duplicate_blocks("other", 3).await.unwrap().is_empty());
+
+        let mut search = SemanticSearch::new(storage).with_dedup_content(true);
+        let results = search.search("proj", "clamp_retries", 10).await.unwrap().results;
+        assert_eq!(results.len(), 2);
+        let collapsed = results.iter().find(|r| r.file_path != "pkg_d/util.rs").unwrap();
+        let mut copies = collapsed.duplicates.clone();
+        copies.push(collapsed.file_path.clone());
+        copies.sort();
+        assert_eq!(copies, vec!["pkg_a/util.rs", "pkg_b/util.rs", "pkg_c/util.rs"]);
+        assert!(results.iter().find(|r| r.file_path == "pkg_d/util.rs").unwrap().duplicates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_indexes_new_directory_and_removes_it() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap();
+        watcher.watch(root.clone()).unwrap();
+        let shutdown = watcher.shutdown_signal();
+        let handle = tokio::spawn(async move { watcher.process_events().await });
+
+        let new_dir = root.join("feature");
+        std::fs::create_dir_all(&new_dir).unwrap();
+        std::fs::write(new_dir.join("alpha.rs"), "fn alpha() { let x = 1; }\n").unwrap();
+        std::fs::write(new_dir.join("beta.rs"), "fn beta() { let y = 2; }\n").unwrap();
+        assert!(wait_for_file_count(&pool, 2).await, "both files should be indexed");
+
+        std::fs::remove_dir_all(&new_dir).unwrap();
+        assert!(wait_for_file_count(&pool, 0).await, "both files should be removed");
+
+        shutdown.store(true, Ordering::Relaxed);
+        handle.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_watcher_indexes_each_root_under_its_project() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        let api = root.join("api");
+        let plugins = api.join("plugins");
+        std::fs::create_dir_all(&plugins).unwrap();
+
+        let indexer = CodebaseIndexer::new("workspace".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap();
+        watcher.watch(root.clone()).unwrap();
+        let api_indexer = CodebaseIndexer::new("api".to_string(), IndexStorage::new(pool.clone()));
+        watcher.watch_project(api.clone(), api_indexer).unwrap();
+        let plugins_indexer = CodebaseIndexer::new("plugins".to_string(), IndexStorage::new(pool.clone()));
+        watcher.watch_project(plugins.clone(), plugins_indexer).unwrap();
+        let shutdown = watcher.shutdown_signal();
+        let handle = tokio::spawn(async move { watcher.process_events().await });
+
+        std::fs::write(root.join("build.rs"), "fn main() { let x = 1; }\n").unwrap();
+        std::fs::write(api.join("server.rs"), "fn serve() { let y = 2; }\n").unwrap();
+        std::fs::write(plugins.join("auth.rs"), "fn login() { let z = 3; }\n").unwrap();
+        assert!(wait_for_file_count(&pool, 3).await, "every file should be indexed once");
+
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT project_id, file_path FROM indexed_files ORDER BY project_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let projects: Vec<(&str, &str)> = rows
+            .iter()
+            .map(|(project, path)| (project.as_str(), path.rsplit('/').next().unwrap()))
+            .collect();
+        assert_eq!(projects, vec![("api", "server.rs"), ("plugins", "auth.rs"), ("workspace", "build.rs")]);
+
+        std::fs::remove_file(plugins.join("auth.rs")).unwrap();
+        assert!(wait_for_file_count(&pool, 2).await, "removal should reach the plugins project");
+
+        shutdown.store(true, Ordering::Relaxed);
+        handle.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_project_indexers_share_the_watcher_configuration() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        std::fs::create_dir_all(root.join("api")).unwrap();
+
+        let indexer = CodebaseIndexer::new("workspace".to_string(), IndexStorage::new(pool.clone()))
+            .with_skip_patterns(vec!["*.gen.rs".to_string()]);
+        let mut watcher = FileWatcher::new(indexer).unwrap();
+        // Registered through an uncanonicalized path
+        let api_indexer = watcher.project_indexer("api".to_string());
+        watcher.watch_project(root.join("api").join("..").join("api"), api_indexer).unwrap();
+        let shutdown = watcher.shutdown_signal();
+        let handle = tokio::spawn(async move { watcher.process_events().await });
+
+        std::fs::write(root.join("api").join("schema.gen.rs"), "fn generated() { let x = 1; }\n").unwrap();
+        std::fs::write(root.join("api").join("server.rs"), "fn serve() { let y = 2; }\n").unwrap();
+        assert!(wait_for_file_count(&pool, 1).await, "the handwritten file should be indexed");
+
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT project_id, file_path FROM indexed_files")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0, "api");
+        assert!(rows[0].1.ends_with("server.rs"));
+
+        shutdown.store(true, Ordering::Relaxed);
+        handle.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    /// Poll `condition` for up to five seconds
+    async fn wait_until(condition: impl Fn() -> bool) -> bool {
+        for _ in 0..50 {
+            if condition() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_paused_watcher_indexes_backlog_on_resume() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap();
+        watcher.watch(root.clone()).unwrap();
+        let control = watcher.control();
+        let shutdown = watcher.shutdown_signal();
+        let handle = tokio::spawn(async move { watcher.process_events().await });
+
+        control.pause();
+        std::fs::write(root.join("alpha.rs"), "fn alpha() { let x = 1; }\n").unwrap();
+        std::fs::write(root.join("beta.rs"), "fn beta() { let y = 2; }\n").unwrap();
+        assert!(wait_until(|| control.backlog_len() == 2).await, "changes should be recorded per path");
+        // Well past the debounce, nothing was indexed
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert_eq!(indexed_file_count(&pool).await, 0);
+
+        control.resume(false);
+        assert!(wait_for_file_count(&pool, 2).await, "the backlog should be indexed");
+        assert_eq!(control.backlog_len(), 0);
+
+        shutdown.store(true, Ordering::Relaxed);
+        handle.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_drop_backlog_discards_recorded_changes() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let journal = WatcherJournal::new(pool.clone(), "proj");
+        let mut watcher = FileWatcher::new(indexer).unwrap().with_journal(journal.clone());
+        watcher.watch(root.clone()).unwrap();
+        let control = watcher.control();
+        let shutdown = watcher.shutdown_signal();
+        let handle = tokio::spawn(async move { watcher.process_events().await });
+
+        control.pause();
+        std::fs::write(root.join("alpha.rs"), "fn alpha() { let x = 1; }\n").unwrap();
+        std::fs::write(root.join("beta.rs"), "fn beta() { let y = 2; }\n").unwrap();
+        assert!(wait_until(|| control.backlog_len() == 2).await);
+
+        control.resume(true);
+        assert!(wait_until(|| control.backlog_len() == 0).await);
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert_eq!(indexed_file_count(&pool).await, 0);
+        // Dropped changes are not replayed by crash recovery either
+        assert!(journal.pending().await.unwrap().is_empty());
+
+        // Changes after the resume are indexed as usual
+        std::fs::write(root.join("gamma.rs"), "fn gamma() { let z = 3; }\n").unwrap();
+        assert!(wait_for_file_count(&pool, 1).await);
+
+        shutdown.store(true, Ordering::Relaxed);
+        handle.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_watcher_records_failed_indexing_as_event() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        let path = root.join("locked.rs");
+        std::fs::write(&path, "fn locked() { let x = 1; }\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o000)).unwrap();
+        if std::fs::read(&path).is_ok() {
+            // Permissions are not enforced, e.g. when running as root
+            std::fs::remove_dir_all(&root).ok();
+            return;
+        }
+
+        let journal = WatcherJournal::new(pool.clone(), "proj");
+        journal.append(&path, ChangeKind::Update).await.unwrap();
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap().with_journal(journal.clone());
+        watcher.recover_pending().await.unwrap();
+
+        let events = IndexStorage::new(pool.clone()).recent_index_events("proj", 10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].path, path.to_string_lossy());
+        assert_eq!(events[0].action, ChangeKind::Update);
+        assert_eq!(events[0].outcome, IndexEventOutcome::Failed);
+        assert!(events[0].error.as_deref().unwrap().contains("Failed to read file"));
+        // The change stays journaled for the next recovery
+        assert_eq!(journal.pending().await.unwrap().len(), 1);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_index_events_are_sampled_and_pruned() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        let storage = IndexStorage::new(pool.clone());
+        let old = |path: &str| IndexEvent {
+            path: path.to_string(),
+            action: ChangeKind::Update,
+            outcome: IndexEventOutcome::Indexed,
+            error: None,
+            recorded_at: 0,
+        };
+        storage.record_index_event("proj", &old("old.rs")).await.unwrap();
+        storage.record_index_event("other", &old("other.rs")).await.unwrap();
+
+        let journal = WatcherJournal::new(pool.clone(), "proj");
+        let write_files = |prefix: &str, count: usize| -> Vec<PathBuf> {
+            (0..count)
+                .map(|i| {
+                    let path = root.join(format!("{}_{}.rs", prefix, i));
+                    std::fs::write(&path, format!("fn {}_{}() {{ let v = {}; }}\n", prefix, i, i)).unwrap();
+                    path
+                })
+                .collect()
+        };
+
+        // Every success recorded, but only the newest three kept
+        for path in write_files("kept", 6) {
+            journal.append(&path, ChangeKind::Update).await.unwrap();
+        }
+        let policy = IndexEventPolicy {
+            success_sample_rate: 1,
+            max_age: Duration::from_secs(3600),
+            max_events: 3,
+        };
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap()
+            .with_journal(journal.clone())
+            .with_index_events(Some(policy));
+        assert_eq!(watcher.recover_pending().await.unwrap(), 6);
+
+        let events = storage.recent_index_events("proj", 100).await.unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|e| e.outcome == IndexEventOutcome::Indexed && e.path.contains("kept_")));
+        // Other projects are pruned on their own schedule
+        assert_eq!(storage.recent_index_events("other", 100).await.unwrap().len(), 1);
+
+        // One in four successes recorded
+        for path in write_files("sampled", 8) {
+            journal.append(&path, ChangeKind::Update).await.unwrap();
+        }
+        let policy = IndexEventPolicy {
+            success_sample_rate: 4,
+            max_events: 100,
+            ..policy
+        };
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap()
+            .with_journal(journal)
+            .with_index_events(Some(policy));
+        assert_eq!(watcher.recover_pending().await.unwrap(), 8);
+        assert_eq!(storage.recent_index_events("proj", 100).await.unwrap().len(), 5);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_watcher_auto_pauses_on_event_burst() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap().with_auto_pause(5);
+        watcher.watch(root.clone()).unwrap();
+        let control = watcher.control();
+        let diagnostics = watcher.diagnostics().clone();
+        let shutdown = watcher.shutdown_signal();
+        let handle = tokio::spawn(async move { watcher.process_events().await });
+
+        for i in 0..20 {
+            std::fs::write(root.join(format!("gen_{}.rs", i)), format!("fn gen_{}() {{ let v = {}; }}\n", i, i)).unwrap();
+        }
+        assert!(wait_until(|| control.is_paused()).await, "a burst should pause the watcher");
+        assert!(diagnostics
+            .recent(10)
+            .iter()
+            .any(|d| d.source == DiagnosticSource::Watcher && d.message.contains("bulk re-index")));
+
+        // The bulk re-index the diagnostic suggests, then drop the backlog
+        let mut bulk = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        assert_eq!(bulk.index_directory(&root).await.unwrap(), 20);
+        control.resume(true);
+        assert!(wait_until(|| control.backlog_len() == 0).await);
+        assert_eq!(indexed_file_count(&pool).await, 20);
+
+        shutdown.store(true, Ordering::Relaxed);
+        handle.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_relative_to_root_normalizes_paths() {
+        assert_eq!(relative_to_root("/home/dev/repo", "/home/dev/repo/src/main.rs"), "src/main.rs");
+        assert_eq!(relative_to_root("/home/dev/repo/", "/home/dev/repo/src/main.rs"), "src/main.rs");
+        assert_eq!(relative_to_root("C:\\dev\\repo", "C:\\dev\\repo\\src\\main.rs"), "src/main.rs");
+        // Sibling directories sharing a name prefix are not inside the root
+        assert_eq!(relative_to_root("/home/dev/repo", "/home/dev/repo2/lib.rs"), "/home/dev/repo2/lib.rs");
+    }
+
+    #[tokio::test]
+    async fn test_index_file_stores_relative_path() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn library() { let z = 3; }\n").unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 1);
+
+        let (path,): (String,) = sqlx::query_as("SELECT file_path FROM indexed_files")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(path, "src/lib.rs");
+
+        let mut search = SemanticSearch::new(IndexStorage::new(pool)).with_display_root(root.clone());
+        let results = search.search("proj", "library", 5).await.unwrap().results;
+        assert_eq!(PathBuf::from(&results[0].file_path), root.join("src/lib.rs"));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_uncanonical_root_and_root_removal() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn library() { let z = 3; }\n").unwrap();
+
+        // The same root spelled through `..`, as a watcher would never report it
+        let spelled = root.join("src").join("..");
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_root(spelled.clone());
+        indexer.index_file(&root.join("src/lib.rs")).await.unwrap();
+        let (path,): (String,) = sqlx::query_as("SELECT file_path FROM indexed_files")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(path, "src/lib.rs");
+
+        // A remove event for the root must not wipe the project's index
+        assert!(indexer.remove_directory(&root).await.is_err());
+        assert!(indexer.remove_directory(&spelled).await.is_err());
+        assert_eq!(indexed_file_count(&pool).await, 1);
+        assert_eq!(indexer.remove_directory(&root.join("src")).await.unwrap(), 1);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_git_discovery_skips_ignored_files() {
+        let root = temp_dir();
+        let git = |args: &[&str]| std::process::Command::new("git").arg("-C").arg(&root).args(args).output();
+        if !git(&["init", "-q"]).map(|o| o.status.success()).unwrap_or(false) {
+            std::fs::remove_dir_all(&root).ok();
+            return; // No git on this machine
+        }
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("generated")).unwrap();
+        std::fs::write(root.join(".gitignore"), "generated/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn library() { let z = 3; }\n").unwrap();
+        std::fs::write(root.join("generated/bindings.rs"), "fn generated() { let g = 1; }\n").unwrap();
+        // Untracked but not ignored files are still discovered
+        std::fs::write(root.join("src/new.rs"), "fn fresh() { let n = 2; }\n").unwrap();
+        git(&["add", "src/lib.rs", ".gitignore"]).unwrap();
+
+        let pool = create_test_pool().await;
+        let walker = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        assert_eq!(walker.collect_indexable_files(&root).len(), 3);
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_git_discovery(true);
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 2);
+        let mut paths: Vec<(String,)> = sqlx::query_as("SELECT file_path FROM indexed_files")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        paths.sort();
+        assert_eq!(paths, vec![("src/lib.rs".to_string(),), ("src/new.rs".to_string(),)]);
+
+        // Outside a work tree the walker takes over
+        let plain = temp_dir();
+        std::fs::write(plain.join("main.rs"), "fn main() { let m = 0; }\n").unwrap();
+        let mut fallback = CodebaseIndexer::new("other".to_string(), IndexStorage::new(pool.clone()))
+            .with_git_discovery(true);
+        assert_eq!(fallback.index_directory(&plain).await.unwrap(), 1);
+        assert!(fallback
+            .diagnostics()
+            .recent(10)
+            .iter()
+            .any(|d| d.source == DiagnosticSource::Indexer && d.message.contains("Git discovery unavailable")));
+
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::remove_dir_all(&plain).ok();
+    }
+
+    #[tokio::test]
+    async fn test_git_discovery_judges_hidden_names_below_the_walked_directory() {
+        let root = temp_dir();
+        let tools = root.join(".tools");
+        std::fs::create_dir_all(&tools).unwrap();
+        let git = |args: &[&str]| std::process::Command::new("git").arg("-C").arg(&tools).args(args).output();
+        if !git(&["init", "-q"]).map(|o| o.status.success()).unwrap_or(false) {
+            std::fs::remove_dir_all(&root).ok();
+            return; // No git on this machine
+        }
+        std::fs::write(tools.join("lint.rs"), "fn lint() { let l = 1; }\n").unwrap();
+        // A name git lists as raw bytes, not valid UTF-8
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = std::ffi::OsStr::from_bytes(b"caf\xe9.rs");
+            std::fs::write(tools.join(name), "fn cafe() { let c = 2; }\n").unwrap();
+        }
+        let expected = if cfg!(unix) { 2 } else { 1 };
+
+        // The indexer's root is above the hidden directory it is asked to index
+        let pool = create_test_pool().await;
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_root(root.clone())
+            .with_git_discovery(true);
+        assert_eq!(indexer.index_directory(&tools).await.unwrap(), expected);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_validate_and_repair_corrupted_index() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        std::fs::write(root.join("kept.rs"), "fn kept() { let a = 1; }\n").unwrap();
+        std::fs::write(root.join("deleted.rs"), "fn deleted() { let b = 2; }\n").unwrap();
+        std::fs::write(root.join("edited.rs"), "fn edited() { let c = 3; }\n").unwrap();
+
+        let storage = IndexStorage::new(pool.clone());
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_embedding_dim(4);
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 3);
+        assert!(indexer.validate_index().await.unwrap().is_healthy());
+
+        // Corrupt the index: an orphaned block, a deleted file, an edited file
+        // and an embedding of the wrong size
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&pool).await.unwrap();
+        let (orphan_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO code_blocks (file_id, block_type, content) VALUES (9999, 'function_item', 'fn ghost() {}') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        std::fs::remove_file(root.join("deleted.rs")).unwrap();
+        std::fs::write(root.join("edited.rs"), "fn edited() { let c = 4; }\n").unwrap();
+        let kept_block = storage.get_block_id("proj", "kept.rs", Some("kept")).await.unwrap().unwrap();
+        storage.store_embedding(kept_block, &[0.5; 3]).await.unwrap();
+
+        let validation = indexer.validate_index().await.unwrap();
+        assert!(!validation.is_healthy());
+        assert_eq!(validation.total_files, 3);
+        assert_eq!(validation.orphaned_blocks, vec![orphan_id]);
+        assert_eq!(validation.missing_files, vec!["deleted.rs".to_string()]);
+        assert_eq!(validation.out_of_date_files, vec!["edited.rs".to_string()]);
+        assert_eq!(validation.mismatched_embeddings, vec![kept_block]);
+
+        // Missing files are kept unless removal is requested
+        let report = indexer.repair_index(false).await.unwrap();
+        assert_eq!(report.orphaned_blocks_removed, 1);
+        assert_eq!(report.missing_files_removed, 0);
+        assert_eq!(report.files_reindexed, 1);
+        assert_eq!(report.embeddings_cleared, 1);
+        assert!(report.errors.is_empty());
+
+        let validation = indexer.validate_index().await.unwrap();
+        assert_eq!(validation.missing_files, vec!["deleted.rs".to_string()]);
+        assert!(validation.orphaned_blocks.is_empty());
+        assert!(validation.out_of_date_files.is_empty());
+        assert!(validation.mismatched_embeddings.is_empty());
+
+        let report = indexer.repair_index(true).await.unwrap();
+        assert_eq!(report.missing_files_removed, 1);
+        assert!(indexer.validate_index().await.unwrap().is_healthy());
+        assert_eq!(indexed_file_count(&pool).await, 2);
+
+        let results = SemanticSearch::new(storage).search("proj", "edited", 5).await.unwrap().results;
+        assert!(results[0].content.contains("let c = 4"));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_path_prefix() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let blocks = [block("main", "fn main() { run(); }")];
+        storage.store_file("unix", "/home/dev/repo/src/main.rs", "rust", &blocks).await.unwrap();
+        storage.store_file("win", "C:\\dev\\repo\\src\\main.rs", "rust", &blocks).await.unwrap();
+
+        assert_eq!(storage.rewrite_path_prefix("unix", "/home/dev/repo/", "").await.unwrap(), 1);
+        assert_eq!(storage.rewrite_path_prefix("win", "C:\\dev\\repo\\", "").await.unwrap(), 1);
+
+        let paths: Vec<(String,)> = sqlx::query_as("SELECT file_path FROM indexed_files ORDER BY project_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(paths, vec![("src/main.rs".to_string(),), ("src/main.rs".to_string(),)]);
+    }
+
+    fn rust_functions(count: usize, changed: Option<usize>) -> String {
+        (0..count)
+            .map(|i| {
+                let body = if Some(i) == changed {
+                    "let value = 42;\n    let other = value * 2;"
+                } else {
+                    "let value = 1;"
+                };
+                format!("fn func_{}() {{\n    {}\n}}\n\n", i, body)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_incremental_update_matches_full_reparse() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        let file = root.join("lib.rs");
+        let old_content = rust_functions(20, None);
+        let new_content = rust_functions(20, Some(10));
+        std::fs::write(&file, &old_content).unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_root(root.clone());
+        indexer.index_file(&file).await.unwrap();
+
+        // The incremental path re-extracts only the blocks around the edit
+        let mut probe = ASTParser::new();
+        probe.parse_file_cached("lib.rs", &old_content, "rust").unwrap();
+        match probe.parse_file_incremental("lib.rs", &new_content, "rust").unwrap() {
+            ParseOutcome::Incremental { blocks, changed } => {
+                assert!(!blocks.is_empty() && blocks.len() < 20, "touched {} blocks", blocks.len());
+                assert_eq!(changed.line_delta(), 1);
+            }
+            _ => panic!("expected an incremental parse"),
+        }
+
+        std::fs::write(&file, &new_content).unwrap();
+        indexer.update_file(&file).await.unwrap();
+
+        let mut stored: Vec<(Option<String>, i64, i64, String)> = sqlx::query_as(
+            "SELECT name, start_line, end_line, content FROM code_blocks"
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        stored.sort();
+
+        let mut expected: Vec<(Option<String>, i64, i64, String)> = ASTParser::new()
+            .parse_file(&new_content, "rust")
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.name, b.start_line as i64, b.end_line as i64, b.content))
+            .collect();
+        expected.sort();
+
+        assert_eq!(stored, expected);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    fn service_class(fetch_body: &str) -> String {
+        format!(
+            "class Service:\n    def start(self):\n        self.running = True\n\n    def fetch(self, key):\n        {}\n\n    def stop(self):\n        self.running = False\n",
+            fetch_body
+        )
+    }
+
+    fn assert_service_tree(tree: &[BlockNode]) {
+        assert_eq!(tree.len(), 1);
+        let class = &tree[0];
+        assert_eq!(class.name.as_deref(), Some("Service"));
+        assert_eq!(class.block_type, "class_definition");
+        let methods: Vec<_> = class.children.iter().map(|c| c.name.as_deref().unwrap()).collect();
+        assert_eq!(methods, vec!["Service.start", "Service.fetch", "Service.stop"]);
+        assert!(class.children.iter().all(|c| c.children.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_block_tree_for_class_methods() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        let file = root.join("service.py");
+        std::fs::write(&file, service_class("return self.cache.get(key)")).unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_root(root.clone());
+        indexer.index_file(&file).await.unwrap();
+
+        let storage = IndexStorage::new(pool.clone());
+        let tree = storage.get_block_tree("proj", "service.py").await.unwrap();
+        assert_service_tree(&tree);
+
+        let children = storage.get_children(tree[0].id).await.unwrap();
+        assert_eq!(children.len(), 3);
+        assert!(storage.get_children(children[0].0).await.unwrap().is_empty());
+
+        let mut search = SemanticSearch::new(IndexStorage::new(pool.clone()));
+        let results = search.search("proj", "fetch", 5).await.unwrap().results;
+        assert_eq!(results[0].name.as_deref(), Some("Service.fetch"));
+        assert_eq!(results[0].parent_name.as_deref(), Some("Service"));
+        assert_eq!(results[0].parent_block_type.as_deref(), Some("class_definition"));
+
+        // Full re-index replaces every row and re-links parents in the same batch
+        indexer.index_file(&file).await.unwrap();
+        assert_service_tree(&storage.get_block_tree("proj", "service.py").await.unwrap());
+
+        // Editing one method re-inserts the class; untouched methods follow it
+        std::fs::write(&file, service_class("return self.cache.get(key, None)")).unwrap();
+        indexer.update_file(&file).await.unwrap();
+        assert_service_tree(&storage.get_block_tree("proj", "service.py").await.unwrap());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    fn search_fixture() -> Vec<CodeBlock> {
+        vec![
+            block("parse_config", "fn parse_config(raw: &str) -> Config { toml::from_str(raw) }"),
+            block("write_config", "fn write_config(config: &Config) { std::fs::write(path, config) }"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_project_without_embeddings_is_keyword_only() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        storage.store_file("proj", "src/config.rs", "rust", &search_fixture()).await.unwrap();
+
+        let mut search = SemanticSearch::new(storage);
+        let capabilities = search.capabilities("proj").await.unwrap();
+        assert!(!capabilities.embeddings_available);
+        assert_eq!(capabilities.embedding_model, None);
+        assert_eq!(capabilities.embedding_dim, 384);
+        assert_eq!((capabilities.blocks_with_embeddings, capabilities.blocks_total), (0, 2));
+        assert!(!capabilities.fts_enabled);
+
+        let response = search.search("proj", "config", 5).await.unwrap();
+        assert_eq!(response.mode, SearchMode::KeywordOnly);
+        assert_eq!(response.mode.as_str(), "keyword_only");
+        assert!(response.degraded);
+        assert_eq!(response.results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fully_embedded_project_is_hybrid() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = search_fixture();
+        storage.store_file("proj", "src/config.rs", "rust", &blocks).await.unwrap();
+
+        let mut generator = EmbeddingGenerator::default();
+        for block in &blocks {
+            let block_id = storage
+                .get_block_id("proj", "src/config.rs", block.name.as_deref())
+                .await
+                .unwrap()
+                .unwrap();
+            storage.store_embedding(block_id, &generator.generate_embedding(block)).await.unwrap();
+        }
+
+        let mut search = SemanticSearch::new(storage);
+        let capabilities = search.capabilities("proj").await.unwrap();
+        assert!(capabilities.embeddings_available);
+        assert_eq!((capabilities.blocks_with_embeddings, capabilities.blocks_total), (2, 2));
+
+        let response = search.search("proj", "config", 5).await.unwrap();
+        assert_eq!(response.mode, SearchMode::Hybrid);
+        assert!(!response.degraded);
+        assert!(!response.results.is_empty());
+
+        let semantic = search.search_semantic_only("proj", "config", 5, -1.0).await.unwrap();
+        assert_eq!(semantic.mode, SearchMode::SemanticOnly);
+        assert!(!semantic.degraded);
+        assert_eq!(semantic.results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tied_scores_order_identically_across_runs() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let mut generator = EmbeddingGenerator::default();
+        // Every block gets the query's own embedding, so all semantic scores tie
+        let tied_embedding = generator.generate_query_embedding("dispatch");
+        for file in ["src/c.rs", "src/a.rs", "src/b.rs"] {
+            let blocks: Vec<CodeBlock> = (0..10)
+                .map(|i| CodeBlock {
+                    start_line: (10 - i) * 5,
+                    end_line: (10 - i) * 5 + 2,
+                    ..block(&format!("handler_{}", i), "fn handler() { dispatch(); }")
+                })
+                .collect();
+            storage.store_file("proj", file, "rust", &blocks).await.unwrap();
+            for block in &blocks {
+                let block_id = storage.get_block_id("proj", file, block.name.as_deref()).await.unwrap().unwrap();
+                storage.store_embedding(block_id, &tied_embedding).await.unwrap();
+            }
+        }
+
+        let mut hybrid_runs = Vec::new();
+        let mut semantic_runs = Vec::new();
+        for _ in 0..20 {
+            // A fresh engine per run, so embedding map iteration order varies.
+            // Few keyword candidates leave most blocks to the semantic pass.
+            let mut search = SemanticSearch::new(IndexStorage::new(pool.clone())).with_candidate_multiplier(1);
+            let response = search.search("proj", "dispatch", 12).await.unwrap();
+            hybrid_runs.push(format!("{:?}", response.results));
+            let response = search.search_semantic_only("proj", "dispatch", 12, 0.0).await.unwrap();
+            semantic_runs.push(format!("{:?}", response.results));
+        }
+        assert!(hybrid_runs.iter().all(|run| run == &hybrid_runs[0]));
+        assert!(semantic_runs.iter().all(|run| run == &semantic_runs[0]));
+
+        // Ties fall back to file path, then start line
+        let mut search = SemanticSearch::new(storage).with_candidate_multiplier(1);
+        let results = search.search("proj", "dispatch", 12).await.unwrap().results;
+        assert_eq!(results.len(), 12);
+        for pair in results.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+            if pair[0].score == pair[1].score {
+                assert!((&pair[0].file_path, pair[0].start_line) < (&pair[1].file_path, pair[1].start_line));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_page_walks_all_results_without_gaps() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks: Vec<CodeBlock> = (0..25)
+            .map(|i| block(&format!("caller_{}", i), "fn caller() { load_config(); }"))
+            .collect();
+        storage.store_file("proj", "src/callers.rs", "rust", &blocks).await.unwrap();
+
+        let mut search = SemanticSearch::new(storage);
+        let mut names = Vec::new();
+        let mut page_sizes = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = search.search_page("proj", "load_config", 10, cursor).await.unwrap();
+            page_sizes.push(page.results.len());
+            names.extend(page.results.into_iter().map(|r| r.name.unwrap()));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(page_sizes, vec![10, 10, 5]);
+
+        let mut expected: Vec<String> = (0..25).map(|i| format!("caller_{}", i)).collect();
+        names.sort();
+        expected.sort();
+        assert_eq!(names, expected);
+
+        // The same query returns the same second page for a reused cursor
+        let first = search.search_page("proj", "load_config", 10, None).await.unwrap();
+        let cursor = first.next_cursor.unwrap();
+        let a = search.search_page("proj", "load_config", 10, Some(cursor.clone())).await.unwrap();
+        let b = search.search_page("proj", "load_config", 10, Some(cursor)).await.unwrap();
+        let ids = |results: &[rust_core::indexer::search::SearchResult]| {
+            results.iter().map(|r| r.block_id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&a.results), ids(&b.results));
+    }
+
+    #[tokio::test]
+    async fn test_search_page_rejects_garbled_cursor() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        storage.store_file("proj", "src/config.rs", "rust", &search_fixture()).await.unwrap();
+
+        let mut search = SemanticSearch::new(storage);
+        let err = search
+            .search_page("proj", "config", 10, Some("not-a-cursor".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, rust_core::OrchestratorError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_index_searches_and_rejects_writes() {
+        let dir = temp_dir();
+        let db_path = dir.join("index.db");
+        {
+            let pool = SqlitePoolOptions::new()
+                .connect_with(
+                    sqlx::sqlite::SqliteConnectOptions::new()
+                        .filename(&db_path)
+                        .create_if_missing(true),
+                )
+                .await
+                .unwrap();
+            let mut runner = MigrationRunner::new(pool.clone());
+            register_migrations(&mut runner);
+            runner.migrate_up(None).await.unwrap();
+            let storage = IndexStorage::new(pool.clone());
+            storage.store_file("proj", "src/config.rs", "rust", &[block("load_config", "fn load_config() {}")]).await.unwrap();
+            pool.close().await;
+        }
+        let mut permissions = std::fs::metadata(&db_path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&db_path, permissions).unwrap();
+
+        let storage = IndexStorage::open_read_only(&db_path).await.unwrap();
+        assert!(storage.is_read_only());
+        match storage.store_file("proj", "src/other.rs", "rust", &[block("other", "fn other() {}")]).await {
+            Err(OrchestratorError::InvalidConfig(message)) => assert_eq!(message, "index is read-only"),
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+        assert!(matches!(storage.remove_file("proj", "src/config.rs").await, Err(OrchestratorError::InvalidConfig(_))));
+        assert!(matches!(storage.store_embedding(1, &[0.5; 4]).await, Err(OrchestratorError::InvalidConfig(_))));
+
+        let mut search = SemanticSearch::new(storage);
+        let results = search.search("proj", "load_config", 5).await.unwrap().results;
+        assert_eq!(results[0].name.as_deref(), Some("load_config"));
+
+        // A missing index is an error, not a new empty file
+        let missing = dir.join("missing.db");
+        assert!(matches!(IndexStorage::open_read_only(&missing).await, Err(OrchestratorError::InvalidConfig(_))));
+        assert!(!missing.exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Counts how often embeddings are fetched from the wrapped storage
+    struct CountingSource {
+        storage: IndexStorage,
+        fetches: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingSource for CountingSource {
+        async fn embedding_fingerprint(&self, project_id: &str) -> rust_core::Result<EmbeddingFingerprint> {
+            self.storage.embedding_fingerprint(project_id).await
+        }
+
+        async fn get_block_embeddings(&self, project_id: &str) -> rust_core::Result<Vec<(i64, Vec<f32>)>> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.storage.get_block_embeddings(project_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedding_cache_fetches_once_until_index_changes() {
+        let source = CountingSource {
+            storage: IndexStorage::new(create_test_pool().await),
+            fetches: AtomicUsize::new(0),
+        };
+        source.storage.store_file("proj", "src/a.rs", "rust", &[block("alpha", "fn alpha() {}")]).await.unwrap();
+        let alpha = source.storage.get_block_id("proj", "src/a.rs", Some("alpha")).await.unwrap().unwrap();
+        source.storage.store_embedding(alpha, &[1.0, 0.0]).await.unwrap();
+
+        let mut cache = EmbeddingCache::new();
+        for _ in 0..5 {
+            assert_eq!(cache.get(&source, "proj").await.unwrap().len(), 1);
+        }
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+
+        // A newly embedded block changes the fingerprint
+        source.storage.store_file("proj", "src/b.rs", "rust", &[block("beta", "fn beta() {}")]).await.unwrap();
+        let beta = source.storage.get_block_id("proj", "src/b.rs", Some("beta")).await.unwrap().unwrap();
+        source.storage.store_embedding(beta, &[0.0, 1.0]).await.unwrap();
+        assert_eq!(cache.get(&source, "proj").await.unwrap().len(), 2);
+        cache.get(&source, "proj").await.unwrap();
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
+
+        // Projects are cached separately, and invalidation forces a reload
+        assert!(cache.get(&source, "other").await.unwrap().is_empty());
+        assert!(cache.invalidate("proj"));
+        assert!(!cache.invalidate("proj"));
+        cache.get(&source, "proj").await.unwrap();
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 4);
+        assert_eq!(cache.loads(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_search_reuses_embeddings_until_file_is_reindexed() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        storage.store_file("proj", "src/retry.rs", "rust", &[block("retry", "fn retry() {}")]).await.unwrap();
+        let old_block = storage.get_block_id("proj", "src/retry.rs", Some("retry")).await.unwrap().unwrap();
+        let query_embedding = EmbeddingGenerator::default().generate_query_embedding("retry");
+        storage.store_embedding(old_block, &query_embedding).await.unwrap();
+
+        let mut search = SemanticSearch::new(IndexStorage::new(pool));
+        assert_eq!(search.preload("proj").await.unwrap(), 1);
+        for _ in 0..3 {
+            let response = search.search("proj", "retry", 5).await.unwrap();
+            assert_eq!(response.mode, SearchMode::Hybrid);
+            assert_eq!(response.results[0].block_id, Some(old_block));
+        }
+        assert_eq!(search.embedding_loads(), 1);
+
+        // Re-indexing replaces the block, so its embedding must not be reused
+        storage.store_file("proj", "src/retry.rs", "rust", &[block("retry", "fn retry() { backoff(); }")]).await.unwrap();
+        let response = search.search("proj", "retry", 5).await.unwrap();
+        assert_eq!(search.embedding_loads(), 2);
+        assert_eq!(response.mode, SearchMode::KeywordOnly);
+        assert_ne!(response.results[0].block_id, Some(old_block));
+
+        assert!(search.invalidate("proj"));
+        search.search("proj", "retry", 5).await.unwrap();
+        assert_eq!(search.embedding_loads(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_search_batch_matches_individual_searches() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let blocks = search_fixture();
+        storage.store_file("proj", "src/config.rs", "rust", &blocks).await.unwrap();
+        let mut generator = EmbeddingGenerator::default();
+        for block in &blocks {
+            let block_id = storage.get_block_id("proj", "src/config.rs", block.name.as_deref()).await.unwrap().unwrap();
+            storage.store_embedding(block_id, &generator.generate_embedding(block)).await.unwrap();
+        }
+
+        let queries = ["config", "parse_config", "write"];
+        let mut batch_search = SemanticSearch::new(IndexStorage::new(pool.clone()));
+        let batch = batch_search.search_batch("proj", &queries, 5).await.unwrap();
+        assert_eq!(batch_search.embedding_loads(), 1);
+        assert_eq!(batch.len(), queries.len());
+
+        for (query, results) in queries.iter().zip(&batch) {
+            let mut single = SemanticSearch::new(IndexStorage::new(pool.clone()));
+            let expected = single.search("proj", query, 5).await.unwrap().results;
+            assert_eq!(format!("{:?}", results), format!("{:?}", expected));
+        }
+
+        // With dedup, a repeated query only gets blocks the first one did not return
+        let deduped = batch_search
+            .search_batch_with_dedup("proj", &["parse_config", "config"], 1, true)
+            .await
+            .unwrap();
+        assert_eq!(batch_search.embedding_loads(), 1);
+        assert_eq!(deduped[0].len(), 1);
+        assert_eq!(deduped[1].len(), 1);
+        assert_ne!(deduped[0][0].block_id, deduped[1][0].block_id);
+        assert_eq!(deduped[0][0].name.as_deref(), Some("parse_config"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_error_carries_breadcrumbs() {
+        let storage = IndexStorage::new(create_test_pool().await);
+
+        let err = storage
+            .update_blocks_in_range("proj", "src/missing.rs", 1, 2, 0, &[])
+            .await
+            .context("reindexing src/missing.rs")
+            .unwrap_err();
+        assert_eq!(
+            err.breadcrumbs(),
+            ["reindexing src/missing.rs", "updating blocks of file src/missing.rs in project proj"]
+        );
+        assert!(matches!(err.root_cause(), OrchestratorError::Storage(sqlx::Error::RowNotFound)));
+        let rendered = err.to_string();
+        assert!(rendered.starts_with(
+            "reindexing src/missing.rs: updating blocks of file src/missing.rs in project proj: Storage error: "
+        ));
+        assert!(rendered.contains("no rows returned"));
+    }
+
+    #[tokio::test]
+    async fn test_explanation_components_sum_to_score() {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = [
+            block("retry", "fn retry() { attempt(); }"),
+            block("schedule", "fn schedule() { retry_later(); }"),
+            block("backoff", "fn backoff() { sleep(); }"),
+        ];
+        storage.store_file("proj", "src/retry.rs", "rust", &blocks).await.unwrap();
+        let query_embedding = EmbeddingGenerator::default().generate_query_embedding("retry");
+        for name in ["retry", "backoff"] {
+            let block_id = storage.get_block_id("proj", "src/retry.rs", Some(name)).await.unwrap().unwrap();
+            storage.store_embedding(block_id, &query_embedding).await.unwrap();
+        }
+
+        let mut search = SemanticSearch::new(storage);
+        let results = search.search_with_explain("proj", "retry", 10, true).await.unwrap().results;
+        assert_eq!(results.len(), 3);
+        for result in &results {
+            let explanation = result.explanation.as_ref().unwrap();
+            let total = explanation.semantic_weight * explanation.semantic_score
+                + explanation.keyword_weight * explanation.keyword_score;
+            assert!((total - result.score).abs() < 1e-5, "{}", render_explanation(result));
+        }
+
+        let by_name = |name: &str| results.iter().find(|r| r.name.as_deref() == Some(name)).unwrap();
+        let exact = by_name("retry").explanation.as_ref().unwrap();
+        assert_eq!(exact.source, ResultSource::KeywordCandidate);
+        assert!(exact.exact_name_match && exact.content_hit);
+        assert_eq!((exact.semantic_weight, exact.keyword_weight), (0.7, 0.3));
+
+        let content_only = by_name("schedule").explanation.as_ref().unwrap();
+        assert!(!content_only.name_contains && content_only.content_hit);
+        assert_eq!((content_only.semantic_weight, content_only.keyword_weight), (0.0, 1.0));
+
+        let semantic = by_name("backoff").explanation.as_ref().unwrap();
+        assert_eq!(semantic.source, ResultSource::SemanticOnly);
+        assert_eq!(semantic.keyword_weight, 0.0);
+
+        let ranks: Vec<usize> = results.iter().map(|r| r.explanation.as_ref().unwrap().pre_dedup_rank).collect();
+        assert_eq!(ranks, vec![1, 2, 3]);
+        assert!(render_explanation(&results[0]).starts_with("#1 retry src/retry.rs"));
+
+        let plain = search.search("proj", "retry", 10).await.unwrap().results;
+        assert!(plain.iter().all(|r| r.explanation.is_none()));
+        assert!(render_explanation(&plain[0]).ends_with("(no explanation)"));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_dimension_is_recorded_and_enforced() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let blocks = [block("retry", "fn retry() {}"), block("backoff", "fn backoff() {}")];
+        storage.store_file("proj", "src/retry.rs", "rust", &blocks).await.unwrap();
+        let retry = storage.get_block_id("proj", "src/retry.rs", Some("retry")).await.unwrap().unwrap();
+        let backoff = storage.get_block_id("proj", "src/retry.rs", Some("backoff")).await.unwrap().unwrap();
+
+        assert_eq!(storage.embedding_meta("proj").await.unwrap(), None);
+        storage.store_embedding(retry, &EmbeddingGenerator::new(384).generate_embedding(&blocks[0])).await.unwrap();
+        assert_eq!(
+            storage.embedding_meta("proj").await.unwrap(),
+            Some(EmbeddingMeta { model: None, dim: 384 })
+        );
+
+        let wider = EmbeddingGenerator::new(768).generate_embedding(&blocks[1]);
+        let result = storage.store_embedding(backoff, &wider).await;
+        assert!(matches!(result, Err(OrchestratorError::InvalidInput(_))));
+
+        // A 768-dimensional search sees the 384-dimensional embedding as stale
+        let mut search = SemanticSearch::with_embedding_generator(IndexStorage::new(pool.clone()), EmbeddingGenerator::new(768));
+        let capabilities = search.capabilities("proj").await.unwrap();
+        assert_eq!(capabilities.stale_embeddings, 1);
+        assert!(capabilities.warning.unwrap().starts_with("1 stale embeddings are not 768-dimensional"));
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_embedding_generator(EmbeddingGenerator::new(768));
+        assert_eq!(indexer.reembed_all(1).await.unwrap(), 2);
+        assert_eq!(storage.embedding_meta("proj").await.unwrap().unwrap().dim, 768);
+        assert_eq!(storage.find_mismatched_embeddings("proj", 768).await.unwrap(), Vec::<i64>::new());
+        storage.store_embedding(backoff, &wider).await.unwrap();
+
+        let capabilities = search.capabilities("proj").await.unwrap();
+        assert_eq!((capabilities.stale_embeddings, capabilities.blocks_with_embeddings), (0, 2));
+        assert_eq!(capabilities.warning, None);
+        assert_eq!(search.search("proj", "retry", 5).await.unwrap().warning, None);
+
+        // Other projects record their own size
+        storage.store_file("other", "src/a.rs", "rust", &[block("a", "fn a() {}")]).await.unwrap();
+        let other = storage.get_block_id("other", "src/a.rs", Some("a")).await.unwrap().unwrap();
+        storage.store_embedding(other, &[1.0, 0.0]).await.unwrap();
+        assert_eq!(storage.embedding_meta("other").await.unwrap().unwrap().dim, 2);
+    }
+
+    /// Puts candidates in reverse order, or fails when `fail` is set
+    struct ReversingReranker {
+        fail: bool,
+        seen: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Reranker for ReversingReranker {
+        async fn rerank(&self, _query: &str, candidates: &[SearchResult]) -> rust_core::Result<Vec<(usize, f32)>> {
+            self.seen.store(candidates.len(), Ordering::SeqCst);
+            if self.fail {
+                return Err(OrchestratorError::Timeout("reranker unavailable".to_string()));
+            }
+            Ok((0..candidates.len()).rev().enumerate().map(|(rank, i)| (i, 1.0 - rank as f32 * 0.1)).collect())
+        }
+    }
+
+    async fn rerank_fixture() -> IndexStorage {
+        let storage = IndexStorage::new(create_test_pool().await);
+        let blocks = [
+            block("retry", "fn retry() {}"),
+            block("retry_later", "fn retry_later() {}"),
+            block("with_retry", "fn with_retry() {}"),
+            block("backoff", "fn backoff() { retry(); }"),
+        ];
+        storage.store_file("proj", "src/retry.rs", "rust", &blocks).await.unwrap();
+        storage
+    }
+
+    fn names(results: &[SearchResult]) -> Vec<String> {
+        results.iter().map(|r| r.name.clone().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_reranker_replaces_final_order() {
+        let hybrid = SemanticSearch::new(rerank_fixture().await).search("proj", "retry", 10).await.unwrap().results;
+        assert_eq!(names(&hybrid)[0], "retry");
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let reranker = ReversingReranker { fail: false, seen: seen.clone() };
+        let mut search = SemanticSearch::new(rerank_fixture().await)
+            .with_reranker(Box::new(reranker))
+            .with_rerank_candidates(3);
+        let response = search.search_with_explain("proj", "retry", 10, true).await.unwrap();
+        assert_eq!(response.warning, None);
+        assert_eq!(seen.load(Ordering::SeqCst), 4); // The window grows to the requested limit
+
+        let mut expected = names(&hybrid);
+        expected.reverse();
+        assert_eq!(names(&response.results), expected);
+        let top = &response.results[0];
+        assert_eq!(top.score, 1.0);
+        let explanation = top.explanation.as_ref().unwrap();
+        assert_eq!(explanation.rerank_score, Some(1.0));
+        // The hybrid components are kept
+        let original = hybrid.iter().find(|r| r.name == top.name).unwrap();
+        let hybrid_score = explanation.semantic_weight * explanation.semantic_score
+            + explanation.keyword_weight * explanation.keyword_score;
+        assert!((hybrid_score - original.score).abs() < 1e-5);
+        assert!(render_explanation(top).contains("reranked: 1.000"));
+
+        // Only the top candidates are reranked when the limit is small
+        let response = search.search("proj", "retry", 2).await.unwrap();
+        assert_eq!(seen.load(Ordering::SeqCst), 3);
+        assert_eq!(names(&response.results), vec![names(&hybrid)[2].clone(), names(&hybrid)[1].clone()]);
+
+        // Paging ranks by hybrid score
+        let page = search.search_page("proj", "retry", 10, None).await.unwrap();
+        assert_eq!(names(&page.results), names(&hybrid));
+    }
+
+    #[tokio::test]
+    async fn test_reranker_failure_keeps_hybrid_order() {
+        let hybrid = SemanticSearch::new(rerank_fixture().await).search("proj", "retry", 10).await.unwrap().results;
+
+        let failing = ReversingReranker { fail: true, seen: Arc::new(AtomicUsize::new(0)) };
+        let unreachable = HttpReranker::new("http://127.0.0.1:9/rerank").with_timeout(Duration::from_millis(500));
+        for reranker in [Box::new(failing) as Box<dyn Reranker>, Box::new(unreachable)] {
+            let mut search = SemanticSearch::new(rerank_fixture().await).with_reranker(reranker);
+            let response = search.search("proj", "retry", 10).await.unwrap();
+            assert_eq!(names(&response.results), names(&hybrid));
+            assert!(response.results.iter().zip(&hybrid).all(|(a, b)| a.score == b.score));
+            assert!(response.warning.unwrap().starts_with("Reranking failed"));
+        }
+
+        let mut noop = SemanticSearch::new(rerank_fixture().await).with_reranker(Box::new(NoopReranker));
+        let response = noop.search("proj", "retry", 10).await.unwrap();
+        assert_eq!(names(&response.results), names(&hybrid));
+        assert_eq!(response.warning, None);
+    }
+
+    const RETRY_FIXTURE: &str = r#"/// Retry policy for flaky calls. Shared by all clients.
+struct RetryPolicy {
+    attempts: u32,
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> u64 {
+        100 * attempt as u64
+    }
+}
+
+/// Run `op` until it succeeds.
+fn retry<F: Fn() -> bool>(policy: &RetryPolicy, op: F) -> bool {
+    (0..policy.attempts).any(|_| op())
+}
+"#;
+
+    #[tokio::test]
+    async fn test_extractive_file_summary() {
+        let root = temp_dir();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/retry.rs"), RETRY_FIXTURE).unwrap();
+        std::fs::write(root.join("src/render.rs"), "fn render_page() -> String {\n    String::new()\n}\n").unwrap();
+
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 2);
+        assert_eq!(storage.get_file_summary("proj", "src/retry.rs").await.unwrap(), None);
+        assert_eq!(indexer.summarize_files().await.unwrap(), 2);
+        assert_eq!(indexer.summarize_files().await.unwrap(), 0);
+
+        let summary = storage.get_file_summary("proj", "src/retry.rs").await.unwrap().unwrap();
+        assert!(summary.starts_with("src/retry.rs (rust): 2 functions, 1 impl, 1 struct."), "{}", summary);
+        assert!(summary.contains(
+            "Top-level: struct RetryPolicy; impl RetryPolicy; fn retry<F: Fn() -> bool>(policy: &RetryPolicy, op: F) -> bool."
+        ), "{}", summary);
+        // Only the first sentence of each docstring, and nested blocks are not listed
+        assert!(summary.ends_with("Notes: Retry policy for flaky calls. Run `op` until it succeeds."), "{}", summary);
+        assert!(!summary.contains("fn delay"));
+
+        // Re-indexing clears the summary until the next pass
+        std::fs::write(root.join("src/render.rs"), "fn render_header() -> String {\n    String::new()\n}\n").unwrap();
+        indexer.update_file(&root.join("src/render.rs")).await.unwrap();
+        assert_eq!(storage.get_file_summary("proj", "src/render.rs").await.unwrap(), None);
+        assert_eq!(indexer.summarize_files().await.unwrap(), 1);
+        assert!(storage.get_file_summary("proj", "src/render.rs").await.unwrap().unwrap().contains("render_header"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_files_matches_summaries_and_paths() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        storage.store_file("proj", "src/retry.rs", "rust", &[block("retry", "fn retry() -> bool { backoff() }")]).await.unwrap();
+        storage.store_file("proj", "src/net/client.rs", "rust", &[block("send", "fn send() { retry(); }")]).await.unwrap();
+        storage.store_file("proj", "README.md", "markdown", &[]).await.unwrap();
+        storage.set_file_summary("proj", "src/retry.rs", "Retry policy with exponential backoff.").await.unwrap();
+        storage.set_file_summary("proj", "src/net/client.rs", "HTTP client that calls retry on failure.").await.unwrap();
+
+        let search = SemanticSearch::new(storage).with_display_root(PathBuf::from("/repo"));
+        let results = search.search_files("proj", "retry backoff", 10).await.unwrap();
+        let found: Vec<(&str, f32)> = results.iter().map(|r| (r.file_path.as_str(), r.score)).collect();
+        // retry: path and summary (3), backoff: summary (1), out of 6
+        assert_eq!(found[0], ("/repo/src/retry.rs", 4.0 / 6.0));
+        assert_eq!(found[1], ("/repo/src/net/client.rs", 1.0 / 6.0));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].matched_terms, vec!["retry", "backoff"]);
+        assert_eq!(results[0].summary.as_deref(), Some("Retry policy with exponential backoff."));
+
+        // Files without a summary still match on their path
+        let readme = search.search_files("proj", "readme", 10).await.unwrap();
+        assert_eq!(readme.len(), 1);
+        assert_eq!(readme[0].summary, None);
+        assert!(search.search_files("proj", "  ", 10).await.unwrap().is_empty());
+        assert_eq!(search.search_files("proj", "retry", 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_recovers_journaled_changes() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        std::fs::write(root.join("a.rs"), "fn alpha() { let a = 1; }\n").unwrap();
+        std::fs::write(root.join("c.rs"), "fn gamma() { let c = 3; }\n").unwrap();
+        std::fs::write(root.join("d.rs"), "fn delta() { let d = 4; }\n").unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 3);
+
+        // Changes made while the watcher was down, journaled but never applied
+        std::fs::write(root.join("a.rs"), "fn renamed_alpha() { let a = 10; }\n").unwrap();
+        std::fs::write(root.join("b.rs"), "fn beta() { let b = 2; }\n").unwrap();
+        std::fs::remove_file(root.join("c.rs")).unwrap();
+        std::fs::remove_file(root.join("d.rs")).unwrap();
+        let journal = WatcherJournal::new(pool.clone(), "proj");
+        journal.append(&root.join("a.rs"), ChangeKind::Update).await.unwrap();
+        journal.append(&root.join("b.rs"), ChangeKind::Update).await.unwrap();
+        journal.append(&root.join("c.rs"), ChangeKind::RemoveFile).await.unwrap();
+        // Newest kind wins: d.rs was modified, then removed
+        journal.append(&root.join("d.rs"), ChangeKind::Update).await.unwrap();
+        journal.append(&root.join("d.rs"), ChangeKind::RemoveFile).await.unwrap();
+        journal.append(&root.join("a.rs"), ChangeKind::Update).await.unwrap();
+
+        let pending = journal.pending().await.unwrap();
+        assert_eq!(pending.len(), 4);
+        assert_eq!(pending[0].path, root.join("a.rs"));
+        assert_eq!(pending[3].kind, ChangeKind::RemoveFile);
+
+        let mut watcher = FileWatcher::new(indexer).unwrap().with_journal(journal.clone());
+        assert_eq!(watcher.recover_pending().await.unwrap(), 4);
+        assert!(journal.pending().await.unwrap().is_empty());
+
+        let mut files: Vec<(String,)> = sqlx::query_as("SELECT file_path FROM indexed_files")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        files.sort();
+        assert_eq!(files, vec![("a.rs".to_string(),), ("b.rs".to_string(),)]);
+        let mut search = SemanticSearch::new(IndexStorage::new(pool.clone()));
+        let results = search.search("proj", "renamed_alpha", 5).await.unwrap().results;
+        assert!(!results.is_empty());
+
+        // Nothing left to replay
+        assert_eq!(watcher.recover_pending().await.unwrap(), 0);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_watcher_journal_purges_expired_entries() {
+        let pool = create_test_pool().await;
+        let journal = WatcherJournal::new(pool.clone(), "proj").with_max_age(Duration::from_secs(3600));
+        let now = chrono::Utc::now().timestamp();
+        journal.append_at(std::path::Path::new("/repo/old.rs"), ChangeKind::Update, now - 7200).await.unwrap();
+        journal.append_at(std::path::Path::new("/repo/new.rs"), ChangeKind::Update, now - 60).await.unwrap();
+        WatcherJournal::new(pool.clone(), "other")
+            .append_at(std::path::Path::new("/repo/old.rs"), ChangeKind::Update, now - 7200)
+            .await
+            .unwrap();
+
+        assert_eq!(journal.purge_expired().await.unwrap(), 1);
+        let pending = journal.pending().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path, PathBuf::from("/repo/new.rs"));
+
+        // Replaying a missing file is a no-op that still clears the entry
+        let indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        let mut watcher = FileWatcher::new(indexer).unwrap().with_journal(journal.clone());
+        assert_eq!(watcher.recover_pending().await.unwrap(), 1);
+        assert!(journal.pending().await.unwrap().is_empty());
+        assert_eq!(ChangeKind::parse("remove_dir").unwrap(), ChangeKind::RemoveDir);
+        assert!(ChangeKind::parse("rename").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_indexing_problems_are_recorded_as_diagnostics() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        std::fs::write(root.join("lib.rs"), "fn library() { let z = 3; }\n").unwrap();
+        // Go is detected but has no grammar, so parsing fails and the file is one block
+        std::fs::write(root.join("main.go"), "package main\n\nfunc main() {}\n").unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()));
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 2);
+        let recent = indexer.diagnostics().recent(10);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].severity, Severity::Warning);
+        assert_eq!(recent[0].source, DiagnosticSource::Parser);
+        assert_eq!(recent[0].path.as_deref(), Some("main.go"));
+
+        sqlx::query("DROP TABLE code_blocks").execute(&pool).await.unwrap();
+        std::fs::write(root.join("broken.rs"), "fn broken() { let b = 2; }\n").unwrap();
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 0);
+
+        let recent = indexer.diagnostics().recent(100);
+        let storage: Vec<_> = recent.iter().filter(|d| d.source == DiagnosticSource::Storage).collect();
+        assert!(storage.iter().any(|d| d.path.as_deref() == Some("broken.rs") && d.severity == Severity::Error));
+        assert!(recent.iter().any(|d| d.source == DiagnosticSource::Indexer && d.message.contains("broken.rs")));
+        assert_eq!(indexer.diagnostics().recent(1).len(), 1);
+
+        // The watcher reports into the indexer's sink
+        let watcher = FileWatcher::new(indexer).unwrap();
+        let drained = watcher.diagnostics().drain();
+        assert_eq!(drained.len(), recent.len());
+        assert!(watcher.diagnostics().recent(10).is_empty());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_diagnostics_ring_buffer_and_severity_filter() {
+        let diagnostics = Diagnostics::with_capacity(2).with_min_severity(Severity::Error);
+        diagnostics.warn(DiagnosticSource::Embedding, None, "fell back to hash");
+        assert!(diagnostics.recent(10).is_empty());
+
+        for i in 0..3 {
+            diagnostics.error(DiagnosticSource::Watcher, Some("a.rs"), format!("failure {}", i));
+        }
+        let messages: Vec<String> = diagnostics.recent(10).into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, vec!["failure 1", "failure 2"]);
+
+        diagnostics.set_min_severity(Severity::Info);
+        diagnostics.record(Severity::Info, DiagnosticSource::Indexer, None, "started");
+        assert_eq!(diagnostics.recent(1)[0].message, "started");
+        assert_eq!(Severity::parse("warning").unwrap(), Severity::Warning);
+        assert!(Severity::parse("fatal").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_latin1_file_is_indexed_lossily_and_binary_skipped() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        let latin1 = b"fn menu_total(items: usize) -> usize {\n    // R\xe9sum\xe9 of the caf\xe9 menu\n    items * 3\n}\n";
+        std::fs::write(root.join("menu.rs"), latin1).unwrap();
+        let binary: Vec<u8> = (0..4096u32).map(|i| if i % 3 == 0 { 0 } else { 0x80 | (i % 0x7f) as u8 }).collect();
+        std::fs::write(root.join("blob.rs"), &binary).unwrap();
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_root(root.clone());
+        indexer.diagnostics().set_min_severity(Severity::Info);
+        assert_eq!(indexer.index_file(&root.join("menu.rs")).await.unwrap(), IndexOutcome::Indexed);
+        assert_eq!(
+            indexer.index_file(&root.join("blob.rs")).await.unwrap(),
+            IndexOutcome::Skipped(SkipReason::Binary)
+        );
+        assert_eq!(indexer.index_directory(&root).await.unwrap(), 1);
+        assert_eq!(indexed_file_count(&pool).await, 1);
+
+        let mut search = SemanticSearch::new(IndexStorage::new(pool));
+        let results = search.search("proj", "menu_total", 5).await.unwrap().results;
+        assert!(results[0].content.contains("R\u{FFFD}sum\u{FFFD}"));
+
+        let diagnostics = indexer.diagnostics().recent(10);
+        assert!(diagnostics.iter().any(|d| d.severity == Severity::Warning
+            && d.path.as_deref() == Some("menu.rs")
+            && d.message.contains("lossily")));
+        assert!(diagnostics.iter().any(|d| d.path.as_deref() == Some("blob.rs") && d.message.contains("binary")));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_crlf_and_bom_files_match_lf_line_numbers() {
+        let lf = rust_functions(5, Some(2));
+        let crlf = format!("\u{FEFF}{}", lf.replace('\n', "\r\n"));
+
+        let mut lines = Vec::new();
+        for content in [&lf, &crlf] {
+            let pool = create_test_pool().await;
+            let root = temp_dir();
+            std::fs::write(root.join("lib.rs"), content).unwrap();
+            let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+                .with_root(root.clone());
+            indexer.index_file(&root.join("lib.rs")).await.unwrap();
+
+            let stored: Vec<(Option<String>, i64, i64, String)> = sqlx::query_as(
+                "SELECT name, start_line, end_line, content FROM code_blocks ORDER BY start_line"
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            assert!(stored.iter().all(|(_, _, _, content)| !content.contains('\r')));
+            lines.push(stored);
+            std::fs::remove_dir_all(&root).ok();
+        }
+        assert_eq!(lines[0], lines[1]);
+
+        let decoded = decode_source(b"a\0b\r\n", 0.5).unwrap();
+        assert_eq!((decoded.content.as_str(), decoded.replacements), ("a\u{FFFD}b\n", 1));
+        assert_eq!(decode_source(b"\0\0\0x", 0.5), Err(SkipReason::Binary));
+    }
+
+    const STORAGE_SCALA: &str = "class Storage {\n  def save(key: String): Unit = {\n    println(key)\n  }\n}\n";
+
+    /// (file_path, block_type, name, start_line, end_line, content, source, parent name)
+    async fn stored_rows(pool: &SqlitePool) -> Vec<(String, String, String, i64, i64, String, String, Option<String>)> {
+        sqlx::query_as(
+            r#"
+            SELECT f.file_path, c.block_type, c.name, c.start_line, c.end_line, c.content, c.source, p.name
+            FROM code_blocks c
+            JOIN indexed_files f ON c.file_id = f.id
+            LEFT JOIN code_blocks p ON c.parent_block_id = p.id
+            ORDER BY f.file_path, c.start_line, c.source
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    fn write_ctags_fixture(root: &std::path::Path, include_kotlin: bool) -> PathBuf {
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/Storage.scala"), STORAGE_SCALA).unwrap();
+        let mut tags = vec![
+            r#"{"_type": "ptag", "name": "JSON_OUTPUT_VERSION", "path": "0.0", "pattern": "in development"}"#,
+            r#"{"_type": "tag", "name": "Storage", "path": "src/Storage.scala", "language": "Scala", "line": 1, "end": 5, "kind": "class"}"#,
+            r#"{"_type": "tag", "name": "save", "path": "src/Storage.scala", "language": "Scala", "line": 2, "end": 4, "kind": "method", "scope": "Storage", "scopeKind": "class"}"#,
+        ];
+        if include_kotlin {
+            // Not checked out locally
+            tags.push(r#"{"_type": "tag", "name": "Retry", "path": "./src/Retry.kt", "language": "Kotlin", "line": 3, "kind": "object"}"#);
+        }
+        let path = root.join("tags.json");
+        std::fs::write(&path, tags.join("\n")).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_import_ctags_stores_searchable_blocks() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let root = temp_dir();
+        let tags = write_ctags_fixture(&root, true);
+
+        assert_eq!(import_ctags("proj", &tags, &storage).await.unwrap(), 3);
+        let rows = stored_rows(&pool).await;
+        assert_eq!(rows.len(), 3);
+
+        let (path, block_type, name, start, end, content, source, parent) = &rows[0];
+        assert_eq!((path.as_str(), block_type.as_str(), name.as_str()), ("src/Retry.kt", "object", "Retry"));
+        assert_eq!((*start, *end), (2, 2));
+        assert!(content.is_empty());
+        assert_eq!((source.as_str(), parent), ("ctags", &None));
+
+        let (_, block_type, name, start, end, content, _, parent) = &rows[1];
+        assert_eq!((block_type.as_str(), name.as_str(), *start, *end), ("class", "Storage", 0, 4));
+        assert_eq!(content, STORAGE_SCALA.trim_end());
+        assert!(parent.is_none());
+
+        let (_, block_type, name, start, end, content, _, parent) = &rows[2];
+        assert_eq!((block_type.as_str(), name.as_str(), *start, *end), ("method", "Storage.save", 1, 3));
+        assert!(content.starts_with("  def save(key: String)"));
+        assert_eq!(parent.as_deref(), Some("Storage"));
+
+        let mut search = SemanticSearch::new(IndexStorage::new(pool));
+        let results = search.search("proj", "save", 5).await.unwrap().results;
+        let hit = results.iter().find(|r| r.name.as_deref() == Some("Storage.save")).unwrap();
+        assert_eq!(hit.file_path, "src/Storage.scala");
+        assert_eq!(hit.parent_name.as_deref(), Some("Storage"));
+        let results = search.search("proj", "Retry", 5).await.unwrap().results;
+        assert_eq!(results[0].file_path, "src/Retry.kt");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_imported_and_parsed_blocks_do_not_replace_each_other() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let root = temp_dir();
+        let tags = write_ctags_fixture(&root, true);
+        import_ctags("proj", &tags, &storage).await.unwrap();
+
+        // Re-indexing from the AST keeps the imported symbols
+        storage
+            .store_file("proj", "src/Storage.scala", "scala", &[block("Storage", STORAGE_SCALA)])
+            .await
+            .unwrap();
+        storage
+            .store_file("proj", "src/Storage.scala", "scala", &[block("Storage", STORAGE_SCALA)])
+            .await
+            .unwrap();
+        let sources: Vec<String> = stored_rows(&pool).await.into_iter().map(|row| row.6).collect();
+        assert_eq!(sources, vec!["ctags", "ast", "ctags", "ctags"]);
+
+        // A new import replaces only earlier imports; files no longer tagged go away
+        let tags = write_ctags_fixture(&root, false);
+        assert_eq!(import_ctags("proj", &tags, &storage).await.unwrap(), 2);
+        let rows = stored_rows(&pool).await;
+        let sources: Vec<&str> = rows.iter().map(|row| row.6.as_str()).collect();
+        assert_eq!(sources, vec!["ast", "ctags", "ctags"]);
+        assert!(rows.iter().all(|row| row.0 == "src/Storage.scala"));
+        assert_eq!(indexed_file_count(&pool).await, 1);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_import_lsif_definitions() {
+        let pool = create_test_pool().await;
+        let storage = IndexStorage::new(pool.clone());
+        let root = temp_dir();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/Storage.scala"), STORAGE_SCALA).unwrap();
+
+        let root_uri = format!("file://{}", root.display());
+        let dump = [
+            format!(r#"{{"id": 1, "type": "vertex", "label": "metaData", "version": "0.4.3", "projectRoot": "{}"}}"#, root_uri),
+            format!(r#"{{"id": 2, "type": "vertex", "label": "document", "uri": "{}/src/Storage.scala", "languageId": "scala"}}"#, root_uri),
+            r#"{"id": 3, "type": "vertex", "label": "range", "start": {"line": 0, "character": 6}, "end": {"line": 0, "character": 13}, "tag": {"type": "definition", "text": "Storage", "kind": 5, "fullRange": {"start": {"line": 0, "character": 0}, "end": {"line": 4, "character": 1}}}}"#.to_string(),
+            r#"{"id": 4, "type": "vertex", "label": "range", "start": {"line": 1, "character": 6}, "end": {"line": 1, "character": 10}, "tag": {"type": "definition", "text": "save", "kind": 6, "fullRange": {"start": {"line": 1, "character": 2}, "end": {"line": 3, "character": 3}}}}"#.to_string(),
+            r#"{"id": 5, "type": "vertex", "label": "range", "start": {"line": 2, "character": 4}, "end": {"line": 2, "character": 11}, "tag": {"type": "reference", "text": "println"}}"#.to_string(),
+            r#"{"id": 6, "type": "edge", "label": "contains", "outV": 2, "inVs": [3, 4, 5]}"#.to_string(),
+        ];
+        let path = root.join("dump.lsif");
+        std::fs::write(&path, dump.join("\n")).unwrap();
+
+        assert_eq!(import_lsif("proj", &path, &storage).await.unwrap(), 2);
+        let rows = stored_rows(&pool).await;
+        let summary: Vec<(&str, &str, i64, i64, &str)> = rows
+            .iter()
+            .map(|row| (row.1.as_str(), row.2.as_str(), row.3, row.4, row.6.as_str()))
+            .collect();
+        assert_eq!(summary, vec![("class", "Storage", 0, 4, "lsif"), ("method", "Storage.save", 1, 3, "lsif")]);
+        assert!(rows.iter().all(|row| row.0 == "src/Storage.scala"));
+        assert_eq!(rows[1].7.as_deref(), Some("Storage"));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, "not json\n").unwrap();
+        assert!(matches!(
+            import_lsif("proj", &path, &storage).await,
+            Err(OrchestratorError::InvalidInput(_))
+        ));
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    /// Embeds each block as its content length after a delay
+    struct SlowEmbedder {
+        delay: Duration,
+    }
+
+    impl BlockEmbedder for SlowEmbedder {
+        fn embed(&mut self, block: &CodeBlock) -> Vec<f32> {
+            std::thread::sleep(self.delay);
+            vec![block.content.len() as f32]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedding_pool_applies_backpressure_to_a_fast_producer() {
+        let (threads, capacity) = (2, 3);
+        let mut pool = EmbeddingPool::new(threads, capacity, || {
+            Box::new(SlowEmbedder { delay: Duration::from_millis(5) }) as Box<dyn BlockEmbedder>
+        });
+
+        let mut embeddings = Vec::new();
+        for id in 0..40 {
+            let content = "x".repeat(id as usize + 1);
+            pool.submit(id, block(&format!("f{}", id), &content)).await.unwrap();
+            embeddings.extend(pool.take_completed());
+        }
+        let stats = pool.stats();
+        assert_eq!(stats.submitted, 40);
+        assert!(stats.max_pending <= capacity + threads, "max pending was {}", stats.max_pending);
+        assert!(stats.max_pending > 0);
+
+        embeddings.extend(pool.finish().await.unwrap());
+        embeddings.sort_by_key(|(id, _)| *id);
+        assert_eq!(embeddings.len(), 40);
+        for (id, embedding) in &embeddings {
+            assert_eq!(embedding, &vec![*id as f32 + 1.0]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_embeds_blocks_on_workers() {
+        let pool = create_test_pool().await;
+        let root = temp_dir();
+        for name in ["alpha", "beta", "gamma", "delta"] {
+            std::fs::write(
+                root.join(format!("{}.rs", name)),
+                format!("fn {}() {{ let x = 1; }}
+
+fn {}_helper() {{ let y = 2; }}
+", name, name),
+            )
+            .unwrap();
+        }
+
+        let mut indexer = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(pool.clone()))
+            .with_embedding_workers(2);
+        let report = indexer.index_directory_report(&root).await.unwrap();
+
+        let storage = IndexStorage::new(pool.clone());
+        let (with_embeddings, total) = storage.embedding_coverage("proj").await.unwrap();
+        assert_eq!(report.files_indexed, 4);
+        assert!(total >= 8);
+        assert_eq!(report.embeddings_completed, total as usize);
+        assert_eq!(with_embeddings, total);
+        assert_eq!(report.embeddings_pending, 0);
+
+        // Without workers nothing is embedded
+        let other = create_test_pool().await;
+        let mut plain = CodebaseIndexer::new("proj".to_string(), IndexStorage::new(other.clone()));
+        let report = plain.index_directory_report(&root).await.unwrap();
+        assert_eq!(report.embeddings_completed, 0);
+        assert_eq!(IndexStorage::new(other).embedding_coverage("proj").await.unwrap().0, 0);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_persistent_cache_serves_embeddings_after_restart() {
+        let dir = temp_dir();
+        let blocks: Vec<CodeBlock> = (0..3)
+            .map(|i| block(&format!("handler_{}", i), &format!("fn handler_{}() {{ serve({}); }}", i, i)))
+            .collect();
+
+        let mut first = EmbeddingGenerator::default().with_persistent_cache(dir.join("cache"), 100).unwrap();
+        let embeddings: Vec<Vec<f32>> = blocks.iter().map(|b| first.generate_embedding(b)).collect();
+        first.generate_embedding(&blocks[0]);
+        let stats = first.cache_stats();
+        assert_eq!((stats.memory_hits, stats.disk_hits, stats.computed), (1, 0, 3));
+        assert_eq!(first.persistent_cache().unwrap().len(), 3);
+        drop(first);
+
+        // A new generator on the same directory reads every embedding back
+        let mut second = EmbeddingGenerator::default().with_persistent_cache(dir.join("cache"), 100).unwrap();
+        let reloaded: Vec<Vec<f32>> = blocks.iter().map(|b| second.generate_embedding(b)).collect();
+        assert_eq!(reloaded, embeddings);
+        let stats = second.cache_stats();
+        assert_eq!((stats.memory_hits, stats.disk_hits, stats.computed), (0, 3, 0));
+
+        // Forks share the disk cache and the counters
+        let mut fork = second.fork();
+        fork.generate_embedding(&blocks[1]);
+        fork.generate_embedding(&block("fresh", "fn fresh() {}"));
+        let stats = second.cache_stats();
+        assert_eq!((stats.disk_hits, stats.computed), (4, 1));
+        assert_eq!(second.persistent_cache().unwrap().len(), 4);
+        drop((second, fork));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_persistent_cache_evicts_least_recently_used() {
+        let dir = temp_dir();
+        let cache = DiskEmbeddingCache::open(&dir.join("cache"), 2).unwrap();
+        cache.put("a", &[1.0, 2.0]).unwrap();
+        cache.put("b", &[3.0]).unwrap();
+        assert_eq!(cache.get("a").unwrap(), Some(vec![1.0, 2.0]));
+
+        // "b" is now the least recently used
+        cache.put("c", &[4.0]).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b").unwrap(), None);
+        assert_eq!(cache.get("c").unwrap(), Some(vec![4.0]));
+        drop(cache);
+
+        // Reopening with a smaller cap keeps only the most recently used
+        let reopened = DiskEmbeddingCache::open(&dir.join("cache"), 1).unwrap();
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened.get("c").unwrap(), Some(vec![4.0]));
+        assert_eq!(reopened.get("a").unwrap(), None);
+        drop(reopened);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_persistent_cache_reopens_right_after_drop() {
+        let dir = temp_dir();
+        for round in 0..10 {
+            let cache = DiskEmbeddingCache::open(&dir.join("cache"), 100).unwrap();
+            assert_eq!(cache.len(), round);
+            cache.put(&format!("k{}", round), &[round as f32]).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
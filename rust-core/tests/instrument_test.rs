@@ -0,0 +1,137 @@
+/// Tests for per-query timing and slow query logging
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{Context, ContextStorage};
+    use rust_core::observability::testing::{CapturingMetrics, CapturingSpanExporter};
+    use rust_core::storage::{fingerprint, InstrumentedPool, QueryLog};
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tracing::Level;
+
+    async fn create_test_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    #[test]
+    fn test_fingerprint_strips_literals() {
+        assert_eq!(
+            fingerprint("SELECT * FROM t\n  WHERE name = 'it''s'   AND id = 42 AND x = ?1"),
+            "SELECT * FROM t WHERE name = ? AND id = ? AND x = ?1"
+        );
+        assert_eq!(
+            fingerprint("SELECT col2 FROM t2 LIMIT 10 OFFSET 2.5"),
+            "SELECT col2 FROM t2 LIMIT ? OFFSET ?"
+        );
+        assert_eq!(fingerprint("SELECT 1"), fingerprint("SELECT 2"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_like_scan_is_reported() {
+        let capture = CapturingSpanExporter::new();
+        let _guard = capture.install();
+
+        // A threshold of zero makes every query slow, so nothing depends on timing
+        let log = Arc::new(QueryLog::new());
+        let metrics = CapturingMetrics::new();
+        let pool = InstrumentedPool::new(create_test_pool().await)
+            .with_log(log.clone())
+            .with_metrics(metrics.collector())
+            .with_slow_threshold(Duration::ZERO);
+
+        pool.execute(sqlx::query("CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT)"))
+            .await
+            .unwrap();
+        let inserted = pool
+            .execute(sqlx::query(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200000)
+                 INSERT INTO docs (body) SELECT hex(randomblob(64)) FROM n",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(inserted.rows_affected(), 200000);
+        log.clear();
+        capture.clear();
+
+        for needle in ["%ABCDEF%", "%FEDCBA%", "%012345%"] {
+            let _: (i64,) = pool
+                .fetch_one(sqlx::query_as("SELECT COUNT(*) FROM docs WHERE body LIKE ?1 OR body LIKE '%zz%'").bind(needle))
+                .await
+                .unwrap();
+        }
+        let _: Option<(i64,)> = pool
+            .fetch_optional(sqlx::query_as("SELECT id FROM docs WHERE id = 7"))
+            .await
+            .unwrap();
+
+        let scan = "SELECT COUNT(*) FROM docs WHERE body LIKE ?1 OR body LIKE ?";
+        let lookup = "SELECT id FROM docs WHERE id = ?";
+        let top = log.top_queries(5);
+        let mut fingerprints: Vec<&str> = top.iter().map(|stats| stats.fingerprint.as_str()).collect();
+        fingerprints.sort();
+        assert_eq!(fingerprints, vec![scan, lookup]);
+        let stats = log.get(scan).unwrap();
+        assert_eq!((stats.count, stats.rows), (3, 3));
+        let stats = log.get(lookup).unwrap();
+        assert_eq!((stats.count, stats.rows), (1, 1));
+
+        let events = capture.events_at(Level::WARN);
+        assert_eq!(events.len(), 4);
+        assert!(events[..3].iter().all(|event| event.field("fingerprint") == Some(scan)));
+        assert!(events[..3].iter().all(|event| event.field("rows") == Some("1")));
+        assert_eq!(events[3].field("fingerprint"), Some(lookup));
+        assert_eq!(events[0].message(), Some("slow query"));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.get("uai_db_query_duration_seconds_count", &[("fingerprint", scan)]), Some(3.0));
+    }
+
+    #[test]
+    fn test_top_queries_rank_by_total_time() {
+        let log = QueryLog::new();
+        log.record("SELECT a", Duration::from_millis(30), 1);
+        log.record("SELECT b", Duration::from_millis(20), 1);
+        log.record("SELECT b", Duration::from_millis(20), 1);
+        log.record("SELECT c", Duration::from_millis(5), 1);
+
+        let top = log.top_queries(2);
+        let fingerprints: Vec<&str> = top.iter().map(|stats| stats.fingerprint.as_str()).collect();
+        assert_eq!(fingerprints, vec!["SELECT b", "SELECT a"]);
+        assert_eq!(top[0].max, Duration::from_millis(20));
+        assert_eq!(top[0].mean(), Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_storage_queries_go_through_the_instrumented_pool() {
+        let metrics = CapturingMetrics::new();
+        let storage = ContextStorage::from_pool(create_test_pool().await)
+            .await
+            .unwrap()
+            .with_metrics(metrics.collector());
+
+        let mut context = Context::new(None);
+        storage.save_context(&mut context).await.unwrap();
+        assert!(storage.load_context(&context.conversation_id).await.unwrap().is_some());
+
+        let load = "SELECT data, version FROM contexts WHERE conversation_id = ?1";
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.get("uai_db_query_duration_seconds_count", &[("fingerprint", load)]), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_failed_queries_are_recorded() {
+        let log = Arc::new(QueryLog::new());
+        let pool = InstrumentedPool::new(create_test_pool().await).with_log(log.clone());
+        assert!(pool.execute(sqlx::query("DELETE FROM missing WHERE id = 1")).await.is_err());
+
+        let stats = log.get("DELETE FROM missing WHERE id = ?").unwrap();
+        assert_eq!((stats.count, stats.rows), (1, 0));
+        assert_eq!(log.top_queries(5).len(), 1);
+        assert!(log.top_queries(0).is_empty());
+    }
+}
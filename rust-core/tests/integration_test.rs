@@ -2,8 +2,10 @@
 
 #[cfg(test)]
 mod tests {
-    use rust_core::context::{Context, ContextManager, ContextStorage};
-    use rust_core::error::Result;
+    use rust_core::context::summarizer::ContextSummarizer;
+    use rust_core::context::title::title_from_message;
+    use rust_core::context::{Context, ContextManager, ContextStorage, Message, Role};
+    use rust_core::error::OrchestratorError;
     use std::path::PathBuf;
     
     #[tokio::test]
@@ -12,9 +14,9 @@ mod tests {
         let storage = ContextStorage::new(db_path).await.unwrap();
         
         let mut context = Context::new(None);
-        context.add_message("user".to_string(), "Hello".to_string());
+        context.add_message(Role::User, "Hello".to_string());
         
-        storage.save_context(&context).await.unwrap();
+        storage.save_context(&mut context).await.unwrap();
         
         let loaded = storage.load_context(&context.conversation_id).await.unwrap();
         assert!(loaded.is_some());
@@ -31,4 +33,341 @@ mod tests {
         let context = manager.get_or_create_context(None, None).await.unwrap();
         assert!(!context.conversation_id.is_empty());
     }
+    
+    #[test]
+    fn test_role_aliases_normalize() {
+        assert_eq!(Role::parse("User").unwrap(), Role::User);
+        assert_eq!(Role::parse("assistant ").unwrap(), Role::Assistant);
+        assert_eq!(Role::parse("AI").unwrap(), Role::Assistant);
+        assert_eq!(Role::parse(" Human").unwrap(), Role::User);
+        assert_eq!(Role::parse("SYSTEM").unwrap(), Role::System);
+        assert_eq!(Role::parse("function").unwrap(), Role::Tool);
+        assert_eq!(Role::parse(" Critic ").unwrap(), Role::Other("critic".to_string()));
+        
+        let mut context = Context::new(None);
+        context.add_message_str("Assistant", "Hi".to_string()).unwrap();
+        assert_eq!(context.messages[0].role, Role::Assistant);
+        assert_eq!(serde_json::to_value(&context.messages[0]).unwrap()["role"], "assistant");
+    }
+    
+    #[test]
+    fn test_empty_role_is_rejected() {
+        assert!(Role::parse("").is_err());
+        assert!(Role::parse("   ").is_err());
+        
+        let mut context = Context::new(None);
+        assert!(context.add_message_str(" ", "Hi".to_string()).is_err());
+        assert!(context.messages.is_empty());
+        
+        // Stored data with an empty role fails to load rather than passing it on
+        let stored = r#"{"role":"","content":"Hi","timestamp":0}"#;
+        assert!(serde_json::from_str::<rust_core::context::Message>(stored).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_other_role_round_trips_through_storage() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .unwrap();
+        let storage = ContextStorage::from_pool(pool).await.unwrap();
+        
+        let mut context = Context::new(None);
+        context.add_message_str("Reviewer ", "Looks good".to_string()).unwrap();
+        storage.save_context(&mut context).await.unwrap();
+        
+        let loaded = storage.load_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(loaded.messages[0].role, Role::Other("reviewer".to_string()));
+        assert_eq!(loaded.messages[0].role.as_str(), "reviewer");
+    }
+    
+    #[test]
+    fn test_reconcile_skips_messages_already_at_tail() {
+        let mut context = Context::new(None);
+        context.add_message(Role::User, "Hello".to_string());
+        context.add_message(Role::Assistant, "Hi".to_string());
+        let returned = context.messages.clone();
+        
+        // Sending back the full history appends nothing
+        assert_eq!(context.reconcile_messages(returned.clone()), 0);
+        assert_eq!(context.messages.len(), 2);
+        
+        // Only the new tail is appended; unknown timestamps still match
+        let mut incoming: Vec<Message> = returned
+            .into_iter()
+            .map(|m| Message { timestamp: 0, ..m })
+            .collect();
+        incoming.push(Message { role: Role::User, content: "Bye".to_string(), timestamp: 0, pinned: false });
+        assert_eq!(context.reconcile_messages(incoming), 1);
+        assert_eq!(context.messages.len(), 3);
+        assert!(context.messages[2].timestamp > 0);
+        
+        // A repeated message with a different timestamp is new
+        let repeat = Message { timestamp: context.messages[2].timestamp + 5, ..context.messages[2].clone() };
+        assert_eq!(context.reconcile_messages(vec![repeat]), 1);
+        assert_eq!(context.messages.len(), 4);
+    }
+    
+    async fn shared_manager() -> std::sync::Arc<ContextManager> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .unwrap();
+        std::sync::Arc::new(ContextManager::new(ContextStorage::from_pool(pool).await.unwrap()))
+    }
+    
+    #[tokio::test]
+    async fn test_stale_save_is_rejected() {
+        let manager = shared_manager().await;
+        let created = manager.get_or_create_context(None, None).await.unwrap();
+        let id = Some(created.conversation_id.clone());
+        
+        let mut first = manager.get_or_create_context(id.clone(), None).await.unwrap();
+        let mut second = manager.get_or_create_context(id.clone(), None).await.unwrap();
+        
+        second.add_message(Role::User, "from second".to_string());
+        manager.update_context(&mut second).await.unwrap();
+        
+        first.add_message(Role::User, "from first".to_string());
+        match manager.update_context(&mut first).await {
+            Err(OrchestratorError::ConflictDetected(_)) => {}
+            other => panic!("expected ConflictDetected, got {:?}", other),
+        }
+        
+        // Retrying merges the rejected message after the winning one
+        manager.update_context_with_retry(&mut first, 3).await.unwrap();
+        let stored = manager.get_context(&created.conversation_id).await.unwrap().unwrap();
+        let contents: Vec<&str> = stored.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["from second", "from first"]);
+        assert_eq!(stored.version, first.version);
+    }
+    
+    #[tokio::test]
+    async fn test_interleaved_updates_lose_no_messages() {
+        let manager = shared_manager().await;
+        let id = manager.get_or_create_context(None, None).await.unwrap().conversation_id;
+        
+        let workers: Vec<_> = (0..2)
+            .map(|worker| {
+                let manager = manager.clone();
+                let id = id.clone();
+                tokio::spawn(async move {
+                    for turn in 0..10 {
+                        let mut context = manager
+                            .get_or_create_context(Some(id.clone()), None)
+                            .await
+                            .unwrap();
+                        // Let the other worker load the same version
+                        tokio::task::yield_now().await;
+                        context.add_message(Role::User, format!("worker {} turn {}", worker, turn));
+                        manager.update_context_with_retry(&mut context, 10).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.await.unwrap();
+        }
+        
+        let stored = manager.get_context(&id).await.unwrap().unwrap();
+        assert_eq!(stored.messages.len(), 20);
+        for worker in 0..2 {
+            let turns: Vec<&str> = stored
+                .messages
+                .iter()
+                .map(|m| m.content.as_str())
+                .filter(|c| c.starts_with(&format!("worker {} ", worker)))
+                .collect();
+            let expected: Vec<String> = (0..10).map(|turn| format!("worker {} turn {}", worker, turn)).collect();
+            assert_eq!(turns, expected);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_list_contexts_pages_without_gaps() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .unwrap();
+        let storage = ContextStorage::from_pool(pool.clone()).await.unwrap();
+        let mut expected = Vec::new();
+        for i in 0..25 {
+            let mut context = Context::new(Some("proj".to_string()));
+            // Groups of three share a timestamp to exercise the tie-break
+            storage.save_context_at(&mut context, 1_700_000_000 + i / 3).await.unwrap();
+            expected.push(context.conversation_id);
+        }
+        let mut other = Context::new(Some("other".to_string()));
+        storage.save_context_at(&mut other, 1_700_000_000).await.unwrap();
+        
+        let manager = ContextManager::new(ContextStorage::from_pool(pool).await.unwrap());
+        let mut seen = Vec::new();
+        let mut page_sizes = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = manager.list_contexts(Some("proj"), None, 10, cursor).await.unwrap();
+            page_sizes.push(page.items.len());
+            seen.extend(page.items.into_iter().map(|c| c.conversation_id));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(page_sizes, vec![10, 10, 5]);
+        
+        let unique: std::collections::HashSet<&String> = seen.iter().collect();
+        assert_eq!(unique.len(), 25);
+        seen.sort();
+        expected.sort();
+        assert_eq!(seen, expected);
+        
+        // A full final page hands out a cursor that yields an empty page
+        let mut cursor = None;
+        for _ in 0..5 {
+            cursor = manager.list_contexts(Some("proj"), None, 5, cursor).await.unwrap().next_cursor;
+        }
+        let last = manager.list_contexts(Some("proj"), None, 5, cursor).await.unwrap();
+        assert!(last.items.is_empty());
+        assert!(last.next_cursor.is_none());
+        
+        match manager.list_contexts(None, None, 10, Some("zz".to_string())).await {
+            Err(OrchestratorError::InvalidInput(_)) => {}
+            other => panic!("expected InvalidInput, got {:?}", other.map(|p| p.items)),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_summarize_archive_and_restore() {
+        let manager = shared_manager().await;
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        for i in 0..60 {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            context.add_message(role, format!("message {} about the config loader", i));
+        }
+        manager.update_context(&mut context).await.unwrap();
+        let original = context.messages.clone();
+        
+        let summarizer = ContextSummarizer::new(50, 0.8);
+        let summary_id = manager
+            .summarize_and_archive(&mut context, &summarizer)
+            .await
+            .unwrap()
+            .expect("context is over the threshold");
+        assert_eq!(context.messages.len(), 13);
+        assert_eq!(context.messages[0].role, Role::System);
+        
+        let stored = manager.get_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(stored.messages, context.messages);
+        
+        let archive = manager.get_archived_messages(&context.conversation_id, None).await.unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive[0].summary_id, summary_id);
+        assert_eq!(archive[0].messages, original[..48].to_vec());
+        assert_eq!(archive[0].summary_message, context.messages[0]);
+        
+        let restored = manager.restore_from_archive(&context.conversation_id, summary_id).await.unwrap();
+        assert_eq!(restored.messages, original);
+        let stored = manager.get_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(stored.messages, original);
+        
+        // The summary message is gone, so the archive cannot be spliced in twice
+        match manager.restore_from_archive(&context.conversation_id, summary_id).await {
+            Err(OrchestratorError::InvalidInput(_)) => {}
+            other => panic!("expected InvalidInput, got {:?}", other.map(|c| c.messages.len())),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_short_context_is_not_archived() {
+        let manager = shared_manager().await;
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        context.add_message(Role::User, "hello".to_string());
+        
+        let summarizer = ContextSummarizer::new(50, 0.8);
+        assert_eq!(manager.summarize_and_archive(&mut context, &summarizer).await.unwrap(), None);
+        assert!(manager.get_archived_messages(&context.conversation_id, None).await.unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_title_skips_code_and_truncates_at_word() {
+        let message = "```rust\nfn main() {\n    let config = load();\n}\n```\n\
+            Why does this panic when the configuration file is missing from the working directory?";
+        let title = title_from_message(message).unwrap();
+        assert_eq!(title, "Why does this panic when the configuration file is missing…");
+        assert!(title.chars().count() <= 60);
+        
+        // Nothing but code falls back to the first line of it
+        let code_only = "```python\n\nimport os\nprint(os.getcwd())\n```";
+        assert_eq!(title_from_message(code_only).as_deref(), Some("import os"));
+        
+        // A single long word is cut mid-word
+        let title = title_from_message(&"x".repeat(80)).unwrap();
+        assert_eq!(title.chars().count(), 60);
+        
+        assert_eq!(title_from_message("  short   question\n"), Some("short question".to_string()));
+        assert_eq!(title_from_message("   "), None);
+    }
+    
+    #[tokio::test]
+    async fn test_user_set_title_is_never_regenerated() {
+        let manager = shared_manager().await;
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        context.add_message(Role::Assistant, "How can I help?".to_string());
+        context.add_message(Role::User, "Fix the flaky retry test".to_string());
+        manager.update_context(&mut context).await.unwrap();
+        let id = context.conversation_id.clone();
+        
+        assert_eq!(manager.generate_title(&id).await.unwrap().as_deref(), Some("Fix the flaky retry test"));
+        
+        manager.set_title(&id, "  Retry cleanup ").await.unwrap();
+        assert_eq!(manager.generate_title(&id).await.unwrap().as_deref(), Some("Retry cleanup"));
+        
+        let page = manager.list_contexts(None, None, 10, None).await.unwrap();
+        assert_eq!(page.items[0].title.as_deref(), Some("Retry cleanup"));
+        assert!(matches!(manager.set_title(&id, " ").await, Err(OrchestratorError::InvalidInput(_))));
+    }
+    
+    async fn names_with_labels(
+        manager: &ContextManager,
+        ids: &std::collections::HashMap<String, &str>,
+        labels: &[(&str, &str)],
+    ) -> Vec<String> {
+        let labels: std::collections::HashMap<String, String> =
+            labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let page = manager.list_contexts(None, Some(&labels), 10, None).await.unwrap();
+        let mut names: Vec<String> = page.items.iter().map(|c| ids[&c.conversation_id].to_string()).collect();
+        names.sort();
+        names
+    }
+    
+    #[tokio::test]
+    async fn test_list_contexts_filters_by_labels() {
+        let manager = shared_manager().await;
+        let mut ids = std::collections::HashMap::new();
+        for (name, team, env) in [("a", "search", "prod"), ("b", "search", "dev"), ("c", "billing", "prod")] {
+            let mut context = manager.get_or_create_context(None, None).await.unwrap();
+            context.labels.insert("team".to_string(), team.to_string());
+            context.labels.insert("env".to_string(), env.to_string());
+            manager.update_context(&mut context).await.unwrap();
+            ids.insert(context.conversation_id, name);
+        }
+        
+        assert_eq!(names_with_labels(&manager, &ids, &[("team", "search")]).await, vec!["a", "b"]);
+        assert_eq!(names_with_labels(&manager, &ids, &[("team", "search"), ("env", "prod")]).await, vec!["a"]);
+        assert!(names_with_labels(&manager, &ids, &[("env", "staging")]).await.is_empty());
+        assert_eq!(names_with_labels(&manager, &ids, &[]).await, vec!["a", "b", "c"]);
+        
+        let page = manager.list_contexts(None, None, 10, None).await.unwrap();
+        assert!(page.items.iter().all(|c| c.labels.len() == 2));
+        
+        // Invalid labels are rejected on save
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        for i in 0..100 {
+            context.labels.insert(format!("key{}", i), "v".to_string());
+        }
+        assert!(matches!(manager.update_context(&mut context).await, Err(OrchestratorError::InvalidInput(_))));
+    }
 }
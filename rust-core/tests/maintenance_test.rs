@@ -0,0 +1,130 @@
+/// Tests for database maintenance
+
+#[cfg(test)]
+mod tests {
+    use rust_core::storage::{maintain, MaintenanceOptions, VacuumMode};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+    use std::path::PathBuf;
+
+    fn temp_db() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uai-maintenance-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("db.sqlite")
+    }
+
+    async fn open_pool(path: &PathBuf, max_connections: u32) -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true))
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    /// Fill a table with ~2MB of rows, then delete them all
+    async fn grow_and_prune(pool: &SqlitePool) {
+        sqlx::query("CREATE TABLE payloads (id INTEGER PRIMARY KEY, data BLOB NOT NULL)")
+            .execute(pool)
+            .await
+            .unwrap();
+        for _ in 0..2000 {
+            sqlx::query("INSERT INTO payloads (data) VALUES (randomblob(1024))")
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("DELETE FROM payloads").execute(pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_full_vacuum_shrinks_database_after_pruning() {
+        let path = temp_db();
+        let pool = open_pool(&path, 1).await;
+        grow_and_prune(&pool).await;
+
+        let options = MaintenanceOptions::default().with_vacuum(VacuumMode::Full);
+        let report = maintain(&pool, &options).await.unwrap();
+
+        assert!(report.size_before > 2_000_000);
+        assert!(report.freelist_before > 0);
+        assert!(report.size_after < report.size_before / 10);
+        assert_eq!(report.freelist_after, 0);
+        assert!(report.bytes_reclaimed() > 0);
+        assert!(report.analyzed);
+        assert!(std::fs::metadata(&path).unwrap().len() < report.size_before);
+
+        pool.close().await;
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[tokio::test]
+    async fn test_incremental_vacuum_needs_incremental_auto_vacuum() {
+        let path = temp_db();
+        let pool = open_pool(&path, 1).await;
+        // Must be set before the first table is created
+        sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&pool).await.unwrap();
+        grow_and_prune(&pool).await;
+
+        let report = maintain(&pool, &MaintenanceOptions::default()).await.unwrap();
+        assert_eq!(report.vacuum, VacuumMode::Incremental);
+        assert!(report.size_after < report.size_before / 10);
+        assert_eq!(report.freelist_after, 0);
+
+        pool.close().await;
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check_passes_through() {
+        let path = temp_db();
+        let pool = open_pool(&path, 1).await;
+        grow_and_prune(&pool).await;
+
+        let report = maintain(&pool, &MaintenanceOptions::default()).await.unwrap();
+        assert_eq!(report.integrity, Some(vec!["ok".to_string()]));
+        assert!(report.integrity_ok());
+        // Rollback journal, so there is no WAL to checkpoint
+        assert_eq!(report.wal_frames_checkpointed, None);
+
+        let options = MaintenanceOptions::default()
+            .with_vacuum(VacuumMode::None)
+            .with_integrity_check(false);
+        let report = maintain(&pool, &options).await.unwrap();
+        assert_eq!(report.integrity, None);
+        assert_eq!(report.size_after, report.size_before);
+
+        pool.close().await;
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[tokio::test]
+    async fn test_full_vacuum_refused_while_connections_in_use() {
+        let path = temp_db();
+        let pool = open_pool(&path, 2).await;
+        grow_and_prune(&pool).await;
+
+        let mut writer = pool.begin().await.unwrap();
+        sqlx::query("INSERT INTO payloads (data) VALUES (x'00')")
+            .execute(&mut *writer)
+            .await
+            .unwrap();
+
+        let options = MaintenanceOptions::default().with_vacuum(VacuumMode::Full);
+        let err = maintain(&pool, &options).await.unwrap_err();
+        assert!(matches!(err, OrchestratorError::ConflictDetected(_)));
+
+        writer.commit().await.unwrap();
+        let report = maintain(&pool, &options).await.unwrap();
+        assert!(report.bytes_reclaimed() > 0);
+
+        pool.close().await;
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_vacuum_mode_parse() {
+        assert_eq!(VacuumMode::parse(" Full ").unwrap(), VacuumMode::Full);
+        assert_eq!(VacuumMode::parse("none").unwrap().as_str(), "none");
+        assert!(matches!(VacuumMode::parse("aggressive"), Err(OrchestratorError::InvalidInput(_))));
+    }
+}
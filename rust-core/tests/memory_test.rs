@@ -0,0 +1,140 @@
+/// Tests for project memories
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::memory::MEMORY_HEADER;
+    use rust_core::context::token_counter::TokenCounter;
+    use rust_core::context::{Context, ContextManager, InMemoryContextStore, Memory, MemoryStore, MockClock, Role};
+    use rust_core::migrations::{register_migrations, MigrationRunner};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::sync::Arc;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+
+        pool
+    }
+
+    async fn store_with(memories: &[Memory]) -> MemoryStore {
+        let store = MemoryStore::from_pool(create_test_pool().await).await.unwrap();
+        for memory in memories {
+            store.upsert(memory).await.unwrap();
+        }
+        store
+    }
+
+    fn keys(memories: &[Memory]) -> Vec<&str> {
+        memories.iter().map(|m| m.key.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_upsert_get_list_delete_and_search() {
+        let store = store_with(&[
+            Memory::new("proj", "db", "Uses SQLite through sqlx").with_importance(5),
+            Memory::new("proj", "style", "Blank lines inside blocks are indented").with_importance(1),
+            Memory::new("other", "db", "Uses Postgres"),
+        ])
+        .await;
+
+        let mut replaced = Memory::new("proj", "style", "Four-space indentation").with_importance(9);
+        replaced.created_at = NOW;
+        store.upsert(&replaced).await.unwrap();
+        let style = store.get("proj", "style").await.unwrap().unwrap();
+        assert_eq!(style.content, "Four-space indentation");
+        assert_eq!(style.importance, 9);
+        assert_ne!(style.created_at, NOW, "a replaced memory keeps its creation time");
+
+        assert_eq!(keys(&store.list("proj").await.unwrap()), vec!["style", "db"]);
+        assert_eq!(keys(&store.search("proj", "sqlite SQLX", 10).await.unwrap()), vec!["db"]);
+        assert_eq!(keys(&store.search("proj", "db sqlite", 10).await.unwrap()), vec!["db"]);
+        assert!(store.search("proj", "postgres", 10).await.unwrap().is_empty());
+        assert_eq!(store.search("proj", "", 1).await.unwrap().len(), 1);
+
+        assert!(store.delete("proj", "db").await.unwrap());
+        assert!(!store.delete("proj", "db").await.unwrap());
+        assert!(store.get("proj", "db").await.unwrap().is_none());
+        assert!(store.get("other", "db").await.unwrap().is_some());
+
+        let empty = store.upsert(&Memory::new("proj", " ", "content")).await;
+        assert!(matches!(empty, Err(OrchestratorError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_expired_memories_are_hidden_and_purged() {
+        let clock = Arc::new(MockClock::new(NOW));
+        let store = store_with(&[
+            Memory::new("proj", "sprint", "Sprint ends Friday").with_importance(10).with_expires_at(NOW + 60),
+            Memory::new("proj", "lang", "Written in Rust"),
+        ])
+        .await
+        .with_clock(clock.clone());
+
+        assert_eq!(keys(&store.list("proj").await.unwrap()), vec!["sprint", "lang"]);
+        assert_eq!(store.purge_expired().await.unwrap(), 0);
+
+        clock.set(NOW + 60);
+        assert!(store.get("proj", "sprint").await.unwrap().is_none());
+        assert_eq!(keys(&store.list("proj").await.unwrap()), vec!["lang"]);
+        assert!(store.search("proj", "sprint", 10).await.unwrap().is_empty());
+
+        let manager = ContextManager::new(InMemoryContextStore::new()).with_memory_store(store.clone());
+        let mut context = Context::new(Some("proj".to_string()));
+        assert_eq!(manager.inject_memories(&mut context, "proj", 1000).await.unwrap(), 1);
+        assert!(!context.messages[0].content.contains("Sprint"));
+
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        clock.set(NOW);
+        assert!(store.get("proj", "sprint").await.unwrap().is_none(), "purged memories are gone");
+    }
+
+    #[tokio::test]
+    async fn test_inject_memories_by_importance_within_budget() {
+        let store = store_with(&[
+            Memory::new("proj", "low", "Prefer small pull requests").with_importance(1),
+            Memory::new("proj", "high", "Never commit secrets to the repo").with_importance(10),
+            Memory::new("proj", "mid", "Run the linters before pushing").with_importance(5),
+        ])
+        .await;
+        let manager = ContextManager::new(InMemoryContextStore::new()).with_memory_store(store);
+
+        // Room for the two most important; the third does not fit
+        let two = format!(
+            "{}\n- Never commit secrets to the repo\n- Run the linters before pushing",
+            MEMORY_HEADER
+        );
+        let budget = TokenCounter::new().estimate_tokens(&two);
+        let mut context = Context::new(Some("proj".to_string()));
+        context.add_message(Role::User, "Hello".to_string());
+        assert_eq!(manager.inject_memories(&mut context, "proj", budget).await.unwrap(), 2);
+        assert_eq!(context.messages.len(), 2);
+        assert_eq!(context.messages[0].role, Role::System);
+        assert_eq!(context.messages[0].content, two);
+        assert_eq!(context.messages[1].content, "Hello");
+
+        // Those already injected are skipped, leaving the least important
+        assert_eq!(manager.inject_memories(&mut context, "proj", budget).await.unwrap(), 1);
+        assert_eq!(context.messages[0].content, format!("{}\n- Prefer small pull requests", MEMORY_HEADER));
+        assert_eq!(manager.inject_memories(&mut context, "proj", budget).await.unwrap(), 0);
+        assert_eq!(context.messages.len(), 3);
+
+        // A budget too small for the most important memory injects nothing
+        let mut fresh = Context::new(Some("proj".to_string()));
+        assert_eq!(manager.inject_memories(&mut fresh, "proj", 5).await.unwrap(), 0);
+        assert!(fresh.messages.is_empty());
+
+        let unconfigured = ContextManager::new(InMemoryContextStore::new());
+        let result = unconfigured.inject_memories(&mut fresh, "proj", budget).await;
+        assert!(matches!(result, Err(OrchestratorError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,148 @@
+/// Tests for migrating several databases together
+
+#[cfg(test)]
+mod tests {
+    use rust_core::migrations::{Migration, MigrationRunner, MigrationSet};
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::collections::HashMap;
+
+    async fn create_test_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    /// Migration `version` creates table `{prefix}_{version}`, or fails if `broken`
+    fn table_migration(prefix: &'static str, version: u32, broken: bool) -> Migration {
+        Migration::new(
+            version,
+            format!("create_{}_{}", prefix, version),
+            move |pool| {
+                Box::pin(async move {
+                    let sql = if broken {
+                        "CREATE TABLE broken (".to_string()
+                    } else {
+                        format!("CREATE TABLE {}_{} (id INTEGER PRIMARY KEY)", prefix, version)
+                    };
+                    sqlx::query(&sql).execute(pool).await?;
+                    Ok(())
+                })
+            },
+            move |pool| {
+                Box::pin(async move {
+                    sqlx::query(&format!("DROP TABLE IF EXISTS {}_{}", prefix, version))
+                        .execute(pool)
+                        .await?;
+                    Ok(())
+                })
+            },
+        )
+    }
+
+    /// A runner with `count` migrations; the one numbered `broken_at` fails
+    fn runner(pool: &SqlitePool, prefix: &'static str, count: u32, broken_at: Option<u32>) -> MigrationRunner {
+        let mut runner = MigrationRunner::new(pool.clone());
+        for version in 1..=count {
+            runner.add_migration(table_migration(prefix, version, broken_at == Some(version)));
+        }
+        runner
+    }
+
+    async fn tables(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name != 'schema_migrations' ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn pools() -> (SqlitePool, SqlitePool, SqlitePool) {
+        (create_test_pool().await, create_test_pool().await, create_test_pool().await)
+    }
+
+    #[tokio::test]
+    async fn test_migrate_all_with_per_database_targets() {
+        let (contexts, costs, index) = pools().await;
+        let mut set = MigrationSet::new();
+        set.add_database("contexts", runner(&contexts, "ctx", 3, None));
+        set.add_database("costs", runner(&costs, "cost", 2, None));
+        set.add_database("index", runner(&index, "idx", 3, None));
+
+        let targets = HashMap::from([("contexts".to_string(), 1), ("index".to_string(), 2)]);
+        let report = set.migrate_all(Some(targets)).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.get("contexts").unwrap().to_version, Some(1));
+        assert_eq!(report.get("costs").unwrap().to_version, Some(2));
+        assert_eq!(report.get("index").unwrap().to_version, Some(2));
+        assert_eq!(tables(&contexts).await, vec!["ctx_1"]);
+        assert_eq!(tables(&index).await, vec!["idx_1", "idx_2"]);
+
+        // A second run without targets brings everything to latest
+        set.migrate_all(None).await.unwrap();
+        let status = set.status_all().await.unwrap();
+        let versions: Vec<(&str, Option<u32>)> = status.iter().map(|s| (s.name.as_str(), s.current_version)).collect();
+        assert_eq!(versions, vec![("contexts", Some(3)), ("costs", Some(2)), ("index", Some(3))]);
+        assert!(status.iter().all(|s| s.migrations.iter().all(|(_, _, applied)| *applied)));
+
+        let unknown = HashMap::from([("logs".to_string(), 1)]);
+        assert!(set.migrate_all(Some(unknown)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failure_stops_batch_without_rollback_by_default() {
+        let (contexts, costs, index) = pools().await;
+        let mut set = MigrationSet::new();
+        set.add_database("contexts", runner(&contexts, "ctx", 2, None));
+        set.add_database("costs", runner(&costs, "cost", 3, Some(2)));
+        set.add_database("index", runner(&index, "idx", 1, None));
+
+        let report = set.migrate_all(None).await.unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.databases.len(), 2);
+        assert_eq!(report.get("contexts").unwrap().to_version, Some(2));
+        let costs_report = report.get("costs").unwrap();
+        assert!(costs_report.error.as_deref().unwrap().contains("create_cost_2"));
+        assert_eq!(costs_report.to_version, Some(1));
+        assert!(!costs_report.rolled_back);
+        assert!(report.get("index").is_none());
+
+        assert_eq!(tables(&contexts).await, vec!["ctx_1", "ctx_2"]);
+        assert!(tables(&index).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_all_or_nothing_rolls_back_earlier_databases() {
+        let (contexts, costs, index) = pools().await;
+        let mut set = MigrationSet::new().with_all_or_nothing(true);
+        set.add_database("contexts", runner(&contexts, "ctx", 2, None));
+        set.add_database("costs", runner(&costs, "cost", 1, None));
+        set.add_database("index", runner(&index, "idx", 3, Some(3)));
+
+        // Bring contexts to version 1 first; the batch should only undo what it applied
+        let targets = HashMap::from([("contexts".to_string(), 1), ("costs".to_string(), 0), ("index".to_string(), 0)]);
+        set.migrate_all(Some(targets)).await.unwrap();
+        let before = set.status_all().await.unwrap();
+
+        let report = set.migrate_all(None).await.unwrap();
+        assert!(!report.is_ok());
+        let summary: Vec<(&str, bool, Option<u32>)> = report
+            .databases
+            .iter()
+            .map(|db| (db.name.as_str(), db.rolled_back, db.to_version))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("contexts", true, Some(1)), ("costs", true, None), ("index", true, None)]
+        );
+        assert!(report.databases.iter().all(|db| db.rollback_error.is_none()));
+        assert!(report.get("index").unwrap().error.is_some());
+
+        assert_eq!(tables(&contexts).await, vec!["ctx_1"]);
+        assert!(tables(&costs).await.is_empty());
+        assert!(tables(&index).await.is_empty());
+        assert_eq!(set.status_all().await.unwrap(), before);
+    }
+}
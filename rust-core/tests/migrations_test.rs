@@ -2,8 +2,15 @@
 
 #[cfg(test)]
 mod tests {
-    use rust_core::migrations::{MigrationRunner, Migration, register_migrations};
+    use chrono::{TimeZone, Utc};
+    use rust_core::cost::storage::{CostRecord, CostStorage};
+    use rust_core::migrations::{
+        data, expected_schema_for, register_data_migration, register_migrations, snapshot_schema, Migration, MigrationRunner,
+        SchemaObject,
+    };
     use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::collections::HashMap;
+    use std::sync::Arc;
     use std::time::Duration;
 
     async fn create_test_pool() -> SqlitePool {
@@ -114,21 +121,21 @@ mod tests {
         let mut runner = MigrationRunner::new(pool);
         
         // Add invalid migration
-        runner.add_migration(Migration {
-            version: 999,
-            name: "invalid".to_string(),
-            up: Box::new(|_pool| {
+        runner.add_migration(Migration::new(
+            999,
+            "invalid",
+            |_pool| {
                 Box::pin(async move {
                     sqlx::query("INVALID SQL SYNTAX!!!").execute(_pool).await?;
                     Ok(())
                 })
-            }),
-            down: Box::new(|_pool| {
+            },
+            |_pool| {
                 Box::pin(async move {
                     Ok(())
                 })
-            }),
-        });
+            },
+        ));
         
         // Should fail
         let result = runner.migrate_up(None).await;
@@ -165,4 +172,259 @@ mod tests {
             assert_eq!(current_version, Some(i as u32));
         }
     }
+
+    #[tokio::test]
+    async fn test_closure_migration_writes_captured_value() {
+        let pool = create_test_pool().await;
+        let mut runner = MigrationRunner::new(pool.clone());
+        let greeting: Arc<str> = Arc::from(format!("hello from {}", "config"));
+        runner.add_migration(Migration::new(
+            1,
+            "seed_settings",
+            move |pool| {
+                let greeting = greeting.clone();
+                Box::pin(async move {
+                    sqlx::query("CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+                        .execute(pool)
+                        .await?;
+                    sqlx::query("INSERT INTO settings (key, value) VALUES ('greeting', ?1)")
+                        .bind(greeting.as_ref())
+                        .execute(pool)
+                        .await?;
+                    Ok(())
+                })
+            },
+            |pool| {
+                Box::pin(async move {
+                    sqlx::query("DROP TABLE settings").execute(pool).await?;
+                    Ok(())
+                })
+            },
+        ));
+
+        runner.migrate_up(None).await.expect("Migration should succeed");
+        let (value,): (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'greeting'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(value, "hello from config");
+    }
+
+    #[tokio::test]
+    async fn test_data_migrations_are_tracked_by_name() {
+        let pool = create_test_pool().await;
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+        let schema_version = runner.get_current_version().await.unwrap().unwrap();
+
+        sqlx::query("INSERT INTO indexed_files (project_id, file_path, language, file_hash) VALUES ('proj', '/home/dev/app/src/main.rs', 'rust', 'h')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO cost_records (tool, model, input_tokens, output_tokens, cost_usd, timestamp) VALUES ('gpt', 'gpt-4', 1, 1, 0.1, 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        register_data_migration(&mut runner, data::backfill_cost_project("backfill_default_project", "default"));
+        register_data_migration(
+            &mut runner,
+            data::rewrite_index_paths("relative_app_paths", "proj", "/home/dev/app/", ""),
+        );
+        runner.migrate_up(None).await.expect("Data migrations should succeed");
+
+        // They take no schema versions, so a later schema migration still fits
+        assert_eq!(runner.get_current_version().await.unwrap(), Some(schema_version));
+        let applied = runner.get_applied_data_migrations().await.unwrap();
+        assert!(applied.contains("backfill_default_project") && applied.contains("relative_app_paths"));
+        runner.add_migration(Migration::new(
+            schema_version + 1,
+            "noop",
+            |_pool| Box::pin(async { Ok(()) }),
+            |_pool| Box::pin(async { Ok(()) }),
+        ));
+        runner.validate_migrations().unwrap();
+        runner.migrate_up(None).await.unwrap();
+        assert_eq!(runner.get_current_version().await.unwrap(), Some(schema_version + 1));
+
+        let (project_id,): (String,) = sqlx::query_as("SELECT project_id FROM cost_records")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(project_id, "default");
+        let paths = || {
+            sqlx::query_scalar::<_, String>("SELECT file_path FROM indexed_files ORDER BY id").fetch_all(&pool)
+        };
+        assert_eq!(paths().await.unwrap(), vec!["src/main.rs"]);
+
+        // Rolling back restores the rewritten path and leaves files indexed since alone
+        sqlx::query("INSERT INTO indexed_files (project_id, file_path, language, file_hash) VALUES ('proj', 'src/lib.rs', 'rust', 'h')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        runner.rollback_data_migrations().await.unwrap();
+        assert_eq!(paths().await.unwrap(), vec!["/home/dev/app/src/main.rs", "src/lib.rs"]);
+        assert!(runner.get_applied_data_migrations().await.unwrap().is_empty());
+        assert!(runner.check_drift().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drift_reports_dropped_index() {
+        let pool = create_test_pool().await;
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+        assert!(runner.check_drift().await.unwrap().is_empty());
+
+        sqlx::query("DROP INDEX idx_sessions_user_id").execute(&pool).await.unwrap();
+        let diff = runner.check_drift().await.unwrap();
+        assert_eq!(diff.missing, vec![SchemaObject::Index("idx_sessions_user_id".to_string())]);
+        assert!(diff.added.is_empty());
+        assert!(diff.mismatched.is_empty());
+        assert!(diff.to_string().contains("missing index idx_sessions_user_id"));
+    }
+
+    #[tokio::test]
+    async fn test_drift_reports_hand_edited_columns_and_tables() {
+        let pool = create_test_pool().await;
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(Some(4)).await.expect("Migration should succeed");
+
+        sqlx::query("ALTER TABLE users ADD COLUMN nickname TEXT").execute(&pool).await.unwrap();
+        sqlx::query("CREATE TABLE scratch_notes (id INTEGER)").execute(&pool).await.unwrap();
+        let diff = runner.check_drift().await.unwrap();
+        assert_eq!(
+            diff.added,
+            vec![
+                SchemaObject::Column { table: "users".to_string(), column: "nickname".to_string() },
+                SchemaObject::Table("scratch_notes".to_string()),
+            ]
+        );
+        assert!(diff.missing.is_empty());
+
+        // The expected schema follows the applied version, not the latest
+        let expected = expected_schema_for(4).await.unwrap();
+        assert!(expected.tables.contains_key("users"));
+        assert!(!expected.tables.contains_key("responses"));
+        let actual = snapshot_schema(&pool).await.unwrap();
+        assert_eq!(actual.indexes, expected.indexes);
+    }
+
+    async fn migrate(pool: &SqlitePool) {
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+    }
+
+    /// Record a cost through CostStorage and check the totals include `legacy_usd`
+    async fn assert_cost_storage_works(pool: &SqlitePool, legacy_usd: f64) {
+        let storage = CostStorage::from_pool(pool.clone()).await.unwrap();
+        let record = CostRecord {
+            id: None,
+            request_id: Some("req-new".to_string()),
+            tool: "claude".to_string(),
+            model: "claude-3-haiku".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd: 0.5,
+            timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            user_id: None,
+            project_id: Some("proj".to_string()),
+            conversation_id: Some("conv-1".to_string()),
+            labels: HashMap::new(),
+        };
+        storage.record_cost(&record).await.unwrap();
+
+        let all_time = (Utc.timestamp_opt(0, 0).unwrap(), Utc.timestamp_opt(4_000_000_000, 0).unwrap());
+        let total = storage.get_total_cost(all_time.0, all_time.1, None, None, None).await.unwrap();
+        assert!((total - (0.5 + legacy_usd)).abs() < 1e-9, "total was {}", total);
+        let project = storage.get_total_cost(all_time.0, all_time.1, None, Some("proj"), None).await.unwrap();
+        assert!((project - (0.5 + legacy_usd)).abs() < 1e-9);
+
+        let page = storage.list_records(None, 10, None).await.unwrap();
+        assert_eq!(page.items[0].request_id.as_deref(), Some("req-new"));
+        assert_eq!(page.items[0].conversation_id.as_deref(), Some("conv-1"));
+    }
+
+    #[tokio::test]
+    async fn test_cost_records_reconciled_from_migration_shape() {
+        let pool = create_test_pool().await;
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(Some(2)).await.expect("Migration should succeed");
+        sqlx::query(
+            "INSERT INTO cost_records (request_id, tool, model, tokens_input, tokens_output, cost_usd, project_id, created_at)
+             VALUES ('req-old', 'gpt', NULL, 10, 20, 0.25, 'proj', '2023-11-14 22:13:20')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        runner.migrate_up(None).await.expect("Migration should succeed");
+        let (request_id, model, input, output, timestamp): (Option<String>, String, i64, i64, i64) = sqlx::query_as(
+            "SELECT request_id, model, input_tokens, output_tokens, timestamp FROM cost_records",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(request_id.as_deref(), Some("req-old"));
+        assert_eq!((model.as_str(), input, output, timestamp), ("", 10, 20, 1_700_000_000));
+
+        assert_cost_storage_works(&pool, 0.25).await;
+    }
+
+    #[tokio::test]
+    async fn test_cost_records_reconciled_from_cost_storage_shape() {
+        // The table CostStorage created before it had labels or request_id
+        let pool = create_test_pool().await;
+        sqlx::query(
+            "CREATE TABLE cost_records (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tool TEXT NOT NULL,
+                model TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL,
+                output_tokens INTEGER NOT NULL,
+                cost_usd REAL NOT NULL,
+                timestamp INTEGER NOT NULL,
+                user_id TEXT,
+                project_id TEXT,
+                conversation_id TEXT
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO cost_records (tool, model, input_tokens, output_tokens, cost_usd, timestamp, project_id, conversation_id)
+             VALUES ('claude', 'claude-3-opus', 5, 6, 1.5, 1600000000, 'proj', 'conv-0')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        migrate(&pool).await;
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('cost_records')")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert!(columns.contains(&"request_id".to_string()));
+        assert!(columns.contains(&"labels".to_string()));
+
+        assert_cost_storage_works(&pool, 1.5).await;
+    }
+
+    #[tokio::test]
+    async fn test_cost_records_reconciliation_on_fresh_database() {
+        let pool = create_test_pool().await;
+        migrate(&pool).await;
+        assert_cost_storage_works(&pool, 0.0).await;
+
+        // CostStorage created first, then the migrations
+        let pool = create_test_pool().await;
+        CostStorage::from_pool(pool.clone()).await.unwrap();
+        migrate(&pool).await;
+        assert_cost_storage_works(&pool, 0.0).await;
+    }
 }
@@ -0,0 +1,162 @@
+/// Tests for model aliases across pricing, token counting and routing
+
+#[cfg(test)]
+mod tests {
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::context::token_counter::TokenCounter;
+    use rust_core::cost::PricingTable;
+    use rust_core::models::{ModelCatalog, ModelEntry};
+    use rust_core::router::{Router, RoutingRequest};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Level, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    const HAIKU: &str = "claude-3-haiku-20240307";
+
+    const MODELS_CONFIG: &str = r#"
+[routing]
+default_tool = "claude"
+code_editing = ["smart", "cheap"]
+
+[models.smart]
+model = "claude-3-5-sonnet-20241022"
+tool = "claude"
+
+[models.cheap]
+model = "gpt-3.5-turbo"
+tool = "gpt"
+context_window = 4096
+"#;
+
+    /// Collects the fields of every warn event as "name=value" strings
+    #[derive(Clone, Default)]
+    struct WarnCapture {
+        events: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    struct FieldVisitor(Vec<String>);
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for WarnCapture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            if *event.metadata().level() == Level::WARN {
+                let mut visitor = FieldVisitor(Vec::new());
+                event.record(&mut visitor);
+                self.events.lock().unwrap().push(visitor.0);
+            }
+        }
+    }
+
+    fn catalog() -> ModelCatalog {
+        ModelCatalog::new()
+            .with_alias("fast", ModelEntry::new(HAIKU, "claude"))
+            .with_alias(
+                "huge",
+                ModelEntry::new("acme-1m", "acme").with_context_window(1_000_000).with_pricing("gpt-4"),
+            )
+    }
+
+    fn request(message: &str, explicit_tool: Option<&str>) -> RoutingRequest {
+        RoutingRequest {
+            message: message.to_string(),
+            conversation_id: None,
+            project_id: None,
+            explicit_tool: explicit_tool.map(str::to_string),
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn test_alias_and_canonical_name_resolve_alike() {
+        let catalog = catalog();
+        assert_eq!(catalog.resolve("fast"), HAIKU);
+        assert_eq!(catalog.resolve("claude"), "claude");
+        assert_eq!(catalog.lookup(HAIKU), catalog.lookup("fast"));
+        assert!(catalog.lookup("gpt-4").is_none());
+
+        let pricing = PricingTable::new().with_catalog(catalog.clone());
+        assert_eq!(pricing.get_pricing("claude", "fast").unwrap().input_price_per_1m, 0.25);
+        assert_eq!(pricing.get_pricing("claude", HAIKU).unwrap().input_price_per_1m, 0.25);
+        assert_eq!(pricing.get_pricing("acme", "huge").unwrap().input_price_per_1m, 30.0);
+        assert_eq!(pricing.get_pricing("acme", "acme-1m").unwrap().input_price_per_1m, 30.0);
+        assert!(PricingTable::new().get_pricing("claude", "fast").is_none());
+
+        let counter = TokenCounter::new().with_catalog(catalog.clone());
+        assert_eq!(counter.get_context_window("fast"), 200_000);
+        assert_eq!(counter.get_context_window(HAIKU), 200_000);
+        assert_eq!(counter.get_context_window("huge"), 1_000_000);
+        assert_eq!(counter.get_context_window("acme-1m"), 1_000_000);
+
+        let mut rules = HashMap::new();
+        rules.insert("code_editing".to_string(), vec!["fast".to_string(), "gpt".to_string()]);
+        let router = Router::new(rules, "gpt".to_string()).with_model_catalog(catalog);
+        let decision = router.route(&request("Fix the bug in this function", None));
+        assert_eq!(decision.selected_tools, vec!["claude", "gpt"]);
+        assert_eq!(decision.selected_models, vec![Some(HAIKU.to_string()), None]);
+
+        for name in ["fast", HAIKU] {
+            let explicit = router.route(&request("hello", Some(name)));
+            assert_eq!(explicit.selected_tools, vec!["claude"]);
+            assert_eq!(explicit.selected_models, vec![Some(HAIKU.to_string())]);
+        }
+        let plain = router.route(&request("hello", None));
+        assert_eq!((plain.selected_tools, plain.selected_models), (vec!["gpt".to_string()], vec![None]));
+    }
+
+    #[test]
+    fn test_config_catalog_is_shared_by_every_builder() {
+        let config = OrchestratorConfig::from_toml_str(MODELS_CONFIG).unwrap();
+        config.validate().unwrap();
+        assert!(config.model_warnings().is_empty());
+
+        let sonnet = config.build_pricing_table().get_pricing("claude", "smart").unwrap().output_price_per_1m;
+        assert_eq!(sonnet, 15.0);
+        assert_eq!(config.build_token_counter().get_context_window("cheap"), 4096);
+        assert_eq!(config.build_token_counter().get_context_window("gpt-3.5-turbo"), 4096);
+        assert_eq!(config.build_window_manager().context_window("smart"), 200_000);
+
+        let decision = config.build_router().route(&request("Refactor this function", None));
+        assert_eq!(decision.selected_tools, vec!["claude", "gpt"]);
+        assert_eq!(
+            decision.selected_models,
+            vec![Some("claude-3-5-sonnet-20241022".to_string()), Some("gpt-3.5-turbo".to_string())]
+        );
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../config/default.toml");
+        let defaults = OrchestratorConfig::from_toml_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(defaults.build_model_catalog().resolve("fast"), HAIKU);
+        assert!(defaults.model_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_unpriced_alias_is_a_warning_and_empty_fields_are_errors() {
+        let capture = WarnCapture::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let config = OrchestratorConfig::from_toml_str(
+            "[models.local]\nmodel = \"llama-3.1-8b\"\ntool = \"ollama\"\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let warning = "models.local: no pricing for 'llama-3.1-8b'; its cost will be recorded as 0";
+        assert_eq!(config.model_warnings(), vec![warning]);
+        let events = capture.events.lock().unwrap().clone();
+        assert_eq!(events, vec![vec![format!("message={}", warning)]]);
+
+        let invalid = OrchestratorConfig::from_toml_str(
+            "[models.broken]\nmodel = \"\"\ntool = \"claude\"\ncontext_window = 0\n",
+        )
+        .unwrap();
+        let message = invalid.validate().unwrap_err().to_string();
+        assert!(message.contains("models.broken.model must not be empty"), "{}", message);
+        assert!(message.contains("models.broken.context_window must be greater than 0"), "{}", message);
+    }
+}
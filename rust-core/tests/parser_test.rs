@@ -0,0 +1,400 @@
+/// Tests for AST parsing and block extraction
+
+#[cfg(test)]
+mod tests {
+    use rust_core::indexer::extraction_rules::ExtractionRules;
+    use rust_core::indexer::parser::{ASTParser, CodeBlock};
+    use rust_core::OrchestratorError;
+    use std::path::Path;
+
+    fn find<'a>(blocks: &'a [CodeBlock], name: &str) -> &'a CodeBlock {
+        blocks
+            .iter()
+            .find(|b| b.name.as_deref() == Some(name))
+            .unwrap_or_else(|| panic!("no block named {}", name))
+    }
+
+    #[test]
+    fn test_rust_docstring_skips_attributes() {
+        let source = r#"
+/// A point in space.
+/// Used for rendering.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+/** Block documented
+ * function. */
+fn block_doc() -> i32 {
+    1
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "rust").unwrap();
+
+        assert_eq!(
+            find(&blocks, "Point").docstring.as_deref(),
+            Some("A point in space.\nUsed for rendering.")
+        );
+        assert_eq!(
+            find(&blocks, "block_doc").docstring.as_deref(),
+            Some("Block documented\nfunction.")
+        );
+    }
+
+    #[test]
+    fn test_rust_inner_docs_belong_to_module() {
+        let source = r#"//! Crate level docs.
+
+fn first_item() -> i32 {
+    1
+}
+
+mod storage {
+    //! Storage backends.
+
+    fn open() -> bool {
+        true
+    }
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "rust").unwrap();
+
+        assert_eq!(find(&blocks, "first_item").docstring, None);
+        assert_eq!(find(&blocks, "storage").docstring.as_deref(), Some("Storage backends."));
+        assert_eq!(find(&blocks, "open").docstring, None);
+    }
+
+    #[test]
+    fn test_jsdoc_immediately_preceding() {
+        let source = r#"
+/**
+ * Adds two numbers.
+ * @param {number} a
+ */
+function add(a, b) {
+    return a + b;
+}
+
+/** Exported helper. */
+export function helper() {
+    return add(1, 2);
+}
+
+// plain comment
+function undocumented() {
+    return 0;
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "javascript").unwrap();
+
+        assert_eq!(
+            find(&blocks, "add").docstring.as_deref(),
+            Some("Adds two numbers.\n@param {number} a")
+        );
+        assert_eq!(find(&blocks, "helper").docstring.as_deref(), Some("Exported helper."));
+        assert_eq!(find(&blocks, "undocumented").docstring, None);
+    }
+
+    #[test]
+    fn test_python_decorated_definitions_emit_one_block() {
+        let source = r#"
+@app.route("/health")
+@requires_auth
+def health_check():
+    return {"status": "ok"}
+
+@dataclass
+class Settings:
+    debug: bool = False
+"#;
+        let blocks = ASTParser::new().parse_file(source, "python").unwrap();
+
+        let health: Vec<_> = blocks.iter().filter(|b| b.name.as_deref() == Some("health_check")).collect();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].decorators, vec!["@app.route(\"/health\")", "@requires_auth"]);
+        assert_eq!(find(&blocks, "Settings").decorators, vec!["@dataclass"]);
+        assert!(blocks.iter().all(|b| b.block_type != "decorated_definition"));
+    }
+
+    #[test]
+    fn test_rust_attributes_attached_to_function() {
+        let source = r#"
+/// Checks storage.
+#[tokio::test]
+async fn test_storage() {
+    assert!(true);
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "rust").unwrap();
+
+        assert_eq!(find(&blocks, "test_storage").decorators, vec!["#[tokio::test]"]);
+    }
+
+    #[test]
+    fn test_python_methods_qualified_with_nested_classes() {
+        let source = r#"
+class Outer:
+    def run(self):
+        return 1
+
+    class Inner:
+        def method(self):
+            return 2
+
+def free_function():
+    return 3
+"#;
+        let blocks = ASTParser::new().parse_file(source, "python").unwrap();
+
+        assert_eq!(find(&blocks, "Outer.run").short_name.as_deref(), Some("run"));
+        assert_eq!(find(&blocks, "Outer.Inner").short_name.as_deref(), Some("Inner"));
+        assert_eq!(find(&blocks, "Outer.Inner.method").short_name.as_deref(), Some("method"));
+        assert_eq!(find(&blocks, "free_function").short_name.as_deref(), Some("free_function"));
+    }
+
+    #[test]
+    fn test_rust_impl_and_trait_methods_qualified() {
+        let source = r#"
+impl<T: Clone> ContextStorage<T> {
+    pub async fn save(&self) -> bool {
+        true
+    }
+}
+
+trait Store {
+    fn load(&self) -> bool {
+        false
+    }
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "rust").unwrap();
+
+        assert_eq!(find(&blocks, "ContextStorage").block_type, "impl_item");
+        assert_eq!(find(&blocks, "ContextStorage::save").short_name.as_deref(), Some("save"));
+        assert_eq!(find(&blocks, "Store::load").short_name.as_deref(), Some("load"));
+    }
+
+    #[test]
+    fn test_js_class_methods_qualified() {
+        let source = r#"
+class Router {
+    route(path) {
+        return path;
+    }
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "javascript").unwrap();
+
+        assert_eq!(find(&blocks, "Router").short_name.as_deref(), Some("Router"));
+        assert_eq!(find(&blocks, "Router.route").short_name.as_deref(), Some("route"));
+    }
+
+    #[test]
+    fn test_tsx_component_extracted_with_name() {
+        assert_eq!(ASTParser::detect_language(Path::new("src/App.tsx")).as_deref(), Some("tsx"));
+
+        let source = r#"
+import React from "react";
+
+interface HeaderProps {
+    title: string;
+}
+
+/** Page header. */
+export const Header = ({ title }: HeaderProps) => {
+    return <h1 className="title">{title}</h1>;
+};
+
+export default function App() {
+    return (
+        <main>
+            <Header title="Home" />
+        </main>
+    );
+}
+"#;
+        let blocks = ASTParser::new().parse_file(source, "tsx").unwrap();
+
+        let header = find(&blocks, "Header");
+        assert_eq!(header.block_type, "lexical_declaration");
+        assert_eq!(header.language, "tsx");
+        assert_eq!(header.docstring.as_deref(), Some("Page header."));
+        assert_eq!(find(&blocks, "App").block_type, "function_declaration");
+        assert!(blocks.iter().all(|b| b.name.is_some()));
+        assert!(blocks.iter().all(|b| b.block_type != "file"));
+    }
+
+    const BOUND_FUNCTIONS: &str = r#"
+const fetchUser = async (id) => {
+    return await db.get(id);
+};
+
+let retry = function () {
+    return fetchUser(1);
+};
+
+var legacy = function () {
+    return retry();
+};
+
+export const handler = async (req) => {
+    const ids = req.items.map(x => x + 1);
+    return ids;
+};
+
+module.exports.cleanup = () => {
+    legacy();
+};
+
+const api = {
+    save: async (item) => {
+        await db.put(item);
+    },
+    "load": function (key) {
+        return db.get(key);
+    },
+};
+"#;
+
+    #[test]
+    fn test_js_functions_named_after_binding() {
+        let blocks = ASTParser::new().parse_file(BOUND_FUNCTIONS, "javascript").unwrap();
+
+        for name in ["fetchUser", "retry", "legacy", "handler", "module.exports.cleanup", "save", "load"] {
+            find(&blocks, name);
+        }
+        assert_eq!(find(&blocks, "handler").block_type, "lexical_declaration");
+        assert_eq!(find(&blocks, "legacy").block_type, "function_expression");
+        assert_eq!(find(&blocks, "save").block_type, "arrow_function");
+        assert!(blocks.iter().all(|b| b.name.is_some()), "inline arrow should be skipped");
+    }
+
+    #[test]
+    fn test_inline_arrow_kept_with_lower_minimum() {
+        let blocks = ASTParser::new()
+            .with_min_anonymous_function_lines(1)
+            .parse_file(BOUND_FUNCTIONS, "javascript")
+            .unwrap();
+
+        let inline: Vec<_> = blocks.iter().filter(|b| b.name.is_none()).collect();
+        assert_eq!(inline.len(), 1);
+        assert_eq!(inline[0].content, "x => x + 1");
+    }
+
+    #[test]
+    fn test_python_lambda_named_after_assignment() {
+        let source = r#"
+square = lambda value: value * value
+
+def total(items):
+    return sum(map(lambda item: item.price, items))
+"#;
+        let blocks = ASTParser::new().parse_file(source, "python").unwrap();
+
+        assert_eq!(find(&blocks, "square").block_type, "lambda");
+        assert!(blocks.iter().all(|b| b.name.is_some()));
+    }
+
+    #[test]
+    fn test_parse_file_shared_across_threads() {
+        let parser = ASTParser::new();
+        let sources = [
+            ("rust", "fn alpha() -> i32 {\n    1\n}\n", "alpha"),
+            ("python", "def beta():\n    return 2\n", "beta"),
+            ("javascript", "function gamma() {\n  return 3;\n}\n", "gamma"),
+            ("typescript", "function delta(): number {\n  return 4;\n}\n", "delta"),
+        ];
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let parser = &parser;
+                let (language, source, name) = sources[thread % sources.len()];
+                scope.spawn(move || {
+                    for _ in 0..25 {
+                        let blocks = parser.parse_file(source, language).unwrap();
+                        assert_eq!(find(&blocks, name).language, language);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_unsupported_language_errors_on_every_call() {
+        let parser = ASTParser::new();
+        for _ in 0..3 {
+            let err = parser.parse_file("IDENTIFICATION DIVISION.", "cobol").unwrap_err();
+            assert_eq!(err, "Language 'cobol' not supported");
+        }
+        // Supported languages still parse afterwards
+        assert!(parser.parse_file("fn ok() {}", "rust").is_ok());
+    }
+
+    const MACROS: &str = r#"
+macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
+
+fn area(side: i32) -> i32 {
+    square!(side)
+}
+"#;
+
+    fn summary(blocks: &[CodeBlock]) -> Vec<(String, Option<String>, usize)> {
+        blocks.iter().map(|b| (b.block_type.clone(), b.name.clone(), b.start_line)).collect()
+    }
+
+    #[test]
+    fn test_custom_rules_capture_macro_rules() {
+        let rules = ExtractionRules::parse(
+            r#"
+[rust]
+capture = ["function_item", "struct_item", "impl_item", "trait_item", "enum_item", "mod_item", "macro_definition"]
+"#,
+        )
+        .unwrap();
+
+        let default_blocks = ASTParser::new().parse_file(MACROS, "rust").unwrap();
+        assert!(default_blocks.iter().all(|b| b.block_type != "macro_definition"));
+
+        let blocks = ASTParser::new().with_rules(rules).parse_file(MACROS, "rust").unwrap();
+        assert_eq!(find(&blocks, "square").block_type, "macro_definition");
+        assert_eq!(find(&blocks, "area").block_type, "function_item");
+    }
+
+    #[test]
+    fn test_default_rules_match_built_ins() {
+        assert_eq!(ExtractionRules::parse("").unwrap(), ExtractionRules::default());
+
+        let sources = [(MACROS, "rust"), (BOUND_FUNCTIONS, "javascript"), ("def beta():\n    return 2\n", "python")];
+        for (source, language) in sources {
+            let built_in = ASTParser::new().parse_file(source, language).unwrap();
+            let with_defaults = ASTParser::new()
+                .with_rules(ExtractionRules::default())
+                .parse_file(source, language)
+                .unwrap();
+            assert_eq!(summary(&built_in), summary(&with_defaults));
+        }
+
+        // Overriding one key of one language keeps everything else
+        let rules = ExtractionRules::parse("[python]\nmin_content_length = 40\n").unwrap();
+        let defaults = ExtractionRules::default();
+        assert_eq!(rules.for_language("python").capture, defaults.for_language("python").capture);
+        assert_eq!(rules.for_language("python").min_content_length, 40);
+        assert_eq!(rules.for_language("rust"), defaults.for_language("rust"));
+    }
+
+    #[test]
+    fn test_rules_report_unknown_languages_and_reject_unknown_keys() {
+        let rules = ExtractionRules::parse("[cobol]\ncapture = [\"paragraph\"]\n[rust]\nmin_content_length = 5\n").unwrap();
+        assert_eq!(rules.unknown_languages(), vec!["cobol"]);
+
+        let typo = ExtractionRules::parse("[rust]\ncaptures = [\"function_item\"]\n");
+        assert!(matches!(typo, Err(OrchestratorError::InvalidConfig(_))));
+    }
+}
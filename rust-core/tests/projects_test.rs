@@ -0,0 +1,203 @@
+/// Tests for the project registry
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rust_core::context::{Context, ContextManager, ContextStorage};
+    use rust_core::cost::storage::{CostRecord, CostStorage};
+    use rust_core::indexer::codebase::CodebaseIndexer;
+    use rust_core::indexer::parser::CodeBlock;
+    use rust_core::indexer::storage::IndexStorage;
+    use rust_core::migrations::{MigrationRunner, register_migrations};
+    use rust_core::projects::{Project, ProjectCascade, ProjectDeletion, ProjectSettings, ProjectStore};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+
+        pool
+    }
+
+    fn block(name: &str) -> CodeBlock {
+        CodeBlock {
+            block_type: "function_item".to_string(),
+            name: Some(name.to_string()),
+            short_name: Some(name.to_string()),
+            content: format!("fn {}() {{}}", name),
+            start_line: 0,
+            end_line: 1,
+            language: "rust".to_string(),
+            docstring: None,
+            decorators: Vec::new(),
+            parent_index: None,
+        }
+    }
+
+    fn cost(project_id: &str) -> CostRecord {
+        CostRecord {
+            id: None,
+            request_id: None,
+            tool: "claude".to_string(),
+            model: "claude-3-haiku".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cost_usd: 0.01,
+            timestamp: Utc::now(),
+            user_id: None,
+            project_id: Some(project_id.to_string()),
+            conversation_id: None,
+            labels: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_get_list_and_update() {
+        let projects = ProjectStore::from_pool(create_test_pool().await).await.unwrap();
+        let settings = ProjectSettings {
+            skip_patterns: Some(vec!["vendor".to_string()]),
+            routing_overrides: HashMap::from([("research".to_string(), vec!["gpt".to_string()])]),
+            budget_usd: Some(25.0),
+        };
+        let beta = Project::new("beta", "Beta").with_root_path("/src/beta").with_settings(settings.clone());
+        projects.create(&beta).await.unwrap();
+        projects.create(&Project::new("alpha", "Alpha")).await.unwrap();
+
+        assert_eq!(projects.get("beta").await.unwrap(), Some(beta.clone()));
+        assert_eq!(projects.get("gamma").await.unwrap(), None);
+        let ids: Vec<String> = projects.list().await.unwrap().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec!["alpha", "beta"]);
+
+        // Ids are unique
+        let duplicate = projects.create(&Project::new("beta", "Other")).await;
+        assert!(matches!(duplicate, Err(OrchestratorError::InvalidInput(_))));
+
+        let renamed = Project { name: "Beta 2".to_string(), ..beta };
+        assert!(projects.update(&renamed).await.unwrap());
+        assert_eq!(projects.get("beta").await.unwrap().unwrap().name, "Beta 2");
+        assert!(!projects.update(&Project::new("gamma", "Gamma")).await.unwrap());
+
+        // Overrides are keyed by routing rule, not task type
+        let mut misnamed = ProjectSettings::default();
+        misnamed.routing_overrides.insert("code_generation".to_string(), vec!["gpt".to_string()]);
+        let gamma = Project::new("gamma", "Gamma").with_settings(misnamed);
+        assert!(matches!(projects.create(&gamma).await, Err(OrchestratorError::InvalidInput(_))));
+        assert_eq!(projects.get("gamma").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cascade_delete_removes_project_data_only() {
+        let pool = create_test_pool().await;
+        let projects = ProjectStore::from_pool(pool.clone()).await.unwrap();
+        let contexts = ContextManager::new(ContextStorage::from_pool(pool.clone()).await.unwrap());
+        let index = IndexStorage::new(pool.clone());
+        let costs = CostStorage::from_pool(pool.clone()).await.unwrap();
+
+        for id in ["alpha", "beta"] {
+            projects.create(&Project::new(id, id)).await.unwrap();
+            contexts.get_or_create_context(None, Some(id.to_string())).await.unwrap();
+            index.store_file(id, "src/lib.rs", "rust", &[block("run")]).await.unwrap();
+            costs.record_cost(&cost(id)).await.unwrap();
+        }
+        // More contexts than fit in one page of the cascade
+        for _ in 0..150 {
+            contexts.get_or_create_context(None, Some("alpha".to_string())).await.unwrap();
+        }
+        index.store_file("alpha", "src/main.rs", "rust", &[block("main")]).await.unwrap();
+        costs.record_cost(&cost("alpha")).await.unwrap();
+
+        let cascade = ProjectCascade {
+            contexts: Some(&contexts),
+            index: Some(&index),
+            costs: Some(&costs),
+        };
+        let deletion = projects.delete("alpha", cascade).await.unwrap();
+        assert_eq!(
+            deletion,
+            Some(ProjectDeletion {
+                contexts_deleted: 151,
+                files_removed: 2,
+                cost_records_deleted: 2,
+            })
+        );
+
+        assert_eq!(projects.get("alpha").await.unwrap(), None);
+        assert!(contexts.list_contexts(Some("alpha"), None, 10, None).await.unwrap().items.is_empty());
+        assert!(index.list_files_with_hash("alpha").await.unwrap().is_empty());
+        assert!(costs.list_records(Some("alpha"), 10, None).await.unwrap().items.is_empty());
+
+        // The other project is untouched
+        assert!(projects.exists("beta").await.unwrap());
+        assert_eq!(contexts.list_contexts(Some("beta"), None, 10, None).await.unwrap().items.len(), 1);
+        assert_eq!(index.list_files_with_hash("beta").await.unwrap().len(), 1);
+        assert_eq!(costs.list_records(Some("beta"), 10, None).await.unwrap().items.len(), 1);
+
+        // Deleting again finds nothing
+        assert_eq!(projects.delete("alpha", cascade).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_delete_without_cascade_keeps_project_data() {
+        let pool = create_test_pool().await;
+        let projects = ProjectStore::from_pool(pool.clone()).await.unwrap();
+        let costs = CostStorage::from_pool(pool.clone()).await.unwrap();
+        projects.create(&Project::new("alpha", "Alpha")).await.unwrap();
+        costs.record_cost(&cost("alpha")).await.unwrap();
+
+        let deletion = projects.delete("alpha", ProjectCascade::default()).await.unwrap();
+        assert_eq!(deletion, Some(ProjectDeletion::default()));
+        assert_eq!(costs.list_records(Some("alpha"), 10, None).await.unwrap().items.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects_unknown_project_ids() {
+        let pool = create_test_pool().await;
+        let projects = ProjectStore::from_pool(pool.clone()).await.unwrap();
+        projects.create(&Project::new("alpha", "Alpha")).await.unwrap();
+
+        let contexts = ContextManager::new(ContextStorage::from_pool(pool.clone()).await.unwrap())
+            .with_project_registry(projects.clone());
+        contexts.get_or_create_context(None, Some("alpha".to_string())).await.unwrap();
+        let typo = contexts.get_or_create_context(None, Some("aplha".to_string())).await;
+        assert!(matches!(typo, Err(OrchestratorError::InvalidInput(ref msg)) if msg.contains("aplha")));
+        // Contexts without a project are still allowed
+        contexts.get_or_create_context(None, None).await.unwrap();
+        // Nor can a context be saved or modified into an unknown project
+        let typo = contexts.update_context(&mut Context::new(Some("aplha".to_string()))).await;
+        assert!(matches!(typo, Err(OrchestratorError::InvalidInput(ref msg)) if msg.contains("aplha")));
+        let typo = contexts
+            .with_conversation_lock("locked", |context| {
+                context.project_id = Some("aplha".to_string());
+                Ok(())
+            })
+            .await;
+        assert!(matches!(typo, Err(OrchestratorError::InvalidInput(ref msg)) if msg.contains("aplha")));
+        assert!(contexts.get_context("locked").await.unwrap().is_none());
+
+        let costs = CostStorage::from_pool(pool.clone()).await.unwrap()
+            .with_project_registry(projects.clone());
+        costs.record_cost(&cost("alpha")).await.unwrap();
+        let typo = costs.record_cost(&cost("aplha")).await;
+        assert!(matches!(typo, Err(OrchestratorError::InvalidInput(_))));
+        assert!(costs.list_records(Some("aplha"), 10, None).await.unwrap().items.is_empty());
+
+        let mut indexer = CodebaseIndexer::new("aplha".to_string(), IndexStorage::new(pool.clone()))
+            .with_project_registry(projects.clone());
+        let error = indexer.index_file(&PathBuf::from("src/lib.rs")).await.unwrap_err();
+        assert!(error.contains("Unknown project: aplha"));
+
+        // Without strict mode, unknown ids are accepted as before
+        let lenient = CostStorage::from_pool(pool).await.unwrap();
+        lenient.record_cost(&cost("aplha")).await.unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+/// Tests for rate limiter warm-up, stats and tracing
+
+#[cfg(test)]
+mod tests {
+    use rust_core::observability::MetricsCollector;
+    use rust_core::resilience::{RateLimiter, RateLimiterConfig, TokenBucket};
+    use rust_core::OrchestratorError;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::time::Instant;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// Collects span and event fields as "name=value" strings
+    #[derive(Clone, Default)]
+    struct TraceCapture {
+        spans: Arc<Mutex<HashMap<u64, Vec<String>>>>,
+        events: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    impl TraceCapture {
+        fn spans_named(&self, name: &str) -> Vec<Vec<String>> {
+            let marker = format!("span={}", name);
+            self.spans.lock().unwrap().values().filter(|fields| fields.contains(&marker)).cloned().collect()
+        }
+    }
+
+    struct FieldVisitor(Vec<String>);
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for TraceCapture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor(vec![format!("span={}", attrs.metadata().name())]);
+            attrs.record(&mut visitor);
+            self.spans.lock().unwrap().insert(id.into_u64(), visitor.0);
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor(Vec::new());
+            values.record(&mut visitor);
+            if let Some(fields) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+                fields.extend(visitor.0);
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor(vec![format!("level={}", event.metadata().level())]);
+            event.record(&mut visitor);
+            self.events.lock().unwrap().push(visitor.0);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_initial_tokens_limit_cold_start_burst() {
+        let limiter = RateLimiter::from_config(
+            "provider",
+            RateLimiterConfig::new(50, 1.0).with_initial_tokens(2),
+        );
+
+        assert!(limiter.try_acquire(1).is_ok());
+        assert!(limiter.try_acquire(1).is_ok());
+        assert!(limiter.try_acquire(1).is_err());
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(limiter.try_acquire(1).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_default_config_starts_full() {
+        let limiter = RateLimiter::new("provider", 5, 1.0);
+        for _ in 0..5 {
+            assert!(limiter.try_acquire(1).is_ok());
+        }
+        assert!(limiter.try_acquire(1).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warmup_ramps_refill_rate_linearly() {
+        let config = RateLimiterConfig::new(1000, 10.0)
+            .with_initial_tokens(0)
+            .with_warmup(Duration::from_secs(10));
+        let mut bucket = TokenBucket::from_config(&config);
+
+        // Halfway through warm-up the average rate is a quarter of full
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(bucket.available(), 12);
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(bucket.available(), 50);
+
+        // Full rate once warmed up
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(bucket.available(), 60);
+
+        let mut cold = TokenBucket::from_config(&RateLimiterConfig::new(1000, 10.0).with_initial_tokens(0));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(cold.available(), 50);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_time_accounts_for_warmup() {
+        let config = RateLimiterConfig::new(100, 2.0)
+            .with_initial_tokens(0)
+            .with_warmup(Duration::from_secs(4));
+        let bucket = TokenBucket::from_config(&config);
+
+        // 2 * t^2 / 8 = 1 token at t = 2s
+        let wait = bucket.wait_time();
+        assert!((wait.as_secs_f64() - 2.0).abs() < 1e-6, "{:?}", wait);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stats_and_metrics_counters() {
+        let metrics = MetricsCollector::new();
+        let limiter = RateLimiter::from_config(
+            "provider",
+            RateLimiterConfig::new(10, 2.0).with_initial_tokens(1),
+        )
+        .with_metrics(metrics.clone());
+
+        limiter.acquire(1).await.unwrap();
+        assert!(limiter.try_acquire(1).is_err());
+
+        // Waits for the next token at 2 tokens/sec
+        limiter.acquire(1).await.unwrap();
+
+        let stats = limiter.stats();
+        assert_eq!(stats.acquired, 2);
+        assert_eq!(stats.rejected, 1);
+        assert!(stats.total_wait_time >= Duration::from_millis(500), "{:?}", stats.total_wait_time);
+        assert!(stats.total_wait_time < Duration::from_millis(600), "{:?}", stats.total_wait_time);
+
+        let exported = metrics.export();
+        assert!(exported.contains(r#"uai_rate_limiter_waits_total{limiter="provider"} 1"#), "{}", exported);
+        assert!(exported.contains(r#"uai_rate_limiter_rejections_total{limiter="provider"} 1"#), "{}", exported);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_waiting_gauge_rises_and_falls() {
+        let metrics = MetricsCollector::new();
+        let limiter = RateLimiter::from_config(
+            "provider",
+            RateLimiterConfig::new(10, 1.0).with_initial_tokens(0),
+        )
+        .with_metrics(metrics.clone());
+
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire(1).await })
+            })
+            .collect();
+        tokio::task::yield_now().await;
+        assert_eq!(limiter.stats().waiting, 2);
+        assert!(metrics.export().contains(r#"uai_rate_limiter_waiting{limiter="provider"} 2"#));
+
+        // One token refills per second; each lets one waiter through
+        tokio::time::advance(Duration::from_secs(1)).await;
+        tokio::task::yield_now().await;
+        assert_eq!(limiter.stats().waiting, 1);
+
+        for waiter in waiters {
+            waiter.await.unwrap().unwrap();
+        }
+        let stats = limiter.stats();
+        assert_eq!((stats.acquired, stats.waiting), (2, 0));
+        assert!(metrics.export().contains(r#"uai_rate_limiter_waiting{limiter="provider"} 0"#));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_span_records_wait() {
+        let capture = TraceCapture::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+        let limiter = RateLimiter::from_config(
+            "provider",
+            RateLimiterConfig::new(10, 2.0).with_initial_tokens(1),
+        );
+
+        limiter.acquire(1).await.unwrap();
+        // 3 tokens at 2 tokens/sec
+        limiter.acquire(3).await.unwrap();
+
+        let spans = capture.spans_named("rate_limiter_acquire");
+        assert_eq!(spans.len(), 2);
+        let waits: Vec<&Vec<String>> = spans.iter().filter(|fields| fields.contains(&"tokens=3".to_string())).collect();
+        assert_eq!(waits.len(), 1);
+        assert!(waits[0].contains(&"limiter=provider".to_string()), "{:?}", waits[0]);
+        let wait_ms: u64 = waits[0]
+            .iter()
+            .find_map(|field| field.strip_prefix("wait_ms="))
+            .expect("wait_ms is recorded")
+            .parse()
+            .unwrap();
+        assert!((1500..1510).contains(&wait_ms), "{}", wait_ms);
+        assert_eq!(limiter.stats().total_wait_time.as_millis() as u64, wait_ms);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_with_deadline_gives_up_early() {
+        let capture = TraceCapture::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+        let limiter = RateLimiter::from_config(
+            "provider",
+            RateLimiterConfig::new(10, 1.0).with_initial_tokens(0),
+        );
+
+        let start = Instant::now();
+        let result = limiter.acquire_with_deadline(1, start + Duration::from_millis(500)).await;
+        assert!(matches!(result, Err(OrchestratorError::RateLimitExceeded(_))));
+        assert_eq!(start.elapsed(), Duration::ZERO, "gives up without sleeping");
+
+        let events = capture.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains(&"level=WARN".to_string()));
+        assert!(events[0].contains(&"message=rate limiter deadline exceeded".to_string()), "{:?}", events[0]);
+
+        limiter.acquire_with_deadline(1, start + Duration::from_secs(2)).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1), "{:?}", start.elapsed());
+        assert!(start.elapsed() < Duration::from_millis(1010), "{:?}", start.elapsed());
+        let stats = limiter.stats();
+        assert_eq!((stats.acquired, stats.deadline_exceeded, stats.waiting), (1, 1, 0));
+        assert_eq!(capture.events.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+/// Tests for request ID propagation across routing, context, cost and metrics
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rust_core::context::{ContextManager, InMemoryContextStore};
+    use rust_core::cost::storage::{CostRecord, CostStorage};
+    use rust_core::observability::{ensure_request_id, request_id, MetricsCollector, RequestMetrics};
+    use rust_core::router::{Router, RoutingRequest};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// (span name, request_id field) of every span created
+    type SpanLog = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+    #[derive(Clone, Default)]
+    struct CapturedSpans(SpanLog);
+
+    struct RequestIdVisitor(Option<String>);
+
+    impl Visit for RequestIdVisitor {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "request_id" {
+                self.0 = Some(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "request_id" {
+                self.0 = Some(format!("{:?}", value));
+            }
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CapturedSpans {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            let mut visitor = RequestIdVisitor(None);
+            attrs.record(&mut visitor);
+            self.0.lock().unwrap().push((attrs.metadata().name().to_string(), visitor.0));
+        }
+    }
+
+    impl CapturedSpans {
+        fn request_id_of(&self, span: &str) -> Option<String> {
+            let spans = self.0.lock().unwrap();
+            spans.iter().find(|(name, _)| name == span).and_then(|(_, id)| id.clone())
+        }
+    }
+
+    #[test]
+    fn test_request_ids_are_unique_uuid_v7() {
+        let first = request_id();
+        let second = request_id();
+        assert_ne!(first, second);
+        assert_eq!(uuid::Uuid::parse_str(&first).unwrap().get_version_num(), 7);
+
+        assert_eq!(ensure_request_id(Some("req-1")), "req-1");
+        assert_ne!(ensure_request_id(Some("")), "");
+    }
+
+    #[tokio::test]
+    async fn test_routed_request_shares_one_id() {
+        let spans = CapturedSpans::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+
+        let router = Router::new(HashMap::new(), "claude".to_string());
+        let decision = router.route(&RoutingRequest {
+            message: "explain this function".to_string(),
+            conversation_id: None,
+            project_id: Some("proj".to_string()),
+            explicit_tool: None,
+            request_id: None,
+        });
+        let id = decision.request_id.clone();
+        assert!(!id.is_empty());
+
+        // Tool call recorded in the conversation
+        let manager = ContextManager::new(InMemoryContextStore::new());
+        let mut context = manager.get_or_create_context(None, Some("proj".to_string())).await.unwrap();
+        context.add_tool_call_for_request(
+            "claude".to_string(),
+            "explain".to_string(),
+            "it retries".to_string(),
+            Some(id.clone()),
+        );
+        manager.update_context(&mut context).await.unwrap();
+        let stored = manager.get_context(&context.conversation_id).await.unwrap().unwrap();
+        assert_eq!(stored.tool_history[0].request_id.as_deref(), Some(id.as_str()));
+
+        // Cost row
+        let pool = SqlitePoolOptions::new().max_connections(1).connect(":memory:").await.unwrap();
+        let costs = CostStorage::from_pool(pool).await.unwrap();
+        costs
+            .record_cost(&CostRecord {
+                id: None,
+                request_id: Some(id.clone()),
+                tool: "claude".to_string(),
+                model: "claude-3-haiku".to_string(),
+                input_tokens: 100,
+                output_tokens: 50,
+                cost_usd: 0.01,
+                timestamp: Utc::now(),
+                user_id: None,
+                project_id: Some("proj".to_string()),
+                conversation_id: Some(context.conversation_id.clone()),
+                labels: HashMap::new(),
+            })
+            .await
+            .unwrap();
+        let page = costs.list_records(None, 10, None).await.unwrap();
+        assert_eq!(page.items[0].request_id.as_deref(), Some(id.as_str()));
+
+        // Metrics record
+        let metrics = RequestMetrics {
+            request_id: id.clone(),
+            tool: "claude".to_string(),
+            duration_ms: 120,
+            tokens_input: Some(100),
+            tokens_output: Some(50),
+            cost_usd: Some(0.01),
+            success: true,
+            error: None,
+        };
+        MetricsCollector::new().record_request(metrics.clone());
+        assert_eq!(metrics.request_id, id);
+
+        // Spans
+        assert_eq!(spans.request_id_of("route").as_deref(), Some(id.as_str()));
+        assert_eq!(spans.request_id_of("record_cost").as_deref(), Some(id.as_str()));
+        assert_eq!(spans.request_id_of("record_request").as_deref(), Some(id.as_str()));
+    }
+
+    #[test]
+    fn test_caller_request_id_is_kept() {
+        let router = Router::new(HashMap::new(), "claude".to_string());
+        let decision = router.route(&RoutingRequest {
+            message: "hello".to_string(),
+            conversation_id: None,
+            project_id: None,
+            explicit_tool: None,
+            request_id: Some("req-from-caller".to_string()),
+        });
+        assert_eq!(decision.request_id, "req-from-caller");
+    }
+}
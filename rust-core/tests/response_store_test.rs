@@ -0,0 +1,116 @@
+/// Tests for persisted composed responses
+
+#[cfg(test)]
+mod tests {
+    use rust_core::composer::{Composer, ResponseStore, ToolResponse};
+    use rust_core::migrations::{register_migrations, MigrationRunner};
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+    use std::time::Duration;
+
+    const DAY: i64 = 24 * 60 * 60;
+    const NOW: i64 = 1_700_000_000;
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+
+        pool
+    }
+
+    fn tool_response(tool: &str, content: &str) -> ToolResponse {
+        ToolResponse {
+            tool: tool.to_string(),
+            content: content.to_string(),
+            metadata: Some(serde_json::json!({ "model": format!("{}-model", tool) })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_stores_raw_responses_compressed() {
+        let pool = create_test_pool().await;
+        let store = ResponseStore::from_pool(pool.clone()).await.unwrap();
+
+        let raw = vec![
+            tool_response("claude", &"fn main() {}\n".repeat(500)),
+            tool_response("gpt", "use a loop"),
+        ];
+        let composed = Composer::compose(raw.clone());
+        let id = store.save(&composed, &raw, "conv-1", "req-1").await.unwrap();
+
+        let stored = store.get_by_request("req-1").await.unwrap().expect("stored response");
+        assert_eq!(stored.id, id);
+        assert_eq!(stored.conversation_id, "conv-1");
+        assert_eq!(stored.content, composed.content);
+        assert_eq!(stored.strategy, "concatenate");
+        assert_eq!(stored.sources, vec!["claude", "gpt"]);
+        assert_eq!(stored.metadata, composed.metadata);
+        assert_eq!(stored.raw_responses.len(), 2);
+        assert_eq!(stored.raw_responses[0].content, raw[0].content);
+        assert_eq!(stored.raw_responses[1].metadata, raw[1].metadata);
+
+        // The raw column holds gzip data, much smaller than the JSON it encodes
+        let (blob,): (Vec<u8>,) = sqlx::query_as("SELECT raw_responses FROM responses WHERE id = ?1")
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(&blob[..2], &[0x1f, 0x8b]);
+        assert!(blob.len() < serde_json::to_vec(&raw).unwrap().len() / 10);
+
+        assert!(store.get_by_request("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_conversation_listing_is_oldest_first() {
+        let store = ResponseStore::from_pool(create_test_pool().await).await.unwrap();
+        let raw = vec![tool_response("claude", "answer")];
+        let composed = Composer::compose(raw.clone());
+
+        store.save_at(&composed, &raw, "conv-1", "req-b", NOW).await.unwrap();
+        store.save_at(&composed, &raw, "conv-1", "req-a", NOW - DAY).await.unwrap();
+        store.save_at(&composed, &raw, "conv-2", "req-x", NOW - 2 * DAY).await.unwrap();
+        store.save_at(&composed, &raw, "conv-1", "req-c", NOW).await.unwrap();
+
+        let listed = store.get_by_conversation("conv-1").await.unwrap();
+        let requests: Vec<&str> = listed.iter().map(|r| r.request_id.as_str()).collect();
+        // Same timestamp falls back to insertion order
+        assert_eq!(requests, vec!["req-a", "req-b", "req-c"]);
+        assert_eq!(listed[0].strategy, "single");
+        assert!(store.get_by_conversation("conv-3").await.unwrap().is_empty());
+
+        // A request saved twice resolves to the latest copy
+        let retried = store.save_at(&composed, &raw, "conv-1", "req-b", NOW + 1).await.unwrap();
+        assert_eq!(store.get_by_request("req-b").await.unwrap().unwrap().id, retried);
+    }
+
+    #[tokio::test]
+    async fn test_prune_removes_old_responses() {
+        let store = ResponseStore::from_pool(create_test_pool().await).await.unwrap();
+        let raw = vec![tool_response("claude", "answer")];
+        let composed = Composer::compose(raw.clone());
+        let now = chrono::Utc::now().timestamp();
+
+        store.save_at(&composed, &raw, "conv-1", "old", now - 40 * DAY).await.unwrap();
+        store.save_at(&composed, &raw, "conv-1", "recent", now - DAY).await.unwrap();
+        store.save(&composed, &raw, "conv-1", "new").await.unwrap();
+
+        let removed = store.prune_older_than(Duration::from_secs(30 * DAY as u64)).await.unwrap();
+        assert_eq!(removed, 1);
+        let remaining: Vec<String> = store
+            .get_by_conversation("conv-1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.request_id)
+            .collect();
+        assert_eq!(remaining, vec!["recent", "new"]);
+        assert_eq!(store.prune_before(now + 60).await.unwrap(), 2);
+    }
+}
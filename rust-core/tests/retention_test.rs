@@ -0,0 +1,153 @@
+/// Tests for context retention policies
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{Context, ContextManager, ContextStorage, MockClock, RetentionPolicy, Role};
+    use rust_core::migrations::{MigrationRunner, register_migrations};
+    use rust_core::security::AuditLogger;
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+    const DAY: i64 = 24 * 60 * 60;
+    const NOW: i64 = 1_700_000_000;
+
+    async fn create_test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .expect("Failed to create test pool");
+
+        let mut runner = MigrationRunner::new(pool.clone());
+        register_migrations(&mut runner);
+        runner.migrate_up(None).await.expect("Migration should succeed");
+
+        pool
+    }
+
+    async fn create_manager(pool: &SqlitePool) -> (ContextManager, ContextStorage) {
+        let manager = ContextManager::new(ContextStorage::from_pool(pool.clone()).await.unwrap())
+            .with_audit_logger(AuditLogger::new(pool.clone()));
+        (manager, ContextStorage::from_pool(pool.clone()).await.unwrap())
+    }
+
+    /// Store a context with one message, last updated `age_days` before NOW
+    async fn seed(storage: &ContextStorage, project_id: Option<&str>, age_days: i64) -> String {
+        let mut context = Context::new(project_id.map(String::from));
+        context.add_message(Role::User, "hello".to_string());
+        storage.save_context_at(&mut context, NOW - age_days * DAY).await.unwrap();
+        context.conversation_id
+    }
+
+    async fn deletion_audit_ids(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT resource_id FROM audit_logs WHERE event_type = 'resource.delete' ORDER BY resource_id",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_expired_contexts_are_deleted_and_audited() {
+        let pool = create_test_pool().await;
+        let (manager, storage) = create_manager(&pool).await;
+        let stale = seed(&storage, Some("web"), 31).await;
+        let fresh = seed(&storage, Some("web"), 29).await;
+
+        let clock = MockClock::new(NOW);
+        let report = manager.apply_retention(&RetentionPolicy::default(), &clock).await.unwrap();
+
+        assert_eq!((report.examined, report.deleted, report.held), (2, 1, 0));
+        assert_eq!(report.deleted_ids, vec![stale.clone()]);
+        assert!(manager.get_context(&stale).await.unwrap().is_none());
+        assert!(manager.get_context(&fresh).await.unwrap().is_some());
+        assert_eq!(deletion_audit_ids(&pool).await, vec![stale]);
+
+        // The fresh context expires once the clock moves past its TTL
+        clock.advance_days(2);
+        let report = manager.apply_retention(&RetentionPolicy::default(), &clock).await.unwrap();
+        assert_eq!(report.deleted_ids, vec![fresh]);
+        assert!(storage.list_contexts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_per_project_override_replaces_default_ttl() {
+        let pool = create_test_pool().await;
+        let (manager, storage) = create_manager(&pool).await;
+        let long_lived = seed(&storage, Some("research"), 45).await;
+        let short_lived = seed(&storage, Some("scratch"), 8).await;
+        let default_ttl = seed(&storage, None, 45).await;
+
+        let policy = RetentionPolicy::default()
+            .with_project_ttl("research", 90)
+            .with_project_ttl("scratch", 7);
+        let report = manager.apply_retention(&policy, &MockClock::new(NOW)).await.unwrap();
+
+        assert_eq!(report.examined, 3);
+        let mut deleted = report.deleted_ids.clone();
+        deleted.sort();
+        let mut expected = vec![short_lived, default_ttl];
+        expected.sort();
+        assert_eq!(deleted, expected);
+        assert!(manager.get_context(&long_lived).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_legal_hold_projects_are_never_deleted() {
+        let pool = create_test_pool().await;
+        let (manager, storage) = create_manager(&pool).await;
+        let held = seed(&storage, Some("litigation"), 400).await;
+        let held_fresh = seed(&storage, Some("litigation"), 1).await;
+        let expired = seed(&storage, Some("web"), 400).await;
+
+        let policy = RetentionPolicy::default()
+            .with_project_ttl("litigation", 1)
+            .with_legal_hold("litigation");
+        let report = manager.apply_retention(&policy, &MockClock::new(NOW)).await.unwrap();
+
+        // Only expired contexts count as held
+        assert_eq!((report.examined, report.deleted, report.held), (3, 1, 1));
+        assert_eq!(report.deleted_ids, vec![expired.clone()]);
+        assert!(manager.get_context(&held).await.unwrap().is_some());
+        assert!(manager.get_context(&held_fresh).await.unwrap().is_some());
+        assert_eq!(deletion_audit_ids(&pool).await, vec![expired]);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_without_deleting() {
+        let pool = create_test_pool().await;
+        let (manager, storage) = create_manager(&pool).await;
+        let stale = seed(&storage, None, 60).await;
+
+        let policy = RetentionPolicy::default().with_dry_run(true);
+        let report = manager.apply_retention(&policy, &MockClock::new(NOW)).await.unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.deleted, 1);
+        assert_eq!(report.deleted_ids, vec![stale.clone()]);
+        assert!(manager.get_context(&stale).await.unwrap().is_some());
+        assert!(deletion_audit_ids(&pool).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_context_removes_messages() {
+        let pool = create_test_pool().await;
+        let (manager, storage) = create_manager(&pool).await;
+        let id = seed(&storage, None, 0).await;
+        sqlx::query("INSERT INTO messages (conversation_id, role, content, timestamp) VALUES (?1, 'user', 'hi', 0)")
+            .bind(&id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(manager.delete_context(&id).await.unwrap());
+        assert!(!manager.delete_context(&id).await.unwrap());
+
+        let (remaining,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM messages WHERE conversation_id = ?1")
+            .bind(&id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+}
@@ -0,0 +1,107 @@
+/// Tests for routing rules files and hot reload
+
+#[cfg(test)]
+mod tests {
+    use rust_core::router::{load_rules, ReloadableRouter, RoutingRequest, RoutingRule};
+    use rust_core::OrchestratorError;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn rules_file(contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uai-router-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("routing.toml");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn route_code_edit(router: &ReloadableRouter) -> Vec<String> {
+        router
+            .route(&RoutingRequest {
+                message: "fix the bug in this function".to_string(),
+                conversation_id: None,
+                project_id: None,
+                explicit_tool: None,
+                request_id: None,
+            })
+            .selected_tools
+    }
+
+    #[test]
+    fn test_load_rules_parses_top_level_and_routing_table() {
+        let path = rules_file("default_tool = \"claude\"\nresearch = [\"perplexity\"]\ncode_editing = [\"cursor\", \"claude\"]\n");
+        let rules = load_rules(&path).unwrap();
+        assert_eq!(rules.default_tool, "claude");
+        assert_eq!(
+            rules.rules,
+            vec![
+                RoutingRule { task: "code_editing".to_string(), tools: vec!["cursor".to_string(), "claude".to_string()] },
+                RoutingRule { task: "research".to_string(), tools: vec!["perplexity".to_string()] },
+            ]
+        );
+
+        std::fs::write(&path, "[routing]\ndefault_tool = \"gpt\"\ngeneral_chat = [\"gpt\"]\n").unwrap();
+        assert_eq!(load_rules(&path).unwrap().default_tool, "gpt");
+
+        std::fs::write(&path, "default_tool = \"claude\"\ncode_edit = [\"cursor\"]\n").unwrap();
+        match load_rules(&path) {
+            Err(OrchestratorError::InvalidConfig(message)) => assert!(message.contains("unknown task 'code_edit'")),
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_reload_swaps_rules_and_keeps_them_on_invalid_file() {
+        let path = rules_file("default_tool = \"claude\"\ncode_editing = [\"cursor\"]\n");
+        let router = ReloadableRouter::load(&path).unwrap();
+        assert_eq!(route_code_edit(&router), vec!["cursor"]);
+        let loaded_at = router.last_loaded_at();
+
+        std::fs::write(&path, "default_tool = \"claude\"\ncode_editing = [\"codex\"]\n").unwrap();
+        router.reload().unwrap();
+        assert_eq!(route_code_edit(&router), vec!["codex"]);
+        assert!(router.last_loaded_at() >= loaded_at);
+        assert!(router.last_error().is_none());
+
+        // A broken file is rejected and the previous rules stay in use
+        std::fs::write(&path, "default_tool = \"claude\"\ncode_editing = [\"cursor\"\n").unwrap();
+        assert!(matches!(router.reload(), Err(OrchestratorError::InvalidConfig(_))));
+        assert_eq!(route_code_edit(&router), vec!["codex"]);
+        assert!(router.last_error().unwrap().contains("Invalid routing rules"));
+
+        std::fs::write(&path, "default_tool = \"\"\ncode_editing = []\n").unwrap();
+        let err = router.reload().unwrap_err().to_string();
+        assert!(err.contains("default_tool must not be empty"));
+        assert!(err.contains("code_editing must list at least one tool"));
+        assert_eq!(route_code_edit(&router), vec!["codex"]);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_watcher_reloads_on_change() {
+        let path = rules_file("default_tool = \"claude\"\ncode_editing = [\"cursor\"]\n");
+        let router = Arc::new(ReloadableRouter::load(&path).unwrap());
+        let _watcher = router.watch().unwrap();
+
+        std::fs::write(&path, "default_tool = \"claude\"\ncode_editing = [\"codex\"]\n").unwrap();
+        let mut reloaded = false;
+        for _ in 0..50 {
+            if route_code_edit(&router) == vec!["codex"] {
+                reloaded = true;
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert!(reloaded, "rules were not reloaded after the file changed");
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_fixed_router_cannot_reload() {
+        let router = ReloadableRouter::from_router(rust_core::Router::new(Default::default(), "claude".to_string()));
+        assert_eq!(route_code_edit(&router), vec!["claude"]);
+        assert!(matches!(router.reload(), Err(OrchestratorError::InvalidConfig(_))));
+    }
+}
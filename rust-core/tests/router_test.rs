@@ -0,0 +1,348 @@
+/// Tests for routing decisions and their reasoning
+
+#[cfg(test)]
+mod tests {
+    use rust_core::router::{
+        analyze_request_detailed, analyzer, CodeFence, Complexity, ProjectRouting, ReloadableRouter, RoutingRequest,
+        SelectionPolicy, TaskType,
+    };
+    use rust_core::Router;
+    use std::collections::HashMap;
+
+    fn router() -> Router {
+        let mut rules = HashMap::new();
+        rules.insert("code_editing".to_string(), vec!["cursor".to_string(), "claude".to_string()]);
+        Router::new(rules, "gpt".to_string())
+    }
+
+    fn request(message: &str, explicit_tool: Option<&str>) -> RoutingRequest {
+        RoutingRequest {
+            message: message.to_string(),
+            conversation_id: None,
+            project_id: None,
+            explicit_tool: explicit_tool.map(str::to_string),
+            request_id: None,
+        }
+    }
+
+    fn project_request(message: &str, project_id: &str) -> RoutingRequest {
+        RoutingRequest {
+            project_id: Some(project_id.to_string()),
+            ..request(message, None)
+        }
+    }
+
+    fn wiki_override() -> ProjectRouting {
+        let mut extra_rules = HashMap::new();
+        extra_rules.insert("research".to_string(), vec!["wiki".to_string()]);
+        ProjectRouting {
+            default_tool: Some("local-llm".to_string()),
+            extra_rules,
+        }
+    }
+
+    #[test]
+    fn test_analysis_reports_matched_keywords() {
+        let analysis = analyzer::analyze("Fix the bug and run the tests");
+        assert_eq!(analysis.task_type, TaskType::CodeEditing);
+        assert_eq!(analysis.keyword_hits, vec!["fix", "bug", "test"]);
+        // "run" is a terminal keyword, so a quarter of the hits disagree
+        assert!((analysis.confidence - 0.75).abs() < 1e-9);
+
+        let chat = analyzer::analyze("hello there");
+        assert_eq!(chat.task_type, TaskType::GeneralChat);
+        assert!(chat.keyword_hits.is_empty());
+        assert_eq!(chat.confidence, 0.0);
+    }
+
+    #[test]
+    fn test_decision_carries_structured_reasoning() {
+        let decision = router().route(&request("Fix the bug and run the tests", None));
+        let details = &decision.reasoning_details;
+        assert_eq!(decision.selected_tools, vec!["cursor", "claude"]);
+        assert_eq!(details.matched_rule.as_deref(), Some("code_editing"));
+        assert_eq!(details.keyword_hits, vec!["fix", "bug", "test"]);
+        assert!(!details.fallback_used);
+        assert!(details.explicit_tool.is_none());
+        assert!(details.skipped_tools.is_empty());
+        assert_eq!(decision.reasoning, details.render(&decision.selected_tools));
+        assert_eq!(
+            decision.reasoning,
+            "Task type: CodeEditing, Selected tools: [\"cursor\", \"claude\"]; rule: code_editing; keywords: fix, bug, test (confidence 0.75)"
+        );
+    }
+
+    #[test]
+    fn test_fallback_and_explicit_reasoning() {
+        let fallback = router().route(&request("find a paper on transformers", None));
+        assert_eq!(fallback.selected_tools, vec!["gpt"]);
+        assert_eq!(fallback.reasoning_details.task_type, TaskType::Research);
+        assert!(fallback.reasoning_details.fallback_used);
+        assert!(fallback.reasoning_details.matched_rule.is_none());
+        assert_eq!(fallback.reasoning, fallback.reasoning_details.render(&fallback.selected_tools));
+        assert!(fallback.reasoning.contains("no matching rule, used default tool"));
+
+        let explicit = router().route(&request("fix this bug", Some("codex")));
+        assert_eq!(explicit.selected_tools, vec!["codex"]);
+        assert_eq!(explicit.reasoning_details.explicit_tool.as_deref(), Some("codex"));
+        assert!(explicit.reasoning_details.matched_rule.is_none());
+        assert!(!explicit.reasoning_details.fallback_used);
+        assert_eq!(explicit.reasoning, "Explicit tool selection: codex");
+    }
+
+    #[test]
+    fn test_project_override_layers_over_global_rules() {
+        let mut router = router();
+        router.set_project_override("alpha", wiki_override());
+
+        let research = router.route(&project_request("find a paper on transformers", "alpha"));
+        assert_eq!(research.selected_tools, vec!["wiki"]);
+        assert_eq!(research.reasoning_details.matched_rule.as_deref(), Some("research"));
+        assert_eq!(research.reasoning_details.project_override.as_deref(), Some("alpha"));
+        assert!(research.reasoning.contains("project override: alpha"));
+
+        // Global rules still cover task types the override leaves alone, and
+        // then the override is not reported
+        let editing = router.route(&project_request("fix this bug", "alpha"));
+        assert_eq!(editing.selected_tools, vec!["cursor", "claude"]);
+        assert!(editing.reasoning_details.project_override.is_none());
+        assert!(!editing.reasoning.contains("project override"));
+
+        // The project default replaces the global one where no rule applies
+        let chat = router.route(&project_request("hello there", "alpha"));
+        assert_eq!(chat.selected_tools, vec!["local-llm"]);
+        assert!(chat.reasoning_details.fallback_used);
+        assert_eq!(chat.reasoning_details.project_override.as_deref(), Some("alpha"));
+
+        // Other projects and explicit tools are unaffected
+        let other = router.route(&project_request("find a paper on transformers", "beta"));
+        assert_eq!(other.selected_tools, vec!["gpt"]);
+        assert!(other.reasoning_details.project_override.is_none());
+        assert!(!other.reasoning.contains("project override"));
+        let explicit = router.route(&RoutingRequest {
+            explicit_tool: Some("codex".to_string()),
+            ..project_request("find a paper", "alpha")
+        });
+        assert_eq!(explicit.selected_tools, vec!["codex"]);
+    }
+
+    #[test]
+    fn test_project_override_removal() {
+        let reloadable = ReloadableRouter::from_router(router());
+        reloadable.set_project_override("alpha", wiki_override());
+        assert_eq!(
+            reloadable.route(&project_request("find a paper on transformers", "alpha")).selected_tools,
+            vec!["wiki"]
+        );
+
+        assert_eq!(reloadable.remove_project_override("alpha"), Some(wiki_override()));
+        assert_eq!(reloadable.remove_project_override("alpha"), None);
+        let decision = reloadable.route(&project_request("find a paper on transformers", "alpha"));
+        assert_eq!(decision.selected_tools, vec!["gpt"]);
+        assert!(decision.reasoning_details.project_override.is_none());
+    }
+
+    #[test]
+    fn test_project_override_to_disabled_tool_falls_back() {
+        let mut overrides = HashMap::new();
+        overrides.insert("alpha".to_string(), wiki_override());
+        let router = router()
+            .with_project_overrides(overrides)
+            .with_disabled_tools(vec!["wiki".to_string(), "local-llm".to_string()]);
+
+        let research = router.route(&project_request("find a paper on transformers", "alpha"));
+        assert_eq!(research.selected_tools, vec!["gpt"]);
+        assert!(research.reasoning_details.fallback_used);
+        assert_eq!(
+            research.reasoning_details.skipped_tools,
+            vec![
+                ("wiki".to_string(), "disabled; ignored project override".to_string()),
+                ("local-llm".to_string(), "disabled; used global default tool".to_string()),
+            ]
+        );
+        assert!(research.reasoning.contains("skipped wiki: disabled; ignored project override"));
+        assert!(research.reasoning_details.project_override.is_none());
+
+        let chat = router.route(&project_request("hello there", "alpha"));
+        assert_eq!(chat.selected_tools, vec!["gpt"]);
+        assert_eq!(chat.reasoning_details.skipped_tools.len(), 1);
+    }
+
+    #[test]
+    fn test_override_rules_are_keyed_by_rule() {
+        assert!(wiki_override().validate().is_ok());
+
+        // Task type names that differ from their rule key are rejected
+        let mut extra_rules = HashMap::new();
+        extra_rules.insert("code_generation".to_string(), vec!["cursor".to_string()]);
+        extra_rules.insert("debugging".to_string(), vec!["claude".to_string()]);
+        let routing = ProjectRouting { default_tool: None, extra_rules };
+        let err = routing.validate().unwrap_err().to_string();
+        assert!(err.starts_with("Invalid input: Unknown routing rule keys code_generation (expected"));
+
+        // Code generation is routed by the code_editing key
+        let mut extra_rules = HashMap::new();
+        extra_rules.insert("code_editing".to_string(), vec!["codex".to_string()]);
+        let mut router = router();
+        router.set_project_override("alpha", ProjectRouting { default_tool: None, extra_rules });
+        let decision = router.route(&project_request("scaffold a new web app", "alpha"));
+        assert_eq!(decision.reasoning_details.task_type, TaskType::CodeGeneration);
+        assert_eq!(decision.selected_tools, vec!["codex"]);
+    }
+
+    #[test]
+    fn test_round_robin_alternates_exactly() {
+        let router = router().with_selection_policy("code_editing", SelectionPolicy::RoundRobin);
+        let picks: Vec<String> = (0..6)
+            .map(|_| router.route(&request("Fix the bug", None)).selected_tools[0].clone())
+            .collect();
+        assert_eq!(picks, vec!["cursor", "claude", "cursor", "claude", "cursor", "claude"]);
+
+        // The other tool stays listed as a fallback
+        let decision = router.route(&request("Fix the bug", None));
+        assert_eq!(decision.selected_tools, vec!["cursor", "claude"]);
+        assert_eq!(decision.reasoning_details.selection_policy.as_deref(), Some("round_robin"));
+        assert!(decision.reasoning.contains("; picked by round_robin"));
+
+        // Clones share the rotation
+        let clone = router.clone();
+        assert_eq!(clone.route(&request("Fix the bug", None)).selected_tools[0], "claude");
+        assert_eq!(router.route(&request("Fix the bug", None)).selected_tools[0], "cursor");
+    }
+
+    #[test]
+    fn test_weighted_random_approximates_weights() {
+        let mut weights = HashMap::new();
+        weights.insert("cursor".to_string(), 3.0);
+        weights.insert("claude".to_string(), 1.0);
+        let weighted = router()
+            .with_selection_policy("code_editing", SelectionPolicy::WeightedRandom(weights))
+            .with_selection_seed(42);
+
+        let cursor_picks = (0..1000)
+            .filter(|_| weighted.route(&request("Fix the bug", None)).selected_tools[0] == "cursor")
+            .count();
+        assert!((700..=800).contains(&cursor_picks), "cursor picked {} times", cursor_picks);
+
+        // The same seed picks the same tools
+        let replay = router().with_selection_seed(42).with_selection_policy(
+            "code_editing",
+            weighted.selection_policy("code_editing"),
+        );
+        let replay_picks = (0..1000)
+            .filter(|_| replay.route(&request("Fix the bug", None)).selected_tools[0] == "cursor")
+            .count();
+        assert_eq!(replay_picks, cursor_picks);
+    }
+
+    #[test]
+    fn test_first_match_is_the_default_policy() {
+        let router = router();
+        assert_eq!(router.selection_policy("code_editing"), SelectionPolicy::FirstMatch);
+        let decision = router.route(&request("Fix the bug", None));
+        assert_eq!(decision.selected_tools, vec!["cursor", "claude"]);
+        assert!(decision.reasoning_details.selection_policy.is_none());
+    }
+
+    const PYTHON_TRACE: &str = "Traceback (most recent call last):\n  File \"/srv/app/main.py\", line 12, in <module>\n    run()\nValueError: bad input";
+
+    #[test]
+    fn test_detailed_analysis_of_fenced_python_traceback() {
+        let message = format!(
+            "Why does this fail? Please fix src/app/main.py:12.\n```Python\n{}\n```\nAlso, what changed?",
+            PYTHON_TRACE
+        );
+        let analysis = analyze_request_detailed(&message);
+        assert_eq!(analysis.classification, analyzer::analyze(&message));
+        assert_eq!(analysis.task_types, vec![TaskType::CodeEditing, TaskType::TerminalAutomation]);
+        assert_eq!(
+            analysis.code_fences,
+            vec![CodeFence {
+                language: Some("python".to_string()),
+                content_len: PYTHON_TRACE.len(),
+            }]
+        );
+        assert!(analysis.contains_error_trace);
+        assert_eq!(analysis.detected_languages, vec!["python"]);
+        assert_eq!(analysis.mentioned_paths, vec!["src/app/main.py", "/srv/app/main.py"]);
+        // Prose only: the traceback's lines are not sentences
+        assert_eq!(analysis.question_count, 2);
+        assert_eq!(analysis.imperative_verbs, vec!["fix"]);
+        assert_eq!(analysis.estimated_complexity, Complexity::High);
+    }
+
+    #[test]
+    fn test_error_trace_detectors() {
+        assert_eq!(analyzer::error_trace_languages(PYTHON_TRACE), vec!["python"]);
+        let rust_panic = "thread 'main' panicked at src/main.rs:4:5:\nindex out of bounds\nnote: run with `RUST_BACKTRACE=1`";
+        assert_eq!(analyzer::error_trace_languages(rust_panic), vec!["rust"]);
+        let js_stack = "TypeError: Cannot read properties of undefined (reading 'id')\n    at getUser (/app/src/users.js:10:15)\n    at processTicksAndRejections (node:internal/process/task_queues:95:5)";
+        assert_eq!(analyzer::error_trace_languages(js_stack), vec!["javascript"]);
+        assert_eq!(analyzer::error_trace_languages(&format!("{}\n{}", js_stack, PYTHON_TRACE)), vec!["python", "javascript"]);
+
+        // Talking about errors is not a trace
+        assert!(analyzer::error_trace_languages("Why do I get a TypeError: here?\nat least once a day").is_empty());
+        assert!(analyzer::error_trace_languages("the thread panicked at startup").is_empty());
+
+        let analysis = analyze_request_detailed(js_stack);
+        assert_eq!(analysis.detected_languages, vec!["javascript"]);
+        assert_eq!(analysis.mentioned_paths, vec!["/app/src/users.js"]);
+    }
+
+    #[test]
+    fn test_detailed_analysis_of_plain_requests() {
+        let analysis = analyze_request_detailed(
+            "Please refactor ./scripts/deploy and README.md. Add tests! See https://example.com/a.rs, then run it?",
+        );
+        assert_eq!(analysis.mentioned_paths, vec!["./scripts/deploy", "README.md"]);
+        assert!(analysis.detected_languages.is_empty());
+        assert_eq!(analysis.imperative_verbs, vec!["refactor", "add"]);
+        assert_eq!(analysis.question_count, 1);
+        assert!(!analysis.contains_error_trace);
+        assert_eq!(analysis.estimated_complexity, Complexity::Medium);
+
+        let fences = analyze_request_detailed("```\nfn main() {}\n```\n```rust\nlet x = 1;");
+        assert_eq!(
+            fences.code_fences,
+            vec![
+                CodeFence { language: None, content_len: 12 },
+                CodeFence { language: Some("rust".to_string()), content_len: 10 },
+            ]
+        );
+        assert_eq!(fences.detected_languages, vec!["rust"]);
+        assert_eq!(fences.question_count, 0);
+
+        let chat = analyze_request_detailed("hello there");
+        assert!(chat.task_types.is_empty());
+        assert!(chat.code_fences.is_empty() && chat.mentioned_paths.is_empty() && chat.imperative_verbs.is_empty());
+        assert_eq!(chat.estimated_complexity, Complexity::Low);
+    }
+
+    #[test]
+    fn test_error_traces_prefer_the_debugging_rule() {
+        let trace = format!("Fix this:\n{}", PYTHON_TRACE);
+        let plain = router().route(&request(&trace, None));
+        assert!(plain.reasoning_details.error_trace);
+        assert_eq!(plain.reasoning_details.matched_rule.as_deref(), Some("code_editing"));
+
+        let mut rules = HashMap::new();
+        rules.insert("code_editing".to_string(), vec!["cursor".to_string()]);
+        rules.insert("debugging".to_string(), vec!["claude".to_string()]);
+        let router = Router::new(rules, "gpt".to_string());
+        let decision = router.route(&request(&trace, None));
+        assert_eq!(decision.selected_tools, vec!["claude"]);
+        assert_eq!(decision.reasoning_details.matched_rule.as_deref(), Some("debugging"));
+        assert!(decision.reasoning.contains("error trace detected"));
+        let no_trace = router.route(&request("Fix the bug", None));
+        assert_eq!(no_trace.selected_tools, vec!["cursor"]);
+        assert!(!no_trace.reasoning_details.error_trace);
+
+        // A project's debugging rule comes before the global one
+        let mut extra_rules = HashMap::new();
+        extra_rules.insert("debugging".to_string(), vec!["debugger".to_string()]);
+        let mut router = router;
+        router.set_project_override("proj", ProjectRouting { default_tool: None, extra_rules });
+        assert_eq!(router.route(&project_request(&trace, "proj")).selected_tools, vec!["debugger"]);
+        assert_eq!(router.route(&project_request("Fix the bug", "proj")).selected_tools, vec!["cursor"]);
+    }
+}
@@ -0,0 +1,159 @@
+/// Tests for the priority scheduler
+
+#[cfg(test)]
+mod tests {
+    use rust_core::resilience::{Priority, PriorityScheduler, SchedulerConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// Queue one job that logs its label when it starts, then holds its slot
+    async fn queue_job(
+        scheduler: &PriorityScheduler,
+        log: &Log,
+        priority: Priority,
+        label: &'static str,
+        hold: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let queued_before = scheduler.stats().classes[priority as usize].queued;
+        let task = {
+            let scheduler = scheduler.clone();
+            let log = log.clone();
+            tokio::spawn(async move {
+                scheduler
+                    .submit(priority, async {
+                        log.lock().unwrap().push(label);
+                        tokio::time::sleep(hold).await;
+                    })
+                    .await
+            })
+        };
+        while scheduler.stats().classes[priority as usize].queued == queued_before {
+            tokio::task::yield_now().await;
+        }
+        task
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_higher_classes_dispatch_first() {
+        let scheduler = PriorityScheduler::new(SchedulerConfig::new(1));
+        let log: Log = Arc::default();
+        let held = scheduler.acquire(Priority::Normal).await;
+
+        let hold = Duration::from_millis(10);
+        let tasks = vec![
+            queue_job(&scheduler, &log, Priority::Background, "background", hold).await,
+            queue_job(&scheduler, &log, Priority::Normal, "normal-1", hold).await,
+            queue_job(&scheduler, &log, Priority::Interactive, "interactive", hold).await,
+            queue_job(&scheduler, &log, Priority::Normal, "normal-2", hold).await,
+        ];
+        let stats = scheduler.stats();
+        assert_eq!(stats.running, 1);
+        let queued: Vec<usize> = stats.classes.iter().map(|c| c.queued).collect();
+        assert_eq!(queued, vec![1, 2, 1]);
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*log.lock().unwrap(), vec!["interactive", "normal-1", "normal-2", "background"]);
+        let stats = scheduler.stats();
+        assert_eq!(stats.running, 0);
+        assert!(stats.classes.iter().all(|c| c.queued == 0 && c.running == 0 && c.aged == 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_aged_background_work_escapes_starvation() {
+        let config = SchedulerConfig::new(1).with_background_aging(Duration::from_secs(1));
+        let scheduler = PriorityScheduler::new(config);
+        let log: Log = Arc::default();
+        let held = scheduler.acquire(Priority::Interactive).await;
+
+        let hold = Duration::from_secs(1);
+        let mut tasks = vec![queue_job(&scheduler, &log, Priority::Background, "background", hold).await];
+        for label in ["interactive-1", "interactive-2", "interactive-3"] {
+            tasks.push(queue_job(&scheduler, &log, Priority::Interactive, label, hold).await);
+        }
+
+        // Background has not waited long enough yet, so interactive goes first;
+        // by the time that finishes the background job has aged past the others
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["interactive-1", "background", "interactive-2", "interactive-3"]
+        );
+        assert_eq!(scheduler.stats().classes[Priority::Background as usize].aged, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_class_limit_leaves_room_for_other_classes() {
+        let config = SchedulerConfig::new(3).with_class_limit(Priority::Background, 1);
+        let scheduler = PriorityScheduler::new(config);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let scheduler = scheduler.clone();
+                let running = running.clone();
+                let peak = peak.clone();
+                tokio::spawn(async move {
+                    scheduler
+                        .submit(Priority::Background, async {
+                            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            running.fetch_sub(1, Ordering::SeqCst);
+                        })
+                        .await
+                })
+            })
+            .collect();
+        while scheduler.stats().classes[Priority::Background as usize].queued < 3 {
+            tokio::task::yield_now().await;
+        }
+
+        // Background is at its cap, so the other slots stay free for interactive work
+        let interactive = scheduler.try_acquire(Priority::Interactive).expect("free slot");
+        assert_eq!(interactive.priority(), Priority::Interactive);
+        assert!(scheduler.try_acquire(Priority::Background).is_none());
+        let stats = scheduler.stats();
+        assert_eq!(stats.running, 2);
+        assert_eq!(stats.classes[Priority::Background as usize].running, 1);
+        drop(interactive);
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert_eq!(scheduler.stats().classes[Priority::Background as usize].dispatched, 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_waiter_leaves_queue() {
+        let scheduler = PriorityScheduler::new(SchedulerConfig::new(1));
+        let held = scheduler.acquire(Priority::Background).await;
+
+        let timed_out = tokio::time::timeout(Duration::from_millis(10), scheduler.acquire(Priority::Interactive)).await;
+        assert!(timed_out.is_err());
+        assert_eq!(scheduler.stats().classes[Priority::Interactive as usize].queued, 0);
+
+        drop(held);
+        let permit = scheduler.try_acquire(Priority::Normal).expect("slot was released");
+        assert_eq!(scheduler.stats().running, 1);
+        drop(permit);
+        assert_eq!(scheduler.stats().running, 0);
+    }
+
+    #[test]
+    fn test_priority_parse() {
+        assert_eq!(Priority::parse(" Interactive ").unwrap(), Priority::Interactive);
+        assert_eq!(Priority::parse("background").unwrap().as_str(), "background");
+        assert!(Priority::parse("urgent").is_err());
+    }
+}
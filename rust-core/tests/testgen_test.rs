@@ -0,0 +1,53 @@
+/// Tests for the synthetic corpus generator
+
+#[cfg(test)]
+mod tests {
+    use rust_core::indexer::parser::ASTParser;
+    use rust_core::indexer::testgen::{generate_blocks, generate_corpus, CorpusSpec, SeededRng};
+
+    fn block_contents(count: usize, seed: u64) -> Vec<(Option<String>, String)> {
+        generate_blocks(count, seed).into_iter().map(|b| (b.name, b.content)).collect()
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_corpus() {
+        let spec = CorpusSpec::new(12, 5).with_seed(42);
+        assert_eq!(generate_corpus(&spec), generate_corpus(&spec));
+        assert_eq!(block_contents(200, 42), block_contents(200, 42));
+
+        let other = generate_corpus(&spec.clone().with_seed(43));
+        assert_ne!(generate_corpus(&spec), other);
+        assert_ne!(block_contents(200, 42), block_contents(200, 43));
+
+        let mut a = SeededRng::new(9);
+        let mut b = SeededRng::new(9);
+        let drawn: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(drawn, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_corpus_shape_and_parsing() {
+        let spec = CorpusSpec::new(120, 4).with_languages(&["rust", "python"]).with_seed(1);
+        let corpus = generate_corpus(&spec);
+        assert_eq!(corpus.files.len(), 120);
+        assert_eq!(corpus.files[0].relative_path, "pkg0/module_0.rs");
+        assert_eq!(corpus.files[1].relative_path, "pkg0/module_1.py");
+        assert_eq!(corpus.files[119].relative_path, "pkg2/module_119.py");
+
+        // Every generated function parses as its own block
+        let mut parser = ASTParser::new();
+        for file in &corpus.files[..2] {
+            let blocks = parser.parse_file_cached(&file.relative_path, &file.content, &file.language).unwrap();
+            let functions = blocks.iter().filter(|b| b.name.is_some()).count();
+            assert_eq!(functions, 4, "{}", file.content);
+        }
+    }
+
+    #[test]
+    fn test_generated_blocks_cycle_languages() {
+        let blocks = generate_blocks(8, 0);
+        let languages: Vec<&str> = blocks.iter().map(|b| b.language.as_str()).collect();
+        assert_eq!(languages, vec!["rust", "python", "javascript", "typescript", "rust", "python", "javascript", "typescript"]);
+        assert!(blocks.iter().all(|b| b.content.len() >= 10 && b.name.is_some()));
+    }
+}
@@ -0,0 +1,79 @@
+/// Tests for operation timeouts and their configuration
+
+#[cfg(test)]
+mod tests {
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::error::Result;
+    use rust_core::observability::MetricsCollector;
+    use rust_core::resilience::{with_timeout, TimeoutConfig, Timeouts};
+    use rust_core::OrchestratorError;
+    use std::time::Duration;
+
+    async fn respond(after_secs: u64) -> Result<&'static str> {
+        tokio::time::sleep(Duration::from_secs(after_secs)).await;
+        Ok("done")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_elapsed_timeout_names_operation_and_duration() {
+        assert_eq!(with_timeout("embed", Duration::from_secs(5), respond(4)).await.unwrap(), "done");
+
+        let err = with_timeout("embed", Duration::from_secs(5), respond(6)).await.unwrap_err();
+        match err {
+            OrchestratorError::Timeout(message) => {
+                assert!(message.contains("embed"), "{}", message);
+                assert!(message.contains("5s"), "{}", message);
+            }
+            other => panic!("expected Timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tool_timeouts_count_in_metrics() {
+        let config = OrchestratorConfig::from_toml_str(
+            r#"
+[timeouts]
+default_secs = 30
+
+[timeouts.tools]
+claude = 90
+"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.tool_timeout("claude"), Duration::from_secs(90));
+        assert_eq!(config.tool_timeout("gpt"), Duration::from_secs(30));
+
+        let metrics = MetricsCollector::new();
+        let timeouts = config.build_timeouts().with_metrics(metrics.clone());
+
+        // Within the tool's own timeout, though over the default
+        assert!(timeouts.run("claude", respond(60)).await.is_ok());
+        assert!(!metrics.export().contains("uai_timeouts_total{"));
+
+        let err = timeouts.run("gpt", respond(60)).await.unwrap_err();
+        assert!(err.to_string().contains("gpt did not complete within the configured 30s"), "{}", err);
+        assert!(metrics.export().contains(r#"uai_timeouts_total{operation="gpt"} 1"#));
+
+        // Timeouts raised by the operation itself are passed through uncounted
+        let inner = timeouts
+            .run("claude", async { Err::<(), _>(OrchestratorError::Timeout("upstream".to_string())) })
+            .await;
+        assert!(matches!(inner, Err(OrchestratorError::Timeout(message)) if message == "upstream"));
+        assert!(!metrics.export().contains(r#"operation="claude""#));
+    }
+
+    #[test]
+    fn test_zero_timeouts_rejected() {
+        let mut config = OrchestratorConfig::default();
+        assert_eq!(config.timeouts, TimeoutConfig::default());
+        config.timeouts.default_secs = 0;
+        config.timeouts.tools.insert("claude".to_string(), 0);
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("timeouts.default_secs"));
+        assert!(message.contains("timeouts.tools.claude"));
+
+        assert_eq!(Timeouts::default().for_tool("claude"), Duration::from_secs(120));
+    }
+}
@@ -0,0 +1,84 @@
+/// Tests for model context window lookup
+
+#[cfg(test)]
+mod tests {
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::context::token_counter::{TokenCounter, DEFAULT_CONTEXT_WINDOW};
+    use rust_core::context::window::ContextWindowManager;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Level, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// Collects the fields of every warn event as "name=value" strings
+    #[derive(Clone, Default)]
+    struct WarnCapture {
+        events: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    struct FieldVisitor(Vec<String>);
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for WarnCapture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            if *event.metadata().level() == Level::WARN {
+                let mut visitor = FieldVisitor(Vec::new());
+                event.record(&mut visitor);
+                self.events.lock().unwrap().push(visitor.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dated_model_uses_longest_prefix() {
+        let counter = TokenCounter::new();
+        assert_eq!(counter.get_context_window("claude-3-5-sonnet-20241022"), 200000);
+        assert_eq!(counter.get_context_window("gpt-4-turbo-2024-04-09"), 128000);
+        assert_eq!(counter.get_context_window("gpt-4o-mini"), 128000);
+        assert_eq!(counter.get_context_window("gpt-4-0613"), 8192);
+        assert_eq!(counter.get_context_window("gpt-3.5-turbo"), 16385);
+    }
+
+    #[test]
+    fn test_registered_models_override_and_extend_the_table() {
+        let mut manager = ContextWindowManager::new(0);
+        manager.register_model("mistral-large", 32000);
+        manager.register_model("gpt-4", 32768);
+        assert_eq!(manager.context_window("mistral-large-2407"), 32000);
+        assert_eq!(manager.context_window("gpt-4-0613"), 32768);
+        assert_eq!(manager.context_window("gpt-4o"), 128000);
+
+        let config = OrchestratorConfig::from_toml_str(
+            "[context.model_windows]\n\"llama-3.1\" = 131072\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.build_token_counter().get_context_window("llama-3.1-70b"), 131072);
+        assert_eq!(config.build_window_manager().context_window("llama-3.1-8b"), 131072);
+
+        let invalid = OrchestratorConfig::from_toml_str("[context.model_windows]\nbroken = 0\n").unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_unknown_model_falls_back_with_warning() {
+        let capture = WarnCapture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let counter = TokenCounter::new();
+        assert_eq!(counter.get_context_window("claude-3-5-sonnet"), 200000);
+        assert!(capture.events.lock().unwrap().is_empty());
+
+        assert_eq!(counter.get_context_window("mystery-model"), DEFAULT_CONTEXT_WINDOW);
+        let events = capture.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].iter().any(|field| field == "model=\"mystery-model\""));
+    }
+}
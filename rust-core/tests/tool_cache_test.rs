@@ -0,0 +1,120 @@
+/// Tests for tool call caching
+
+#[cfg(test)]
+mod tests {
+    use rust_core::context::{request_hash, Context, ContextManager, InMemoryContextStore, MockClock, ToolCallCache};
+    use rust_core::OrchestratorError;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn open_pool(path: &Path) -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true))
+            .await
+            .expect("Failed to create test pool")
+    }
+
+    #[test]
+    fn test_request_hash_normalizes_whitespace() {
+        assert_eq!(request_hash("  foo "), request_hash("foo"));
+        assert_eq!(request_hash("grep\t-n  foo\nsrc"), request_hash("grep -n foo src"));
+        assert_ne!(request_hash("foo bar"), request_hash("foobar"));
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_after_ttl() {
+        let clock = Arc::new(MockClock::new(1_000));
+        let cache = ToolCallCache::new(10).with_clock(clock.clone());
+        let hash = request_hash("weather in Oslo");
+
+        cache.put("web", &hash, "rain", Some(Duration::from_secs(60))).await.unwrap();
+        cache.put("web", "forever", "sun", None).await.unwrap();
+        clock.set(1_059);
+        assert_eq!(cache.get("web", &hash).await.unwrap().as_deref(), Some("rain"));
+        assert_eq!(cache.get("shell", &hash).await.unwrap(), None);
+
+        clock.set(1_060);
+        assert_eq!(cache.get("web", &hash).await.unwrap(), None);
+        assert_eq!(cache.get("web", "forever").await.unwrap().as_deref(), Some("sun"));
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_entry_is_evicted() {
+        let cache = ToolCallCache::new(2);
+        cache.put("t", "a", "1", None).await.unwrap();
+        cache.put("t", "b", "2", None).await.unwrap();
+        cache.get("t", "a").await.unwrap();
+        cache.put("t", "c", "3", None).await.unwrap();
+
+        assert!(cache.get("t", "a").await.unwrap().is_some());
+        assert!(cache.get("t", "b").await.unwrap().is_none());
+        assert!(cache.get("t", "c").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_entries_survive_restart_with_sqlite() {
+        let path = std::env::temp_dir().join(format!("uai-tool-cache-{}.db", uuid::Uuid::new_v4()));
+        let clock = Arc::new(MockClock::new(5_000));
+        {
+            let pool = open_pool(&path).await;
+            let cache = ToolCallCache::from_pool(pool.clone()).await.unwrap().with_clock(clock.clone());
+            cache.put("search", &request_hash("rust lru"), "3 results", None).await.unwrap();
+            cache.put("search", &request_hash("stale"), "old", Some(Duration::from_secs(10))).await.unwrap();
+            pool.close().await;
+        }
+
+        clock.set(5_010);
+        let pool = open_pool(&path).await;
+        let cache = ToolCallCache::from_pool(pool.clone()).await.unwrap().with_clock(clock);
+        assert_eq!(
+            cache.get("search", &request_hash(" rust   lru ")).await.unwrap().as_deref(),
+            Some("3 results")
+        );
+        assert_eq!(cache.get("search", &request_hash("stale")).await.unwrap(), None);
+
+        // Expired rows are deleted when found
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tool_cache").fetch_one(&pool).await.unwrap();
+        assert_eq!(rows, 1);
+        pool.close().await;
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_record_tool_call_cached_invokes_tool_once() {
+        let manager = ContextManager::new(InMemoryContextStore::new()).with_tool_cache(ToolCallCache::new(10));
+        let mut context = Context::new(None);
+        let invocations = AtomicUsize::new(0);
+        let invoke = || async {
+            invocations.fetch_add(1, Ordering::SeqCst);
+            Ok("file contents".to_string())
+        };
+
+        let first = manager
+            .record_tool_call_cached(&mut context, "read_file", "src/lib.rs", None, invoke)
+            .await
+            .unwrap();
+        let second = manager
+            .record_tool_call_cached(&mut context, "read_file", "  src/lib.rs\n", None, invoke)
+            .await
+            .unwrap();
+        assert!(!first.cache_hit);
+        assert!(second.cache_hit);
+        assert_eq!(second.response, "file contents");
+        assert_eq!(invocations.load(Ordering::SeqCst), 1);
+        assert_eq!(context.tool_history.len(), 2);
+
+        // Failures are not cached or recorded
+        let failed = manager
+            .record_tool_call_cached(&mut context, "read_file", "missing.rs", None, || async {
+                Err(OrchestratorError::ToolUnavailable("read_file".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(context.tool_history.len(), 2);
+        assert!(manager.tool_cache().unwrap().get("read_file", &request_hash("missing.rs")).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,92 @@
+/// Tests for embedding vector math
+
+#[cfg(test)]
+mod tests {
+    use rust_core::indexer::vector::{cosine_similarity, dot, l2_norm, l2_normalize, l2_normalized, mean_pool, top_k_similar};
+    use rust_core::OrchestratorError;
+
+    const CASES: usize = 200;
+
+    /// Deterministic pseudo-random vectors, so failures reproduce
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next_f32(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 40) as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+        }
+
+        fn vector(&mut self, dim: usize) -> Vec<f32> {
+            (0..dim).map(|_| self.next_f32() * 100.0).collect()
+        }
+    }
+
+    #[test]
+    fn test_normalized_vectors_have_norm_at_most_one() {
+        let mut rng = Lcg(1);
+        for case in 0..CASES {
+            let mut v = rng.vector(1 + case % 64);
+            l2_normalize(&mut v);
+            let norm = l2_norm(&v);
+            assert!(norm <= 1.0 + 1e-5, "norm {} for case {}", norm, case);
+            assert!((norm - 1.0).abs() < 1e-4 || norm == 0.0);
+        }
+        // Zero vectors stay zero rather than becoming NaN
+        assert_eq!(l2_normalized(&[0.0, 0.0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_cosine_similarity_is_symmetric_and_bounded() {
+        let mut rng = Lcg(2);
+        for case in 0..CASES {
+            let dim = 1 + case % 32;
+            let (a, b) = (rng.vector(dim), rng.vector(dim));
+            let ab = cosine_similarity(&a, &b).unwrap();
+            assert_eq!(ab, cosine_similarity(&b, &a).unwrap());
+            assert!((-1.0..=1.0).contains(&ab));
+            assert!((cosine_similarity(&a, &a).unwrap() - 1.0).abs() < 1e-5);
+            // Scaling does not change the angle
+            let scaled: Vec<f32> = a.iter().map(|x| x * 3.0).collect();
+            assert!((cosine_similarity(&scaled, &b).unwrap() - ab).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_dimension_mismatch_and_zero_vectors_are_invalid_input() {
+        let mut rng = Lcg(3);
+        for case in 0..CASES {
+            let dim = 1 + case % 16;
+            let (a, b) = (rng.vector(dim), rng.vector(dim + 1));
+            assert!(matches!(dot(&a, &b), Err(OrchestratorError::InvalidInput(_))));
+            assert!(matches!(cosine_similarity(&a, &b), Err(OrchestratorError::InvalidInput(_))));
+            assert!(matches!(mean_pool(&[a.clone(), b.clone()]), Err(OrchestratorError::InvalidInput(_))));
+            assert!(matches!(top_k_similar(&a, &[b], 1), Err(OrchestratorError::InvalidInput(_))));
+        }
+        let zero = vec![0.0; 3];
+        assert!(matches!(cosine_similarity(&zero, &[1.0, 2.0, 3.0]), Err(OrchestratorError::InvalidInput(_))));
+        assert!(matches!(top_k_similar(&zero, &[], 1), Err(OrchestratorError::InvalidInput(_))));
+        assert!(matches!(mean_pool(&[]), Err(OrchestratorError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_mean_pool_averages_elementwise() {
+        let pooled = mean_pool(&[vec![1.0, 2.0], vec![3.0, 6.0]]).unwrap();
+        assert_eq!(pooled, vec![2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_top_k_similar_orders_best_first_with_ties_by_index() {
+        let corpus = vec![
+            vec![0.0, 1.0],
+            vec![1.0, 0.0],
+            vec![0.0, 0.0], // No direction; skipped
+            vec![2.0, 0.0], // Same direction as index 1
+            vec![1.0, 1.0],
+        ];
+        let top = top_k_similar(&[1.0, 0.0], &corpus, 3).unwrap();
+        let indices: Vec<usize> = top.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, vec![1, 3, 4]);
+        assert!(top.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert_eq!(top_k_similar(&[1.0, 0.0], &corpus, 10).unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,177 @@
+/// Tests for per-conversation window state across model switches
+
+#[cfg(test)]
+mod tests {
+    use rust_core::config::OrchestratorConfig;
+    use rust_core::context::summarizer::ContextSummarizer;
+    use rust_core::context::token_counter::TokenCounter;
+    use rust_core::context::window::{ContextWindowManager, ReservedBudget};
+    use rust_core::context::{Context, ContextManager, InMemoryContextStore, Role};
+
+    /// `count` alternating messages of about 500 tokens each
+    fn long_conversation(count: usize) -> Context {
+        let mut context = Context::new(None);
+        for i in 0..count {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            context.add_message(role, format!("turn {} {}", i, "word ".repeat(400)));
+        }
+        context
+    }
+
+    fn window_manager() -> ContextWindowManager {
+        // A threshold no test reaches, so only truncation drops messages
+        ContextWindowManager::new(1000).with_summarizer(ContextSummarizer::new(10_000, 0.5))
+    }
+
+    #[test]
+    fn test_window_state_accumulates_across_models() {
+        let manager = window_manager();
+        let mut context = long_conversation(40);
+
+        let first = manager.manage_context(&mut context, "gpt-4");
+        assert!(first.dropped_messages > 0);
+        assert!(!first.suggest_restore);
+        let state = context.window_state.clone().unwrap();
+        assert_eq!(state.last_model.as_deref(), Some("gpt-4"));
+        assert_eq!(state.last_window, 8192);
+        assert_eq!(state.dropped_messages, first.dropped_messages);
+        assert_eq!(state.last_summary_at, None);
+
+        // A bigger window after truncation suggests restoring
+        for i in 0..10 {
+            context.add_message(Role::User, format!("follow-up {} {}", i, "word ".repeat(400)));
+        }
+        let second = manager.manage_context(&mut context, "gpt-3.5-turbo");
+        assert!(second.suggest_restore);
+        let state = context.window_state.clone().unwrap();
+        assert_eq!(state.last_model.as_deref(), Some("gpt-3.5-turbo"));
+        assert_eq!(state.last_window, 16385);
+        assert_eq!(state.dropped_messages, first.dropped_messages + second.dropped_messages);
+
+        // Switching back to a smaller window does not
+        let third = manager.manage_context(&mut context, "gpt-4");
+        assert!(!third.suggest_restore);
+        assert!(third.dropped_messages > 0);
+        let state = context.window_state.clone().unwrap();
+        assert_eq!(state.last_model.as_deref(), Some("gpt-4"));
+        assert_eq!(
+            state.dropped_messages,
+            first.dropped_messages + second.dropped_messages + third.dropped_messages
+        );
+    }
+
+    #[test]
+    fn test_summarization_is_recorded() {
+        let manager = ContextWindowManager::new(0).with_summarizer(ContextSummarizer::new(5, 0.5));
+        let mut context = long_conversation(4);
+        for i in 0..6 {
+            context.add_message(Role::User, format!("short {}", i));
+        }
+
+        let outcome = manager.manage_context(&mut context, "claude-3-5-sonnet");
+        assert!(outcome.summarized);
+        let state = context.window_state.unwrap();
+        assert!(state.last_summary_at.is_some());
+        assert_eq!(state.dropped_messages, outcome.dropped_messages);
+    }
+
+    #[tokio::test]
+    async fn test_window_state_is_stored_and_restore_needs_an_archive() {
+        let manager = ContextManager::new(InMemoryContextStore::new());
+        let mut context = manager.get_or_create_context(None, None).await.unwrap();
+        let id = context.conversation_id.clone();
+        assert_eq!(manager.get_window_state(&id).await.unwrap(), None);
+
+        for message in long_conversation(40).messages {
+            context.add_message(message.role, message.content);
+        }
+        let window_manager = window_manager();
+        manager.manage_window(&mut context, &window_manager, "gpt-4").await.unwrap();
+        manager.update_context(&mut context).await.unwrap();
+        let stored = manager.get_window_state(&id).await.unwrap().unwrap();
+        assert_eq!(stored, context.window_state.clone().unwrap());
+
+        // Truncated messages are not archived, so there is nothing to restore
+        let outcome = manager.manage_window(&mut context, &window_manager, "claude-3-5-sonnet").await.unwrap();
+        assert!(!outcome.suggest_restore);
+    }
+
+    const AGENT_BUDGET: ReservedBudget = ReservedBudget {
+        output: 4000,
+        system_suffix: 500,
+        tool_schemas: 1500,
+        safety_margin: 200,
+    };
+
+    #[test]
+    fn test_single_number_reserves_output_tokens() {
+        let manager = ContextWindowManager::new(1000);
+        assert_eq!(manager.reserved_budget("gpt-4"), ReservedBudget::output(1000));
+        assert_eq!(manager.reserved_budget("gpt-4").total(), 1000);
+        assert_eq!(manager.available_tokens("gpt-4"), 8192 - 1000);
+        assert_eq!(ContextWindowManager::new(AGENT_BUDGET).available_tokens("gpt-4"), 8192 - 6200);
+        assert_eq!(ContextWindowManager::default().reserved_budget("gpt-4"), ReservedBudget::from(1000));
+    }
+
+    #[test]
+    fn test_per_model_budgets_override_the_default() {
+        let mut manager = ContextWindowManager::new(1000);
+        manager.register_model("acme-large", 32_000);
+        manager.register_model_reserved("acme-large", AGENT_BUDGET);
+        manager.register_model_reserved("gpt-3.5-turbo", ReservedBudget::output(20_000));
+
+        assert_eq!(AGENT_BUDGET.total(), 6200);
+        assert_eq!(manager.available_tokens("acme-large"), 32_000 - 6200);
+        // Dated variants share the budget, as they share the window
+        assert_eq!(manager.reserved_budget("acme-large-2025-01"), AGENT_BUDGET);
+        assert_eq!(manager.available_tokens("acme-large-2025-01"), 32_000 - 6200);
+        assert_eq!(manager.available_tokens("gpt-4"), 8192 - 1000);
+        // A budget larger than the window leaves nothing
+        assert_eq!(manager.available_tokens("gpt-3.5-turbo"), 0);
+    }
+
+    #[test]
+    fn test_truncation_keeps_the_system_prompt_over_important_messages() {
+        let mut context = Context::new(None);
+        context.add_message(Role::System, format!("You review code. {}", "rule ".repeat(400)));
+        for i in 0..40 {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            context.add_message(role, format!("important decision {} ```{}```", i, "word ".repeat(400)));
+        }
+
+        let outcome = window_manager().manage_context(&mut context, "gpt-4");
+        assert!(outcome.dropped_messages > 0);
+        assert_eq!(context.messages[0].role, Role::System);
+    }
+
+    #[test]
+    fn test_truncation_leaves_room_for_the_whole_budget() {
+        let mut manager = window_manager();
+        let mut by_default = long_conversation(40);
+        manager.manage_context(&mut by_default, "gpt-4");
+
+        manager.register_model_reserved("gpt-4", AGENT_BUDGET);
+        let mut with_budget = long_conversation(40);
+        manager.manage_context(&mut with_budget, "gpt-4");
+
+        let counter = TokenCounter::new();
+        let tokens = |context: &Context| {
+            context.messages.iter().map(|m| counter.estimate_tokens(&m.content) + 4).sum::<usize>()
+        };
+        assert!(tokens(&with_budget) <= 8192 - 6200);
+        assert!(tokens(&by_default) <= 8192 - 1000);
+        assert!(with_budget.messages.len() < by_default.messages.len());
+    }
+
+    #[test]
+    fn test_config_registers_model_budgets() {
+        let config = OrchestratorConfig::from_toml_str(
+            "[context]\nreserved_tokens = 2000\n\n[context.model_reserved.gpt-4]\noutput = 1000\ntool_schemas = 3000\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let manager = config.build_window_manager();
+        assert_eq!(manager.available_tokens("gpt-4"), 8192 - 4000);
+        assert_eq!(manager.available_tokens("claude-3-opus"), 200_000 - 2000);
+    }
+}